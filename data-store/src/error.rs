@@ -40,6 +40,12 @@ pub struct Error {
     kind: ErrorKind,
 }
 
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
 impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
@@ -12,7 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::error::{ErrorKind, Result};
+use crate::error::Result;
+use crate::relation::Relation;
 use csv::{ByteRecord, ReaderBuilder};
 use flate2::read::GzDecoder;
 use numa_gpu::runtime::allocator::{self, DerefMemType};
@@ -27,10 +28,58 @@ use std::time::{Duration, Instant};
 pub type JoinDataGenFn<T> = Box<dyn FnMut(&mut [T], &mut [T], &mut [T], &mut [T]) -> Result<()>>;
 
 pub struct JoinData<T: DeviceCopy> {
-    pub build_relation_key: Mem<T>,
-    pub build_relation_payload: Mem<T>,
-    pub probe_relation_key: Mem<T>,
-    pub probe_relation_payload: Mem<T>,
+    pub build_relation: Relation<T, T>,
+    pub probe_relation: Relation<T, T>,
+}
+
+/// The build and probe relations stored back-to-back in a single buffer.
+///
+/// The attributes are laid out in the order: build keys, build payloads,
+/// probe keys, and probe payloads. The payloads of key-only relations are
+/// empty.
+pub struct CombinedJoinData<T: DeviceCopy> {
+    mem: DerefMem<T>,
+    lens: [usize; 4],
+}
+
+impl<T: DeviceCopy> CombinedJoinData<T> {
+    /// Returns the number of tuples in the build relation.
+    pub fn build_len(&self) -> usize {
+        self.lens[0]
+    }
+
+    /// Returns the number of tuples in the probe relation.
+    pub fn probe_len(&self) -> usize {
+        self.lens[2]
+    }
+
+    /// Returns the size of the build relation in bytes.
+    pub fn build_bytes(&self) -> usize {
+        (self.lens[0] + self.lens[1]) * std::mem::size_of::<T>()
+    }
+
+    /// Returns the size of the probe relation in bytes.
+    pub fn probe_bytes(&self) -> usize {
+        (self.lens[2] + self.lens[3]) * std::mem::size_of::<T>()
+    }
+
+    /// Returns the build keys, build payloads, probe keys, and probe payloads.
+    pub fn attributes(&self) -> (&[T], &[T], &[T], &[T]) {
+        let (build_key, rest) = self.mem.as_slice().split_at(self.lens[0]);
+        let (build_pay, rest) = rest.split_at(self.lens[1]);
+        let (probe_key, probe_pay) = rest.split_at(self.lens[2]);
+
+        (build_key, build_pay, probe_key, probe_pay)
+    }
+
+    /// Returns the build keys, build payloads, probe keys, and probe payloads.
+    pub fn attributes_mut(&mut self) -> (&mut [T], &mut [T], &mut [T], &mut [T]) {
+        let (build_key, rest) = self.mem.as_mut_slice().split_at_mut(self.lens[0]);
+        let (build_pay, rest) = rest.split_at_mut(self.lens[1]);
+        let (probe_key, probe_pay) = rest.split_at_mut(self.lens[2]);
+
+        (build_key, build_pay, probe_key, probe_pay)
+    }
 }
 
 pub struct JoinDataBuilder {
@@ -39,6 +88,8 @@ pub struct JoinDataBuilder {
     inner_mem_type: DerefMemType,
     outer_mem_type: DerefMemType,
     do_mlock: bool,
+    shuffle_seed: Option<u64>,
+    keys_only: bool,
 }
 
 impl Default for JoinDataBuilder {
@@ -49,6 +100,8 @@ impl Default for JoinDataBuilder {
             inner_mem_type: DerefMemType::SysMem,
             outer_mem_type: DerefMemType::SysMem,
             do_mlock: false,
+            shuffle_seed: None,
+            keys_only: false,
         }
     }
 }
@@ -79,56 +132,71 @@ impl JoinDataBuilder {
         self
     }
 
+    /// Shuffles the relations after generating or loading them.
+    ///
+    /// Keys and payloads are permuted in lockstep with the given seed. By
+    /// default (`None`), the relations retain their original order.
+    pub fn shuffle(&mut self, seed: Option<u64>) -> &mut Self {
+        self.shuffle_seed = seed;
+        self
+    }
+
+    /// Generates only the keys, and skips allocating the payloads.
+    ///
+    /// Saves memory and setup time if the join reads only the keys, e.g., to
+    /// count the matches. The data generator then receives empty payload
+    /// slices, and the relations are key-only. Loading relations from files
+    /// always reads the payloads.
+    pub fn keys_only(&mut self, keys_only: bool) -> &mut Self {
+        self.keys_only = keys_only;
+        self
+    }
+
+    fn shuffle_relations<T>(
+        &self,
+        inner_key: &mut [T],
+        inner_payload: &mut [T],
+        outer_key: &mut [T],
+        outer_payload: &mut [T],
+    ) -> Result<()> {
+        if let Some(seed) = self.shuffle_seed {
+            // Use a different seed for each relation, so that a foreign key
+            // relation isn't permuted the same way as its primary key relation.
+            // Key-only relations have empty payloads, which `shuffle` permutes
+            // the same way as `shuffle_with_payload`.
+            for (key, payload, seed) in [
+                (inner_key, inner_payload, seed),
+                (outer_key, outer_payload, seed.wrapping_add(1)),
+            ] {
+                if payload.is_empty() {
+                    datagen::shuffle::shuffle(key, seed);
+                } else {
+                    datagen::shuffle::shuffle_with_payload(key, payload, seed)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Allocates the attributes of both relations.
+    ///
+    /// Without payloads, the payload attributes are empty.
     fn allocate_relations<T>(
         &self,
+        with_payloads: bool,
     ) -> Result<(DerefMem<T>, DerefMem<T>, DerefMem<T>, DerefMem<T>, Duration)>
     where
         T: Clone + Default + DeviceCopy,
     {
         // Allocate memory for data sets
         let malloc_timer = Instant::now();
-        let mut memory: VecDeque<_> = [
-            (self.inner_len, self.inner_mem_type.clone()),
-            (self.inner_len, self.inner_mem_type.clone()),
-            (self.outer_len, self.outer_mem_type.clone()),
-            (self.outer_len, self.outer_mem_type.clone()),
-        ]
-        .iter()
-        .cloned()
-        .map(|(len, mem_type)| {
-            let mut mem = allocator::Allocator::alloc_deref_mem(mem_type, len);
-
-            // Force the OS to physically allocate the memory
-            if self.do_mlock {
-                mem.mlock()?;
-            }
-
-            Ok(mem)
-        })
-        .collect::<Result<_>>()?;
+        let (inner_key, inner_payload) =
+            self.allocate_attributes(self.inner_len, &self.inner_mem_type, with_payloads)?;
+        let (outer_key, outer_payload) =
+            self.allocate_attributes(self.outer_len, &self.outer_mem_type, with_payloads)?;
         let malloc_time = malloc_timer.elapsed();
 
-        let inner_key = memory.pop_front().ok_or_else(|| {
-            ErrorKind::LogicError(
-                "Failed to get primary key relation. Is it allocated?".to_string(),
-            )
-        })?;
-        let inner_payload = memory.pop_front().ok_or_else(|| {
-            ErrorKind::LogicError(
-                "Failed to get primary key relation. Is it allocated?".to_string(),
-            )
-        })?;
-        let outer_key = memory.pop_front().ok_or_else(|| {
-            ErrorKind::LogicError(
-                "Failed to get foreign key relation. Is it allocated?".to_string(),
-            )
-        })?;
-        let outer_payload = memory.pop_front().ok_or_else(|| {
-            ErrorKind::LogicError(
-                "Failed to get foreign key relation. Is it allocated?".to_string(),
-            )
-        })?;
-
         Ok((
             inner_key,
             inner_payload,
@@ -138,6 +206,37 @@ impl JoinDataBuilder {
         ))
     }
 
+    /// Allocates the key and payload attributes of a relation.
+    fn allocate_attributes<T>(
+        &self,
+        len: usize,
+        mem_type: &DerefMemType,
+        with_payload: bool,
+    ) -> Result<(DerefMem<T>, DerefMem<T>)>
+    where
+        T: Clone + Default + DeviceCopy,
+    {
+        let alloc = || -> Result<DerefMem<T>> {
+            let mut mem = allocator::Allocator::try_alloc_deref_mem(mem_type.clone(), len)?;
+
+            // Force the OS to physically allocate the memory
+            if self.do_mlock {
+                mem.mlock()?;
+            }
+
+            Ok(mem)
+        };
+
+        let key = alloc()?;
+        let payload = if with_payload {
+            alloc()?
+        } else {
+            DerefMem::SysMem(Vec::new())
+        };
+
+        Ok((key, payload))
+    }
+
     pub fn build_with_data_gen<T>(
         &mut self,
         mut data_gen_fn: JoinDataGenFn<T>,
@@ -146,7 +245,7 @@ impl JoinDataBuilder {
         T: Copy + Default + DeviceCopy,
     {
         let (mut inner_key, mut inner_payload, mut outer_key, mut outer_payload, malloc_time) =
-            self.allocate_relations()?;
+            self.allocate_relations(!self.keys_only)?;
 
         // Generate dataset
         let gen_timer = Instant::now();
@@ -156,20 +255,79 @@ impl JoinDataBuilder {
             outer_key.as_mut_slice(),
             outer_payload.as_mut_slice(),
         )?;
+        self.shuffle_relations(
+            inner_key.as_mut_slice(),
+            inner_payload.as_mut_slice(),
+            outer_key.as_mut_slice(),
+            outer_payload.as_mut_slice(),
+        )?;
         let gen_time = gen_timer.elapsed();
 
+        let (build_relation, probe_relation) = if self.keys_only {
+            (
+                Relation::from_keys(inner_key.into()),
+                Relation::from_keys(outer_key.into()),
+            )
+        } else {
+            (
+                Relation::new(inner_key.into(), inner_payload.into())?,
+                Relation::new(outer_key.into(), outer_payload.into())?,
+            )
+        };
+
         Ok((
             JoinData {
-                build_relation_key: inner_key.into(),
-                build_relation_payload: inner_payload.into(),
-                probe_relation_key: outer_key.into(),
-                probe_relation_payload: outer_payload.into(),
+                build_relation,
+                probe_relation,
             },
             malloc_time,
             gen_time,
         ))
     }
 
+    /// Generates the relations into a single buffer of the memory type.
+    ///
+    /// The buffer co-locates the build and probe relations, e.g., to measure
+    /// the effect of locality. See `CombinedJoinData` for the layout. The
+    /// inner and outer memory types are ignored.
+    pub fn build_combined_with_data_gen<T>(
+        &mut self,
+        mem_type: DerefMemType,
+        mut data_gen_fn: JoinDataGenFn<T>,
+    ) -> Result<(CombinedJoinData<T>, Duration, Duration)>
+    where
+        T: Copy + Default + DeviceCopy,
+    {
+        let (inner_payload_len, outer_payload_len) = if self.keys_only {
+            (0, 0)
+        } else {
+            (self.inner_len, self.outer_len)
+        };
+        let lens = [
+            self.inner_len,
+            inner_payload_len,
+            self.outer_len,
+            outer_payload_len,
+        ];
+
+        let malloc_timer = Instant::now();
+        let mut mem = allocator::Allocator::try_alloc_deref_mem(mem_type, lens.iter().sum())?;
+        if self.do_mlock {
+            mem.mlock()?;
+        }
+        let malloc_time = malloc_timer.elapsed();
+
+        let mut data = CombinedJoinData { mem, lens };
+
+        let gen_timer = Instant::now();
+        let (inner_key, inner_payload, outer_key, outer_payload) = data.attributes_mut();
+        data_gen_fn(inner_key, inner_payload, outer_key, outer_payload)?;
+        self.shuffle_relations(inner_key, inner_payload, outer_key, outer_payload)?;
+        let gen_time = gen_timer.elapsed();
+
+        Ok((data, malloc_time, gen_time))
+    }
+
     pub fn build_with_files<T: DeserializeOwned>(
         &mut self,
         inner_relation_path: &str,
@@ -220,7 +378,7 @@ impl JoinDataBuilder {
         let io_count_time = io_timer.elapsed();
 
         let (mut inner_key, mut inner_payload, mut outer_key, mut outer_payload, malloc_time) =
-            self.allocate_relations()?;
+            self.allocate_relations(true)?;
 
         let io_timer = Instant::now();
 
@@ -265,14 +423,19 @@ impl JoinDataBuilder {
                 .expect("Allocated length is too short") = value;
         }
 
+        self.shuffle_relations(
+            inner_key.as_mut_slice(),
+            inner_payload.as_mut_slice(),
+            outer_key.as_mut_slice(),
+            outer_payload.as_mut_slice(),
+        )?;
+
         let io_read_time = io_timer.elapsed();
 
         Ok((
             JoinData {
-                build_relation_key: inner_key.into(),
-                build_relation_payload: inner_payload.into(),
-                probe_relation_key: outer_key.into(),
-                probe_relation_payload: outer_payload.into(),
+                build_relation: Relation::new(inner_key.into(), inner_payload.into())?,
+                probe_relation: Relation::new(outer_key.into(), outer_payload.into())?,
             },
             malloc_time,
             io_count_time + io_read_time,
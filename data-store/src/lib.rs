@@ -14,3 +14,5 @@
 
 pub mod error;
 pub mod join_data;
+pub mod relation;
+pub mod relation_file;
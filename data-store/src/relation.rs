@@ -0,0 +1,134 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A relation stored in columnar layout.
+//!
+//! Joins and partitioning operators take a key and a value attribute as input.
+//! `Relation` bundles both attributes, and guarantees that they have the same
+//! length. Thus, operators don't have to check the lengths of each pair of
+//! buffers.
+//!
+//! Operators that read only the keys, e.g., a join that counts the matches,
+//! don't need the values. A key-only relation saves the memory of the value
+//! attribute by leaving it empty.
+
+use crate::error::{ErrorKind, Result};
+use numa_gpu::runtime::memory::{LaunchableSlice, Mem};
+use rustacuda::memory::DeviceCopy;
+use std::mem;
+
+/// A relation with a key and a value attribute.
+///
+/// # Invariants
+///
+/// The key and value attributes have the same length, unless the relation is
+/// key-only. The value attribute of a key-only relation is empty.
+#[derive(Debug)]
+pub struct Relation<K: DeviceCopy, V: DeviceCopy> {
+    key: Mem<K>,
+    value: Mem<V>,
+}
+
+impl<K: DeviceCopy, V: DeviceCopy> Relation<K, V> {
+    /// Creates a relation from its key and value attributes.
+    ///
+    /// Returns an `InvalidArgument` error if the attributes have different
+    /// lengths.
+    pub fn new(key: Mem<K>, value: Mem<V>) -> Result<Self> {
+        if key.len() != value.len() {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Key and value attributes have different lengths ({} and {})",
+                key.len(),
+                value.len()
+            )))?;
+        }
+
+        Ok(Self { key, value })
+    }
+
+    /// Creates a key-only relation, which has an empty value attribute.
+    pub fn from_keys(key: Mem<K>) -> Self {
+        Self {
+            key,
+            value: Mem::SysMem(Vec::new()),
+        }
+    }
+
+    /// Returns the number of tuples.
+    pub fn len(&self) -> usize {
+        self.key.len()
+    }
+
+    /// Returns `true` if the relation contains no tuples.
+    pub fn is_empty(&self) -> bool {
+        self.key.len() == 0
+    }
+
+    /// Returns `true` if the relation has no value attribute.
+    ///
+    /// An empty relation isn't key-only.
+    pub fn is_key_only(&self) -> bool {
+        self.value.len() != self.key.len()
+    }
+
+    /// Returns the total number of bytes of both attributes.
+    ///
+    /// The empty value attribute of a key-only relation doesn't take up any
+    /// bytes.
+    pub fn bytes(&self) -> usize {
+        self.key.len() * mem::size_of::<K>() + self.value.len() * mem::size_of::<V>()
+    }
+
+    /// Returns the key attribute.
+    pub fn key(&self) -> &Mem<K> {
+        &self.key
+    }
+
+    /// Returns the value attribute.
+    pub fn value(&self) -> &Mem<V> {
+        &self.value
+    }
+
+    /// Returns mutable references to the key and value attributes.
+    ///
+    /// The attributes may be modified in-place, but must not be replaced by
+    /// memory of a different length.
+    pub fn parts_mut(&mut self) -> (&mut Mem<K>, &mut Mem<V>) {
+        (&mut self.key, &mut self.value)
+    }
+
+    /// Returns the key and value attributes, and consumes the relation.
+    pub fn into_parts(self) -> (Mem<K>, Mem<V>) {
+        (self.key, self.value)
+    }
+
+    /// Returns both attributes as slices, if the CPU can access the memory.
+    pub fn try_as_slices(&self) -> Result<(&[K], &[V])> {
+        Ok((self.key.try_as_slice()?, self.value.try_as_slice()?))
+    }
+
+    /// Returns both attributes as mutable slices, if the CPU can access the
+    /// memory.
+    pub fn try_as_mut_slices(&mut self) -> Result<(&mut [K], &mut [V])> {
+        Ok((self.key.try_as_mut_slice()?, self.value.try_as_mut_slice()?))
+    }
+
+    /// Returns both attributes as slices that can be passed to a GPU kernel.
+    pub fn as_launchable_slices(&self) -> (LaunchableSlice<'_, K>, LaunchableSlice<'_, V>) {
+        (
+            self.key.as_launchable_slice(),
+            self.value.as_launchable_slice(),
+        )
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A binary file format for unpartitioned relations.
+//!
+//! The format captures the exact input of a join, e.g., to attach a failing
+//! input to a bug report. In contrast to the CSV relation files, the binary
+//! format loads the same bytes on every machine.
+//!
+//! ## Layout
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! ```text
+//! magic:      8 bytes, "NGPUREL\0"
+//! version:    u32
+//! key type:   u8 type tag
+//! value type: u8 type tag
+//! reserved:   u16, always zero
+//! tuples:     u64
+//! data:       tuples * (key, value)
+//! ```
+
+use crate::error::{ErrorKind, Result};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::path::Path;
+
+/// Identifies a relation file.
+const MAGIC: [u8; 8] = *b"NGPUREL\0";
+
+/// Version of the file layout. Must be incremented when the layout changes.
+const VERSION: u32 = 1;
+
+/// An attribute type that can be stored in a relation file.
+pub trait RelationFileAttribute: Copy + Sized {
+    /// A unique tag that identifies the type in the file header.
+    const TYPE_TAG: u8;
+
+    /// Appends the attribute to `bytes` in little-endian byte order.
+    fn write_le(self, bytes: &mut Vec<u8>);
+
+    /// Reads the attribute from little-endian bytes.
+    ///
+    /// `bytes` must have the size of the attribute.
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_relation_file_attribute {
+    ($Type:ty, $Tag:expr) => {
+        impl RelationFileAttribute for $Type {
+            const TYPE_TAG: u8 = $Tag;
+
+            fn write_le(self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn read_le(bytes: &[u8]) -> Self {
+                <$Type>::from_le_bytes(bytes.try_into().expect("Attribute has a wrong size"))
+            }
+        }
+    };
+}
+
+impl_relation_file_attribute!(i8, 1);
+impl_relation_file_attribute!(i16, 2);
+impl_relation_file_attribute!(i32, 3);
+impl_relation_file_attribute!(i64, 4);
+impl_relation_file_attribute!(u8, 5);
+impl_relation_file_attribute!(u16, 6);
+impl_relation_file_attribute!(u32, 7);
+impl_relation_file_attribute!(u64, 8);
+
+/// Writes a relation to a file.
+///
+/// The key and value attributes are stored as tuples.
+pub fn dump_relation<K, V, P>(path: P, keys: &[K], values: &[V]) -> Result<()>
+where
+    K: RelationFileAttribute,
+    V: RelationFileAttribute,
+    P: AsRef<Path>,
+{
+    if keys.len() != values.len() {
+        Err(ErrorKind::InvalidArgument(
+            "Key and value attributes have different lengths".to_string(),
+        ))?;
+    }
+
+    let mut header = Vec::with_capacity(24);
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.push(K::TYPE_TAG);
+    header.push(V::TYPE_TAG);
+    header.extend_from_slice(&0_u16.to_le_bytes());
+    header.extend_from_slice(&(keys.len() as u64).to_le_bytes());
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&header)?;
+
+    let mut tuple = Vec::with_capacity(mem::size_of::<K>() + mem::size_of::<V>());
+    for (&key, &value) in keys.iter().zip(values.iter()) {
+        tuple.clear();
+        key.write_le(&mut tuple);
+        value.write_le(&mut tuple);
+        writer.write_all(&tuple)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Reads a relation from a file written by `dump_relation`.
+///
+/// Returns the key and value attributes. Returns an error if the file stores
+/// other attribute types than `K` and `V`.
+pub fn load_relation<K, V, P>(path: P) -> Result<(Vec<K>, Vec<V>)>
+where
+    K: RelationFileAttribute,
+    V: RelationFileAttribute,
+    P: AsRef<Path>,
+{
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut header = [0_u8; 24];
+    reader.read_exact(&mut header)?;
+
+    if header[0..8] != MAGIC {
+        Err(ErrorKind::InvalidArgument(
+            "File is not a relation file".to_string(),
+        ))?;
+    }
+
+    let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    if version != VERSION {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Unsupported relation file version {}, expected version {}",
+            version, VERSION
+        )))?;
+    }
+
+    let (key_tag, value_tag) = (header[12], header[13]);
+    if key_tag != K::TYPE_TAG || value_tag != V::TYPE_TAG {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Relation file has type tags ({}, {}), but expected ({}, {})",
+            key_tag,
+            value_tag,
+            K::TYPE_TAG,
+            V::TYPE_TAG
+        )))?;
+    }
+
+    let len: usize = u64::from_le_bytes(header[16..24].try_into().unwrap())
+        .try_into()
+        .map_err(|_| ErrorKind::IntegerOverflow("Relation length overflows usize".to_string()))?;
+
+    let key_bytes = mem::size_of::<K>();
+    let mut tuple = vec![0_u8; key_bytes + mem::size_of::<V>()];
+    // Don't trust the header with the allocation size, the file may be truncated
+    let capacity = std::cmp::min(len, 1 << 20);
+    let mut keys = Vec::with_capacity(capacity);
+    let mut values = Vec::with_capacity(capacity);
+
+    for _ in 0..len {
+        reader.read_exact(&mut tuple)?;
+        keys.push(K::read_le(&tuple[..key_bytes]));
+        values.push(V::read_le(&tuple[key_bytes..]));
+    }
+
+    if reader.read(&mut [0_u8])? != 0 {
+        Err(ErrorKind::InvalidArgument(
+            "Relation file contains trailing data".to_string(),
+        ))?;
+    }
+
+    Ok((keys, values))
+}
@@ -0,0 +1,106 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use data_store::join_data::{JoinData, JoinDataBuilder, JoinDataGenFn};
+use numa_gpu::runtime::allocator::DerefMemType;
+use std::error::Error;
+use std::result::Result;
+
+const INNER_LEN: usize = 1000;
+const OUTER_LEN: usize = 4000;
+
+/// Returns a generator that derives the keys only from their positions.
+fn data_gen() -> JoinDataGenFn<i64> {
+    Box::new(
+        |pk_key: &mut [i64], pk_pay: &mut [i64], fk_key: &mut [i64], fk_pay: &mut [i64]| {
+            pk_key
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, k)| *k = i as i64 + 1);
+            fk_key
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, k)| *k = (i % INNER_LEN) as i64 + 1);
+            pk_pay.iter_mut().for_each(|p| *p = 1);
+            fk_pay.iter_mut().for_each(|p| *p = 1);
+            Ok(())
+        },
+    )
+}
+
+fn data_builder(keys_only: bool) -> JoinDataBuilder {
+    let mut data_builder = JoinDataBuilder::default();
+    data_builder
+        .inner_len(INNER_LEN)
+        .outer_len(OUTER_LEN)
+        .shuffle(Some(7))
+        .keys_only(keys_only);
+
+    data_builder
+}
+
+/// Generates the join data, with the keys derived only from their positions.
+fn gen_join_data(keys_only: bool) -> Result<JoinData<i64>, Box<dyn Error>> {
+    let (join_data, _, _) = data_builder(keys_only).build_with_data_gen(data_gen())?;
+
+    Ok(join_data)
+}
+
+#[test]
+fn keys_only_data_gen_equals_full_data_gen_keys() -> Result<(), Box<dyn Error>> {
+    let full = gen_join_data(false)?;
+    let keys_only = gen_join_data(true)?;
+
+    for (full, keys_only) in [
+        (&full.build_relation, &keys_only.build_relation),
+        (&full.probe_relation, &keys_only.probe_relation),
+    ] {
+        assert!(!full.is_key_only());
+        assert!(keys_only.is_key_only());
+
+        assert_eq!(full.len(), keys_only.len());
+        assert_eq!(full.key().try_as_slice()?, keys_only.key().try_as_slice()?);
+
+        // No payloads are allocated
+        assert_eq!(keys_only.value().len(), 0);
+        assert_eq!(
+            keys_only.bytes(),
+            keys_only.len() * std::mem::size_of::<i64>()
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn combined_data_gen_equals_separate_data_gen() -> Result<(), Box<dyn Error>> {
+    for &keys_only in &[false, true] {
+        let separate = gen_join_data(keys_only)?;
+        let (combined, _, _) = data_builder(keys_only)
+            .build_combined_with_data_gen(DerefMemType::SysMem, data_gen())?;
+
+        assert_eq!(combined.build_len(), separate.build_relation.len());
+        assert_eq!(combined.probe_len(), separate.probe_relation.len());
+        assert_eq!(combined.build_bytes(), separate.build_relation.bytes());
+        assert_eq!(combined.probe_bytes(), separate.probe_relation.bytes());
+
+        let (build_key, build_pay, probe_key, probe_pay) = combined.attributes();
+        assert_eq!(build_key, separate.build_relation.key().try_as_slice()?);
+        assert_eq!(build_pay, separate.build_relation.value().try_as_slice()?);
+        assert_eq!(probe_key, separate.probe_relation.key().try_as_slice()?);
+        assert_eq!(probe_pay, separate.probe_relation.value().try_as_slice()?);
+    }
+
+    Ok(())
+}
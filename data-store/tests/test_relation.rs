@@ -0,0 +1,54 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use data_store::error::ErrorKind;
+use data_store::relation::Relation;
+use numa_gpu::runtime::memory::Mem;
+use std::error::Error;
+use std::result::Result;
+
+#[test]
+fn relation_bundles_key_and_value() -> Result<(), Box<dyn Error>> {
+    let keys: Vec<i64> = vec![1, 2, 3, 4];
+    let values: Vec<i32> = vec![10, 20, 30, 40];
+
+    let mut relation = Relation::new(Mem::SysMem(keys.clone()), Mem::SysMem(values.clone()))?;
+
+    assert_eq!(relation.len(), 4);
+    assert!(!relation.is_empty());
+    assert_eq!(relation.bytes(), 4 * (8 + 4));
+
+    let (key_slice, value_slice) = relation.try_as_mut_slices()?;
+    value_slice[0] = key_slice[0] as i32;
+
+    let (key_slice, value_slice) = relation.try_as_slices()?;
+    assert_eq!(key_slice, keys.as_slice());
+    assert_eq!(value_slice, &[1, 20, 30, 40]);
+
+    Ok(())
+}
+
+#[test]
+fn relation_rejects_different_lengths() {
+    let keys: Vec<i64> = vec![1, 2, 3];
+    let values: Vec<i64> = vec![1, 2];
+
+    match Relation::new(Mem::SysMem(keys), Mem::SysMem(values)) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for attributes with different lengths"),
+    }
+}
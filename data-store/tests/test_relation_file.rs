@@ -0,0 +1,81 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use data_store::error::ErrorKind;
+use data_store::relation_file::{dump_relation, load_relation};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::result::Result;
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("data_store_{}_{}.rel", name, std::process::id()))
+}
+
+#[test]
+fn dump_and_load_preserves_bytes() -> Result<(), Box<dyn Error>> {
+    let path = temp_file("round_trip");
+    let reloaded_path = temp_file("round_trip_reloaded");
+
+    let keys: Vec<i64> = vec![i64::MIN, -1, 0, 1, 0x0102_0304_0506_0708, i64::MAX];
+    let values: Vec<i32> = vec![7, i32::MIN, 0, -42, 0x0a0b_0c0d, i32::MAX];
+
+    dump_relation(&path, &keys, &values)?;
+    let (loaded_keys, loaded_values) = load_relation::<i64, i32, _>(&path)?;
+    dump_relation(&reloaded_path, &loaded_keys, &loaded_values)?;
+
+    let bytes = fs::read(&path)?;
+    let reloaded_bytes = fs::read(&reloaded_path)?;
+    fs::remove_file(&path)?;
+    fs::remove_file(&reloaded_path)?;
+
+    assert_eq!(keys, loaded_keys);
+    assert_eq!(values, loaded_values);
+    assert_eq!(bytes, reloaded_bytes);
+
+    // The tuples are stored in little-endian byte order after the header
+    assert_eq!(bytes.len(), 24 + keys.len() * 12);
+    assert_eq!(
+        &bytes[24 + 4 * 12..24 + 4 * 12 + 8],
+        &[8, 7, 6, 5, 4, 3, 2, 1]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn load_rejects_mismatching_types() -> Result<(), Box<dyn Error>> {
+    let path = temp_file("type_mismatch");
+
+    dump_relation(&path, &[1_i32, 2, 3], &[4_i32, 5, 6])?;
+    let loaded = load_relation::<i64, i64, _>(&path);
+    fs::remove_file(&path)?;
+
+    match loaded {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error when loading mismatching types"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn dump_rejects_mismatching_lengths() {
+    let path = temp_file("length_mismatch");
+
+    assert!(dump_relation(&path, &[1_u32, 2], &[3_u32]).is_err());
+}
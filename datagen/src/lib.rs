@@ -15,3 +15,5 @@
 pub mod error;
 pub mod popular;
 pub mod relation;
+pub mod rng;
+pub mod shuffle;
@@ -17,6 +17,7 @@
 
 use super::relation::{KeyAttribute, UniformRelation};
 use crate::error::Result;
+use crate::rng::SeededRng;
 use num_traits::FromPrimitive;
 
 /// Generator for the Kim data set.
@@ -51,12 +52,29 @@ impl Kim {
         pk_attr: &mut [T],
         fk_attr: &mut [T],
         selectivity: Option<u32>,
+    ) -> Result<()> {
+        Self::gen_with_rng(
+            pk_attr,
+            fk_attr,
+            selectivity,
+            &mut SeededRng::from_entropy(),
+        )
+    }
+
+    /// Generates the data set from the random numbers of `rng`.
+    ///
+    /// See `gen`.
+    pub fn gen_with_rng<T: Copy + Send + KeyAttribute + FromPrimitive>(
+        pk_attr: &mut [T],
+        fk_attr: &mut [T],
+        selectivity: Option<u32>,
+        rng: &mut SeededRng,
     ) -> Result<()> {
         assert!(pk_attr.len() == Self::primary_key_len());
         assert!(fk_attr.len() == Self::foreign_key_len());
 
-        UniformRelation::gen_primary_key_par(pk_attr, selectivity)?;
-        UniformRelation::gen_attr_par(fk_attr, 0..pk_attr.len())?;
+        UniformRelation::gen_primary_key_par_with_rng(pk_attr, selectivity, rng)?;
+        UniformRelation::gen_attr_par_with_rng(fk_attr, 0..pk_attr.len(), rng)?;
         Ok(())
     }
 }
@@ -94,12 +112,29 @@ impl Blanas {
         pk_attr: &mut [T],
         fk_attr: &mut [T],
         selectivity: Option<u32>,
+    ) -> Result<()> {
+        Self::gen_with_rng(
+            pk_attr,
+            fk_attr,
+            selectivity,
+            &mut SeededRng::from_entropy(),
+        )
+    }
+
+    /// Generates the data set from the random numbers of `rng`.
+    ///
+    /// See `gen`.
+    pub fn gen_with_rng<T: Copy + Send + KeyAttribute + FromPrimitive>(
+        pk_attr: &mut [T],
+        fk_attr: &mut [T],
+        selectivity: Option<u32>,
+        rng: &mut SeededRng,
     ) -> Result<()> {
         assert!(pk_attr.len() == Self::primary_key_len());
         assert!(fk_attr.len() == Self::foreign_key_len());
 
-        UniformRelation::gen_primary_key_par(pk_attr, selectivity)?;
-        UniformRelation::gen_attr_par(fk_attr, 0..pk_attr.len())?;
+        UniformRelation::gen_primary_key_par_with_rng(pk_attr, selectivity, rng)?;
+        UniformRelation::gen_attr_par_with_rng(fk_attr, 0..pk_attr.len(), rng)?;
         Ok(())
     }
 }
@@ -19,23 +19,31 @@
 use num_traits::FromPrimitive;
 
 use crate::error::{ErrorKind, Result};
+use crate::rng::{chunk_rng, sort_key, SeededRng};
 
 use std::convert::TryFrom;
 use std::ops::Range;
 
 use rand::distributions::{Distribution, Uniform};
 use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use rayon::prelude::*;
 
 use zipf::ZipfDistribution;
 
+/// Number of tuples that a parallel generator draws from the same random
+/// number generator.
+///
+/// The chunks are independent of the thread schedule, which keeps the
+/// parallel generators reproducible.
+const PAR_CHUNK_LEN: usize = 1 << 16;
+
 /// Specifies that the type is suitable to be a join, grouping, or partitioning key.
 ///
 /// A key attribute is a primitive type (e.g., an integer or floating point type).
 /// It reserves a `NULL` value in it's value range and `usize` values map to a
-/// unique key value.
+/// unique key value. Non-`NULL` keys map back to their `usize` value.
 ///
 /// The `NULL` value is expected to have a binary representation of all ones. For
 /// signed integers, that value equals -1, for unsigned integers, the value
@@ -46,6 +54,7 @@ use zipf::ZipfDistribution;
 pub trait KeyAttribute: Sized + 'static {
     fn null_key() -> Self;
     fn try_from_usize(x: usize) -> Result<Self>;
+    fn to_usize(&self) -> usize;
 }
 
 impl KeyAttribute for i32 {
@@ -58,6 +67,10 @@ impl KeyAttribute for i32 {
             ErrorKind::IntegerOverflow("Failed to covnert from usize".to_string()).into()
         })
     }
+
+    fn to_usize(&self) -> usize {
+        *self as usize
+    }
 }
 
 impl KeyAttribute for i64 {
@@ -70,6 +83,48 @@ impl KeyAttribute for i64 {
             ErrorKind::IntegerOverflow("Failed to covnert from usize".to_string()).into()
         })
     }
+
+    fn to_usize(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl KeyAttribute for u8 {
+    fn null_key() -> Self {
+        Self::MAX
+    }
+
+    fn try_from_usize(x: usize) -> Result<Self> {
+        Self::try_from(x)
+            .ok()
+            .filter(|&key| key != Self::null_key())
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to convert from usize".to_string()).into()
+            })
+    }
+
+    fn to_usize(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl KeyAttribute for u16 {
+    fn null_key() -> Self {
+        Self::MAX
+    }
+
+    fn try_from_usize(x: usize) -> Result<Self> {
+        Self::try_from(x)
+            .ok()
+            .filter(|&key| key != Self::null_key())
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to convert from usize".to_string()).into()
+            })
+    }
+
+    fn to_usize(&self) -> usize {
+        *self as usize
+    }
 }
 
 /// Generator for relations with uniform distribution.
@@ -88,17 +143,27 @@ impl UniformRelation {
     pub fn gen_primary_key<T: KeyAttribute>(
         attr: &mut [T],
         selectivity: Option<u32>,
+    ) -> Result<()> {
+        Self::gen_primary_key_with_rng(attr, selectivity, &mut SeededRng::from_entropy())
+    }
+
+    /// Generates a primary key attribute from the random numbers of `rng`.
+    ///
+    /// See `gen_primary_key`.
+    pub fn gen_primary_key_with_rng<T: KeyAttribute>(
+        attr: &mut [T],
+        selectivity: Option<u32>,
+        rng: &mut SeededRng,
     ) -> Result<()> {
         let selectivity = selectivity.unwrap_or_else(|| 100);
         let percent = Uniform::from(0..=100);
-        let mut rng = thread_rng();
 
         attr.iter_mut()
             .by_ref()
             .zip(0..)
             .map(|(x, i)| {
                 T::try_from_usize(i).map(|i| {
-                    let val = if percent.sample(&mut rng) <= selectivity {
+                    let val = if percent.sample(rng) <= selectivity {
                         i
                     } else {
                         T::null_key()
@@ -108,7 +173,7 @@ impl UniformRelation {
             })
             .collect::<Result<()>>()?;
 
-        attr.shuffle(&mut rng);
+        attr.shuffle(rng);
         Ok(())
     }
 
@@ -121,31 +186,53 @@ impl UniformRelation {
     /// `selectivity` specifies the join selectivity in percent. An according
     /// percentage of keys are set to the `NULL` value. By default (`None`), the
     /// selectivity is 100%.
-    pub fn gen_primary_key_par<T: Clone + Send + KeyAttribute>(
+    pub fn gen_primary_key_par<T: Send + KeyAttribute>(
+        attr: &mut [T],
+        selectivity: Option<u32>,
+    ) -> Result<()> {
+        Self::gen_primary_key_par_with_rng(attr, selectivity, &mut SeededRng::from_entropy())
+    }
+
+    /// Generates a primary key attribute in parallel from the random numbers
+    /// of `rng`.
+    ///
+    /// See `gen_primary_key_par`. The keys don't depend on the number of
+    /// threads.
+    pub fn gen_primary_key_par_with_rng<T: Send + KeyAttribute>(
         attr: &mut [T],
         selectivity: Option<u32>,
+        rng: &mut SeededRng,
     ) -> Result<()> {
         let selectivity = selectivity.unwrap_or_else(|| 100);
         let percent = Uniform::from(0..=100);
-        let mut shuffled: Vec<(usize, T)> = (0..(attr.len()))
-            .into_par_iter()
-            .map_init(thread_rng, |mut rng, i| {
-                T::try_from_usize(i).map(|i| {
-                    let val = if percent.sample(&mut rng) <= selectivity {
-                        i
-                    } else {
-                        T::null_key()
-                    };
-                    (rng.gen(), val)
-                })
+        let chunk_seed = rng.chunk_seed();
+        let sort_seed = rng.chunk_seed();
+
+        attr.par_chunks_mut(PAR_CHUNK_LEN)
+            .enumerate()
+            .map(|(chunk_id, chunk)| {
+                chunk
+                    .iter_mut()
+                    .zip((chunk_id * PAR_CHUNK_LEN)..)
+                    .map(|(x, i)| T::try_from_usize(i).map(|i| *x = i))
+                    .collect::<Result<()>>()
             })
-            .collect::<Result<_>>()?;
-
-        shuffled.as_mut_slice().par_sort_unstable_by_key(|x| x.0);
+            .collect::<Result<()>>()?;
 
-        attr.par_iter_mut()
-            .zip_eq(shuffled.into_par_iter())
-            .for_each(|(x, t)| *x = t.1);
+        // Sorting by a random permutation of the keys shuffles them in place.
+        // The keys are unique, thus the unstable sort is deterministic.
+        attr.par_sort_unstable_by_key(|x| sort_key(sort_seed, x.to_usize()));
+
+        attr.par_chunks_mut(PAR_CHUNK_LEN)
+            .enumerate()
+            .for_each(|(chunk_id, chunk)| {
+                let mut rng = chunk_rng(chunk_seed, chunk_id);
+                chunk.iter_mut().for_each(|x| {
+                    if percent.sample(&mut rng) > selectivity {
+                        *x = T::null_key();
+                    }
+                })
+            });
 
         Ok(())
     }
@@ -156,27 +243,202 @@ impl UniformRelation {
     /// they follow a foreign-key relationship. If the primary keys are unique,
     /// then the generated foreign keys follow a uniform distribution.
     pub fn gen_foreign_key_from_primary_key<T: Copy>(fk_attr: &mut [T], pk_attr: &[T]) {
-        let mut rng = thread_rng();
+        Self::gen_foreign_key_from_primary_key_with_rng(
+            fk_attr,
+            pk_attr,
+            &mut SeededRng::from_entropy(),
+        )
+    }
 
+    /// Generates a foreign key attribute based on a primary key attribute from
+    /// the random numbers of `rng`.
+    ///
+    /// See `gen_foreign_key_from_primary_key`.
+    pub fn gen_foreign_key_from_primary_key_with_rng<T: Copy>(
+        fk_attr: &mut [T],
+        pk_attr: &[T],
+        rng: &mut SeededRng,
+    ) {
         fk_attr
             .iter_mut()
             .by_ref()
             .zip(pk_attr.iter().cycle())
             .for_each(|(fk, pk)| *fk = *pk);
-        fk_attr.shuffle(&mut rng);
+        fk_attr.shuffle(rng);
+    }
+
+    /// Generates a foreign key attribute in which each referenced primary key
+    /// occurs about `multiplicity` times.
+    ///
+    /// The multiplicity is the fan-out of the join, i.e., each referenced
+    /// primary key joins with about `multiplicity` foreign keys. Thus, the join
+    /// result has the length of `fk_attr`, and the build side contributes
+    /// `fk_attr.len() / multiplicity` distinct keys to it.
+    ///
+    /// The referenced primary keys are sampled without replacement from
+    /// `pk_attr`. The remaining primary keys are not referenced. Fractional
+    /// multiplicities reference some keys once more than others.
+    ///
+    /// Returns an error if `multiplicity` is less than 1, or if `pk_attr`
+    /// doesn't contain enough keys for the requested multiplicity.
+    pub fn gen_foreign_key_with_multiplicity<T: Copy>(
+        fk_attr: &mut [T],
+        pk_attr: &[T],
+        multiplicity: f64,
+    ) -> Result<()> {
+        Self::gen_foreign_key_with_multiplicity_with_rng(
+            fk_attr,
+            pk_attr,
+            multiplicity,
+            &mut SeededRng::from_entropy(),
+        )
+    }
+
+    /// Generates a foreign key attribute with a multiplicity from the random
+    /// numbers of `rng`.
+    ///
+    /// See `gen_foreign_key_with_multiplicity`.
+    pub fn gen_foreign_key_with_multiplicity_with_rng<T: Copy>(
+        fk_attr: &mut [T],
+        pk_attr: &[T],
+        multiplicity: f64,
+        rng: &mut SeededRng,
+    ) -> Result<()> {
+        if !(multiplicity >= 1.0) {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Multiplicity must be at least 1, got {}",
+                multiplicity
+            )))?;
+        }
+
+        if fk_attr.is_empty() {
+            return Ok(());
+        }
+
+        let referenced_len = ((fk_attr.len() as f64 / multiplicity).round() as usize).max(1);
+        if referenced_len > pk_attr.len() {
+            Err(ErrorKind::InvalidArgument(format!(
+                "{} foreign keys with multiplicity {} require {} primary keys, but got {}",
+                fk_attr.len(),
+                multiplicity,
+                referenced_len,
+                pk_attr.len()
+            )))?;
+        }
+
+        let referenced: Vec<T> = pk_attr
+            .choose_multiple(rng, referenced_len)
+            .copied()
+            .collect();
+
+        fk_attr
+            .iter_mut()
+            .zip(referenced.iter().cycle())
+            .for_each(|(fk, pk)| *fk = *pk);
+        fk_attr.shuffle(rng);
+
+        Ok(())
+    }
+
+    /// Generates a foreign key attribute in which a few heavy-hitter keys
+    /// occur much more frequently than the others.
+    ///
+    /// A `heavy_fraction` of the foreign keys are the `heavy_keys`, each with
+    /// the same frequency. The remaining foreign keys are sampled uniformly
+    /// with replacement from the primary keys that aren't heavy hitters. Thus,
+    /// the heavy keys occur exactly in the requested fraction of the rows.
+    ///
+    /// Returns an error if `heavy_fraction` isn't within [0, 1], or if there
+    /// are no keys to sample the heavy or the uniform rows from.
+    pub fn gen_foreign_key_heavy_hitters<T: Copy + PartialEq>(
+        fk_attr: &mut [T],
+        pk_attr: &[T],
+        heavy_keys: &[T],
+        heavy_fraction: f64,
+    ) -> Result<()> {
+        Self::gen_foreign_key_heavy_hitters_with_rng(
+            fk_attr,
+            pk_attr,
+            heavy_keys,
+            heavy_fraction,
+            &mut SeededRng::from_entropy(),
+        )
+    }
+
+    /// Generates a foreign key attribute with heavy hitters from the random
+    /// numbers of `rng`.
+    ///
+    /// See `gen_foreign_key_heavy_hitters`.
+    pub fn gen_foreign_key_heavy_hitters_with_rng<T: Copy + PartialEq>(
+        fk_attr: &mut [T],
+        pk_attr: &[T],
+        heavy_keys: &[T],
+        heavy_fraction: f64,
+        rng: &mut SeededRng,
+    ) -> Result<()> {
+        if !(0.0..=1.0).contains(&heavy_fraction) {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Heavy-hitter fraction must be within [0, 1], got {}",
+                heavy_fraction
+            )))?;
+        }
+
+        let heavy_len = (fk_attr.len() as f64 * heavy_fraction).round() as usize;
+        if heavy_len > 0 && heavy_keys.is_empty() {
+            Err(ErrorKind::InvalidArgument(
+                "Heavy-hitter fraction requires at least one heavy key".to_string(),
+            ))?;
+        }
+
+        let uniform_keys: Vec<T> = pk_attr
+            .iter()
+            .filter(|key| !heavy_keys.contains(key))
+            .copied()
+            .collect();
+        if heavy_len < fk_attr.len() && uniform_keys.is_empty() {
+            Err(ErrorKind::InvalidArgument(
+                "Primary keys contain no keys besides the heavy keys".to_string(),
+            ))?;
+        }
+
+        let (heavy, uniform) = fk_attr.split_at_mut(heavy_len);
+
+        heavy
+            .iter_mut()
+            .zip(heavy_keys.iter().cycle())
+            .for_each(|(fk, key)| *fk = *key);
+        uniform.iter_mut().for_each(|fk| {
+            *fk = *uniform_keys
+                .choose(rng)
+                .expect("Uniform keys must not be empty")
+        });
+        fk_attr.shuffle(rng);
+
+        Ok(())
     }
 
     /// Generates a uniformly distributed attribute.
     ///
     /// The generated values are sampled from `range`.
     pub fn gen_attr<T: FromPrimitive>(attr: &mut [T], range: Range<usize>) -> Result<()> {
-        let mut rng = thread_rng();
+        Self::gen_attr_with_rng(attr, range, &mut SeededRng::from_entropy())
+    }
+
+    /// Generates a uniformly distributed attribute from the random numbers of
+    /// `rng`.
+    ///
+    /// See `gen_attr`.
+    pub fn gen_attr_with_rng<T: FromPrimitive>(
+        attr: &mut [T],
+        range: Range<usize>,
+        rng: &mut SeededRng,
+    ) -> Result<()> {
         let between = Uniform::from(range);
 
         attr.iter_mut()
             .by_ref()
             .map(|x| {
-                FromPrimitive::from_usize(between.sample(&mut rng))
+                FromPrimitive::from_usize(between.sample(rng))
                     .ok_or_else(|| {
                         ErrorKind::IntegerOverflow("Failed to convert from usize".to_string())
                             .into()
@@ -195,20 +457,39 @@ impl UniformRelation {
         attr: &mut [T],
         range: Range<usize>,
     ) -> Result<()> {
-        let between = Uniform::from(range);
+        Self::gen_attr_par_with_rng(attr, range, &mut SeededRng::from_entropy())
+    }
 
-        attr.par_iter_mut()
-            .map_init(
-                || thread_rng(),
-                |mut rng, x| {
-                    FromPrimitive::from_usize(between.sample(&mut rng))
-                        .ok_or_else(|| {
-                            ErrorKind::IntegerOverflow("Failed to convert from usize".to_string())
+    /// Generates a uniformly distributed attribute in parallel from the random
+    /// numbers of `rng`.
+    ///
+    /// See `gen_attr_par`. The values don't depend on the number of threads.
+    pub fn gen_attr_par_with_rng<T: FromPrimitive + Send>(
+        attr: &mut [T],
+        range: Range<usize>,
+        rng: &mut SeededRng,
+    ) -> Result<()> {
+        let between = Uniform::from(range);
+        let chunk_seed = rng.chunk_seed();
+
+        attr.par_chunks_mut(PAR_CHUNK_LEN)
+            .enumerate()
+            .map(|(chunk_id, chunk)| {
+                let mut rng = chunk_rng(chunk_seed, chunk_id);
+                chunk
+                    .iter_mut()
+                    .map(|x| {
+                        FromPrimitive::from_usize(between.sample(&mut rng))
+                            .ok_or_else(|| {
+                                ErrorKind::IntegerOverflow(
+                                    "Failed to convert from usize".to_string(),
+                                )
                                 .into()
-                        })
-                        .map(|r| *x = r)
-                },
-            )
+                            })
+                            .map(|r| *x = r)
+                    })
+                    .collect::<Result<()>>()
+            })
             .collect::<Result<()>>()?;
 
         Ok(())
@@ -230,7 +511,19 @@ impl ZipfRelation {
         num_elements: usize,
         exponent: f64,
     ) -> Result<()> {
-        let mut rng = thread_rng();
+        Self::gen_attr_with_rng(attr, num_elements, exponent, &mut SeededRng::from_entropy())
+    }
+
+    /// Generates an attribute following the Zipf distribution from the random
+    /// numbers of `rng`.
+    ///
+    /// See `gen_attr`.
+    pub fn gen_attr_with_rng<T: FromPrimitive>(
+        attr: &mut [T],
+        num_elements: usize,
+        exponent: f64,
+        rng: &mut SeededRng,
+    ) -> Result<()> {
         let between = ZipfDistribution::new(num_elements, exponent).map_err(|_| {
             ErrorKind::InvalidArgument(
                 "ZipfDistribution requires num_elements and exponent greater than 0".to_string(),
@@ -240,7 +533,7 @@ impl ZipfRelation {
         attr.iter_mut()
             .by_ref()
             .map(|x| {
-                FromPrimitive::from_usize(between.sample(&mut rng))
+                FromPrimitive::from_usize(between.sample(rng))
                     .ok_or_else(|| {
                         ErrorKind::IntegerOverflow("Failed to convert from usize".to_string())
                             .into()
@@ -262,6 +555,19 @@ impl ZipfRelation {
         attr: &mut [T],
         num_elements: usize,
         exponent: f64,
+    ) -> Result<()> {
+        Self::gen_attr_par_with_rng(attr, num_elements, exponent, &mut SeededRng::from_entropy())
+    }
+
+    /// Generates an attribute following the Zipf distribution in parallel
+    /// from the random numbers of `rng`.
+    ///
+    /// See `gen_attr_par`. The values don't depend on the number of threads.
+    pub fn gen_attr_par_with_rng<T: FromPrimitive + Send>(
+        attr: &mut [T],
+        num_elements: usize,
+        exponent: f64,
+        rng: &mut SeededRng,
     ) -> Result<()> {
         let between = ZipfDistribution::new(num_elements, exponent).map_err(|_| {
             ErrorKind::InvalidArgument(
@@ -269,20 +575,28 @@ impl ZipfRelation {
             )
         })?;
 
+        let chunk_seed = rng.chunk_seed();
+
         // ZipfDistribution generates elements in range [1, num_elements]. Thus,
         // need to substract 1 to get a range [0, num_elements[.
-        attr.par_iter_mut()
-            .map_init(
-                || thread_rng(),
-                |mut rng, x| {
-                    FromPrimitive::from_usize(between.sample(&mut rng) - 1)
-                        .ok_or_else(|| {
-                            ErrorKind::IntegerOverflow("Failed to convert from usize".to_string())
+        attr.par_chunks_mut(PAR_CHUNK_LEN)
+            .enumerate()
+            .map(|(chunk_id, chunk)| {
+                let mut rng = chunk_rng(chunk_seed, chunk_id);
+                chunk
+                    .iter_mut()
+                    .map(|x| {
+                        FromPrimitive::from_usize(between.sample(&mut rng) - 1)
+                            .ok_or_else(|| {
+                                ErrorKind::IntegerOverflow(
+                                    "Failed to convert from usize".to_string(),
+                                )
                                 .into()
-                        })
-                        .map(|r| *x = r)
-                },
-            )
+                            })
+                            .map(|r| *x = r)
+                    })
+                    .collect::<Result<()>>()
+            })
             .collect::<Result<()>>()?;
 
         Ok(())
@@ -0,0 +1,100 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A resettable random number generator for reproducible data sets.
+//!
+//! A benchmark sweep generates a new data set for each parameter point. To
+//! compare the points, each point must see the same data. `SeededRng`
+//! remembers its base seed, and `reset` rewinds the generator to the state
+//! after seeding. Thus, resetting the generator before generating a point's
+//! relations yields the same relations for every point.
+//!
+//! Parallel generators must not depend on the thread schedule. They split the
+//! relation into fixed-size chunks, and derive one generator per chunk with
+//! `chunk_rng`.
+
+use rand::rngs::StdRng;
+use rand::{thread_rng, Error, Rng, RngCore, SeedableRng};
+
+/// A seeded random number generator that can be reset to its base seed.
+#[derive(Clone, Debug)]
+pub struct SeededRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SeededRng {
+    /// Creates a generator from a base seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Creates a generator with a random base seed.
+    pub fn from_entropy() -> Self {
+        Self::new(thread_rng().gen())
+    }
+
+    /// Returns the base seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Rewinds the generator to its base seed.
+    pub fn reset(&mut self) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+
+    /// Draws a seed from which `chunk_rng` derives the per-chunk generators
+    /// of a parallel generator.
+    pub(crate) fn chunk_seed(&mut self) -> u64 {
+        self.rng.gen()
+    }
+}
+
+/// Returns the generator for a chunk of a parallel generator.
+pub(crate) fn chunk_rng(chunk_seed: u64, chunk_id: usize) -> StdRng {
+    StdRng::seed_from_u64(chunk_seed.wrapping_add(chunk_id as u64))
+}
+
+/// Returns the position of `x` in a random permutation.
+///
+/// The permutation is the SplitMix64 output function applied to a Weyl
+/// sequence. It's a bijection on `u64`, thus distinct `x` never collide.
+pub(crate) fn sort_key(seed: u64, x: usize) -> u64 {
+    let mut z = seed.wrapping_add((x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Permutations that break up the input order of relations.
+//!
+//! Generators and data set files may produce sorted or nearly sorted keys.
+//! Sorted input flatters the cache behavior of joins and partitioning, and
+//! thus misrepresents the performance on real data. Shuffling the relation
+//! removes such ordering effects.
+//!
+//! The shuffle is a Fisher-Yates permutation driven by a seeded random number
+//! generator. Thus, the same seed always produces the same permutation for a
+//! given relation length.
+
+use crate::error::{ErrorKind, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Shuffles an attribute with a seeded Fisher-Yates permutation.
+pub fn shuffle<T>(attr: &mut [T], seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for i in (1..attr.len()).rev() {
+        let j = rng.gen_range(0, i + 1);
+        attr.swap(i, j);
+    }
+}
+
+/// Shuffles a key and a payload attribute in lockstep.
+///
+/// Both attributes are permuted with the same seeded Fisher-Yates
+/// permutation. Thus, each key stays together with its payload. The
+/// permutation is identical to the one applied by `shuffle` with the same
+/// seed.
+///
+/// Returns an error if the attributes have different lengths.
+pub fn shuffle_with_payload<K, V>(keys: &mut [K], payloads: &mut [V], seed: u64) -> Result<()> {
+    if keys.len() != payloads.len() {
+        Err(ErrorKind::InvalidArgument(
+            "Key and payload attributes have different lengths".to_string(),
+        ))?;
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for i in (1..keys.len()).rev() {
+        let j = rng.gen_range(0, i + 1);
+        keys.swap(i, j);
+        payloads.swap(i, j);
+    }
+
+    Ok(())
+}
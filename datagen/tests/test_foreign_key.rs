@@ -0,0 +1,143 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datagen::relation::UniformRelation;
+use std::collections::HashMap;
+use std::error::Error;
+use std::result::Result;
+
+const PK_LEN: usize = 1 << 16;
+const FK_LEN: usize = 1 << 18;
+
+/// Returns the number of references per referenced primary key.
+fn references_per_key(fk_attr: &[i64]) -> HashMap<i64, usize> {
+    let mut counts = HashMap::new();
+    fk_attr
+        .iter()
+        .for_each(|&fk| *counts.entry(fk).or_insert(0) += 1);
+    counts
+}
+
+#[test]
+fn foreign_key_multiplicity_matches_average_references() -> Result<(), Box<dyn Error>> {
+    let mut pk_attr = vec![0_i64; PK_LEN];
+    UniformRelation::gen_primary_key(&mut pk_attr, None)?;
+
+    for &multiplicity in &[4.0, 6.5, 10.0, 32.0] {
+        let mut fk_attr = vec![-1_i64; FK_LEN];
+        UniformRelation::gen_foreign_key_with_multiplicity(&mut fk_attr, &pk_attr, multiplicity)?;
+
+        let counts = references_per_key(&fk_attr);
+        assert!(counts.keys().all(|&fk| fk >= 0 && (fk as usize) < PK_LEN));
+
+        let average = FK_LEN as f64 / counts.len() as f64;
+        assert!(
+            (average - multiplicity).abs() / multiplicity < 0.05,
+            "Average of {} references per key, expected {}",
+            average,
+            multiplicity
+        );
+
+        // The references are spread evenly over the referenced keys
+        let min = counts.values().min().ok_or("No references")?;
+        let max = counts.values().max().ok_or("No references")?;
+        assert!(max - min <= 1);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn foreign_key_multiplicity_rejects_invalid_arguments() {
+    let pk_attr: Vec<i64> = (0..16).collect();
+    let mut fk_attr = vec![0_i64; 1024];
+
+    assert!(
+        UniformRelation::gen_foreign_key_with_multiplicity(&mut fk_attr, &pk_attr, 0.5).is_err()
+    );
+    assert!(
+        UniformRelation::gen_foreign_key_with_multiplicity(&mut fk_attr, &pk_attr, f64::NAN)
+            .is_err()
+    );
+
+    // 1024 keys with multiplicity 2 need 512 primary keys
+    assert!(
+        UniformRelation::gen_foreign_key_with_multiplicity(&mut fk_attr, &pk_attr, 2.0).is_err()
+    );
+}
+
+#[test]
+fn heavy_hitters_occur_in_requested_fraction() -> Result<(), Box<dyn Error>> {
+    let mut pk_attr = vec![0_i64; PK_LEN];
+    UniformRelation::gen_primary_key(&mut pk_attr, None)?;
+    let heavy_keys = [pk_attr[0], pk_attr[1], pk_attr[2]];
+
+    for &heavy_fraction in &[0.0, 0.1, 0.5, 0.9, 1.0] {
+        let mut fk_attr = vec![-1_i64; FK_LEN];
+        UniformRelation::gen_foreign_key_heavy_hitters(
+            &mut fk_attr,
+            &pk_attr,
+            &heavy_keys,
+            heavy_fraction,
+        )?;
+
+        let counts = references_per_key(&fk_attr);
+        assert!(counts.keys().all(|&fk| fk >= 0 && (fk as usize) < PK_LEN));
+
+        let heavy_counts: Vec<usize> = heavy_keys
+            .iter()
+            .map(|key| counts.get(key).copied().unwrap_or(0))
+            .collect();
+        let heavy_rows: usize = heavy_counts.iter().sum();
+        let expected_rows = (FK_LEN as f64 * heavy_fraction).round() as usize;
+        assert_eq!(
+            heavy_rows, expected_rows,
+            "Heavy fraction {}",
+            heavy_fraction
+        );
+
+        // The heavy keys share their rows evenly
+        let min = heavy_counts.iter().min().ok_or("No heavy keys")?;
+        let max = heavy_counts.iter().max().ok_or("No heavy keys")?;
+        assert!(max - min <= 1);
+
+        // The uniform tail spreads over many different keys
+        assert!(counts.len() > (FK_LEN - expected_rows) / 4);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn heavy_hitters_reject_invalid_arguments() {
+    let pk_attr: Vec<i64> = (0..16).collect();
+    let mut fk_attr = vec![0_i64; 1024];
+
+    assert!(
+        UniformRelation::gen_foreign_key_heavy_hitters(&mut fk_attr, &pk_attr, &[0], 1.5).is_err()
+    );
+    assert!(
+        UniformRelation::gen_foreign_key_heavy_hitters(&mut fk_attr, &pk_attr, &[0], f64::NAN)
+            .is_err()
+    );
+    assert!(
+        UniformRelation::gen_foreign_key_heavy_hitters(&mut fk_attr, &pk_attr, &[], 0.5).is_err()
+    );
+
+    // All primary keys are heavy, thus there is no uniform tail
+    assert!(
+        UniformRelation::gen_foreign_key_heavy_hitters(&mut fk_attr, &pk_attr, &pk_attr, 0.5)
+            .is_err()
+    );
+}
@@ -0,0 +1,114 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datagen::relation::{UniformRelation, ZipfRelation};
+use datagen::rng::SeededRng;
+use std::error::Error;
+use std::result::Result;
+
+// Spans multiple chunks of the parallel generators
+const PK_LEN: usize = 100_000;
+const FK_LEN: usize = 300_000;
+const SEED: u64 = 42;
+
+/// Generates the relations of a sweep point after resetting the RNG.
+fn gen_point(rng: &mut SeededRng) -> Result<(Vec<i64>, Vec<i64>, Vec<i64>), Box<dyn Error>> {
+    rng.reset();
+
+    let mut pk = vec![0_i64; PK_LEN];
+    let mut fk = vec![0_i64; FK_LEN];
+    let mut zipf = vec![0_i64; FK_LEN];
+
+    UniformRelation::gen_primary_key_par_with_rng(&mut pk, Some(90), rng)?;
+    UniformRelation::gen_attr_par_with_rng(&mut fk, 0..PK_LEN, rng)?;
+    ZipfRelation::gen_attr_par_with_rng(&mut zipf, PK_LEN, 1.0, rng)?;
+
+    Ok((pk, fk, zipf))
+}
+
+#[test]
+fn reset_rng_generates_identical_points() -> Result<(), Box<dyn Error>> {
+    let mut rng = SeededRng::new(SEED);
+
+    let first = gen_point(&mut rng)?;
+    let second = gen_point(&mut rng)?;
+    assert!(first == second, "Reset RNG generated different relations");
+
+    // A fresh generator with the same seed is equivalent to a reset
+    let third = gen_point(&mut SeededRng::new(SEED))?;
+    assert!(first == third, "Same seed generated different relations");
+
+    Ok(())
+}
+
+#[test]
+fn different_seeds_generate_different_points() -> Result<(), Box<dyn Error>> {
+    let first = gen_point(&mut SeededRng::new(SEED))?;
+    let second = gen_point(&mut SeededRng::new(SEED + 1))?;
+
+    assert!(first.0 != second.0);
+    assert!(first.1 != second.1);
+    assert!(first.2 != second.2);
+
+    Ok(())
+}
+
+#[test]
+fn parallel_generators_are_independent_of_thread_count() -> Result<(), Box<dyn Error>> {
+    let gen_with_threads = |threads| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("Failed to build thread pool");
+        pool.install(|| gen_point(&mut SeededRng::new(SEED)).expect("Failed to generate relations"))
+    };
+
+    assert!(gen_with_threads(1) == gen_with_threads(4));
+
+    Ok(())
+}
+
+#[test]
+fn sequential_generators_follow_the_seed() -> Result<(), Box<dyn Error>> {
+    let mut rng = SeededRng::new(SEED);
+    let gen = |rng: &mut SeededRng| -> Result<_, Box<dyn Error>> {
+        rng.reset();
+        let mut pk = vec![0_i32; 1000];
+        let mut fk = vec![0_i32; 5000];
+        UniformRelation::gen_primary_key_with_rng(&mut pk, None, rng)?;
+        UniformRelation::gen_foreign_key_from_primary_key_with_rng(&mut fk, &pk, rng);
+        Ok((pk, fk))
+    };
+
+    let first = gen(&mut rng)?;
+    let second = gen(&mut rng)?;
+    assert_eq!(first, second);
+    assert_eq!(rng.seed(), SEED);
+
+    Ok(())
+}
+
+#[test]
+fn parallel_primary_key_is_a_shuffled_permutation() -> Result<(), Box<dyn Error>> {
+    let mut pk = vec![0_i64; PK_LEN];
+    UniformRelation::gen_primary_key_par_with_rng(&mut pk, None, &mut SeededRng::new(SEED))?;
+
+    let in_order: Vec<i64> = (0..PK_LEN as i64).collect();
+    assert!(pk != in_order, "Primary keys aren't shuffled");
+
+    pk.sort_unstable();
+    assert!(pk == in_order, "Primary keys aren't a permutation");
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datagen::shuffle::{shuffle, shuffle_with_payload};
+use std::error::Error;
+use std::result::Result;
+
+const LEN: usize = 1 << 16;
+const SEED: u64 = 42;
+
+#[test]
+fn shuffle_preserves_elements() {
+    let original: Vec<u64> = (0..LEN as u64).map(|x| x % 1000).collect();
+    let mut shuffled = original.clone();
+
+    shuffle(&mut shuffled, SEED);
+    assert_ne!(original, shuffled);
+
+    let mut expected = original;
+    expected.sort_unstable();
+    shuffled.sort_unstable();
+    assert_eq!(expected, shuffled);
+}
+
+#[test]
+fn shuffle_is_deterministic() {
+    let mut first: Vec<u64> = (0..LEN as u64).collect();
+    let mut second = first.clone();
+    let mut other_seed = first.clone();
+
+    shuffle(&mut first, SEED);
+    shuffle(&mut second, SEED);
+    shuffle(&mut other_seed, SEED + 1);
+
+    assert_eq!(first, second);
+    assert_ne!(first, other_seed);
+}
+
+#[test]
+fn shuffle_with_payload_keeps_tuples_together() -> Result<(), Box<dyn Error>> {
+    let mut keys: Vec<u64> = (0..LEN as u64).collect();
+    let mut payloads: Vec<u64> = keys.iter().map(|k| k * 3 + 1).collect();
+
+    shuffle_with_payload(&mut keys, &mut payloads, SEED)?;
+
+    assert!(keys
+        .iter()
+        .zip(payloads.iter())
+        .all(|(&k, &p)| p == k * 3 + 1));
+
+    let mut shuffled_keys = keys.clone();
+    shuffled_keys.sort_unstable();
+    assert!(shuffled_keys.iter().copied().eq(0..LEN as u64));
+
+    Ok(())
+}
+
+#[test]
+fn shuffle_with_payload_matches_shuffle() -> Result<(), Box<dyn Error>> {
+    let mut keys: Vec<u64> = (0..LEN as u64).collect();
+    let mut payloads = keys.clone();
+    let mut attr = keys.clone();
+
+    shuffle_with_payload(&mut keys, &mut payloads, SEED)?;
+    shuffle(&mut attr, SEED);
+
+    assert_eq!(keys, attr);
+
+    Ok(())
+}
+
+#[test]
+fn shuffle_with_payload_rejects_mismatching_lengths() {
+    let mut keys = vec![0_u64; 16];
+    let mut payloads = vec![0_u64; 15];
+
+    assert!(shuffle_with_payload(&mut keys, &mut payloads, SEED).is_err());
+}
@@ -19,29 +19,39 @@ mod types;
 use crate::error::{ErrorKind, Result};
 use crate::measurement::data_point::DataPoint;
 use crate::measurement::harness;
-use crate::measurement::hash_join_bench::{HashJoinBenchBuilder, HashJoinPoint};
+use crate::measurement::hash_join_bench::{
+    self, HashJoinBench, HashJoinBenchBuilder, HashJoinPoint, JoinRelations,
+};
 use crate::types::*;
 use data_store::join_data::{JoinDataBuilder, JoinDataGenFn};
 use datagen::relation::KeyAttribute;
+use datagen::rng::SeededRng;
 use likwid;
 use num_rational::Ratio;
 use num_traits::cast::AsPrimitive;
 use numa_gpu::runtime::allocator;
+use numa_gpu::runtime::cache_flush::CacheFlusher;
 use numa_gpu::runtime::cpu_affinity::CpuAffinity;
+use numa_gpu::runtime::cuda_wrapper::PrefetchDestination;
 use numa_gpu::runtime::dispatcher::{MorselSpec, WorkerCpuAffinity};
 use numa_gpu::runtime::hw_info::NvidiaDriverInfo;
 use numa_gpu::runtime::linux_wrapper;
 use numa_gpu::runtime::numa::{self, NodeRatio};
+use numa_gpu::runtime::perf_counters::{PerfCounterGroup, PerfEvent};
+use numa_gpu::runtime::trace::TraceRecorder;
 use rustacuda::context::CurrentContext;
-use rustacuda::device::DeviceAttribute;
 use rustacuda::function::{BlockSize, GridSize};
 use rustacuda::memory::DeviceCopy;
 use rustacuda::prelude::*;
 use serde::de::DeserializeOwned;
 use sql_ops::join::{no_partitioning_join, HashingScheme, HtEntry};
+use std::cell::RefCell;
+use std::convert::{TryFrom, TryInto};
 use std::mem::size_of;
 use std::os::raw::c_uint;
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
 use structopt::StructOpt;
 
 fn main() -> Result<()> {
@@ -53,6 +63,10 @@ fn main() -> Result<()> {
     let device = Device::get_device(cmd.device_id.into())?;
     let _context =
         Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device)?;
+    allocator::Allocator::set_device_memory_limit(
+        cmd.device_memory_limit.map(|s| s * 1024 * 1024), // convert MiB to bytes
+    );
+    allocator::Allocator::set_pinning_fallback(cmd.pinning_fallback);
 
     // Initialize LIKWID
     let _likwid = likwid::Likwid::init();
@@ -67,20 +81,116 @@ fn main() -> Result<()> {
     };
     cmd.set_spill_hash_table(cache_node, overflow_node)?;
 
+    let steady_state = if cmd.steady_state {
+        Some(harness::SteadyState {
+            device,
+            tolerance_mhz: cmd.steady_state_tolerance_mhz,
+            samples: cmd.steady_state_samples,
+            max_warm_ups: cmd.steady_state_max_warm_ups,
+        })
+    } else {
+        None
+    };
+
+    let adaptive_repeat = match cmd.ci_width {
+        Some(width) if width.is_nan() || width <= 0.0 => Err(ErrorKind::InvalidArgument(
+            "--ci-width must be greater than zero".to_string(),
+        ))?,
+        Some(width) => Some(harness::AdaptiveRepeat {
+            relative_width: width,
+            max_repeat: cmd.max_repeat,
+        }),
+        None => None,
+    };
+
+    // The flusher's threads run on the same cores as the CPU join threads, and
+    // thus flush the private caches of these cores
+    let mut cache_flusher = if cmd.flush_caches {
+        let threads = std::cmp::max(
+            cmd.build_threads.unwrap_or(cmd.threads),
+            cmd.probe_threads.unwrap_or(cmd.threads),
+        );
+        let cpu_affinity = if let Some(ref cpu_affinity_file) = cmd.cpu_affinity {
+            CpuAffinity::from_file(cpu_affinity_file.as_path())?
+        } else {
+            CpuAffinity::default()
+        };
+        let thread_pool = hash_join_bench::cpu_thread_pool(threads, &cpu_affinity, None)?;
+        Some(CacheFlusher::with_thread_pool(thread_pool))
+    } else {
+        None
+    };
+
+    let trace = if cmd.trace.is_some() {
+        if cmd.execution_method != ArgExecutionMethod::Gpu {
+            Err(ErrorKind::InvalidArgument(
+                "Tracing is only supported by the GPU execution method".to_string(),
+            ))?;
+        }
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        Some(Rc::new(RefCell::new(TraceRecorder::new(&stream)?)))
+    } else {
+        None
+    };
+
+    // The main thread registers itself, and the CPU thread pool registers its
+    // threads when they start
+    let perf_counters = if cmd.perf_counters.is_empty() {
+        None
+    } else {
+        let group = Arc::new(PerfCounterGroup::new(&cmd.perf_counters));
+        group.register_current_thread()?;
+        Some(group)
+    };
+
     match cmd.tuple_bytes {
         ArgTupleBytes::Bytes8 => {
-            let (hjc, dp) = args_to_bench::<i32>(&cmd, device)?;
-            harness::measure("hash_join_kim", cmd.repeat, cmd.csv, dp, hjc)?;
+            let (hjc, dp) =
+                args_to_bench::<i32>(&cmd, device, trace.clone(), perf_counters.clone())?;
+            harness::measure(
+                "hash_join_kim",
+                cmd.repeat,
+                adaptive_repeat.clone(),
+                cmd.csv.map(harness::CsvOutput::from),
+                cmd.csv_schema_header,
+                dp,
+                hjc,
+                steady_state,
+                cache_flusher.as_mut(),
+                perf_counters.as_deref(),
+            )?;
         }
         ArgTupleBytes::Bytes16 => {
-            let (hjc, dp) = args_to_bench::<i64>(&cmd, device)?;
-            harness::measure("hash_join_kim", cmd.repeat, cmd.csv, dp, hjc)?;
+            let (hjc, dp) =
+                args_to_bench::<i64>(&cmd, device, trace.clone(), perf_counters.clone())?;
+            harness::measure(
+                "hash_join_kim",
+                cmd.repeat,
+                adaptive_repeat.clone(),
+                cmd.csv.map(harness::CsvOutput::from),
+                cmd.csv_schema_header,
+                dp,
+                hjc,
+                steady_state,
+                cache_flusher.as_mut(),
+                perf_counters.as_deref(),
+            )?;
         }
     };
 
+    if let (Some(trace), Some(trace_file)) = (trace, cmd.trace) {
+        let file = std::fs::File::create(trace_file)?;
+        trace.borrow().write_json(std::io::BufWriter::new(file))?;
+    }
+
     Ok(())
 }
 
+/// Payload sizes supported by `--payload-bytes`.
+///
+/// Each size instantiates the wide-payload join for a fixed-size array.
+const PAYLOAD_BYTES: [&str; 8] = ["4", "8", "16", "32", "60", "64", "128", "256"];
+
 #[derive(StructOpt)]
 #[structopt(name = "hash_join", about = "A benchmark for the hash join operator")]
 struct CmdOpt {
@@ -88,10 +198,46 @@ struct CmdOpt {
     #[structopt(short = "r", long = "repeat", default_value = "30")]
     repeat: u32,
 
+    /// Repeat until the 95% confidence interval of the probe throughput is
+    /// at most this width relative to the mean (e.g., 0.05)
+    ///
+    /// Runs at least `--repeat` times, and at most `--max-repeat` times.
+    #[structopt(long = "ci-width")]
+    ci_width: Option<f64>,
+
+    /// Maximum number of runs with `--ci-width`
+    #[structopt(long = "max-repeat", default_value = "1000")]
+    max_repeat: u32,
+
     /// Output filename for measurement CSV file
-    #[structopt(long = "csv", parse(from_os_str))]
+    ///
+    /// With `-`, the CSV is written to stdout and the summary to stderr.
+    #[structopt(short = "o", long = "csv", parse(from_os_str))]
     csv: Option<PathBuf>,
 
+    /// Start the CSV with a `# schema_version=N` comment line
+    ///
+    /// The version changes whenever the columns change. Thus, downstream
+    /// tools can check that they understand the columns.
+    #[structopt(long = "csv-schema-header")]
+    csv_schema_header: bool,
+
+    /// Output filename for a Chrome trace of the GPU join phases
+    ///
+    /// The trace can be viewed with chrome://tracing or Perfetto. Only the
+    /// GPU execution method is traced.
+    #[structopt(long = "trace", parse(from_os_str))]
+    trace: Option<PathBuf>,
+
+    /// Count CPU hardware events during each run (e.g.: llc-misses,dtlb-misses)
+    ///
+    /// Supported events are cycles, instructions, l1d-misses, l2-misses,
+    /// llc-misses, dtlb-misses, and itlb-misses. The counters include the main
+    /// thread and the CPU join's thread pool. Requires access to perf events,
+    /// see perf_event_paranoid.
+    #[structopt(long = "perf-counters", require_delimiter = true)]
+    perf_counters: Vec<PerfEvent>,
+
     /// Memory type with which to allocate data.
     //   unified: CUDA Unified memory (default)
     //   numa: NUMA-local memory on node specified with [inner,outer]-rel-location
@@ -106,6 +252,7 @@ struct CmdOpt {
     /// Hashing scheme to use in hash table.
     //   linearprobing: Linear probing (default)
     //   perfect: Perfect hashing for unique primary keys
+    //   cuckoo: Cuckoo hashing for unique primary keys (CPU only)
     #[structopt(
         long = "hashing-scheme",
         default_value = "LinearProbing",
@@ -207,6 +354,21 @@ struct CmdOpt {
     )]
     selectivity: u32,
 
+    /// Seed for the data generator
+    ///
+    /// The same seed always generates the same relations. By default, the
+    /// relations are generated with a random seed.
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    /// Shuffle the relations with a seeded permutation after generating or loading them
+    #[structopt(long = "shuffle")]
+    shuffle: bool,
+
+    /// Seed for `--shuffle`; the same seed always yields the same permutation
+    #[structopt(long = "shuffle-seed", default_value = "0")]
+    shuffle_seed: u64,
+
     /// Load data set from a TSV file with "key value" pairs and automatic gzip decompression
     #[structopt(
         long = "inner-rel-file",
@@ -234,6 +396,74 @@ struct CmdOpt {
     )]
     tuple_bytes: ArgTupleBytes,
 
+    /// Set the build-side payload size (bytes) independently of the key size
+    ///
+    /// The key size is half of `--tuple-bytes`. Only the CPU execution method
+    /// supports wide payloads. The hash table is built by a single thread in
+    /// `System` memory.
+    #[structopt(long = "payload-bytes", possible_values = &PAYLOAD_BYTES)]
+    payload_bytes: Option<usize>,
+
+    /// Aggregate the join result by summing the probe payloads or by counting
+    /// the matches
+    ///
+    /// Count doesn't need the payloads, thus the join reads only the keys.
+    /// Only the CPU and GPU execution methods support Count.
+    #[structopt(
+        long = "aggregate",
+        default_value = "Sum",
+        possible_values = &ArgAggregate::variants(),
+        case_insensitive = true
+    )]
+    aggregate: ArgAggregate,
+
+    /// Skip allocating and generating the payloads
+    ///
+    /// Saves memory and setup time, as Count reads only the keys. Requires
+    /// `--aggregate Count` and generated data.
+    #[structopt(long = "keys-only-datagen")]
+    keys_only_datagen: bool,
+
+    /// Prefetch unified memory before each phase, either to "cpu" or to a
+    /// device ID
+    ///
+    /// Prefetches the relations and the hash table if they are allocated as
+    /// unified memory. Only the GPU execution method supports prefetching.
+    #[structopt(long = "prefetch-to")]
+    prefetch_to: Option<PrefetchDestination>,
+
+    /// Fault in the relations' pages before the build phase
+    ///
+    /// Touches each page on the join's threads, so that page faults don't
+    /// add noise to the measurement. Only the CPU execution method
+    /// pre-touches the relations.
+    #[structopt(long = "pre-touch")]
+    pre_touch: bool,
+
+    /// Store the relations in separate buffers, or combined in one buffer
+    ///
+    /// Combined generates the build and probe relations back-to-back into a
+    /// single buffer at the inner relation's memory location. Only the CPU
+    /// and GPU execution methods support Combined, and only for generated
+    /// data sets.
+    #[structopt(
+        long = "relation-layout",
+        default_value = "Separate",
+        possible_values = &ArgRelationLayout::variants(),
+        case_insensitive = true
+    )]
+    relation_layout: ArgRelationLayout,
+
+    /// Flush the CPU caches before each run to measure cold performance
+    ///
+    /// The CPU worker cores stream through a buffer twice the size of the
+    /// last-level cache, which evicts the relations and the hash table.
+    /// Without flushing, small data sets stay cached across runs, and the runs
+    /// after the first measure warm-cache performance. The GPU caches aren't
+    /// flushed.
+    #[structopt(long = "flush-caches")]
+    flush_caches: bool,
+
     /// Set the inner relation size (tuples); required for `-data-set Custom`
     #[structopt(long = "inner-rel-tuples", required_if("data_set", "Custom"))]
     inner_rel_tuples: Option<usize>,
@@ -270,9 +500,32 @@ struct CmdOpt {
     /// Execute on GPU (See CUDA device list)
     device_id: u16,
 
+    /// Limit the CUDA device memory that the allocator hands out (in MiB) [Default: No limit]
+    ///
+    /// Simulates a GPU with less memory, e.g., to test out-of-core execution
+    /// on a large GPU. Device memory allocations beyond the limit fail as if
+    /// the GPU ran out of memory.
+    #[structopt(long)]
+    device_memory_limit: Option<usize>,
+
+    /// Fall back to unpinned memory if pinning NumaLazyPinned memory fails
+    ///
+    /// Pinning fails, e.g., if the locked memory limit (RLIMIT_MEMLOCK) is too
+    /// small. Without the fallback, the benchmark exits with an error.
+    #[structopt(long)]
+    pinning_fallback: bool,
+
     #[structopt(short = "t", long = "threads", default_value = "1")]
     threads: usize,
 
+    /// Number of CPU threads that build the hash table [Default: threads]
+    #[structopt(long = "build-threads")]
+    build_threads: Option<usize>,
+
+    /// Number of CPU threads that probe the hash table [Default: threads]
+    #[structopt(long = "probe-threads")]
+    probe_threads: Option<usize>,
+
     /// Path to CPU affinity map file for CPU workers
     #[structopt(long = "cpu-affinity", parse(from_os_str))]
     cpu_affinity: Option<PathBuf>,
@@ -281,13 +534,33 @@ struct CmdOpt {
     #[structopt(long = "gpu-affinity", parse(from_os_str))]
     gpu_affinity: Option<PathBuf>,
 
-    /// The CUDA grid size [Default: all SMs]
+    /// The CUDA grid size [Default: maximum occupancy on all SMs]
     #[structopt(long = "grid-size", require_delimiter = true)]
     grid_size: Option<u32>,
 
-    /// The CUDA block size [Default: 1024]
+    /// The CUDA block size [Default: maximum occupancy]
     #[structopt(long = "block-size", require_delimiter = true)]
     block_size: Option<u32>,
+
+    /// Warm up until the GPU's SM clock is steady before measuring
+    ///
+    /// The clock is polled with NVML after each warm-up run. Measuring starts
+    /// once the clock stays within `--steady-state-tolerance-mhz` for
+    /// `--steady-state-samples` consecutive runs.
+    #[structopt(long = "steady-state")]
+    steady_state: bool,
+
+    /// Maximum clock difference between the samples of a steady clock (MHz)
+    #[structopt(long = "steady-state-tolerance-mhz", default_value = "15")]
+    steady_state_tolerance_mhz: u32,
+
+    /// Number of consecutive samples for a steady clock
+    #[structopt(long = "steady-state-samples", default_value = "5")]
+    steady_state_samples: usize,
+
+    /// Maximum number of warm-up runs before measuring anyways
+    #[structopt(long = "steady-state-max-warm-ups", default_value = "100")]
+    steady_state_max_warm_ups: u32,
 }
 
 impl CmdOpt {
@@ -330,13 +603,66 @@ fn is_percent(x: String) -> std::result::Result<(), String> {
         })
 }
 
+/// Checks that the processors executing the join can access the hash table and
+/// the relations.
+///
+/// The relations are generated on the CPU, thus they must always be accessible
+/// by the CPU. GPUs can access pageable memory only if the hardware supports it,
+/// e.g., with NVLink 2.0 on POWER9.
+fn check_mem_types(
+    execution_method: ArgExecutionMethod,
+    hash_table_mem_type: &allocator::MemType,
+    relation_mem_type: &allocator::MemType,
+    device: &Device,
+) -> Result<()> {
+    let (is_cpu_hash_table, is_gpu_hash_table, is_gpu_relation) = match execution_method {
+        ArgExecutionMethod::Cpu => (true, false, false),
+        ArgExecutionMethod::Gpu => (false, true, true),
+        ArgExecutionMethod::GpuStream => (false, true, false),
+        ArgExecutionMethod::Het | ArgExecutionMethod::GpuBuildHetProbe => (true, true, false),
+    };
+
+    if !relation_mem_type.is_cpu_accessible() {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Relations cannot be stored in {:?}, because the CPU must be able to write them",
+            relation_mem_type
+        )))?;
+    }
+
+    if is_cpu_hash_table && !hash_table_mem_type.is_cpu_accessible() {
+        Err(ErrorKind::InvalidArgument(format!(
+            "The {:?} execution method requires a CPU-accessible hash table, but got {:?}",
+            execution_method, hash_table_mem_type
+        )))?;
+    }
+
+    if is_gpu_hash_table && !hash_table_mem_type.is_gpu_accessible(device)? {
+        Err(ErrorKind::InvalidArgument(format!(
+            "The GPU cannot access a hash table in {:?}. Pageable memory requires a GPU with pageable memory access.",
+            hash_table_mem_type
+        )))?;
+    }
+
+    if is_gpu_relation && !relation_mem_type.is_gpu_accessible(device)? {
+        Err(ErrorKind::InvalidArgument(format!(
+            "The GPU cannot access relations in {:?}. Pageable memory requires a GPU with pageable memory access.",
+            relation_mem_type
+        )))?;
+    }
+
+    Ok(())
+}
+
 fn args_to_bench<T>(
     cmd: &CmdOpt,
     device: Device,
+    trace: Option<Rc<RefCell<TraceRecorder>>>,
+    perf_counters: Option<Arc<PerfCounterGroup>>,
 ) -> Result<(Box<dyn FnMut() -> Result<HashJoinPoint>>, DataPoint)>
 where
     T: Default
         + AsPrimitive<c_uint>
+        + AsPrimitive<u64>
         + Copy
         + DeviceCopy
         + Sync
@@ -375,19 +701,46 @@ where
     let (hashing_scheme, hash_table_load_factor) = match cmd.hashing_scheme {
         ArgHashingScheme::Perfect => (HashingScheme::Perfect, 1),
         ArgHashingScheme::LinearProbing => (HashingScheme::LinearProbing, 2),
+        ArgHashingScheme::Cuckoo => (HashingScheme::Cuckoo, 2),
     };
 
-    // Device tuning
-    let multiprocessors = device.get_attribute(DeviceAttribute::MultiprocessorCount)? as u32;
-    let warp_size = device.get_attribute(DeviceAttribute::WarpSize)? as u32;
-    let warp_overcommit_factor = 4;
-    let grid_overcommit_factor = 2;
+    if cmd.hashing_scheme == ArgHashingScheme::Cuckoo
+        && cmd.execution_method != ArgExecutionMethod::Cpu
+    {
+        Err(ErrorKind::InvalidArgument(
+            "--hashing-scheme Cuckoo is only supported by the CPU execution method".to_string(),
+        ))?;
+    }
 
-    let block_size = BlockSize::x(cmd.block_size.unwrap_or(warp_size * warp_overcommit_factor));
-    let grid_size = GridSize::x(
-        cmd.grid_size
-            .unwrap_or(multiprocessors * grid_overcommit_factor),
-    );
+    // Device tuning: maximize the occupancy of the build and probe kernels by
+    // default, and apply the manual launch dimensions to both phases
+    let is_gpu_join = cmd.execution_method != ArgExecutionMethod::Cpu;
+    let (default_build_dim, default_probe_dim) = if is_gpu_join {
+        (
+            no_partitioning_join::CudaHashJoin::<T>::max_occupancy_build_dim(
+                hashing_scheme,
+                &device,
+            )?,
+            no_partitioning_join::CudaHashJoin::<T>::max_occupancy_probe_dim(
+                hashing_scheme,
+                &device,
+            )?,
+        )
+    } else {
+        (
+            (GridSize::x(0), BlockSize::x(0)),
+            (GridSize::x(0), BlockSize::x(0)),
+        )
+    };
+
+    let launch_dim = |(default_grid_size, default_block_size): (GridSize, BlockSize)| {
+        (
+            cmd.grid_size.map_or(default_grid_size, GridSize::x),
+            cmd.block_size.map_or(default_block_size, BlockSize::x),
+        )
+    };
+    let build_dim = launch_dim(default_build_dim);
+    let probe_dim = launch_dim(default_probe_dim);
 
     assert_eq!(
         cmd.hash_table_location.len(),
@@ -407,31 +760,58 @@ where
         );
     }
 
+    let node_ratios: Box<[NodeRatio]> = cmd
+        .hash_table_location
+        .iter()
+        .zip(cmd.hash_table_proportions.iter())
+        .map(|(node, pct)| NodeRatio {
+            node: *node,
+            ratio: Ratio::new(*pct, 100),
+        })
+        .collect();
+
+    let relation_mem_type = |node| -> Result<allocator::MemType> {
+        ArgMemTypeHelper {
+            mem_type: cmd.mem_type,
+            node_ratios: Box::new([NodeRatio {
+                node,
+                ratio: Ratio::from_integer(1),
+            }]),
+            page_type: cmd.page_type,
+        }
+        .try_into()
+    };
+    let inner_mem_type = relation_mem_type(cmd.inner_rel_location)?;
+    let outer_mem_type = relation_mem_type(cmd.outer_rel_location)?;
+    let hash_table_mem_type: allocator::MemType = ArgMemTypeHelper {
+        mem_type: cmd.hash_table_mem_type,
+        node_ratios: node_ratios.clone(),
+        page_type: cmd.page_type,
+    }
+    .try_into()?;
+
+    check_mem_types(
+        cmd.execution_method,
+        &hash_table_mem_type,
+        &inner_mem_type,
+        &device,
+    )?;
+
+    let combined_mem_type: Option<allocator::DerefMemType> = match cmd.relation_layout {
+        ArgRelationLayout::Separate => None,
+        ArgRelationLayout::Combined => Some(inner_mem_type.clone().try_into()?),
+    };
+
     let mut data_builder = JoinDataBuilder::default();
     data_builder
         .mlock(true)
-        .inner_mem_type(
-            ArgMemTypeHelper {
-                mem_type: cmd.mem_type,
-                node_ratios: Box::new([NodeRatio {
-                    node: cmd.inner_rel_location,
-                    ratio: Ratio::from_integer(1),
-                }]),
-                page_type: cmd.page_type,
-            }
-            .into(),
-        )
-        .outer_mem_type(
-            ArgMemTypeHelper {
-                mem_type: cmd.mem_type,
-                node_ratios: Box::new([NodeRatio {
-                    node: cmd.outer_rel_location,
-                    ratio: Ratio::from_integer(1),
-                }]),
-                page_type: cmd.page_type,
-            }
-            .into(),
-        );
+        .shuffle(if cmd.shuffle {
+            Some(cmd.shuffle_seed)
+        } else {
+            None
+        })
+        .inner_mem_type(inner_mem_type.try_into()?)
+        .outer_mem_type(outer_mem_type.try_into()?);
 
     // Select the operator to run, depending on the device type
     let exec_method = cmd.execution_method.clone();
@@ -440,64 +820,169 @@ where
     let spill_hash_table = cmd.spill_hash_table;
     let max_hash_table_cache_bytes = cmd.max_hash_table_cache_size.map(|s| s * 1024 * 1024); // convert MiB to bytes
     let threads = cmd.threads.clone();
+    let build_threads = cmd.build_threads.unwrap_or(cmd.threads);
+    let probe_threads = cmd.probe_threads.unwrap_or(cmd.threads);
     let device_id = cmd.device_id;
     let page_type = cmd.page_type;
+    let payload_bytes = cmd.payload_bytes;
+
+    if payload_bytes.is_some() && cmd.execution_method != ArgExecutionMethod::Cpu {
+        Err(ErrorKind::InvalidArgument(
+            "--payload-bytes is only supported by the CPU execution method".to_string(),
+        ))?;
+    }
+
+    // The wide payload hash table is a single-threaded build into system memory
+    if payload_bytes.is_some() && cmd.hash_table_mem_type != ArgMemType::System {
+        Err(ErrorKind::InvalidArgument(
+            "--payload-bytes requires --hash-table-mem-type System".to_string(),
+        ))?;
+    }
+    if payload_bytes.is_some() && cmd.build_threads.map_or(false, |t| t != 1) {
+        Err(ErrorKind::InvalidArgument(
+            "--payload-bytes builds the hash table with a single thread, set --build-threads 1"
+                .to_string(),
+        ))?;
+    }
+
+    let key_only = cmd.aggregate == ArgAggregate::Count;
+    if key_only && payload_bytes.is_some() {
+        Err(ErrorKind::InvalidArgument(
+            "--aggregate Count cannot be combined with --payload-bytes".to_string(),
+        ))?;
+    }
+    if key_only
+        && cmd.execution_method != ArgExecutionMethod::Cpu
+        && cmd.execution_method != ArgExecutionMethod::Gpu
+    {
+        Err(ErrorKind::InvalidArgument(
+            "--aggregate Count is only supported by the CPU and GPU execution methods".to_string(),
+        ))?;
+    }
+    if cmd.keys_only_datagen && !key_only {
+        Err(ErrorKind::InvalidArgument(
+            "--keys-only-datagen requires --aggregate Count".to_string(),
+        ))?;
+    }
+    if cmd.keys_only_datagen && (cmd.inner_rel_file.is_some() || cmd.outer_rel_file.is_some()) {
+        Err(ErrorKind::InvalidArgument(
+            "--keys-only-datagen cannot be combined with relation files".to_string(),
+        ))?;
+    }
+
+    if cmd.prefetch_to.is_some() && cmd.execution_method != ArgExecutionMethod::Gpu {
+        Err(ErrorKind::InvalidArgument(
+            "--prefetch-to is only supported by the GPU execution method".to_string(),
+        ))?;
+    }
+
+    if cmd.pre_touch && cmd.execution_method != ArgExecutionMethod::Cpu {
+        Err(ErrorKind::InvalidArgument(
+            "--pre-touch is only supported by the CPU execution method".to_string(),
+        ))?;
+    }
+
+    if cmd.relation_layout == ArgRelationLayout::Combined
+        && cmd.execution_method != ArgExecutionMethod::Cpu
+        && cmd.execution_method != ArgExecutionMethod::Gpu
+    {
+        Err(ErrorKind::InvalidArgument(
+            "--relation-layout Combined is only supported by the CPU and GPU execution methods"
+                .to_string(),
+        ))?;
+    }
+    if cmd.relation_layout == ArgRelationLayout::Combined
+        && (payload_bytes.is_some() || cmd.inner_rel_file.is_some() || cmd.outer_rel_file.is_some())
+    {
+        Err(ErrorKind::InvalidArgument(
+            "--relation-layout Combined cannot be combined with --payload-bytes or relation files"
+                .to_string(),
+        ))?;
+    }
 
     let morsel_spec = MorselSpec {
         cpu_morsel_bytes: cmd.cpu_morsel_bytes,
         gpu_morsel_bytes: cmd.gpu_morsel_bytes,
     };
 
-    let node_ratios: Box<[NodeRatio]> = cmd
-        .hash_table_location
-        .iter()
-        .zip(cmd.hash_table_proportions.iter())
-        .map(|(node, pct)| NodeRatio {
-            node: *node,
-            ratio: Ratio::new(*pct, 100),
-        })
-        .collect();
-
     // Load file or generate data set
     let (mut join_data, malloc_time, data_gen_time) =
         if let (Some(inner_rel_path), Some(outer_rel_path)) = (
             cmd.inner_rel_file.as_ref().and_then(|p| p.to_str()),
             cmd.outer_rel_file.as_ref().and_then(|p| p.to_str()),
         ) {
-            data_builder.build_with_files::<T>(inner_rel_path, outer_rel_path)?
+            let (join_data, malloc_time, data_gen_time) =
+                data_builder.build_with_files::<T>(inner_rel_path, outer_rel_path)?;
+            (
+                JoinRelations::Separate(join_data),
+                malloc_time,
+                data_gen_time,
+            )
         } else {
             let data_distribution = match cmd.data_distribution {
                 ArgDataDistribution::Uniform => DataDistribution::Uniform,
                 ArgDataDistribution::Zipf => DataDistribution::Zipf(cmd.zipf_exponent.unwrap()),
             };
 
+            let rng = cmd
+                .seed
+                .map_or_else(SeededRng::from_entropy, SeededRng::new);
             let (inner_relation_len, outer_relation_len, data_gen) = data_gen_fn::<_>(
                 cmd.data_set,
                 cmd.inner_rel_tuples,
                 cmd.outer_rel_tuples,
                 data_distribution,
                 Some(cmd.selectivity),
+                rng,
             );
             data_builder
                 .inner_len(inner_relation_len)
                 .outer_len(outer_relation_len)
-                .build_with_data_gen(data_gen)?
+                .keys_only(cmd.keys_only_datagen);
+
+            // The combined layout generates the relations directly into a
+            // single buffer
+            if let Some(mem_type) = combined_mem_type {
+                let (join_data, malloc_time, data_gen_time) =
+                    data_builder.build_combined_with_data_gen(mem_type, data_gen)?;
+                (
+                    JoinRelations::Combined(join_data),
+                    malloc_time,
+                    data_gen_time,
+                )
+            } else {
+                let (join_data, malloc_time, data_gen_time) =
+                    data_builder.build_with_data_gen(data_gen)?;
+                (
+                    JoinRelations::Separate(join_data),
+                    malloc_time,
+                    data_gen_time,
+                )
+            }
         };
 
     let mut hjb_builder = HashJoinBenchBuilder::default();
     let hjb = hjb_builder
         .hashing_scheme(hashing_scheme)
         .is_selective(cmd.selectivity != 100)
+        .key_only(key_only)
+        .prefetch_destination(cmd.prefetch_to)
+        .pre_touch(cmd.pre_touch)
         .hash_table_load_factor(hash_table_load_factor)
-        .build(join_data.build_relation_key.len())?;
+        .build(join_data.build_len())?;
 
     // Construct data point template for CSV
     let dp = DataPoint::new()?
         .fill_from_cmd_options(cmd)?
         .fill_from_join_data(&join_data)
         .fill_from_hash_join_bench(&hjb)
-        .set_init_time(malloc_time, data_gen_time)
-        .set_gpu_threads(&grid_size, &block_size);
+        .set_init_time(malloc_time, data_gen_time);
+    // Record the probe dimensions, as the probe phase dominates the join time
+    let dp = if is_gpu_join {
+        dp.set_gpu_threads(&probe_dim.0, &probe_dim.1)
+    } else {
+        dp
+    };
 
     let worker_cpu_affinity = {
         let cpu_workers = if let Some(ref cpu_affinity_file) = cmd.cpu_affinity {
@@ -518,22 +1003,64 @@ where
 
     // Create closure that wraps a hash join benchmark function
     let hjc: Box<dyn FnMut() -> Result<HashJoinPoint>> = match exec_method {
-        ArgExecutionMethod::Cpu => Box::new(move || {
-            let ht_alloc = allocator::Allocator::deref_mem_alloc_fn::<HtEntry<T, T>>(
-                ArgMemTypeHelper {
-                    mem_type,
-                    node_ratios: node_ratios.clone(),
-                    page_type,
+        ArgExecutionMethod::Cpu if payload_bytes.is_some() => {
+            let thread_pool = hash_join_bench::cpu_thread_pool(
+                probe_threads,
+                &worker_cpu_affinity.cpu_workers,
+                perf_counters,
+            )?;
+            match payload_bytes {
+                Some(4) => wide_payload_bench::<T, 4>(hjb, join_data, probe_threads, thread_pool)?,
+                Some(8) => wide_payload_bench::<T, 8>(hjb, join_data, probe_threads, thread_pool)?,
+                Some(16) => {
+                    wide_payload_bench::<T, 16>(hjb, join_data, probe_threads, thread_pool)?
                 }
-                .into(),
-            );
-            hjb.cpu_hash_join(
-                &mut join_data,
-                threads,
+                Some(32) => {
+                    wide_payload_bench::<T, 32>(hjb, join_data, probe_threads, thread_pool)?
+                }
+                Some(60) => {
+                    wide_payload_bench::<T, 60>(hjb, join_data, probe_threads, thread_pool)?
+                }
+                Some(64) => {
+                    wide_payload_bench::<T, 64>(hjb, join_data, probe_threads, thread_pool)?
+                }
+                Some(128) => {
+                    wide_payload_bench::<T, 128>(hjb, join_data, probe_threads, thread_pool)?
+                }
+                Some(256) => {
+                    wide_payload_bench::<T, 256>(hjb, join_data, probe_threads, thread_pool)?
+                }
+                _ => Err(ErrorKind::InvalidArgument(format!(
+                    "Unsupported payload size: {:?} bytes",
+                    payload_bytes
+                )))?,
+            }
+        }
+        ArgExecutionMethod::Cpu => {
+            // Pin the threads once, outside of the measured repetitions
+            let thread_pool = hash_join_bench::cpu_thread_pool(
+                std::cmp::max(build_threads, probe_threads),
                 &worker_cpu_affinity.cpu_workers,
-                ht_alloc,
-            )
-        }),
+                perf_counters,
+            )?;
+            Box::new(move || {
+                let ht_alloc = allocator::Allocator::deref_mem_alloc_fn::<HtEntry<T, T>>(
+                    ArgMemTypeHelper {
+                        mem_type,
+                        node_ratios: node_ratios.clone(),
+                        page_type,
+                    }
+                    .try_into()?,
+                );
+                hjb.cpu_hash_join(
+                    &mut join_data,
+                    build_threads,
+                    probe_threads,
+                    &thread_pool,
+                    ht_alloc,
+                )
+            })
+        }
         ArgExecutionMethod::Gpu => Box::new(move || {
             let (cache_and_spill, cache_node) = if spill_hash_table == Some(true) {
                 let (cache_node, spill_node) = if let [cache_node, spill_node] = *node_ratios {
@@ -553,11 +1080,11 @@ where
                 (cache_spill_type, cache_node)
             } else {
                 let cache_spill_type: allocator::CacheSpillType =
-                    allocator::MemType::from(ArgMemTypeHelper {
+                    allocator::MemType::try_from(ArgMemTypeHelper {
                         mem_type,
                         node_ratios: node_ratios.clone(),
                         page_type,
-                    })
+                    })?
                     .into();
                 let cache_node: u16 = 0;
 
@@ -573,8 +1100,9 @@ where
                 cache_node,
                 max_hash_table_cache_bytes,
                 cache_bytes_future,
-                (grid_size.clone(), block_size.clone()),
-                (grid_size.clone(), block_size.clone()),
+                build_dim.clone(),
+                probe_dim.clone(),
+                trace.as_deref(),
             )
         }),
         ArgExecutionMethod::GpuStream if transfer_strategy == ArgTransferStrategy::Unified => {
@@ -585,13 +1113,13 @@ where
                         node_ratios: node_ratios.clone(),
                         page_type,
                     }
-                    .into(),
+                    .try_into()?,
                 );
                 hjb.cuda_streaming_unified_hash_join(
-                    &mut join_data,
+                    join_data.separate_mut()?,
                     ht_alloc,
-                    (grid_size.clone(), block_size.clone()),
-                    (grid_size.clone(), block_size.clone()),
+                    build_dim.clone(),
+                    probe_dim.clone(),
                     morsel_spec.gpu_morsel_bytes,
                 )
             })
@@ -603,13 +1131,13 @@ where
                     node_ratios: node_ratios.clone(),
                     page_type,
                 }
-                .into(),
+                .try_into()?,
             );
             hjb.cuda_streaming_hash_join(
-                &mut join_data,
+                join_data.separate_mut()?,
                 ht_alloc,
-                (grid_size.clone(), block_size.clone()),
-                (grid_size.clone(), block_size.clone()),
+                build_dim.clone(),
+                probe_dim.clone(),
                 transfer_strategy.into(),
                 morsel_spec.gpu_morsel_bytes,
                 threads,
@@ -623,16 +1151,16 @@ where
                     node_ratios: node_ratios.clone(),
                     page_type,
                 }
-                .into(),
+                .try_into()?,
             );
             hjb.hetrogeneous_hash_join(
-                &mut join_data,
+                join_data.separate_mut()?,
                 ht_alloc,
                 threads,
                 &worker_cpu_affinity,
                 vec![device_id, device_id],
-                (grid_size.clone(), block_size.clone()),
-                (grid_size.clone(), block_size.clone()),
+                build_dim.clone(),
+                probe_dim.clone(),
                 &morsel_spec,
             )
         }),
@@ -653,7 +1181,7 @@ where
                     }]),
                     page_type,
                 }
-                .into(),
+                .try_into()?,
             );
 
             // Allocate GPU memory as specified on the commandline
@@ -663,18 +1191,18 @@ where
                     node_ratios: node_ratios.clone(),
                     page_type,
                 }
-                .into(),
+                .try_into()?,
             );
 
             hjb.gpu_build_heterogeneous_probe(
-                &mut join_data,
+                join_data.separate_mut()?,
                 cpu_ht_alloc,
                 gpu_ht_alloc,
                 threads,
                 &worker_cpu_affinity,
                 vec![device_id, device_id],
-                (grid_size.clone(), block_size.clone()),
-                (grid_size.clone(), block_size.clone()),
+                build_dim.clone(),
+                probe_dim.clone(),
                 &morsel_spec,
             )
         }),
@@ -683,99 +1211,139 @@ where
     Ok((hjc, dp))
 }
 
+/// Wraps the wide-payload CPU join in a benchmark closure.
+///
+/// The `N`-byte payloads are derived from the generated build payloads, by
+/// repeating the payload's bytes. Thus, each payload is unique if the build
+/// payloads are unique.
+fn wide_payload_bench<T, const N: usize>(
+    hjb: HashJoinBench<T>,
+    mut join_data: JoinRelations<T>,
+    probe_threads: usize,
+    thread_pool: rayon::ThreadPool,
+) -> Result<Box<dyn FnMut() -> Result<HashJoinPoint>>>
+where
+    T: Default
+        + AsPrimitive<c_uint>
+        + AsPrimitive<u64>
+        + DeviceCopy
+        + Sync
+        + Send
+        + KeyAttribute
+        + no_partitioning_join::CudaHashJoinable
+        + no_partitioning_join::CpuHashJoinable,
+{
+    let build_payloads: Vec<[u8; N]> = join_data
+        .separate_mut()?
+        .build_relation
+        .value()
+        .try_as_slice()?
+        .iter()
+        .map(|&payload| {
+            let bytes = AsPrimitive::<u64>::as_(payload).to_le_bytes();
+            let mut wide = [0_u8; N];
+            wide.iter_mut()
+                .zip(bytes.iter().cycle())
+                .for_each(|(w, &b)| *w = b);
+            wide
+        })
+        .collect();
+
+    Ok(Box::new(move || {
+        hjb.cpu_wide_payload_hash_join::<N>(
+            join_data.separate_mut()?,
+            &build_payloads,
+            probe_threads,
+            &thread_pool,
+        )
+    }))
+}
+
 fn data_gen_fn<T>(
     description: ArgDataSet,
     inner_rel_tuples: Option<usize>,
     outer_rel_tuples: Option<usize>,
     data_distribution: DataDistribution,
     selectivity: Option<u32>,
+    rng: SeededRng,
 ) -> (usize, usize, JoinDataGenFn<T>)
 where
     T: Copy + Send + KeyAttribute + num_traits::FromPrimitive,
 {
+    // Each generator resets the RNG, thus every call generates the same data
+    // set for a fixed seed
+    let mut rng = rng;
+
     match description {
         ArgDataSet::Blanas => (
             datagen::popular::Blanas::primary_key_len(),
             datagen::popular::Blanas::foreign_key_len(),
             Box::new(move |pk_rel, _, fk_rel, _| {
-                datagen::popular::Blanas::gen(pk_rel, fk_rel, selectivity).map_err(|e| e.into())
+                rng.reset();
+                datagen::popular::Blanas::gen_with_rng(pk_rel, fk_rel, selectivity, &mut rng)
+                    .map_err(|e| e.into())
             }),
         ),
         ArgDataSet::Kim => (
             datagen::popular::Kim::primary_key_len(),
             datagen::popular::Kim::foreign_key_len(),
             Box::new(move |pk_rel, _, fk_rel, _| {
-                datagen::popular::Kim::gen(pk_rel, fk_rel, selectivity).map_err(|e| e.into())
+                rng.reset();
+                datagen::popular::Kim::gen_with_rng(pk_rel, fk_rel, selectivity, &mut rng)
+                    .map_err(|e| e.into())
             }),
         ),
-        ArgDataSet::Blanas4MB => {
-            let gen = move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                datagen::relation::UniformRelation::gen_primary_key_par(pk_rel, selectivity)?;
-                datagen::relation::UniformRelation::gen_attr_par(fk_rel, 0..pk_rel.len())?;
-                Ok(())
-            };
-
-            (512 * 2_usize.pow(10), 256 * 2_usize.pow(20), Box::new(gen))
-        }
+        ArgDataSet::Blanas4MB => (
+            512 * 2_usize.pow(10),
+            256 * 2_usize.pow(20),
+            uniform_data_gen_fn(selectivity, rng),
+        ),
         ArgDataSet::Test => {
             let gen = move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                datagen::relation::UniformRelation::gen_primary_key(pk_rel, selectivity)?;
-                datagen::relation::UniformRelation::gen_foreign_key_from_primary_key(
-                    fk_rel, pk_rel,
+                rng.reset();
+                datagen::relation::UniformRelation::gen_primary_key_with_rng(
+                    pk_rel,
+                    selectivity,
+                    &mut rng,
+                )?;
+                datagen::relation::UniformRelation::gen_foreign_key_from_primary_key_with_rng(
+                    fk_rel, pk_rel, &mut rng,
                 );
                 Ok(())
             };
 
             (1000, 1000, Box::new(gen))
         }
-        ArgDataSet::Lutz2Gv32G => {
-            let gen = move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                datagen::relation::UniformRelation::gen_primary_key_par(pk_rel, selectivity)?;
-                datagen::relation::UniformRelation::gen_attr_par(fk_rel, 0..pk_rel.len())?;
-                Ok(())
-            };
-
-            (
-                2 * 2_usize.pow(30) / (2 * size_of::<T>()),
-                32 * 2_usize.pow(30) / (2 * size_of::<T>()),
-                Box::new(gen),
-            )
-        }
-        ArgDataSet::Lutz32Gv32G => {
-            let gen = move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                datagen::relation::UniformRelation::gen_primary_key_par(pk_rel, selectivity)?;
-                datagen::relation::UniformRelation::gen_attr_par(fk_rel, 0..pk_rel.len())?;
-                Ok(())
-            };
-
-            (
-                32 * 2_usize.pow(30) / (2 * size_of::<T>()),
-                32 * 2_usize.pow(30) / (2 * size_of::<T>()),
-                Box::new(gen),
-            )
-        }
+        ArgDataSet::Lutz2Gv32G => (
+            2 * 2_usize.pow(30) / (2 * size_of::<T>()),
+            32 * 2_usize.pow(30) / (2 * size_of::<T>()),
+            uniform_data_gen_fn(selectivity, rng),
+        ),
+        ArgDataSet::Lutz32Gv32G => (
+            32 * 2_usize.pow(30) / (2 * size_of::<T>()),
+            32 * 2_usize.pow(30) / (2 * size_of::<T>()),
+            uniform_data_gen_fn(selectivity, rng),
+        ),
         ArgDataSet::Custom => {
-            let uniform_gen = Box::new(
-                move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                    datagen::relation::UniformRelation::gen_primary_key_par(pk_rel, selectivity)?;
-                    datagen::relation::UniformRelation::gen_attr_par(fk_rel, 0..pk_rel.len())?;
-                    Ok(())
-                },
-            );
-
             let gen: JoinDataGenFn<T> = match data_distribution {
-                DataDistribution::Uniform => uniform_gen,
-                DataDistribution::Zipf(exp) if !(exp > 0.0) => uniform_gen,
-                DataDistribution::Zipf(exp) => Box::new(
+                DataDistribution::Zipf(exp) if exp > 0.0 => Box::new(
                     move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                        datagen::relation::UniformRelation::gen_primary_key_par(
+                        rng.reset();
+                        datagen::relation::UniformRelation::gen_primary_key_par_with_rng(
                             pk_rel,
                             selectivity,
+                            &mut rng,
+                        )?;
+                        datagen::relation::ZipfRelation::gen_attr_par_with_rng(
+                            fk_rel,
+                            pk_rel.len(),
+                            exp,
+                            &mut rng,
                         )?;
-                        datagen::relation::ZipfRelation::gen_attr_par(fk_rel, pk_rel.len(), exp)?;
                         Ok(())
                     },
                 ),
+                _ => uniform_data_gen_fn(selectivity, rng),
             };
 
             (
@@ -790,3 +1358,27 @@ where
         }
     }
 }
+
+/// Generates a primary key inner relation and a uniform foreign key outer
+/// relation in parallel.
+fn uniform_data_gen_fn<T>(selectivity: Option<u32>, mut rng: SeededRng) -> JoinDataGenFn<T>
+where
+    T: Copy + Send + KeyAttribute + num_traits::FromPrimitive,
+{
+    Box::new(
+        move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
+            rng.reset();
+            datagen::relation::UniformRelation::gen_primary_key_par_with_rng(
+                pk_rel,
+                selectivity,
+                &mut rng,
+            )?;
+            datagen::relation::UniformRelation::gen_attr_par_with_rng(
+                fk_rel,
+                0..pk_rel.len(),
+                &mut rng,
+            )?;
+            Ok(())
+        },
+    )
+}
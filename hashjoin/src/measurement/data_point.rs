@@ -12,48 +12,65 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::hash_join_bench::HashJoinBench;
+use super::hash_join_bench::{HashJoinBench, JoinRelations};
 use crate::types::*;
 use crate::CmdOpt;
-use data_store::join_data::JoinData;
 use numa_gpu::error::Result;
-use numa_gpu::runtime::hw_info::cpu_codename;
+use numa_gpu::runtime::hw_info::{cpu_codename, cuda_driver_version};
+use numa_gpu::runtime::nvml::nvidia_driver_version;
 use numa_gpu::runtime::nvtx::RangeId;
+use numa_gpu::runtime::perf_counters::{PerfCounts, PerfEvent};
 use rustacuda::device::Device;
 use rustacuda::function::{BlockSize, GridSize};
 use rustacuda::memory::DeviceCopy;
+use serde::de::{self, Deserializer};
 use serde::Serializer;
-use serde_derive::Serialize;
-use std::mem::size_of;
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
 use std::string::ToString;
 use std::time::Duration;
 
-#[derive(Clone, Debug, Default, Serialize)]
+/// A measurement result, i.e., one row of the CSV output.
+///
+/// The field names are the CSV column names. Scripts and tools read the
+/// columns by name, thus a renamed field must keep its old column name with
+/// `#[serde(rename = "...")]`. Deserializing the CSV output yields the same
+/// data point.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct DataPoint {
     pub data_set: Option<String>,
     pub hostname: String,
+    pub crate_version: Option<String>,
+    pub cuda_driver_version: Option<String>,
+    pub nvidia_driver_version: Option<String>,
     pub execution_method: Option<ArgExecutionMethod>,
-    #[serde(serialize_with = "serialize_vec")]
+    #[serde(serialize_with = "serialize_vec", deserialize_with = "deserialize_vec")]
     pub device_codename: Option<Vec<String>>,
     pub transfer_strategy: Option<ArgTransferStrategy>,
     pub cpu_morsel_bytes: Option<usize>,
     pub gpu_morsel_bytes: Option<usize>,
     pub threads: Option<usize>,
+    pub build_threads: Option<usize>,
+    pub probe_threads: Option<usize>,
     pub grid_size: Option<u32>,
     pub block_size: Option<u32>,
     pub hashing_scheme: Option<ArgHashingScheme>,
     pub hash_table_memory_type: Option<ArgMemType>,
-    #[serde(serialize_with = "serialize_vec")]
+    #[serde(serialize_with = "serialize_vec", deserialize_with = "deserialize_vec")]
     pub hash_table_memory_location: Option<Vec<u16>>,
-    #[serde(serialize_with = "serialize_vec")]
+    #[serde(serialize_with = "serialize_vec", deserialize_with = "deserialize_vec")]
     pub hash_table_proportions: Option<Vec<usize>>,
     pub hash_table_tuples: Option<usize>,
     pub cached_hash_table_tuples: Option<usize>,
     pub tuple_bytes: Option<ArgTupleBytes>,
+    pub payload_bytes: Option<usize>,
+    pub aggregate: Option<ArgAggregate>,
+    pub keys_only_datagen: Option<bool>,
     pub relation_memory_type: Option<ArgMemType>,
     pub page_type: Option<ArgPageType>,
     pub inner_relation_memory_location: Option<u16>,
     pub outer_relation_memory_location: Option<u16>,
+    pub relation_layout: Option<ArgRelationLayout>,
     pub build_tuples: Option<usize>,
     pub build_bytes: Option<usize>,
     pub probe_tuples: Option<usize>,
@@ -61,6 +78,8 @@ pub struct DataPoint {
     pub data_distribution: Option<ArgDataDistribution>,
     pub zipf_exponent: Option<f64>,
     pub join_selectivity: Option<f64>,
+    pub shuffle_seed: Option<u64>,
+    pub iteration: Option<u32>,
     pub warm_up: Option<bool>,
     pub nvtx_run_id: Option<RangeId>,
     pub build_ns: Option<f64>,
@@ -76,14 +95,59 @@ pub struct DataPoint {
     pub hash_table_malloc_ns: Option<f64>,
     pub relation_malloc_ns: Option<f64>,
     pub relation_gen_ns: Option<f64>,
+    pub result_sum: Option<u64>,
+    pub probe_gib_per_sec: Option<f64>,
+    pub probe_tuples_per_sec: Option<f64>,
+    pub throughput_ci_relative_width: Option<f64>,
+    pub steady_sm_clock_mhz: Option<u32>,
+    pub flush_caches: Option<bool>,
+    pub cycles: Option<u64>,
+    pub instructions: Option<u64>,
+    pub l1d_misses: Option<u64>,
+    pub l2_misses: Option<u64>,
+    pub llc_misses: Option<u64>,
+    pub dtlb_misses: Option<u64>,
+    pub itlb_misses: Option<u64>,
 }
 
 impl DataPoint {
+    /// The version of the CSV schema, i.e., of the columns and their order.
+    ///
+    /// Bump the version whenever a field is added, removed, renamed, or
+    /// reordered. Thus, downstream tools can detect a changed schema instead
+    /// of misreading the columns.
+    pub const SCHEMA_VERSION: u32 = 3;
+
+    /// The prefix of the schema header line.
+    ///
+    /// The header is a comment, which CSV readers skip if they are configured
+    /// with `#` as comment character.
+    const SCHEMA_HEADER_PREFIX: &'static str = "# schema_version=";
+
+    /// Returns the schema header line, e.g., `# schema_version=1`.
+    pub fn schema_header() -> String {
+        format!("{}{}", Self::SCHEMA_HEADER_PREFIX, Self::SCHEMA_VERSION)
+    }
+
+    /// Parses the schema version from a schema header line.
+    ///
+    /// Returns `None` if the line isn't a schema header.
+    pub fn parse_schema_header(line: &str) -> Option<u32> {
+        line.trim_end()
+            .strip_prefix(Self::SCHEMA_HEADER_PREFIX)?
+            .parse()
+            .ok()
+    }
+
     pub fn new() -> Result<DataPoint> {
         let hostname = hostname::get_hostname().ok_or_else(|| "Couldn't get hostname")?;
 
         let dp = DataPoint {
             hostname,
+            crate_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            cuda_driver_version: Some(cuda_driver_version()?),
+            // NVML is not available on all platforms, thus leave the field empty
+            nvidia_driver_version: nvidia_driver_version().ok(),
             ..DataPoint::default()
         };
 
@@ -135,15 +199,29 @@ impl DataPoint {
             } else {
                 None
             },
+            build_threads: if cmd.execution_method == ArgExecutionMethod::Cpu {
+                Some(cmd.build_threads.unwrap_or(cmd.threads))
+            } else {
+                None
+            },
+            probe_threads: if cmd.execution_method == ArgExecutionMethod::Cpu {
+                Some(cmd.probe_threads.unwrap_or(cmd.threads))
+            } else {
+                None
+            },
             hashing_scheme: Some(cmd.hashing_scheme),
             hash_table_memory_type: Some(cmd.hash_table_mem_type),
             hash_table_memory_location: Some(cmd.hash_table_location.clone()),
             hash_table_proportions: Some(cmd.hash_table_proportions.clone()),
             tuple_bytes: Some(cmd.tuple_bytes),
+            payload_bytes: cmd.payload_bytes,
+            aggregate: Some(cmd.aggregate),
+            keys_only_datagen: Some(cmd.keys_only_datagen),
             relation_memory_type: Some(cmd.mem_type),
             page_type: Some(cmd.page_type),
             inner_relation_memory_location: Some(cmd.inner_rel_location),
             outer_relation_memory_location: Some(cmd.outer_rel_location),
+            relation_layout: Some(cmd.relation_layout),
             data_distribution: Some(cmd.data_distribution),
             zipf_exponent: if cmd.data_distribution == ArgDataDistribution::Zipf {
                 cmd.zipf_exponent
@@ -151,6 +229,11 @@ impl DataPoint {
                 None
             },
             join_selectivity: Some(cmd.selectivity as f64 / 100.0),
+            shuffle_seed: if cmd.shuffle {
+                Some(cmd.shuffle_seed)
+            } else {
+                None
+            },
             ..self.clone()
         };
 
@@ -164,18 +247,12 @@ impl DataPoint {
         }
     }
 
-    pub fn fill_from_join_data<T: DeviceCopy>(&self, join_data: &JoinData<T>) -> DataPoint {
+    pub fn fill_from_join_data<T: DeviceCopy>(&self, join_data: &JoinRelations<T>) -> DataPoint {
         DataPoint {
-            build_tuples: Some(join_data.build_relation_key.len()),
-            build_bytes: Some(
-                (join_data.build_relation_key.len() + join_data.build_relation_payload.len())
-                    * size_of::<T>(),
-            ),
-            probe_tuples: Some(join_data.probe_relation_key.len()),
-            probe_bytes: Some(
-                (join_data.probe_relation_key.len() + join_data.probe_relation_payload.len())
-                    * size_of::<T>(),
-            ),
+            build_tuples: Some(join_data.build_len()),
+            build_bytes: Some(join_data.build_bytes()),
+            probe_tuples: Some(join_data.probe_len()),
+            probe_bytes: Some(join_data.probe_bytes()),
             ..self.clone()
         }
     }
@@ -195,6 +272,55 @@ impl DataPoint {
             ..self.clone()
         }
     }
+
+    /// Fills in the hardware event counts of a run.
+    ///
+    /// Events that weren't counted remain empty.
+    pub fn with_perf_counts(self, counts: Option<&PerfCounts>) -> DataPoint {
+        let count = |event| counts.and_then(|c| c.get(event));
+
+        DataPoint {
+            cycles: count(PerfEvent::Cycles),
+            instructions: count(PerfEvent::Instructions),
+            l1d_misses: count(PerfEvent::L1dMisses),
+            l2_misses: count(PerfEvent::L2Misses),
+            llc_misses: count(PerfEvent::LlcMisses),
+            dtlb_misses: count(PerfEvent::DtlbMisses),
+            itlb_misses: count(PerfEvent::ItlbMisses),
+            ..self
+        }
+    }
+
+    /// Fills in the probe throughput computed from the probe size and time.
+    pub fn fill_probe_throughput(&self) -> DataPoint {
+        DataPoint {
+            probe_gib_per_sec: self.compute_probe_gib_per_sec(),
+            probe_tuples_per_sec: self.compute_probe_tuples_per_sec(),
+            ..self.clone()
+        }
+    }
+
+    /// Computes the probe throughput in GiB/s.
+    ///
+    /// Returns `None` if the probe bytes or the probe time are unknown.
+    pub fn compute_probe_gib_per_sec(&self) -> Option<f64> {
+        let bytes = self.probe_bytes? as f64;
+        let secs = self.probe_ns? * 10_f64.powf(-9.0);
+
+        Some(bytes / 2_f64.powf(30.0) / secs)
+    }
+
+    /// Computes the probe throughput in tuples/s.
+    ///
+    /// For fixed-width tuples, tuples/s is proportional to GiB/s, but doesn't
+    /// depend on the tuple size. Returns `None` if the probe tuples or the
+    /// probe time are unknown.
+    pub fn compute_probe_tuples_per_sec(&self) -> Option<f64> {
+        let tuples = self.probe_tuples? as f64;
+        let secs = self.probe_ns? * 10_f64.powf(-9.0);
+
+        Some(tuples / secs)
+    }
 }
 
 /// Serialize `Option<Vec<T>>` by converting it into a `String`.
@@ -223,3 +349,192 @@ where
         ser.serialize_none()
     }
 }
+
+/// Deserialize `Option<Vec<T>>` from a `String`.
+///
+/// Reverses `serialize_vec`. Note that elements must not contain commas.
+fn deserialize_vec<'de, D, T>(de: D) -> std::result::Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let record: Option<String> = serde::Deserialize::deserialize(de)?;
+    record
+        .map(|record| {
+            record
+                .split(',')
+                .map(|e| e.parse().map_err(de::Error::custom))
+                .collect()
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DataPoint;
+    use crate::types::*;
+    use std::error::Error;
+
+    #[test]
+    fn probe_tuples_per_sec_from_known_point() {
+        let dp = DataPoint {
+            probe_tuples: Some(128_000_000),
+            probe_bytes: Some(128_000_000 * 16),
+            probe_ns: Some(250_000_000.0),
+            ..DataPoint::default()
+        }
+        .fill_probe_throughput();
+
+        let expected = 128_000_000_f64 / (250_000_000.0 * 1e-9);
+        let tuples_per_sec = dp.probe_tuples_per_sec.unwrap();
+        assert!((tuples_per_sec - expected).abs() <= expected * 1e-12);
+
+        let expected_gib = (128_000_000 * 16) as f64 / 2_f64.powf(30.0) / 0.25;
+        let gib_per_sec = dp.probe_gib_per_sec.unwrap();
+        assert!((gib_per_sec - expected_gib).abs() <= expected_gib * 1e-12);
+    }
+
+    #[test]
+    fn probe_throughput_is_unknown_without_probe_time() {
+        let dp = DataPoint {
+            probe_tuples: Some(1024),
+            probe_bytes: Some(1024 * 8),
+            ..DataPoint::default()
+        };
+
+        assert_eq!(dp.compute_probe_tuples_per_sec(), None);
+        assert_eq!(dp.compute_probe_gib_per_sec(), None);
+    }
+
+    #[test]
+    fn csv_round_trip_is_lossless() -> Result<(), Box<dyn Error>> {
+        let dp = DataPoint {
+            data_set: Some("Custom".to_string()),
+            hostname: "localhost".to_string(),
+            execution_method: Some(ArgExecutionMethod::GpuBuildHetProbe),
+            device_codename: Some(vec!["POWER9".to_string(), "Tesla V100".to_string()]),
+            hashing_scheme: Some(ArgHashingScheme::LinearProbing),
+            hash_table_memory_type: Some(ArgMemType::DistributedNuma),
+            hash_table_memory_location: Some(vec![0, 255]),
+            hash_table_proportions: Some(vec![40, 60]),
+            tuple_bytes: Some(ArgTupleBytes::Bytes16),
+            page_type: Some(ArgPageType::Huge2MB),
+            data_distribution: Some(ArgDataDistribution::Zipf),
+            zipf_exponent: Some(1.25),
+            join_selectivity: Some(0.1),
+            warm_up: Some(false),
+            probe_ns: Some(123_456_789.123),
+            result_sum: Some(u64::MAX),
+            steady_sm_clock_mhz: Some(1530),
+            llc_misses: Some(42),
+            ..DataPoint::default()
+        }
+        .fill_probe_throughput();
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.serialize(&dp)?;
+        let csv = writer.into_inner().map_err(|e| e.into_error())?;
+
+        let mut reader = csv::Reader::from_reader(csv.as_slice());
+        let header = reader.headers()?.clone();
+        assert!(header.iter().any(|column| column == "steady_sm_clock_mhz"));
+        assert!(header.iter().any(|column| column == "llc_misses"));
+
+        let points = reader
+            .deserialize()
+            .collect::<Result<Vec<DataPoint>, _>>()?;
+        assert_eq!(vec![dp], points);
+
+        Ok(())
+    }
+
+    /// Pins the CSV columns to the schema version.
+    ///
+    /// If this test fails, the columns changed. Bump `SCHEMA_VERSION`, and
+    /// update the expected version and columns.
+    #[test]
+    fn csv_columns_match_schema_version() -> Result<(), Box<dyn Error>> {
+        const COLUMNS: &[&str] = &[
+            "data_set",
+            "hostname",
+            "crate_version",
+            "cuda_driver_version",
+            "nvidia_driver_version",
+            "execution_method",
+            "device_codename",
+            "transfer_strategy",
+            "cpu_morsel_bytes",
+            "gpu_morsel_bytes",
+            "threads",
+            "build_threads",
+            "probe_threads",
+            "grid_size",
+            "block_size",
+            "hashing_scheme",
+            "hash_table_memory_type",
+            "hash_table_memory_location",
+            "hash_table_proportions",
+            "hash_table_tuples",
+            "cached_hash_table_tuples",
+            "tuple_bytes",
+            "payload_bytes",
+            "aggregate",
+            "keys_only_datagen",
+            "relation_memory_type",
+            "page_type",
+            "inner_relation_memory_location",
+            "outer_relation_memory_location",
+            "relation_layout",
+            "build_tuples",
+            "build_bytes",
+            "probe_tuples",
+            "probe_bytes",
+            "data_distribution",
+            "zipf_exponent",
+            "join_selectivity",
+            "shuffle_seed",
+            "iteration",
+            "warm_up",
+            "nvtx_run_id",
+            "build_ns",
+            "probe_ns",
+            "build_warm_up_ns",
+            "probe_warm_up_ns",
+            "build_copy_ns",
+            "probe_copy_ns",
+            "build_compute_ns",
+            "probe_compute_ns",
+            "build_cool_down_ns",
+            "probe_cool_down_ns",
+            "hash_table_malloc_ns",
+            "relation_malloc_ns",
+            "relation_gen_ns",
+            "result_sum",
+            "probe_gib_per_sec",
+            "probe_tuples_per_sec",
+            "throughput_ci_relative_width",
+            "steady_sm_clock_mhz",
+            "flush_caches",
+            "cycles",
+            "instructions",
+            "l1d_misses",
+            "l2_misses",
+            "llc_misses",
+            "dtlb_misses",
+            "itlb_misses",
+        ];
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.serialize(&DataPoint::default())?;
+        let csv = writer.into_inner().map_err(|e| e.into_error())?;
+
+        let mut reader = csv::Reader::from_reader(csv.as_slice());
+        let header: Vec<String> = reader.headers()?.iter().map(String::from).collect();
+
+        assert_eq!(3, DataPoint::SCHEMA_VERSION);
+        assert_eq!(COLUMNS, header.as_slice());
+
+        Ok(())
+    }
+}
@@ -15,29 +15,251 @@
 use super::data_point::DataPoint;
 use super::hash_join_bench::HashJoinPoint;
 use crate::error::Result;
+use average::{Estimate, Variance};
 use error_chain::ensure;
+use numa_gpu::runtime::cache_flush::CacheFlusher;
+use numa_gpu::runtime::nvml::{self, SteadyClockDetector};
 use numa_gpu::runtime::nvtx::Range;
+use numa_gpu::runtime::perf_counters::PerfCounterGroup;
+use rustacuda::device::Device;
 use std::ffi::CString;
+use std::io::{self, Write};
 use std::path::PathBuf;
 
+/// Configures the warm-up until the GPU clock reaches a steady state.
+#[derive(Clone, Debug)]
+pub struct SteadyState {
+    /// The CUDA device that runs the benchmark.
+    pub device: Device,
+
+    /// The maximum difference between the samples of a steady clock.
+    pub tolerance_mhz: u32,
+
+    /// The number of consecutive samples that must lie within the tolerance.
+    pub samples: usize,
+
+    /// The maximum number of warm-up runs before measuring anyways.
+    pub max_warm_ups: u32,
+}
+
+/// Runs the benchmark until the SM clock is steady.
+///
+/// The clock is sampled after each warm-up run. Returns the steady clock, or
+/// `None` if the clock didn't settle within the maximum number of warm-ups.
+fn warm_up_until_steady(
+    steady_state: &SteadyState,
+    func: &mut dyn FnMut() -> Result<HashJoinPoint>,
+) -> Result<Option<u32>> {
+    let mut detector = SteadyClockDetector::new(steady_state.tolerance_mhz, steady_state.samples);
+
+    for _ in 0..steady_state.max_warm_ups {
+        func()?;
+        let clock = nvml::sm_clock_mhz(&steady_state.device)?;
+        if let Some(steady_clock) = detector.push(clock) {
+            return Ok(Some(steady_clock));
+        }
+    }
+
+    eprintln!(
+        "Warning: GPU clock didn't reach a steady state within {} warm-up runs",
+        steady_state.max_warm_ups
+    );
+    Ok(None)
+}
+
+/// Configures repeating the measurement until the throughput is known
+/// precisely enough.
+#[derive(Clone, Debug)]
+pub struct AdaptiveRepeat {
+    /// The target width of the 95% confidence interval relative to the mean.
+    pub relative_width: f64,
+
+    /// The maximum number of runs, including the warm-up run.
+    pub max_repeat: u32,
+}
+
+/// Two-sided 95% quantiles of Student's t-distribution for 1 to 30 degrees of
+/// freedom.
+const T_QUANTILES_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// The number of tuples in a million tuples, the unit of the summary.
+const TUPLES_PER_MTUPLE: f64 = 1_000_000.0;
+
+/// The normal distribution's quantile, which approximates larger degrees of
+/// freedom.
+const Z_QUANTILE_95: f64 = 1.96;
+
+/// Decides when to stop sampling based on the 95% confidence interval of the
+/// mean.
+///
+/// The interval is based on Student's t-distribution, and thus assumes
+/// normally distributed samples.
+#[derive(Debug)]
+pub struct ConfidenceStoppingRule {
+    target_relative_width: f64,
+    samples: Variance,
+}
+
+impl ConfidenceStoppingRule {
+    pub fn new(target_relative_width: f64) -> Self {
+        Self {
+            target_relative_width,
+            samples: Variance::new(),
+        }
+    }
+
+    pub fn push(&mut self, sample: f64) {
+        self.samples.add(sample);
+    }
+
+    /// Returns the width of the confidence interval relative to the mean.
+    ///
+    /// Returns `None` for fewer than two samples or a zero mean.
+    pub fn relative_width(&self) -> Option<f64> {
+        let len = self.samples.len();
+        let mean = self.samples.mean();
+        if len < 2 || mean == 0.0 {
+            return None;
+        }
+
+        let quantile = T_QUANTILES_95
+            .get(len as usize - 2)
+            .copied()
+            .unwrap_or(Z_QUANTILE_95);
+        Some(2.0 * quantile * self.samples.error() / mean.abs())
+    }
+
+    /// Returns `true` if the confidence interval is at most the target width.
+    pub fn is_satisfied(&self) -> bool {
+        self.relative_width()
+            .map_or(false, |width| width <= self.target_relative_width)
+    }
+}
+
+/// The destination of the measurement CSV.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CsvOutput {
+    /// Write the CSV to stdout, e.g., to pipe it into another tool.
+    Stdout,
+
+    /// Write the CSV to a file.
+    File(PathBuf),
+}
+
+impl CsvOutput {
+    /// Opens the destination for writing.
+    pub fn writer(&self) -> Result<Box<dyn Write>> {
+        let writer: Box<dyn Write> = match self {
+            Self::Stdout => Box::new(io::stdout()),
+            Self::File(path) => Box::new(std::fs::File::create(path)?),
+        };
+        Ok(writer)
+    }
+}
+
+/// The path `-` denotes stdout.
+impl From<PathBuf> for CsvOutput {
+    fn from(path: PathBuf) -> Self {
+        if path.as_os_str() == "-" {
+            Self::Stdout
+        } else {
+            Self::File(path)
+        }
+    }
+}
+
+/// Serializes the measurements as CSV rows into `writer`.
+///
+/// With `schema_header`, the CSV starts with a comment line that states the
+/// schema version (see `DataPoint::SCHEMA_VERSION`).
+pub fn write_csv<W: Write>(
+    measurements: &[DataPoint],
+    mut writer: W,
+    schema_header: bool,
+) -> Result<()> {
+    if schema_header {
+        writeln!(writer, "{}", DataPoint::schema_header())?;
+    }
+
+    let mut csv = csv::Writer::from_writer(writer);
+    ensure!(
+        measurements
+            .iter()
+            .try_for_each(|row| csv.serialize(row))
+            .is_ok(),
+        "Couldn't write serialized measurements"
+    );
+    csv.flush()?;
+
+    Ok(())
+}
+
+/// Runs the benchmark and writes the measurements as CSV.
+///
+/// A summary of each run is printed to stdout. If the CSV goes to stdout, the
+/// summary is printed to stderr instead, so that the CSV can be piped.
+///
+/// The first run is a warm-up run. With `adaptive_repeat`, the benchmark runs
+/// at least `repeat` times, and then continues until the confidence interval
+/// of the probe throughput is narrow enough. The warm-up run isn't included in
+/// the interval.
+///
+/// With a `cache_flusher`, the CPU caches are flushed before each run. The
+/// runs then measure cold-cache instead of warm-cache performance. The flush
+/// isn't timed.
+///
+/// With `perf_counters`, the CPU counters of all threads in the group are
+/// counted during each run. The threads must register with the group before
+/// the first run, e.g., when the thread pool starts.
 pub fn measure(
     _name: &str,
     repeat: u32,
-    out_file_name: Option<PathBuf>,
+    adaptive_repeat: Option<AdaptiveRepeat>,
+    csv_output: Option<CsvOutput>,
+    csv_schema_header: bool,
     template: DataPoint,
     mut func: Box<dyn FnMut() -> Result<HashJoinPoint>>,
+    steady_state: Option<SteadyState>,
+    mut cache_flusher: Option<&mut CacheFlusher>,
+    perf_counters: Option<&PerfCounterGroup>,
 ) -> Result<()> {
-    let measurements = (0..repeat)
-        .zip(std::iter::once(true).chain(std::iter::repeat(false)))
-        .map(|(run, warm_up)| {
-            let range_message =
-                CString::new(format!("Measurement run {}", run)).expect("Failed to format string");
+    let template = DataPoint {
+        flush_caches: Some(cache_flusher.is_some()),
+        ..template
+    };
+    let template = match steady_state {
+        Some(ref steady_state) => DataPoint {
+            steady_sm_clock_mhz: warm_up_until_steady(steady_state, &mut *func)?,
+            ..template
+        },
+        None => template,
+    };
 
-            let range = Range::new(&range_message);
-            let result = func();
-            let run_id = range.end();
+    let mut run_once = |run: u32, warm_up: bool| -> Result<DataPoint> {
+        if let Some(flusher) = cache_flusher.as_mut() {
+            flusher.flush();
+        }
 
-            result.map(|p| DataPoint {
+        let range_message =
+            CString::new(format!("Measurement run {}", run)).expect("Failed to format string");
+
+        let range = Range::new(&range_message);
+        let (result, counts) = match perf_counters {
+            Some(counters) => {
+                let (result, counts) = counters.measure(|| func())?;
+                (result, Some(counts))
+            }
+            None => (func(), None),
+        };
+        let run_id = range.end();
+
+        result
+            .map(|p| DataPoint {
+                iteration: Some(run),
                 warm_up: Some(warm_up),
                 nvtx_run_id: Some(run_id),
                 relation_malloc_ns: if warm_up {
@@ -62,22 +284,297 @@ pub fn measure(
                 build_cool_down_ns: p.build_cool_down_ns,
                 probe_cool_down_ns: p.probe_cool_down_ns,
                 cached_hash_table_tuples: p.cached_hash_table_tuples,
-                ..template.clone()
+                result_sum: p.result_sum,
+                ..template.clone().with_perf_counts(counts.as_ref())
+            })
+            .map(|dp| dp.fill_probe_throughput())
+    };
+
+    let max_repeat = adaptive_repeat
+        .as_ref()
+        .map_or(repeat, |adaptive| adaptive.max_repeat.max(repeat));
+    let mut stopping_rule = adaptive_repeat
+        .as_ref()
+        .map(|adaptive| ConfidenceStoppingRule::new(adaptive.relative_width));
+    let mut measurements = Vec::with_capacity(repeat as usize);
+
+    for run in 0..max_repeat {
+        let warm_up = run == 0;
+        let dp = run_once(run, warm_up)?;
+
+        if let (Some(rule), false, Some(tuples)) =
+            (stopping_rule.as_mut(), warm_up, dp.probe_tuples_per_sec)
+        {
+            rule.push(tuples);
+        }
+        measurements.push(dp);
+
+        let is_precise = stopping_rule
+            .as_ref()
+            .map_or(true, ConfidenceStoppingRule::is_satisfied);
+        if run + 1 >= repeat && is_precise {
+            break;
+        }
+    }
+
+    if let Some(rule) = stopping_rule {
+        let width = rule.relative_width();
+        if !rule.is_satisfied() {
+            eprintln!(
+                "Warning: Confidence interval didn't reach the target width within {} runs",
+                max_repeat
+            );
+        }
+        measurements
+            .iter_mut()
+            .for_each(|dp| dp.throughput_ci_relative_width = width);
+    }
+
+    let mut summary: Box<dyn Write> = match csv_output {
+        Some(CsvOutput::Stdout) => Box::new(io::stderr()),
+        _ => Box::new(io::stdout()),
+    };
+    for (run, dp) in measurements
+        .iter()
+        .enumerate()
+        .filter(|(_, dp)| dp.warm_up != Some(true))
+    {
+        if let (Some(gib), Some(tuples)) = (dp.probe_gib_per_sec, dp.probe_tuples_per_sec) {
+            writeln!(
+                summary,
+                "Run {}: probe throughput {:.2} GiB/s, {:.2} Mtuples/s",
+                run,
+                gib,
+                tuples / TUPLES_PER_MTUPLE
+            )?;
+        }
+    }
+
+    if let Some(output) = csv_output {
+        write_csv(&measurements, output.writer()?, csv_schema_header)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{measure, write_csv, ConfidenceStoppingRule, CsvOutput};
+    use crate::error::Result;
+    use crate::measurement::data_point::DataPoint;
+    use crate::measurement::hash_join_bench::HashJoinPoint;
+    use numa_gpu::runtime::cache_flush::CacheFlusher;
+    use std::error::Error;
+    use std::path::PathBuf;
+
+    /// A trivial operator that returns its run count as the probe time.
+    fn counting_func() -> Box<dyn FnMut() -> Result<HashJoinPoint>> {
+        let mut runs = 0;
+        Box::new(move || {
+            runs += 1;
+            Ok(HashJoinPoint {
+                probe_ns: Some(runs as f64),
+                ..HashJoinPoint::default()
             })
         })
-        .collect::<Result<Vec<_>>>()?;
-
-    if let Some(ofn) = out_file_name {
-        let csv_file = std::fs::File::create(ofn)?;
-        let mut csv = csv::Writer::from_writer(csv_file);
-        ensure!(
-            measurements
-                .iter()
-                .try_for_each(|row| csv.serialize(row))
-                .is_ok(),
-            "Couldn't write serialized measurements"
+    }
+
+    #[test]
+    fn each_repeat_writes_a_row_with_its_iteration() -> std::result::Result<(), Box<dyn Error>> {
+        const REPEAT: u32 = 5;
+
+        let csv_path =
+            std::env::temp_dir().join(format!("hashjoin_iterations_{}.csv", std::process::id()));
+
+        measure(
+            "iterations",
+            REPEAT,
+            None,
+            Some(CsvOutput::File(csv_path.clone())),
+            false,
+            DataPoint::default(),
+            counting_func(),
+            None,
+            None,
+            None,
+        )?;
+
+        let points = csv::Reader::from_path(&csv_path)?
+            .deserialize()
+            .collect::<std::result::Result<Vec<DataPoint>, _>>()?;
+        std::fs::remove_file(&csv_path)?;
+
+        assert_eq!(points.len(), REPEAT as usize);
+        for (i, point) in points.iter().enumerate() {
+            assert_eq!(point.iteration, Some(i as u32));
+            assert_eq!(point.warm_up, Some(i == 0));
+            assert_eq!(point.probe_ns, Some((i + 1) as f64));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn cache_flusher_flushes_before_each_run() -> std::result::Result<(), Box<dyn Error>> {
+        const REPEAT: u32 = 4;
+
+        let csv_path =
+            std::env::temp_dir().join(format!("hashjoin_flush_{}.csv", std::process::id()));
+
+        // A small buffer suffices, as the test only counts the flushes
+        let mut flusher = CacheFlusher::with_bytes(4096);
+        measure(
+            "flush",
+            REPEAT,
+            None,
+            Some(CsvOutput::File(csv_path.clone())),
+            false,
+            DataPoint::default(),
+            counting_func(),
+            None,
+            Some(&mut flusher),
+            None,
+        )?;
+
+        let points = csv::Reader::from_path(&csv_path)?
+            .deserialize()
+            .collect::<std::result::Result<Vec<DataPoint>, _>>()?;
+        std::fs::remove_file(&csv_path)?;
+
+        assert_eq!(flusher.flushes(), REPEAT as u64);
+        assert!(points.iter().all(|point| point.flush_caches == Some(true)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dash_writes_csv_to_stdout() -> std::result::Result<(), Box<dyn Error>> {
+        assert_eq!(CsvOutput::from(PathBuf::from("-")), CsvOutput::Stdout);
+        assert_eq!(
+            CsvOutput::from(PathBuf::from("out.csv")),
+            CsvOutput::File(PathBuf::from("out.csv"))
         );
+
+        let measurements = (0..3)
+            .map(|i| DataPoint {
+                iteration: Some(i),
+                warm_up: Some(i == 0),
+                ..DataPoint::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut buffer = Vec::new();
+        write_csv(&measurements, &mut buffer, false)?;
+
+        let points = csv::Reader::from_reader(buffer.as_slice())
+            .deserialize()
+            .collect::<std::result::Result<Vec<DataPoint>, _>>()?;
+        assert_eq!(points.len(), measurements.len());
+        for (i, point) in points.iter().enumerate() {
+            assert_eq!(point.iteration, Some(i as u32));
+            assert_eq!(point.warm_up, Some(i == 0));
+        }
+
+        Ok(())
     }
 
-    Ok(())
+    #[test]
+    fn schema_header_states_schema_version() -> std::result::Result<(), Box<dyn Error>> {
+        let measurements = vec![DataPoint {
+            iteration: Some(0),
+            ..DataPoint::default()
+        }];
+
+        let mut buffer = Vec::new();
+        write_csv(&measurements, &mut buffer, true)?;
+
+        let csv = String::from_utf8(buffer)?;
+        let header = csv.lines().next().ok_or("Empty CSV")?;
+        assert_eq!(
+            DataPoint::parse_schema_header(header),
+            Some(DataPoint::SCHEMA_VERSION)
+        );
+
+        // The column names aren't a schema header
+        let columns = csv.lines().nth(1).ok_or("Missing column names")?;
+        assert_eq!(DataPoint::parse_schema_header(columns), None);
+
+        // Readers skip the header as a comment
+        let points = csv::ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .from_reader(csv.as_bytes())
+            .deserialize()
+            .collect::<std::result::Result<Vec<DataPoint>, _>>()?;
+        assert_eq!(points, measurements);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stopping_rule_needs_two_samples() {
+        let mut rule = ConfidenceStoppingRule::new(0.5);
+        assert_eq!(rule.relative_width(), None);
+        assert!(!rule.is_satisfied());
+
+        rule.push(100.0);
+        assert_eq!(rule.relative_width(), None);
+        assert!(!rule.is_satisfied());
+
+        rule.push(100.0);
+        assert_eq!(rule.relative_width(), Some(0.0));
+        assert!(rule.is_satisfied());
+    }
+
+    #[test]
+    fn stopping_rule_computes_t_interval() {
+        // Four samples with mean 100 have 3 degrees of freedom, and the
+        // standard error is the sample standard deviation divided by two
+        let mut rule = ConfidenceStoppingRule::new(0.3);
+        for &sample in &[90.0, 90.0, 110.0, 110.0] {
+            rule.push(sample);
+        }
+
+        let standard_error = (400.0_f64 / 3.0).sqrt() / 2.0;
+        let expected = 2.0 * 3.182 * standard_error / 100.0;
+        let width = rule.relative_width().unwrap();
+        assert!((width - expected).abs() < 1e-9, "width {}", width);
+        assert!(!rule.is_satisfied());
+    }
+
+    #[test]
+    fn stopping_rule_stops_sooner_for_stable_samples() {
+        const TARGET: f64 = 0.02;
+
+        // Alternate around the mean with a relative amplitude
+        let runs_until_stop = |amplitude: f64| {
+            let mut rule = ConfidenceStoppingRule::new(TARGET);
+            (0..10_000)
+                .map(|i| 1000.0 * (1.0 + if i % 2 == 0 { amplitude } else { -amplitude }))
+                .position(|sample| {
+                    rule.push(sample);
+                    rule.is_satisfied()
+                })
+                .map(|pos| pos + 1)
+        };
+
+        let stable = runs_until_stop(0.001).unwrap();
+        let noisy = runs_until_stop(0.05).unwrap();
+        assert!(stable <= 3, "stable samples stopped after {}", stable);
+        assert!(noisy > stable);
+
+        // The interval shrinks with the square root of the number of samples,
+        // thus 5% noise needs about (2 * 1.96 * 0.05 / 0.02)^2 = 96 samples
+        assert!(
+            noisy > 50 && noisy < 150,
+            "noisy samples stopped after {}",
+            noisy
+        );
+
+        // Noise that never fits into the target with few samples doesn't stop
+        // early
+        let mut rule = ConfidenceStoppingRule::new(TARGET);
+        rule.push(500.0);
+        rule.push(1500.0);
+        assert!(!rule.is_satisfied());
+    }
 }
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::error::{ErrorKind, Result};
-use data_store::join_data::JoinData;
+use data_store::join_data::{CombinedJoinData, JoinData};
 use datagen::relation::KeyAttribute;
 use num_traits::cast::AsPrimitive;
 use numa_gpu::runtime::allocator;
@@ -21,25 +21,46 @@ use numa_gpu::runtime::cpu_affinity::CpuAffinity;
 use numa_gpu::runtime::cuda::{
     CudaTransferStrategy, IntoCudaIterator, IntoCudaIteratorWithStrategy,
 };
+use numa_gpu::runtime::cuda_wrapper::PrefetchDestination;
 use numa_gpu::runtime::dispatcher::{
     HetMorselExecutorBuilder, IntoHetMorselIterator, MorselSpec, WorkerCpuAffinity,
 };
+use numa_gpu::runtime::hw_info::ProcessorCache;
 use numa_gpu::runtime::memory::*;
+use numa_gpu::runtime::numa;
+use numa_gpu::runtime::perf_counters::PerfCounterGroup;
+use numa_gpu::runtime::timer::{CpuTimer, CudaEventTimer, PhaseTimer};
+use numa_gpu::runtime::trace::TraceRecorder;
 use numa_gpu::runtime::{cuda_wrapper, linux_wrapper};
 use numa_gpu::utils::CachePadded;
-use rustacuda::event::{Event, EventFlags};
 use rustacuda::function::{BlockSize, GridSize};
-use rustacuda::memory::{AsyncCopyDestination, DeviceBuffer, DeviceCopy};
+use rustacuda::memory::{DeviceBuffer, DeviceCopy};
 use rustacuda::stream::{Stream, StreamFlags};
+use sql_ops::join::wide_payload_join::WidePayloadHashTable;
 use sql_ops::join::{no_partitioning_join, HashingScheme, HtEntry};
+use sql_ops::reduction::GpuTreeSum;
 use std::cell::RefCell;
-use std::convert::TryInto;
 use std::os::raw::c_uint;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Instant;
 use std::{cmp, mem};
 
+/// Prefetches unified memory to the destination.
+///
+/// Other memory types cannot be migrated and are left in place.
+fn prefetch_unified<T: DeviceCopy>(
+    mem: &mut Mem<T>,
+    destination: Option<PrefetchDestination>,
+    stream: &Stream,
+) -> Result<()> {
+    if let (Some(destination), CudaUniMem(mem)) = (destination, mem) {
+        cuda_wrapper::prefetch_async(mem.as_unified_ptr(), mem.len(), destination, stream)?;
+    }
+
+    Ok(())
+}
+
 /// GPU memory to leave free when allocating a hybrid hash table
 ///
 /// Getting the amount of free GPU memory and allocating the memory is
@@ -48,9 +69,118 @@ use std::{cmp, mem};
 /// Instead of allocating every last byte of GPU memory, leave some slack space.
 const GPU_MEM_SLACK_BYTES: usize = 32 * 1024 * 1024;
 
+/// Creates a thread pool for the CPU joins, and pins each thread to its core.
+///
+/// Building the pool spawns and pins the threads. Thus, the benchmark creates
+/// the pool once and reuses it for all repetitions, instead of timing the
+/// pool's setup and teardown in each repetition.
+///
+/// With `perf_counters`, each thread opens its own counters in the group, as
+/// the long-lived threads don't report inherited counts.
+pub fn cpu_thread_pool(
+    threads: usize,
+    cpu_affinity: &CpuAffinity,
+    perf_counters: Option<Arc<PerfCounterGroup>>,
+) -> Result<rayon::ThreadPool> {
+    let boxed_cpu_affinity = Arc::new(cpu_affinity.clone());
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .start_handler(move |tid| {
+            boxed_cpu_affinity
+                .clone()
+                .set_affinity(tid as u16)
+                .expect("Couldn't set CPU core affinity");
+            if let Some(ref perf_counters) = perf_counters {
+                perf_counters
+                    .register_current_thread()
+                    .expect("Couldn't open perf counters");
+            }
+            likwid::thread_init();
+        })
+        .build()
+        .map_err(|_| ErrorKind::RuntimeError("Failed to create thread pool".to_string()))?;
+
+    Ok(thread_pool)
+}
+
+/// Checks that the thread pool has enough threads to run a phase.
+fn check_thread_pool(thread_pool: &rayon::ThreadPool, threads: usize) -> Result<()> {
+    if thread_pool.current_num_threads() < threads {
+        Err(ErrorKind::InvalidArgument(format!(
+            "The thread pool has {} threads, but the join requires {} threads",
+            thread_pool.current_num_threads(),
+            threads
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// The build and probe relations in their memory layout.
+pub enum JoinRelations<T: DeviceCopy> {
+    /// Each attribute is stored in a separate buffer.
+    Separate(JoinData<T>),
+
+    /// All attributes are stored back-to-back in a single buffer.
+    ///
+    /// Only `cuda_hash_join` and `cpu_hash_join` support the combined layout,
+    /// and neither prefetches nor pre-touches the combined buffer.
+    Combined(CombinedJoinData<T>),
+}
+
+impl<T: DeviceCopy> JoinRelations<T> {
+    /// Returns the number of tuples in the build relation.
+    pub fn build_len(&self) -> usize {
+        match self {
+            Self::Separate(data) => data.build_relation.len(),
+            Self::Combined(data) => data.build_len(),
+        }
+    }
+
+    /// Returns the number of tuples in the probe relation.
+    pub fn probe_len(&self) -> usize {
+        match self {
+            Self::Separate(data) => data.probe_relation.len(),
+            Self::Combined(data) => data.probe_len(),
+        }
+    }
+
+    /// Returns the size of the build relation in bytes.
+    pub fn build_bytes(&self) -> usize {
+        match self {
+            Self::Separate(data) => data.build_relation.bytes(),
+            Self::Combined(data) => data.build_bytes(),
+        }
+    }
+
+    /// Returns the size of the probe relation in bytes.
+    pub fn probe_bytes(&self) -> usize {
+        match self {
+            Self::Separate(data) => data.probe_relation.bytes(),
+            Self::Combined(data) => data.probe_bytes(),
+        }
+    }
+
+    /// Returns the relations in separate buffers.
+    ///
+    /// Returns an `InvalidArgument` error for the combined layout, which only
+    /// `cuda_hash_join` and `cpu_hash_join` support.
+    pub fn separate_mut(&mut self) -> Result<&mut JoinData<T>> {
+        match self {
+            Self::Separate(data) => Ok(data),
+            Self::Combined(_) => Err(ErrorKind::InvalidArgument(
+                "The execution method doesn't support the combined relation layout".to_string(),
+            ))?,
+        }
+    }
+}
+
 pub struct HashJoinBench<T> {
     pub hashing_scheme: HashingScheme,
     pub is_selective: bool,
+    pub key_only: bool,
+    pub prefetch_destination: Option<PrefetchDestination>,
+    pub pre_touch: bool,
     pub hash_table_len: usize,
     _phantom_data: std::marker::PhantomData<T>,
 }
@@ -59,6 +189,9 @@ pub struct HashJoinBenchBuilder {
     hash_table_load_factor: usize,
     hashing_scheme: HashingScheme,
     is_selective: bool,
+    key_only: bool,
+    prefetch_destination: Option<PrefetchDestination>,
+    pre_touch: bool,
 }
 
 #[derive(Debug, Default)]
@@ -75,6 +208,7 @@ pub struct HashJoinPoint {
     pub build_cool_down_ns: Option<f64>,
     pub probe_cool_down_ns: Option<f64>,
     pub cached_hash_table_tuples: Option<usize>,
+    pub result_sum: Option<u64>,
 }
 
 impl Default for HashJoinBenchBuilder {
@@ -83,6 +217,9 @@ impl Default for HashJoinBenchBuilder {
             hash_table_load_factor: 2,
             hashing_scheme: HashingScheme::LinearProbing,
             is_selective: false,
+            key_only: false,
+            prefetch_destination: None,
+            pre_touch: false,
         }
     }
 }
@@ -103,9 +240,40 @@ impl HashJoinBenchBuilder {
         self
     }
 
+    /// Join only the keys, and count the matches instead of summing up the
+    /// probe payloads.
+    ///
+    /// Only `cuda_hash_join` and `cpu_hash_join` support key-only joins.
+    pub fn key_only(&mut self, key_only: bool) -> &mut Self {
+        self.key_only = key_only;
+        self
+    }
+
+    /// Prefetch unified memory to the destination before each phase.
+    ///
+    /// Only `cuda_hash_join` prefetches. Memory that is not unified memory is
+    /// left in place.
+    pub fn prefetch_destination(
+        &mut self,
+        prefetch_destination: Option<PrefetchDestination>,
+    ) -> &mut Self {
+        self.prefetch_destination = prefetch_destination;
+        self
+    }
+
+    /// Fault in the relations' pages on the join's threads before the build
+    /// phase.
+    ///
+    /// Otherwise, pages that haven't been touched yet are faulted in during
+    /// the measured phases. Only `cpu_hash_join` pre-touches the relations.
+    pub fn pre_touch(&mut self, pre_touch: bool) -> &mut Self {
+        self.pre_touch = pre_touch;
+        self
+    }
+
     fn get_hash_table_len(&self, inner_relation_len: usize) -> Result<usize> {
         let hash_table_len = match self.hashing_scheme {
-            HashingScheme::LinearProbing => inner_relation_len
+            HashingScheme::LinearProbing | HashingScheme::Cuckoo => inner_relation_len
                 .checked_next_power_of_two()
                 .and_then(|x| x.checked_mul(self.hash_table_load_factor))
                 .ok_or_else(|| {
@@ -118,11 +286,25 @@ impl HashJoinBenchBuilder {
         Ok(hash_table_len)
     }
 
-    pub fn build<T>(&mut self, inner_relation_len: usize) -> Result<HashJoinBench<T>> {
+    /// Builds the benchmark.
+    ///
+    /// Returns an `IntegerOverflow` error if the hash table exceeds the
+    /// maximum allocation size. Thus, the hash table allocators of the
+    /// benchmark never receive an overflowing length.
+    pub fn build<T>(&mut self, inner_relation_len: usize) -> Result<HashJoinBench<T>>
+    where
+        T: AsPrimitive<c_uint> + DeviceCopy + KeyAttribute,
+    {
+        let hash_table_len = self.get_hash_table_len(inner_relation_len)?;
+        no_partitioning_join::HashTable::<T>::check_len(hash_table_len)?;
+
         Ok(HashJoinBench {
             hashing_scheme: self.hashing_scheme,
             is_selective: self.is_selective,
-            hash_table_len: self.get_hash_table_len(inner_relation_len)?,
+            key_only: self.key_only,
+            prefetch_destination: self.prefetch_destination,
+            pre_touch: self.pre_touch,
+            hash_table_len,
             _phantom_data: std::marker::PhantomData::<T>,
         })
     }
@@ -141,16 +323,24 @@ where
 {
     pub fn cuda_hash_join(
         &self,
-        data: &mut JoinData<T>,
+        data: &mut JoinRelations<T>,
         hash_table_alloc: allocator::MemSpillAllocFn<HtEntry<T, T>>,
         cache_node: u16,
         max_hash_table_cache_bytes: Option<usize>,
         cached_hash_table_tuples: Rc<RefCell<Option<usize>>>,
         build_dim: (GridSize, BlockSize),
         probe_dim: (GridSize, BlockSize),
+        trace: Option<&RefCell<TraceRecorder>>,
     ) -> Result<HashJoinPoint> {
         let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
 
+        let trace_phase = |name: &str, timer: &CudaEventTimer<'_>| -> Result<()> {
+            if let Some(trace) = trace {
+                trace.borrow_mut().record_timer(name, timer)?;
+            }
+            Ok(())
+        };
+
         // FIXME: specify load factor as argument
         let ht_malloc_timer = Instant::now();
 
@@ -173,15 +363,8 @@ where
         let ht_alloc = hash_table_alloc(cache_max_len);
 
         let mut hash_table_mem = ht_alloc(self.hash_table_len);
-        if let CudaUniMem(ref mut _mem) = hash_table_mem {
-            // mem_advise(
-            //     mem.as_unified_ptr(),
-            //     mem.len(),
-            //     MemAdviseFlags::CU_MEM_ADVISE_SET_PREFERRED_LOCATION,
-            //     CPU_DEVICE_ID,
-            // )?;
-            // prefetch_async(mem.as_unified_ptr(), mem.len(), CPU_DEVICE_ID, &stream)?;
-        }
+        let mut init_timer = CudaEventTimer::start(&stream)?;
+        prefetch_unified(&mut hash_table_mem, self.prefetch_destination, &stream)?;
         let mut hash_table =
             no_partitioning_join::HashTable::new_on_gpu(hash_table_mem, self.hash_table_len)?;
         hash_table.mlock()?;
@@ -195,7 +378,9 @@ where
             Mem::CudaDevMem(mem)
         };
 
+        init_timer.stop()?;
         stream.synchronize()?;
+        trace_phase("initialize", &init_timer)?;
 
         let hj_op = hj_op_builder
             .hashing_scheme(self.hashing_scheme)
@@ -205,44 +390,86 @@ where
             .hash_table(Arc::new(hash_table))
             .build()?;
 
-        let start_event = Event::new(EventFlags::DEFAULT)?;
-        let stop_event = Event::new(EventFlags::DEFAULT)?;
+        // Prefetch outside of the measured time span
+        if let JoinRelations::Separate(data) = &mut *data {
+            let mut prefetch_timer = CudaEventTimer::start(&stream)?;
+            let (build_key, build_value) = data.build_relation.parts_mut();
+            prefetch_unified(build_key, self.prefetch_destination, &stream)?;
+            if !self.key_only {
+                prefetch_unified(build_value, self.prefetch_destination, &stream)?;
+            }
+            prefetch_timer.stop()?;
+            stream.synchronize()?;
+            trace_phase("build prefetch", &prefetch_timer)?;
+        }
 
-        start_event.record(&stream)?;
-        hj_op.build(
-            data.build_relation_key.as_launchable_slice(),
-            data.build_relation_payload.as_launchable_slice(),
-            &stream,
-        )?;
+        let (build_key, build_value) = match &*data {
+            JoinRelations::Separate(data) => data.build_relation.as_launchable_slices(),
+            JoinRelations::Combined(data) => {
+                let (build_key, build_value, _, _) = data.attributes();
+                (
+                    build_key.as_launchable_slice(),
+                    build_value.as_launchable_slice(),
+                )
+            }
+        };
 
-        stop_event.record(&stream)?;
-        stop_event.synchronize()?;
-        let build_millis = stop_event.elapsed_time_f32(&start_event)?;
+        let mut build_timer = CudaEventTimer::start(&stream)?;
+        if self.key_only {
+            hj_op.build_keys(build_key, &stream)?;
+        } else {
+            hj_op.build(build_key, build_value, &stream)?;
+        }
 
-        start_event.record(&stream)?;
-        hj_op.probe_sum(
-            data.probe_relation_key.as_launchable_slice(),
-            data.probe_relation_payload.as_launchable_slice(),
-            &mut result_sums,
-            &stream,
-        )?;
+        build_timer.stop()?;
+        let build_ns = build_timer.elapsed_ns()?;
+        trace_phase("build", &build_timer)?;
 
-        stop_event.record(&stream)?;
-        stop_event.synchronize()?;
-        let probe_millis = stop_event.elapsed_time_f32(&start_event)?;
+        if let JoinRelations::Separate(data) = &mut *data {
+            let mut prefetch_timer = CudaEventTimer::start(&stream)?;
+            let (probe_key, probe_value) = data.probe_relation.parts_mut();
+            prefetch_unified(probe_key, self.prefetch_destination, &stream)?;
+            if !self.key_only {
+                prefetch_unified(probe_value, self.prefetch_destination, &stream)?;
+            }
+            prefetch_timer.stop()?;
+            stream.synchronize()?;
+            trace_phase("probe prefetch", &prefetch_timer)?;
+        }
 
-        let mut result_sums_host = vec![0; result_sums.len()];
-        if let Mem::CudaDevMem(results) = result_sums {
-            unsafe { results.async_copy_to(&mut result_sums_host, &stream)? };
+        let (probe_key, probe_value) = match &*data {
+            JoinRelations::Separate(data) => data.probe_relation.as_launchable_slices(),
+            JoinRelations::Combined(data) => {
+                let (_, _, probe_key, probe_value) = data.attributes();
+                (
+                    probe_key.as_launchable_slice(),
+                    probe_value.as_launchable_slice(),
+                )
+            }
+        };
+
+        let mut probe_timer = CudaEventTimer::start(&stream)?;
+        if self.key_only {
+            hj_op.probe_count(probe_key, &mut result_sums, &stream)?;
+        } else {
+            hj_op.probe_sum(probe_key, probe_value, &mut result_sums, &stream)?;
         }
 
-        stream.synchronize()?;
-        let _sum: u64 = result_sums_host.iter().sum();
+        probe_timer.stop()?;
+        let probe_ns = probe_timer.elapsed_ns()?;
+        trace_phase("probe", &probe_timer)?;
+
+        // The tree sum adds the per-thread results in a fixed order
+        let mut sum_timer = CudaEventTimer::start(&stream)?;
+        let result_sum = GpuTreeSum::sum(result_sums.as_launchable_slice(), &stream)?;
+        sum_timer.stop()?;
+        trace_phase("result sum", &sum_timer)?;
 
         Ok(HashJoinPoint {
-            build_ns: Some(build_millis as f64 * 10_f64.powf(6.0)),
-            probe_ns: Some(probe_millis as f64 * 10_f64.powf(6.0)),
+            build_ns: Some(build_ns),
+            probe_ns: Some(probe_ns),
             hash_table_malloc_ns: Some(ht_malloc_time.as_nanos() as f64),
+            result_sum: Some(result_sum),
             cached_hash_table_tuples: *cached_hash_table_tuples.borrow(),
             ..Default::default()
         })
@@ -290,22 +517,8 @@ where
 
         stream.synchronize()?;
 
-        let build_rel_key: &mut [T] = (&mut data.build_relation_key)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let build_rel_pay: &mut [T] = (&mut data.build_relation_payload)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let probe_rel_key: &mut [T] = (&mut data.probe_relation_key)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let probe_rel_pay: &mut [T] = (&mut data.probe_relation_payload)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
+        let (build_rel_key, build_rel_pay) = data.build_relation.try_as_mut_slices()?;
+        let (probe_rel_key, probe_rel_pay) = data.probe_relation.try_as_mut_slices()?;
 
         let hj_op = no_partitioning_join::CudaHashJoinBuilder::<T>::default()
             .hashing_scheme(self.hashing_scheme)
@@ -330,36 +543,32 @@ where
             cpu_affinity,
         )?;
 
-        let build_timer = Instant::now();
+        let mut build_timer = CpuTimer::start();
         let build_mnts = build_iter.fold(|(key, val), stream| {
             hj_op
                 .build(key, val, stream)
                 .expect("Failed to run hash join build");
             Ok(())
         })?;
-        let build_time = build_timer.elapsed();
+        build_timer.stop()?;
 
-        let probe_timer = Instant::now();
+        let mut probe_timer = CpuTimer::start();
         let probe_mnts = probe_iter.fold(|(key, val), stream| {
             hj_op
                 .probe_sum(key, val, &result_sums, stream)
                 .expect("Failed to run hash join probe");
             Ok(())
         })?;
-        let probe_time = probe_timer.elapsed();
+        probe_timer.stop()?;
 
-        let mut result_sums_host = vec![0; result_sums.len()];
-        if let Mem::CudaDevMem(results) = result_sums {
-            unsafe { results.async_copy_to(&mut result_sums_host, &stream)? };
-        }
-
-        stream.synchronize()?;
-        let _sum: u64 = result_sums_host.iter().sum();
+        // The tree sum adds the per-thread results in a fixed order
+        let result_sum = GpuTreeSum::sum(result_sums.as_launchable_slice(), &stream)?;
 
         Ok(HashJoinPoint {
-            build_ns: Some(build_time.as_nanos() as f64),
-            probe_ns: Some(probe_time.as_nanos() as f64),
+            build_ns: Some(build_timer.elapsed_ns()?),
+            probe_ns: Some(probe_timer.elapsed_ns()?),
             hash_table_malloc_ns: Some(ht_malloc_time.as_nanos() as f64),
+            result_sum: Some(result_sum),
             build_warm_up_ns: build_mnts.warm_up_ns,
             probe_warm_up_ns: probe_mnts.warm_up_ns,
             build_copy_ns: build_mnts.copy_ns,
@@ -399,20 +608,12 @@ where
 
         stream.synchronize()?;
 
-        let build_rel_key = match data.build_relation_key {
-            Mem::CudaUniMem(ref mut m) => m,
-            _ => unreachable!(),
-        };
-        let build_rel_pay = match data.build_relation_payload {
-            Mem::CudaUniMem(ref mut m) => m,
-            _ => unreachable!(),
-        };
-        let probe_rel_key = match data.probe_relation_key {
-            Mem::CudaUniMem(ref mut m) => m,
+        let (build_rel_key, build_rel_pay) = match data.build_relation.parts_mut() {
+            (Mem::CudaUniMem(k), Mem::CudaUniMem(p)) => (k, p),
             _ => unreachable!(),
         };
-        let probe_rel_pay = match data.probe_relation_payload {
-            Mem::CudaUniMem(ref mut m) => m,
+        let (probe_rel_key, probe_rel_pay) = match data.probe_relation.parts_mut() {
+            (Mem::CudaUniMem(k), Mem::CudaUniMem(p)) => (k, p),
             _ => unreachable!(),
         };
 
@@ -427,7 +628,7 @@ where
         let mut build_relation = (build_rel_key, build_rel_pay);
         let mut probe_relation = (probe_rel_key, probe_rel_pay);
 
-        let build_timer = Instant::now();
+        let mut build_timer = CpuTimer::start();
         let build_mnts =
             build_relation
                 .into_cuda_iter(gpu_morsel_bytes)?
@@ -437,9 +638,9 @@ where
                         .expect("Failed to run hash join build");
                     Ok(())
                 })?;
-        let build_time = build_timer.elapsed();
+        build_timer.stop()?;
 
-        let probe_timer = Instant::now();
+        let mut probe_timer = CpuTimer::start();
         let probe_mnts =
             probe_relation
                 .into_cuda_iter(gpu_morsel_bytes)?
@@ -449,20 +650,16 @@ where
                         .expect("Failed to run hash join probe");
                     Ok(())
                 })?;
-        let probe_time = probe_timer.elapsed();
-
-        let mut result_sums_host = vec![0; result_sums.len()];
-        if let Mem::CudaDevMem(results) = result_sums {
-            unsafe { results.async_copy_to(&mut result_sums_host, &stream)? };
-        }
+        probe_timer.stop()?;
 
-        stream.synchronize()?;
-        let _sum: u64 = result_sums_host.iter().sum();
+        // The tree sum adds the per-thread results in a fixed order
+        let result_sum = GpuTreeSum::sum(result_sums.as_launchable_slice(), &stream)?;
 
         Ok(HashJoinPoint {
-            build_ns: Some(build_time.as_nanos() as f64),
-            probe_ns: Some(probe_time.as_nanos() as f64),
+            build_ns: Some(build_timer.elapsed_ns()?),
+            probe_ns: Some(probe_timer.elapsed_ns()?),
             hash_table_malloc_ns: Some(ht_malloc_time.as_nanos() as f64),
+            result_sum: Some(result_sum),
             build_warm_up_ns: build_mnts.warm_up_ns,
             probe_warm_up_ns: probe_mnts.warm_up_ns,
             build_copy_ns: build_mnts.copy_ns,
@@ -475,13 +672,26 @@ where
         })
     }
 
+    /// Joins the relations on the CPU.
+    ///
+    /// The phases run on `thread_pool`, which must have at least as many
+    /// threads as the larger phase. See `cpu_thread_pool` for creating a
+    /// pinned pool.
     pub fn cpu_hash_join(
         &self,
-        data: &mut JoinData<T>,
-        threads: usize,
-        cpu_affinity: &CpuAffinity,
+        data: &mut JoinRelations<T>,
+        build_threads: usize,
+        probe_threads: usize,
+        thread_pool: &rayon::ThreadPool,
         hash_table_alloc: allocator::DerefMemAllocFn<HtEntry<T, T>>,
     ) -> Result<HashJoinPoint> {
+        if build_threads == 0 || probe_threads == 0 {
+            Err(ErrorKind::InvalidArgument(
+                "The build and probe phases require at least one thread each".to_string(),
+            ))?;
+        }
+        check_thread_pool(thread_pool, cmp::max(build_threads, probe_threads))?;
+
         let ht_malloc_timer = Instant::now();
         let hash_table_mem = hash_table_alloc(self.hash_table_len);
         let mut hash_table =
@@ -490,84 +700,178 @@ where
         let hash_table = hash_table;
         let ht_malloc_time = ht_malloc_timer.elapsed();
 
-        let mut result_sums = vec![CachePadded { value: 0 }; threads];
-
-        let boxed_cpu_affinity = Arc::new(cpu_affinity.clone());
-        let thread_pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .start_handler(move |tid| {
-                boxed_cpu_affinity
-                    .clone()
-                    .set_affinity(tid as u16)
-                    .expect("Couldn't set CPU core affinity");
-                likwid::thread_init();
-            })
-            .build()
-            .map_err(|_| ErrorKind::RuntimeError("Failed to create thread pool".to_string()))?;
-        let build_chunk_size = (data.build_relation_key.len() + threads - 1) / threads;
-        let probe_chunk_size = (data.probe_relation_key.len() + threads - 1) / threads;
-
-        let build_rel_key: &[T] = (&data.build_relation_key)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let build_rel_chunks: Vec<_> = build_rel_key.chunks(build_chunk_size).collect();
+        let mut result_sums = vec![CachePadded { value: 0 }; probe_threads];
+
+        // The build and probe phases each spawn one task per thread. Thus, a
+        // phase never runs on more threads than requested, even though the
+        // pool is sized for the larger phase.
+        if let (true, JoinRelations::Separate(data)) = (self.pre_touch, &mut *data) {
+            let page_size = ProcessorCache::page_size();
+            for relation in [&mut data.build_relation, &mut data.probe_relation] {
+                let (key, value) = relation.parts_mut();
+                numa::pre_touch(key.try_as_mut_slice()?, page_size, thread_pool);
+                if !self.key_only {
+                    numa::pre_touch(value.try_as_mut_slice()?, page_size, thread_pool);
+                }
+            }
+        }
+
+        let (build_rel_key, build_rel_pay, probe_rel_key, probe_rel_pay): (&[T], &[T], &[T], &[T]) =
+            match &*data {
+                JoinRelations::Separate(data) => (
+                    data.build_relation.key().try_as_slice()?,
+                    data.build_relation.value().try_as_slice()?,
+                    data.probe_relation.key().try_as_slice()?,
+                    data.probe_relation.value().try_as_slice()?,
+                ),
+                JoinRelations::Combined(data) => data.attributes(),
+            };
+
+        // A cuckoo build must insert the entire relation in a single call,
+        // because a failed insert rebuilds the hash table with a new seed
+        let build_threads = match self.hashing_scheme {
+            HashingScheme::Cuckoo => 1,
+            _ => build_threads,
+        };
 
-        let build_rel_pay: &[T] = (&data.build_relation_payload)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let build_pay_chunks: Vec<_> = build_rel_pay.chunks(build_chunk_size).collect();
+        // `chunks` requires a non-zero chunk size, also for empty relations
+        let build_chunk_size = ((build_rel_key.len() + build_threads - 1) / build_threads).max(1);
+        let probe_chunk_size = ((probe_rel_key.len() + probe_threads - 1) / probe_threads).max(1);
 
-        let probe_rel_key: &[T] = (&data.probe_relation_key)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
+        let build_rel_chunks: Vec<_> = build_rel_key.chunks(build_chunk_size).collect();
         let probe_rel_chunks: Vec<_> = probe_rel_key.chunks(probe_chunk_size).collect();
 
-        let probe_rel_pay: &[T] = (&data.probe_relation_payload)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let probe_pay_chunks: Vec<_> = probe_rel_pay.chunks(probe_chunk_size).collect();
+        // A key-only join doesn't touch the payloads
+        let (build_pay_chunks, probe_pay_chunks): (Vec<Option<&[T]>>, Vec<Option<&[T]>>) =
+            if self.key_only {
+                (
+                    vec![None; build_rel_chunks.len()],
+                    vec![None; probe_rel_chunks.len()],
+                )
+            } else {
+                (
+                    build_rel_pay.chunks(build_chunk_size).map(Some).collect(),
+                    probe_rel_pay.chunks(probe_chunk_size).map(Some).collect(),
+                )
+            };
 
         let hj_builder = no_partitioning_join::CpuHashJoinBuilder::default()
             .hashing_scheme(self.hashing_scheme)
             .is_selective(self.is_selective)
             .hash_table(Arc::new(hash_table));
 
-        let build_timer = Instant::now();
+        let mut build_timer = CpuTimer::start();
         thread_pool.scope(|s| {
-            for ((_tid, rel), pay) in (0..threads).zip(build_rel_chunks).zip(build_pay_chunks) {
+            for ((_tid, rel), pay) in (0..build_threads)
+                .zip(build_rel_chunks)
+                .zip(build_pay_chunks)
+            {
                 let mut hj_op = hj_builder.build();
                 s.spawn(move |_| {
-                    hj_op.build(rel, pay).expect("Couldn't build hash table");
+                    match pay {
+                        Some(pay) => hj_op.build(rel, pay),
+                        None => hj_op.build_keys(rel),
+                    }
+                    .expect("Couldn't build hash table");
                 });
             }
         });
-        let build_time = build_timer.elapsed();
+        build_timer.stop()?;
 
-        let probe_timer = Instant::now();
+        let mut probe_timer = CpuTimer::start();
         thread_pool.scope(|s| {
-            for (((_tid, rel), pay), res) in (0..threads)
+            for (((_tid, rel), pay), res) in (0..probe_threads)
                 .zip(probe_rel_chunks)
                 .zip(probe_pay_chunks)
                 .zip(result_sums.iter_mut())
             {
                 let mut hj_op = hj_builder.build();
                 s.spawn(move |_| {
-                    hj_op
-                        .probe_sum(rel, pay, &mut res.value)
+                    match pay {
+                        Some(pay) => hj_op.probe_sum(rel, pay, &mut res.value),
+                        None => hj_op.probe_count(rel, &mut res.value),
+                    }
+                    .expect("Couldn't execute hash table probe");
+                });
+            }
+        });
+        probe_timer.stop()?;
+
+        // Each thread writes its own slot, thus the total is the sum over all slots
+        let result_sum = result_sums.iter().map(|sum| sum.value).sum();
+
+        Ok(HashJoinPoint {
+            build_ns: Some(build_timer.elapsed_ns()?),
+            probe_ns: Some(probe_timer.elapsed_ns()?),
+            hash_table_malloc_ns: Some(ht_malloc_time.as_nanos() as f64),
+            result_sum: Some(result_sum),
+            ..Default::default()
+        })
+    }
+
+    /// Joins the relations on the CPU with an `N`-byte payload on the build
+    /// side.
+    ///
+    /// The probe materializes the payload of each match. The build phase is
+    /// single-threaded, see `WidePayloadHashTable` for details. The probe phase
+    /// runs on `thread_pool`.
+    pub fn cpu_wide_payload_hash_join<const N: usize>(
+        &self,
+        data: &mut JoinData<T>,
+        build_payloads: &[[u8; N]],
+        probe_threads: usize,
+        thread_pool: &rayon::ThreadPool,
+    ) -> Result<HashJoinPoint>
+    where
+        T: AsPrimitive<u64>,
+    {
+        if probe_threads == 0 {
+            Err(ErrorKind::InvalidArgument(
+                "The probe phase requires at least one thread".to_string(),
+            ))?;
+        }
+        check_thread_pool(thread_pool, probe_threads)?;
+
+        let ht_malloc_timer = Instant::now();
+        let mut hash_table =
+            WidePayloadHashTable::<T, N>::new(self.hash_table_len.next_power_of_two())?;
+        let mut join_result = vec![[0_u8; N]; data.probe_relation.len()];
+        let ht_malloc_time = ht_malloc_timer.elapsed();
+
+        let build_rel_key: &[T] = data.build_relation.key().try_as_slice()?;
+        let probe_rel_key: &[T] = data.probe_relation.key().try_as_slice()?;
+        let probe_chunk_size = (probe_rel_key.len() + probe_threads - 1) / probe_threads;
+
+        let mut build_timer = CpuTimer::start();
+        hash_table.build(build_rel_key, build_payloads)?;
+        build_timer.stop()?;
+
+        let hash_table = &hash_table;
+        let mut matches = vec![CachePadded { value: 0 }; probe_threads];
+        let mut probe_timer = CpuTimer::start();
+        thread_pool.scope(|s| {
+            for ((rel, res), matches) in probe_rel_key
+                .chunks(probe_chunk_size.max(1))
+                .zip(join_result.chunks_mut(probe_chunk_size.max(1)))
+                .zip(matches.iter_mut())
+            {
+                s.spawn(move |_| {
+                    matches.value = hash_table
+                        .probe(rel, res)
                         .expect("Couldn't execute hash table probe");
                 });
             }
         });
-        let probe_time = probe_timer.elapsed();
+        probe_timer.stop()?;
+
+        // Count the matches, as the payloads are not summable
+        let result_sum = matches.iter().map(|m| m.value).sum();
 
         Ok(HashJoinPoint {
-            build_ns: Some(build_time.as_nanos() as f64),
-            probe_ns: Some(probe_time.as_nanos() as f64),
+            build_ns: Some(build_timer.elapsed_ns()?),
+            probe_ns: Some(probe_timer.elapsed_ns()?),
             hash_table_malloc_ns: Some(ht_malloc_time.as_nanos() as f64),
+            result_sum: Some(result_sum),
             ..Default::default()
         })
     }
@@ -604,22 +908,8 @@ where
         stream.synchronize()?;
 
         // Convert Mem<T> into &mut [T]
-        let build_rel_key: &mut [T] = (&mut data.build_relation_key)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let build_rel_pay: &mut [T] = (&mut data.build_relation_payload)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let probe_rel_key: &mut [T] = (&mut data.probe_relation_key)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let probe_rel_pay: &mut [T] = (&mut data.probe_relation_payload)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
+        let (build_rel_key, build_rel_pay) = data.build_relation.try_as_mut_slices()?;
+        let (probe_rel_key, probe_rel_pay) = data.probe_relation.try_as_mut_slices()?;
 
         let cpu_hj_builder = no_partitioning_join::CpuHashJoinBuilder::default()
             .hashing_scheme(self.hashing_scheme)
@@ -640,7 +930,7 @@ where
             .morsel_spec(morsel_spec.clone())
             .build()?;
 
-        let build_timer = Instant::now();
+        let mut build_timer = CpuTimer::start();
         (build_rel_key, build_rel_pay)
             .into_het_morsel_iter(&mut executor)
             .fold(
@@ -659,9 +949,9 @@ where
                     Ok(())
                 },
             )?;
-        let build_time = build_timer.elapsed();
+        build_timer.stop()?;
 
-        let probe_timer = Instant::now();
+        let mut probe_timer = CpuTimer::start();
         (probe_rel_key, probe_rel_pay)
             .into_het_morsel_iter(&mut executor)
             .fold(
@@ -689,19 +979,14 @@ where
                 },
             )?;
 
-        let probe_time = probe_timer.elapsed();
-
-        let mut result_sums_host = vec![0; result_sums.len()];
-        if let Mem::CudaDevMem(results) = result_sums {
-            unsafe { results.async_copy_to(&mut result_sums_host, &stream)? };
-        }
+        probe_timer.stop()?;
 
-        stream.synchronize()?;
-        let _sum: u64 = result_sums_host.iter().sum();
+        // The tree sum adds the per-thread results in a fixed order
+        let _sum = GpuTreeSum::sum(result_sums.as_launchable_slice(), &stream)?;
 
         Ok(HashJoinPoint {
-            build_ns: Some(build_time.as_nanos() as f64),
-            probe_ns: Some(probe_time.as_nanos() as f64),
+            build_ns: Some(build_timer.elapsed_ns()?),
+            probe_ns: Some(probe_timer.elapsed_ns()?),
             hash_table_malloc_ns: Some(ht_malloc_time.as_nanos() as f64),
             ..Default::default()
         })
@@ -746,14 +1031,7 @@ where
         stream.synchronize()?;
 
         // Convert Mem<T> into &mut [T]
-        let probe_rel_key: &mut [T] = (&mut data.probe_relation_key)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
-        let probe_rel_pay: &mut [T] = (&mut data.probe_relation_payload)
-            .try_into()
-            .map_err(|(err, _)| err)
-            .expect("Can't use CUDA device memory on CPU!");
+        let (probe_rel_key, probe_rel_pay) = data.probe_relation.try_as_mut_slices()?;
 
         let gpu_hj_builder = no_partitioning_join::CudaHashJoinBuilder::<T>::default()
             .hashing_scheme(self.hashing_scheme)
@@ -769,12 +1047,12 @@ where
             .morsel_spec(morsel_spec.clone())
             .build()?;
 
-        let build_timer = Instant::now();
+        let mut build_timer = CpuTimer::start();
         let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
         let gpu_hj_op = gpu_hj_builder.build()?;
         gpu_hj_op.build(
-            data.build_relation_key.as_launchable_slice(),
-            data.build_relation_payload.as_launchable_slice(),
+            data.build_relation.key().as_launchable_slice(),
+            data.build_relation.value().as_launchable_slice(),
             &stream,
         )?;
         stream.synchronize()?;
@@ -783,9 +1061,9 @@ where
             cpu_hash_table_mem,
             &gpu_hash_table,
         )?);
-        let build_time = build_timer.elapsed();
+        build_timer.stop()?;
 
-        let probe_timer = Instant::now();
+        let mut probe_timer = CpuTimer::start();
         let cpu_hj_builder = no_partitioning_join::CpuHashJoinBuilder::default()
             .hashing_scheme(self.hashing_scheme)
             .is_selective(self.is_selective)
@@ -816,21 +1094,144 @@ where
                     Ok(())
                 },
             )?;
-        let probe_time = probe_timer.elapsed();
+        probe_timer.stop()?;
 
-        let mut result_sums_host = vec![0; result_sums.len()];
-        if let Mem::CudaDevMem(results) = result_sums {
-            unsafe { results.async_copy_to(&mut result_sums_host, &stream)? };
-        }
-
-        stream.synchronize()?;
-        let _sum: u64 = result_sums_host.iter().sum();
+        // The tree sum adds the per-thread results in a fixed order
+        let _sum = GpuTreeSum::sum(result_sums.as_launchable_slice(), &stream)?;
 
         Ok(HashJoinPoint {
-            build_ns: Some(build_time.as_nanos() as f64),
-            probe_ns: Some(probe_time.as_nanos() as f64),
+            build_ns: Some(build_timer.elapsed_ns()?),
+            probe_ns: Some(probe_timer.elapsed_ns()?),
             hash_table_malloc_ns: Some(ht_malloc_time.as_nanos() as f64),
             ..Default::default()
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{cpu_thread_pool, HashJoinBenchBuilder, JoinRelations};
+    use data_store::join_data::{JoinDataBuilder, JoinDataGenFn};
+    use numa_gpu::runtime::allocator::{Allocator, DerefMemType};
+    use numa_gpu::runtime::cpu_affinity::CpuAffinity;
+    use sql_ops::join::HtEntry;
+    use std::error::Error;
+
+    const BUILD_LEN: usize = 1024;
+    const PROBE_LEN: usize = 16 * 1024;
+    const THREADS: usize = 4;
+
+    /// Returns a generator of relations in which each probe tuple matches
+    /// exactly once and has a payload of one.
+    fn data_gen() -> JoinDataGenFn<i64> {
+        Box::new(
+            |pk_key: &mut [i64], pk_pay: &mut [i64], fk_key: &mut [i64], fk_pay: &mut [i64]| {
+                pk_key
+                    .iter_mut()
+                    .zip(pk_pay.iter_mut())
+                    .enumerate()
+                    .for_each(|(i, (k, p))| {
+                        *k = (i + 1) as i64;
+                        *p = (i + 1) as i64;
+                    });
+                fk_key
+                    .iter_mut()
+                    .zip(fk_pay.iter_mut())
+                    .enumerate()
+                    .for_each(|(i, (k, p))| {
+                        *k = (i % BUILD_LEN + 1) as i64;
+                        *p = 1;
+                    });
+                Ok(())
+            },
+        )
+    }
+
+    fn gen_join_data() -> Result<JoinRelations<i64>, Box<dyn Error>> {
+        let mut data_builder = JoinDataBuilder::default();
+        data_builder.inner_len(BUILD_LEN).outer_len(PROBE_LEN);
+        let (join_data, _, _) = data_builder.build_with_data_gen(data_gen())?;
+
+        Ok(JoinRelations::Separate(join_data))
+    }
+
+    fn gen_combined_join_data() -> Result<JoinRelations<i64>, Box<dyn Error>> {
+        let mut data_builder = JoinDataBuilder::default();
+        data_builder.inner_len(BUILD_LEN).outer_len(PROBE_LEN);
+        let (join_data, _, _) =
+            data_builder.build_combined_with_data_gen(DerefMemType::SysMem, data_gen())?;
+
+        Ok(JoinRelations::Combined(join_data))
+    }
+
+    #[test]
+    fn reused_thread_pool_yields_same_results() -> Result<(), Box<dyn Error>> {
+        let mut join_data = gen_join_data()?;
+        let hjb = HashJoinBenchBuilder::default().build::<i64>(BUILD_LEN)?;
+        let thread_pool = cpu_thread_pool(THREADS, &CpuAffinity::default(), None)?;
+
+        for _ in 0..3 {
+            let ht_alloc = Allocator::deref_mem_alloc_fn::<HtEntry<i64, i64>>(DerefMemType::SysMem);
+            let point =
+                hjb.cpu_hash_join(&mut join_data, THREADS, THREADS, &thread_pool, ht_alloc)?;
+
+            // Each probe tuple matches once and contributes its payload of one
+            assert_eq!(point.result_sum, Some(PROBE_LEN as u64));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn combined_relation_layout_yields_same_results() -> Result<(), Box<dyn Error>> {
+        let mut separate = gen_join_data()?;
+        let mut combined = gen_combined_join_data()?;
+        let thread_pool = cpu_thread_pool(THREADS, &CpuAffinity::default(), None)?;
+
+        for &key_only in &[false, true] {
+            let mut result_sums = Vec::new();
+
+            for join_data in vec![&mut separate, &mut combined] {
+                let hjb = HashJoinBenchBuilder::default()
+                    .key_only(key_only)
+                    .build::<i64>(BUILD_LEN)?;
+                let ht_alloc =
+                    Allocator::deref_mem_alloc_fn::<HtEntry<i64, i64>>(DerefMemType::SysMem);
+                let point =
+                    hjb.cpu_hash_join(join_data, THREADS, THREADS, &thread_pool, ht_alloc)?;
+                result_sums.push(point.result_sum);
+            }
+
+            assert_eq!(result_sums[0], Some(PROBE_LEN as u64));
+            assert_eq!(result_sums[0], result_sums[1]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn too_small_thread_pool_is_rejected() -> Result<(), Box<dyn Error>> {
+        let mut data_builder = JoinDataBuilder::default();
+        let (join_data, _, _) = data_builder.build_with_data_gen(Box::new(|_, _, _, _| Ok(())))?;
+        let mut join_data = JoinRelations::Separate(join_data);
+
+        let hjb = HashJoinBenchBuilder::default().build::<i64>(1)?;
+        let thread_pool = cpu_thread_pool(1, &CpuAffinity::default(), None)?;
+        let ht_alloc = Allocator::deref_mem_alloc_fn::<HtEntry<i64, i64>>(DerefMemType::SysMem);
+
+        assert!(hjb
+            .cpu_hash_join(&mut join_data, 2, 2, &thread_pool, ht_alloc)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn overflowing_hash_table_len_is_rejected() {
+        // The hash table length fits into `usize`, but its size in bytes
+        // overflows
+        let hjb = HashJoinBenchBuilder::default().build::<i64>(1 << (usize::BITS - 2));
+
+        assert!(hjb.is_err());
+    }
+}
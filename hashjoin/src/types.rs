@@ -12,12 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::error::{Error, ErrorKind, Result};
 use numa_gpu::runtime::allocator;
 use numa_gpu::runtime::cuda::CudaTransferStrategy;
 use numa_gpu::runtime::numa::{NodeRatio, PageType};
-use serde_derive::Serialize;
-use serde_repr::Serialize_repr;
+use serde_derive::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 use sql_ops::join::HashingScheme;
+use std::convert::TryFrom;
 use structopt::clap::arg_enum;
 
 arg_enum! {
@@ -34,7 +36,7 @@ arg_enum! {
 }
 
 arg_enum! {
-    #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+    #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
     pub enum ArgDataDistribution {
         Uniform,
         Zipf,
@@ -48,7 +50,7 @@ pub enum DataDistribution {
 }
 
 arg_enum! {
-    #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+    #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
     pub enum ArgMemType {
         System,
         Numa,
@@ -61,7 +63,7 @@ arg_enum! {
 }
 
 arg_enum! {
-    #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+    #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
     pub enum ArgPageType {
         Default,
         Small,
@@ -74,7 +76,7 @@ arg_enum! {
 }
 
 arg_enum! {
-    #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+    #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
     pub enum ArgExecutionMethod {
         Cpu,
         Gpu,
@@ -85,7 +87,7 @@ arg_enum! {
 }
 
 arg_enum! {
-    #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+    #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
     pub enum ArgTransferStrategy {
         PageableCopy,
         PinnedCopy,
@@ -96,15 +98,32 @@ arg_enum! {
 }
 
 arg_enum! {
-    #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+    #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
     pub enum ArgHashingScheme {
         Perfect,
         LinearProbing,
+        Cuckoo,
     }
 }
 
 arg_enum! {
-    #[derive(Copy, Clone, Debug, PartialEq, Serialize_repr)]
+    #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+    pub enum ArgRelationLayout {
+        Separate,
+        Combined,
+    }
+}
+
+arg_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+    pub enum ArgAggregate {
+        Sum,
+        Count,
+    }
+}
+
+arg_enum! {
+    #[derive(Copy, Clone, Debug, Deserialize_repr, PartialEq, Serialize_repr)]
     #[repr(usize)]
     pub enum ArgTupleBytes {
         Bytes8 = 8,
@@ -119,64 +138,69 @@ pub struct ArgMemTypeHelper {
     pub page_type: ArgPageType,
 }
 
-impl From<ArgMemTypeHelper> for allocator::MemType {
-    fn from(
+impl TryFrom<ArgMemTypeHelper> for allocator::MemType {
+    type Error = Error;
+
+    fn try_from(
         ArgMemTypeHelper {
             mem_type,
             node_ratios,
             page_type,
         }: ArgMemTypeHelper,
-    ) -> Self {
-        match mem_type {
+    ) -> Result<Self> {
+        let mem_type = match mem_type {
             ArgMemType::System => allocator::MemType::SysMem,
             ArgMemType::Numa => allocator::MemType::NumaMem {
-                node: node_ratios[0].node,
+                node: first_node(mem_type, &node_ratios)?,
                 page_type: page_type.into(),
             },
             ArgMemType::NumaLazyPinned => allocator::MemType::NumaPinnedMem {
-                node: node_ratios[0].node,
-                page_type: page_type.into(),
-            },
-            ArgMemType::DistributedNuma => allocator::MemType::DistributedNumaMem {
-                nodes: node_ratios,
+                node: first_node(mem_type, &node_ratios)?,
                 page_type: page_type.into(),
             },
+            ArgMemType::DistributedNuma => {
+                first_node(mem_type, &node_ratios)?;
+                allocator::MemType::DistributedNumaMem {
+                    nodes: node_ratios,
+                    page_type: page_type.into(),
+                }
+            }
             ArgMemType::Pinned => allocator::MemType::CudaPinnedMem,
             ArgMemType::Unified => allocator::MemType::CudaUniMem,
             ArgMemType::Device => allocator::MemType::CudaDevMem,
-        }
+        };
+
+        Ok(mem_type)
     }
 }
 
-impl From<ArgMemTypeHelper> for allocator::DerefMemType {
-    fn from(
-        ArgMemTypeHelper {
-            mem_type,
-            node_ratios,
-            page_type,
-        }: ArgMemTypeHelper,
-    ) -> Self {
-        match mem_type {
-            ArgMemType::System => allocator::DerefMemType::SysMem,
-            ArgMemType::Numa => allocator::DerefMemType::NumaMem {
-                node: node_ratios[0].node,
-                page_type: page_type.into(),
-            },
-            ArgMemType::NumaLazyPinned => allocator::DerefMemType::NumaPinnedMem {
-                node: node_ratios[0].node,
-                page_type: page_type.into(),
-            },
-            ArgMemType::DistributedNuma => allocator::DerefMemType::DistributedNumaMem {
-                nodes: node_ratios,
-                page_type: page_type.into(),
-            },
-            ArgMemType::Pinned => allocator::DerefMemType::CudaPinnedMem,
-            ArgMemType::Unified => allocator::DerefMemType::CudaUniMem,
-            ArgMemType::Device => panic!("Error: Device memory not supported in this context!"),
-        }
+/// Converts the memory type into a type that the CPU can dereference.
+///
+/// Fails for device memory, because the CPU cannot access it.
+impl TryFrom<ArgMemTypeHelper> for allocator::DerefMemType {
+    type Error = Error;
+
+    fn try_from(helper: ArgMemTypeHelper) -> Result<Self> {
+        let arg_mem_type = helper.mem_type;
+        let mem_type = allocator::MemType::try_from(helper)?;
+
+        allocator::DerefMemType::try_from(mem_type).map_err(|_| {
+            ErrorKind::InvalidArgument(format!(
+                "{:?} memory isn't supported here, because the CPU must be able to access it",
+                arg_mem_type
+            ))
+            .into()
+        })
     }
 }
 
+/// Returns the first NUMA node, or an error if none is specified.
+fn first_node(mem_type: ArgMemType, node_ratios: &[NodeRatio]) -> Result<u16> {
+    node_ratios.first().map(|ratio| ratio.node).ok_or_else(|| {
+        ErrorKind::InvalidArgument(format!("{:?} memory requires a NUMA node", mem_type)).into()
+    })
+}
+
 impl From<ArgPageType> for PageType {
     fn from(arg_page_type: ArgPageType) -> PageType {
         match arg_page_type {
@@ -210,6 +234,80 @@ impl From<ArgHashingScheme> for HashingScheme {
         match ahs {
             ArgHashingScheme::Perfect => HashingScheme::Perfect,
             ArgHashingScheme::LinearProbing => HashingScheme::LinearProbing,
+            ArgHashingScheme::Cuckoo => HashingScheme::Cuckoo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_rational::Ratio;
+    use std::convert::TryInto;
+
+    fn helper(mem_type: ArgMemType, nodes: &[u16]) -> ArgMemTypeHelper {
+        ArgMemTypeHelper {
+            mem_type,
+            node_ratios: nodes
+                .iter()
+                .map(|&node| NodeRatio {
+                    node,
+                    ratio: Ratio::new(1, nodes.len()),
+                })
+                .collect(),
+            page_type: ArgPageType::Default,
+        }
+    }
+
+    #[test]
+    fn all_mem_types_convert_with_a_node() {
+        for &mem_type in &[
+            ArgMemType::System,
+            ArgMemType::Numa,
+            ArgMemType::NumaLazyPinned,
+            ArgMemType::DistributedNuma,
+            ArgMemType::Pinned,
+            ArgMemType::Unified,
+            ArgMemType::Device,
+        ] {
+            let result: Result<allocator::MemType> = helper(mem_type, &[0, 1]).try_into();
+            assert!(result.is_ok(), "Failed to convert {:?}", mem_type);
+        }
+    }
+
+    #[test]
+    fn host_mem_types_convert_to_deref_mem_types() {
+        for &mem_type in &[
+            ArgMemType::System,
+            ArgMemType::Numa,
+            ArgMemType::NumaLazyPinned,
+            ArgMemType::DistributedNuma,
+            ArgMemType::Pinned,
+            ArgMemType::Unified,
+        ] {
+            let result: Result<allocator::DerefMemType> = helper(mem_type, &[0]).try_into();
+            assert!(result.is_ok(), "Failed to convert {:?}", mem_type);
+        }
+    }
+
+    #[test]
+    fn device_mem_type_is_not_a_deref_mem_type() {
+        let result: Result<allocator::DerefMemType> = helper(ArgMemType::Device, &[0]).try_into();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn numa_mem_types_require_a_node() {
+        for &mem_type in &[
+            ArgMemType::Numa,
+            ArgMemType::NumaLazyPinned,
+            ArgMemType::DistributedNuma,
+        ] {
+            let result: Result<allocator::MemType> = helper(mem_type, &[]).try_into();
+            assert!(result.is_err(), "Expected an error for {:?}", mem_type);
+
+            let result: Result<allocator::DerefMemType> = helper(mem_type, &[]).try_into();
+            assert!(result.is_err(), "Expected an error for {:?}", mem_type);
         }
     }
 }
@@ -24,12 +24,14 @@
 // Disable prefeching
 #define PPC_TUNE_DSCR 1ULL
 
-extern "C" uint64_t cpu_stride(uint32_t *data, uint32_t iterations) {
+// Chases pointers through `data` and measures the average ns per access
+template <typename T>
+uint64_t cpu_stride(T *data, uint32_t iterations) {
 #if defined(__powerpc64__)
   __mtspr(PPC_DSCR, PPC_TUNE_DSCR);
 #endif
 
-  uint32_t pos = 0;
+  T pos = 0;
 
   // Warm-up
   for (uint32_t i = 0; i < iterations; ++i) {
@@ -60,3 +62,11 @@ extern "C" uint64_t cpu_stride(uint32_t *data, uint32_t iterations) {
 
   return nanos;
 }
+
+extern "C" uint64_t cpu_stride_u32(uint32_t *data, uint32_t iterations) {
+  return cpu_stride(data, iterations);
+}
+
+extern "C" uint64_t cpu_stride_u64(uint64_t *data, uint32_t iterations) {
+  return cpu_stride(data, iterations);
+}
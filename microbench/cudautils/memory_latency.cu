@@ -17,13 +17,14 @@
 // X mod Y, assuming that Y is a power of 2
 #define FAST_MODULO(X, Y) (X & (Y - 1))
 
-__global__ void gpu_stride(uint32_t *data, uint32_t iterations,
-                           uint64_t *cycles) {
+// Chases pointers through `data` and measures the average cycles per access
+template <typename T>
+__device__ void gpu_stride(T *data, uint32_t iterations, uint64_t *cycles) {
   uint64_t sum = 0;
   uint64_t start = 0;
   uint64_t stop = 0;
-  uint32_t pos = 0;
-  uint32_t dependency = 0;  // Prevent compiler from optimizing away the loop
+  T pos = 0;
+  T dependency = 0;  // Prevent compiler from optimizing away the loop
 
   // Warm-up the cache
   for (uint32_t i = 0; i < iterations; ++i) {
@@ -55,3 +56,13 @@ __global__ void gpu_stride(uint32_t *data, uint32_t iterations,
     data[1] = dependency;
   }
 }
+
+extern "C" __global__ void gpu_stride_u32(uint32_t *data, uint32_t iterations,
+                                          uint64_t *cycles) {
+  gpu_stride(data, iterations, cycles);
+}
+
+extern "C" __global__ void gpu_stride_u64(uint64_t *data, uint32_t iterations,
+                                          uint64_t *cycles) {
+  gpu_stride(data, iterations, cycles);
+}
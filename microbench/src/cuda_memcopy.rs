@@ -21,6 +21,7 @@ use numa_gpu::runtime::memory::{
     LaunchableMem, LaunchableMutSlice, LaunchableSlice, Mem, MemLock, PageLock,
 };
 use numa_gpu::runtime::numa::NumaMemory;
+use numa_gpu::runtime::timer::millis_to_nanos;
 use rustacuda::context::{Context, ContextFlags};
 use rustacuda::device::Device;
 use rustacuda::event::{Event, EventFlags};
@@ -108,7 +109,7 @@ impl CudaMemcopy {
                 malloc_ns,
                 pin_ns,
                 mlock_ns,
-                copy_ns: copy_ms.map(|t| (t as f64 * 10.0_f64.powf(6.0)) as u64),
+                copy_ns: copy_ms.map(|t| millis_to_nanos(t) as u64),
                 ..template.clone()
             }
         })
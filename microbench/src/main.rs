@@ -21,7 +21,7 @@ mod tlb_latency;
 mod types;
 
 use crate::cuda_memcopy::CudaMemcopy;
-use crate::error::Result;
+use crate::error::{ErrorKind, Result};
 use crate::memory_bandwidth::MemoryBandwidth;
 use crate::memory_latency::MemoryLatency;
 use crate::numa_memcopy::NumaMemcopy;
@@ -281,9 +281,10 @@ struct CmdLatency {
     )]
     mem_type: ArgMemType,
 
-    #[structopt(long = "mem-location", default_value = "0")]
-    /// Allocate memory on CPU or GPU (See numactl -H and CUDA device list)
-    mem_location: u16,
+    #[structopt(long = "mem-location", default_value = "0", require_delimiter = true)]
+    /// Allocate memory on CPU or GPU (See numactl -H and CUDA device list), a list sweeps the
+    /// locations in one run
+    mem_location: Vec<u16>,
 
     /// Page type with with to allocate memory
     #[structopt(
@@ -313,6 +314,19 @@ struct CmdLatency {
     #[structopt(short = "r", long = "repeat", default_value = "100")]
     /// Number of times to repeat benchmark
     repeat: u32,
+
+    #[structopt(long = "with-gpu")]
+    /// Additionally measure the GPU with this ID on the same buffer (requires CPU device type)
+    with_gpu: Option<u32>,
+
+    #[structopt(long = "element-bytes", default_value = "4", possible_values = &["4", "8"])]
+    /// Width of the stride elements (Bytes), wider elements index larger buffers
+    element_bytes: usize,
+
+    #[structopt(long = "loaded")]
+    /// Measure the latency under load by chasing pointers on all cores of the CPU node
+    /// simultaneously (requires CPU device type)
+    loaded: bool,
 }
 
 #[derive(StructOpt)]
@@ -512,25 +526,61 @@ fn main() -> Result<()> {
             );
         }
         Command::Latency(ref lat) => {
-            let device = match lat.device_type {
+            let mut devices = vec![match lat.device_type {
                 ArgDeviceType::CPU => DeviceId::Cpu(lat.device_id),
                 ArgDeviceType::GPU => DeviceId::Gpu(lat.device_id.into()),
-            };
-
-            let mem_type_helper = ArgMemTypeHelper {
-                mem_type: lat.mem_type,
-                node: lat.mem_location,
-                page_type: lat.page_type,
-            };
-
-            MemoryLatency::measure(
-                device,
-                mem_type_helper.into(),
-                (lat.range_lower * kb)..=(lat.range_upper * kb),
-                (lat.stride_lower)..=(lat.stride_upper),
-                lat.repeat,
-                csv_file.as_mut(),
-            );
+            }];
+            if let Some(gpu_id) = lat.with_gpu {
+                if let ArgDeviceType::GPU = lat.device_type {
+                    Err(ErrorKind::InvalidArgument(
+                        "A combined run requires the CPU device type".to_string(),
+                    ))?;
+                }
+                devices.push(DeviceId::Gpu(gpu_id));
+            }
+
+            if let (true, ArgDeviceType::GPU) = (lat.loaded, lat.device_type) {
+                Err(ErrorKind::InvalidArgument(
+                    "A loaded run requires the CPU device type".to_string(),
+                ))?;
+            }
+
+            let mem_types: Vec<_> = lat
+                .mem_location
+                .iter()
+                .map(|&node| {
+                    ArgMemTypeHelper {
+                        mem_type: lat.mem_type,
+                        node,
+                        page_type: lat.page_type,
+                    }
+                    .into()
+                })
+                .collect();
+
+            let range = (lat.range_lower * kb)..=(lat.range_upper * kb);
+            let stride = (lat.stride_lower)..=(lat.stride_upper);
+            match lat.element_bytes {
+                4 => MemoryLatency::sweep::<u32, _>(
+                    &devices,
+                    &mem_types,
+                    range,
+                    stride,
+                    lat.repeat,
+                    lat.loaded,
+                    csv_file.as_mut(),
+                ),
+                8 => MemoryLatency::sweep::<u64, _>(
+                    &devices,
+                    &mem_types,
+                    range,
+                    stride,
+                    lat.repeat,
+                    lat.loaded,
+                    csv_file.as_mut(),
+                ),
+                _ => unreachable!(),
+            }
         }
         Command::TlbLatency(ref tlb) => {
             let mem_type_helper = ArgMemTypeHelper {
@@ -17,6 +17,7 @@ use super::{Benchmark, ItemBytes, MemoryOperation, TileSize};
 use crate::types::Cycles;
 use numa_gpu::runtime::memory::Mem;
 use numa_gpu::runtime::nvml::ThrottleReasons;
+use numa_gpu::runtime::timer::millis_to_nanos;
 use nvml_wrapper::device::Device as NvmlDevice;
 use nvml_wrapper::enum_wrappers::device::Clock;
 use rustacuda::context::CurrentContext;
@@ -307,7 +308,7 @@ impl GpuMemoryBandwidth {
         let ms = timer_end
             .elapsed_time_f32(&timer_begin)
             .expect("Couldn't get elapsed time");
-        let ns = ms as f64 * 10.0_f64.powf(6.0);
+        let ns = millis_to_nanos(ms);
 
         let mut memory_accesses = 0;
         memory_accesses_device
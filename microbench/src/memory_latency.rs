@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use numa_gpu::runtime::allocator::{Allocator, MemType};
+use numa_gpu::runtime::cpu_affinity::CpuAffinity;
 use numa_gpu::runtime::hw_info::NvidiaDriverInfo;
 use numa_gpu::runtime::memory::{Mem, MemLock};
 use numa_gpu::runtime::nvml::ThrottleReasons;
@@ -24,7 +25,7 @@ use nvml_wrapper::{enum_wrappers::device::Clock, NVML};
 #[cfg(target_arch = "aarch64")]
 use numa_gpu::runtime::hw_info::CudaDeviceInfo;
 
-use rustacuda::memory::DeviceBox;
+use rustacuda::memory::{DeviceBox, DeviceCopy};
 use rustacuda::prelude::*;
 use rustacuda::stream::{Stream, StreamFlags};
 use rustacuda::{launch, CudaFlags};
@@ -35,35 +36,243 @@ use std::convert::TryInto;
 use std::ffi::CString;
 use std::mem::size_of;
 use std::ops::RangeInclusive;
+use std::sync::Barrier;
 
 use crate::types::*;
 use crate::ArgPageType;
 
 extern "C" {
-    pub fn cpu_stride(data: *const u32, iterations: u32) -> u64;
+    fn cpu_stride_u32(data: *const u32, iterations: u32) -> u64;
+    fn cpu_stride_u64(data: *const u64, iterations: u32) -> u64;
+}
+
+/// An element type of the stride buffer.
+///
+/// Each element type has its own CPU and GPU stride kernels. The element
+/// width determines the largest buffer that the kernels can index.
+pub trait StrideElement: DeviceCopy + Into<u64> + Sync {
+    /// The name of the GPU stride kernel.
+    const GPU_KERNEL: &'static str;
+
+    /// Runs the CPU stride kernel and returns the nanoseconds per access.
+    unsafe fn cpu_stride(data: *const Self, iterations: u32) -> u64;
+
+    /// Converts a buffer index into an element.
+    fn from_index(index: usize) -> Self;
+}
+
+impl StrideElement for u32 {
+    const GPU_KERNEL: &'static str = "gpu_stride_u32";
+
+    unsafe fn cpu_stride(data: *const Self, iterations: u32) -> u64 {
+        cpu_stride_u32(data, iterations)
+    }
+
+    fn from_index(index: usize) -> Self {
+        index as u32
+    }
+}
+
+impl StrideElement for u64 {
+    const GPU_KERNEL: &'static str = "gpu_stride_u64";
+
+    unsafe fn cpu_stride(data: *const Self, iterations: u32) -> u64 {
+        cpu_stride_u64(data, iterations)
+    }
+
+    fn from_index(index: usize) -> Self {
+        index as u64
+    }
+}
+
+/// The device that runs the stride kernel.
+pub enum StrideDevice<'m> {
+    Cpu,
+    Gpu {
+        module: &'m Module,
+        clock_rate_mhz: u32,
+    },
+}
+
+/// Measures the average latency of chasing the strides in `mem`.
+///
+/// The strides must have been written by `write_strides`. Returns the cycles
+/// and the nanoseconds per access. The CPU kernel measures only nanoseconds,
+/// thus it doesn't return cycles.
+pub fn stride_latency<T: StrideElement>(
+    device: &StrideDevice<'_>,
+    mem: &Mem<T>,
+    iterations: u32,
+) -> (Option<u64>, u64) {
+    match *device {
+        StrideDevice::Cpu => {
+            if let Mem::CudaDevMem(_) = mem {
+                panic!("The CPU cannot access GPU device memory");
+            }
+            let ns = unsafe { T::cpu_stride(mem.as_ptr(), iterations) };
+
+            (None, ns)
+        }
+        StrideDevice::Gpu {
+            module,
+            clock_rate_mhz,
+        } => {
+            let mut dev_cycles = DeviceBox::new(&0_u64).expect("Couldn't allocate device memory");
+            let stream =
+                Stream::new(StreamFlags::NON_BLOCKING, None).expect("Failed to create CUDA stream");
+
+            let name = CString::new(T::GPU_KERNEL).expect("Failed to convert kernel name");
+            let function = module
+                .get_function(&name)
+                .unwrap_or_else(|_| panic!("Failed to load the GPU function: {}", T::GPU_KERNEL));
+
+            unsafe {
+                launch!(function<<<1, 1, 0, stream>>>(
+                    mem.as_launchable_ptr(),
+                    iterations,
+                    dev_cycles.as_device_ptr()
+                ))
+                .expect("Failed to launch gpu_stride kernel");
+            };
+            stream
+                .synchronize()
+                .expect("Failed to synchronize CUDA stream");
+
+            let mut cycles = 0;
+            dev_cycles
+                .copy_to(&mut cycles)
+                .expect("Couldn't copy result data from device");
+            let ns: u64 = cycles * 1000 / (clock_rate_mhz as u64);
+
+            (Some(cycles), ns)
+        }
+    }
+}
+
+/// Measures the latency of chasing the strides in `mems` on all threads of the
+/// pool simultaneously, i.e., the latency under load.
+///
+/// Each chaser follows the chain in its own buffer. Thus, the chasers don't
+/// hit the cache lines that another chaser loaded. Returns the average and
+/// the worst nanoseconds per access over all chasers.
+fn loaded_stride_latency<T: StrideElement>(
+    thread_pool: &rayon::ThreadPool,
+    mems: &[&Mem<T>],
+    iterations: u32,
+) -> (u64, u64) {
+    let chasers = thread_pool.current_num_threads();
+    assert_eq!(chasers, mems.len(), "Each pointer chaser requires a buffer");
+
+    let buffers: Vec<&[T]> = mems
+        .iter()
+        .map(|&mem| match mem.try_into() {
+            Ok(data) => data,
+            Err(_) => panic!("The CPU cannot access GPU device memory"),
+        })
+        .collect();
+
+    // A chaser blocks its thread until all chasers are running. Thus, each
+    // thread runs exactly one chaser, and the chasers overlap.
+    let barrier = Barrier::new(chasers);
+    let mut latencies = vec![None; chasers];
+
+    thread_pool.scope(|s| {
+        for (latency, &data) in latencies.iter_mut().zip(buffers.iter()) {
+            let barrier = &barrier;
+            s.spawn(move |_| {
+                barrier.wait();
+                *latency = Some(unsafe { T::cpu_stride(data.as_ptr(), iterations) });
+            });
+        }
+    });
+
+    let latencies: Vec<u64> = latencies
+        .into_iter()
+        .map(|latency| latency.expect("A pointer chaser didn't complete"))
+        .collect();
+    let avg_ns = latencies.iter().sum::<u64>() / chasers as u64;
+    let max_ns = *latencies.iter().max().expect("Failed due to empty vector");
+
+    (avg_ns, max_ns)
 }
 
 pub struct MemoryLatency;
 
 impl MemoryLatency {
-    pub fn measure<W>(
-        device_id: DeviceId,
+    /// Measures the memory latency of one or more devices at a single memory
+    /// location.
+    ///
+    /// See `sweep` for details.
+    #[allow(dead_code)]
+    pub fn measure<T, W>(
+        device_ids: &[DeviceId],
         mem_type: MemType,
         range: RangeInclusive<usize>,
         stride: RangeInclusive<usize>,
         repeat: u32,
+        loaded: bool,
+        writer: Option<&mut W>,
+    ) where
+        T: StrideElement,
+        W: std::io::Write,
+    {
+        Self::sweep::<T, W>(
+            device_ids,
+            &[mem_type],
+            range,
+            stride,
+            repeat,
+            loaded,
+            writer,
+        )
+    }
+
+    /// Measures the memory latency of one or more devices for each memory
+    /// location in `mem_types`.
+    ///
+    /// For each location, all devices access the same buffer, which is
+    /// allocated only once. Thus, a combined run of a CPU and a GPU compares
+    /// the latencies to literally the same pages, and isn't affected by
+    /// variance between allocations. The devices are measured one after the
+    /// other, and the rows are tagged with the device that produced them.
+    ///
+    /// The locations are swept in a single process, e.g., to measure a NUMA
+    /// latency matrix. Every location is measured with the same CUDA context
+    /// and methodology, and the rows are tagged with the memory location.
+    ///
+    /// If `loaded` is set, the CPU runs one pointer chaser on each core of its
+    /// NUMA node simultaneously. The first chaser follows the shared buffer,
+    /// and each other chaser gets its own buffer at the same location. The
+    /// rows then report the average latency as `ns`, and the worst latency of
+    /// a chaser as `max_ns`.
+    pub fn sweep<T, W>(
+        device_ids: &[DeviceId],
+        mem_types: &[MemType],
+        range: RangeInclusive<usize>,
+        stride: RangeInclusive<usize>,
+        repeat: u32,
+        loaded: bool,
         writer: Option<&mut W>,
     ) where
+        T: StrideElement,
         W: std::io::Write,
     {
-        if let (MemType::CudaDevMem, DeviceId::Cpu(_)) = (mem_type.clone(), &device_id) {
-            panic!("Cannot run benchmark on CPU with the given type of memory. Did you specify GPU device memory?");
+        for (mem_type, device_id) in mem_types
+            .iter()
+            .flat_map(|mem_type| std::iter::repeat(mem_type).zip(device_ids.iter()))
+        {
+            if let (MemType::CudaDevMem, DeviceId::Cpu(_)) = (mem_type, device_id) {
+                panic!("Cannot run benchmark on CPU with the given type of memory. Did you specify GPU device memory?");
+            }
         }
 
-        let gpu_id = match device_id {
-            DeviceId::Gpu(id) => id,
-            _ => 0,
-        };
+        let gpu_id = device_ids
+            .iter()
+            .find_map(|device_id| match device_id {
+                DeviceId::Gpu(id) => Some(*id),
+                _ => None,
+            })
+            .unwrap_or(0);
 
         let (_context, device) = match rustacuda::init(CudaFlags::empty()) {
             Ok(_) => {
@@ -84,84 +293,104 @@ impl MemoryLatency {
         numa::set_strict(true);
 
         let buffer_bytes = *range.end() + 1;
-        let element_bytes = size_of::<u32>();
+        let element_bytes = size_of::<T>();
         let buffer_len = buffer_bytes / element_bytes;
 
         let hostname = hostname::get()
             .expect("Couldn't get hostname")
             .into_string()
             .expect("Couldn't convert hostname into UTF-8 string");
-        let device_type = match device_id {
-            DeviceId::Cpu(_) => "CPU",
-            DeviceId::Gpu(_) => "GPU",
-        };
-        let cpu_node = match device_id {
-            DeviceId::Cpu(node) => Some(node),
-            _ => None,
-        };
-        let device_codename = match device_id {
-            DeviceId::Cpu(_) => Some(hw_info::cpu_codename().expect("Couldn't get CPU codename")),
-            DeviceId::Gpu(_) => device.map(|d| d.name().expect("Couldn't get device codename")),
-        };
 
-        let mem_type_description: MemTypeDescription = (&mem_type).into();
-
-        let template = DataPoint {
-            hostname: Some(hostname),
-            device_type: Some(device_type.to_string()),
-            device_codename,
-            cpu_node,
-            memory_node: mem_type_description.location,
-            memory_type: Some(mem_type_description.bare_mem_type),
-            page_type: Some(mem_type_description.page_type),
-            ..Default::default()
-        };
+        let mut latencies = Vec::new();
+        for mem_type in mem_types {
+            let mem_type_description: MemTypeDescription = mem_type.into();
 
-        let mnt = Measurement::new(range, stride, template);
+            let mut mem = Allocator::alloc_mem::<T>(mem_type.clone(), buffer_len);
+            mem.mlock().expect("Failed to mlock the memory");
 
-        let mut mem = Allocator::alloc_mem(mem_type, buffer_len);
-        mem.mlock().expect("Failed to mlock the memory");
+            // Initialize the memory with some non-zero data
+            if let Ok(slice) = (&mut mem).try_into() {
+                let _: &mut [_] = slice;
+                slice
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, x)| *x = T::from_index(i));
+            }
 
-        // Initialize the memory with some non-zero data
-        if let Ok(slice) = (&mut mem).try_into() {
-            let _: &mut [_] = slice;
-            slice.iter_mut().by_ref().zip(0..).for_each(|(x, i)| *x = i);
-        }
+            for &device_id in device_ids {
+                let device_type = match device_id {
+                    DeviceId::Cpu(_) => "CPU",
+                    DeviceId::Gpu(_) => "GPU",
+                };
+                let cpu_node = match device_id {
+                    DeviceId::Cpu(node) => Some(node),
+                    _ => None,
+                };
+                let device_codename = match device_id {
+                    DeviceId::Cpu(_) => {
+                        Some(hw_info::cpu_codename().expect("Couldn't get CPU codename"))
+                    }
+                    DeviceId::Gpu(_) => {
+                        device.map(|d| d.name().expect("Couldn't get device codename"))
+                    }
+                };
 
-        let latencies = match device_id {
-            DeviceId::Cpu(did) => {
-                let ml = CpuMemoryLatency::new(did);
-                mnt.measure(
-                    mem,
-                    ml,
-                    CpuMemoryLatency::prepare,
-                    CpuMemoryLatency::run,
-                    repeat,
-                )
-            }
-            DeviceId::Gpu(did) => {
-                let device = device.expect("No device found");
-                if let Ok(local_cpu_node) = device.numa_memory_affinity() {
-                    linux_wrapper::numa_run_on_node(local_cpu_node).expect(&format!(
-                        "Failed to bind main thread to CPU node {}",
-                        local_cpu_node
-                    ));
-                } else {
-                    eprintln!(
-                        "Warning: Couldn't bind main thread to the CPU closest to GPU {}. This may
-                        cause additional latency in measurements.",
-                        did
-                    );
-                }
+                let template = DataPoint {
+                    hostname: Some(hostname.clone()),
+                    device_type: Some(device_type.to_string()),
+                    device_codename,
+                    cpu_node,
+                    memory_node: mem_type_description.location,
+                    memory_type: Some(mem_type_description.bare_mem_type),
+                    page_type: Some(mem_type_description.page_type),
+                    element_bytes,
+                    ..Default::default()
+                };
 
-                let ml = GpuMemoryLatency::new(did);
-                let prepare = match mem {
-                    Mem::CudaUniMem(_) => GpuMemoryLatency::prepare_prefetch,
-                    _ => GpuMemoryLatency::prepare,
+                let device_latencies = match device_id {
+                    DeviceId::Cpu(did) => {
+                        let ml = CpuMemoryLatency::new(did, loaded, mem_type, buffer_len);
+                        let template = DataPoint {
+                            chasers: ml.chasers(),
+                            ..template
+                        };
+                        let mnt = Measurement::new(range.clone(), stride.clone(), template);
+                        mnt.measure(
+                            &mut mem,
+                            ml,
+                            CpuMemoryLatency::prepare,
+                            CpuMemoryLatency::run,
+                            repeat,
+                        )
+                    }
+                    DeviceId::Gpu(did) => {
+                        let device = device.expect("No device found");
+                        if let Ok(local_cpu_node) = device.numa_memory_affinity() {
+                            linux_wrapper::numa_run_on_node(local_cpu_node).expect(&format!(
+                                "Failed to bind main thread to CPU node {}",
+                                local_cpu_node
+                            ));
+                        } else {
+                            eprintln!(
+                                "Warning: Couldn't bind main thread to the CPU closest to GPU {}. This may
+                                cause additional latency in measurements.",
+                                did
+                            );
+                        }
+
+                        let ml = GpuMemoryLatency::new(did);
+                        let mnt = Measurement::new(range.clone(), stride.clone(), template);
+                        let prepare = match mem {
+                            Mem::CudaUniMem(_) => GpuMemoryLatency::prepare_prefetch::<T>,
+                            _ => GpuMemoryLatency::prepare::<T>,
+                        };
+                        mnt.measure(&mut mem, ml, prepare, GpuMemoryLatency::run, repeat)
+                    }
                 };
-                mnt.measure(mem, ml, prepare, GpuMemoryLatency::run, repeat)
+
+                latencies.extend(device_latencies);
             }
-        };
+        }
 
         if let Some(w) = writer {
             let mut csv = csv::Writer::from_writer(w);
@@ -182,14 +411,17 @@ struct DataPoint {
     pub memory_type: Option<BareMemType>,
     pub memory_node: Option<u16>,
     pub page_type: Option<ArgPageType>,
+    pub element_bytes: usize,
+    pub chasers: Option<usize>,
     pub warm_up: bool,
     pub range_bytes: usize,
     pub stride_bytes: usize,
     pub iterations: u32,
     pub throttle_reasons: Option<String>,
     pub clock_rate_mhz: Option<u32>,
-    pub cycles: u64,
+    pub cycles: Option<u64>,
     pub ns: u64,
+    pub max_ns: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -211,7 +443,15 @@ struct GpuMemoryLatency {
 }
 
 #[derive(Debug)]
-struct CpuMemoryLatency;
+struct CpuMemoryLatency<T: DeviceCopy> {
+    /// Runs one pointer chaser per core of the NUMA node, if the latency is
+    /// measured under load.
+    thread_pool: Option<rayon::ThreadPool>,
+
+    /// The buffers of all chasers except the first, which chases the shared
+    /// buffer.
+    chaser_mems: Vec<Mem<T>>,
+}
 
 #[derive(Debug)]
 struct MeasurementParameters {
@@ -232,21 +472,22 @@ impl Measurement {
         }
     }
 
-    fn measure<P, R, S>(
+    fn measure<T, P, R, S>(
         &self,
-        mut mem: Mem<u32>,
+        mem: &mut Mem<T>,
         mut state: S,
         prepare: P,
         run: R,
         repeat: u32,
     ) -> Vec<DataPoint>
     where
-        P: Fn(&mut S, &mut Mem<u32>, &MeasurementParameters),
+        T: DeviceCopy,
+        P: Fn(&mut S, &mut Mem<T>, &MeasurementParameters),
         R: Fn(
             &mut S,
-            &Mem<u32>,
+            &Mem<T>,
             &MeasurementParameters,
-        ) -> (u32, Option<ThrottleReasons>, u64, u64),
+        ) -> (u32, Option<ThrottleReasons>, Option<u64>, u64, Option<u64>),
     {
         let stride_iter = self.stride.clone();
         let range_iter = self.range.clone();
@@ -268,11 +509,12 @@ impl Measurement {
                 let mp = MeasurementParameters { stride, iterations };
 
                 if i == 0 {
-                    prepare(&mut state, &mut mem, &mp);
+                    prepare(&mut state, mem, &mp);
                 }
 
                 for _ in 0..repeat + 1 {
-                    let (clock_rate_mhz, throttle_reasons, cycles, ns) = run(&mut state, &mem, &mp);
+                    let (clock_rate_mhz, throttle_reasons, cycles, ns, max_ns) =
+                        run(&mut state, mem, &mp);
 
                     data_points.push(DataPoint {
                         warm_up,
@@ -283,6 +525,7 @@ impl Measurement {
                         clock_rate_mhz: Some(clock_rate_mhz),
                         cycles,
                         ns,
+                        max_ns,
                         ..self.template.clone()
                     });
                     warm_up = false;
@@ -324,14 +567,14 @@ impl GpuMemoryLatency {
         module
     }
 
-    fn prepare(_state: &mut Self, mem: &mut Mem<u32>, mp: &MeasurementParameters) {
+    fn prepare<T: StrideElement>(_state: &mut Self, mem: &mut Mem<T>, mp: &MeasurementParameters) {
         let len = mem.len();
         match mem.try_into() {
             Ok(slice) => {
                 write_strides(slice, mp.stride);
             }
             Err((_, dev_slice)) => {
-                let mut host_mem = vec![0; len];
+                let mut host_mem = vec![T::from_index(0); len];
                 write_strides(&mut host_mem, mp.stride);
                 dev_slice
                     .copy_from(&host_mem)
@@ -340,14 +583,18 @@ impl GpuMemoryLatency {
         }
     }
 
-    fn prepare_prefetch(_state: &mut Self, mem: &mut Mem<u32>, mp: &MeasurementParameters) {
+    fn prepare_prefetch<T: StrideElement>(
+        _state: &mut Self,
+        mem: &mut Mem<T>,
+        mp: &MeasurementParameters,
+    ) {
         let len = mem.len();
         match mem.try_into() {
             Ok(slice) => {
                 write_strides(slice, mp.stride);
             }
             Err((_, dev_slice)) => {
-                let mut host_mem = vec![0; len];
+                let mut host_mem = vec![T::from_index(0); len];
                 write_strides(&mut host_mem, mp.stride);
                 dev_slice
                     .copy_from(&host_mem)
@@ -366,11 +613,11 @@ impl GpuMemoryLatency {
         }
     }
 
-    fn run(
+    fn run<T: StrideElement>(
         _state: &mut Self,
-        mem: &Mem<u32>,
+        mem: &Mem<T>,
         mp: &MeasurementParameters,
-    ) -> (u32, Option<ThrottleReasons>, u64, u64) {
+    ) -> (u32, Option<ThrottleReasons>, Option<u64>, u64, Option<u64>) {
         // Get current GPU clock rate
         #[cfg(not(target_arch = "aarch64"))]
         let clock_rate_mhz = _state
@@ -387,22 +634,11 @@ impl GpuMemoryLatency {
             .expect("Couldn't get clock rate");
 
         // Launch GPU code
-        let mut dev_cycles = DeviceBox::new(&0_u64).expect("Couldn't allocate device memory");
-        let stream =
-            Stream::new(StreamFlags::NON_BLOCKING, None).expect("Failed to create CUDA stream");
-
-        unsafe {
-            let module = &_state.module;
-            launch!(module.gpu_stride<<<1, 1, 0, stream>>>(
-                mem.as_launchable_ptr(),
-                mp.iterations,
-                dev_cycles.as_device_ptr()
-            ))
-            .expect("Failed to launch gpu_stride kernel");
+        let device = StrideDevice::Gpu {
+            module: &_state.module,
+            clock_rate_mhz,
         };
-        stream
-            .synchronize()
-            .expect("Failed to synchronize CUDA stream");
+        let (cycles, ns) = stride_latency(&device, mem, mp.iterations);
 
         // Check if GPU is running in a throttled state
         #[cfg(not(target_arch = "aarch64"))]
@@ -419,58 +655,103 @@ impl GpuMemoryLatency {
         #[cfg(target_arch = "aarch64")]
         let throttle_reasons = None;
 
-        let mut cycles = 0;
-        dev_cycles
-            .copy_to(&mut cycles)
-            .expect("Couldn't copy result data from device");
-        let ns: u64 = cycles * 1000 / (clock_rate_mhz as u64);
-
-        (clock_rate_mhz, throttle_reasons, cycles, ns)
+        (clock_rate_mhz, throttle_reasons, cycles, ns, None)
     }
 }
 
-impl CpuMemoryLatency {
-    fn new(device_id: u16) -> Self {
+impl<T: StrideElement> CpuMemoryLatency<T> {
+    fn new(device_id: u16, loaded: bool, mem_type: &MemType, len: usize) -> Self {
         numa::run_on_node(device_id).expect("Couldn't set NUMA node");
 
-        Self
+        let thread_pool = if loaded {
+            let cpu_affinity = CpuAffinity::from_numa_node(device_id)
+                .expect("Couldn't get the CPU cores of the NUMA node");
+            if cpu_affinity.len() == 0 {
+                panic!("NUMA node {} has no available CPU cores", device_id);
+            }
+
+            let thread_pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(cpu_affinity.len())
+                .start_handler(move |tid| {
+                    cpu_affinity
+                        .set_affinity(tid as u16)
+                        .expect("Couldn't set CPU core affinity")
+                })
+                .build()
+                .expect("Couldn't build Rayon thread pool");
+            Some(thread_pool)
+        } else {
+            None
+        };
+
+        let chaser_mems = thread_pool
+            .as_ref()
+            .map_or(0, |thread_pool| thread_pool.current_num_threads() - 1);
+        let chaser_mems = (0..chaser_mems)
+            .map(|_| {
+                let mut mem = Allocator::alloc_mem::<T>(mem_type.clone(), len);
+                mem.mlock().expect("Failed to mlock the memory");
+                mem
+            })
+            .collect();
+
+        Self {
+            thread_pool,
+            chaser_mems,
+        }
+    }
+
+    /// Returns the number of pointer chasers, if the latency is measured under
+    /// load.
+    fn chasers(&self) -> Option<usize> {
+        self.thread_pool
+            .as_ref()
+            .map(|thread_pool| thread_pool.current_num_threads())
     }
 
     fn run(
-        _state: &mut Self,
-        mem: &Mem<u32>,
+        state: &mut Self,
+        mem: &Mem<T>,
         mp: &MeasurementParameters,
-    ) -> (u32, Option<ThrottleReasons>, u64, u64) {
-        let ns = if let Mem::CudaDevMem(_) = mem {
-            unreachable!();
-        } else {
-            // Launch CPU code
-            unsafe { cpu_stride(mem.as_ptr(), mp.iterations) }
+    ) -> (u32, Option<ThrottleReasons>, Option<u64>, u64, Option<u64>) {
+        // Launch CPU code
+        let (cycles, ns, max_ns) = match state.thread_pool {
+            Some(ref thread_pool) => {
+                let mems: Vec<&Mem<T>> = std::iter::once(mem)
+                    .chain(state.chaser_mems.iter())
+                    .collect();
+                let (ns, max_ns) = loaded_stride_latency(thread_pool, &mems, mp.iterations);
+                (None, ns, Some(max_ns))
+            }
+            None => {
+                let (cycles, ns) = stride_latency(&StrideDevice::Cpu, mem, mp.iterations);
+                (cycles, ns, None)
+            }
         };
-
-        let cycles = 0;
         let clock_rate_mhz = 0;
 
-        (clock_rate_mhz, None, cycles, ns)
+        (clock_rate_mhz, None, cycles, ns, max_ns)
     }
 
-    fn prepare(_state: &mut Self, mem: &mut Mem<u32>, mp: &MeasurementParameters) {
-        if let Ok(slice) = mem.try_into() {
-            write_strides(slice, mp.stride);
-        } else {
-            unreachable!();
+    fn prepare(state: &mut Self, mem: &mut Mem<T>, mp: &MeasurementParameters) {
+        for mem in std::iter::once(mem).chain(state.chaser_mems.iter_mut()) {
+            if let Ok(slice) = mem.try_into() {
+                write_strides(slice, mp.stride);
+            } else {
+                unreachable!();
+            }
         }
     }
 }
 
-fn write_strides(data: &mut [u32], stride: usize) -> usize {
-    let element_bytes = size_of::<u32>();
+fn write_strides<T: StrideElement>(data: &mut [T], stride: usize) -> usize {
+    let element_bytes = size_of::<T>();
     let len = data.len();
 
     let number_of_strides = data
         .iter_mut()
         .zip((stride / element_bytes)..)
-        .map(|(it, next)| *it = (next % len) as u32)
+        .map(|(it, next)| *it = T::from_index(next % len))
         .count();
 
     number_of_strides
@@ -0,0 +1,363 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::process::Command;
+use std::result::Result;
+
+#[test]
+fn combined_latency_run_emits_cpu_and_gpu_rows() -> Result<(), Box<dyn Error>> {
+    let csv_path = std::env::temp_dir().join(format!(
+        "microbench_combined_latency_{}.csv",
+        std::process::id()
+    ));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_microbench"))
+        .arg("--csv")
+        .arg(&csv_path)
+        .args(&[
+            "latency",
+            "--device-type",
+            "CPU",
+            "--with-gpu",
+            "0",
+            "--range-lower",
+            "1",
+            "--range-upper",
+            "4",
+            "--stride-lower",
+            "64",
+            "--stride-upper",
+            "128",
+            "--repeat",
+            "1",
+        ])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut reader = csv::Reader::from_path(&csv_path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or_else(|| format!("Missing column {}", name))
+    };
+    let device_type = column("device_type")?;
+    let range_bytes = column("range_bytes")?;
+    let stride_bytes = column("stride_bytes")?;
+
+    let mut cpu_rows = BTreeSet::new();
+    let mut gpu_rows = BTreeSet::new();
+    for record in reader.records() {
+        let record = record?;
+        let key = (
+            record[range_bytes].parse::<usize>()?,
+            record[stride_bytes].parse::<usize>()?,
+        );
+        match &record[device_type] {
+            "CPU" => cpu_rows.insert(key),
+            "GPU" => gpu_rows.insert(key),
+            other => panic!("Unexpected device type {}", other),
+        };
+    }
+    std::fs::remove_file(&csv_path)?;
+
+    assert!(!cpu_rows.is_empty());
+    assert_eq!(cpu_rows, gpu_rows);
+
+    Ok(())
+}
+
+#[test]
+fn latency_run_supports_u32_and_u64_elements() -> Result<(), Box<dyn Error>> {
+    for element_bytes in &["4", "8"] {
+        let csv_path = std::env::temp_dir().join(format!(
+            "microbench_element_latency_{}_{}.csv",
+            element_bytes,
+            std::process::id()
+        ));
+
+        let output = Command::new(env!("CARGO_BIN_EXE_microbench"))
+            .arg("--csv")
+            .arg(&csv_path)
+            .args(&[
+                "latency",
+                "--device-type",
+                "CPU",
+                "--with-gpu",
+                "0",
+                "--range-lower",
+                "4",
+                "--range-upper",
+                "16",
+                "--stride-lower",
+                "64",
+                "--stride-upper",
+                "64",
+                "--repeat",
+                "1",
+                "--element-bytes",
+                element_bytes,
+            ])
+            .output()?;
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let mut reader = csv::Reader::from_path(&csv_path)?;
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| format!("Missing column {}", name))
+        };
+        let element_bytes_column = column("element_bytes")?;
+        let ns_column = column("ns")?;
+
+        let mut rows = 0;
+        for record in reader.records() {
+            let record = record?;
+            assert_eq!(&record[element_bytes_column], *element_bytes);
+
+            let ns = record[ns_column].parse::<u64>()?;
+            assert!(
+                ns > 0 && ns < 10_000,
+                "Latency of {} ns for {} byte elements",
+                ns,
+                element_bytes
+            );
+            rows += 1;
+        }
+        std::fs::remove_file(&csv_path)?;
+
+        assert!(rows > 0);
+    }
+
+    Ok(())
+}
+
+/// Runs a CPU latency sweep over the NUMA node locations, and returns the
+/// number of rows per memory node.
+fn latency_sweep_rows(locations: &[u16]) -> Result<BTreeMap<u16, usize>, Box<dyn Error>> {
+    let locations = locations
+        .iter()
+        .map(|node| node.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let csv_path = std::env::temp_dir().join(format!(
+        "microbench_latency_sweep_{}_{}.csv",
+        locations.replace(',', "_"),
+        std::process::id()
+    ));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_microbench"))
+        .arg("--csv")
+        .arg(&csv_path)
+        .args(&[
+            "latency",
+            "--device-type",
+            "CPU",
+            "--mem-type",
+            "Numa",
+            "--mem-location",
+            &locations,
+            "--range-lower",
+            "4",
+            "--range-upper",
+            "8",
+            "--stride-lower",
+            "64",
+            "--stride-upper",
+            "64",
+            "--repeat",
+            "1",
+        ])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut reader = csv::Reader::from_path(&csv_path)?;
+    let memory_node = reader
+        .headers()?
+        .iter()
+        .position(|header| header == "memory_node")
+        .ok_or("Missing column memory_node")?;
+
+    let mut rows_per_node = BTreeMap::new();
+    for record in reader.records() {
+        let node = record?[memory_node].parse::<u16>()?;
+        *rows_per_node.entry(node).or_insert(0) += 1;
+    }
+    std::fs::remove_file(&csv_path)?;
+
+    Ok(rows_per_node)
+}
+
+#[test]
+fn latency_sweep_emits_rows_for_each_location() -> Result<(), Box<dyn Error>> {
+    let mut nodes = std::fs::read_dir("/sys/devices/system/node")?
+        .filter_map(|entry| {
+            entry
+                .ok()?
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse::<u16>()
+                .ok()
+        })
+        .collect::<Vec<_>>();
+    nodes.sort_unstable();
+
+    // Sweep a single node twice on a single-node machine. Thus, the sweep
+    // always measures more than one location.
+    let locations = if nodes.len() > 1 {
+        nodes.clone()
+    } else {
+        vec![nodes[0], nodes[0]]
+    };
+
+    let single_location_rows = latency_sweep_rows(&locations[..1])?[&locations[0]];
+    assert!(single_location_rows > 0);
+
+    let rows_per_node = latency_sweep_rows(&locations)?;
+    for node in &nodes {
+        let sweeps = locations
+            .iter()
+            .filter(|&location| location == node)
+            .count();
+        assert_eq!(
+            rows_per_node.get(node).copied().unwrap_or(0),
+            sweeps * single_location_rows,
+            "Wrong number of rows for memory node {}",
+            node
+        );
+    }
+    assert_eq!(rows_per_node.len(), nodes.len());
+
+    Ok(())
+}
+
+/// Returns the number of CPU cores of the NUMA node.
+fn node_cpus(node: u16) -> Result<usize, Box<dyn Error>> {
+    let cpu_list =
+        std::fs::read_to_string(format!("/sys/devices/system/node/node{}/cpulist", node))?;
+
+    let mut cpus = 0;
+    for range in cpu_list.trim().split(',').filter(|range| !range.is_empty()) {
+        cpus += match range.split_once('-') {
+            Some((first, last)) => last.parse::<usize>()? - first.parse::<usize>()? + 1,
+            None => 1,
+        };
+    }
+
+    Ok(cpus)
+}
+
+#[test]
+fn loaded_latency_run_completes_all_chasers() -> Result<(), Box<dyn Error>> {
+    let cpus = node_cpus(0)?;
+    if cpus < 2 {
+        eprintln!("Skipping the loaded latency test on a single core");
+        return Ok(());
+    }
+
+    let csv_path = std::env::temp_dir().join(format!(
+        "microbench_loaded_latency_{}.csv",
+        std::process::id()
+    ));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_microbench"))
+        .arg("--csv")
+        .arg(&csv_path)
+        .args(&[
+            "latency",
+            "--device-type",
+            "CPU",
+            "--loaded",
+            "--range-lower",
+            "4",
+            "--range-upper",
+            "16",
+            "--stride-lower",
+            "64",
+            "--stride-upper",
+            "64",
+            "--repeat",
+            "1",
+        ])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut reader = csv::Reader::from_path(&csv_path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or_else(|| format!("Missing column {}", name))
+    };
+    let chasers_column = column("chasers")?;
+    let ns_column = column("ns")?;
+    let max_ns_column = column("max_ns")?;
+    let cycles_column = column("cycles")?;
+
+    let mut rows = 0;
+    for record in reader.records() {
+        let record = record?;
+
+        // The CPU chasers measure only nanoseconds
+        assert_eq!(&record[cycles_column], "");
+
+        // The process may be restricted to a subset of the node's cores
+        let chasers = record[chasers_column].parse::<usize>()?;
+        assert!(
+            chasers >= 1 && chasers <= cpus,
+            "Ran {} chasers on {} cores",
+            chasers,
+            cpus
+        );
+
+        let ns = record[ns_column].parse::<u64>()?;
+        let max_ns = record[max_ns_column].parse::<u64>()?;
+        assert!(
+            ns > 0 && ns <= max_ns && max_ns < 10_000,
+            "Average latency of {} ns, worst latency of {} ns",
+            ns,
+            max_ns
+        );
+        rows += 1;
+    }
+    std::fs::remove_file(&csv_path)?;
+
+    assert!(rows > 0);
+
+    Ok(())
+}
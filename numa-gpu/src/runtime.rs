@@ -13,13 +13,18 @@
 // limitations under the License.
 
 pub mod allocator;
+pub mod cache_flush;
 pub mod cpu_affinity;
 pub mod cuda;
 pub mod cuda_wrapper;
 pub mod dispatcher;
 pub mod hw_info;
+pub mod l2_persistence;
 pub mod linux_wrapper;
 pub mod memory;
 pub mod numa;
 pub mod nvml;
 pub mod nvtx;
+pub mod perf_counters;
+pub mod timer;
+pub mod trace;
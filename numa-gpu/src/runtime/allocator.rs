@@ -21,17 +21,25 @@
 //! The allocated memory is of type Mem, and specialized to DerefMem whenever
 //! possible.
 
+use once_cell::sync::Lazy;
+use rustacuda::device::Device;
+use rustacuda::error::CudaError;
 use rustacuda::memory::{DeviceBuffer, DeviceCopy, LockedBuffer, UnifiedBuffer};
 
 use std::alloc::{self, Layout};
 use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::default::Default;
-use std::mem::size_of;
+use std::mem::{align_of, size_of};
 use std::rc::Rc;
 use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
 
-use super::hw_info::ProcessorCache;
+use super::cuda_wrapper;
+use super::hw_info::{CudaDeviceInfo, ProcessorCache};
 use super::memory::{DerefMem, Mem, PageLock};
 use super::numa::{DistributedNumaMemory, NodeLen, NodeRatio, NumaMemory, PageType};
 use crate::error::{Error, ErrorKind, Result};
@@ -39,6 +47,50 @@ use crate::error::{Error, ErrorKind, Result};
 /// Heterogeneous memory allocator.
 pub struct Allocator;
 
+/// The soft limit on CUDA device memory, and the allocations that count
+/// towards the limit.
+#[derive(Debug, Default)]
+struct DeviceMemoryLimit {
+    limit: Option<usize>,
+
+    /// Maps the base address of each allocation to its size in bytes.
+    allocations: HashMap<u64, usize>,
+}
+
+impl DeviceMemoryLimit {
+    /// Forgets the freed allocations, and returns the bytes of the remaining
+    /// allocations.
+    ///
+    /// An allocation counts as freed if the driver doesn't know its address
+    /// anymore, or if the address now belongs to a different allocation.
+    fn outstanding_bytes(&mut self) -> usize {
+        self.allocations.retain(|&ptr, &mut bytes| {
+            match cuda_wrapper::device_allocation_range(ptr as *const u8) {
+                Ok((base, size)) => base == ptr && size == bytes,
+                Err(_) => false,
+            }
+        });
+
+        self.allocations.values().sum()
+    }
+}
+
+static DEVICE_MEMORY_LIMIT: Lazy<Mutex<DeviceMemoryLimit>> =
+    Lazy::new(|| Mutex::new(DeviceMemoryLimit::default()));
+
+/// Locks the device memory limit.
+///
+/// The limit stays consistent if a thread panics while holding the lock.
+/// Thus, a poisoned lock is recovered.
+fn lock_device_memory_limit() -> MutexGuard<'static, DeviceMemoryLimit> {
+    DEVICE_MEMORY_LIMIT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Falls back to unpinned memory if page-locking `NumaPinnedMem` fails.
+static PINNING_FALLBACK: AtomicBool = AtomicBool::new(false);
+
 /// Memory type specifier
 ///
 /// Some memory types cannot be directly accessed on the host, e.g., CudaDevMem.
@@ -127,6 +179,30 @@ impl MemType {
             MemType::CudaDevMem => PageType::Default,
         }
     }
+
+    /// Returns `true` if the memory type can be accessed by the device.
+    ///
+    /// CUDA pinned, unified, and device memory are always accessible. In
+    /// contrast, pageable memory is only accessible if the device supports
+    /// pageable memory access.
+    pub fn is_gpu_accessible(&self, device: &Device) -> Result<bool> {
+        match self {
+            MemType::NumaPinnedMem { .. }
+            | MemType::CudaPinnedMem
+            | MemType::CudaUniMem
+            | MemType::CudaDevMem => Ok(true),
+            MemType::SysMem
+            | MemType::AlignedSysMem { .. }
+            | MemType::NumaMem { .. }
+            | MemType::DistributedNumaMem { .. }
+            | MemType::DistributedNumaMemWithLen { .. } => device.pageable_memory_access(),
+        }
+    }
+
+    /// Returns `true` if the memory type can be accessed by the CPU.
+    pub fn is_cpu_accessible(&self) -> bool {
+        *self != MemType::CudaDevMem
+    }
 }
 
 impl DerefMemType {
@@ -220,6 +296,58 @@ pub type DerefMemAllocFn<T> = Box<dyn Fn(usize) -> DerefMem<T>>;
 pub type MemSpillAllocFn<T> = Box<dyn Fn(usize) -> MemAllocFn<T>>;
 
 impl Allocator {
+    /// Minimum alignment of CUDA unified and device memory allocations
+    ///
+    /// The CUDA programming guide specifies that memory allocated by
+    /// `cudaMalloc()` and `cudaMallocManaged()` is aligned to at least 256 bytes.
+    const CUDA_MALLOC_ALIGN_BYTES: usize = 256;
+
+    /// Sets a soft limit on the CUDA device memory that the allocator hands
+    /// out, in bytes.
+    ///
+    /// A `CudaDevMem` allocation fails with an `OutOfMemory` error if it
+    /// would exceed the limit together with the outstanding allocations,
+    /// regardless of the physical memory capacity. Thus, a large GPU can
+    /// simulate a smaller one, e.g., to test out-of-core execution.
+    ///
+    /// The limit covers all devices together, and only counts the `CudaDevMem`
+    /// allocations made through the allocator after the limit is set. Freed
+    /// allocations are detected at the next allocation. `None` removes the
+    /// limit.
+    pub fn set_device_memory_limit(bytes: Option<usize>) {
+        let mut device_memory_limit = lock_device_memory_limit();
+        device_memory_limit.limit = bytes;
+        device_memory_limit.allocations.clear();
+    }
+
+    /// Returns the soft limit on CUDA device memory in bytes, if it is set.
+    pub fn device_memory_limit() -> Option<usize> {
+        lock_device_memory_limit().limit
+    }
+
+    /// Returns the bytes of the outstanding `CudaDevMem` allocations that
+    /// count towards the device memory limit.
+    pub fn outstanding_device_memory() -> usize {
+        lock_device_memory_limit().outstanding_bytes()
+    }
+
+    /// Falls back to unpinned NUMA memory if page-locking a `NumaPinnedMem`
+    /// allocation fails.
+    ///
+    /// Page-locking fails, e.g., if the allocation exceeds the locked memory
+    /// limit of the process. With the fallback, the allocator prints a
+    /// warning and returns `NumaMem` instead. Transfers from the memory are
+    /// then slower, but the program still runs in a constrained environment.
+    /// The fallback is disabled by default.
+    pub fn set_pinning_fallback(enable: bool) {
+        PINNING_FALLBACK.store(enable, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the allocator falls back to unpinned NUMA memory.
+    pub fn pinning_fallback() -> bool {
+        PINNING_FALLBACK.load(Ordering::SeqCst)
+    }
+
     /// Allocates memory of the specified type
     pub fn alloc_mem<T: Clone + Default + DeviceCopy>(mem_type: MemType, len: usize) -> Mem<T> {
         match mem_type {
@@ -241,6 +369,28 @@ impl Allocator {
         }
     }
 
+    /// Allocates memory of the specified type, and returns an error if the
+    /// allocation of CUDA device memory or page-locking fails.
+    ///
+    /// In contrast to `alloc_mem`, which panics, exceeding the device memory
+    /// limit or the physical capacity returns an `OutOfMemory` error. Thus,
+    /// the caller can fall back to an out-of-core strategy.
+    pub fn try_alloc_mem<T: Clone + Default + DeviceCopy>(
+        mem_type: MemType,
+        len: usize,
+    ) -> Result<Mem<T>> {
+        match mem_type {
+            MemType::AlignedSysMem { align_bytes } => {
+                Ok(Self::try_alloc_aligned(len, align_bytes)?.into())
+            }
+            MemType::CudaDevMem => Self::try_alloc_cuda_device(len),
+            MemType::NumaPinnedMem { node, page_type } => {
+                Ok(Self::try_alloc_numa_pinned(len, node, page_type)?.into())
+            }
+            other => Ok(Self::alloc_mem(other, len)),
+        }
+    }
+
     /// Allocates host-dereferencable memory of the specified type
     pub fn alloc_deref_mem<T: Clone + Default + DeviceCopy>(
         mem_type: DerefMemType,
@@ -266,6 +416,108 @@ impl Allocator {
         }
     }
 
+    /// Allocates host-dereferencable memory of the specified type, and returns
+    /// an error if page-locking fails.
+    ///
+    /// See `set_pinning_fallback` to fall back to unpinned memory instead.
+    pub fn try_alloc_deref_mem<T: Clone + Default + DeviceCopy>(
+        mem_type: DerefMemType,
+        len: usize,
+    ) -> Result<DerefMem<T>> {
+        match mem_type {
+            DerefMemType::AlignedSysMem { align_bytes } => {
+                Self::try_alloc_aligned(len, align_bytes)
+            }
+            DerefMemType::NumaPinnedMem { node, page_type } => {
+                Self::try_alloc_numa_pinned(len, node, page_type)
+            }
+            other => Ok(Self::alloc_deref_mem(other, len)),
+        }
+    }
+
+    /// Allocates memory of the specified type with a minimum alignment
+    ///
+    /// The alignment is specified in bytes and must be a power of two.
+    ///
+    /// System memory is allocated with the requested alignment. All other
+    /// memory types are allocated by the OS or by CUDA, which align memory to
+    /// at least a page (or to 256 bytes for CUDA unified and device memory).
+    /// For these types, the allocation fails if the requested alignment is
+    /// larger than the guaranteed alignment.
+    ///
+    /// The returned memory is never offset from the pointer returned by the
+    /// underlying allocator. Thus, dropping it frees the original allocation.
+    pub fn alloc_mem_aligned<T: Clone + Default + DeviceCopy>(
+        mem_type: MemType,
+        len: usize,
+        alignment: usize,
+    ) -> Result<Mem<T>> {
+        if !alignment.is_power_of_two() {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Alignment must be a power of two, but is {}",
+                alignment
+            )))?;
+        }
+
+        let mem: Mem<T> = match mem_type {
+            MemType::SysMem => {
+                Self::try_alloc_aligned(len, cmp::max(alignment, align_of::<T>()))?.into()
+            }
+            MemType::AlignedSysMem { align_bytes } => {
+                Self::try_alloc_aligned(len, cmp::max(alignment, align_bytes))?.into()
+            }
+            other => {
+                let guaranteed_alignment = Self::guaranteed_alignment(&other);
+                if alignment > guaranteed_alignment {
+                    Err(ErrorKind::InvalidArgument(format!(
+                        "{:?} cannot be aligned to {} bytes; the maximum alignment is {} bytes",
+                        other, alignment, guaranteed_alignment
+                    )))?;
+                }
+
+                Self::try_alloc_mem(other, len)?
+            }
+        };
+
+        if mem.as_ptr() as usize % alignment != 0 {
+            Err(ErrorKind::LogicError(format!(
+                "Allocated memory is not aligned to {} bytes",
+                alignment
+            )))?;
+        }
+
+        Ok(mem)
+    }
+
+    /// Allocates host-dereferencable memory of the specified type with a
+    /// minimum alignment
+    ///
+    /// See `alloc_mem_aligned` for details on the supported alignments.
+    pub fn alloc_deref_mem_aligned<T: Clone + Default + DeviceCopy>(
+        mem_type: DerefMemType,
+        len: usize,
+        alignment: usize,
+    ) -> Result<DerefMem<T>> {
+        let mem = Self::alloc_mem_aligned(mem_type.into(), len, alignment)?;
+
+        DerefMem::try_from(mem).map_err(|(error, _)| error)
+    }
+
+    /// Returns the alignment in bytes that the memory type guarantees
+    /// regardless of the requested alignment.
+    fn guaranteed_alignment(mem_type: &MemType) -> usize {
+        match mem_type {
+            MemType::SysMem => 1,
+            MemType::AlignedSysMem { align_bytes } => *align_bytes,
+            MemType::NumaMem { .. }
+            | MemType::NumaPinnedMem { .. }
+            | MemType::DistributedNumaMem { .. }
+            | MemType::DistributedNumaMemWithLen { .. }
+            | MemType::CudaPinnedMem => ProcessorCache::page_size(),
+            MemType::CudaUniMem | MemType::CudaDevMem => Self::CUDA_MALLOC_ALIGN_BYTES,
+        }
+    }
+
     /// Returns a generic 'Mem' memory allocator that allocates memory of the
     /// specified 'Mem' type.
     pub fn mem_alloc_fn<T: Clone + Default + DeviceCopy>(mem_type: MemType) -> MemAllocFn<T> {
@@ -326,11 +578,34 @@ impl Allocator {
 
     /// Allocates aligned system memory using Rust's global allocator.
     fn alloc_aligned<T: Clone + Default + DeviceCopy>(len: usize, alignment: usize) -> DerefMem<T> {
+        Self::try_alloc_aligned(len, alignment).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Allocates aligned system memory using Rust's global allocator, and
+    /// returns an error if the allocation size overflows or the allocation
+    /// fails.
+    fn try_alloc_aligned<T: Clone + Default + DeviceCopy>(
+        len: usize,
+        alignment: usize,
+    ) -> Result<DerefMem<T>> {
+        let bytes = len.checked_mul(size_of::<T>()).ok_or_else(|| {
+            ErrorKind::IntegerOverflow("Failed to compute the allocation size".to_string())
+        })?;
+        let layout = Layout::from_size_align(bytes, alignment).map_err(|_| {
+            ErrorKind::InvalidArgument(format!(
+                "Cannot allocate {} bytes aligned to {} bytes",
+                bytes, alignment
+            ))
+        })?;
+
         let mem = unsafe {
-            let layout = Layout::from_size_align(len * size_of::<T>(), alignment)
-                .expect("Memory alignment must be at least size of T");
             let ptr = alloc::alloc(layout) as *mut T;
-            assert!(!ptr.is_null(), "Failed to allocate aligned memory");
+            if ptr.is_null() {
+                Err(ErrorKind::RuntimeError(format!(
+                    "Failed to allocate {} bytes of aligned memory",
+                    bytes
+                )))?;
+            }
 
             let slice = slice::from_raw_parts_mut(ptr, len);
             slice.iter_mut().for_each(|x| *x = T::default());
@@ -338,7 +613,7 @@ impl Allocator {
             let output: Box<[T]> = Box::from_raw(slice);
             output
         };
-        DerefMem::BoxedSysMem(mem)
+        Ok(DerefMem::BoxedSysMem(mem))
     }
 
     /// Allocates memory on the specified NUMA node.
@@ -348,9 +623,44 @@ impl Allocator {
 
     /// Allocates pinned memory on the specified NUMA node.
     fn alloc_numa_pinned<T: DeviceCopy>(len: usize, node: u16, page_type: PageType) -> DerefMem<T> {
+        Self::try_alloc_numa_pinned(len, node, page_type)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Allocates pinned memory on the specified NUMA node, and returns an
+    /// error if page-locking fails.
+    ///
+    /// Falls back to unpinned memory with a warning if the pinning fallback
+    /// is enabled.
+    fn try_alloc_numa_pinned<T: DeviceCopy>(
+        len: usize,
+        node: u16,
+        page_type: PageType,
+    ) -> Result<DerefMem<T>> {
         let mut mem = NumaMemory::new(len, node, page_type);
-        mem.page_lock().expect("Failed to pin memory");
-        DerefMem::NumaMem(mem)
+
+        if let Err(error) = mem.page_lock() {
+            let bytes = len * size_of::<T>();
+            if !Self::pinning_fallback() {
+                return Err(Error::with_chain(
+                    error,
+                    ErrorKind::RuntimeError(format!(
+                        "Failed to pin {} bytes of NUMA memory. Check that the locked memory \
+                        limit (RLIMIT_MEMLOCK, see `ulimit -l`) is large enough, or allow a \
+                        fallback to unpinned memory",
+                        bytes
+                    )),
+                ));
+            }
+
+            eprintln!(
+                "Warning: Failed to pin {} bytes of NUMA memory, falling back to unpinned \
+                memory: {}",
+                bytes, error
+            );
+        }
+
+        Ok(DerefMem::NumaMem(mem))
     }
 
     /// Allocates memory on multiple, specified NUMA nodes.
@@ -406,12 +716,47 @@ impl Allocator {
     /// cannot initialize the memory asynchronously, due to the user not
     /// providing a CUDA stream in the API.
     fn alloc_cuda_device<T: DeviceCopy>(len: usize) -> Mem<T> {
-        unsafe {
-            Mem::CudaDevMem(DeviceBuffer::<T>::uninitialized(len).expect(&format!(
-                "Failed to allocate {} bytes of CUDA device memory",
-                len * size_of::<T>()
-            )))
+        Self::try_alloc_cuda_device(len).unwrap_or_else(|error| {
+            panic!(
+                "Failed to allocate {} bytes of CUDA device memory: {}",
+                len * size_of::<T>(),
+                error
+            )
+        })
+    }
+
+    /// Allocates CUDA device memory within the device memory limit.
+    ///
+    /// Warning: Returns uninitialized memory (see `alloc_cuda_device`).
+    fn try_alloc_cuda_device<T: DeviceCopy>(len: usize) -> Result<Mem<T>> {
+        let bytes = len.checked_mul(size_of::<T>()).ok_or_else(|| {
+            ErrorKind::IntegerOverflow("Failed to compute the allocation size".to_string())
+        })?;
+
+        // Hold the lock during the allocation, so that concurrent allocations
+        // cannot exceed the limit together
+        let mut device_memory_limit = lock_device_memory_limit();
+        if let Some(limit) = device_memory_limit.limit {
+            if device_memory_limit
+                .outstanding_bytes()
+                .saturating_add(bytes)
+                > limit
+            {
+                Err(ErrorKind::Cuda(CudaError::OutOfMemory))?;
+            }
         }
+
+        let buffer = unsafe { DeviceBuffer::<T>::uninitialized(len)? };
+
+        // Zero-sized buffers don't allocate memory
+        if device_memory_limit.limit.is_some() && bytes != 0 {
+            let ptr = buffer.as_ptr();
+            let (base, size) =
+                cuda_wrapper::device_allocation_range(ptr).unwrap_or((ptr as u64, bytes));
+            device_memory_limit.allocations.insert(base, size);
+        }
+
+        Ok(Mem::CudaDevMem(buffer))
     }
 
     /// Captures the cache memory type and returns a function that returns an allocator
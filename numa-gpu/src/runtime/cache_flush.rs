@@ -0,0 +1,167 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Evicts data from the CPU caches, e.g., to measure cold-cache performance.
+//!
+//! Repeated runs of a benchmark find the data of the previous run in the
+//! caches. If the data set is small enough to fit, the runs after the first
+//! measure warm-cache performance. `CacheFlusher` instead streams through a
+//! buffer that is larger than the last-level cache. This displaces all other
+//! cache lines, independent of the CPU architecture.
+//!
+//! The flush evicts only the caches that the flushing threads pass through,
+//! i.e., the private caches of their cores and the last-level caches of their
+//! sockets. Without a thread pool, the calling thread flushes the caches.
+//! With a thread pool, each thread of the pool flushes its part of the
+//! buffer. The GPU caches aren't affected.
+
+use super::hw_info::ProcessorCache;
+use rayon::ThreadPool;
+use std::cmp;
+use std::ptr;
+use std::sync::Barrier;
+
+/// Evicts the CPU caches by streaming through a buffer.
+#[derive(Debug)]
+pub struct CacheFlusher {
+    buffer: Vec<u8>,
+    thread_pool: Option<ThreadPool>,
+    flushes: u64,
+}
+
+impl CacheFlusher {
+    /// The stride between the touched bytes.
+    ///
+    /// Touches each line at least once for cache lines of 64 bytes and larger.
+    const STRIDE_BYTES: usize = 64;
+
+    /// The assumed last-level cache size if the size isn't reported.
+    const FALLBACK_LLC_BYTES: usize = 64 * 1024 * 1024;
+
+    /// Creates a flusher that is twice the size of the last-level cache.
+    ///
+    /// Replacement policies aren't strictly LRU, thus streaming through
+    /// exactly the cache size leaves some of the old lines cached.
+    pub fn new() -> Self {
+        Self::with_bytes(2 * Self::llc_bytes())
+    }
+
+    /// Creates a flusher that streams through `bytes` bytes.
+    pub fn with_bytes(bytes: usize) -> Self {
+        Self {
+            buffer: vec![0; bytes],
+            thread_pool: None,
+            flushes: 0,
+        }
+    }
+
+    /// Creates a flusher that flushes the caches of all threads in
+    /// `thread_pool`.
+    ///
+    /// The pool's threads should run on the same cores as the benchmark's
+    /// threads. Each thread streams through an equal part of the buffer. The
+    /// buffer is at least twice the size of the last-level cache, and each
+    /// part is at least twice the size of the L2 cache.
+    pub fn with_thread_pool(thread_pool: ThreadPool) -> Self {
+        let l2_bytes = match ProcessorCache::L2_size() {
+            l2 if l2 > 0 && l2 != usize::MAX => l2,
+            _ => 0,
+        };
+        let threads = thread_pool.current_num_threads();
+        let bytes = cmp::max(2 * Self::llc_bytes(), threads * 2 * l2_bytes);
+
+        Self {
+            buffer: vec![0; bytes],
+            thread_pool: Some(thread_pool),
+            flushes: 0,
+        }
+    }
+
+    /// Returns the size of the flush buffer in bytes.
+    pub fn bytes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns the number of flushes so far.
+    pub fn flushes(&self) -> u64 {
+        self.flushes
+    }
+
+    /// Evicts the caches by writing to each cache line of the buffer.
+    ///
+    /// Writing instead of only reading the lines also evicts the caches that
+    /// don't allocate lines on reads.
+    ///
+    /// With a thread pool, each thread flushes its part of the buffer. The
+    /// threads wait for each other before starting. Thus, each part runs on a
+    /// different thread, even if a thread finishes its part early.
+    pub fn flush(&mut self) {
+        match self.thread_pool {
+            None => Self::flush_part(&mut self.buffer),
+            Some(ref thread_pool) => {
+                let threads = thread_pool.current_num_threads();
+                let barrier = Barrier::new(threads);
+                let barrier = &barrier;
+
+                // Splits the buffer into exactly one part per thread, as the
+                // barrier deadlocks with fewer parts
+                let mut remainder = self.buffer.as_mut_slice();
+                let mut parts = Vec::with_capacity(threads);
+                for thread in 0..threads {
+                    let part_len = remainder.len() / (threads - thread);
+                    let (part, rest) = remainder.split_at_mut(part_len);
+                    parts.push(part);
+                    remainder = rest;
+                }
+
+                thread_pool.scope(|s| {
+                    for part in parts {
+                        s.spawn(move |_| {
+                            barrier.wait();
+                            Self::flush_part(part);
+                        });
+                    }
+                });
+            }
+        }
+
+        self.flushes += 1;
+    }
+
+    /// Writes to each cache line of `part`.
+    fn flush_part(part: &mut [u8]) {
+        for byte in part.iter_mut().step_by(Self::STRIDE_BYTES) {
+            // Volatile accesses prevent the compiler from eliding the writes
+            unsafe {
+                let value = ptr::read_volatile(byte);
+                ptr::write_volatile(byte, value.wrapping_add(1));
+            }
+        }
+    }
+
+    /// Returns the size of the last-level cache.
+    fn llc_bytes() -> usize {
+        match (ProcessorCache::L3_size(), ProcessorCache::L2_size()) {
+            (l3, _) if l3 > 0 && l3 != usize::MAX => l3,
+            (_, l2) if l2 > 0 && l2 != usize::MAX => l2,
+            _ => Self::FALLBACK_LLC_BYTES,
+        }
+    }
+}
+
+impl Default for CacheFlusher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -15,7 +15,7 @@
 //! Set the CPU core affinity of a thread.
 
 use crate::error::{ErrorKind, Result};
-use crate::runtime::linux_wrapper::CpuSet;
+use crate::runtime::linux_wrapper::{self, CpuSet};
 use std::default::Default;
 use std::fs::File;
 use std::io::Error as IoError;
@@ -62,6 +62,21 @@ impl CpuAffinity {
         Ok(Self { affinity_list })
     }
 
+    /// Lists the CPU cores of a NUMA node.
+    ///
+    /// Only includes the cores that the current thread is allowed to run on.
+    /// The core IDs are in ascending order.
+    pub fn from_numa_node(node: u16) -> Result<Self> {
+        let mut affinity_list = Vec::new();
+        for core_id in Self::default().affinity_list {
+            if linux_wrapper::numa_node_of_cpu(core_id)? == node {
+                affinity_list.push(core_id);
+            }
+        }
+
+        Ok(Self { affinity_list })
+    }
+
     /// Maps a thread ID to a CPU core ID.
     ///
     /// Returns a CPU core ID, or `None` if the thread ID is out-of-bounds.
@@ -47,6 +47,7 @@ use crate::runtime::cuda_wrapper::{
 use crate::runtime::hw_info::NvidiaDriverInfo;
 use crate::runtime::linux_wrapper;
 use crate::runtime::memory::{LaunchableMem, LaunchableSlice};
+use crate::runtime::timer::{event_elapsed_ns, millis_to_nanos};
 
 /// Timer based on CUDA events.
 ///
@@ -812,8 +813,7 @@ impl<'a, R: Copy + DeviceCopy + Send, S: Copy + DeviceCopy + Send> CudaIterator2
                                     // cooling down resources created in the warm_up
                                     end_copy_event.synchronize()?;
                                     let copy_ns =
-                                        end_copy_event.elapsed_time_f32(&begin_copy_event)? as f64
-                                            * 10_f64.powf(6.0);
+                                        event_elapsed_ns(&begin_copy_event, &end_copy_event)?;
 
                                     let cool_down_timer = Instant::now();
                                     strategy_fst.cool_down(&fst, &stream)?;
@@ -822,8 +822,7 @@ impl<'a, R: Copy + DeviceCopy + Send, S: Copy + DeviceCopy + Send> CudaIterator2
 
                                     end_comp_event.synchronize()?;
                                     let comp_ns =
-                                        end_comp_event.elapsed_time_f32(&begin_comp_event)? as f64
-                                            * 10_f64.powf(6.0);
+                                        event_elapsed_ns(&begin_comp_event, &end_comp_event)?;
 
                                     Ok((warm_up_ns, copy_ns, comp_ns, cool_down_ns))
                                 })
@@ -936,10 +935,10 @@ impl<'a, R: Copy + DeviceCopy, S: Copy + DeviceCopy> CudaIterator2<'a, R, S> {
             .iter()
             .map(|(warm_up, copy, comp, cool_down)| {
                 Ok((
-                    warm_up.synchronize_and_time()? as f64 * 10_f64.powf(6.0),
-                    copy.synchronize_and_time()? as f64 * 10_f64.powf(6.0),
-                    comp.synchronize_and_time()? as f64 * 10_f64.powf(6.0),
-                    cool_down.synchronize_and_time()? as f64 * 10_f64.powf(6.0),
+                    millis_to_nanos(warm_up.synchronize_and_time()?),
+                    millis_to_nanos(copy.synchronize_and_time()?),
+                    millis_to_nanos(comp.synchronize_and_time()?),
+                    millis_to_nanos(cool_down.synchronize_and_time()?),
                 ))
             })
             .fold(
@@ -1060,8 +1059,8 @@ impl<'a, R: Copy + DeviceCopy, S: Copy + DeviceCopy> CudaUnifiedIterator2<'a, R,
             .iter()
             .map(|(prefetch, comp)| {
                 Ok((
-                    prefetch.synchronize_and_time()? as f64 * 10_f64.powf(6.0),
-                    comp.synchronize_and_time()? as f64 * 10_f64.powf(6.0),
+                    millis_to_nanos(prefetch.synchronize_and_time()?),
+                    millis_to_nanos(comp.synchronize_and_time()?),
                 ))
             })
             .fold(
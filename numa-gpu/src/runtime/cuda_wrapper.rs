@@ -17,14 +17,16 @@
 use crate::error::{Error, ErrorKind, Result, ToResult};
 use crate::runtime::memory::LaunchableMutSlice;
 use cuda_driver_sys::{
-    cuCtxGetDevice, cuMemAdvise, cuMemGetInfo_v2, cuMemHostRegister_v2, cuMemHostUnregister,
-    cuMemPrefetchAsync, cuMemcpyAsync, cuMemsetD32Async, CUdevice, CUstream,
-    CU_MEMHOSTREGISTER_DEVICEMAP, CU_MEMHOSTREGISTER_PORTABLE,
+    cuCtxGetDevice, cuMemAdvise, cuMemGetAddressRange_v2, cuMemGetInfo_v2, cuMemHostRegister_v2,
+    cuMemHostUnregister, cuMemPrefetchAsync, cuMemcpyAsync, cuMemsetD16Async, cuMemsetD32Async,
+    cuMemsetD8Async, CUdevice, CUdeviceptr, CUstream, CU_MEMHOSTREGISTER_DEVICEMAP,
+    CU_MEMHOSTREGISTER_PORTABLE,
 };
 use rustacuda::memory::{DeviceCopy, UnifiedPointer};
 use rustacuda::stream::Stream;
 use std::mem::{size_of, transmute_copy, zeroed};
 use std::os::raw::{c_uint, c_void};
+use std::str::FromStr;
 
 // re-export mem_advise enum
 pub use cuda_driver_sys::CUmem_advise_enum as MemAdviseFlags;
@@ -56,6 +58,26 @@ pub fn mem_info() -> Result<CudaMemInfo> {
     Ok(CudaMemInfo { free, total })
 }
 
+/// Returns the base address and the size in bytes of the device allocation
+/// that contains `ptr`.
+///
+/// Returns an error if `ptr` isn't part of a live device allocation, e.g.,
+/// because the allocation is already freed.
+pub fn device_allocation_range<T>(ptr: *const T) -> Result<(CUdeviceptr, usize)> {
+    let mut base: CUdeviceptr = 0;
+    let mut size: usize = 0;
+
+    unsafe {
+        cuMemGetAddressRange_v2(&mut base, &mut size, ptr as CUdeviceptr)
+            .to_result()
+            .map_err(|e| {
+                Error::with_chain::<Error, _>(e.into(), "Failed to get the allocation range")
+            })?;
+    }
+
+    Ok((base, size))
+}
+
 /// Page-lock an existing memory range for efficient GPU transfers.
 ///
 /// # Unsafety
@@ -89,6 +111,58 @@ pub unsafe fn host_unregister<T>(mem: &[T]) -> Result<()> {
 
 pub const CPU_DEVICE_ID: CUdevice = -1;
 
+/// The destination to which unified memory is prefetched.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PrefetchDestination {
+    /// Prefetch to CPU memory
+    Cpu,
+
+    /// Prefetch to the memory of the GPU with the given device ID
+    Device(CUdevice),
+}
+
+impl PrefetchDestination {
+    /// Returns the device of the current context as the destination.
+    pub fn current_device() -> Result<Self> {
+        Ok(Self::Device(current_device_id()?))
+    }
+}
+
+impl From<PrefetchDestination> for CUdevice {
+    fn from(destination: PrefetchDestination) -> Self {
+        match destination {
+            PrefetchDestination::Cpu => CPU_DEVICE_ID,
+            PrefetchDestination::Device(device_id) => device_id,
+        }
+    }
+}
+
+impl FromStr for PrefetchDestination {
+    type Err = Error;
+
+    /// Parses either "cpu" or a GPU device ID.
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("cpu") {
+            Ok(Self::Cpu)
+        } else {
+            let device_id = s.parse::<CUdevice>().map_err(|_| {
+                ErrorKind::InvalidArgument(format!(
+                    "Prefetch destination must be \"cpu\" or a device ID, got \"{}\"",
+                    s
+                ))
+            })?;
+            if device_id < 0 {
+                Err(ErrorKind::InvalidArgument(format!(
+                    "Invalid device ID {}",
+                    device_id
+                )))?;
+            }
+
+            Ok(Self::Device(device_id))
+        }
+    }
+}
+
 pub fn current_device_id() -> Result<CUdevice> {
     unsafe {
         let mut cu_device: CUdevice = zeroed();
@@ -99,13 +173,19 @@ pub fn current_device_id() -> Result<CUdevice> {
     }
 }
 
-/// Prefetch memory to the device specified in the current context.
-pub fn prefetch_async<T: DeviceCopy>(
+/// Prefetch unified memory to the destination device.
+///
+/// The destination is either a device ID, `CPU_DEVICE_ID`, or a
+/// `PrefetchDestination`. Returns an error if the destination is not a valid
+/// device, or doesn't support concurrent managed access.
+pub fn prefetch_async<T: DeviceCopy, D: Into<CUdevice>>(
     mem: UnifiedPointer<T>,
     len: usize,
-    destination_device: CUdevice,
+    destination_device: D,
     stream: &Stream,
 ) -> Result<()> {
+    let destination_device = destination_device.into();
+
     unsafe {
         // FIXME: Find a safer solution to replace transmute_copy!!!
         let cu_stream = transmute_copy::<Stream, CUstream>(stream);
@@ -225,3 +305,75 @@ pub fn memset_async<T: DeviceCopy>(
 
     Ok(())
 }
+
+/// Fill a launchable slice with a byte value using the CUDA `memset_async`
+/// function
+///
+/// In contrast to `memset_async`, the function supports types of any size.
+pub fn memset_d8_async<T: DeviceCopy>(
+    mem: LaunchableMutSlice<T>,
+    value: u8,
+    stream: &Stream,
+) -> Result<()> {
+    unsafe {
+        // FIXME: Find a safer solution to replace transmute_copy!!!
+        let cu_stream = transmute_copy::<Stream, CUstream>(&stream);
+
+        cuMemsetD8Async(
+            mem.as_ptr() as u64,
+            value,
+            mem.len().checked_mul(size_of::<T>()).ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to compute memset length".to_string())
+            })?,
+            cu_stream,
+        )
+        .to_result()
+        .map_err(|e| {
+            Error::with_chain::<Error, _>(e.into(), format!("Failed to schedule memset"))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Fill a launchable slice with a 16-bit value using the CUDA `memset_async`
+/// function
+///
+/// In contrast to `memset_async`, the function supports types that are smaller
+/// than 32 bits.
+///
+/// # Limitations
+///
+///  - The size of `T` must be an even multiple of a 16-bit integer.
+pub fn memset_d16_async<T: DeviceCopy>(
+    mem: LaunchableMutSlice<T>,
+    value: u16,
+    stream: &Stream,
+) -> Result<()> {
+    assert!(
+        size_of::<T>() % size_of::<u16>() == 0,
+        "Size of type T must be divisible by u16"
+    );
+
+    unsafe {
+        // FIXME: Find a safer solution to replace transmute_copy!!!
+        let cu_stream = transmute_copy::<Stream, CUstream>(&stream);
+
+        cuMemsetD16Async(
+            mem.as_ptr() as u64,
+            value,
+            mem.len()
+                .checked_mul(size_of::<T>() / size_of::<u16>())
+                .ok_or_else(|| {
+                    ErrorKind::IntegerOverflow("Failed to compute memset length".to_string())
+                })?,
+            cu_stream,
+        )
+        .to_result()
+        .map_err(|e| {
+            Error::with_chain::<Error, _>(e.into(), format!("Failed to schedule memset"))
+        })?;
+    }
+
+    Ok(())
+}
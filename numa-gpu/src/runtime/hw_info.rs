@@ -16,6 +16,7 @@ use crate::error::{ErrorKind, Result};
 use crate::runtime::linux_wrapper;
 use procfs::CpuInfo;
 use rustacuda::device::{Device, DeviceAttribute};
+use rustacuda::CudaApiVersion;
 use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
@@ -116,6 +117,14 @@ pub fn cpu_codename() -> Result<String> {
         .to_string())
 }
 
+/// Returns the version of the CUDA driver API.
+///
+/// For example: `10.2`
+pub fn cuda_driver_version() -> Result<String> {
+    let version = CudaApiVersion::get()?;
+    Ok(format!("{}.{}", version.major(), version.minor()))
+}
+
 /// Extends Rustacuda's Device with methods that provide additional hardware
 /// information.
 pub trait CudaDeviceInfo {
@@ -128,6 +137,12 @@ pub trait CudaDeviceInfo {
     /// Returns `true` if concurrent managed access is supported by the device
     fn concurrent_managed_access(&self) -> Result<bool>;
 
+    /// Returns `true` if the device can coherently access pageable host memory
+    ///
+    /// Pageable memory access requires hardware support, e.g., NVLink 2.0 on
+    /// POWER9, or a kernel with heterogeneous memory management (HMM).
+    fn pageable_memory_access(&self) -> Result<bool>;
+
     /// Returns the default clock rate of the streaming multiprocessor in megahertz
     fn clock_rate(&self) -> Result<u32>;
 
@@ -170,6 +185,16 @@ impl CudaDeviceInfo for Device {
         })
     }
 
+    fn pageable_memory_access(&self) -> Result<bool> {
+        let is_supported = self.get_attribute(DeviceAttribute::PageableMemoryAccess)?;
+
+        Ok(match is_supported {
+            1 => true,
+            0 => false,
+            _ => unreachable!("Pageable memory access should return 0 or 1"),
+        })
+    }
+
     fn clock_rate(&self) -> Result<u32> {
         Ok(self.get_attribute(DeviceAttribute::ClockRate)? as u32 / 1000)
     }
@@ -305,7 +330,7 @@ struct NvidiaDriverInternal {
 impl NvidiaDriverInternal {
     fn from_device(device: &Device) -> Result<Self> {
         let device_id = unsafe { mem::transmute_copy::<Device, c_int>(device) };
-        let pci_id = Self::pci_id(device)?;
+        let pci_id = pci_id(device)?;
 
         let mut device_path = PathBuf::from_str("/proc/driver/nvidia/gpus")
             .expect("Failed to convert string to a path");
@@ -368,7 +393,7 @@ impl NvidiaDriverInternal {
     fn numa_memory_affinity(device: &Device) -> Result<u16> {
         let mut device_path =
             PathBuf::from_str("/sys/bus/pci/devices").expect("Failed to convert string to a path");
-        let pci_id = Self::pci_id(device)?;
+        let pci_id = pci_id(device)?;
         device_path.push(pci_id);
         let device_path = device_path;
 
@@ -393,18 +418,21 @@ impl NvidiaDriverInternal {
             Ok(numa_node as u16)
         }
     }
+}
 
-    fn pci_id(device: &Device) -> Result<String> {
-        let pci_domain_id = device.get_attribute(DeviceAttribute::PciDomainId)?;
-        let pci_bus_id = device.get_attribute(DeviceAttribute::PciBusId)?;
-        let pci_device_id = device.get_attribute(DeviceAttribute::PciDeviceId)?;
-        let pci_function_id: u32 = 0;
-
-        let pci_id = format!(
-            "{:04x}:{:02x}:{:02x}.{:1x}",
-            pci_domain_id, pci_bus_id, pci_device_id, pci_function_id
-        );
-
-        Ok(pci_id)
-    }
+/// Returns the PCI bus ID of a CUDA device.
+///
+/// The ID has the format `domain:bus:device.function`, e.g., `0000:04:00.0`.
+pub(crate) fn pci_id(device: &Device) -> Result<String> {
+    let pci_domain_id = device.get_attribute(DeviceAttribute::PciDomainId)?;
+    let pci_bus_id = device.get_attribute(DeviceAttribute::PciBusId)?;
+    let pci_device_id = device.get_attribute(DeviceAttribute::PciDeviceId)?;
+    let pci_function_id: u32 = 0;
+
+    let pci_id = format!(
+        "{:04x}:{:02x}:{:02x}.{:1x}",
+        pci_domain_id, pci_bus_id, pci_device_id, pci_function_id
+    );
+
+    Ok(pci_id)
 }
@@ -0,0 +1,288 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persists a memory range in the GPU L2 cache.
+//!
+//! Since Ampere, a stream can mark a memory range as L2-persisting with an
+//! access policy window. Kernels launched on the stream then preferentially
+//! keep cache lines of the window in a set-aside portion of the L2 cache,
+//! instead of evicting them by streaming accesses. This benefits small data
+//! structures that are accessed many times, e.g., a hash table during the
+//! probe.
+//!
+//! The access policy window was introduced in CUDA 11.0, whereas the CUDA
+//! driver bindings target CUDA 10.2. Thus, this module looks up the driver
+//! functions at runtime. On older drivers and on GPUs without a set-aside L2
+//! cache, the functions return `false` and have no effect.
+
+use crate::error::{Error, Result, ToResult};
+use crate::runtime::cuda_wrapper::current_device_id;
+use crate::runtime::memory::LaunchableSlice;
+use cuda_driver_sys::{CUdevice, CUresult, CUstream};
+use once_cell::sync::Lazy;
+use rustacuda::stream::Stream;
+use std::ffi::CStr;
+use std::mem::{size_of, transmute_copy};
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+/// `CU_DEVICE_ATTRIBUTE_MAX_PERSISTING_L2_CACHE_SIZE`
+const MAX_PERSISTING_L2_CACHE_SIZE: c_int = 108;
+
+/// `CU_DEVICE_ATTRIBUTE_MAX_ACCESS_POLICY_WINDOW_SIZE`
+const MAX_ACCESS_POLICY_WINDOW_SIZE: c_int = 109;
+
+/// `CU_LIMIT_PERSISTING_L2_CACHE_SIZE`
+const LIMIT_PERSISTING_L2_CACHE_SIZE: c_int = 0x06;
+
+/// `CU_STREAM_ATTRIBUTE_ACCESS_POLICY_WINDOW`
+const STREAM_ATTRIBUTE_ACCESS_POLICY_WINDOW: c_int = 1;
+
+/// `CU_ACCESS_PROPERTY_STREAMING`
+const ACCESS_PROPERTY_STREAMING: c_int = 1;
+
+/// `CU_ACCESS_PROPERTY_PERSISTING`
+const ACCESS_PROPERTY_PERSISTING: c_int = 2;
+
+/// Mirrors `CUaccessPolicyWindow`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct AccessPolicyWindow {
+    base_ptr: *mut c_void,
+    num_bytes: usize,
+    hit_ratio: f32,
+    hit_prop: c_int,
+    miss_prop: c_int,
+}
+
+/// Mirrors the `CUstreamAttrValue` union.
+///
+/// Newer drivers define the union with 64 bytes, thus pad the window to
+/// prevent the driver from reading out of bounds.
+#[repr(C)]
+struct StreamAttrValue {
+    access_policy_window: AccessPolicyWindow,
+    _padding: [u8; 64 - size_of::<AccessPolicyWindow>()],
+}
+
+type DeviceGetAttributeFn = unsafe extern "C" fn(*mut c_int, c_int, CUdevice) -> CUresult;
+type CtxSetLimitFn = unsafe extern "C" fn(c_int, usize) -> CUresult;
+type CtxResetPersistingL2CacheFn = unsafe extern "C" fn() -> CUresult;
+type StreamSetAttributeFn =
+    unsafe extern "C" fn(CUstream, c_int, *const StreamAttrValue) -> CUresult;
+
+/// The driver functions, if the driver supports the access policy window.
+struct DriverFunctions {
+    device_get_attribute: DeviceGetAttributeFn,
+    ctx_set_limit: CtxSetLimitFn,
+    ctx_reset_persisting_l2_cache: CtxResetPersistingL2CacheFn,
+    stream_set_attribute: StreamSetAttributeFn,
+}
+
+static DRIVER_FUNCTIONS: Lazy<Option<DriverFunctions>> = Lazy::new(|| unsafe {
+    Some(DriverFunctions {
+        device_get_attribute: driver_function(CStr::from_bytes_with_nul_unchecked(
+            b"cuDeviceGetAttribute\0",
+        ))?,
+        ctx_set_limit: driver_function(CStr::from_bytes_with_nul_unchecked(b"cuCtxSetLimit\0"))?,
+        ctx_reset_persisting_l2_cache: driver_function(CStr::from_bytes_with_nul_unchecked(
+            b"cuCtxResetPersistingL2Cache\0",
+        ))?,
+        stream_set_attribute: driver_function(CStr::from_bytes_with_nul_unchecked(
+            b"cuStreamSetAttribute\0",
+        ))?,
+    })
+});
+
+/// Looks up a function of the loaded CUDA driver.
+///
+/// # Safety
+///
+/// `F` must be a function pointer type that matches the driver function.
+unsafe fn driver_function<F>(name: &CStr) -> Option<F> {
+    let symbol = libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr());
+    if symbol.is_null() {
+        None
+    } else {
+        Some(transmute_copy::<*mut c_void, F>(&symbol))
+    }
+}
+
+/// Returns the size of the set-aside L2 cache of the current device in bytes.
+///
+/// Returns zero if the device or the driver doesn't support L2 persistence.
+pub fn max_persisting_l2_bytes() -> Result<usize> {
+    match &*DRIVER_FUNCTIONS {
+        Some(functions) => device_attribute(functions, MAX_PERSISTING_L2_CACHE_SIZE),
+        None => Ok(0),
+    }
+}
+
+/// Sets aside `bytes` of the L2 cache for persisting accesses.
+///
+/// The set-aside cache is a context-wide limit, thus set it once before
+/// probing instead of on every launch. The size is capped at the maximum that
+/// the device supports.
+///
+/// Returns `false` without an effect if the device or the driver doesn't
+/// support L2 persistence.
+pub fn set_aside_l2_cache(bytes: usize) -> Result<bool> {
+    let functions = match &*DRIVER_FUNCTIONS {
+        Some(functions) => functions,
+        None => return Ok(false),
+    };
+
+    let max_persisting_bytes = device_attribute(functions, MAX_PERSISTING_L2_CACHE_SIZE)?;
+    if max_persisting_bytes == 0 {
+        return Ok(false);
+    }
+
+    unsafe {
+        (functions.ctx_set_limit)(
+            LIMIT_PERSISTING_L2_CACHE_SIZE,
+            bytes.min(max_persisting_bytes),
+        )
+        .to_result()
+        .map_err(|e| Error::with_chain::<Error, _>(e.into(), "Failed to set aside the L2 cache"))?;
+    }
+
+    Ok(true)
+}
+
+/// Marks `data` as L2-persisting for the kernels launched on `stream`.
+///
+/// The set-aside L2 cache should be sized to fit `data` with
+/// `set_aside_l2_cache`. If `data` is larger than the maximum set-aside
+/// cache, only a proportional share of the accesses persists, so that the
+/// window doesn't thrash the set-aside cache.
+///
+/// Returns `false` without an effect if the device or the driver doesn't
+/// support L2 persistence.
+pub fn set_access_policy_window<T>(stream: &Stream, data: LaunchableSlice<'_, T>) -> Result<bool> {
+    let functions = match &*DRIVER_FUNCTIONS {
+        Some(functions) => functions,
+        None => return Ok(false),
+    };
+
+    let max_persisting_bytes = device_attribute(functions, MAX_PERSISTING_L2_CACHE_SIZE)?;
+    let max_window_bytes = device_attribute(functions, MAX_ACCESS_POLICY_WINDOW_SIZE)?;
+    if max_persisting_bytes == 0 || max_window_bytes == 0 {
+        return Ok(false);
+    }
+
+    let data_bytes = data.len() * size_of::<T>();
+    let persisting_bytes = data_bytes.min(max_persisting_bytes);
+    let window_bytes = data_bytes.min(max_window_bytes);
+    let hit_ratio = if window_bytes == 0 {
+        0.0
+    } else {
+        (persisting_bytes as f32 / window_bytes as f32).min(1.0)
+    };
+
+    set_stream_window(
+        functions,
+        stream,
+        AccessPolicyWindow {
+            base_ptr: data.as_ptr() as *mut c_void,
+            num_bytes: window_bytes,
+            hit_ratio,
+            hit_prop: ACCESS_PROPERTY_PERSISTING,
+            miss_prop: ACCESS_PROPERTY_STREAMING,
+        },
+    )?;
+
+    Ok(true)
+}
+
+/// Removes the access policy window from `stream`, and resets all persisting
+/// cache lines to normal.
+///
+/// Returns `false` without an effect if the device or the driver doesn't
+/// support L2 persistence.
+pub fn reset_access_policy_window(stream: &Stream) -> Result<bool> {
+    let functions = match &*DRIVER_FUNCTIONS {
+        Some(functions) => functions,
+        None => return Ok(false),
+    };
+
+    if device_attribute(functions, MAX_PERSISTING_L2_CACHE_SIZE)? == 0 {
+        return Ok(false);
+    }
+
+    set_stream_window(
+        functions,
+        stream,
+        AccessPolicyWindow {
+            base_ptr: ptr::null_mut(),
+            num_bytes: 0,
+            hit_ratio: 0.0,
+            hit_prop: ACCESS_PROPERTY_STREAMING,
+            miss_prop: ACCESS_PROPERTY_STREAMING,
+        },
+    )?;
+
+    unsafe {
+        (functions.ctx_reset_persisting_l2_cache)()
+            .to_result()
+            .map_err(|e| {
+                Error::with_chain::<Error, _>(e.into(), "Failed to reset the persisting L2 cache")
+            })?;
+    }
+
+    Ok(true)
+}
+
+/// Returns a device attribute of the current device.
+///
+/// Drivers that don't know the attribute reject it as an invalid value. In
+/// this case, the attribute is reported as zero.
+fn device_attribute(functions: &DriverFunctions, attribute: c_int) -> Result<usize> {
+    let device = current_device_id()?;
+    let mut value: c_int = 0;
+
+    let result = unsafe { (functions.device_get_attribute)(&mut value, attribute, device) };
+    if let CUresult::CUDA_ERROR_INVALID_VALUE = result {
+        return Ok(0);
+    }
+
+    result.to_result().map_err(|e| {
+        Error::with_chain::<Error, _>(e.into(), "Failed to get the L2 cache attributes")
+    })?;
+
+    Ok(value as usize)
+}
+
+/// Sets the access policy window attribute of the stream.
+fn set_stream_window(
+    functions: &DriverFunctions,
+    stream: &Stream,
+    access_policy_window: AccessPolicyWindow,
+) -> Result<()> {
+    let value = StreamAttrValue {
+        access_policy_window,
+        _padding: [0; 64 - size_of::<AccessPolicyWindow>()],
+    };
+
+    unsafe {
+        // FIXME: Find a safer solution to replace transmute_copy!!!
+        let cu_stream = transmute_copy::<Stream, CUstream>(stream);
+        (functions.stream_set_attribute)(cu_stream, STREAM_ATTRIBUTE_ACCESS_POLICY_WINDOW, &value)
+            .to_result()
+            .map_err(|e| {
+                Error::with_chain::<Error, _>(e.into(), "Failed to set the access policy window")
+            })?;
+    }
+
+    Ok(())
+}
@@ -22,6 +22,7 @@ use std::io::Error as IoError;
 use std::io::{BufRead, BufReader};
 use std::mem::{size_of, size_of_val};
 use std::os::raw::{c_int, c_long, c_uint, c_ulong, c_void};
+use std::ptr;
 
 mod bindings {
     use super::*;
@@ -423,6 +424,57 @@ pub fn numa_node_of_cpu(cpu_id: u16) -> Result<u16> {
     }
 }
 
+/// Returns the NUMA node of each page in the memory region.
+///
+/// The nodes are queried with `move_pages`, which doesn't move any pages.
+/// `page_size` sets the granularity of the query. The memory of a page is
+/// allocated on first touch. Thus, the node of a page that hasn't been touched
+/// yet is `None`.
+pub fn page_nodes<T>(data: &[T], page_size: usize) -> Result<Vec<Option<u16>>> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let begin = data.as_ptr() as usize & !(page_size - 1);
+    let end = data.as_ptr() as usize + size_of_val(data);
+    let pages: Vec<*const c_void> = (begin..end)
+        .step_by(page_size)
+        .map(|page| page as *const c_void)
+        .collect();
+    let mut status: Vec<c_int> = vec![0; pages.len()];
+
+    // A null list of target nodes queries the pages' current nodes
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_move_pages,
+            0 as libc::pid_t,
+            pages.len() as c_ulong,
+            pages.as_ptr(),
+            ptr::null::<c_int>(),
+            status.as_mut_ptr(),
+            0 as c_int,
+        )
+    };
+    if ret == -1 {
+        Err(Error::with_chain(
+            io::Error::last_os_error(),
+            "Couldn't query the NUMA nodes of the pages",
+        ))?;
+    }
+
+    status
+        .into_iter()
+        .map(|node| match node {
+            node if node >= 0 => Ok(Some(node as u16)),
+            node if node == -libc::ENOENT => Ok(None),
+            err => Err(Error::with_chain(
+                io::Error::from_raw_os_error(-err),
+                "Couldn't query the NUMA node of a page",
+            )),
+        })
+        .collect()
+}
+
 /// NUMA node memory information
 pub struct NumaMemInfo {
     /// Total bytes
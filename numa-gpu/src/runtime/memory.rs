@@ -16,6 +16,7 @@ use rustacuda::memory::{
     CopyDestination, DeviceBuffer, DeviceCopy, DevicePointer, DeviceSlice, LockedBuffer,
     UnifiedBuffer, UnifiedPointer,
 };
+use rustacuda::stream::Stream;
 
 use std::convert::{TryFrom, TryInto};
 use std::ffi;
@@ -23,6 +24,7 @@ use std::ops::Deref;
 use std::ops::DerefMut;
 use std::ptr;
 
+use super::cuda_wrapper;
 use super::linux_wrapper::{MemProtect, MemProtectFlags};
 use super::numa::{DistributedNumaMemory, NumaMemory};
 use crate::error::{Error, ErrorKind, Result};
@@ -142,6 +144,39 @@ impl<T: DeviceCopy> Mem<T> {
         }
     }
 
+    /// Returns the memory as a slice, if the CPU can access the memory.
+    ///
+    /// Returns an `InvalidConversion` error for CUDA device memory.
+    pub fn try_as_slice(&self) -> Result<&[T]> {
+        self.try_into().map_err(|(err, _)| err)
+    }
+
+    /// Returns the memory as a mutable slice, if the CPU can access the memory.
+    ///
+    /// Returns an `InvalidConversion` error for CUDA device memory.
+    pub fn try_as_mut_slice(&mut self) -> Result<&mut [T]> {
+        self.try_into().map_err(|(err, _)| err)
+    }
+
+    /// Sets all bytes of the memory to zero.
+    ///
+    /// CUDA device and unified memory are zeroed by the GPU with an
+    /// asynchronous memset on `stream`. Thus, unified memory isn't migrated
+    /// to the CPU. Synchronize on `stream` before reading the memory. Host
+    /// memory is zeroed by the CPU before the function returns.
+    pub fn zero_async(&mut self, stream: &Stream) -> Result<()> {
+        match self {
+            CudaDevMem(_) | CudaUniMem(_) => {
+                cuda_wrapper::memset_d8_async(self.as_launchable_mut_slice(), 0, stream)
+            }
+            _ => {
+                let slice = self.try_as_mut_slice()?;
+                unsafe { ptr::write_bytes(slice.as_mut_ptr(), 0, slice.len()) };
+                Ok(())
+            }
+        }
+    }
+
     pub fn as_launchable_slice(&self) -> LaunchableSlice<'_, T> {
         // Note: This is implementation is a short-cut. The proper way is
         // implemented in as_launchable_mut_ptr(). The reason we don't do the
@@ -737,6 +772,17 @@ impl<'a, T> LaunchableSlice<'a, T> {
     pub unsafe fn as_slice(&self) -> &'a [T] {
         self.0
     }
+
+    /// Returns an iterator over contiguous chunks of at most `chunk_len`
+    /// elements.
+    ///
+    /// The chunks only offset the pointer, and thus never dereference the
+    /// memory on the CPU.
+    ///
+    /// Panics if `chunk_len` is 0.
+    pub fn chunks(&self, chunk_len: usize) -> impl Iterator<Item = LaunchableSlice<'a, T>> {
+        self.0.chunks(chunk_len).map(LaunchableSlice)
+    }
 }
 
 /// A slice of mutable memory that can be dereferenced on the GPU.
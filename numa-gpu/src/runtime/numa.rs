@@ -24,9 +24,14 @@ use crate::error::{ErrorKind, Result, ResultExt};
 
 use libc::{madvise, mlock, mmap, munlock, munmap};
 
+use rayon::ThreadPool;
+
+use std::cmp;
+use std::fs::File;
 use std::io::Error as IoError;
-use std::mem::size_of;
+use std::mem::{size_of, size_of_val};
 use std::ops::{Deref, DerefMut};
+use std::os::unix::io::AsRawFd;
 use std::ptr;
 use std::slice;
 
@@ -35,7 +40,7 @@ use num_rational::Ratio;
 /// Re-export Linux's NUMA bindings
 pub use super::linux_wrapper::{
     numa_node_of_cpu as node_of_cpu, numa_run_on_node as run_on_node,
-    numa_set_strict as set_strict, numa_tonode_memory as tonode_memory,
+    numa_set_strict as set_strict, numa_tonode_memory as tonode_memory, page_nodes,
 };
 
 /// Specifies the allocation page type
@@ -164,6 +169,12 @@ impl<T> NumaMemory<T> {
     ///
     /// `mmap` with `MMAP_ANONYMOUS` allocates pages. Separate alignment for cacheline alignment is
     /// not necessary.
+    ///
+    /// == First Touch ==
+    ///
+    /// The region is bound to `node` before any of its pages are touched. Thus, the pages are
+    /// allocated on `node` even if a thread on another node touches them first, e.g., when
+    /// partitioning into a remote buffer. `verify_node` checks the placement.
     pub fn new(len: usize, node: u16, page_type: PageType) -> Self {
         assert_ne!(len, 0);
 
@@ -239,6 +250,62 @@ impl<T> NumaMemory<T> {
         }
     }
 
+    /// Maps `len` elements of a file into memory, starting at byte `offset`.
+    ///
+    /// The mapping is private and read-only, i.e., writing to the memory region
+    /// causes a segmentation fault. Pages are read lazily from the page cache
+    /// on first access and are shared with the page cache. Thus, `node` is only
+    /// recorded as the region's location and doesn't migrate any pages.
+    ///
+    /// The `offset` must be a multiple of the page size. The file must contain
+    /// at least `len` elements after `offset`.
+    pub fn map_file(file: &File, offset: u64, len: usize, node: u16) -> Result<Self> {
+        let page_size = PageType::Default.page_size()?;
+        if len == 0 || offset % page_size as u64 != 0 {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Cannot map {} elements at offset {}, which is not page-aligned",
+                len, offset
+            )))?;
+        }
+
+        let size = len
+            .checked_mul(size_of::<T>())
+            .ok_or_else(|| ErrorKind::IntegerOverflow("Mapping is too large".to_string()))?;
+        let file_len = file.metadata()?.len();
+        if (size as u64)
+            .checked_add(offset)
+            .map_or(true, |end| end > file_len)
+        {
+            Err(ErrorKind::InvalidArgument(format!(
+                "File of {} bytes is too short to map {} bytes at offset {}",
+                file_len, size, offset
+            )))?;
+        }
+
+        let pointer = unsafe {
+            mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                offset as libc::off_t,
+            )
+        };
+        if pointer == libc::MAP_FAILED {
+            Err(ErrorKind::Io(IoError::last_os_error()))?;
+        }
+
+        Ok(Self {
+            pointer: pointer as *mut T,
+            len,
+            node,
+            page_type: PageType::Default,
+            is_memory_locked: false,
+            is_page_locked: false,
+        })
+    }
+
     /// Extracts a slice of the entire memory region.
     pub fn as_slice(&self) -> &[T] {
         unsafe { slice::from_raw_parts(self.pointer, self.len) }
@@ -253,6 +320,34 @@ impl<T> NumaMemory<T> {
     pub fn node(&self) -> u16 {
         self.node
     }
+
+    /// Returns `true` if the memory region is page-locked.
+    pub fn is_page_locked(&self) -> bool {
+        self.is_page_locked
+    }
+
+    /// Checks that all touched pages reside on the memory region's node.
+    ///
+    /// Returns an error that counts the misplaced pages otherwise. Pages that
+    /// haven't been touched yet are ignored.
+    pub fn verify_node(&self) -> Result<()> {
+        let nodes = page_nodes(self.as_slice(), ProcessorCache::page_size())?;
+        let misplaced = nodes
+            .iter()
+            .filter(|node| node.map_or(false, |node| node != self.node))
+            .count();
+
+        if misplaced != 0 {
+            Err(ErrorKind::RuntimeError(format!(
+                "{} of {} pages don't reside on NUMA node {}",
+                misplaced,
+                nodes.len(),
+                self.node
+            )))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<T> Deref for NumaMemory<T> {
@@ -366,6 +461,58 @@ impl<T> Drop for NumaMemory<T> {
 unsafe impl<T> Send for NumaMemory<T> {}
 unsafe impl<T> Sync for NumaMemory<T> {}
 
+/// Faults in all pages of a memory region in parallel.
+///
+/// Reads and writes back one byte per page on the threads of `thread_pool`,
+/// thus the contents are preserved. The pages are split into contiguous
+/// ranges, one per thread, and each range is touched by a separate task. This
+/// is the same way as a join spawns one task per chunk of its input.
+///
+/// Rayon doesn't guarantee which thread runs a task. Thus, a page that isn't
+/// bound to a node is allocated on the node of one of the pool's threads, but
+/// not necessarily on the node of the thread that later processes it.
+///
+/// `page_size` must be a power of two. It should be the smallest page size
+/// of the region, as a page is faulted in by touching any of its bytes.
+pub fn pre_touch<T>(mem: &mut [T], page_size: usize, thread_pool: &ThreadPool) {
+    assert!(
+        page_size.is_power_of_two(),
+        "The page size must be a power of two"
+    );
+
+    let bytes = size_of_val(mem);
+    if bytes == 0 {
+        return;
+    }
+
+    // Pointers aren't Send, thus pass the addresses to the threads
+    let begin = mem.as_mut_ptr() as usize;
+    let end = begin + bytes;
+    let first_page = begin & !(page_size - 1);
+    let pages = (end - first_page + page_size - 1) / page_size;
+
+    let threads = thread_pool.current_num_threads();
+    let pages_per_thread = (pages + threads - 1) / threads;
+
+    thread_pool.scope(|s| {
+        for chunk_begin in (0..pages).step_by(pages_per_thread) {
+            let chunk_end = cmp::min(chunk_begin + pages_per_thread, pages);
+            s.spawn(move |_| {
+                for page in chunk_begin..chunk_end {
+                    let addr = cmp::max(first_page + page * page_size, begin) as *mut u8;
+
+                    // Safe, because the mutable borrow of `mem` excludes other
+                    // accesses
+                    unsafe {
+                        let byte = ptr::read_volatile(addr);
+                        ptr::write_volatile(addr, byte);
+                    }
+                }
+            });
+        }
+    });
+}
+
 /// Specifies the ratio of total memory allocated on the NUMA node
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct NodeRatio {
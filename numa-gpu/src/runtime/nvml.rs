@@ -14,8 +14,65 @@
 
 pub use nvml_impl::*;
 
+use std::collections::VecDeque;
+
+/// Detects when the GPU clock has reached a steady state.
+///
+/// GPUs boost their clock while they are cool, and throttle it once they hit
+/// their power or thermal limit. Thus, early measurements overstate the
+/// sustained performance. The detector considers the clock steady when `K`
+/// consecutive samples lie within a tolerance of each other.
+#[derive(Clone, Debug)]
+pub struct SteadyClockDetector {
+    tolerance_mhz: u32,
+    required_samples: usize,
+    samples: VecDeque<u32>,
+}
+
+impl SteadyClockDetector {
+    /// Creates a detector that requires `required_samples` consecutive
+    /// samples, which differ by at most `tolerance_mhz`.
+    ///
+    /// At least one sample is required.
+    pub fn new(tolerance_mhz: u32, required_samples: usize) -> Self {
+        let required_samples = required_samples.max(1);
+
+        Self {
+            tolerance_mhz,
+            required_samples,
+            samples: VecDeque::with_capacity(required_samples),
+        }
+    }
+
+    /// Adds a clock sample.
+    ///
+    /// Returns the mean clock of the last `K` samples if the clock is steady,
+    /// and `None` otherwise.
+    pub fn push(&mut self, clock_mhz: u32) -> Option<u32> {
+        if self.samples.len() == self.required_samples {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(clock_mhz);
+
+        if self.samples.len() < self.required_samples {
+            return None;
+        }
+
+        let min = *self.samples.iter().min()?;
+        let max = *self.samples.iter().max()?;
+        if max - min > self.tolerance_mhz {
+            return None;
+        }
+
+        let sum: u64 = self.samples.iter().map(|&s| s as u64).sum();
+        Some((sum / self.samples.len() as u64) as u32)
+    }
+}
+
 #[cfg(target_arch = "aarch64")]
 mod nvml_impl {
+    use crate::error::{ErrorKind, Result};
+    use rustacuda::device::Device as CudaDevice;
     use std::fmt;
 
     pub struct ThrottleReasons;
@@ -25,16 +82,33 @@ mod nvml_impl {
             Ok(())
         }
     }
+
+    /// Returns the version of the Nvidia driver
+    ///
+    /// NVML is not available on this platform, thus always returns an error.
+    pub fn nvidia_driver_version() -> Result<String> {
+        Err(ErrorKind::RuntimeError("NVML is not supported on this platform".to_string()).into())
+    }
+
+    /// Returns the current SM clock of the GPU in MHz
+    ///
+    /// NVML is not available on this platform, thus always returns an error.
+    pub fn sm_clock_mhz(_device: &CudaDevice) -> Result<u32> {
+        Err(ErrorKind::RuntimeError("NVML is not supported on this platform".to_string()).into())
+    }
 }
 
 #[cfg(not(target_arch = "aarch64"))]
 mod nvml_impl {
     use crate::error::{ErrorKind, Result};
+    use crate::runtime::hw_info;
     use crate::runtime::linux_wrapper::{numa_node_of_cpu, CpuSet};
     use nvml_wrapper::bitmasks::device::ThrottleReasons as NvmlTR;
     use nvml_wrapper::device::Device;
     use nvml_wrapper::enum_wrappers::device::Clock as GpuClock;
     use nvml_wrapper::error::NvmlError;
+    use nvml_wrapper::NVML;
+    use rustacuda::device::Device as CudaDevice;
     use std::convert::From;
     use std::fmt;
     use std::mem;
@@ -82,6 +156,33 @@ mod nvml_impl {
         }
     }
 
+    /// Returns the version of the Nvidia driver
+    ///
+    /// For example: `440.33.01`
+    pub fn nvidia_driver_version() -> Result<String> {
+        let nvml = NVML::init().map_err(|e| ErrorKind::RuntimeError(e.to_string()))?;
+        let version = nvml
+            .sys_driver_version()
+            .map_err(|e| ErrorKind::RuntimeError(e.to_string()))?;
+
+        Ok(version)
+    }
+
+    /// Returns the current SM clock of the GPU in MHz
+    ///
+    /// NVML enumerates devices in another order than CUDA. Thus, the NVML
+    /// device is looked up by the PCI bus ID of the CUDA device.
+    pub fn sm_clock_mhz(device: &CudaDevice) -> Result<u32> {
+        let pci_id = hw_info::pci_id(device)?;
+        let nvml = NVML::init().map_err(|e| ErrorKind::RuntimeError(e.to_string()))?;
+        let clock = nvml
+            .device_by_pci_bus_id(pci_id)
+            .and_then(|device| device.clock_info(GpuClock::SM))
+            .map_err(|e| ErrorKind::RuntimeError(e.to_string()))?;
+
+        Ok(clock)
+    }
+
     /// Extra features for GPU devices with NVML
     pub trait NvmlDeviceExtra {
         /// Returns the NUMA memory affinity of the GPU device
@@ -18,7 +18,7 @@ use crate::error::{ErrorKind, Result};
 use nvtx_sys::{
     nvtxMarkA, nvtxRangeEnd, nvtxRangeId_t, nvtxRangePop, nvtxRangePushA, nvtxRangeStartA,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::ffi::CStr;
 use std::fmt;
 
@@ -78,7 +78,7 @@ pub struct Range {
 ///
 /// Each range ID is associated with a range, and can be matched to the output
 /// of a profiler. For example, nvprof lists the range ID in its output.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct RangeId(nvtxRangeId_t);
 
 impl Range {
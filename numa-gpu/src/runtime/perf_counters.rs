@@ -0,0 +1,460 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CPU hardware performance counters based on Linux `perf_event_open`.
+//!
+//! `PerfCounters` measure only the thread that opens them. Inherited counters
+//! would cover threads spawned later on, but Linux adds a child's counts only
+//! when the child exits, which a thread pool's threads don't do between runs.
+//! Instead, each thread of a pool opens its own counters in a
+//! `PerfCounterGroup`, which then sums up the counts of all threads. Only
+//! user-space events are counted.
+//!
+//! Opening the counters requires `/proc/sys/kernel/perf_event_paranoid` to be
+//! at most 2, or the `CAP_PERFMON` capability.
+//!
+//! The generic cache events of Linux cover the L1 data cache, the last-level
+//! cache, and the TLBs. Linux has no generic event for the L2 cache. Thus, L2
+//! misses are counted with a raw event, which is only known for Intel and AMD
+//! CPUs.
+
+use crate::error::{Error, ErrorKind, Result};
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+// perf_event_attr.type
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_TYPE_HW_CACHE: u32 = 3;
+const PERF_TYPE_RAW: u32 = 4;
+
+// perf_event_attr.config for PERF_TYPE_HARDWARE
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+
+// perf_event_attr.config for PERF_TYPE_HW_CACHE, see perf_event_open(2)
+const PERF_COUNT_HW_CACHE_L1D: u64 = 0;
+const PERF_COUNT_HW_CACHE_LL: u64 = 2;
+const PERF_COUNT_HW_CACHE_DTLB: u64 = 3;
+const PERF_COUNT_HW_CACHE_ITLB: u64 = 4;
+const PERF_COUNT_HW_CACHE_OP_READ: u64 = 0;
+const PERF_COUNT_HW_CACHE_RESULT_MISS: u64 = 1;
+
+// perf_event_attr.read_format
+const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+
+// perf_event_attr flag bits
+const ATTR_FLAG_DISABLED: u64 = 1 << 0;
+const ATTR_FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const ATTR_FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+// The ioctl numbers are _IO('$', n), which has a different encoding on POWER
+#[cfg(any(target_arch = "powerpc", target_arch = "powerpc64"))]
+const IOC_NONE: libc::c_ulong = 1 << 29;
+#[cfg(not(any(target_arch = "powerpc", target_arch = "powerpc64")))]
+const IOC_NONE: libc::c_ulong = 0;
+
+const PERF_EVENT_IOC_ENABLE: libc::c_ulong = IOC_NONE | 0x2400;
+const PERF_EVENT_IOC_DISABLE: libc::c_ulong = IOC_NONE | 0x2401;
+const PERF_EVENT_IOC_RESET: libc::c_ulong = IOC_NONE | 0x2403;
+
+/// `struct perf_event_attr` of `linux/perf_event.h` (`PERF_ATTR_SIZE_VER5`).
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+    config2: u64,
+    branch_sample_type: u64,
+    sample_regs_user: u64,
+    sample_stack_user: u32,
+    clockid: i32,
+    sample_regs_intr: u64,
+    aux_watermark: u32,
+    sample_max_stack: u16,
+    reserved_2: u16,
+}
+
+/// A hardware event that can be counted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PerfEvent {
+    Cycles,
+    Instructions,
+    L1dMisses,
+    L2Misses,
+    LlcMisses,
+    DtlbMisses,
+    ItlbMisses,
+}
+
+impl PerfEvent {
+    /// Returns all events in a fixed order.
+    pub fn all() -> &'static [PerfEvent] {
+        &[
+            PerfEvent::Cycles,
+            PerfEvent::Instructions,
+            PerfEvent::L1dMisses,
+            PerfEvent::L2Misses,
+            PerfEvent::LlcMisses,
+            PerfEvent::DtlbMisses,
+            PerfEvent::ItlbMisses,
+        ]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PerfEvent::Cycles => "cycles",
+            PerfEvent::Instructions => "instructions",
+            PerfEvent::L1dMisses => "l1d-misses",
+            PerfEvent::L2Misses => "l2-misses",
+            PerfEvent::LlcMisses => "llc-misses",
+            PerfEvent::DtlbMisses => "dtlb-misses",
+            PerfEvent::ItlbMisses => "itlb-misses",
+        }
+    }
+
+    fn type_and_config(self) -> Result<(u32, u64)> {
+        let read_miss = |cache: u64| {
+            (
+                PERF_TYPE_HW_CACHE,
+                cache
+                    | (PERF_COUNT_HW_CACHE_OP_READ << 8)
+                    | (PERF_COUNT_HW_CACHE_RESULT_MISS << 16),
+            )
+        };
+
+        let type_and_config = match self {
+            PerfEvent::Cycles => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CPU_CYCLES),
+            PerfEvent::Instructions => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_INSTRUCTIONS),
+            PerfEvent::L1dMisses => read_miss(PERF_COUNT_HW_CACHE_L1D),
+            PerfEvent::L2Misses => (PERF_TYPE_RAW, l2_misses_raw_config()?),
+            PerfEvent::LlcMisses => read_miss(PERF_COUNT_HW_CACHE_LL),
+            PerfEvent::DtlbMisses => read_miss(PERF_COUNT_HW_CACHE_DTLB),
+            PerfEvent::ItlbMisses => read_miss(PERF_COUNT_HW_CACHE_ITLB),
+        };
+
+        Ok(type_and_config)
+    }
+}
+
+/// Returns the raw event that counts L2 misses on the host CPU.
+///
+/// The raw config is `umask << 8 | event`. On Intel (Haswell and later), the
+/// event is `L2_RQSTS.MISS`. On AMD (Zen and later), the event is
+/// `L2_CACHE_REQ_STAT.IC_DC_MISS_IN_L2`.
+#[cfg(target_arch = "x86_64")]
+fn l2_misses_raw_config() -> Result<u64> {
+    const INTEL_L2_RQSTS_MISS: u64 = 0x3f24;
+    const AMD_L2_CACHE_REQ_STAT_MISS: u64 = 0x0964;
+
+    // CPUID leaf 0 returns the vendor string in EBX, EDX, ECX
+    let cpuid = unsafe { std::arch::x86_64::__cpuid(0) };
+    let vendor: Vec<u8> = [cpuid.ebx, cpuid.edx, cpuid.ecx]
+        .iter()
+        .flat_map(|reg| reg.to_le_bytes())
+        .collect();
+
+    match vendor.as_slice() {
+        b"GenuineIntel" => Ok(INTEL_L2_RQSTS_MISS),
+        b"AuthenticAMD" => Ok(AMD_L2_CACHE_REQ_STAT_MISS),
+        _ => Err(ErrorKind::InvalidArgument(format!(
+            "L2 misses aren't supported on CPU vendor {}",
+            String::from_utf8_lossy(&vendor)
+        ))
+        .into()),
+    }
+}
+
+/// Returns the raw event that counts L2 misses on the host CPU.
+#[cfg(not(target_arch = "x86_64"))]
+fn l2_misses_raw_config() -> Result<u64> {
+    Err(
+        ErrorKind::InvalidArgument("L2 misses are only supported on x86_64 CPUs".to_string())
+            .into(),
+    )
+}
+
+impl fmt::Display for PerfEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for PerfEvent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PerfEvent::all()
+            .iter()
+            .copied()
+            .find(|event| event.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = PerfEvent::all().iter().map(|e| e.name()).collect();
+                ErrorKind::InvalidArgument(format!(
+                    "Unknown perf event \"{}\", expected one of: {}",
+                    s,
+                    names.join(", ")
+                ))
+                .into()
+            })
+    }
+}
+
+/// The event counts of a measurement.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PerfCounts {
+    counts: Vec<(PerfEvent, u64)>,
+}
+
+impl PerfCounts {
+    /// Returns the count of `event`, or `None` if the event wasn't counted.
+    pub fn get(&self, event: PerfEvent) -> Option<u64> {
+        self.counts
+            .iter()
+            .find(|(e, _)| *e == event)
+            .map(|&(_, count)| count)
+    }
+
+    /// Returns the counts in the order in which the events were configured.
+    pub fn iter(&self) -> impl Iterator<Item = &(PerfEvent, u64)> {
+        self.counts.iter()
+    }
+
+    /// Adds the counts of another measurement of the same events.
+    fn add(&mut self, other: &PerfCounts) {
+        if self.counts.is_empty() {
+            self.counts = other.counts.clone();
+            return;
+        }
+
+        self.counts
+            .iter_mut()
+            .zip(other.counts.iter())
+            .for_each(|((_, count), &(_, other_count))| *count += other_count);
+    }
+}
+
+/// A set of open hardware counters of the calling thread.
+///
+/// The counters are closed on drop.
+#[derive(Debug)]
+pub struct PerfCounters {
+    counters: Vec<(PerfEvent, RawFd)>,
+}
+
+impl PerfCounters {
+    /// Opens disabled counters for the events on the calling thread.
+    ///
+    /// Fails if the kernel denies access, or if the CPU doesn't support an
+    /// event.
+    pub fn new(events: &[PerfEvent]) -> Result<Self> {
+        let mut counters = Self {
+            counters: Vec::with_capacity(events.len()),
+        };
+
+        for &event in events {
+            let (type_, config) = event.type_and_config()?;
+            let attr = PerfEventAttr {
+                type_,
+                size: mem::size_of::<PerfEventAttr>() as u32,
+                config,
+                read_format: PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+                flags: ATTR_FLAG_DISABLED | ATTR_FLAG_EXCLUDE_KERNEL | ATTR_FLAG_EXCLUDE_HV,
+                ..PerfEventAttr::default()
+            };
+
+            // Measure the calling thread on any CPU, without a group leader
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_perf_event_open,
+                    &attr as *const PerfEventAttr,
+                    0 as libc::pid_t,
+                    -1 as libc::c_int,
+                    -1 as libc::c_int,
+                    0 as libc::c_ulong,
+                )
+            };
+            if fd == -1 {
+                Err(Error::with_chain(
+                    io::Error::last_os_error(),
+                    format!("Couldn't open perf counter for {}", event),
+                ))?;
+            }
+
+            // Drop closes the already opened counters on error
+            counters.counters.push((event, fd as RawFd));
+        }
+
+        Ok(counters)
+    }
+
+    /// Resets and starts the counters.
+    pub fn start(&mut self) -> Result<()> {
+        self.ioctl_all(PERF_EVENT_IOC_RESET)?;
+        self.ioctl_all(PERF_EVENT_IOC_ENABLE)
+    }
+
+    /// Stops the counters and returns the counts since the last `start`.
+    ///
+    /// If the kernel multiplexed the counters, the counts are scaled up to the
+    /// time that the counters were enabled.
+    pub fn stop(&mut self) -> Result<PerfCounts> {
+        self.ioctl_all(PERF_EVENT_IOC_DISABLE)?;
+
+        let counts = self
+            .counters
+            .iter()
+            .map(|&(event, fd)| {
+                // value, time_enabled, time_running
+                let mut buf = [0_u64; 3];
+                let len = mem::size_of_val(&buf);
+                let ret = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, len) };
+                if ret != len as isize {
+                    Err(Error::with_chain(
+                        io::Error::last_os_error(),
+                        format!("Couldn't read perf counter for {}", event),
+                    ))?;
+                }
+
+                let [value, enabled, running] = buf;
+                let count = if running == 0 {
+                    0
+                } else if running < enabled {
+                    (value as f64 * enabled as f64 / running as f64) as u64
+                } else {
+                    value
+                };
+
+                Ok((event, count))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(PerfCounts { counts })
+    }
+
+    /// Counts the events while running `func`.
+    pub fn measure<F, R>(&mut self, func: F) -> Result<(R, PerfCounts)>
+    where
+        F: FnOnce() -> R,
+    {
+        self.start()?;
+        let result = func();
+        let counts = self.stop()?;
+
+        Ok((result, counts))
+    }
+
+    fn ioctl_all(&self, request: libc::c_ulong) -> Result<()> {
+        for &(event, fd) in &self.counters {
+            if unsafe { libc::ioctl(fd, request as _, 0) } == -1 {
+                Err(Error::with_chain(
+                    io::Error::last_os_error(),
+                    format!("Couldn't control perf counter for {}", event),
+                ))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PerfCounters {
+    fn drop(&mut self) {
+        for &(_, fd) in &self.counters {
+            unsafe { libc::close(fd) };
+        }
+    }
+}
+
+/// The counters of a set of threads, e.g., of a thread pool.
+///
+/// Each thread registers itself once, e.g., in the thread pool's start
+/// handler. Afterwards, any thread can start and stop the counters of all
+/// registered threads, and read the summed counts.
+#[derive(Debug)]
+pub struct PerfCounterGroup {
+    events: Vec<PerfEvent>,
+    threads: Mutex<Vec<PerfCounters>>,
+}
+
+impl PerfCounterGroup {
+    /// Creates a group without any registered threads.
+    pub fn new(events: &[PerfEvent]) -> Self {
+        Self {
+            events: events.to_vec(),
+            threads: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Opens counters for the calling thread, and adds them to the group.
+    pub fn register_current_thread(&self) -> Result<()> {
+        let counters = PerfCounters::new(&self.events)?;
+        self.lock()?.push(counters);
+
+        Ok(())
+    }
+
+    /// Returns the number of registered threads.
+    pub fn threads(&self) -> Result<usize> {
+        Ok(self.lock()?.len())
+    }
+
+    /// Resets and starts the counters of all threads.
+    pub fn start(&self) -> Result<()> {
+        self.lock()?
+            .iter_mut()
+            .try_for_each(|counters| counters.start())
+    }
+
+    /// Stops the counters of all threads, and returns the sum of their counts
+    /// since the last `start`.
+    pub fn stop(&self) -> Result<PerfCounts> {
+        let mut sum = PerfCounts::default();
+        for counters in self.lock()?.iter_mut() {
+            sum.add(&counters.stop()?);
+        }
+
+        Ok(sum)
+    }
+
+    /// Counts the events of all threads while running `func`.
+    pub fn measure<F, R>(&self, func: F) -> Result<(R, PerfCounts)>
+    where
+        F: FnOnce() -> R,
+    {
+        self.start()?;
+        let result = func();
+        let counts = self.stop()?;
+
+        Ok((result, counts))
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Vec<PerfCounters>>> {
+        self.threads.lock().map_err(|_| {
+            ErrorKind::RuntimeError("Perf counter group lock is poisoned".to_string()).into()
+        })
+    }
+}
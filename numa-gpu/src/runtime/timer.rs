@@ -0,0 +1,140 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timers that measure the phases of CPU and GPU operators in nanoseconds.
+//!
+//! CPU phases are timed with the monotonic wall clock, whereas GPU phases are
+//! timed with CUDA events on a stream. CUDA reports the elapsed time between
+//! two events in milliseconds. `PhaseTimer` hides the difference, so that all
+//! phases are reported in nanoseconds.
+
+use crate::error::{ErrorKind, Result};
+use rustacuda::event::{Event, EventFlags};
+use rustacuda::stream::Stream;
+use std::time::Instant;
+
+/// Measures the duration of a phase.
+///
+/// A timer starts on construction.
+pub trait PhaseTimer {
+    /// Marks the end of the phase.
+    fn stop(&mut self) -> Result<()>;
+
+    /// Returns the duration of the phase in nanoseconds.
+    ///
+    /// Waits for the phase to finish. Fails if the timer hasn't been stopped.
+    fn elapsed_ns(&self) -> Result<f64>;
+}
+
+/// Times a phase that runs on the CPU.
+#[derive(Debug)]
+pub struct CpuTimer {
+    start: Instant,
+    stop: Option<Instant>,
+}
+
+impl CpuTimer {
+    /// Starts the timer.
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            stop: None,
+        }
+    }
+}
+
+impl PhaseTimer for CpuTimer {
+    fn stop(&mut self) -> Result<()> {
+        self.stop = Some(Instant::now());
+        Ok(())
+    }
+
+    fn elapsed_ns(&self) -> Result<f64> {
+        let stop = self.stop.ok_or_else(not_stopped)?;
+        Ok(stop.duration_since(self.start).as_nanos() as f64)
+    }
+}
+
+/// Times a phase that runs on a CUDA stream.
+///
+/// The start and stop events are recorded on the stream. Thus, the timer
+/// measures the GPU time between the preceding and the following work on the
+/// stream, and not the time at which the CPU enqueues the work.
+pub struct CudaEventTimer<'s> {
+    stream: &'s Stream,
+    start: Event,
+    stop: Event,
+    is_stopped: bool,
+}
+
+impl<'s> CudaEventTimer<'s> {
+    /// Starts the timer by recording the start event on `stream`.
+    pub fn start(stream: &'s Stream) -> Result<Self> {
+        let start = Event::new(EventFlags::DEFAULT)?;
+        let stop = Event::new(EventFlags::DEFAULT)?;
+        start.record(stream)?;
+
+        Ok(Self {
+            stream,
+            start,
+            stop,
+            is_stopped: false,
+        })
+    }
+
+    /// Returns the start and stop events, e.g., to record a trace.
+    pub fn events(&self) -> (&Event, &Event) {
+        (&self.start, &self.stop)
+    }
+
+    /// Returns the stream on which the events are recorded.
+    pub fn stream(&self) -> &'s Stream {
+        self.stream
+    }
+}
+
+impl<'s> PhaseTimer for CudaEventTimer<'s> {
+    fn stop(&mut self) -> Result<()> {
+        self.stop.record(self.stream)?;
+        self.is_stopped = true;
+        Ok(())
+    }
+
+    fn elapsed_ns(&self) -> Result<f64> {
+        if !self.is_stopped {
+            Err(not_stopped())?;
+        }
+
+        self.stop.synchronize()?;
+        event_elapsed_ns(&self.start, &self.stop)
+    }
+}
+
+/// Returns the time between two completed CUDA events in nanoseconds.
+///
+/// Useful if a phase spans multiple streams, and thus has a stop event per
+/// stream.
+pub fn event_elapsed_ns(start: &Event, stop: &Event) -> Result<f64> {
+    let millis = stop.elapsed_time_f32(start)?;
+    Ok(millis_to_nanos(millis))
+}
+
+/// Converts the milliseconds reported by CUDA events to nanoseconds.
+pub fn millis_to_nanos(millis: f32) -> f64 {
+    millis as f64 * 1_000_000.0
+}
+
+fn not_stopped() -> crate::error::Error {
+    ErrorKind::LogicError("The phase timer hasn't been stopped".to_string()).into()
+}
@@ -0,0 +1,182 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of kernel timings to the Chrome trace event format.
+//!
+//! The trace is a JSON file that can be opened with `chrome://tracing` or
+//! [Perfetto](https://ui.perfetto.dev). Each span is written as a "complete"
+//! event (phase `X`). Spans are grouped into rows by their stream ID. CUDA
+//! streams are numbered in the order in which the recorder first sees them.
+//!
+//! The format is specified in the [Trace Event Format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+//! document.
+
+use super::timer::{self, CudaEventTimer};
+use crate::error::Result;
+use cuda_driver_sys::CUstream;
+use rustacuda::event::{Event, EventFlags};
+use rustacuda::stream::Stream;
+use std::io::Write;
+use std::mem::transmute_copy;
+
+/// A named time span on a stream.
+///
+/// Times are in nanoseconds, relative to the origin of the `TraceRecorder`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceSpan {
+    pub name: String,
+    pub start_ns: u64,
+    pub dur_ns: u64,
+    pub stream_id: u32,
+}
+
+/// Collects spans and writes them as a Chrome trace.
+///
+/// The recorder records an origin event on creation. Spans measured with
+/// CUDA events are placed on the timeline relative to the origin.
+///
+/// CUDA reports the elapsed time between events in milliseconds as `f32`.
+/// Thus, the resolution decreases for spans that start long after the origin,
+/// e.g., to about 8 microseconds after 100 seconds.
+#[derive(Debug)]
+pub struct TraceRecorder {
+    origin: Event,
+    spans: Vec<TraceSpan>,
+    streams: Vec<CUstream>,
+}
+
+impl TraceRecorder {
+    /// Creates a new recorder, and records its origin on `stream`.
+    pub fn new(stream: &Stream) -> Result<Self> {
+        let origin = Event::new(EventFlags::DEFAULT)?;
+        origin.record(stream)?;
+
+        Ok(Self {
+            origin,
+            spans: Vec::new(),
+            streams: Vec::new(),
+        })
+    }
+
+    /// Returns the ID of the trace row that shows the spans of `stream`.
+    pub fn stream_id(&mut self, stream: &Stream) -> u32 {
+        // FIXME: Find a safer solution to replace transmute_copy!!!
+        let cu_stream = unsafe { transmute_copy::<Stream, CUstream>(stream) };
+
+        let id = match self.streams.iter().position(|&s| s == cu_stream) {
+            Some(id) => id,
+            None => {
+                self.streams.push(cu_stream);
+                self.streams.len() - 1
+            }
+        };
+
+        id as u32
+    }
+
+    /// Adds a span with explicit times.
+    pub fn add_span(&mut self, name: &str, start_ns: u64, dur_ns: u64, stream_id: u32) {
+        self.spans.push(TraceSpan {
+            name: name.to_string(),
+            start_ns,
+            dur_ns,
+            stream_id,
+        });
+    }
+
+    /// Adds the span between two CUDA events that were recorded on `stream`.
+    ///
+    /// Both events must have completed, e.g., by synchronizing on `stop`.
+    pub fn record_events(
+        &mut self,
+        name: &str,
+        start: &Event,
+        stop: &Event,
+        stream: &Stream,
+    ) -> Result<()> {
+        let start_ms = start.elapsed_time_f32(&self.origin)?;
+        let dur_ms = stop.elapsed_time_f32(start)?;
+        let stream_id = self.stream_id(stream);
+
+        self.add_span(
+            name,
+            millis_to_nanos(start_ms),
+            millis_to_nanos(dur_ms),
+            stream_id,
+        );
+
+        Ok(())
+    }
+
+    /// Adds the span of a stopped timer.
+    ///
+    /// Waits for the timer's phase to finish.
+    pub fn record_timer(&mut self, name: &str, timer: &CudaEventTimer<'_>) -> Result<()> {
+        let (start, stop) = timer.events();
+        stop.synchronize()?;
+        self.record_events(name, start, stop, timer.stream())
+    }
+
+    /// Returns the recorded spans.
+    pub fn spans(&self) -> &[TraceSpan] {
+        &self.spans
+    }
+
+    /// Writes the spans as a Chrome trace in the JSON object format.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> Result<()> {
+        write!(writer, "{{\"traceEvents\":[")?;
+
+        for (i, span) in self.spans.iter().enumerate() {
+            if i != 0 {
+                write!(writer, ",")?;
+            }
+
+            // Chrome trace timestamps are in microseconds
+            write!(
+                writer,
+                "{{\"name\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":0,\"tid\":{}}}",
+                escape_json(&span.name),
+                span.start_ns as f64 / 1000.0,
+                span.dur_ns as f64 / 1000.0,
+                span.stream_id
+            )?;
+        }
+
+        writeln!(writer, "],\"displayTimeUnit\":\"ns\"}}")?;
+
+        Ok(())
+    }
+}
+
+fn millis_to_nanos(millis: f32) -> u64 {
+    timer::millis_to_nanos(millis.max(0.0)) as u64
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
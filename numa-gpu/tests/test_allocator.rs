@@ -0,0 +1,110 @@
+// Copyright 2019-2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::memory::Mem;
+use numa_gpu::runtime::numa::PageType;
+
+use rustacuda::quick_init;
+
+use std::error::Error;
+
+const LEN: usize = 1000;
+
+fn assert_aligned(ptr: *const u64, alignment: usize) {
+    assert_eq!(
+        ptr as usize % alignment,
+        0,
+        "Pointer {:p} is not aligned to {} bytes",
+        ptr,
+        alignment
+    );
+}
+
+#[test]
+fn test_alloc_mem_aligned_sysmem() -> Result<(), Box<dyn Error>> {
+    for &alignment in &[8, 128, 4096] {
+        let mem: Mem<u64> = Allocator::alloc_mem_aligned(MemType::SysMem, LEN, alignment)?;
+        assert_eq!(mem.len(), LEN);
+        assert_aligned(mem.as_ptr(), alignment);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_alloc_deref_mem_aligned_sysmem() -> Result<(), Box<dyn Error>> {
+    for &alignment in &[8, 128, 4096] {
+        let mem = Allocator::alloc_deref_mem_aligned::<u64>(DerefMemType::SysMem, LEN, alignment)?;
+        assert_eq!(mem.len(), LEN);
+        assert_aligned(mem.as_ptr(), alignment);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_alloc_mem_aligned_numa() -> Result<(), Box<dyn Error>> {
+    let mem_type = MemType::NumaMem {
+        node: 0,
+        page_type: PageType::Default,
+    };
+
+    for &alignment in &[8, 128, 4096] {
+        let mem: Mem<u64> = Allocator::alloc_mem_aligned(mem_type.clone(), LEN, alignment)?;
+        assert_eq!(mem.len(), LEN);
+        assert_aligned(mem.as_ptr(), alignment);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_alloc_mem_aligned_cuda_pinned() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+
+    for &alignment in &[8, 128, 4096] {
+        let mem: Mem<u64> = Allocator::alloc_mem_aligned(MemType::CudaPinnedMem, LEN, alignment)?;
+        assert_eq!(mem.len(), LEN);
+        assert_aligned(mem.as_ptr(), alignment);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_alloc_mem_aligned_rejects_non_power_of_two() {
+    let mem = Allocator::alloc_mem_aligned::<u64>(MemType::SysMem, LEN, 100);
+    assert!(mem.is_err());
+}
+
+#[test]
+fn test_alloc_mem_aligned_rejects_overflowing_len() {
+    let mem = Allocator::alloc_mem_aligned::<u64>(MemType::SysMem, usize::MAX, 8);
+    assert!(mem.is_err());
+
+    let mem_type = DerefMemType::AlignedSysMem { align_bytes: 64 };
+    let mem = Allocator::try_alloc_deref_mem::<u64>(mem_type, usize::MAX / 4);
+    assert!(mem.is_err());
+}
+
+#[test]
+fn test_alloc_mem_aligned_rejects_excessive_alignment() {
+    let mem_type = MemType::NumaMem {
+        node: 0,
+        page_type: PageType::Default,
+    };
+    let mem = Allocator::alloc_mem_aligned::<u64>(mem_type, LEN, 1 << 40);
+    assert!(mem.is_err());
+}
@@ -0,0 +1,54 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::cache_flush::CacheFlusher;
+use numa_gpu::runtime::hw_info::ProcessorCache;
+
+#[test]
+fn flusher_is_larger_than_last_level_cache() {
+    let flusher = CacheFlusher::new();
+    let l3_bytes = ProcessorCache::L3_size();
+
+    assert!(flusher.bytes() > 0);
+    if l3_bytes > 0 && l3_bytes != usize::MAX {
+        assert!(flusher.bytes() >= 2 * l3_bytes);
+    }
+}
+
+#[test]
+fn flush_counts_each_call() {
+    let mut flusher = CacheFlusher::with_bytes(4096);
+
+    flusher.flush();
+    flusher.flush();
+
+    assert_eq!(flusher.flushes(), 2);
+}
+
+#[test]
+fn thread_pool_flush_completes_on_all_threads() {
+    // An odd thread count doesn't divide the buffer evenly
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(3)
+        .build()
+        .expect("Failed to build thread pool");
+    let mut flusher = CacheFlusher::with_thread_pool(thread_pool);
+
+    assert!(flusher.bytes() >= CacheFlusher::new().bytes());
+
+    flusher.flush();
+    flusher.flush();
+
+    assert_eq!(flusher.flushes(), 2);
+}
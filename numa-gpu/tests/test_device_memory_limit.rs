@@ -0,0 +1,57 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The device memory limit is global. Thus, all checks run in a single test,
+// so that concurrent tests don't share the limit.
+
+use numa_gpu::error::ErrorKind;
+use numa_gpu::runtime::allocator::{Allocator, MemType};
+use rustacuda::error::CudaError;
+use rustacuda::quick_init;
+use std::error::Error;
+
+const MIB: usize = 1024 * 1024;
+
+#[test]
+fn device_memory_limit_rejects_excess_and_free_restores_headroom() -> Result<(), Box<dyn Error>> {
+    let _context = quick_init()?;
+
+    Allocator::set_device_memory_limit(Some(64 * MIB));
+    assert_eq!(Some(64 * MIB), Allocator::device_memory_limit());
+
+    let first = Allocator::try_alloc_mem::<u8>(MemType::CudaDevMem, 48 * MIB)?;
+    assert_eq!(48 * MIB, Allocator::outstanding_device_memory());
+
+    let error = Allocator::try_alloc_mem::<u8>(MemType::CudaDevMem, 32 * MIB)
+        .expect_err("Allocation beyond the device memory limit succeeded");
+    assert!(
+        matches!(error.kind(), ErrorKind::Cuda(CudaError::OutOfMemory)),
+        "Expected an out-of-memory error, got: {}",
+        error
+    );
+
+    // The limit applies only to device memory
+    let _pinned = Allocator::try_alloc_mem::<u8>(MemType::CudaPinnedMem, 32 * MIB)?;
+
+    drop(first);
+    let second = Allocator::try_alloc_mem::<u8>(MemType::CudaDevMem, 32 * MIB)?;
+    assert_eq!(second.len(), 32 * MIB);
+    assert_eq!(32 * MIB, Allocator::outstanding_device_memory());
+
+    Allocator::set_device_memory_limit(None);
+    let unlimited = Allocator::try_alloc_mem::<u8>(MemType::CudaDevMem, 128 * MIB)?;
+    assert_eq!(unlimited.len(), 128 * MIB);
+
+    Ok(())
+}
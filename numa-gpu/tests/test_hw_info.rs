@@ -0,0 +1,41 @@
+// Copyright 2019-2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::hw_info::cuda_driver_version;
+
+use rustacuda::quick_init;
+
+use std::error::Error;
+
+#[test]
+fn test_cuda_driver_version_is_not_empty() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+
+    let version = cuda_driver_version()?;
+    assert!(!version.is_empty());
+    assert!(version.contains('.'));
+
+    Ok(())
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+#[test]
+fn test_nvidia_driver_version_is_not_empty() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+
+    let version = numa_gpu::runtime::nvml::nvidia_driver_version()?;
+    assert!(!version.is_empty());
+
+    Ok(())
+}
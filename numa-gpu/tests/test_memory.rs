@@ -0,0 +1,263 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use num_rational::Ratio;
+use numa_gpu::error::ErrorKind;
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::cuda_wrapper::{self, PrefetchDestination};
+use numa_gpu::runtime::hw_info::ProcessorCache;
+use numa_gpu::runtime::memory::{DerefMem, Mem};
+use numa_gpu::runtime::numa::{self, NodeRatio, NumaMemory, PageType};
+
+use rustacuda::context::CurrentContext;
+use rustacuda::device::{Device, DeviceAttribute};
+use rustacuda::quick_init;
+use rustacuda::stream::{Stream, StreamFlags};
+
+use std::convert::TryInto;
+use std::error::Error;
+
+const LEN: usize = 1000;
+
+fn check_host_slices(mut mem: Mem<u64>) -> Result<(), Box<dyn Error>> {
+    mem.try_as_mut_slice()?
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as u64);
+
+    let slice = mem.try_as_slice()?;
+    assert_eq!(slice.len(), LEN);
+    assert!(slice.iter().copied().eq(0..LEN as u64));
+
+    Ok(())
+}
+
+#[test]
+fn test_try_as_slice_sysmem() -> Result<(), Box<dyn Error>> {
+    check_host_slices(Allocator::alloc_mem(MemType::SysMem, LEN))
+}
+
+#[test]
+fn test_try_as_slice_numa() -> Result<(), Box<dyn Error>> {
+    let mem_type = MemType::NumaMem {
+        node: 0,
+        page_type: PageType::Default,
+    };
+
+    check_host_slices(Allocator::alloc_mem(mem_type, LEN))
+}
+
+#[test]
+fn test_numa_memory_pages_reside_on_node() -> Result<(), Box<dyn Error>> {
+    let mut mem = NumaMemory::<u64>::new(1 << 20, 0, PageType::Small);
+    let page_size = ProcessorCache::page_size();
+
+    // Pages are allocated on first touch
+    mem.verify_node()?;
+    assert!(numa::page_nodes(mem.as_slice(), page_size)?
+        .iter()
+        .all(Option::is_none));
+
+    mem.as_mut_slice().iter_mut().for_each(|x| *x = 1);
+    mem.verify_node()?;
+    assert!(numa::page_nodes(mem.as_slice(), page_size)?
+        .iter()
+        .all(|&node| node == Some(0)));
+
+    Ok(())
+}
+
+#[test]
+fn test_pre_touch_faults_in_pages_on_node() -> Result<(), Box<dyn Error>> {
+    const LEN: usize = 1 << 20;
+
+    let mut mem = NumaMemory::<u64>::new(LEN, 0, PageType::Small);
+    let page_size = ProcessorCache::page_size();
+    let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(4).build()?;
+
+    // Skip the first element to touch a region that isn't page-aligned
+    numa::pre_touch(&mut mem.as_mut_slice()[1..], page_size, &thread_pool);
+    assert!(numa::page_nodes(mem.as_slice(), page_size)?
+        .iter()
+        .all(|&node| node == Some(0)));
+
+    // Pre-touching preserves the contents
+    mem.as_mut_slice()
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as u64);
+    numa::pre_touch(mem.as_mut_slice(), page_size, &thread_pool);
+    assert!(mem.as_slice().iter().copied().eq(0..LEN as u64));
+
+    Ok(())
+}
+
+#[test]
+fn test_try_as_slice_cuda_pinned() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+
+    check_host_slices(Allocator::alloc_mem(MemType::CudaPinnedMem, LEN))
+}
+
+#[test]
+fn test_try_as_slice_cuda_unified() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+
+    check_host_slices(Allocator::alloc_mem(MemType::CudaUniMem, LEN))
+}
+
+#[test]
+fn test_try_as_slice_cuda_device_fails() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+
+    let mut mem: Mem<u64> = Allocator::alloc_mem(MemType::CudaDevMem, LEN);
+
+    match mem.try_as_slice() {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidConversion(_) => {}
+            _ => panic!("Expected an InvalidConversion error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error when accessing device memory as a slice"),
+    }
+    match mem.try_as_mut_slice() {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidConversion(_) => {}
+            _ => panic!("Expected an InvalidConversion error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error when accessing device memory as a slice"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_deref_mem_conversions_of_each_variant() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+
+    let host_mem_types = [
+        MemType::SysMem,
+        MemType::AlignedSysMem { align_bytes: 4096 },
+        MemType::NumaMem {
+            node: 0,
+            page_type: PageType::Default,
+        },
+        MemType::DistributedNumaMem {
+            nodes: vec![NodeRatio {
+                node: 0,
+                ratio: Ratio::from_integer(1),
+            }]
+            .into_boxed_slice(),
+            page_type: PageType::Default,
+        },
+        MemType::CudaPinnedMem,
+        MemType::CudaUniMem,
+    ];
+
+    for mem_type in host_mem_types.iter() {
+        let _: DerefMemType = mem_type.clone().try_into()?;
+
+        let mut mem: Mem<u64> = Allocator::alloc_mem(mem_type.clone(), LEN);
+        mem.try_as_mut_slice()?
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = i as u64);
+
+        let deref_mem: DerefMem<u64> = match mem.try_into() {
+            Ok(deref_mem) => deref_mem,
+            Err((e, _)) => panic!("Failed to convert {:?}: {}", mem_type, e),
+        };
+        assert!(deref_mem.iter().copied().eq(0..LEN as u64));
+    }
+
+    let deref_mem_type: Result<DerefMemType, _> = MemType::CudaDevMem.try_into();
+    assert!(deref_mem_type.is_err());
+
+    let mem: Mem<u64> = Allocator::alloc_mem(MemType::CudaDevMem, LEN);
+    let deref_mem: Result<DerefMem<u64>, _> = mem.try_into();
+    match deref_mem {
+        Err((e, mem)) => {
+            assert!(matches!(e.kind(), ErrorKind::InvalidConversion(_)));
+            assert!(matches!(mem, Mem::CudaDevMem(_)));
+            assert_eq!(mem.len(), LEN);
+        }
+        Ok(_) => panic!("Expected an error when converting device memory"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cuda_mem_types_are_gpu_accessible() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+    let device = CurrentContext::get_device()?;
+
+    for mem_type in &[
+        MemType::CudaPinnedMem,
+        MemType::CudaUniMem,
+        MemType::CudaDevMem,
+        MemType::NumaPinnedMem {
+            node: 0,
+            page_type: PageType::Default,
+        },
+    ] {
+        assert!(mem_type.is_gpu_accessible(&device)?);
+    }
+
+    assert!(!MemType::CudaDevMem.is_cpu_accessible());
+    assert!(MemType::CudaUniMem.is_cpu_accessible());
+
+    Ok(())
+}
+
+#[test]
+fn test_prefetch_unified_to_destination() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+
+    let is_concurrent_managed_access_supported =
+        CurrentContext::get_device()?.get_attribute(DeviceAttribute::ConcurrentManagedAccess)?;
+    if is_concurrent_managed_access_supported == 0 {
+        return Ok(());
+    }
+
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    let mut mem: Mem<u64> = Allocator::alloc_mem(MemType::CudaUniMem, LEN);
+    let um = match mem {
+        Mem::CudaUniMem(ref mut um) => um,
+        _ => unreachable!(),
+    };
+
+    for &destination in &[
+        PrefetchDestination::current_device()?,
+        PrefetchDestination::Cpu,
+    ] {
+        cuda_wrapper::prefetch_async(um.as_unified_ptr(), um.len(), destination, &stream)?;
+        stream.synchronize()?;
+    }
+
+    let invalid_device = PrefetchDestination::Device(Device::num_devices()? as i32);
+    assert!(
+        cuda_wrapper::prefetch_async(um.as_unified_ptr(), um.len(), invalid_device, &stream)
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_prefetch_destination() {
+    assert_eq!("cpu".parse().ok(), Some(PrefetchDestination::Cpu));
+    assert_eq!("1".parse().ok(), Some(PrefetchDestination::Device(1)));
+    assert!("-1".parse::<PrefetchDestination>().is_err());
+    assert!("gpu".parse::<PrefetchDestination>().is_err());
+}
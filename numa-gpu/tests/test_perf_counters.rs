@@ -0,0 +1,105 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::perf_counters::{PerfCounterGroup, PerfCounters, PerfEvent};
+use std::error::Error;
+use std::sync::Arc;
+
+// Counters are unavailable without permissions or inside most VMs. Thus, the
+// counter tests must be run explicitly with `cargo test -- --ignored`.
+
+#[test]
+#[ignore = "requires access to perf events, see perf_event_paranoid"]
+fn perf_counters_start_and_stop() -> Result<(), Box<dyn Error>> {
+    let events = [PerfEvent::Instructions, PerfEvent::L1dMisses];
+
+    let mut counters = PerfCounters::new(&events)?;
+
+    let data: Vec<u64> = (0..1 << 20).collect();
+    let (sum, counts) = counters.measure(|| data.iter().sum::<u64>())?;
+    assert_eq!(sum, (data.len() as u64 * (data.len() as u64 - 1)) / 2);
+
+    let counted: Vec<_> = counts.iter().map(|&(event, _)| event).collect();
+    assert_eq!(counted, events);
+    assert!(counts.get(PerfEvent::Instructions).ok_or("Missing count")? > 0);
+    assert_eq!(counts.get(PerfEvent::Cycles), None);
+
+    // The counters can be restarted
+    counters.start()?;
+    counters.stop()?;
+
+    Ok(())
+}
+
+#[test]
+#[ignore = "requires access to perf events, see perf_event_paranoid"]
+fn perf_counter_group_counts_pool_threads() -> Result<(), Box<dyn Error>> {
+    const THREADS: usize = 4;
+
+    let group = Arc::new(PerfCounterGroup::new(&[PerfEvent::Instructions]));
+    let handler_group = group.clone();
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(THREADS)
+        .start_handler(move |_| {
+            handler_group
+                .register_current_thread()
+                .expect("Couldn't open perf counters")
+        })
+        .build()?;
+
+    // The pool spawns its threads asynchronously, thus wait until all threads
+    // ran their start handler
+    while group.threads()? < THREADS {
+        std::thread::yield_now();
+    }
+
+    let count_instructions = |len: u64| -> Result<u64, Box<dyn Error>> {
+        let (_, counts) = group.measure(|| {
+            thread_pool.scope(|s| {
+                for _ in 0..THREADS {
+                    s.spawn(|_| {
+                        let data: Vec<u64> = (0..len).collect();
+                        assert_eq!(data.iter().sum::<u64>(), (len * (len - 1)) / 2);
+                    });
+                }
+            })
+        })?;
+        Ok(counts.get(PerfEvent::Instructions).ok_or("Missing count")?)
+    };
+
+    // The main thread isn't registered, thus the counts grow only with the
+    // work on the pool threads
+    let small = count_instructions(1 << 10)?;
+    let large = count_instructions(1 << 22)?;
+    assert!(
+        large > small,
+        "Counted {} instructions for the small and {} for the large work",
+        small,
+        large
+    );
+
+    Ok(())
+}
+
+#[test]
+fn perf_event_parses_names() -> Result<(), Box<dyn Error>> {
+    for &event in PerfEvent::all() {
+        assert_eq!(event.to_string().parse::<PerfEvent>()?, event);
+    }
+    assert_eq!("LLC-Misses".parse::<PerfEvent>()?, PerfEvent::LlcMisses);
+    assert_eq!("L2-Misses".parse::<PerfEvent>()?, PerfEvent::L2Misses);
+    assert!("l3-misses".parse::<PerfEvent>().is_err());
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The test binary never initializes CUDA. Thus, page-locking fails, which
+// simulates a pinning failure, e.g., due to the locked memory limit.
+//
+// The pinning fallback is global. Thus, all checks run in a single test, so
+// that concurrent tests don't share the setting.
+
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::memory::{DerefMem, Mem};
+use numa_gpu::runtime::numa::PageType;
+use std::error::Error;
+
+const LEN: usize = 1024 * 1024;
+
+#[test]
+fn failed_pinning_returns_error_or_falls_back_to_unpinned() -> Result<(), Box<dyn Error>> {
+    let pinned = DerefMemType::NumaPinnedMem {
+        node: 0,
+        page_type: PageType::Default,
+    };
+
+    assert!(!Allocator::pinning_fallback());
+    let error = Allocator::try_alloc_deref_mem::<u64>(pinned.clone(), LEN)
+        .expect_err("Pinning succeeded without an initialized CUDA driver");
+    assert!(
+        error.to_string().contains("RLIMIT_MEMLOCK"),
+        "Expected guidance on the locked memory limit, got: {}",
+        error
+    );
+
+    let error = Allocator::try_alloc_mem::<u64>(pinned.clone().into(), LEN)
+        .expect_err("Pinning succeeded without an initialized CUDA driver");
+    assert!(error.to_string().contains("RLIMIT_MEMLOCK"));
+
+    Allocator::set_pinning_fallback(true);
+    assert!(Allocator::pinning_fallback());
+
+    match Allocator::try_alloc_deref_mem::<u64>(pinned.clone(), LEN)? {
+        DerefMem::NumaMem(mut mem) => {
+            assert!(!mem.is_page_locked());
+            assert_eq!(mem.node(), 0);
+            assert_eq!(mem.len(), LEN);
+
+            // The unpinned memory is still usable
+            mem.as_mut_slice().iter_mut().for_each(|x| *x = 1);
+            assert_eq!(mem.as_slice().iter().sum::<u64>(), LEN as u64);
+        }
+        _ => panic!("Expected NUMA memory"),
+    }
+
+    match Allocator::try_alloc_mem::<u64>(
+        MemType::NumaPinnedMem {
+            node: 0,
+            page_type: PageType::Default,
+        },
+        LEN,
+    )? {
+        Mem::NumaMem(mem) => assert!(!mem.is_page_locked()),
+        _ => panic!("Expected NUMA memory"),
+    }
+
+    Allocator::set_pinning_fallback(false);
+
+    Ok(())
+}
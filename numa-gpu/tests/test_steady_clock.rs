@@ -0,0 +1,62 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::nvml::SteadyClockDetector;
+
+/// Returns the index of the first steady sample and the steady clock.
+fn detect(trace: &[u32], tolerance_mhz: u32, samples: usize) -> Option<(usize, u32)> {
+    let mut detector = SteadyClockDetector::new(tolerance_mhz, samples);
+
+    trace
+        .iter()
+        .enumerate()
+        .find_map(|(i, &clock)| detector.push(clock).map(|steady| (i, steady)))
+}
+
+#[test]
+fn boosted_clock_settles_at_power_cap() {
+    // The GPU boosts, heats up, and then throttles to its power cap
+    let trace = [
+        1980, 1980, 1965, 1950, 1905, 1860, 1800, 1755, 1740, 1740, 1725, 1740, 1740, 1725,
+    ];
+
+    let (index, clock) = detect(&trace, 15, 4).expect("Clock must become steady");
+
+    assert_eq!(index, 11);
+    assert_eq!(clock, (1740 + 1740 + 1725 + 1740) / 4);
+}
+
+#[test]
+fn initial_boost_is_not_steady() {
+    // The boost clock is constant for a few samples, but not for long enough
+    let trace = [1980, 1980, 1980, 1890, 1800, 1800, 1800, 1800, 1800];
+
+    let (index, clock) = detect(&trace, 0, 4).expect("Clock must become steady");
+
+    assert_eq!(index, 7);
+    assert_eq!(clock, 1800);
+}
+
+#[test]
+fn fluctuating_clock_is_never_steady() {
+    let trace = [1800, 1740, 1800, 1740, 1800, 1740, 1800, 1740];
+
+    assert_eq!(detect(&trace, 30, 3), None);
+}
+
+#[test]
+fn single_sample_is_steady_immediately() {
+    assert_eq!(detect(&[1410], 0, 1), Some((0, 1410)));
+    assert_eq!(detect(&[1410], 0, 0), Some((0, 1410)));
+}
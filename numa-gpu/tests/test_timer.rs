@@ -0,0 +1,73 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::timer::{self, CpuTimer, CudaEventTimer, PhaseTimer};
+use rustacuda::quick_init;
+use rustacuda::stream::{Stream, StreamFlags};
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn cpu_timer_measures_sleep() -> Result<(), Box<dyn Error>> {
+    const SLEEP_NS: f64 = 50_000_000.0;
+    const TOLERANCE_NS: f64 = 250_000_000.0;
+
+    let mut timer = CpuTimer::start();
+    thread::sleep(Duration::from_nanos(SLEEP_NS as u64));
+    timer.stop()?;
+
+    let elapsed = timer.elapsed_ns()?;
+    assert!(
+        elapsed >= SLEEP_NS && elapsed < SLEEP_NS + TOLERANCE_NS,
+        "Measured {} ns for a sleep of {} ns",
+        elapsed,
+        SLEEP_NS
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cpu_timer_fails_if_not_stopped() {
+    let timer = CpuTimer::start();
+    assert!(timer.elapsed_ns().is_err());
+}
+
+#[test]
+fn event_millis_convert_to_nanos() {
+    assert_eq!(timer::millis_to_nanos(0.0), 0.0);
+    assert_eq!(timer::millis_to_nanos(1.0), 1_000_000.0);
+    assert_eq!(timer::millis_to_nanos(2.5), 2_500_000.0);
+    assert_eq!(timer::millis_to_nanos(0.125), 125_000.0);
+}
+
+#[test]
+fn cuda_event_timer_reports_event_time_in_nanos() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    let mut timer = CudaEventTimer::start(&stream)?;
+    assert!(timer.elapsed_ns().is_err());
+    timer.stop()?;
+
+    let elapsed = timer.elapsed_ns()?;
+    let (start, stop) = timer.events();
+    let millis = stop.elapsed_time_f32(start)?;
+
+    assert!(elapsed >= 0.0);
+    assert_eq!(elapsed, timer::millis_to_nanos(millis));
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::timer::{CudaEventTimer, PhaseTimer};
+use numa_gpu::runtime::trace::TraceRecorder;
+use rustacuda::event::{Event, EventFlags};
+use rustacuda::quick_init;
+use rustacuda::stream::{Stream, StreamFlags};
+use serde_json::Value;
+use std::error::Error;
+
+#[test]
+fn trace_json_parses_and_contains_all_spans() -> Result<(), Box<dyn Error>> {
+    let _ctx = quick_init()?;
+    let streams = [
+        Stream::new(StreamFlags::NON_BLOCKING, None)?,
+        Stream::new(StreamFlags::NON_BLOCKING, None)?,
+    ];
+
+    let mut recorder = TraceRecorder::new(&streams[0])?;
+    let start_event = Event::new(EventFlags::DEFAULT)?;
+    let stop_event = Event::new(EventFlags::DEFAULT)?;
+
+    for stream in [&streams[0], &streams[1], &streams[0]].iter() {
+        start_event.record(stream)?;
+        stop_event.record(stream)?;
+        stop_event.synchronize()?;
+        recorder.record_events("event \"span\"", &start_event, &stop_event, stream)?;
+    }
+
+    let mut timer = CudaEventTimer::start(&streams[1])?;
+    timer.stop()?;
+    recorder.record_timer("timer", &timer)?;
+
+    recorder.add_span("explicit", 1_500, 2_000, 7);
+
+    let mut json = Vec::new();
+    recorder.write_json(&mut json)?;
+
+    let trace: Value = serde_json::from_slice(&json)?;
+    let events = trace["traceEvents"]
+        .as_array()
+        .ok_or("Expected an array of trace events")?;
+
+    assert_eq!(events.len(), 5);
+    assert_eq!(events.len(), recorder.spans().len());
+    assert!(events[..3]
+        .iter()
+        .all(|e| e["name"] == "event \"span\"" && e["ph"] == "X"));
+
+    // Each stream has its own row
+    let tids: Vec<_> = events[..4].iter().map(|e| e["tid"].as_u64()).collect();
+    assert_eq!(tids, vec![Some(0), Some(1), Some(0), Some(1)]);
+
+    assert_eq!(events[4]["ts"].as_f64(), Some(1.5));
+    assert_eq!(events[4]["dur"].as_f64(), Some(2.0));
+    assert_eq!(events[4]["tid"].as_u64(), Some(7));
+
+    Ok(())
+}
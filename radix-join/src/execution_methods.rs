@@ -13,5 +13,8 @@
 // limitations under the License.
 
 pub mod cpu_partitioned_radix_join;
+pub mod cpu_radix_join;
+pub mod gpu_build_partitioned_join;
+pub mod gpu_no_partitioning_join;
 pub mod gpu_radix_join;
 pub mod gpu_triton_join;
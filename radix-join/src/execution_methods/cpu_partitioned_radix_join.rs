@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::error::{ErrorKind, Result};
-use crate::measurement::harness::RadixJoinPoint;
+use crate::measurement::harness::{PartitionHistograms, RadixJoinPoint};
 use cstr::cstr;
 use data_store::join_data::JoinData;
 use datagen::relation::KeyAttribute;
@@ -27,6 +27,7 @@ use numa_gpu::runtime::linux_wrapper;
 use numa_gpu::runtime::memory::*;
 use numa_gpu::runtime::numa::PageType;
 use numa_gpu::runtime::nvtx::Range;
+use numa_gpu::runtime::timer::{CpuTimer, PhaseTimer};
 use numa_gpu::utils::DeviceType;
 use rustacuda::context::{CacheConfig, CurrentContext, SharedMemoryConfig};
 use rustacuda::event::{Event, EventFlags};
@@ -132,6 +133,7 @@ pub fn cpu_partitioned_radix_join<T>(
     _page_type: PageType,
     _partition_dim: (&GridSize, &BlockSize),
     join_dim: (&GridSize, &BlockSize),
+    partition_histogram: bool,
 ) -> Result<(i64, RadixJoinPoint)>
 where
     T: Default
@@ -192,27 +194,27 @@ where
                 DerefMemType::AlignedSysMem {
                     align_bytes: sql_ops::CPU_CACHE_LINE_SIZE as usize,
                 },
-            ))
+            )?)
         })
         .collect::<Result<_>>()?;
 
-    let mut inner_rel_partitions = PartitionedRelation::new(
-        data.build_relation_key.len(),
+    let mut inner_rel_partitions = PartitionedRelation::try_new(
+        data.build_relation.len(),
         histogram_algorithm_fst.either(|cpu| cpu.into(), |gpu| gpu.into()),
         radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
         max_chunks_1st,
         Allocator::mem_alloc_fn(partitions_mem_type.clone()),
         Allocator::mem_alloc_fn(partitions_mem_type.clone()),
-    );
+    )?;
 
-    let mut outer_rel_partitions = PartitionedRelation::new(
-        data.probe_relation_key.len(),
+    let mut outer_rel_partitions = PartitionedRelation::try_new(
+        data.probe_relation.len(),
         histogram_algorithm_fst.either(|cpu| cpu.into(), |gpu| gpu.into()),
         radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
         max_chunks_1st,
         Allocator::mem_alloc_fn(partitions_mem_type.clone()),
         Allocator::mem_alloc_fn(partitions_mem_type.clone()),
-    );
+    )?;
 
     let mut inner_rel_partition_offsets = PartitionOffsets::new(
         histogram_algorithm_fst.either(|cpu| cpu.into(), |gpu| gpu.into()),
@@ -236,21 +238,21 @@ where
     let partitions_malloc_time = partitions_malloc_timer.elapsed();
 
     let prefix_sum_range = Range::new(cstr!("phase_prefix_sum"));
-    let prefix_sum_timer = Instant::now();
+    let mut prefix_sum_timer = CpuTimer::start();
 
-    let inner_key_slice: &[T] = (&data.build_relation_key).try_into().map_err(|_| {
+    let inner_key_slice: &[T] = data.build_relation.key().try_into().map_err(|_| {
         ErrorKind::RuntimeError("Failed to run CPU prefix sum on device memory".into())
     })?;
-    let inner_pay_slice: &[T] = (&data.build_relation_payload).try_into().map_err(|_| {
+    let inner_pay_slice: &[T] = data.build_relation.value().try_into().map_err(|_| {
         ErrorKind::RuntimeError("Failed to run CPU prefix sum on device memory".into())
     })?;
     let inner_key_chunks = inner_key_slice.input_chunks::<T>(max_chunks_1st)?;
     let inner_pay_chunks = inner_pay_slice.input_chunks::<T>(max_chunks_1st)?;
 
-    let outer_key_slice: &[T] = (&data.probe_relation_key).try_into().map_err(|_| {
+    let outer_key_slice: &[T] = data.probe_relation.key().try_into().map_err(|_| {
         ErrorKind::RuntimeError("Failed to run CPU prefix sum on device memory".into())
     })?;
-    let outer_pay_slice: &[T] = (&data.probe_relation_payload).try_into().map_err(|_| {
+    let outer_pay_slice: &[T] = data.probe_relation.value().try_into().map_err(|_| {
         ErrorKind::RuntimeError("Failed to run CPU prefix sum on device memory".into())
     })?;
     let outer_key_chunks = outer_key_slice.input_chunks::<T>(max_chunks_1st)?;
@@ -286,9 +288,18 @@ where
         }
     });
 
-    let prefix_sum_time = prefix_sum_timer.elapsed().as_nanos() as f64;
+    prefix_sum_timer.stop()?;
     prefix_sum_range.end();
 
+    let partition_histograms = if partition_histogram {
+        Some(PartitionHistograms {
+            inner: inner_rel_partition_offsets.histogram_and_offsets()?.0,
+            outer: outer_rel_partition_offsets.histogram_and_offsets()?.0,
+        })
+    } else {
+        None
+    };
+
     let state_malloc_timer = Instant::now();
 
     let max_inner_partition_len =
@@ -453,7 +464,7 @@ where
     let state_malloc_time = state_malloc_timer.elapsed();
 
     let partition_range = Range::new(cstr!("phase_partition"));
-    let partition_timer = Instant::now();
+    let mut partition_timer = CpuTimer::start();
 
     // Partition inner relation
     let inner_key_chunks = inner_key_slice.input_chunks::<T>(max_chunks_1st)?;
@@ -546,10 +557,10 @@ where
         }
     });
 
-    let partition_time = partition_timer.elapsed();
+    partition_timer.stop()?;
     partition_range.end();
 
-    let join_timer = Instant::now();
+    let mut join_timer = CpuTimer::start();
 
     let join_range = Range::new(cstr!("phase_join"));
     let join_start_event = Event::new(EventFlags::DEFAULT)?;
@@ -733,7 +744,7 @@ where
             Ok(())
         })?;
 
-    let join_time = join_timer.elapsed();
+    join_timer.stop()?;
     join_range.end();
 
     let mut result_sums_host = vec![0; join_result_sums_len * NUM_STREAMS];
@@ -743,10 +754,13 @@ where
         .map(
             |(
                 StreamState {
-                    join_result_sums, ..
+                    join_result_sums,
+                    radix_join,
+                    ..
                 },
                 host_sums,
             )| {
+                radix_join.check_overflow()?;
                 join_result_sums.copy_to(host_sums)?;
                 Ok(())
             },
@@ -756,13 +770,14 @@ where
     let sum = result_sums_host.iter().sum();
 
     let data_point = RadixJoinPoint {
-        prefix_sum_ns: Some(prefix_sum_time),
-        partition_ns: Some(partition_time.as_nanos() as f64),
-        join_ns: Some(join_time.as_nanos() as f64),
+        prefix_sum_ns: Some(prefix_sum_timer.elapsed_ns()?),
+        partition_ns: Some(partition_timer.elapsed_ns()?),
+        join_ns: Some(join_timer.elapsed_ns()?),
         partitions_malloc_ns: Some(partitions_malloc_time.as_nanos() as f64),
         state_malloc_ns: Some(state_malloc_time.as_nanos() as f64),
         cached_build_tuples: Some(cached_build_tuples),
         cached_probe_tuples: None,
+        partition_histograms,
     };
 
     Ok((sum, data_point))
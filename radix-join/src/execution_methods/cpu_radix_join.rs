@@ -0,0 +1,1151 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::{ErrorKind, Result};
+use crate::measurement::harness::{PartitionHistograms, RadixJoinPoint};
+use cstr::cstr;
+use data_store::join_data::JoinData;
+use data_store::relation::Relation;
+use datagen::relation::KeyAttribute;
+use num_traits::AsPrimitive;
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::cpu_affinity::CpuAffinity;
+use numa_gpu::runtime::memory::*;
+use numa_gpu::runtime::nvtx::Range;
+use numa_gpu::runtime::timer::{CpuTimer, PhaseTimer};
+use numa_gpu::utils::DeviceType;
+use rayon::prelude::*;
+use rustacuda::context::CurrentContext;
+use rustacuda::function::{BlockSize, GridSize};
+use rustacuda::memory::DeviceCopy;
+use rustacuda::stream::{Stream, StreamFlags};
+use sql_ops::join::HashingScheme;
+use sql_ops::partition::cpu_radix_partition::{
+    CpuHistogramAlgorithm, CpuRadixPartitionAlgorithm, CpuRadixPartitionable, CpuRadixPartitioner,
+};
+use sql_ops::partition::gpu_radix_partition::{
+    GpuHistogramAlgorithm, GpuRadixPartitionAlgorithm, GpuRadixPartitionable, GpuRadixPartitioner,
+};
+use sql_ops::partition::{
+    HistogramAlgorithmType, PartitionOffsets, PartitionedRelation, RadixBits,
+    RadixPartitionInputChunk, RadixPartitionInputChunkable, RadixPass, Tuple,
+};
+use std::convert::{TryFrom, TryInto};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Creates a thread pool for the CPU radix joins, and pins each thread to its
+/// core.
+///
+/// Building the pool spawns and pins the threads. Thus, the benchmark creates
+/// the pool once and reuses it for all repetitions, instead of timing the
+/// pool's setup and teardown in each repetition.
+pub fn cpu_thread_pool(threads: usize, cpu_affinity: &CpuAffinity) -> Result<rayon::ThreadPool> {
+    let boxed_cpu_affinity = Arc::new(cpu_affinity.clone());
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .start_handler(move |tid| {
+            boxed_cpu_affinity
+                .clone()
+                .set_affinity(tid as u16)
+                .expect("Couldn't set CPU core affinity")
+        })
+        .build()?;
+
+    Ok(thread_pool)
+}
+
+/// Joins the relations on the CPU with a single radix partitioning pass.
+///
+/// In contrast to the CPU no-partitioning join, which splits the relations
+/// into chunks that share one global hash table, this join assigns whole
+/// partitions to the threads. Each partition is joined independently with its
+/// own, cache-sized hash table. Thus, the threads don't share any state
+/// during the join, and each thread counts its own matches.
+///
+/// The join runs on all threads of `thread_pool`, which each partition one
+/// chunk of the relations. Each thread reuses its hash table for all of its
+/// partitions, and builds and probes the partitions in place.
+///
+/// The partition histograms are only reported if `partition_histogram` is
+/// set.
+///
+/// Returns the number of join matches.
+pub fn cpu_radix_join<T>(
+    data: &mut JoinData<T>,
+    hashing_scheme: HashingScheme,
+    histogram_algorithm: DeviceType<CpuHistogramAlgorithm, GpuHistogramAlgorithm>,
+    partition_algorithm: DeviceType<CpuRadixPartitionAlgorithm, GpuRadixPartitionAlgorithm>,
+    radix_bits: &RadixBits,
+    thread_pool: &rayon::ThreadPool,
+    partitions_mem_type: MemType,
+    partition_histogram: bool,
+) -> Result<(i64, RadixJoinPoint)>
+where
+    T: Default
+        + Copy
+        + DeviceCopy
+        + Sync
+        + Send
+        + CpuRadixPartitionable
+        + KeyAttribute
+        + AsPrimitive<u64>,
+{
+    let pass_radix_bits = check_arguments(hashing_scheme, radix_bits)?;
+    let histogram_algorithm = histogram_algorithm.cpu().ok_or_else(|| {
+        ErrorKind::InvalidArgument("Only CPU prefix sum is supported".to_string())
+    })?;
+    let partition_algorithm = partition_algorithm.cpu().ok_or_else(|| {
+        ErrorKind::InvalidArgument("Only CPU partitioning is supported".to_string())
+    })?;
+
+    let partitions_malloc_timer = Instant::now();
+
+    let threads = thread_pool.current_num_threads();
+    let max_chunks = threads as u32;
+
+    let mut radix_prnrs: Vec<_> = (0..threads)
+        .map(|_| {
+            Ok(CpuRadixPartitioner::new(
+                histogram_algorithm,
+                partition_algorithm,
+                pass_radix_bits,
+                DerefMemType::AlignedSysMem {
+                    align_bytes: sql_ops::CPU_CACHE_LINE_SIZE as usize,
+                },
+            )?)
+        })
+        .collect::<Result<_>>()?;
+
+    let mut inner_rel_partitions = PartitionedRelation::try_new(
+        data.build_relation.len(),
+        histogram_algorithm.into(),
+        pass_radix_bits,
+        max_chunks,
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+    )?;
+
+    let mut outer_rel_partitions = PartitionedRelation::try_new(
+        data.probe_relation.len(),
+        histogram_algorithm.into(),
+        pass_radix_bits,
+        max_chunks,
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+    )?;
+
+    let mut inner_rel_partition_offsets = PartitionOffsets::new(
+        histogram_algorithm.into(),
+        max_chunks,
+        pass_radix_bits,
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+    );
+
+    let mut outer_rel_partition_offsets = PartitionOffsets::new(
+        histogram_algorithm.into(),
+        max_chunks,
+        pass_radix_bits,
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+    );
+
+    inner_rel_partitions.mlock()?;
+    outer_rel_partitions.mlock()?;
+    inner_rel_partition_offsets.mlock()?;
+    outer_rel_partition_offsets.mlock()?;
+
+    let partitions_malloc_time = partitions_malloc_timer.elapsed();
+
+    let prefix_sum_range = Range::new(cstr!("phase_prefix_sum"));
+    let mut prefix_sum_timer = CpuTimer::start();
+
+    thread_pool.install(|| {
+        cpu_prefix_sum(
+            &mut radix_prnrs,
+            &data.build_relation,
+            &mut inner_rel_partition_offsets,
+        )?;
+        cpu_prefix_sum(
+            &mut radix_prnrs,
+            &data.probe_relation,
+            &mut outer_rel_partition_offsets,
+        )
+    })?;
+
+    prefix_sum_timer.stop()?;
+    prefix_sum_range.end();
+
+    let partition_histograms = if partition_histogram {
+        Some(PartitionHistograms {
+            inner: inner_rel_partition_offsets.histogram_and_offsets()?.0,
+            outer: outer_rel_partition_offsets.histogram_and_offsets()?.0,
+        })
+    } else {
+        None
+    };
+
+    let partition_range = Range::new(cstr!("phase_partition"));
+    let mut partition_timer = CpuTimer::start();
+
+    thread_pool.install(|| {
+        cpu_partition(
+            &mut radix_prnrs,
+            &data.build_relation,
+            &mut inner_rel_partition_offsets,
+            &mut inner_rel_partitions,
+        )?;
+        cpu_partition(
+            &mut radix_prnrs,
+            &data.probe_relation,
+            &mut outer_rel_partition_offsets,
+            &mut outer_rel_partitions,
+        )
+    })?;
+
+    partition_timer.stop()?;
+    partition_range.end();
+
+    let join_range = Range::new(cstr!("phase_join"));
+    let mut join_timer = CpuTimer::start();
+
+    // Each partition consists of one slice per partitioning chunk. Collect
+    // the slices up front, so that the workers don't share the relations.
+    let inner_partitions = partition_slices(&inner_rel_partitions)?;
+    let outer_partitions = partition_slices(&outer_rel_partitions)?;
+
+    let count = thread_pool.install(|| {
+        inner_partitions
+            .par_iter()
+            .zip(outer_partitions.par_iter())
+            .map_init(PartitionHashTable::new, |hash_table, (inner, outer)| {
+                hash_table.build(inner)?;
+                Ok(hash_table.probe_count(outer))
+            })
+            .try_reduce(|| 0, |left, right| Ok(left + right))
+    })? as i64;
+
+    join_timer.stop()?;
+    join_range.end();
+
+    let data_point = RadixJoinPoint {
+        prefix_sum_ns: Some(prefix_sum_timer.elapsed_ns()?),
+        partition_ns: Some(partition_timer.elapsed_ns()?),
+        join_ns: Some(join_timer.elapsed_ns()?),
+        partitions_malloc_ns: Some(partitions_malloc_time.as_nanos() as f64),
+        state_malloc_ns: None,
+        cached_build_tuples: None,
+        cached_probe_tuples: None,
+        partition_histograms,
+    };
+
+    Ok((count, data_point))
+}
+
+/// Joins the relations on the CPU, and overlaps partitioning the outer
+/// relation with the join.
+///
+/// In contrast to `cpu_radix_join`, which partitions both relations before
+/// joining any partition, only the inner relation is partitioned up front.
+/// The join workers then build the hash tables of their partitions. Each
+/// partition is owned by a single worker, i.e., partition `p` by worker
+/// `p % workers`.
+///
+/// Meanwhile, the partitioners split the outer relation into batches of about
+/// `batch_len` tuples. A partitioner partitions one batch at a time, and sends
+/// each non-empty partition of the batch to its worker, before it continues
+/// with the next batch. The worker probes the partition while the
+/// partitioners write the next batches.
+///
+/// Each worker's channel holds at most `channel_capacity` partitions. If the
+/// workers fall behind, the partitioners block on the channels. Thus, the
+/// number of partitioned batches in memory is bounded, instead of
+/// materializing the whole partitioned outer relation.
+///
+/// The partitioners run on the CPU or on the GPU, depending on the histogram
+/// and partitioning algorithms. CPU partitioners use half of the threads, and
+/// the workers use the other half. The GPU partitioner instead uses a single
+/// thread to launch its kernels, and leaves all other threads to the workers.
+/// In both cases, the partitions must be accessible by the CPU.
+///
+/// Each partitioner allocates `BATCH_BUFFERS` partitioned batches up front.
+/// After the workers probed all partitions of a batch, the batch returns to
+/// its partitioner, which reuses the memory for a later batch.
+///
+/// All partitioners and workers run concurrently on `thread_pool`, which
+/// requires at least two threads. The reported prefix sum and partition times
+/// cover only the inner relation, whereas the join time covers the whole
+/// pipeline. The partition histograms are only reported if
+/// `partition_histogram` is set.
+///
+/// Returns the number of join matches.
+pub fn cpu_radix_join_pipelined<T>(
+    data: &mut JoinData<T>,
+    hashing_scheme: HashingScheme,
+    histogram_algorithm: DeviceType<CpuHistogramAlgorithm, GpuHistogramAlgorithm>,
+    partition_algorithm: DeviceType<CpuRadixPartitionAlgorithm, GpuRadixPartitionAlgorithm>,
+    radix_bits: &RadixBits,
+    dmem_buffer_bytes: usize,
+    thread_pool: &rayon::ThreadPool,
+    partitions_mem_type: MemType,
+    partition_dim: (&GridSize, &BlockSize),
+    batch_len: usize,
+    channel_capacity: usize,
+    partition_histogram: bool,
+) -> Result<(i64, RadixJoinPoint)>
+where
+    T: Default
+        + Copy
+        + DeviceCopy
+        + Sync
+        + Send
+        + CpuRadixPartitionable
+        + GpuRadixPartitionable
+        + KeyAttribute
+        + AsPrimitive<u64>,
+{
+    let pass_radix_bits = check_arguments(hashing_scheme, radix_bits)?;
+    let algorithms = PartitionAlgorithms::new(histogram_algorithm, partition_algorithm)?;
+    let threads = thread_pool.current_num_threads();
+    if threads < 2 {
+        Err(ErrorKind::InvalidArgument(
+            "The pipelined join requires at least two threads".to_string(),
+        ))?;
+    }
+    if batch_len == 0 || channel_capacity == 0 {
+        Err(ErrorKind::InvalidArgument(
+            "The batch length and the channel capacity must be non-zero".to_string(),
+        ))?;
+    }
+    if DerefMemType::try_from(partitions_mem_type.clone()).is_err() {
+        Err(ErrorKind::InvalidArgument(
+            "The join workers require the partitions in CPU-accessible memory".to_string(),
+        ))?;
+    }
+
+    let partitioners = match algorithms {
+        PartitionAlgorithms::Cpu(..) => threads / 2,
+        PartitionAlgorithms::Gpu(..) => 1,
+    };
+    let workers = threads - partitioners;
+
+    // The GPU partitioner launches its kernels from a pool thread, which must
+    // share the caller's context
+    let context = match algorithms {
+        PartitionAlgorithms::Cpu(..) => None,
+        PartitionAlgorithms::Gpu(..) => Some(CurrentContext::get_current()?),
+    };
+
+    let partitions_malloc_timer = Instant::now();
+
+    let max_chunks = algorithms.max_chunks(threads as u32, partition_dim.0);
+
+    let mut radix_prnr =
+        algorithms.partitioner::<T>(threads, pass_radix_bits, partition_dim, dmem_buffer_bytes)?;
+
+    let mut inner_rel_partitions = PartitionedRelation::try_new(
+        data.build_relation.len(),
+        algorithms.histogram_algorithm_type(),
+        pass_radix_bits,
+        max_chunks,
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+    )?;
+
+    let mut inner_rel_partition_offsets = PartitionOffsets::new(
+        algorithms.histogram_algorithm_type(),
+        max_chunks,
+        pass_radix_bits,
+        Allocator::mem_alloc_fn(partitions_mem_type.clone()),
+    );
+
+    inner_rel_partitions.mlock()?;
+    inner_rel_partition_offsets.mlock()?;
+
+    let inner_key_slice: &[T] = data.build_relation.key().try_into().map_err(|_| {
+        ErrorKind::RuntimeError("Failed to run CPU radix join on device memory".into())
+    })?;
+    let inner_pay_slice: &[T] = data.build_relation.value().try_into().map_err(|_| {
+        ErrorKind::RuntimeError("Failed to run CPU radix join on device memory".into())
+    })?;
+    let outer_key_slice: &[T] = data.probe_relation.key().try_into().map_err(|_| {
+        ErrorKind::RuntimeError("Failed to run CPU radix join on device memory".into())
+    })?;
+    let outer_pay_slice: &[T] = data.probe_relation.value().try_into().map_err(|_| {
+        ErrorKind::RuntimeError("Failed to run CPU radix join on device memory".into())
+    })?;
+
+    // Split the outer relation with the same alignment as the partitioning
+    // input chunks.
+    let num_batches = ((outer_key_slice.len() + batch_len - 1) / batch_len).max(1) as u32;
+    let outer_key_batches = outer_key_slice.input_chunks::<T>(num_batches)?;
+    let outer_pay_batches = outer_pay_slice.input_chunks::<T>(num_batches)?;
+    let max_batch_len = outer_key_batches
+        .iter()
+        .map(|batch| batch.data.len())
+        .max()
+        .unwrap_or(0);
+
+    let batch_max_chunks = algorithms.max_chunks(1, partition_dim.0);
+    let mut batch_buffers = (0..partitioners)
+        .map(|_| {
+            BatchBuffers::try_new(
+                max_batch_len,
+                algorithms.histogram_algorithm_type(),
+                pass_radix_bits,
+                batch_max_chunks,
+                partitions_mem_type.clone(),
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let partitions_malloc_time = partitions_malloc_timer.elapsed();
+
+    let prefix_sum_range = Range::new(cstr!("phase_prefix_sum"));
+    let mut prefix_sum_timer = CpuTimer::start();
+
+    radix_prnr.prefix_sum(
+        thread_pool,
+        inner_key_slice,
+        &mut inner_rel_partition_offsets,
+    )?;
+
+    prefix_sum_timer.stop()?;
+    prefix_sum_range.end();
+
+    let partition_range = Range::new(cstr!("phase_partition"));
+    let mut partition_timer = CpuTimer::start();
+
+    radix_prnr.partition(
+        thread_pool,
+        inner_key_slice,
+        inner_pay_slice,
+        &mut inner_rel_partition_offsets,
+        &mut inner_rel_partitions,
+    )?;
+
+    partition_timer.stop()?;
+    partition_range.end();
+
+    let join_range = Range::new(cstr!("phase_join"));
+    let mut join_timer = CpuTimer::start();
+
+    let inner_partitions = partition_slices(&inner_rel_partitions)?;
+    let next_batch = AtomicUsize::new(0);
+
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..workers)
+        .map(|_| mpsc::sync_channel::<ProbePartition<T>>(channel_capacity))
+        .unzip();
+
+    let mut partitioner_results: Vec<Result<()>> = (0..partitioners).map(|_| Ok(())).collect();
+    let mut worker_results: Vec<Result<u64>> = (0..workers).map(|_| Ok(0)).collect();
+
+    // The pool runs all tasks concurrently, because it has one thread per
+    // task. Thus, the partitioners and workers can block on the channels.
+    thread_pool.scope(|s| {
+        for (worker_id, (receiver, result)) in receivers
+            .into_iter()
+            .zip(worker_results.iter_mut())
+            .enumerate()
+        {
+            let inner_partitions = &inner_partitions;
+
+            s.spawn(move |_| {
+                *result = join_outer_batches(inner_partitions, worker_id, workers, receiver);
+            });
+        }
+
+        for (buffers, result) in batch_buffers.drain(..).zip(partitioner_results.iter_mut()) {
+            let senders = senders.clone();
+            let context = context.as_ref();
+            let outer_key_batches = &outer_key_batches;
+            let outer_pay_batches = &outer_pay_batches;
+            let next_batch = &next_batch;
+
+            s.spawn(move |_| {
+                if let Some(context) = context {
+                    if let Err(error) = CurrentContext::set_current(context) {
+                        *result = Err(error.into());
+                        return;
+                    }
+                }
+
+                *result = algorithms
+                    .partitioner::<T>(1, pass_radix_bits, partition_dim, dmem_buffer_bytes)
+                    .and_then(|mut radix_prnr| {
+                        partition_outer_batches(
+                            thread_pool,
+                            &mut radix_prnr,
+                            buffers,
+                            outer_key_batches,
+                            outer_pay_batches,
+                            next_batch,
+                            &senders,
+                        )
+                    });
+            });
+        }
+
+        // The workers stop after all partitioners dropped their senders
+        drop(senders);
+    });
+
+    partitioner_results.into_iter().collect::<Result<()>>()?;
+    let count = worker_results.into_iter().sum::<Result<u64>>()? as i64;
+
+    join_timer.stop()?;
+    join_range.end();
+
+    // The outer relation is never partitioned as a whole. Thus, its histogram
+    // is computed from the keys on all threads.
+    let partition_histograms = if partition_histogram {
+        let histogram_prnr = CpuRadixPartitioner::new(
+            CpuHistogramAlgorithm::Chunked,
+            CpuRadixPartitionAlgorithm::NC,
+            pass_radix_bits,
+            DerefMemType::SysMem,
+        )?;
+        Some(PartitionHistograms {
+            inner: inner_rel_partitions.histogram()?,
+            outer: histogram_prnr
+                .histogram_and_offsets_parallel(outer_key_slice, thread_pool)?
+                .0,
+        })
+    } else {
+        None
+    };
+
+    let data_point = RadixJoinPoint {
+        prefix_sum_ns: Some(prefix_sum_timer.elapsed_ns()?),
+        partition_ns: Some(partition_timer.elapsed_ns()?),
+        join_ns: Some(join_timer.elapsed_ns()?),
+        partitions_malloc_ns: Some(partitions_malloc_time.as_nanos() as f64),
+        state_malloc_ns: None,
+        cached_build_tuples: None,
+        cached_probe_tuples: None,
+        partition_histograms,
+    };
+
+    Ok((count, data_point))
+}
+
+/// The number of partitioned batches that each partitioner of the pipelined
+/// join allocates.
+///
+/// With two batches, a partitioner writes one batch while the workers probe
+/// the other.
+const BATCH_BUFFERS: usize = 2;
+
+/// A partition of an outer relation batch that is ready to be probed.
+struct ProbePartition<T: DeviceCopy> {
+    batch: Arc<OuterBatch<T>>,
+    partition_id: u32,
+}
+
+/// A partitioned batch of the outer relation, which the workers share.
+///
+/// After the last worker dropped the batch, the batch returns to its
+/// partitioner.
+struct OuterBatch<T: DeviceCopy> {
+    partitions: Option<PartitionedRelation<Tuple<T, T>>>,
+    recycler: Mutex<Sender<PartitionedRelation<Tuple<T, T>>>>,
+}
+
+impl<T: DeviceCopy> OuterBatch<T> {
+    fn partitions(&self) -> &PartitionedRelation<Tuple<T, T>> {
+        self.partitions
+            .as_ref()
+            .expect("The batch is returned only when it's dropped")
+    }
+}
+
+impl<T: DeviceCopy> Drop for OuterBatch<T> {
+    fn drop(&mut self) {
+        if let (Some(partitions), Ok(recycler)) = (self.partitions.take(), self.recycler.lock()) {
+            // The partitioner has already exited if it failed. In this case,
+            // the batch is freed instead.
+            let _ = recycler.send(partitions);
+        }
+    }
+}
+
+/// The partitioned batches and the offsets that a partitioner of the
+/// pipelined join reuses for all of its batches.
+struct BatchBuffers<T: DeviceCopy> {
+    offsets: PartitionOffsets<Tuple<T, T>>,
+    partitions: Vec<PartitionedRelation<Tuple<T, T>>>,
+}
+
+impl<T: DeviceCopy> BatchBuffers<T> {
+    /// Allocates `BATCH_BUFFERS` partitioned batches with a capacity of
+    /// `max_batch_len` tuples each.
+    fn try_new(
+        max_batch_len: usize,
+        histogram_algorithm_type: HistogramAlgorithmType,
+        radix_bits: u32,
+        max_chunks: u32,
+        mem_type: MemType,
+    ) -> Result<Self> {
+        let mut offsets = PartitionOffsets::new(
+            histogram_algorithm_type,
+            max_chunks,
+            radix_bits,
+            Allocator::mem_alloc_fn(mem_type.clone()),
+        );
+        offsets.mlock()?;
+
+        let partitions = (0..BATCH_BUFFERS)
+            .map(|_| {
+                let mut partitions = PartitionedRelation::try_new(
+                    max_batch_len,
+                    histogram_algorithm_type,
+                    radix_bits,
+                    max_chunks,
+                    Allocator::mem_alloc_fn(mem_type.clone()),
+                    Allocator::mem_alloc_fn(mem_type.clone()),
+                )?;
+                partitions.mlock()?;
+                Ok(partitions)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            offsets,
+            partitions,
+        })
+    }
+}
+
+/// The histogram and partitioning algorithms of the pipelined join, which run
+/// either on the CPU or on the GPU.
+#[derive(Clone, Copy, Debug)]
+enum PartitionAlgorithms {
+    Cpu(CpuHistogramAlgorithm, CpuRadixPartitionAlgorithm),
+    Gpu(GpuHistogramAlgorithm, GpuRadixPartitionAlgorithm),
+}
+
+impl PartitionAlgorithms {
+    fn new(
+        histogram_algorithm: DeviceType<CpuHistogramAlgorithm, GpuHistogramAlgorithm>,
+        partition_algorithm: DeviceType<CpuRadixPartitionAlgorithm, GpuRadixPartitionAlgorithm>,
+    ) -> Result<Self> {
+        match (histogram_algorithm, partition_algorithm) {
+            (DeviceType::Cpu(histogram), DeviceType::Cpu(partition)) => {
+                Ok(Self::Cpu(histogram, partition))
+            }
+            (DeviceType::Gpu(histogram), DeviceType::Gpu(partition)) => {
+                Ok(Self::Gpu(histogram, partition))
+            }
+            _ => Err(ErrorKind::InvalidArgument(
+                "The prefix sum and the partitioning must run on the same device".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    fn histogram_algorithm_type(self) -> HistogramAlgorithmType {
+        match self {
+            Self::Cpu(histogram, _) => histogram.into(),
+            Self::Gpu(histogram, _) => histogram.into(),
+        }
+    }
+
+    /// Returns the number of chunks of a partitioned relation.
+    ///
+    /// The CPU partitioners write `cpu_chunks` chunks, i.e., one per
+    /// partitioner, whereas the GPU partitioner writes one chunk per thread
+    /// block.
+    fn max_chunks(self, cpu_chunks: u32, grid_size: &GridSize) -> u32 {
+        match self {
+            Self::Cpu(..) => cpu_chunks,
+            Self::Gpu(..) => grid_size.x,
+        }
+    }
+
+    /// Creates a partitioner.
+    ///
+    /// A CPU partitioner consists of `cpu_chunks` CPU partitioners, which
+    /// partition the relation in parallel. A GPU partitioner must be created
+    /// and used on a thread with a current CUDA context.
+    fn partitioner<T: GpuRadixPartitionable>(
+        self,
+        cpu_chunks: usize,
+        radix_bits: u32,
+        (grid_size, block_size): (&GridSize, &BlockSize),
+        dmem_buffer_bytes: usize,
+    ) -> Result<BatchPartitioner> {
+        match self {
+            Self::Cpu(histogram, partition) => {
+                let radix_prnrs = (0..cpu_chunks)
+                    .map(|_| {
+                        Ok(CpuRadixPartitioner::new(
+                            histogram,
+                            partition,
+                            radix_bits,
+                            DerefMemType::AlignedSysMem {
+                                align_bytes: sql_ops::CPU_CACHE_LINE_SIZE as usize,
+                            },
+                        )?)
+                    })
+                    .collect::<Result<_>>()?;
+
+                Ok(BatchPartitioner::Cpu(radix_prnrs))
+            }
+            Self::Gpu(histogram, partition) => {
+                let mut radix_prnr = GpuRadixPartitioner::new(
+                    histogram,
+                    partition,
+                    RadixBits::from(radix_bits),
+                    grid_size,
+                    block_size,
+                    dmem_buffer_bytes,
+                )?;
+                radix_prnr.preallocate_partition_state::<T>(RadixPass::First)?;
+                let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+                Ok(BatchPartitioner::Gpu { radix_prnr, stream })
+            }
+        }
+    }
+}
+
+/// Partitions a relation with a single pass on the CPU or on the GPU.
+///
+/// The CPU variant runs one partitioner per chunk in parallel. The GPU
+/// variant waits for its kernels to finish, because the callers time the
+/// phases and read the partitions on the CPU.
+enum BatchPartitioner {
+    Cpu(Vec<CpuRadixPartitioner>),
+    Gpu {
+        radix_prnr: GpuRadixPartitioner,
+        stream: Stream,
+    },
+}
+
+impl BatchPartitioner {
+    /// Computes the prefix sum of a relation.
+    ///
+    /// The CPU partitioners run on `thread_pool`.
+    fn prefix_sum<T>(
+        &mut self,
+        thread_pool: &rayon::ThreadPool,
+        key_slice: &[T],
+        offsets: &mut PartitionOffsets<Tuple<T, T>>,
+    ) -> Result<()>
+    where
+        T: DeviceCopy + Sync + CpuRadixPartitionable + GpuRadixPartitionable,
+    {
+        match self {
+            Self::Cpu(radix_prnrs) => {
+                thread_pool.install(|| cpu_prefix_sum_slices(radix_prnrs, key_slice, offsets))
+            }
+            Self::Gpu { radix_prnr, stream } => {
+                radix_prnr.prefix_sum(
+                    RadixPass::First,
+                    key_slice.as_launchable_slice(),
+                    offsets,
+                    stream,
+                )?;
+                stream.synchronize()?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Partitions a relation after its prefix sum is computed.
+    ///
+    /// The partitioned relation is resized to the length of the relation,
+    /// and must have enough capacity.
+    fn partition<T>(
+        &mut self,
+        thread_pool: &rayon::ThreadPool,
+        key_slice: &[T],
+        pay_slice: &[T],
+        offsets: &mut PartitionOffsets<Tuple<T, T>>,
+        partitions: &mut PartitionedRelation<Tuple<T, T>>,
+    ) -> Result<()>
+    where
+        T: DeviceCopy + Sync + Send + CpuRadixPartitionable + GpuRadixPartitionable,
+    {
+        partitions.resize(key_slice.len())?;
+
+        match self {
+            Self::Cpu(radix_prnrs) => thread_pool.install(|| {
+                cpu_partition_slices(radix_prnrs, key_slice, pay_slice, offsets, partitions)
+            }),
+            Self::Gpu { radix_prnr, stream } => {
+                radix_prnr.partition(
+                    RadixPass::First,
+                    key_slice.as_launchable_slice(),
+                    pay_slice.as_launchable_slice(),
+                    offsets,
+                    partitions,
+                    stream,
+                )?;
+                stream.synchronize()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the key and value attributes of a relation as slices.
+///
+/// The CPU partitioners split the slices into one chunk per thread.
+fn cpu_slices<T: DeviceCopy>(relation: &Relation<T, T>) -> Result<(&[T], &[T])> {
+    relation.try_as_slices().map_err(|_| {
+        ErrorKind::RuntimeError("Failed to run CPU radix join on device memory".into()).into()
+    })
+}
+
+/// Computes the prefix sum of a relation in parallel, with one CPU
+/// partitioner per chunk.
+fn cpu_prefix_sum<T>(
+    radix_prnrs: &mut [CpuRadixPartitioner],
+    relation: &Relation<T, T>,
+    offsets: &mut PartitionOffsets<Tuple<T, T>>,
+) -> Result<()>
+where
+    T: DeviceCopy + Sync + CpuRadixPartitionable,
+{
+    let (key_slice, _) = cpu_slices(relation)?;
+    cpu_prefix_sum_slices(radix_prnrs, key_slice, offsets)
+}
+
+/// Computes the prefix sum of a key slice in parallel, with one CPU
+/// partitioner per chunk.
+fn cpu_prefix_sum_slices<T>(
+    radix_prnrs: &mut [CpuRadixPartitioner],
+    key_slice: &[T],
+    offsets: &mut PartitionOffsets<Tuple<T, T>>,
+) -> Result<()>
+where
+    T: DeviceCopy + Sync + CpuRadixPartitionable,
+{
+    let key_chunks = key_slice.input_chunks::<T>(radix_prnrs.len() as u32)?;
+    let offsets_chunks: Vec<_> = offsets.chunks_mut().collect();
+
+    radix_prnrs
+        .par_iter_mut()
+        .zip(key_chunks.into_par_iter())
+        .zip(offsets_chunks.into_par_iter())
+        .try_for_each(|((radix_prnr, key_chunk), offsets_chunk)| {
+            radix_prnr.prefix_sum(key_chunk, offsets_chunk)
+        })?;
+
+    Ok(())
+}
+
+/// Partitions a relation in parallel, with one CPU partitioner per chunk.
+fn cpu_partition<T>(
+    radix_prnrs: &mut [CpuRadixPartitioner],
+    relation: &Relation<T, T>,
+    offsets: &mut PartitionOffsets<Tuple<T, T>>,
+    partitions: &mut PartitionedRelation<Tuple<T, T>>,
+) -> Result<()>
+where
+    T: DeviceCopy + Sync + Send + CpuRadixPartitionable,
+{
+    let (key_slice, pay_slice) = cpu_slices(relation)?;
+    cpu_partition_slices(radix_prnrs, key_slice, pay_slice, offsets, partitions)
+}
+
+/// Partitions key and payload slices in parallel, with one CPU partitioner
+/// per chunk.
+fn cpu_partition_slices<T>(
+    radix_prnrs: &mut [CpuRadixPartitioner],
+    key_slice: &[T],
+    pay_slice: &[T],
+    offsets: &mut PartitionOffsets<Tuple<T, T>>,
+    partitions: &mut PartitionedRelation<Tuple<T, T>>,
+) -> Result<()>
+where
+    T: DeviceCopy + Sync + Send + CpuRadixPartitionable,
+{
+    let key_chunks = key_slice.input_chunks::<T>(radix_prnrs.len() as u32)?;
+    let pay_chunks = pay_slice.input_chunks::<T>(radix_prnrs.len() as u32)?;
+    let offsets_chunks: Vec<_> = offsets.chunks_mut().collect();
+    let partitioned_chunks: Vec<_> = partitions.chunks_mut().collect();
+
+    radix_prnrs
+        .par_iter_mut()
+        .zip(key_chunks.into_par_iter())
+        .zip(pay_chunks.into_par_iter())
+        .zip(offsets_chunks.into_par_iter())
+        .zip(partitioned_chunks.into_par_iter())
+        .try_for_each(
+            |((((radix_prnr, key_chunk), pay_chunk), offsets_chunk), partitioned_chunk)| {
+                radix_prnr.partition(key_chunk, pay_chunk, offsets_chunk, partitioned_chunk)
+            },
+        )?;
+
+    Ok(())
+}
+
+/// Partitions batches of the outer relation, and sends the partitions to the
+/// join workers.
+///
+/// The partitioner takes the next batch from `next_batch` until all batches
+/// are partitioned. Before it partitions a batch, the partitioner waits until
+/// one of its buffers is free.
+///
+/// A CPU partitioner has a single chunk. Thus, it runs only on the current
+/// thread of `thread_pool`.
+fn partition_outer_batches<T>(
+    thread_pool: &rayon::ThreadPool,
+    radix_prnr: &mut BatchPartitioner,
+    buffers: BatchBuffers<T>,
+    key_batches: &[RadixPartitionInputChunk<'_, T>],
+    pay_batches: &[RadixPartitionInputChunk<'_, T>],
+    next_batch: &AtomicUsize,
+    senders: &[SyncSender<ProbePartition<T>>],
+) -> Result<()>
+where
+    T: DeviceCopy + Sync + Send + CpuRadixPartitionable + GpuRadixPartitionable,
+{
+    let BatchBuffers {
+        mut offsets,
+        partitions,
+    } = buffers;
+
+    let (recycler, free_batches) = mpsc::channel();
+    for batch in partitions {
+        recycler.send(batch).map_err(|_| {
+            ErrorKind::LogicError("Failed to queue a free batch buffer".to_string())
+        })?;
+    }
+
+    loop {
+        let batch_id = next_batch.fetch_add(1, Ordering::Relaxed);
+        if batch_id >= key_batches.len() {
+            break;
+        }
+
+        // Blocks until the workers have probed an earlier batch
+        let mut partitions = free_batches
+            .recv()
+            .map_err(|_| ErrorKind::LogicError("The batch buffers were dropped".to_string()))?;
+
+        radix_prnr.prefix_sum(thread_pool, key_batches[batch_id].data, &mut offsets)?;
+        radix_prnr.partition(
+            thread_pool,
+            key_batches[batch_id].data,
+            pay_batches[batch_id].data,
+            &mut offsets,
+            &mut partitions,
+        )?;
+
+        let batch_histogram = partitions.histogram()?;
+        let batch = Arc::new(OuterBatch {
+            partitions: Some(partitions),
+            recycler: Mutex::new(recycler.clone()),
+        });
+
+        for (partition_id, &len) in batch_histogram.iter().enumerate() {
+            if len == 0 {
+                continue;
+            }
+
+            senders[partition_id % senders.len()]
+                .send(ProbePartition {
+                    batch: batch.clone(),
+                    partition_id: partition_id as u32,
+                })
+                .map_err(|_| {
+                    ErrorKind::RuntimeError(
+                        "The join worker exited before the end of the relation".to_string(),
+                    )
+                })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the hash tables of a worker's partitions, and then probes the
+/// partitions that the worker receives.
+///
+/// The worker owns every partition `p` with `p % workers == worker_id`.
+///
+/// Returns the number of matches.
+fn join_outer_batches<T>(
+    inner_partitions: &[Vec<&[Tuple<T, T>]>],
+    worker_id: usize,
+    workers: usize,
+    receiver: Receiver<ProbePartition<T>>,
+) -> Result<u64>
+where
+    T: Copy + Default + DeviceCopy + KeyAttribute + AsPrimitive<u64>,
+{
+    let hash_tables = inner_partitions
+        .iter()
+        .enumerate()
+        .map(|(partition_id, inner)| {
+            if partition_id % workers == worker_id {
+                let mut hash_table = PartitionHashTable::new();
+                hash_table.build(inner)?;
+                Ok(Some(hash_table))
+            } else {
+                Ok(None)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let count = receiver
+        .iter()
+        .map(|probe_partition| {
+            let batch = probe_partition.batch.partitions();
+            let outer: Vec<_> = (0..batch.num_chunks())
+                .map(|chunk_id| &batch[(chunk_id, probe_partition.partition_id)])
+                .collect();
+
+            hash_tables[probe_partition.partition_id as usize]
+                .as_ref()
+                .map_or(0, |hash_table| hash_table.probe_count(&outer))
+        })
+        .sum();
+
+    Ok(count)
+}
+
+/// Checks the arguments of the CPU radix joins, and returns the radix bits.
+fn check_arguments(hashing_scheme: HashingScheme, radix_bits: &RadixBits) -> Result<u32> {
+    let pass_radix_bits = radix_bits
+        .pass_radix_bits(RadixPass::First)
+        .ok_or_else(|| {
+            ErrorKind::InvalidArgument("Radix bits for the first pass are required".to_string())
+        })?;
+    if radix_bits.pass_radix_bits(RadixPass::Second).is_some() {
+        Err(ErrorKind::InvalidArgument(
+            "The CPU radix join supports only a single partitioning pass".to_string(),
+        ))?;
+    }
+
+    // The hash tables span only a single partition. Perfect hashing instead
+    // requires a hash table that spans the whole key range.
+    match hashing_scheme {
+        HashingScheme::LinearProbing => {}
+        _ => Err(ErrorKind::InvalidArgument(
+            "The CPU radix join supports only linear probing".to_string(),
+        ))?,
+    };
+
+    Ok(pass_radix_bits)
+}
+
+/// Groups the chunk slices of a partitioned relation by partition.
+fn partition_slices<T: DeviceCopy>(
+    relation: &PartitionedRelation<Tuple<T, T>>,
+) -> Result<Vec<Vec<&[Tuple<T, T>]>>> {
+    let mut partitions = vec![Vec::new(); relation.fanout() as usize];
+    for (partition_id, slice) in relation.iter_partitions()? {
+        partitions[partition_id].push(slice);
+    }
+
+    Ok(partitions)
+}
+
+/// A linear probing hash table that joins a single partition.
+///
+/// The hash table builds and probes the partitioned tuples in place, instead
+/// of copying the keys and payloads into separate columns. Between two
+/// partitions, the hash table reuses its memory, and grows only if the next
+/// partition doesn't fit.
+///
+/// Radix partitioning assigns the tuples to partitions by the lowest key bits.
+/// Thus, all keys of a partition share these bits. The multiplicative hash
+/// function takes the highest bits of the product instead, which depend on
+/// all key bits.
+///
+/// As in the other hash tables, the null key marks an empty slot and cannot
+/// be joined.
+struct PartitionHashTable<T> {
+    entries: Vec<Tuple<T, T>>,
+    log2_len: u32,
+}
+
+impl<T> PartitionHashTable<T>
+where
+    T: Copy + Default + PartialEq + KeyAttribute + AsPrimitive<u64>,
+{
+    /// Fibonacci hashing multiplier, i.e., 2^64 divided by the golden ratio.
+    const HASH_MULTIPLIER: u64 = 0x9e37_79b9_7f4a_7c15;
+
+    fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            log2_len: 0,
+        }
+    }
+
+    /// Builds the hash table from the chunks of an inner partition.
+    ///
+    /// The hash table has twice as many slots as the next power of two of the
+    /// partition length. Any previous contents are discarded.
+    fn build(&mut self, inner: &[&[Tuple<T, T>]]) -> Result<()> {
+        let len: usize = inner.iter().map(|chunk| chunk.len()).sum();
+        let hash_table_len = len
+            .checked_next_power_of_two()
+            .and_then(|len| len.checked_mul(2))
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to compute hash table length".to_string())
+            })?;
+
+        let null_entry = Tuple {
+            key: T::null_key(),
+            value: T::default(),
+        };
+        self.entries.clear();
+        self.entries.resize(hash_table_len, null_entry);
+        self.log2_len = hash_table_len.trailing_zeros();
+
+        let mask = hash_table_len - 1;
+        for tuple in inner.iter().flat_map(|chunk| chunk.iter()) {
+            let mut index = self.hash(tuple.key);
+            while self.entries[index].key != T::null_key() {
+                index = (index + 1) & mask;
+            }
+            self.entries[index] = *tuple;
+        }
+
+        Ok(())
+    }
+
+    /// Probes the hash table with the chunks of an outer partition, and
+    /// returns the number of matches.
+    fn probe_count(&self, outer: &[&[Tuple<T, T>]]) -> u64 {
+        let mask = self.entries.len() - 1;
+
+        outer
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .map(|tuple| {
+                let mut matches = 0;
+                let mut index = self.hash(tuple.key);
+                while self.entries[index].key != T::null_key() {
+                    if self.entries[index].key == tuple.key {
+                        matches += 1;
+                    }
+                    index = (index + 1) & mask;
+                }
+                matches
+            })
+            .sum()
+    }
+
+    fn hash(&self, key: T) -> usize {
+        (key.as_().wrapping_mul(Self::HASH_MULTIPLIER) >> (u64::BITS - self.log2_len)) as usize
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::{ErrorKind, Result};
+use crate::measurement::harness::RadixJoinPoint;
+use cstr::cstr;
+use data_store::join_data::JoinData;
+use datagen::relation::KeyAttribute;
+use numa_gpu::runtime::allocator::{Allocator, MemType};
+use numa_gpu::runtime::cuda_wrapper;
+use numa_gpu::runtime::memory::*;
+use numa_gpu::runtime::nvtx::Range;
+use numa_gpu::runtime::timer::{CudaEventTimer, PhaseTimer};
+use rustacuda::function::{BlockSize, GridSize};
+use rustacuda::memory::{CopyDestination, DeviceBuffer, DeviceCopy};
+use rustacuda::stream::{Stream, StreamFlags};
+use sql_ops::join::{no_partitioning_join, HashingScheme, HtEntry};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Joins the relations on the GPU by partitioning only the inner relation.
+///
+/// Splits the inner relation into contiguous partitions of at most
+/// `build_partition_len` tuples. For each partition, builds a hash table
+/// sized for the partition, and then probes the hash table with the entire
+/// outer relation. Thus, only one partition's hash table must fit into
+/// `hash_table_mem_type` at a time.
+///
+/// The join suits a large inner and a small outer relation, because the outer
+/// relation is scanned once per partition. In contrast to the radix join, the
+/// outer relation isn't partitioned.
+///
+/// Each key of the inner relation is contained in exactly one partition.
+/// Accumulating the payload sums of all probes thus yields the same result as
+/// the no-partitioning join. Only linear probing is supported, because a
+/// perfect hash table must cover the entire key range.
+///
+/// Returns the sum of the matching outer relation's payloads.
+pub fn gpu_build_partitioned_join<T>(
+    data: &mut JoinData<T>,
+    hashing_scheme: HashingScheme,
+    hash_table_mem_type: MemType,
+    build_partition_len: usize,
+    join_dim: (&GridSize, &BlockSize),
+) -> Result<(i64, RadixJoinPoint)>
+where
+    T: Clone + Default + DeviceCopy + KeyAttribute + no_partitioning_join::CudaHashJoinable,
+{
+    if !matches!(hashing_scheme, HashingScheme::LinearProbing) {
+        Err(ErrorKind::InvalidArgument(
+            "The build-partitioned join supports only linear probing".to_string(),
+        ))?;
+    }
+
+    if build_partition_len == 0 {
+        Err(ErrorKind::InvalidArgument(
+            "The build partition length must be at least one tuple".to_string(),
+        ))?;
+    }
+
+    let (grid_size, block_size) = join_dim;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    let result_sums = {
+        let mut mem =
+            unsafe { DeviceBuffer::uninitialized((grid_size.x * block_size.x) as usize)? };
+        cuda_wrapper::memset_async(mem.as_launchable_mut_slice(), 0, &stream)?;
+        Mem::CudaDevMem(mem)
+    };
+
+    let build_keys = data.build_relation.key().as_launchable_slice();
+    let build_payloads = data.build_relation.value().as_launchable_slice();
+
+    let mut hash_table_malloc_time = Duration::default();
+    let mut join_time = 0_f64;
+
+    let join_range = Range::new(cstr!("phase_join"));
+    for (keys, payloads) in build_keys
+        .chunks(build_partition_len)
+        .zip(build_payloads.chunks(build_partition_len))
+    {
+        let hash_table_len = keys
+            .len()
+            .checked_next_power_of_two()
+            .and_then(|len| len.checked_mul(2))
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to compute hash table length".to_string())
+            })?;
+
+        // The previous partition's hash table is freed at the end of its
+        // iteration, thus only one hash table is allocated at a time
+        let hash_table_malloc_timer = Instant::now();
+        let hash_table_mem =
+            Allocator::alloc_mem::<HtEntry<T, T>>(hash_table_mem_type.clone(), hash_table_len);
+        let mut hash_table =
+            no_partitioning_join::HashTable::new_on_gpu(hash_table_mem, hash_table_len)?;
+        hash_table.mlock()?;
+        stream.synchronize()?;
+        hash_table_malloc_time += hash_table_malloc_timer.elapsed();
+
+        let hj_op = no_partitioning_join::CudaHashJoinBuilder::<T>::default()
+            .hashing_scheme(hashing_scheme)
+            .build_dim(grid_size.clone(), block_size.clone())
+            .probe_dim(grid_size.clone(), block_size.clone())
+            .hash_table(Arc::new(hash_table))
+            .build()?;
+
+        let mut partition_timer = CudaEventTimer::start(&stream)?;
+        hj_op.build(keys, payloads, &stream)?;
+        hj_op.probe_sum_relation(&data.probe_relation, &result_sums, &stream)?;
+        partition_timer.stop()?;
+
+        join_time += partition_timer.elapsed_ns()?;
+    }
+    join_range.end();
+
+    let mut result_sums_host = vec![0_u64; result_sums.len()];
+    if let Mem::CudaDevMem(ref sums) = result_sums {
+        sums.copy_to(result_sums_host.as_mut_slice())?;
+    }
+    let sum = result_sums_host.iter().sum::<u64>() as i64;
+
+    let data_point = RadixJoinPoint {
+        partition_ns: Some(0.0),
+        join_ns: Some(join_time),
+        state_malloc_ns: Some(hash_table_malloc_time.as_nanos() as f64),
+        ..RadixJoinPoint::default()
+    };
+
+    Ok((sum, data_point))
+}
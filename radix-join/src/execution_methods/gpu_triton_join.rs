@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::error::{ErrorKind, Result};
-use crate::measurement::harness::RadixJoinPoint;
+use crate::measurement::harness::{PartitionHistograms, RadixJoinPoint};
 use cstr::cstr;
 use data_store::join_data::JoinData;
 use datagen::relation::KeyAttribute;
@@ -25,6 +25,7 @@ use numa_gpu::runtime::linux_wrapper;
 use numa_gpu::runtime::memory::*;
 use numa_gpu::runtime::numa::PageType;
 use numa_gpu::runtime::nvtx::Range;
+use numa_gpu::runtime::timer::{self, CpuTimer, CudaEventTimer, PhaseTimer};
 use numa_gpu::utils::DeviceType;
 use rustacuda::context::{CacheConfig, CurrentContext, SharedMemoryConfig};
 use rustacuda::event::{Event, EventFlags};
@@ -113,6 +114,7 @@ pub fn gpu_triton_join<T>(
     page_type: PageType,
     partition_dim: (&GridSize, &BlockSize),
     join_dim: (&GridSize, &BlockSize),
+    partition_histogram: bool,
 ) -> Result<(i64, RadixJoinPoint)>
 where
     T: Default
@@ -219,15 +221,15 @@ where
     let prefix_sum_range = Range::new(cstr!("phase_prefix_sum"));
     let prefix_sum_time = match histogram_algorithm_fst {
         DeviceType::Cpu(histogram_algorithm) => {
-            let prefix_sum_timer = Instant::now();
+            let mut prefix_sum_timer = CpuTimer::start();
 
-            let inner_key_slice: &[T] = (&data.build_relation_key).try_into().map_err(|_| {
+            let inner_key_slice: &[T] = data.build_relation.key().try_into().map_err(|_| {
                 ErrorKind::RuntimeError("Failed to run CPU prefix sum on device memory".into())
             })?;
             let inner_key_chunks = inner_key_slice.input_chunks::<T>(max_chunks_1st)?;
             let inner_offsets_chunks = inner_rel_partition_offsets.chunks_mut();
 
-            let outer_key_slice: &[T] = (&data.probe_relation_key).try_into().map_err(|_| {
+            let outer_key_slice: &[T] = data.probe_relation.key().try_into().map_err(|_| {
                 ErrorKind::RuntimeError("Failed to run CPU prefix sum on device memory".into())
             })?;
             let outer_key_chunks = outer_key_slice.input_chunks::<T>(max_chunks_1st)?;
@@ -250,7 +252,8 @@ where
                             DerefMemType::AlignedSysMem {
                                 align_bytes: sql_ops::CPU_CACHE_LINE_SIZE as usize,
                             },
-                        );
+                        )
+                        .expect("Failed to create CPU radix partitioner");
                         radix_prnr
                             .prefix_sum(input, output)
                             .expect("Failed to run CPU prefix sum");
@@ -258,34 +261,40 @@ where
                 }
             });
 
-            prefix_sum_timer.elapsed().as_nanos() as f64
+            prefix_sum_timer.stop()?;
+            prefix_sum_timer.elapsed_ns()?
         }
         DeviceType::Gpu(_) => {
-            let prefix_sum_start_event = Event::new(EventFlags::DEFAULT)?;
-            let prefix_sum_stop_event = Event::new(EventFlags::DEFAULT)?;
-            prefix_sum_start_event.record(&stream)?;
+            let mut prefix_sum_timer = CudaEventTimer::start(&stream)?;
 
-            radix_prnr.prefix_sum(
+            radix_prnr.prefix_sum_relation(
                 RadixPass::First,
-                data.build_relation_key.as_launchable_slice(),
+                &data.build_relation,
                 &mut inner_rel_partition_offsets,
                 &stream,
             )?;
-            radix_prnr.prefix_sum(
+            radix_prnr.prefix_sum_relation(
                 RadixPass::First,
-                data.probe_relation_key.as_launchable_slice(),
+                &data.probe_relation,
                 &mut outer_rel_partition_offsets,
                 &stream,
             )?;
 
-            prefix_sum_stop_event.record(&stream)?;
-            stream.synchronize()?;
-            prefix_sum_stop_event.elapsed_time_f32(&prefix_sum_start_event)? as f64
-                * 10_f64.powf(6.0)
+            prefix_sum_timer.stop()?;
+            prefix_sum_timer.elapsed_ns()?
         }
     };
     prefix_sum_range.end();
 
+    let partition_histograms = if partition_histogram {
+        Some(PartitionHistograms {
+            inner: inner_rel_partition_offsets.histogram_and_offsets()?.0,
+            outer: outer_rel_partition_offsets.histogram_and_offsets()?.0,
+        })
+    } else {
+        None
+    };
+
     let state_malloc_timer = Instant::now();
 
     let max_inner_partition_len =
@@ -411,8 +420,8 @@ where
         }
         cmp::min(bytes, free)
     });
-    let cache_proportion_inner = data.build_relation_key.len() as f64
-        / (data.build_relation_key.len() as f64 + data.probe_relation_key.len() as f64);
+    let cache_proportion_inner = data.build_relation.len() as f64
+        / (data.build_relation.len() as f64 + data.probe_relation.len() as f64);
     let cache_bytes_inner = (cache_bytes as f64 * cache_proportion_inner) as usize;
     let cache_bytes_outer = cache_bytes - cache_bytes_inner;
 
@@ -422,14 +431,14 @@ where
             spill_node,
             page_type,
         });
-    let mut inner_rel_partitions = PartitionedRelation::new(
-        data.build_relation_key.len(),
+    let mut inner_rel_partitions = PartitionedRelation::try_new(
+        data.build_relation.len(),
         histogram_algorithm_fst.either(|cpu| cpu.into(), |gpu| gpu.into()),
         radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
         max_chunks_1st,
         inner_rel_alloc(cache_bytes_inner / mem::size_of::<Tuple<T, T>>()),
         Allocator::mem_alloc_fn(offsets_mem_type.clone()),
-    );
+    )?;
 
     let (outer_rel_alloc, cached_probe_tuples) =
         Allocator::mem_spill_alloc_fn(CacheSpillType::CacheAndSpill {
@@ -437,50 +446,45 @@ where
             spill_node,
             page_type,
         });
-    let mut outer_rel_partitions = PartitionedRelation::new(
-        data.probe_relation_key.len(),
+    let mut outer_rel_partitions = PartitionedRelation::try_new(
+        data.probe_relation.len(),
         histogram_algorithm_fst.either(|cpu| cpu.into(), |gpu| gpu.into()),
         radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
         max_chunks_1st,
         outer_rel_alloc(cache_bytes_outer / mem::size_of::<Tuple<T, T>>()),
         Allocator::mem_alloc_fn(offsets_mem_type.clone()),
-    );
+    )?;
 
     inner_rel_partitions.mlock()?;
     outer_rel_partitions.mlock()?;
 
     let partitions_malloc_time = partitions_malloc_time + partitions_malloc_timer.elapsed();
 
-    let partition_start_event = Event::new(EventFlags::DEFAULT)?;
-    let partition_stop_event = Event::new(EventFlags::DEFAULT)?;
     let partition_range = Range::new(cstr!("phase_partition"));
-    partition_start_event.record(&stream)?;
+    let mut partition_timer = CudaEventTimer::start(&stream)?;
 
     // Partition inner relation
-    radix_prnr.partition(
+    radix_prnr.partition_relation(
         RadixPass::First,
-        data.build_relation_key.as_launchable_slice(),
-        data.build_relation_payload.as_launchable_slice(),
+        &data.build_relation,
         &mut inner_rel_partition_offsets,
         &mut inner_rel_partitions,
         &stream,
     )?;
 
     // Partition outer relation
-    radix_prnr.partition(
+    radix_prnr.partition_relation(
         RadixPass::First,
-        data.probe_relation_key.as_launchable_slice(),
-        data.probe_relation_payload.as_launchable_slice(),
+        &data.probe_relation,
         &mut outer_rel_partition_offsets,
         &mut outer_rel_partitions,
         &stream,
     )?;
 
-    partition_stop_event.record(&stream)?;
+    partition_timer.stop()?;
 
     stream.synchronize()?;
-    let partition_time =
-        partition_stop_event.elapsed_time_f32(&partition_start_event)? as f64 * 10_f64.powf(6.0);
+    let partition_time = partition_timer.elapsed_ns()?;
     partition_range.end();
 
     Stream::drop(stream).map_err(|(e, _)| e)?;
@@ -599,8 +603,7 @@ where
         .zip(join_stop_events.iter())
         .try_fold::<_, _, Result<_>>(0_f64, |time, (StreamState { stream, .. }, stop_event)| {
             stream.synchronize()?;
-            let new_time =
-                stop_event.elapsed_time_f32(&join_start_event)? as f64 * 10_f64.powf(6.0);
+            let new_time = timer::event_elapsed_ns(&join_start_event, stop_event)?;
             Ok(time.max(new_time))
         })?;
     join_range.end();
@@ -612,10 +615,13 @@ where
         .map(
             |(
                 StreamState {
-                    join_result_sums, ..
+                    join_result_sums,
+                    radix_join,
+                    ..
                 },
                 host_sums,
             )| {
+                radix_join.check_overflow()?;
                 join_result_sums.copy_to(host_sums)?;
                 Ok(())
             },
@@ -632,6 +638,7 @@ where
         state_malloc_ns: Some(state_malloc_time.as_nanos() as f64),
         cached_build_tuples: *cached_build_tuples.borrow(),
         cached_probe_tuples: *cached_probe_tuples.borrow(),
+        partition_histograms,
     };
 
     Ok((sum, data_point))
@@ -15,4 +15,5 @@
 pub mod error;
 pub mod execution_methods;
 pub mod measurement;
+pub mod plan;
 pub mod types;
@@ -14,20 +14,29 @@
 
 use data_store::join_data::{JoinDataBuilder, JoinDataGenFn};
 use datagen::relation::KeyAttribute;
+use datagen::rng::SeededRng;
 use num_rational::Ratio;
-use numa_gpu::runtime::allocator::MemType;
+use numa_gpu::runtime::allocator::{Allocator, MemType};
 use numa_gpu::runtime::cpu_affinity::CpuAffinity;
+use numa_gpu::runtime::cuda_wrapper;
 use numa_gpu::runtime::hw_info::{cpu_codename, NvidiaDriverInfo};
 use numa_gpu::runtime::linux_wrapper;
 use numa_gpu::runtime::numa::NodeRatio;
 use numa_gpu::utils::DeviceType;
 use radix_join::error::{ErrorKind, Result};
 use radix_join::execution_methods::{
-    cpu_partitioned_radix_join::cpu_partitioned_radix_join, gpu_radix_join::gpu_radix_join,
+    cpu_partitioned_radix_join::cpu_partitioned_radix_join,
+    cpu_radix_join::{cpu_radix_join, cpu_thread_pool},
+    gpu_build_partitioned_join::gpu_build_partitioned_join,
+    gpu_no_partitioning_join::gpu_no_partitioning_join,
+    gpu_radix_join::gpu_radix_join,
     gpu_triton_join::gpu_triton_join,
 };
+use radix_join::measurement::budget::RuntimeBudget;
 use radix_join::measurement::data_point::DataPoint;
-use radix_join::measurement::harness::{self, RadixJoinPoint};
+use radix_join::measurement::harness::{self, PartitionHistogramWriter, RadixJoinPoint};
+use radix_join::measurement::progress::ProgressReporter;
+use radix_join::plan::{self, MemLocation, MemoryPlan};
 use radix_join::types::*;
 use rustacuda::context::CurrentContext;
 use rustacuda::device::Device;
@@ -38,13 +47,22 @@ use rustacuda::prelude::*;
 use serde::de::DeserializeOwned;
 use sql_ops::join::{cuda_radix_join, no_partitioning_join, HashingScheme};
 use sql_ops::partition::cpu_radix_partition::{CpuHistogramAlgorithm, CpuRadixPartitionable};
-use sql_ops::partition::gpu_radix_partition::{GpuHistogramAlgorithm, GpuRadixPartitionable};
+use sql_ops::partition::gpu_radix_partition::{
+    GpuHistogramAlgorithm, GpuRadixPartitionAlgorithm, GpuRadixPartitionable, GpuRadixPartitioner,
+};
 use sql_ops::partition::{RadixBits, RadixPass};
+use std::cell::RefCell;
 use std::convert::TryInto;
 use std::mem::size_of;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
 use structopt::StructOpt;
 
+/// Number of warps per multiprocessor with which GPU kernels are launched.
+const WARP_OVERCOMMIT_FACTOR: u32 = 32;
+
 fn main() -> Result<()> {
     // Parse commandline arguments
     let mut cmd = CmdOpt::from_args();
@@ -55,6 +73,16 @@ fn main() -> Result<()> {
     let _context =
         Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device)?;
 
+    // Reject invalid options before generating the data set. The
+    // recommended radix bits depend on the fanout limits of the GPU.
+    let warp_size = device.get_attribute(DeviceAttribute::WarpSize)? as u32;
+    cmd.set_radix_bits(&BlockSize::x(warp_size * WARP_OVERCOMMIT_FACTOR))?;
+    cmd.validate()?;
+
+    Allocator::set_device_memory_limit(
+        cmd.device_memory_limit.map(|s| s * 1024 * 1024), // convert MiB to bytes
+    );
+
     let cache_node = device.numa_node().ok();
     let overflow_node = match device.numa_memory_affinity() {
         Ok(numa_node) => numa_node,
@@ -67,15 +95,16 @@ fn main() -> Result<()> {
     cmd.set_state_mem(cache_node);
     cmd.set_partitions_mem(cache_node, overflow_node)?;
 
+    if cmd.dry_run {
+        return match cmd.tuple_bytes {
+            ArgTupleBytes::Bytes8 => dry_run::<i32>(&cmd, device),
+            ArgTupleBytes::Bytes16 => dry_run::<i64>(&cmd, device),
+        };
+    }
+
     match cmd.tuple_bytes {
-        ArgTupleBytes::Bytes8 => {
-            let (hjc, dp) = args_to_bench::<i32>(&cmd, device)?;
-            harness::measure("radix_join", cmd.repeat, cmd.csv, dp, hjc)?;
-        }
-        ArgTupleBytes::Bytes16 => {
-            let (hjc, dp) = args_to_bench::<i64>(&cmd, device)?;
-            harness::measure("radix_join", cmd.repeat, cmd.csv, dp, hjc)?;
-        }
+        ArgTupleBytes::Bytes8 => measure::<i32>(cmd, device)?,
+        ArgTupleBytes::Bytes16 => measure::<i64>(cmd, device)?,
     };
 
     Ok(())
@@ -95,6 +124,34 @@ struct CmdOpt {
     #[structopt(long = "csv", parse(from_os_str))]
     csv: Option<PathBuf>,
 
+    /// Output filename for the partition sizes of the first partitioning pass
+    ///
+    /// Writes one CSV row per partition of each relation with the partition's
+    /// tuple count. The histogram is taken from the first run of each sweep
+    /// point, and only radix-partitioned joins produce a histogram.
+    #[structopt(long, parse(from_os_str))]
+    partition_histogram: Option<PathBuf>,
+
+    /// Print the progress and the estimated remaining time to stderr
+    #[structopt(long)]
+    progress: bool,
+
+    /// Stop the sweep once the run time exceeds the budget (seconds)
+    ///
+    /// The budget is checked before each sweep point. The measurements of
+    /// the completed points are written to the CSV file, and the remaining
+    /// points are skipped.
+    #[structopt(long)]
+    max_runtime: Option<u64>,
+
+    /// Validate the configuration and print the plan without running the benchmark
+    ///
+    /// The validation includes the device lookup, the fanout limits, and
+    /// whether the relations fit into their memory locations. No data are
+    /// generated.
+    #[structopt(long)]
+    dry_run: bool,
+
     /// Memory type with which to allocate data.
     //   unified: CUDA Unified memory (default)
     //   numa: NUMA-local memory on node specified with [inner,outer]-rel-location
@@ -225,6 +282,14 @@ struct CmdOpt {
     #[structopt(long = "zipf-exponent", required_if("data-distribution", "Zipf"))]
     zipf_exponent: Option<f64>,
 
+    /// Seed for the data generator
+    ///
+    /// The generator is reset to the seed before generating each sweep point's
+    /// relations. Thus, all points join the same data. By default, each point
+    /// is generated with a random seed.
+    #[structopt(long)]
+    seed: Option<u64>,
+
     /// Selectivity of the join, in percent
     #[structopt(
         long = "selectivity",
@@ -304,6 +369,28 @@ struct CmdOpt {
     )]
     partition_algorithm_2nd: ArgRadixPartitionAlgorithm,
 
+    /// Join algorithm, or `Both` to compare the algorithms on the same data set
+    ///
+    /// The radix-partitioned join uses the specified execution strategy,
+    /// whereas the no-partitioning join always executes on the GPU. The
+    /// build-partitioned join partitions only the inner relation, and probes
+    /// each partition with the entire outer relation on the GPU. `Both`
+    /// compares the no-partitioning and the radix-partitioned joins.
+    #[structopt(
+        long = "algorithm",
+        default_value = "RadixPartition",
+        possible_values = &ArgAlgorithm::variants(),
+        case_insensitive = true
+    )]
+    algorithm: ArgAlgorithm,
+
+    /// Maximum number of inner relation tuples per partition of the
+    /// build-partitioned join
+    ///
+    /// Each partition's hash table has twice the next power of two entries.
+    #[structopt(long, default_value = "16777216")]
+    build_partition_len: usize,
+
     /// Join execution strategy.
     #[structopt(
         long = "execution-strategy",
@@ -313,18 +400,28 @@ struct CmdOpt {
     )]
     execution_method: ArgExecutionMethod,
 
-    #[structopt(
-        long = "radix-bits",
-        default_value = "8,8",
-        parse(try_from_str = parse_radix_bits)
-    )]
-    /// Radix bits with which to partition
+    /// Radix bits with which to partition (e.g., 8,8) [Default: recommended by the inner relation size]
+    ///
+    /// Relations loaded from files default to 8,8, because their size is
+    /// unknown before they are loaded.
+    #[structopt(long = "radix-bits", parse(try_from_str = parse_radix_bits))]
+    specified_radix_bits: Option<RadixBits>,
+
+    #[structopt(skip = RadixBits::new(Some(8), Some(8), None))]
     radix_bits: RadixBits,
 
     #[structopt(short = "i", long = "device-id", default_value = "0")]
     /// Execute on GPU (See CUDA device list)
     device_id: u16,
 
+    /// Limit the CUDA device memory that the allocator hands out (in MiB) [Default: No limit]
+    ///
+    /// Simulates a GPU with less memory, e.g., to test out-of-core execution
+    /// on a large GPU. Device memory allocations beyond the limit fail as if
+    /// the GPU ran out of memory.
+    #[structopt(long)]
+    device_memory_limit: Option<usize>,
+
     /// Device memory buffer sizes per partition per thread block for HSSWWC variants (in KiB)
     #[structopt(long, default_value = "8", require_delimiter = true)]
     dmem_buffer_size: usize,
@@ -336,6 +433,14 @@ struct CmdOpt {
     #[structopt(short = "t", long = "threads", default_value = "1")]
     threads: usize,
 
+    /// Sweep over the number of threads within a single run (e.g., 1,2,4,8)
+    #[structopt(long, require_delimiter = true, conflicts_with = "sweep-radix-bits")]
+    sweep_threads: Vec<usize>,
+
+    /// Sweep over the first pass radix bits within a single run (e.g., 4..=12)
+    #[structopt(long, parse(try_from_str = parse_radix_bits_sweep))]
+    sweep_radix_bits: Option<RangeInclusive<u32>>,
+
     /// Path to CPU affinity map file for CPU workers
     #[structopt(long = "cpu-affinity", parse(from_os_str))]
     cpu_affinity: Option<PathBuf>,
@@ -346,6 +451,63 @@ struct CmdOpt {
 }
 
 impl CmdOpt {
+    /// Sets the specified radix bits, or otherwise recommends radix bits for
+    /// the inner relation.
+    ///
+    /// The recommended radix bits stay within the fanout limits of the GPU
+    /// partitioning passes with `block_size`.
+    fn set_radix_bits(&mut self, block_size: &BlockSize) -> Result<()> {
+        if let Some(radix_bits) = self.specified_radix_bits {
+            self.radix_bits = radix_bits;
+            return Ok(());
+        }
+
+        if self.inner_rel_file.is_some() {
+            return Ok(());
+        }
+
+        let (inner_len, max_pass_radix_bits) = match self.tuple_bytes {
+            ArgTupleBytes::Bytes8 => (
+                self.generated_inner_len::<i32>(),
+                max_pass_radix_bits::<i32>(self, block_size)?,
+            ),
+            ArgTupleBytes::Bytes16 => (
+                self.generated_inner_len::<i64>(),
+                max_pass_radix_bits::<i64>(self, block_size)?,
+            ),
+        };
+        let passes = match self.execution_method {
+            ArgExecutionMethod::CpuRadixJoin => 1,
+            _ => 2,
+        };
+
+        self.radix_bits = plan::recommend_radix_bits(
+            inner_len,
+            self.tuple_bytes as usize,
+            passes,
+            &max_pass_radix_bits,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the inner relation size of the generated data set.
+    fn generated_inner_len<T>(&self) -> usize
+    where
+        T: Copy + Send + KeyAttribute + num_traits::FromPrimitive,
+    {
+        let (inner_len, _, _) = data_gen_fn::<T>(
+            self.data_set,
+            self.inner_rel_tuples,
+            self.outer_rel_tuples,
+            DataDistribution::Uniform,
+            Some(self.selectivity),
+            SeededRng::new(0),
+        );
+
+        inner_len
+    }
+
     fn set_state_mem(&mut self, state_location: Option<u16>) {
         self.state_mem_type = if let Some(true) = self.use_numa_mem_state {
             ArgMemType::Numa
@@ -387,6 +549,118 @@ impl CmdOpt {
     }
 }
 
+/// A point of a parameter sweep.
+#[derive(Copy, Clone, Debug)]
+enum SweepPoint {
+    Threads(usize),
+    RadixBits(u32),
+}
+
+impl SweepPoint {
+    /// Overrides the swept parameter in the commandline options.
+    ///
+    /// Sweeping the radix bits replaces only the first pass, and keeps the
+    /// radix bits of all later passes.
+    fn apply(&self, cmd: &mut CmdOpt) {
+        match *self {
+            SweepPoint::Threads(threads) => cmd.threads = threads,
+            SweepPoint::RadixBits(bits) => {
+                cmd.radix_bits = with_first_pass_bits(&cmd.radix_bits, bits)
+            }
+        }
+    }
+}
+
+/// Replaces the radix bits of the first pass, and keeps all later passes.
+fn with_first_pass_bits(radix_bits: &RadixBits, first_bits: u32) -> RadixBits {
+    RadixBits::new(
+        Some(first_bits),
+        radix_bits.pass_radix_bits(RadixPass::Second),
+        radix_bits.pass_radix_bits(RadixPass::Third),
+    )
+}
+
+impl CmdOpt {
+    /// Returns the sweep points, or `None` if no sweep is requested.
+    fn sweep_points(&self) -> Option<Vec<SweepPoint>> {
+        if !self.sweep_threads.is_empty() {
+            Some(
+                self.sweep_threads
+                    .iter()
+                    .map(|&threads| SweepPoint::Threads(threads))
+                    .collect(),
+            )
+        } else {
+            self.sweep_radix_bits
+                .clone()
+                .map(|range| range.map(SweepPoint::RadixBits).collect())
+        }
+    }
+
+    /// Returns the radix bits of all runs, including the sweep points.
+    fn radix_bits_points(&self) -> Vec<RadixBits> {
+        match self.sweep_radix_bits.clone() {
+            Some(range) => range
+                .map(|bits| with_first_pass_bits(&self.radix_bits, bits))
+                .collect(),
+            None => vec![self.radix_bits],
+        }
+    }
+
+    /// Validates the options that don't depend on the hardware.
+    ///
+    /// Doesn't initialize CUDA, and can thus run on machines without a GPU.
+    fn validate(&self) -> Result<()> {
+        if self.threads == 0 || self.sweep_threads.contains(&0) {
+            Err(ErrorKind::InvalidArgument(
+                "At least one thread is required".to_string(),
+            ))?;
+        }
+
+        if self.algorithm == ArgAlgorithm::BuildPartition {
+            if self.hashing_scheme != ArgHashingScheme::LinearProbing {
+                Err(ErrorKind::InvalidArgument(
+                    "The build-partitioned join requires --hashing-scheme LinearProbing"
+                        .to_string(),
+                ))?;
+            }
+            if self.build_partition_len == 0 {
+                Err(ErrorKind::InvalidArgument(
+                    "--build-partition-len must be at least one tuple".to_string(),
+                ))?;
+            }
+        }
+
+        let key_bytes = self.tuple_bytes as usize / 2;
+        for radix_bits in self.radix_bits_points() {
+            plan::check_radix_bits_key_width(&radix_bits, key_bytes)?;
+        }
+
+        // The Triton join decides itself how to distribute the partitions
+        if self.partitions_mem_type == ArgMemType::DistributedNuma
+            && self.execution_method != ArgExecutionMethod::GpuTritonJoinTwoPass
+        {
+            plan::check_partitions_proportions(
+                &self.partitions_location,
+                &self.partitions_proportions,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the name of the swept parameter's column.
+    fn sweep_parameter(&self) -> Option<String> {
+        if !self.sweep_threads.is_empty() {
+            Some("threads".to_string())
+        } else if self.sweep_radix_bits.is_some() {
+            Some("radix_bits_fst".to_string())
+        } else {
+            None
+        }
+    }
+}
+
 fn is_percent(x: String) -> std::result::Result<(), String> {
     x.parse::<i32>()
         .map_err(|_| {
@@ -411,20 +685,148 @@ fn parse_radix_bits(input: &str) -> std::result::Result<RadixBits, String> {
         .map(str::trim)
         .map(str::parse)
         .collect::<std::result::Result<_, _>>()
-        .map_err(|e| format!("{}", e))?;
+        .map_err(|e| {
+            format!(
+                "Expected comma-separated radix bits per pass, e.g., 8,8 ({})",
+                e
+            )
+        })?;
 
     let radix_bits = radix_bits_ints
         .as_slice()
         .try_into()
-        .map_err(|e| format!("{}", e))?;
+        .map_err(|e| format!("{}, e.g., 8,8", e))?;
 
     Ok(radix_bits)
 }
 
+fn parse_radix_bits_sweep(input: &str) -> std::result::Result<RangeInclusive<u32>, String> {
+    let (start, end) = input
+        .split_once("..=")
+        .ok_or_else(|| "Expected an inclusive range, e.g., 4..=12".to_string())?;
+
+    let start: u32 = start.trim().parse().map_err(|e| format!("{}", e))?;
+    let end: u32 = end.trim().parse().map_err(|e| format!("{}", e))?;
+
+    if start > end {
+        return Err("The range must not be empty".to_string());
+    }
+
+    Ok(start..=end)
+}
+
+/// Runs the benchmark for each sweep point and algorithm.
+///
+/// Without a sweep, the benchmark runs once with the commandline options. All
+/// measurements are written to a single CSV file.
+fn measure<T>(mut cmd: CmdOpt, device: Device) -> Result<()>
+where
+    T: Default
+        + Clone
+        + Copy
+        + DeviceCopy
+        + Sync
+        + Send
+        + CpuRadixPartitionable
+        + GpuRadixPartitionable
+        + no_partitioning_join::CudaHashJoinable
+        + no_partitioning_join::CpuHashJoinable
+        + cuda_radix_join::CudaRadixJoinable
+        + KeyAttribute
+        + num_traits::AsPrimitive<u64>
+        + num_traits::FromPrimitive
+        + DeserializeOwned,
+{
+    let budget = cmd
+        .max_runtime
+        .map(|secs| RuntimeBudget::new(Duration::from_secs(secs)));
+    let repeat = cmd.repeat;
+    let csv = cmd.csv.clone();
+    let histogram_writer = cmd
+        .partition_histogram
+        .clone()
+        .map(PartitionHistogramWriter::create)
+        .transpose()?
+        .map(|writer| Rc::new(RefCell::new(writer)));
+    let points: Vec<Option<SweepPoint>> = match cmd.sweep_points() {
+        Some(points) => points.into_iter().map(Some).collect(),
+        None => vec![None],
+    };
+    let total_benches = points.len() * cmd.algorithm.algorithms().len();
+
+    let mut progress = if cmd.progress {
+        Some(ProgressReporter::new(total_benches * repeat as usize))
+    } else {
+        None
+    };
+
+    // Set up the benchmarks lazily, so that only one data set is allocated at
+    // a time
+    let benches = points
+        .into_iter()
+        .map(move |point| {
+            if let Some(point) = point {
+                point.apply(&mut cmd);
+            }
+            args_to_bench::<T>(&cmd, device)
+        })
+        .flat_map(|benches| match benches {
+            Ok(benches) => benches.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        });
+
+    let mut started_benches = 0;
+    harness::measure_sweep(
+        repeat,
+        csv,
+        benches,
+        |bench| {
+            let point = started_benches;
+            started_benches += 1;
+
+            let (mut func, template) = bench?;
+            if let Some(writer) = histogram_writer.clone() {
+                let mut is_written = false;
+                func = Box::new(move || {
+                    let data_point = func()?;
+                    if !is_written {
+                        if let Some(ref histograms) = data_point.partition_histograms {
+                            writer.borrow_mut().write(point, histograms)?;
+                            is_written = true;
+                        }
+                    }
+                    Ok(data_point)
+                });
+            }
+
+            Ok((func, template))
+        },
+        progress.as_mut(),
+        budget.as_ref(),
+    )?;
+
+    if let Some(budget) = budget {
+        if started_benches < total_benches {
+            eprintln!(
+                "Maximum runtime of {} s exhausted, skipped {} of {} benchmark points",
+                budget.max_runtime().as_secs(),
+                total_benches - started_benches,
+                total_benches
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets up a benchmark for each algorithm.
+///
+/// All algorithms share the same data set. Their data points are tagged with
+/// the algorithm.
 fn args_to_bench<T>(
     cmd: &CmdOpt,
     device: Device,
-) -> Result<(Box<dyn FnMut() -> Result<RadixJoinPoint>>, DataPoint)>
+) -> Result<Vec<(Box<dyn FnMut() -> Result<RadixJoinPoint>>, DataPoint)>>
 where
     T: Default
         + Clone
@@ -438,6 +840,7 @@ where
         + no_partitioning_join::CpuHashJoinable
         + cuda_radix_join::CudaRadixJoinable
         + KeyAttribute
+        + num_traits::AsPrimitive<u64>
         + num_traits::FromPrimitive
         + DeserializeOwned,
 {
@@ -461,17 +864,19 @@ where
                 );
             }
         }
+        ArgExecutionMethod::CpuRadixJoin => {}
     };
 
     // Device tuning
     let multiprocessors = device.get_attribute(DeviceAttribute::MultiprocessorCount)? as u32;
     let warp_size = device.get_attribute(DeviceAttribute::WarpSize)? as u32;
-    let warp_overcommit_factor = 32;
-
-    let block_size = BlockSize::x(warp_size * warp_overcommit_factor);
+    let block_size = BlockSize::x(warp_size * WARP_OVERCOMMIT_FACTOR);
     let grid_size = GridSize::x(cmd.grid_size.unwrap_or(multiprocessors));
     let stream_grid_size = GridSize::x(grid_size.x / 2);
 
+    // Fail before the data set is generated
+    check_gpu_radix_bits::<T>(cmd, &cmd.radix_bits_points(), &block_size)?;
+
     let page_type = cmd.page_type;
 
     let mut data_builder = JoinDataBuilder::default();
@@ -486,7 +891,7 @@ where
                 }]),
                 page_type,
             }
-            .into(),
+            .try_into()?,
         )
         .outer_mem_type(
             ArgMemTypeHelper {
@@ -497,7 +902,7 @@ where
                 }]),
                 page_type,
             }
-            .into(),
+            .try_into()?,
         );
 
     let exec_method = cmd.execution_method;
@@ -512,6 +917,7 @@ where
     let max_partitions_cache_bytes = cmd.max_partitions_cache_size.map(|s| s * 1024 * 1024); // convert MiB to bytes
     let mem_type = cmd.partitions_mem_type;
     let threads = cmd.threads;
+    let partition_histogram = cmd.partition_histogram.is_some();
 
     let state_mem_type = match cmd.state_mem_type {
         ArgMemType::Numa => MemType::NumaMem {
@@ -540,7 +946,7 @@ where
         node_ratios: node_ratios.clone(),
         page_type,
     }
-    .into();
+    .try_into()?;
 
     // Load file or generate data set
     let (mut join_data, malloc_time, data_gen_time) =
@@ -555,12 +961,16 @@ where
                 ArgDataDistribution::Zipf => DataDistribution::Zipf(cmd.zipf_exponent.unwrap()),
             };
 
+            let rng = cmd
+                .seed
+                .map_or_else(SeededRng::from_entropy, SeededRng::new);
             let (inner_relation_len, outer_relation_len, data_gen) = data_gen_fn::<_>(
                 cmd.data_set,
                 cmd.inner_rel_tuples,
                 cmd.outer_rel_tuples,
                 data_distribution,
                 Some(cmd.selectivity),
+                rng,
             );
             data_builder
                 .inner_len(inner_relation_len)
@@ -581,11 +991,69 @@ where
         CpuAffinity::default()
     };
 
+    let join_data = Rc::new(RefCell::new(join_data));
+    let algorithms = cmd.algorithm.algorithms();
+    let mut benches: Vec<(Box<dyn FnMut() -> Result<RadixJoinPoint>>, DataPoint)> = Vec::new();
+
+    if algorithms.contains(&ArgAlgorithm::NoPartition) {
+        let join_data = join_data.clone();
+        let hash_table_mem_type = state_mem_type.clone();
+        let grid_size = grid_size.clone();
+        let block_size = block_size.clone();
+
+        let hjc: Box<dyn FnMut() -> Result<RadixJoinPoint>> = Box::new(move || {
+            let (_result, data_point) = gpu_no_partitioning_join(
+                &mut join_data.borrow_mut(),
+                hashing_scheme,
+                hash_table_mem_type.clone(),
+                (&grid_size, &block_size),
+            )?;
+
+            Ok(data_point)
+        });
+
+        let dp = DataPoint {
+            algorithm: Some(ArgAlgorithm::NoPartition),
+            ..dp.clone()
+        };
+        benches.push((hjc, dp));
+    }
+
+    if algorithms.contains(&ArgAlgorithm::BuildPartition) {
+        let join_data = join_data.clone();
+        let hash_table_mem_type = state_mem_type.clone();
+        let build_partition_len = cmd.build_partition_len;
+        let grid_size = grid_size.clone();
+        let block_size = block_size.clone();
+
+        let hjc: Box<dyn FnMut() -> Result<RadixJoinPoint>> = Box::new(move || {
+            let (_result, data_point) = gpu_build_partitioned_join(
+                &mut join_data.borrow_mut(),
+                hashing_scheme,
+                hash_table_mem_type.clone(),
+                build_partition_len,
+                (&grid_size, &block_size),
+            )?;
+
+            Ok(data_point)
+        });
+
+        let dp = DataPoint {
+            algorithm: Some(ArgAlgorithm::BuildPartition),
+            ..dp.clone()
+        };
+        benches.push((hjc, dp));
+    }
+
+    if !algorithms.contains(&ArgAlgorithm::RadixPartition) {
+        return Ok(benches);
+    }
+
     // Create closure that wraps a hash join benchmark function
     let hjc: Box<dyn FnMut() -> Result<RadixJoinPoint>> = match exec_method {
         ArgExecutionMethod::CpuPartitionedRadixJoinTwoPass => Box::new(move || {
             let (_result, data_point) = cpu_partitioned_radix_join(
-                &mut join_data,
+                &mut join_data.borrow_mut(),
                 hashing_scheme,
                 histogram_algorithms[0],
                 histogram_algorithms[1],
@@ -601,13 +1069,33 @@ where
                 page_type.into(),
                 (&grid_size, &block_size),
                 (&stream_grid_size, &block_size),
+                partition_histogram,
             )?;
 
             Ok(data_point)
         }),
+        ArgExecutionMethod::CpuRadixJoin => {
+            // Spawn and pin the threads once, instead of in each repetition
+            let thread_pool = cpu_thread_pool(threads, &cpu_affinity)?;
+
+            Box::new(move || {
+                let (_result, data_point) = cpu_radix_join(
+                    &mut join_data.borrow_mut(),
+                    hashing_scheme,
+                    histogram_algorithms[0],
+                    partition_algorithm,
+                    &radix_bits,
+                    &thread_pool,
+                    partitions_mem_type.clone(),
+                    partition_histogram,
+                )?;
+
+                Ok(data_point)
+            })
+        }
         ArgExecutionMethod::GpuRadixJoinTwoPass => Box::new(move || {
             let (_result, data_point) = gpu_radix_join(
-                &mut join_data,
+                &mut join_data.borrow_mut(),
                 hashing_scheme,
                 histogram_algorithms[0],
                 histogram_algorithms[1],
@@ -623,13 +1111,14 @@ where
                 page_type.into(),
                 (&grid_size, &block_size),
                 (&stream_grid_size, &block_size),
+                partition_histogram,
             )?;
 
             Ok(data_point)
         }),
         ArgExecutionMethod::GpuTritonJoinTwoPass => Box::new(move || {
             let (_result, data_point) = gpu_triton_join(
-                &mut join_data,
+                &mut join_data.borrow_mut(),
                 hashing_scheme,
                 histogram_algorithms[0],
                 histogram_algorithms[1],
@@ -645,13 +1134,195 @@ where
                 page_type.into(),
                 (&grid_size, &block_size),
                 (&stream_grid_size, &block_size),
+                partition_histogram,
             )?;
 
             Ok(data_point)
         }),
     };
 
-    Ok((hjc, dp))
+    let dp = DataPoint {
+        algorithm: Some(ArgAlgorithm::RadixPartition),
+        ..dp
+    };
+    benches.push((hjc, dp));
+
+    Ok(benches)
+}
+
+/// Returns the maximum radix bits of the first, second, and third pass.
+///
+/// The shared memory of the device limits the fanout of GPU passes. CPU
+/// passes aren't limited.
+fn max_pass_radix_bits<T: DeviceCopy>(cmd: &CmdOpt, block_size: &BlockSize) -> Result<[u32; 3]> {
+    let mut max_pass_radix_bits = [u32::MAX; 3];
+
+    if !cmd
+        .algorithm
+        .algorithms()
+        .contains(&ArgAlgorithm::RadixPartition)
+    {
+        return Ok(max_pass_radix_bits);
+    }
+
+    let passes = [
+        (cmd.histogram_algorithm, cmd.partition_algorithm, 0..1),
+        (
+            cmd.histogram_algorithm_2nd,
+            cmd.partition_algorithm_2nd,
+            1..3,
+        ),
+    ];
+
+    for (histogram_algorithm, partition_algorithm, pass_ids) in passes.iter() {
+        let histogram_algorithm: DeviceType<CpuHistogramAlgorithm, GpuHistogramAlgorithm> =
+            (*histogram_algorithm).into();
+        let partition_algorithm: DeviceType<_, GpuRadixPartitionAlgorithm> =
+            (*partition_algorithm).into();
+
+        if let (DeviceType::Gpu(histogram_algorithm), DeviceType::Gpu(partition_algorithm)) =
+            (histogram_algorithm, partition_algorithm)
+        {
+            let max_radix_bits = GpuRadixPartitioner::max_radix_bits::<T>(
+                histogram_algorithm,
+                partition_algorithm,
+                block_size,
+            )?;
+            max_pass_radix_bits[pass_ids.clone()]
+                .iter_mut()
+                .for_each(|max| *max = max_radix_bits);
+        }
+    }
+
+    Ok(max_pass_radix_bits)
+}
+
+/// Checks the radix bits against the limits of the GPU partitioning passes.
+///
+/// See `max_pass_radix_bits` for the limits.
+fn check_gpu_radix_bits<T: DeviceCopy>(
+    cmd: &CmdOpt,
+    radix_bits_points: &[RadixBits],
+    block_size: &BlockSize,
+) -> Result<()> {
+    let max_pass_radix_bits = max_pass_radix_bits::<T>(cmd, block_size)?;
+    let passes = [RadixPass::First, RadixPass::Second, RadixPass::Third];
+
+    for radix_bits in radix_bits_points.iter() {
+        for (&pass, &max_radix_bits) in passes.iter().zip(max_pass_radix_bits.iter()) {
+            plan::check_pass_radix_bits(radix_bits, &[pass], max_radix_bits)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates the options against the hardware, and prints the resolved plan.
+///
+/// The data set is neither generated nor loaded. Its size is inferred from
+/// the data set options, and thus unknown for relations loaded from files.
+fn dry_run<T>(cmd: &CmdOpt, device: Device) -> Result<()>
+where
+    T: Copy + DeviceCopy + Send + KeyAttribute + num_traits::FromPrimitive,
+{
+    let multiprocessors = device.get_attribute(DeviceAttribute::MultiprocessorCount)? as u32;
+    let warp_size = device.get_attribute(DeviceAttribute::WarpSize)? as u32;
+    let block_size = BlockSize::x(warp_size * WARP_OVERCOMMIT_FACTOR);
+    let grid_size = GridSize::x(cmd.grid_size.unwrap_or(multiprocessors));
+    let algorithms = cmd.algorithm.algorithms();
+    let radix_bits_points = cmd.radix_bits_points();
+
+    check_gpu_radix_bits::<T>(cmd, &radix_bits_points, &block_size)?;
+
+    let mem_location = |mem_type: ArgMemType, node: u16| match mem_type {
+        ArgMemType::Numa | ArgMemType::NumaPinned => Some(MemLocation::Numa(node)),
+        ArgMemType::Device => Some(MemLocation::Gpu),
+        _ => None,
+    };
+
+    let tuple_bytes = 2 * size_of::<T>();
+    let relation_lens = if cmd.inner_rel_file.is_some() {
+        None
+    } else {
+        let (inner_len, outer_len, _) = data_gen_fn::<T>(
+            cmd.data_set,
+            cmd.inner_rel_tuples,
+            cmd.outer_rel_tuples,
+            DataDistribution::Uniform,
+            Some(cmd.selectivity),
+            SeededRng::new(0),
+        );
+        Some((inner_len, outer_len))
+    };
+
+    let mut memory_plan = MemoryPlan::new();
+    if let Some((inner_len, outer_len)) = relation_lens {
+        let inner_bytes = plan::relation_bytes(inner_len, tuple_bytes)?;
+        let outer_bytes = plan::relation_bytes(outer_len, tuple_bytes)?;
+
+        if let Some(location) = mem_location(cmd.mem_type, cmd.inner_rel_location) {
+            memory_plan.add(location, inner_bytes)?;
+        }
+        if let Some(location) = mem_location(cmd.mem_type, cmd.outer_rel_location) {
+            memory_plan.add(location, outer_bytes)?;
+        }
+
+        // The partitioned relations are at least as large as the relations
+        if algorithms.contains(&ArgAlgorithm::RadixPartition) {
+            let partitions_node = cmd.partitions_location.first().copied().unwrap_or(0);
+            if let Some(location) = mem_location(cmd.partitions_mem_type, partitions_node) {
+                memory_plan.add(location, inner_bytes)?;
+                memory_plan.add(location, outer_bytes)?;
+            }
+        }
+    }
+
+    memory_plan.check_oversubscription(|location| match location {
+        MemLocation::Numa(node) => Ok(linux_wrapper::numa_mem_info(node)?.total),
+        MemLocation::Gpu => Ok(cuda_wrapper::mem_info()?.total),
+    })?;
+
+    println!("Device: {} ({})", cmd.device_id, device.name()?);
+    println!("Algorithms: {:?}", algorithms);
+    println!("Execution strategy: {:?}", cmd.execution_method);
+    println!("Hashing scheme: {:?}", cmd.hashing_scheme);
+    match relation_lens {
+        Some((inner_len, outer_len)) => println!(
+            "Relations: {} inner and {} outer tuples of {} bytes",
+            inner_len, outer_len, tuple_bytes
+        ),
+        None => println!("Relations: loaded from files"),
+    }
+    for radix_bits in radix_bits_points.iter() {
+        println!(
+            "Radix bits: {:?} (fanout {})",
+            [
+                radix_bits.pass_radix_bits(RadixPass::First),
+                radix_bits.pass_radix_bits(RadixPass::Second),
+                radix_bits.pass_radix_bits(RadixPass::Third),
+            ],
+            radix_bits.fanout()
+        );
+    }
+    println!(
+        "Memory: relations {:?}, partitions {:?} on {:?}, state {:?}",
+        cmd.mem_type, cmd.partitions_mem_type, cmd.partitions_location, cmd.state_mem_type
+    );
+    for (location, bytes) in memory_plan.required_bytes() {
+        println!("  {}: {} MiB", location, bytes / 2_usize.pow(20));
+    }
+    println!(
+        "GPU launch: {} blocks of {} threads",
+        grid_size.x, block_size.x
+    );
+    if cmd.sweep_threads.is_empty() {
+        println!("CPU threads: {}", cmd.threads);
+    } else {
+        println!("CPU threads: {:?}", cmd.sweep_threads);
+    }
+    println!("Runs: {}", cmd.repeat);
+
+    Ok(())
 }
 
 fn data_gen_fn<T>(
@@ -660,93 +1331,85 @@ fn data_gen_fn<T>(
     outer_rel_tuples: Option<usize>,
     data_distribution: DataDistribution,
     selectivity: Option<u32>,
+    rng: SeededRng,
 ) -> (usize, usize, JoinDataGenFn<T>)
 where
     T: Copy + Send + KeyAttribute + num_traits::FromPrimitive,
 {
+    // Each generator resets the RNG, thus every call generates the same data
+    // set for a fixed seed
+    let mut rng = rng;
+
     match description {
         ArgDataSet::Blanas => (
             datagen::popular::Blanas::primary_key_len(),
             datagen::popular::Blanas::foreign_key_len(),
             Box::new(move |pk_rel, _, fk_rel, _| {
-                datagen::popular::Blanas::gen(pk_rel, fk_rel, selectivity).map_err(|e| e.into())
+                rng.reset();
+                datagen::popular::Blanas::gen_with_rng(pk_rel, fk_rel, selectivity, &mut rng)
+                    .map_err(|e| e.into())
             }),
         ),
         ArgDataSet::Kim => (
             datagen::popular::Kim::primary_key_len(),
             datagen::popular::Kim::foreign_key_len(),
             Box::new(move |pk_rel, _, fk_rel, _| {
-                datagen::popular::Kim::gen(pk_rel, fk_rel, selectivity).map_err(|e| e.into())
+                rng.reset();
+                datagen::popular::Kim::gen_with_rng(pk_rel, fk_rel, selectivity, &mut rng)
+                    .map_err(|e| e.into())
             }),
         ),
-        ArgDataSet::Blanas4MB => {
-            let gen = move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                datagen::relation::UniformRelation::gen_primary_key_par(pk_rel, selectivity)?;
-                datagen::relation::UniformRelation::gen_attr_par(fk_rel, 0..pk_rel.len())?;
-                Ok(())
-            };
-
-            (512 * 2_usize.pow(10), 256 * 2_usize.pow(20), Box::new(gen))
-        }
+        ArgDataSet::Blanas4MB => (
+            512 * 2_usize.pow(10),
+            256 * 2_usize.pow(20),
+            uniform_data_gen_fn(selectivity, rng),
+        ),
         ArgDataSet::Test => {
             let gen = move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                datagen::relation::UniformRelation::gen_primary_key(pk_rel, selectivity)?;
-                datagen::relation::UniformRelation::gen_foreign_key_from_primary_key(
-                    fk_rel, pk_rel,
+                rng.reset();
+                datagen::relation::UniformRelation::gen_primary_key_with_rng(
+                    pk_rel,
+                    selectivity,
+                    &mut rng,
+                )?;
+                datagen::relation::UniformRelation::gen_foreign_key_from_primary_key_with_rng(
+                    fk_rel, pk_rel, &mut rng,
                 );
                 Ok(())
             };
 
             (1000, 1000, Box::new(gen))
         }
-        ArgDataSet::Lutz2Gv32G => {
-            let gen = move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                datagen::relation::UniformRelation::gen_primary_key_par(pk_rel, selectivity)?;
-                datagen::relation::UniformRelation::gen_attr_par(fk_rel, 0..pk_rel.len())?;
-                Ok(())
-            };
-
-            (
-                2 * 2_usize.pow(30) / (2 * size_of::<T>()),
-                32 * 2_usize.pow(30) / (2 * size_of::<T>()),
-                Box::new(gen),
-            )
-        }
-        ArgDataSet::Lutz32Gv32G => {
-            let gen = move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                datagen::relation::UniformRelation::gen_primary_key_par(pk_rel, selectivity)?;
-                datagen::relation::UniformRelation::gen_attr_par(fk_rel, 0..pk_rel.len())?;
-                Ok(())
-            };
-
-            (
-                32 * 2_usize.pow(30) / (2 * size_of::<T>()),
-                32 * 2_usize.pow(30) / (2 * size_of::<T>()),
-                Box::new(gen),
-            )
-        }
+        ArgDataSet::Lutz2Gv32G => (
+            2 * 2_usize.pow(30) / (2 * size_of::<T>()),
+            32 * 2_usize.pow(30) / (2 * size_of::<T>()),
+            uniform_data_gen_fn(selectivity, rng),
+        ),
+        ArgDataSet::Lutz32Gv32G => (
+            32 * 2_usize.pow(30) / (2 * size_of::<T>()),
+            32 * 2_usize.pow(30) / (2 * size_of::<T>()),
+            uniform_data_gen_fn(selectivity, rng),
+        ),
         ArgDataSet::Custom => {
-            let uniform_gen = Box::new(
-                move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                    datagen::relation::UniformRelation::gen_primary_key_par(pk_rel, selectivity)?;
-                    datagen::relation::UniformRelation::gen_attr_par(fk_rel, 0..pk_rel.len())?;
-                    Ok(())
-                },
-            );
-
             let gen: JoinDataGenFn<T> = match data_distribution {
-                DataDistribution::Uniform => uniform_gen,
-                DataDistribution::Zipf(exp) if !(exp > 0.0) => uniform_gen,
-                DataDistribution::Zipf(exp) => Box::new(
+                DataDistribution::Zipf(exp) if exp > 0.0 => Box::new(
                     move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
-                        datagen::relation::UniformRelation::gen_primary_key_par(
+                        rng.reset();
+                        datagen::relation::UniformRelation::gen_primary_key_par_with_rng(
                             pk_rel,
                             selectivity,
+                            &mut rng,
+                        )?;
+                        datagen::relation::ZipfRelation::gen_attr_par_with_rng(
+                            fk_rel,
+                            pk_rel.len(),
+                            exp,
+                            &mut rng,
                         )?;
-                        datagen::relation::ZipfRelation::gen_attr_par(fk_rel, pk_rel.len(), exp)?;
                         Ok(())
                     },
                 ),
+                _ => uniform_data_gen_fn(selectivity, rng),
             };
 
             (
@@ -762,6 +1425,30 @@ where
     }
 }
 
+/// Generates a primary key inner relation and a uniform foreign key outer
+/// relation in parallel.
+fn uniform_data_gen_fn<T>(selectivity: Option<u32>, mut rng: SeededRng) -> JoinDataGenFn<T>
+where
+    T: Copy + Send + KeyAttribute + num_traits::FromPrimitive,
+{
+    Box::new(
+        move |pk_rel: &mut [_], _: &mut [_], fk_rel: &mut [_], _: &mut [_]| {
+            rng.reset();
+            datagen::relation::UniformRelation::gen_primary_key_par_with_rng(
+                pk_rel,
+                selectivity,
+                &mut rng,
+            )?;
+            datagen::relation::UniformRelation::gen_attr_par_with_rng(
+                fk_rel,
+                0..pk_rel.len(),
+                &mut rng,
+            )?;
+            Ok(())
+        },
+    )
+}
+
 trait CmdOptToDataPoint {
     fn fill_from_cmd_options(&self, cmd: &CmdOpt) -> Result<DataPoint>;
 }
@@ -773,7 +1460,8 @@ impl CmdOptToDataPoint for DataPoint {
             ArgExecutionMethod::GpuRadixJoinTwoPass | ArgExecutionMethod::GpuTritonJoinTwoPass => {
                 let device = Device::get_device(cmd.device_id.into())?;
                 vec![device.name()?]
-            } // CPU execution methods should use: vec![numa_gpu::runtime::hw_info::cpu_codename()?]
+            }
+            ArgExecutionMethod::CpuRadixJoin => vec![cpu_codename()?],
             ArgExecutionMethod::CpuPartitionedRadixJoinTwoPass => {
                 let device = Device::get_device(cmd.device_id.into())?;
                 vec![cpu_codename()?, device.name()?]
@@ -788,6 +1476,7 @@ impl CmdOptToDataPoint for DataPoint {
             execution_method: Some(cmd.execution_method),
             device_codename: Some(dev_codename_str),
             dmem_buffer_size: Some(cmd.dmem_buffer_size),
+            sweep_parameter: cmd.sweep_parameter(),
             threads: Some(cmd.threads),
             radix_bits_fst: cmd.radix_bits.pass_radix_bits(RadixPass::First),
             radix_bits_snd: cmd.radix_bits.pass_radix_bits(RadixPass::Second),
@@ -813,6 +1502,7 @@ impl CmdOptToDataPoint for DataPoint {
                 None
             },
             join_selectivity: Some(cmd.selectivity as f64 / 100.0),
+            data_seed: cmd.seed,
             ..self.clone()
         };
 
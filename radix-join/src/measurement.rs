@@ -12,5 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod budget;
 pub mod data_point;
 pub mod harness;
+pub mod progress;
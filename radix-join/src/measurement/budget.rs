@@ -0,0 +1,81 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A wall-clock budget for benchmark sweeps.
+//!
+//! A sweep checks the budget before it starts a new point. Once the budget is
+//! exhausted, the sweep stops and keeps the measurements of the completed
+//! points. A point that has already started always runs to completion, thus
+//! the sweep can overrun the budget by the run time of one point.
+
+use std::time::{Duration, Instant};
+
+/// A source of the elapsed time since the start of the budget.
+pub trait Clock {
+    fn elapsed(&self) -> Duration;
+}
+
+/// Measures the elapsed wall-clock time since its creation.
+#[derive(Debug)]
+pub struct WallClock {
+    start: Instant,
+}
+
+impl WallClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for WallClock {
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Limits the run time of a sweep.
+pub struct RuntimeBudget {
+    max_runtime: Duration,
+    clock: Box<dyn Clock>,
+}
+
+impl RuntimeBudget {
+    /// Creates a budget that starts now.
+    pub fn new(max_runtime: Duration) -> Self {
+        Self::with_clock(max_runtime, Box::new(WallClock::new()))
+    }
+
+    /// Creates a budget that measures the elapsed time with `clock`.
+    pub fn with_clock(max_runtime: Duration, clock: Box<dyn Clock>) -> Self {
+        Self { max_runtime, clock }
+    }
+
+    /// Returns the maximum run time.
+    pub fn max_runtime(&self) -> Duration {
+        self.max_runtime
+    }
+
+    /// Returns `true` if the elapsed time has reached the maximum run time.
+    pub fn is_exhausted(&self) -> bool {
+        self.clock.elapsed() >= self.max_runtime
+    }
+}
@@ -20,7 +20,6 @@ use rustacuda::function::{BlockSize, GridSize};
 use rustacuda::memory::DeviceCopy;
 use serde::Serializer;
 use serde_derive::Serialize;
-use std::mem::size_of;
 use std::string::ToString;
 use std::time::Duration;
 
@@ -31,10 +30,12 @@ pub struct DataPoint {
     pub histogram_algorithm: Option<ArgHistogramAlgorithm>,
     pub partition_algorithm: Option<ArgRadixPartitionAlgorithm>,
     pub partition_algorithm_2nd: Option<ArgRadixPartitionAlgorithm>,
+    pub algorithm: Option<ArgAlgorithm>,
     pub execution_method: Option<ArgExecutionMethod>,
     #[serde(serialize_with = "serialize_vec")]
     pub device_codename: Option<Vec<String>>,
     pub dmem_buffer_size: Option<usize>,
+    pub sweep_parameter: Option<String>,
     pub threads: Option<usize>,
     pub grid_size: Option<u32>,
     pub block_size: Option<u32>,
@@ -63,6 +64,7 @@ pub struct DataPoint {
     pub data_distribution: Option<ArgDataDistribution>,
     pub zipf_exponent: Option<f64>,
     pub join_selectivity: Option<f64>,
+    pub data_seed: Option<u64>,
     pub warm_up: Option<bool>,
     pub nvtx_run_id: Option<RangeId>,
     pub prefix_sum_ns: Option<f64>,
@@ -72,6 +74,8 @@ pub struct DataPoint {
     pub state_malloc_ns: Option<f64>,
     pub relation_malloc_ns: Option<f64>,
     pub relation_gen_ns: Option<f64>,
+    pub partition_gib_per_sec: Option<f64>,
+    pub join_gib_per_sec: Option<f64>,
 }
 
 impl DataPoint {
@@ -88,16 +92,10 @@ impl DataPoint {
 
     pub fn fill_from_join_data<T: DeviceCopy>(&self, join_data: &JoinData<T>) -> DataPoint {
         DataPoint {
-            build_tuples: Some(join_data.build_relation_key.len()),
-            build_bytes: Some(
-                (join_data.build_relation_key.len() + join_data.build_relation_payload.len())
-                    * size_of::<T>(),
-            ),
-            probe_tuples: Some(join_data.probe_relation_key.len()),
-            probe_bytes: Some(
-                (join_data.probe_relation_key.len() + join_data.probe_relation_payload.len())
-                    * size_of::<T>(),
-            ),
+            build_tuples: Some(join_data.build_relation.len()),
+            build_bytes: Some(join_data.build_relation.bytes()),
+            probe_tuples: Some(join_data.probe_relation.len()),
+            probe_bytes: Some(join_data.probe_relation.bytes()),
             ..self.clone()
         }
     }
@@ -117,6 +115,45 @@ impl DataPoint {
             ..self.clone()
         }
     }
+
+    /// Fills in the partition and join throughputs computed from the relation
+    /// sizes and the phase times.
+    pub fn fill_throughput(&self) -> DataPoint {
+        DataPoint {
+            partition_gib_per_sec: self.compute_partition_gib_per_sec(),
+            join_gib_per_sec: self.compute_join_gib_per_sec(),
+            ..self.clone()
+        }
+    }
+
+    /// Computes the partition throughput in GiB/s.
+    ///
+    /// The partition phase reads both relations. Returns `None` if the
+    /// relation sizes or the partition time are unknown, or if the execution
+    /// method doesn't partition (i.e., the partition time is zero).
+    pub fn compute_partition_gib_per_sec(&self) -> Option<f64> {
+        Self::gib_per_sec(self.relation_bytes()?, self.partition_ns?)
+    }
+
+    /// Computes the join throughput in GiB/s.
+    ///
+    /// Returns `None` if the relation sizes or the join time are unknown.
+    pub fn compute_join_gib_per_sec(&self) -> Option<f64> {
+        Self::gib_per_sec(self.relation_bytes()?, self.join_ns?)
+    }
+
+    fn relation_bytes(&self) -> Option<usize> {
+        Some(self.build_bytes? + self.probe_bytes?)
+    }
+
+    fn gib_per_sec(bytes: usize, ns: f64) -> Option<f64> {
+        if ns > 0.0 {
+            let secs = ns * 10_f64.powf(-9.0);
+            Some(bytes as f64 / 2_f64.powf(30.0) / secs)
+        } else {
+            None
+        }
+    }
 }
 
 /// Serialize `Option<Vec<T>>` by converting it into a `String`.
@@ -145,3 +182,40 @@ where
         ser.serialize_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::DataPoint;
+
+    #[test]
+    fn partition_gib_per_sec_from_known_point() {
+        let dp = DataPoint {
+            build_bytes: Some(1 << 30),
+            probe_bytes: Some(3 << 30),
+            partition_ns: Some(500_000_000.0),
+            join_ns: Some(2_000_000_000.0),
+            ..DataPoint::default()
+        }
+        .fill_throughput();
+
+        let partition_gib_per_sec = dp.partition_gib_per_sec.unwrap();
+        assert!((partition_gib_per_sec - 8.0).abs() <= 8.0 * 1e-12);
+
+        let join_gib_per_sec = dp.join_gib_per_sec.unwrap();
+        assert!((join_gib_per_sec - 2.0).abs() <= 2.0 * 1e-12);
+    }
+
+    #[test]
+    fn partition_throughput_is_unknown_without_partitioning() {
+        let dp = DataPoint {
+            build_bytes: Some(1024),
+            probe_bytes: Some(4096),
+            partition_ns: Some(0.0),
+            join_ns: Some(1000.0),
+            ..DataPoint::default()
+        };
+
+        assert_eq!(dp.compute_partition_gib_per_sec(), None);
+        assert!(dp.compute_join_gib_per_sec().is_some());
+    }
+}
@@ -12,11 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::budget::RuntimeBudget;
 use super::data_point::DataPoint;
+use super::progress::ProgressReporter;
 use crate::error::Result;
 use numa_gpu::runtime::nvtx::Range;
+use serde_derive::Serialize;
 use std::ffi::CString;
+use std::fs::File;
 use std::path::PathBuf;
+use std::time::Instant;
 
 #[derive(Debug, Default)]
 pub struct RadixJoinPoint {
@@ -27,6 +32,57 @@ pub struct RadixJoinPoint {
     pub join_ns: Option<f64>,
     pub cached_build_tuples: Option<usize>,
     pub cached_probe_tuples: Option<usize>,
+    pub partition_histograms: Option<PartitionHistograms>,
+}
+
+/// The number of tuples in each partition after the first partitioning pass.
+#[derive(Clone, Debug, Default)]
+pub struct PartitionHistograms {
+    pub inner: Vec<u64>,
+    pub outer: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct PartitionHistogramRow {
+    point: usize,
+    relation: &'static str,
+    partition_id: usize,
+    tuples: u64,
+}
+
+/// Writes partition histograms to a CSV file, one row per partition.
+///
+/// The rows are tagged with the sweep point, the relation, and the partition
+/// ID. Thus, the histograms of all sweep points share a single file.
+pub struct PartitionHistogramWriter {
+    csv: csv::Writer<File>,
+}
+
+impl PartitionHistogramWriter {
+    pub fn create(out_file_name: PathBuf) -> Result<Self> {
+        let csv_file = File::create(out_file_name)?;
+        let csv = csv::Writer::from_writer(csv_file);
+
+        Ok(Self { csv })
+    }
+
+    /// Appends the histograms of a sweep point.
+    pub fn write(&mut self, point: usize, histograms: &PartitionHistograms) -> Result<()> {
+        let relations = [("inner", &histograms.inner), ("outer", &histograms.outer)];
+        for &(relation, histogram) in relations.iter() {
+            for (partition_id, &tuples) in histogram.iter().enumerate() {
+                self.csv.serialize(PartitionHistogramRow {
+                    point,
+                    relation,
+                    partition_id,
+                    tuples,
+                })?;
+            }
+        }
+        self.csv.flush()?;
+
+        Ok(())
+    }
 }
 
 pub fn measure(
@@ -34,48 +90,142 @@ pub fn measure(
     repeat: u32,
     out_file_name: Option<PathBuf>,
     template: DataPoint,
-    mut func: Box<dyn FnMut() -> Result<RadixJoinPoint>>,
+    func: Box<dyn FnMut() -> Result<RadixJoinPoint>>,
+    progress: Option<&mut ProgressReporter>,
 ) -> Result<()> {
-    let measurements = (0..repeat)
+    let measurements = run(repeat, template, func, progress)?;
+
+    if let Some(ofn) = out_file_name {
+        write_csv(ofn, &measurements)?;
+    }
+
+    Ok(())
+}
+
+/// Measures the benchmark once for each point of a parameter sweep.
+///
+/// `bench` sets up the benchmark for a sweep point. All points run within the
+/// same process, and thus share the CUDA context and module. Each point yields
+/// a group of `repeat` rows, and the groups are written in sweep order to a
+/// single CSV file.
+///
+/// If a `progress` reporter is given, each run reports its progress.
+///
+/// If a `budget` is given, the sweep stops before the next point once the
+/// budget is exhausted. The remaining points are neither set up nor measured,
+/// and the completed points are written to the CSV file as usual.
+///
+/// Returns the measurements of all completed sweep points.
+pub fn measure_sweep<I, F>(
+    repeat: u32,
+    out_file_name: Option<PathBuf>,
+    points: I,
+    mut bench: F,
+    mut progress: Option<&mut ProgressReporter>,
+    budget: Option<&RuntimeBudget>,
+) -> Result<Vec<DataPoint>>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Result<(Box<dyn FnMut() -> Result<RadixJoinPoint>>, DataPoint)>,
+{
+    let mut measurements = Vec::new();
+    let mut points = points.into_iter();
+
+    // Check the budget before advancing the iterator, because a lazy
+    // iterator might set up the next point's data set
+    while !budget.map_or(false, RuntimeBudget::is_exhausted) {
+        let point = match points.next() {
+            Some(point) => point,
+            None => break,
+        };
+
+        let (func, template) = bench(point)?;
+        measurements.extend(run(repeat, template, func, progress.as_deref_mut())?);
+    }
+
+    if let Some(ofn) = out_file_name {
+        write_csv(ofn, &measurements)?;
+    }
+
+    Ok(measurements)
+}
+
+fn run(
+    repeat: u32,
+    template: DataPoint,
+    mut func: Box<dyn FnMut() -> Result<RadixJoinPoint>>,
+    mut progress: Option<&mut ProgressReporter>,
+) -> Result<Vec<DataPoint>> {
+    let point = point_label(&template);
+
+    (0..repeat)
         .zip(std::iter::once(true).chain(std::iter::repeat(false)))
         .map(|(run, warm_up)| {
+            if let Some(ref progress) = progress {
+                progress.report(&point, run, repeat);
+            }
+
             let range_message =
                 CString::new(format!("Measurement run {}", run)).expect("Failed to format string");
 
+            let timer = Instant::now();
             let range = Range::new(&range_message);
             let result = func();
             let run_id = range.end();
 
-            result.map(|p| DataPoint {
-                warm_up: Some(warm_up),
-                nvtx_run_id: Some(run_id),
-                cached_build_tuples: p.cached_build_tuples,
-                cached_probe_tuples: p.cached_probe_tuples,
-                relation_malloc_ns: if warm_up {
-                    template.relation_malloc_ns
-                } else {
-                    None
-                },
-                relation_gen_ns: if warm_up {
-                    template.relation_gen_ns
-                } else {
-                    None
-                },
-                prefix_sum_ns: p.prefix_sum_ns,
-                partition_ns: p.partition_ns,
-                join_ns: p.join_ns,
-                partitions_malloc_ns: p.partitions_malloc_ns,
-                state_malloc_ns: p.state_malloc_ns,
-                ..template.clone()
-            })
+            if let Some(ref mut progress) = progress {
+                progress.record(timer.elapsed());
+            }
+
+            result
+                .map(|p| DataPoint {
+                    warm_up: Some(warm_up),
+                    nvtx_run_id: Some(run_id),
+                    cached_build_tuples: p.cached_build_tuples,
+                    cached_probe_tuples: p.cached_probe_tuples,
+                    relation_malloc_ns: if warm_up {
+                        template.relation_malloc_ns
+                    } else {
+                        None
+                    },
+                    relation_gen_ns: if warm_up {
+                        template.relation_gen_ns
+                    } else {
+                        None
+                    },
+                    prefix_sum_ns: p.prefix_sum_ns,
+                    partition_ns: p.partition_ns,
+                    join_ns: p.join_ns,
+                    partitions_malloc_ns: p.partitions_malloc_ns,
+                    state_malloc_ns: p.state_malloc_ns,
+                    ..template.clone()
+                })
+                .map(|dp| dp.fill_throughput())
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect()
+}
 
-    if let Some(ofn) = out_file_name {
-        let csv_file = std::fs::File::create(ofn)?;
-        let mut csv = csv::Writer::from_writer(csv_file);
-        measurements.iter().try_for_each(|row| csv.serialize(row))?;
+/// Describes the parameter point of a data point for progress reports.
+fn point_label(template: &DataPoint) -> String {
+    let mut label = match template.algorithm {
+        Some(algorithm) => format!("{:?}", algorithm),
+        None => "benchmark".to_string(),
+    };
+
+    if let Some(threads) = template.threads {
+        label.push_str(&format!(", {} threads", threads));
+    }
+    if let Some(radix_bits) = template.radix_bits_fst {
+        label.push_str(&format!(", {} radix bits", radix_bits));
     }
 
+    label
+}
+
+fn write_csv(out_file_name: PathBuf, measurements: &[DataPoint]) -> Result<()> {
+    let csv_file = std::fs::File::create(out_file_name)?;
+    let mut csv = csv::Writer::from_writer(csv_file);
+    measurements.iter().try_for_each(|row| csv.serialize(row))?;
+
     Ok(())
 }
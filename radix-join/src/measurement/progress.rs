@@ -0,0 +1,113 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Progress reports for long-running benchmark sweeps.
+//!
+//! The reports are printed to stderr, so that they don't interleave with
+//! results printed to stdout.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of recent runs from which the ETA is estimated.
+const DEFAULT_WINDOW: usize = 10;
+
+/// Tracks the completed runs of a benchmark and estimates the remaining time.
+///
+/// The ETA is based on the mean duration of the most recent runs. A rolling
+/// window adapts faster than the mean over all runs when the sweep moves to
+/// parameters with a different run time, e.g., more threads.
+#[derive(Debug)]
+pub struct ProgressReporter {
+    total_runs: usize,
+    completed_runs: usize,
+    window: usize,
+    recent_runs: VecDeque<Duration>,
+}
+
+impl ProgressReporter {
+    /// Creates a reporter for `total_runs` runs.
+    pub fn new(total_runs: usize) -> Self {
+        Self::with_window(total_runs, DEFAULT_WINDOW)
+    }
+
+    /// Creates a reporter that estimates the ETA from the last `window` runs.
+    ///
+    /// The window contains at least one run.
+    pub fn with_window(total_runs: usize, window: usize) -> Self {
+        let window = window.max(1);
+
+        Self {
+            total_runs,
+            completed_runs: 0,
+            window,
+            recent_runs: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Records the duration of a completed run.
+    pub fn record(&mut self, run_time: Duration) {
+        if self.recent_runs.len() == self.window {
+            self.recent_runs.pop_front();
+        }
+        self.recent_runs.push_back(run_time);
+        self.completed_runs += 1;
+    }
+
+    /// Returns the number of completed runs.
+    pub fn completed_runs(&self) -> usize {
+        self.completed_runs
+    }
+
+    /// Returns the total number of runs.
+    pub fn total_runs(&self) -> usize {
+        self.total_runs
+    }
+
+    /// Returns the estimated time until all runs are completed.
+    ///
+    /// Returns `None` before the first run is completed.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.recent_runs.is_empty() {
+            return None;
+        }
+
+        let remaining = self.total_runs.saturating_sub(self.completed_runs);
+        let window_time: Duration = self.recent_runs.iter().sum();
+        let mean_secs = window_time.as_secs_f64() / self.recent_runs.len() as f64;
+
+        Some(Duration::from_secs_f64(mean_secs * remaining as f64))
+    }
+
+    /// Prints the progress of the current run to stderr.
+    ///
+    /// `point` describes the current parameter point, and `run` is the
+    /// zero-based run of `repeat` runs at this point.
+    pub fn report(&self, point: &str, run: u32, repeat: u32) {
+        let eta = match self.eta() {
+            Some(eta) => format!("{:.0} s", eta.as_secs_f64()),
+            None => "unknown".to_string(),
+        };
+
+        eprintln!(
+            "[{}/{}] {}: run {} of {}, ETA {}",
+            self.completed_runs + 1,
+            self.total_runs,
+            point,
+            run + 1,
+            repeat,
+            eta
+        );
+    }
+}
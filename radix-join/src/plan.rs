@@ -0,0 +1,259 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of the benchmark configuration.
+//!
+//! An invalid configuration should fail before the data set is generated,
+//! which can take minutes for large relations. The checks are split into
+//! checks that only inspect the configuration, and checks that compare the
+//! configuration to the resources of the machine. The former don't require a
+//! GPU, and can thus run before CUDA is initialized.
+
+use crate::error::{ErrorKind, Result};
+use sql_ops::partition::{RadixBits, RadixPass};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fmt;
+
+/// The build partition size that the recommended radix bits aim for.
+///
+/// The GPU join builds a hash table per build partition in shared memory.
+/// 16 KiB leave enough headroom for the hash table's load factor, and fit
+/// into the shared memory of all supported GPUs.
+pub const RECOMMENDED_BUILD_PARTITION_BYTES: usize = 16 * 1024;
+
+/// Recommends radix bits for partitioning the build relation.
+///
+/// Picks the fewest radix bits with which the partitions of a uniformly
+/// distributed build relation fit into `RECOMMENDED_BUILD_PARTITION_BYTES`.
+/// The radix bits are split evenly over the passes, and each pass uses at
+/// least one radix bit. If the bits don't split evenly, the first pass gets
+/// the remaining bits.
+///
+/// `max_pass_radix_bits` limits the radix bits of each pass, in the order of
+/// the passes. The bits that exceed the limit of a pass are moved to the
+/// other passes. If all passes are at their limit, the partitions are larger
+/// than recommended.
+pub fn recommend_radix_bits(
+    build_tuples: usize,
+    tuple_bytes: usize,
+    passes: usize,
+    max_pass_radix_bits: &[u32],
+) -> Result<RadixBits> {
+    if passes == 0 || passes > 3 {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Radix partitioning requires one to three passes, but got {}",
+            passes
+        )))?;
+    }
+
+    if max_pass_radix_bits.len() < passes {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Expected radix bits limits for {} passes, but got {}",
+            passes,
+            max_pass_radix_bits.len()
+        )))?;
+    }
+
+    if let Some(pass) = max_pass_radix_bits[..passes]
+        .iter()
+        .position(|&max| max == 0)
+    {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Pass {} cannot partition by any radix bits",
+            pass + 1
+        )))?;
+    }
+
+    let build_bytes = relation_bytes(build_tuples, tuple_bytes)?;
+    let partitions =
+        (build_bytes + RECOMMENDED_BUILD_PARTITION_BYTES - 1) / RECOMMENDED_BUILD_PARTITION_BYTES;
+    let radix_bits = partitions
+        .checked_next_power_of_two()
+        .map_or(usize::BITS, usize::trailing_zeros)
+        .max(passes as u32);
+
+    let mut pass_bits: Vec<u32> = (0..passes as u32)
+        .map(|pass| {
+            let bits = radix_bits / passes as u32;
+            if pass == 0 {
+                bits + radix_bits % passes as u32
+            } else {
+                bits
+            }
+        })
+        .collect();
+
+    let mut excess_bits = 0;
+    for (bits, &max) in pass_bits.iter_mut().zip(max_pass_radix_bits.iter()) {
+        excess_bits += bits.saturating_sub(max);
+        *bits = (*bits).min(max);
+    }
+    for (bits, &max) in pass_bits.iter_mut().zip(max_pass_radix_bits.iter()) {
+        let moved_bits = excess_bits.min(max - *bits);
+        *bits += moved_bits;
+        excess_bits -= moved_bits;
+    }
+
+    Ok(pass_bits.as_slice().try_into()?)
+}
+
+/// Checks that the key is wide enough for the total number of radix bits.
+///
+/// Each pass partitions by a distinct range of key bits. Thus, all passes
+/// together cannot use more bits than the key has.
+pub fn check_radix_bits_key_width(radix_bits: &RadixBits, key_bytes: usize) -> Result<()> {
+    let key_bits = key_bytes as u32 * u8::BITS;
+
+    if radix_bits.radix_bits() > key_bits {
+        Err(ErrorKind::InvalidArgument(format!(
+            "{} radix bits exceed the key width of {} bits",
+            radix_bits.radix_bits(),
+            key_bits
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// Checks the radix bits of the specified passes against a per-pass limit.
+///
+/// Passes without radix bits are skipped.
+pub fn check_pass_radix_bits(
+    radix_bits: &RadixBits,
+    passes: &[RadixPass],
+    max_radix_bits: u32,
+) -> Result<()> {
+    for &pass in passes {
+        if let Some(bits) = radix_bits.pass_radix_bits(pass) {
+            if bits > max_radix_bits {
+                Err(ErrorKind::InvalidArgument(format!(
+                    "{:?} pass uses {} radix bits, but at most {} radix bits are supported",
+                    pass, bits, max_radix_bits
+                )))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the partitions are distributed over the nodes without gaps.
+///
+/// Each location requires a proportion, and the proportions must add up to
+/// 100%.
+pub fn check_partitions_proportions(locations: &[u16], proportions: &[usize]) -> Result<()> {
+    if locations.len() != proportions.len() {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Specified {} partitions locations, but {} proportions",
+            locations.len(),
+            proportions.len()
+        )))?;
+    }
+
+    let total: usize = proportions.iter().sum();
+    if total != 100 {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Partitions proportions add up to {}%, instead of 100%",
+            total
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// Returns the size of a relation in bytes.
+pub fn relation_bytes(tuples: usize, tuple_bytes: usize) -> Result<usize> {
+    tuples.checked_mul(tuple_bytes).ok_or_else(|| {
+        ErrorKind::IntegerOverflow(format!(
+            "Relation with {} tuples of {} bytes is too large",
+            tuples, tuple_bytes
+        ))
+        .into()
+    })
+}
+
+/// A memory location, to which allocations are attributed.
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum MemLocation {
+    /// Memory of a NUMA node
+    Numa(u16),
+
+    /// Device memory of the GPU
+    Gpu,
+}
+
+impl fmt::Display for MemLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemLocation::Numa(node) => write!(f, "NUMA node {}", node),
+            MemLocation::Gpu => write!(f, "GPU memory"),
+        }
+    }
+}
+
+/// Sums up the memory required by each location.
+///
+/// Allocations that don't have a fixed location, e.g., unified memory, can
+/// be paged out and are thus not recorded.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryPlan {
+    required_bytes: BTreeMap<MemLocation, usize>,
+}
+
+impl MemoryPlan {
+    /// Creates an empty memory plan.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an allocation of `bytes` at `location`.
+    pub fn add(&mut self, location: MemLocation, bytes: usize) -> Result<()> {
+        let required = self.required_bytes.entry(location).or_insert(0);
+        *required = required.checked_add(bytes).ok_or_else(|| {
+            ErrorKind::IntegerOverflow(format!("Memory required at {} is too large", location))
+        })?;
+
+        Ok(())
+    }
+
+    /// Returns the memory required by each location.
+    pub fn required_bytes(&self) -> impl Iterator<Item = (MemLocation, usize)> + '_ {
+        self.required_bytes
+            .iter()
+            .map(|(&location, &bytes)| (location, bytes))
+    }
+
+    /// Checks that no location is oversubscribed.
+    ///
+    /// `capacity` returns the memory capacity of a location in bytes.
+    pub fn check_oversubscription<F>(&self, mut capacity: F) -> Result<()>
+    where
+        F: FnMut(MemLocation) -> Result<usize>,
+    {
+        for (location, required) in self.required_bytes() {
+            let available = capacity(location)?;
+            if required > available {
+                Err(ErrorKind::InvalidArgument(format!(
+                    "{} requires {} MiB, but has only {} MiB",
+                    location,
+                    required / 2_usize.pow(20),
+                    available / 2_usize.pow(20)
+                )))?;
+            }
+        }
+
+        Ok(())
+    }
+}
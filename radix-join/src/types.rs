@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::error::{Error, ErrorKind, Result};
 use numa_gpu::runtime::allocator;
 use numa_gpu::runtime::numa::{NodeRatio, PageType};
 use numa_gpu::utils::DeviceType;
@@ -20,6 +21,7 @@ use serde_repr::Serialize_repr;
 use sql_ops::join::HashingScheme;
 use sql_ops::partition::cpu_radix_partition::{CpuHistogramAlgorithm, CpuRadixPartitionAlgorithm};
 use sql_ops::partition::gpu_radix_partition::{GpuHistogramAlgorithm, GpuRadixPartitionAlgorithm};
+use std::convert::TryFrom;
 use structopt::clap::arg_enum;
 
 arg_enum! {
@@ -89,6 +91,7 @@ arg_enum! {
     #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
     pub enum ArgRadixPartitionAlgorithm {
         CpuNC,
+        CpuNCStreaming,
         CpuSWWC,
         CpuSWWCSIMD,
         GpuNC,
@@ -98,10 +101,31 @@ arg_enum! {
     }
 }
 
+arg_enum! {
+    #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+    pub enum ArgAlgorithm {
+        NoPartition,
+        RadixPartition,
+        BuildPartition,
+        Both,
+    }
+}
+
+impl ArgAlgorithm {
+    /// Returns the algorithms to run, i.e., expands `Both`.
+    pub fn algorithms(self) -> Vec<ArgAlgorithm> {
+        match self {
+            ArgAlgorithm::Both => vec![ArgAlgorithm::NoPartition, ArgAlgorithm::RadixPartition],
+            algorithm => vec![algorithm],
+        }
+    }
+}
+
 arg_enum! {
     #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
     pub enum ArgExecutionMethod {
         CpuPartitionedRadixJoinTwoPass,
+        CpuRadixJoin,
         GpuRadixJoinTwoPass,
         GpuTritonJoinTwoPass,
     }
@@ -132,64 +156,69 @@ pub struct ArgMemTypeHelper {
     pub page_type: ArgPageType,
 }
 
-impl From<ArgMemTypeHelper> for allocator::MemType {
-    fn from(
+impl TryFrom<ArgMemTypeHelper> for allocator::MemType {
+    type Error = Error;
+
+    fn try_from(
         ArgMemTypeHelper {
             mem_type,
             node_ratios,
             page_type,
         }: ArgMemTypeHelper,
-    ) -> Self {
-        match mem_type {
+    ) -> Result<Self> {
+        let mem_type = match mem_type {
             ArgMemType::System => allocator::MemType::SysMem,
             ArgMemType::Numa => allocator::MemType::NumaMem {
-                node: node_ratios[0].node,
+                node: first_node(mem_type, &node_ratios)?,
                 page_type: page_type.into(),
             },
             ArgMemType::NumaPinned => allocator::MemType::NumaPinnedMem {
-                node: node_ratios[0].node,
-                page_type: page_type.into(),
-            },
-            ArgMemType::DistributedNuma => allocator::MemType::DistributedNumaMem {
-                nodes: node_ratios,
+                node: first_node(mem_type, &node_ratios)?,
                 page_type: page_type.into(),
             },
+            ArgMemType::DistributedNuma => {
+                first_node(mem_type, &node_ratios)?;
+                allocator::MemType::DistributedNumaMem {
+                    nodes: node_ratios,
+                    page_type: page_type.into(),
+                }
+            }
             ArgMemType::Pinned => allocator::MemType::CudaPinnedMem,
             ArgMemType::Unified => allocator::MemType::CudaUniMem,
             ArgMemType::Device => allocator::MemType::CudaDevMem,
-        }
+        };
+
+        Ok(mem_type)
     }
 }
 
-impl From<ArgMemTypeHelper> for allocator::DerefMemType {
-    fn from(
-        ArgMemTypeHelper {
-            mem_type,
-            node_ratios,
-            page_type,
-        }: ArgMemTypeHelper,
-    ) -> Self {
-        match mem_type {
-            ArgMemType::System => allocator::DerefMemType::SysMem,
-            ArgMemType::Numa => allocator::DerefMemType::NumaMem {
-                node: node_ratios[0].node,
-                page_type: page_type.into(),
-            },
-            ArgMemType::NumaPinned => allocator::DerefMemType::NumaPinnedMem {
-                node: node_ratios[0].node,
-                page_type: page_type.into(),
-            },
-            ArgMemType::DistributedNuma => allocator::DerefMemType::DistributedNumaMem {
-                nodes: node_ratios,
-                page_type: page_type.into(),
-            },
-            ArgMemType::Pinned => allocator::DerefMemType::CudaPinnedMem,
-            ArgMemType::Unified => allocator::DerefMemType::CudaUniMem,
-            ArgMemType::Device => panic!("Error: Device memory not supported in this context!"),
-        }
+/// Converts the memory type into a type that the CPU can dereference.
+///
+/// Fails for device memory, because the CPU cannot access it.
+impl TryFrom<ArgMemTypeHelper> for allocator::DerefMemType {
+    type Error = Error;
+
+    fn try_from(helper: ArgMemTypeHelper) -> Result<Self> {
+        let arg_mem_type = helper.mem_type;
+        let mem_type = allocator::MemType::try_from(helper)?;
+
+        allocator::DerefMemType::try_from(mem_type).map_err(|_| {
+            ErrorKind::InvalidArgument(format!(
+                "{:?} memory isn't supported here, because the CPU must be able to access it",
+                arg_mem_type
+            ))
+            .into()
+        })
     }
 }
 
+/// Returns the first NUMA node, or an error if none is specified.
+fn first_node(mem_type: ArgMemType, node_ratios: &[NodeRatio]) -> Result<u16> {
+    node_ratios.first().map(|ratio| ratio.node).ok_or_else(|| {
+        ErrorKind::InvalidArgument(format!("{:?} memory requires a NUMA node", mem_type)).into()
+    })
+}
+
 impl From<ArgPageType> for PageType {
     fn from(arg_page_type: ArgPageType) -> PageType {
         match arg_page_type {
@@ -210,6 +239,7 @@ impl Into<DeviceType<CpuRadixPartitionAlgorithm, GpuRadixPartitionAlgorithm>>
     fn into(self) -> DeviceType<CpuRadixPartitionAlgorithm, GpuRadixPartitionAlgorithm> {
         match self {
             Self::CpuNC => DeviceType::Cpu(CpuRadixPartitionAlgorithm::NC),
+            Self::CpuNCStreaming => DeviceType::Cpu(CpuRadixPartitionAlgorithm::NCStreaming),
             Self::CpuSWWC => DeviceType::Cpu(CpuRadixPartitionAlgorithm::Swwc),
             Self::CpuSWWCSIMD => DeviceType::Cpu(CpuRadixPartitionAlgorithm::SwwcSimd),
             Self::GpuNC => DeviceType::Gpu(GpuRadixPartitionAlgorithm::NC),
@@ -0,0 +1,80 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use num_rational::Ratio;
+use numa_gpu::runtime::allocator::{DerefMemType, MemType};
+use numa_gpu::runtime::numa::NodeRatio;
+use radix_join::error::Result;
+use radix_join::types::{ArgMemType, ArgMemTypeHelper, ArgPageType};
+use std::convert::TryInto;
+
+const HOST_MEM_TYPES: [ArgMemType; 6] = [
+    ArgMemType::System,
+    ArgMemType::Numa,
+    ArgMemType::NumaPinned,
+    ArgMemType::DistributedNuma,
+    ArgMemType::Pinned,
+    ArgMemType::Unified,
+];
+
+const NUMA_MEM_TYPES: [ArgMemType; 3] = [
+    ArgMemType::Numa,
+    ArgMemType::NumaPinned,
+    ArgMemType::DistributedNuma,
+];
+
+fn helper(mem_type: ArgMemType, nodes: &[u16]) -> ArgMemTypeHelper {
+    ArgMemTypeHelper {
+        mem_type,
+        node_ratios: nodes
+            .iter()
+            .map(|&node| NodeRatio {
+                node,
+                ratio: Ratio::new(1, nodes.len()),
+            })
+            .collect(),
+        page_type: ArgPageType::Default,
+    }
+}
+
+#[test]
+fn host_mem_types_convert_to_mem_and_deref_mem_types() {
+    for &mem_type in HOST_MEM_TYPES.iter() {
+        let result: Result<MemType> = helper(mem_type, &[0, 1]).try_into();
+        assert!(result.is_ok(), "Failed to convert {:?}", mem_type);
+
+        let result: Result<DerefMemType> = helper(mem_type, &[0, 1]).try_into();
+        assert!(result.is_ok(), "Failed to convert {:?}", mem_type);
+    }
+}
+
+#[test]
+fn device_mem_type_converts_only_to_mem_type() {
+    let result: Result<MemType> = helper(ArgMemType::Device, &[0]).try_into();
+    assert!(result.is_ok());
+
+    let result: Result<DerefMemType> = helper(ArgMemType::Device, &[0]).try_into();
+    assert!(result.is_err());
+}
+
+#[test]
+fn numa_mem_types_without_a_node_return_an_error() {
+    for &mem_type in NUMA_MEM_TYPES.iter() {
+        let result: Result<MemType> = helper(mem_type, &[]).try_into();
+        assert!(result.is_err(), "Expected an error for {:?}", mem_type);
+
+        let result: Result<DerefMemType> = helper(mem_type, &[]).try_into();
+        assert!(result.is_err(), "Expected an error for {:?}", mem_type);
+    }
+}
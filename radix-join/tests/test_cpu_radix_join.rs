@@ -0,0 +1,193 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use data_store::join_data::{JoinData, JoinDataBuilder};
+use datagen::relation::UniformRelation;
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::cpu_affinity::CpuAffinity;
+use numa_gpu::utils::DeviceType;
+use radix_join::execution_methods::cpu_radix_join::{
+    cpu_radix_join, cpu_radix_join_pipelined, cpu_thread_pool,
+};
+use rustacuda::function::{BlockSize, GridSize};
+use sql_ops::join::{no_partitioning_join, HashingScheme, HtEntry};
+use sql_ops::partition::cpu_radix_partition::{CpuHistogramAlgorithm, CpuRadixPartitionAlgorithm};
+use sql_ops::partition::RadixBits;
+use std::error::Error;
+use std::result::Result;
+use std::sync::Arc;
+
+const INNER_LEN: usize = 100_000;
+const OUTER_LEN: usize = 300_000;
+const THREADS: usize = 2;
+
+/// Generates a join in which roughly half of the outer tuples have a match.
+fn gen_join_data() -> Result<JoinData<i64>, Box<dyn Error>> {
+    let data_gen_fn = Box::new(
+        |pk_rel_key: &mut [_], pk_rel_pay: &mut [_], fk_rel_key: &mut [_], fk_rel_pay: &mut [_]| {
+            UniformRelation::gen_primary_key(pk_rel_key, None)?;
+            UniformRelation::gen_attr(fk_rel_key, 0..(2 * INNER_LEN))?;
+
+            pk_rel_pay
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as i64);
+            fk_rel_pay
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as i64);
+
+            Ok(())
+        },
+    );
+
+    let (join_data, _, _) = JoinDataBuilder::default()
+        .inner_mem_type(DerefMemType::SysMem)
+        .outer_mem_type(DerefMemType::SysMem)
+        .inner_len(INNER_LEN)
+        .outer_len(OUTER_LEN)
+        .build_with_data_gen(data_gen_fn)?;
+
+    Ok(join_data)
+}
+
+/// Counts the matches with a single, global hash table.
+fn no_partitioning_count(data: &JoinData<i64>) -> Result<u64, Box<dyn Error>> {
+    let hash_table_len = 2 * INNER_LEN.next_power_of_two();
+    let hash_table_mem =
+        Allocator::alloc_deref_mem::<HtEntry<i64, i64>>(DerefMemType::SysMem, hash_table_len);
+    let hash_table = no_partitioning_join::HashTable::new_on_cpu(hash_table_mem, hash_table_len)?;
+
+    let mut hj_op = no_partitioning_join::CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+    hj_op.build(
+        data.build_relation.key().try_as_slice()?,
+        data.build_relation.value().try_as_slice()?,
+    )?;
+
+    let mut count = 0;
+    hj_op.probe_count(data.probe_relation.key().try_as_slice()?, &mut count)?;
+
+    Ok(count)
+}
+
+fn run_cpu_radix_join_validate_count(
+    partition_algorithm: CpuRadixPartitionAlgorithm,
+    radix_bits: u32,
+) -> Result<(), Box<dyn Error>> {
+    let mut data = gen_join_data()?;
+    let expected = no_partitioning_count(&data)?;
+    assert!(expected > 0 && expected < OUTER_LEN as u64);
+
+    let (count, data_point) = cpu_radix_join(
+        &mut data,
+        HashingScheme::LinearProbing,
+        DeviceType::Cpu(CpuHistogramAlgorithm::Chunked),
+        DeviceType::Cpu(partition_algorithm),
+        &RadixBits::new(Some(radix_bits), None, None),
+        &cpu_thread_pool(THREADS, &CpuAffinity::default())?,
+        MemType::SysMem,
+        true,
+    )?;
+
+    assert_eq!(expected as i64, count);
+
+    let histograms = data_point
+        .partition_histograms
+        .expect("The join didn't report partition histograms");
+    assert_eq!(histograms.inner.len(), 1 << radix_bits);
+    assert_eq!(histograms.inner.iter().sum::<u64>(), INNER_LEN as u64);
+    assert_eq!(histograms.outer.iter().sum::<u64>(), OUTER_LEN as u64);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_radix_join_nc_count_equals_no_partitioning_join() -> Result<(), Box<dyn Error>> {
+    run_cpu_radix_join_validate_count(CpuRadixPartitionAlgorithm::NC, 6)
+}
+
+#[test]
+fn cpu_radix_join_swwc_count_equals_no_partitioning_join() -> Result<(), Box<dyn Error>> {
+    run_cpu_radix_join_validate_count(CpuRadixPartitionAlgorithm::Swwc, 8)
+}
+
+#[test]
+fn cpu_radix_join_rejects_perfect_hashing() -> Result<(), Box<dyn Error>> {
+    let mut data = gen_join_data()?;
+
+    let result = cpu_radix_join(
+        &mut data,
+        HashingScheme::Perfect,
+        DeviceType::Cpu(CpuHistogramAlgorithm::Chunked),
+        DeviceType::Cpu(CpuRadixPartitionAlgorithm::NC),
+        &RadixBits::new(Some(6), None, None),
+        &cpu_thread_pool(THREADS, &CpuAffinity::default())?,
+        MemType::SysMem,
+        false,
+    );
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn cpu_radix_join_pipelined_count_equals_two_phase_join() -> Result<(), Box<dyn Error>> {
+    const RADIX_BITS: u32 = 6;
+
+    let mut data = gen_join_data()?;
+    let thread_pool = cpu_thread_pool(THREADS, &CpuAffinity::default())?;
+
+    let (expected, expected_point) = cpu_radix_join(
+        &mut data,
+        HashingScheme::LinearProbing,
+        DeviceType::Cpu(CpuHistogramAlgorithm::Chunked),
+        DeviceType::Cpu(CpuRadixPartitionAlgorithm::NC),
+        &RadixBits::new(Some(RADIX_BITS), None, None),
+        &thread_pool,
+        MemType::SysMem,
+        false,
+    )?;
+    assert!(expected_point.partition_histograms.is_none());
+
+    // Small batches and a short channel force the partitioners to wait for
+    // the join workers
+    let (count, data_point) = cpu_radix_join_pipelined(
+        &mut data,
+        HashingScheme::LinearProbing,
+        DeviceType::Cpu(CpuHistogramAlgorithm::Chunked),
+        DeviceType::Cpu(CpuRadixPartitionAlgorithm::NC),
+        &RadixBits::new(Some(RADIX_BITS), None, None),
+        0,
+        &thread_pool,
+        MemType::SysMem,
+        (&GridSize::from(1), &BlockSize::from(1)),
+        OUTER_LEN / 16,
+        2,
+        true,
+    )?;
+
+    assert!(expected > 0);
+    assert_eq!(expected, count);
+
+    let histograms = data_point
+        .partition_histograms
+        .expect("The join didn't report partition histograms");
+    assert_eq!(histograms.outer.len(), 1 << RADIX_BITS);
+    assert_eq!(histograms.outer.iter().sum::<u64>(), OUTER_LEN as u64);
+
+    Ok(())
+}
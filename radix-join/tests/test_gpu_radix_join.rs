@@ -22,6 +22,9 @@ use numa_gpu::runtime::numa::{NodeRatio, PageType};
 use numa_gpu::utils::DeviceType;
 use once_cell::sync::Lazy;
 use radix_join::error::Result as RJResult;
+use radix_join::execution_methods::cpu_radix_join::{cpu_radix_join_pipelined, cpu_thread_pool};
+use radix_join::execution_methods::gpu_build_partitioned_join::gpu_build_partitioned_join;
+use radix_join::execution_methods::gpu_no_partitioning_join::gpu_no_partitioning_join;
 use radix_join::execution_methods::gpu_radix_join::gpu_radix_join;
 use radix_join::measurement::harness::RadixJoinPoint;
 use rustacuda::context::{Context, CurrentContext, UnownedContext};
@@ -83,6 +86,7 @@ where
         PageType,
         (&GridSize, &BlockSize),
         (&GridSize, &BlockSize),
+        bool,
     ) -> RJResult<(i64, RadixJoinPoint)>,
     PartitionsFn: FnOnce(&Device) -> Result<MemType, Box<dyn Error>>,
 {
@@ -141,6 +145,7 @@ where
         PageType::Default,
         (&grid_size, &block_size),
         (&grid_size, &block_size),
+        false,
     )?;
 
     assert_eq!(
@@ -245,6 +250,271 @@ fn test_gpu_radix_partition_validate_sum_bucketchaining_large_i32() -> Result<()
     )
 }
 
+#[test]
+fn test_no_partitioning_and_radix_join_match_sum_i32() -> Result<(), Box<dyn Error>> {
+    const INNER_RELATION_LEN: usize = 100_000;
+    const OUTER_RELATION_LEN: usize = 300_000;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let data_gen_fn = Box::new(
+        |pk_rel_key: &mut [_], pk_rel_pay: &mut [_], fk_rel_key: &mut [_], fk_rel_pay: &mut [_]| {
+            UniformRelation::gen_primary_key(pk_rel_key, None)?;
+            UniformRelation::gen_foreign_key_from_primary_key(fk_rel_key, pk_rel_key);
+
+            pk_rel_pay
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as i32);
+            fk_rel_pay
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as i32);
+
+            Ok(())
+        },
+    );
+
+    let mut data_builder = JoinDataBuilder::default();
+    data_builder
+        .inner_mem_type(DerefMemType::CudaPinnedMem)
+        .outer_mem_type(DerefMemType::CudaPinnedMem)
+        .inner_len(INNER_RELATION_LEN)
+        .outer_len(OUTER_RELATION_LEN);
+    let (mut join_data, _, _) = data_builder.build_with_data_gen(data_gen_fn)?;
+
+    let grid_size = GridSize::from(8);
+    let block_size = BlockSize::from(128);
+
+    let (no_partitioning_sum, _) = gpu_no_partitioning_join(
+        &mut join_data,
+        HashingScheme::LinearProbing,
+        MemType::CudaDevMem,
+        (&grid_size, &block_size),
+    )?;
+
+    let (radix_sum, _) = gpu_radix_join(
+        &mut join_data,
+        HashingScheme::Perfect,
+        DeviceType::Gpu(GpuHistogramAlgorithm::Chunked),
+        DeviceType::Gpu(GpuHistogramAlgorithm::Contiguous),
+        DeviceType::Gpu(GpuRadixPartitionAlgorithm::SSWWCv2),
+        DeviceType::Gpu(GpuRadixPartitionAlgorithm::SSWWCv2),
+        &RadixBits::new(Some(3), Some(3), None),
+        8 * 1024,
+        None,
+        1,
+        CpuAffinity::default(),
+        MemType::CudaPinnedMem,
+        MemType::CudaDevMem,
+        PageType::Default,
+        (&grid_size, &block_size),
+        (&grid_size, &block_size),
+        false,
+    )?;
+
+    assert_eq!(no_partitioning_sum, radix_sum);
+    assert_eq!(
+        (OUTER_RELATION_LEN as i64 * (OUTER_RELATION_LEN as i64 + 1)) / 2,
+        radix_sum
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_partition_ns_is_zero_only_without_partitioning() -> Result<(), Box<dyn Error>> {
+    const INNER_RELATION_LEN: usize = 10_000;
+    const OUTER_RELATION_LEN: usize = 30_000;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let data_gen_fn = Box::new(
+        |pk_rel_key: &mut [_], pk_rel_pay: &mut [_], fk_rel_key: &mut [_], fk_rel_pay: &mut [_]| {
+            UniformRelation::gen_primary_key(pk_rel_key, None)?;
+            UniformRelation::gen_foreign_key_from_primary_key(fk_rel_key, pk_rel_key);
+            pk_rel_pay.iter_mut().for_each(|x| *x = 1_i32);
+            fk_rel_pay.iter_mut().for_each(|x| *x = 1_i32);
+
+            Ok(())
+        },
+    );
+
+    let mut data_builder = JoinDataBuilder::default();
+    data_builder
+        .inner_mem_type(DerefMemType::CudaPinnedMem)
+        .outer_mem_type(DerefMemType::CudaPinnedMem)
+        .inner_len(INNER_RELATION_LEN)
+        .outer_len(OUTER_RELATION_LEN);
+    let (mut join_data, _, _) = data_builder.build_with_data_gen(data_gen_fn)?;
+
+    let grid_size = GridSize::from(8);
+    let block_size = BlockSize::from(128);
+
+    let (_, no_partitioning_point) = gpu_no_partitioning_join(
+        &mut join_data,
+        HashingScheme::LinearProbing,
+        MemType::CudaDevMem,
+        (&grid_size, &block_size),
+    )?;
+    assert_eq!(Some(0.0), no_partitioning_point.partition_ns);
+
+    let (_, radix_point) = gpu_radix_join(
+        &mut join_data,
+        HashingScheme::Perfect,
+        DeviceType::Gpu(GpuHistogramAlgorithm::Chunked),
+        DeviceType::Gpu(GpuHistogramAlgorithm::Contiguous),
+        DeviceType::Gpu(GpuRadixPartitionAlgorithm::SSWWCv2),
+        DeviceType::Gpu(GpuRadixPartitionAlgorithm::SSWWCv2),
+        &RadixBits::new(Some(3), Some(3), None),
+        8 * 1024,
+        None,
+        1,
+        CpuAffinity::default(),
+        MemType::CudaPinnedMem,
+        MemType::CudaDevMem,
+        PageType::Default,
+        (&grid_size, &block_size),
+        (&grid_size, &block_size),
+        false,
+    )?;
+    let partition_ns = radix_point
+        .partition_ns
+        .expect("Radix join didn't measure the partition time");
+    assert!(partition_ns > 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn test_build_partitioned_and_no_partitioning_join_match_sum_i32() -> Result<(), Box<dyn Error>> {
+    const INNER_RELATION_LEN: usize = 300_000;
+    const OUTER_RELATION_LEN: usize = 100_000;
+    const BUILD_PARTITION_LEN: usize = 64 * 1024;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let data_gen_fn = Box::new(
+        |pk_rel_key: &mut [_], pk_rel_pay: &mut [_], fk_rel_key: &mut [_], fk_rel_pay: &mut [_]| {
+            UniformRelation::gen_primary_key(pk_rel_key, None)?;
+            UniformRelation::gen_foreign_key_from_primary_key(fk_rel_key, pk_rel_key);
+
+            pk_rel_pay
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as i32);
+            fk_rel_pay
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as i32);
+
+            Ok(())
+        },
+    );
+
+    let mut data_builder = JoinDataBuilder::default();
+    data_builder
+        .inner_mem_type(DerefMemType::CudaPinnedMem)
+        .outer_mem_type(DerefMemType::CudaPinnedMem)
+        .inner_len(INNER_RELATION_LEN)
+        .outer_len(OUTER_RELATION_LEN);
+    let (mut join_data, _, _) = data_builder.build_with_data_gen(data_gen_fn)?;
+
+    let grid_size = GridSize::from(8);
+    let block_size = BlockSize::from(128);
+
+    let (no_partitioning_sum, _) = gpu_no_partitioning_join(
+        &mut join_data,
+        HashingScheme::LinearProbing,
+        MemType::CudaDevMem,
+        (&grid_size, &block_size),
+    )?;
+
+    // The last partition is smaller than the others
+    assert_ne!(INNER_RELATION_LEN % BUILD_PARTITION_LEN, 0);
+    let (build_partitioned_sum, _) = gpu_build_partitioned_join(
+        &mut join_data,
+        HashingScheme::LinearProbing,
+        MemType::CudaDevMem,
+        BUILD_PARTITION_LEN,
+        (&grid_size, &block_size),
+    )?;
+
+    assert_eq!(no_partitioning_sum, build_partitioned_sum);
+    assert_eq!(
+        (OUTER_RELATION_LEN as i64 * (OUTER_RELATION_LEN as i64 + 1)) / 2,
+        build_partitioned_sum
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_pipelined_join_with_gpu_partitioner_counts_all_matches() -> Result<(), Box<dyn Error>> {
+    const INNER_RELATION_LEN: usize = 100_000;
+    const OUTER_RELATION_LEN: usize = 300_000;
+    const THREADS: usize = 3;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let data_gen_fn = Box::new(
+        |pk_rel_key: &mut [_], pk_rel_pay: &mut [_], fk_rel_key: &mut [_], fk_rel_pay: &mut [_]| {
+            UniformRelation::gen_primary_key(pk_rel_key, None)?;
+            UniformRelation::gen_foreign_key_from_primary_key(fk_rel_key, pk_rel_key);
+
+            pk_rel_pay
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as i32);
+            fk_rel_pay
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as i32);
+
+            Ok(())
+        },
+    );
+
+    let mut data_builder = JoinDataBuilder::default();
+    data_builder
+        .inner_mem_type(DerefMemType::CudaPinnedMem)
+        .outer_mem_type(DerefMemType::CudaPinnedMem)
+        .inner_len(INNER_RELATION_LEN)
+        .outer_len(OUTER_RELATION_LEN);
+    let (mut join_data, _, _) = data_builder.build_with_data_gen(data_gen_fn)?;
+
+    let thread_pool = cpu_thread_pool(THREADS, &CpuAffinity::default())?;
+
+    // Every outer tuple has exactly one match. The GPU partitioner writes
+    // one chunk per thread block, which the workers must all probe.
+    let (count, data_point) = cpu_radix_join_pipelined(
+        &mut join_data,
+        HashingScheme::LinearProbing,
+        DeviceType::Gpu(GpuHistogramAlgorithm::Chunked),
+        DeviceType::Gpu(GpuRadixPartitionAlgorithm::NC),
+        &RadixBits::new(Some(6), None, None),
+        0,
+        &thread_pool,
+        MemType::CudaPinnedMem,
+        (&GridSize::from(8), &BlockSize::from(128)),
+        OUTER_RELATION_LEN / 16,
+        2,
+        true,
+    )?;
+
+    assert_eq!(OUTER_RELATION_LEN as i64, count);
+
+    let histograms = data_point
+        .partition_histograms
+        .expect("The join didn't report partition histograms");
+    assert_eq!(
+        histograms.outer.iter().sum::<u64>(),
+        OUTER_RELATION_LEN as u64
+    );
+
+    Ok(())
+}
+
 #[cfg(target_arch = "powerpc64")]
 #[test]
 fn test_cpu_partitioned_validate_sum_perfect_small_i32() -> Result<(), Box<dyn Error>> {
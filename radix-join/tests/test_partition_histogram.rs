@@ -0,0 +1,85 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::Command;
+use std::result::Result;
+
+const INNER_TUPLES: u64 = 1_000;
+const OUTER_TUPLES: u64 = 10_000;
+const RADIX_BITS_FST: usize = 4;
+
+#[test]
+fn partition_histogram_sums_to_relation_length() -> Result<(), Box<dyn Error>> {
+    let histogram_path = std::env::temp_dir().join(format!(
+        "radix_join_partition_histogram_{}.csv",
+        std::process::id()
+    ));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_radix-join"))
+        .arg("--partition-histogram")
+        .arg(&histogram_path)
+        .args(&[
+            "--repeat",
+            "2",
+            "--data-set",
+            "Custom",
+            "--inner-rel-tuples",
+            &INNER_TUPLES.to_string(),
+            "--outer-rel-tuples",
+            &OUTER_TUPLES.to_string(),
+            "--radix-bits",
+            &format!("{},4", RADIX_BITS_FST),
+        ])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut reader = csv::Reader::from_path(&histogram_path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or_else(|| format!("Missing column {}", name))
+    };
+    let point = column("point")?;
+    let relation = column("relation")?;
+    let tuples = column("tuples")?;
+
+    let mut sums = BTreeMap::new();
+    let mut partitions = BTreeMap::new();
+    for record in reader.records() {
+        let record = record?;
+        assert_eq!(&record[point], "0");
+
+        let relation = record[relation].to_string();
+        *sums.entry(relation.clone()).or_insert(0) += record[tuples].parse::<u64>()?;
+        *partitions.entry(relation).or_insert(0) += 1;
+    }
+    std::fs::remove_file(&histogram_path)?;
+
+    // Only the first run is written, thus each partition has exactly one row
+    assert_eq!(partitions.get("inner"), Some(&(1 << RADIX_BITS_FST)));
+    assert_eq!(partitions.get("outer"), Some(&(1 << RADIX_BITS_FST)));
+
+    assert_eq!(sums.get("inner"), Some(&INNER_TUPLES));
+    assert_eq!(sums.get("outer"), Some(&OUTER_TUPLES));
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use radix_join::plan::{self, MemLocation, MemoryPlan};
+use sql_ops::partition::{RadixBits, RadixPass};
+use std::error::Error;
+use std::process::Command;
+use std::result::Result;
+
+/// Radix bits limits of passes without a fanout limit.
+const UNLIMITED: [u32; 3] = [u32::MAX; 3];
+
+#[test]
+fn radix_bits_exceeding_key_width_are_rejected() {
+    let radix_bits = RadixBits::new(Some(20), Some(13), None);
+
+    assert!(plan::check_radix_bits_key_width(&radix_bits, 4).is_err());
+    assert!(plan::check_radix_bits_key_width(&radix_bits, 8).is_ok());
+}
+
+#[test]
+fn pass_radix_bits_are_checked_per_pass() {
+    let radix_bits = RadixBits::new(Some(12), Some(8), None);
+
+    assert!(plan::check_pass_radix_bits(&radix_bits, &[RadixPass::First], 10).is_err());
+    assert!(
+        plan::check_pass_radix_bits(&radix_bits, &[RadixPass::Second, RadixPass::Third], 10)
+            .is_ok()
+    );
+}
+
+#[test]
+fn recommended_radix_bits_fit_build_partitions() -> Result<(), Box<dyn Error>> {
+    // 128 MiB split into 16 KiB partitions requires 13 radix bits
+    let radix_bits = plan::recommend_radix_bits(16 * 2_usize.pow(20), 8, 2, &UNLIMITED)?;
+    assert_eq!(Some(7), radix_bits.pass_radix_bits(RadixPass::First));
+    assert_eq!(Some(6), radix_bits.pass_radix_bits(RadixPass::Second));
+    assert_eq!(None, radix_bits.pass_radix_bits(RadixPass::Third));
+
+    // Each pass partitions by at least one bit
+    let radix_bits = plan::recommend_radix_bits(1, 8, 2, &UNLIMITED)?;
+    assert_eq!(2, radix_bits.radix_bits());
+
+    assert!(plan::recommend_radix_bits(1024, 8, 0, &UNLIMITED).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn recommended_radix_bits_respect_pass_limits() -> Result<(), Box<dyn Error>> {
+    // The excess bits of the first pass move to the second pass
+    let radix_bits = plan::recommend_radix_bits(16 * 2_usize.pow(20), 8, 2, &[5, 10, 10])?;
+    assert_eq!(Some(5), radix_bits.pass_radix_bits(RadixPass::First));
+    assert_eq!(Some(8), radix_bits.pass_radix_bits(RadixPass::Second));
+
+    // If all passes are at their limit, the partitions are larger
+    let radix_bits = plan::recommend_radix_bits(16 * 2_usize.pow(20), 8, 2, &[5, 5, 5])?;
+    assert_eq!(10, radix_bits.radix_bits());
+
+    assert!(plan::recommend_radix_bits(1024, 8, 2, &[0, 10, 10]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn partitions_proportions_must_add_up() {
+    assert!(plan::check_partitions_proportions(&[0, 1], &[50, 50]).is_ok());
+    assert!(plan::check_partitions_proportions(&[0, 1], &[50, 40]).is_err());
+    assert!(plan::check_partitions_proportions(&[0, 1], &[100]).is_err());
+}
+
+#[test]
+fn memory_plan_detects_oversubscription() -> Result<(), Box<dyn Error>> {
+    let mut memory_plan = MemoryPlan::new();
+    memory_plan.add(MemLocation::Numa(0), 600)?;
+    memory_plan.add(MemLocation::Numa(0), 600)?;
+    memory_plan.add(MemLocation::Gpu, 100)?;
+
+    assert!(memory_plan.check_oversubscription(|_| Ok(1000)).is_err());
+    assert!(memory_plan.check_oversubscription(|_| Ok(1200)).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_with_invalid_config_fails_without_gpu() -> Result<(), Box<dyn Error>> {
+    // 40 radix bits don't fit into a 4-byte key. The check runs before CUDA is
+    // initialized, thus the test also passes on machines without a GPU.
+    let output = Command::new(env!("CARGO_BIN_EXE_radix-join"))
+        .args(&[
+            "--dry-run",
+            "--tuple-bytes",
+            "Bytes8",
+            "--radix-bits",
+            "20,20",
+        ])
+        .output()?;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("radix bits exceed the key width"));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_radix_bits_are_rejected_with_an_example() -> Result<(), Box<dyn Error>> {
+    // The options are parsed before CUDA is initialized
+    let output = Command::new(env!("CARGO_BIN_EXE_radix-join"))
+        .args(&["--dry-run", "--radix-bits", "8,8,8,8"])
+        .output()?;
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("--radix-bits"));
+    assert!(stderr.contains("at most three sets of radix bits required, e.g., 8,8"));
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use radix_join::error::Result as RJResult;
+use radix_join::measurement::data_point::DataPoint;
+use radix_join::measurement::harness::{self, RadixJoinPoint};
+use radix_join::measurement::progress::ProgressReporter;
+use std::error::Error;
+use std::result::Result;
+use std::time::Duration;
+
+#[test]
+fn eta_is_unknown_before_first_run() {
+    let progress = ProgressReporter::new(10);
+
+    assert_eq!(progress.eta(), None);
+}
+
+#[test]
+fn eta_decreases_with_constant_run_times() {
+    const RUNS: usize = 30;
+    let run_time = Duration::from_millis(100);
+
+    let mut progress = ProgressReporter::new(RUNS);
+    let mut last_eta = None;
+
+    for completed in 1..=RUNS {
+        progress.record(run_time);
+        let eta = progress.eta().expect("ETA must be known after a run");
+
+        assert_eq!(eta, run_time * (RUNS - completed) as u32);
+        if let Some(last_eta) = last_eta {
+            assert!(eta < last_eta);
+        }
+        last_eta = Some(eta);
+    }
+
+    assert_eq!(last_eta, Some(Duration::from_secs(0)));
+}
+
+#[test]
+fn eta_follows_recent_run_times() {
+    const RUNS: usize = 20;
+    const WINDOW: usize = 4;
+
+    let mut progress = ProgressReporter::with_window(RUNS, WINDOW);
+
+    // The sweep moves from fast to slow parameters
+    (0..WINDOW).for_each(|_| progress.record(Duration::from_secs(1)));
+    (0..WINDOW).for_each(|_| progress.record(Duration::from_secs(3)));
+
+    // Only the slow runs are within the window
+    let remaining = (RUNS - 2 * WINDOW) as u32;
+    assert_eq!(progress.eta(), Some(Duration::from_secs(3) * remaining));
+}
+
+#[test]
+fn eta_is_zero_after_more_runs_than_expected() {
+    let mut progress = ProgressReporter::new(2);
+
+    (0..3).for_each(|_| progress.record(Duration::from_secs(1)));
+
+    assert_eq!(progress.completed_runs(), 3);
+    assert_eq!(progress.eta(), Some(Duration::from_secs(0)));
+}
+
+#[test]
+fn sweep_records_every_run() -> Result<(), Box<dyn Error>> {
+    const REPEAT: u32 = 3;
+    let points = [1_usize, 2, 4];
+
+    let mut progress = ProgressReporter::new(points.len() * REPEAT as usize);
+
+    harness::measure_sweep(
+        REPEAT,
+        None,
+        &points,
+        |&threads| {
+            let func: Box<dyn FnMut() -> RJResult<RadixJoinPoint>> =
+                Box::new(|| Ok(RadixJoinPoint::default()));
+            let template = DataPoint {
+                threads: Some(threads),
+                ..DataPoint::default()
+            };
+
+            Ok((func, template))
+        },
+        Some(&mut progress),
+        None,
+    )?;
+
+    assert_eq!(progress.completed_runs(), progress.total_runs());
+    assert_eq!(progress.eta(), Some(Duration::from_secs(0)));
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use radix_join::error::{ErrorKind, Result as RJResult};
+use radix_join::measurement::budget::{Clock, RuntimeBudget};
+use radix_join::measurement::data_point::DataPoint;
+use radix_join::measurement::harness::{self, RadixJoinPoint};
+use std::cell::Cell;
+use std::error::Error;
+use std::rc::Rc;
+use std::result::Result;
+use std::time::Duration;
+
+const REPEAT: u32 = 3;
+
+#[test]
+fn sweep_yields_one_row_group_per_point() -> Result<(), Box<dyn Error>> {
+    let points = [1_usize, 2, 4, 8];
+    let mut setups = 0;
+
+    let csv_path =
+        std::env::temp_dir().join(format!("radix_join_test_sweep_{}.csv", std::process::id()));
+
+    let measurements = harness::measure_sweep(
+        REPEAT,
+        Some(csv_path.clone()),
+        &points,
+        |&threads| {
+            setups += 1;
+
+            let func: Box<dyn FnMut() -> RJResult<RadixJoinPoint>> = Box::new(move || {
+                Ok(RadixJoinPoint {
+                    join_ns: Some(threads as f64),
+                    ..RadixJoinPoint::default()
+                })
+            });
+            let template = DataPoint {
+                sweep_parameter: Some("threads".to_string()),
+                threads: Some(threads),
+                ..DataPoint::default()
+            };
+
+            Ok((func, template))
+        },
+        None,
+        None,
+    )?;
+
+    assert_eq!(setups, points.len());
+    assert_eq!(measurements.len(), points.len() * REPEAT as usize);
+
+    for (group, &threads) in measurements.chunks(REPEAT as usize).zip(points.iter()) {
+        assert!(group.iter().all(|row| row.threads == Some(threads)));
+        assert!(group.iter().all(|row| row.join_ns == Some(threads as f64)));
+        assert!(group
+            .iter()
+            .all(|row| row.sweep_parameter.as_deref() == Some("threads")));
+
+        // Each group starts with its own warm-up run
+        assert_eq!(group[0].warm_up, Some(true));
+        assert!(group[1..].iter().all(|row| row.warm_up == Some(false)));
+    }
+
+    // All groups are written to a single CSV file with one header line
+    let csv = std::fs::read_to_string(&csv_path)?;
+    std::fs::remove_file(&csv_path)?;
+    assert_eq!(csv.lines().count(), 1 + measurements.len());
+
+    Ok(())
+}
+
+#[test]
+fn sweep_stops_at_first_failing_point() {
+    let points = [1_u32, 2, 3];
+    let mut setups = 0;
+
+    let result = harness::measure_sweep(
+        REPEAT,
+        None,
+        &points,
+        |&bits| {
+            setups += 1;
+
+            if bits == 2 {
+                Err(ErrorKind::RuntimeError(
+                    "Failed to set up the sweep point".to_string(),
+                ))?;
+            }
+
+            let func: Box<dyn FnMut() -> RJResult<RadixJoinPoint>> =
+                Box::new(|| Ok(RadixJoinPoint::default()));
+
+            Ok((func, DataPoint::default()))
+        },
+        None,
+        None,
+    );
+
+    assert!(result.is_err());
+    assert_eq!(setups, 2);
+}
+
+/// A clock that only advances when the test advances it.
+struct SyntheticClock(Rc<Cell<Duration>>);
+
+impl Clock for SyntheticClock {
+    fn elapsed(&self) -> Duration {
+        self.0.get()
+    }
+}
+
+#[test]
+fn sweep_stops_when_budget_is_exhausted() -> Result<(), Box<dyn Error>> {
+    const POINT_TIME: Duration = Duration::from_secs(10);
+
+    let points = [1_usize, 2, 4, 8, 16];
+    let now = Rc::new(Cell::new(Duration::from_secs(0)));
+    let budget = RuntimeBudget::with_clock(
+        Duration::from_secs(25),
+        Box::new(SyntheticClock(now.clone())),
+    );
+    let mut setups = 0;
+
+    let csv_path =
+        std::env::temp_dir().join(format!("radix_join_test_budget_{}.csv", std::process::id()));
+
+    let measurements = harness::measure_sweep(
+        REPEAT,
+        Some(csv_path.clone()),
+        &points,
+        |&threads| {
+            setups += 1;
+
+            // Each point takes the same time
+            now.set(now.get() + POINT_TIME);
+
+            let func: Box<dyn FnMut() -> RJResult<RadixJoinPoint>> =
+                Box::new(|| Ok(RadixJoinPoint::default()));
+            let template = DataPoint {
+                threads: Some(threads),
+                ..DataPoint::default()
+            };
+
+            Ok((func, template))
+        },
+        None,
+        Some(&budget),
+    )?;
+
+    // The points start at 0 s, 10 s, and 20 s. At 30 s, the budget of 25 s is
+    // exhausted.
+    assert_eq!(setups, 3);
+    assert!(budget.is_exhausted());
+    assert_eq!(measurements.len(), setups * REPEAT as usize);
+    assert!(measurements
+        .chunks(REPEAT as usize)
+        .zip(points.iter())
+        .all(|(group, &threads)| group.iter().all(|row| row.threads == Some(threads))));
+
+    // The completed points are written to the CSV file
+    let csv = std::fs::read_to_string(&csv_path)?;
+    std::fs::remove_file(&csv_path)?;
+    assert_eq!(csv.lines().count(), 1 + measurements.len());
+
+    Ok(())
+}
+
+#[test]
+fn sweep_within_budget_runs_all_points() -> Result<(), Box<dyn Error>> {
+    let points = [1_u32, 2, 3];
+    let budget = RuntimeBudget::new(Duration::from_secs(3600));
+
+    let measurements = harness::measure_sweep(
+        REPEAT,
+        None,
+        &points,
+        |_| {
+            let func: Box<dyn FnMut() -> RJResult<RadixJoinPoint>> =
+                Box::new(|| Ok(RadixJoinPoint::default()));
+
+            Ok((func, DataPoint::default()))
+        },
+        None,
+        Some(&budget),
+    )?;
+
+    assert_eq!(measurements.len(), points.len() * REPEAT as usize);
+
+    Ok(())
+}
@@ -141,6 +141,7 @@ arg_enum! {
     #[derive(Copy, Clone, Debug, PartialEq, Serialize)]
     pub enum ArgRadixPartitionAlgorithm {
         NC,
+        NCStreaming,
         Swwc,
         SwwcSimd,
     }
@@ -150,6 +151,7 @@ impl Into<CpuRadixPartitionAlgorithm> for ArgRadixPartitionAlgorithm {
     fn into(self) -> CpuRadixPartitionAlgorithm {
         match self {
             Self::NC => CpuRadixPartitionAlgorithm::NC,
+            Self::NCStreaming => CpuRadixPartitionAlgorithm::NCStreaming,
             Self::Swwc => CpuRadixPartitionAlgorithm::Swwc,
             Self::SwwcSimd => CpuRadixPartitionAlgorithm::SwwcSimd,
         }
@@ -426,7 +428,7 @@ where
                         DerefMemType::AlignedSysMem { align_bytes },
                     )
                 })
-                .collect();
+                .collect::<Result<_, _>>()?;
 
             let mut partition_offsets = PartitionOffsets::new(
                 prefix_sum_algorithm.into(),
@@ -568,7 +570,7 @@ where
                         partition_algorithm,
                         radix_bits,
                         DerefMemType::AlignedSysMem { align_bytes },
-                    );
+                    )?;
 
                     let streams: [_; PIPELINE_STAGES] = [Stream::new(StreamFlags::NON_BLOCKING, None)?, Stream::new(StreamFlags::NON_BLOCKING, None)?];
 
@@ -466,7 +466,8 @@ where
                                             CpuRadixPartitionAlgorithm::NC,
                                             radix_bits,
                                             DerefMemType::AlignedSysMem { align_bytes },
-                                        );
+                                        )
+                                        .expect("Failed to create CPU radix partitioner");
                                         radix_prnr
                                             .prefix_sum(input, output)
                                             .expect("Failed to run CPU prefix sum");
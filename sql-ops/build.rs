@@ -106,6 +106,7 @@ fn main() {
     pub const ALIGN_BYTES: u32 = {};\n\
     pub const PADDING_BYTES: u32 = {};\n\
     pub const LOG2_NUM_BANKS: u32 = {};\n\
+    pub const LASWWC_TUPLES_PER_THREAD: u32 = {};\n\
     pub const RADIX_JOIN_BUCKET_CHAINING_ENTRIES: u32 = {};\n\
     ",
                 cache_line_size,
@@ -113,6 +114,7 @@ fn main() {
                 align_bytes,
                 padding_bytes,
                 log2_num_banks,
+                laswwc_tuples_per_thread,
                 radix_join_bucket_chaining_entries
             )
             .as_bytes(),
@@ -133,9 +135,12 @@ fn main() {
     let cuda_lib_file = format!("{}/cudautils.fatbin", out_dir);
     let cuda_files = vec![
         "cudautils/gpu_common.cu",
+        "cudautils/hyperloglog.cu",
         "cudautils/no_partitioning_join.cu",
         "cudautils/radix_join.cu",
+        "cudautils/prefix_scan.cu",
         "cudautils/radix_partition.cu",
+        "cudautils/tree_sum.cu",
     ];
     let nvcc_build_args = vec![
         "-rdc=true",
@@ -24,17 +24,36 @@
 #include <atomic>
 #include <cstdint>
 
-template <typename T>
-void cpu_ht_insert_linearprobing(HtEntry<T, T> *const __restrict__ hash_table,
-                                 unsigned int log2_hash_table_entries, T key,
-                                 T payload) {
+// Marks the probe tuple as matched in the semi-join bitmap.
+//
+// Each thread owns its bitmap, thus the update is not atomic.
+inline void cpu_set_bitmap_bit(uint64_t *const __restrict__ bitmap,
+                               uint64_t tuple_id) {
+  bitmap[tuple_id / 64ULL] |= 1ULL << (tuple_id % 64ULL);
+}
+
+// Diagnostics of a hash table build
+//
+// Note that the struct's layout must be kept in sync with its counterpart in
+// Rust.
+struct BuildReport {
+  uint64_t inserted;
+  uint64_t failed;
+  uint64_t max_probe_len;
+};
+
+// Returns the number of probed slots, or 0 if the hash table is full
+template <typename T, ProbeStep S = ProbeStep::Linear>
+uint64_t cpu_ht_insert_linearprobing(
+    HtEntry<T, T> *const __restrict__ hash_table,
+    unsigned int log2_hash_table_entries, T key, T payload) {
   uint64_t index = hash<T>(key, log2_hash_table_entries);
 
   uint64_t hash_table_entries = 1ULL << log2_hash_table_entries;
   uint64_t hash_table_mask = hash_table_entries - 1ULL;
 
   for (uint64_t i = 0; i < hash_table_entries;
-       ++i, index = (index + 1ULL) & hash_table_mask) {
+       index = probe_next<S>(index, i, hash_table_mask), ++i) {
     T old = hash_table[index].key;
     if (old == null_key<T>()) {
       T expected = null_key<T>();
@@ -42,14 +61,16 @@ void cpu_ht_insert_linearprobing(HtEntry<T, T> *const __restrict__ hash_table,
           (std::atomic<T> *)&hash_table[index].key, &expected, key);
       if (is_inserted) {
         hash_table[index].value = payload;
-        return;
+        return i + 1ULL;
       }
     }
   }
+
+  return 0;
 }
 
 // extern "C"
-template <typename T>
+template <typename T, ProbeStep S = ProbeStep::Linear>
 void cpu_ht_build_linearprobing(HtEntry<T, T> *const __restrict__ hash_table,
                                 uint64_t const hash_table_entries,
                                 const T *const __restrict__ join_attr_data,
@@ -59,9 +80,9 @@ void cpu_ht_build_linearprobing(HtEntry<T, T> *const __restrict__ hash_table,
       log2_floor_power_of_two(hash_table_entries);
 
   for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
-    cpu_ht_insert_linearprobing(hash_table, log2_hash_table_entries,
-                                join_attr_data[tuple_id],
-                                payload_attr_data[tuple_id]);
+    cpu_ht_insert_linearprobing<T, S>(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        payload_or(payload_attr_data, tuple_id, T(0)));
   }
 }
 
@@ -85,27 +106,111 @@ extern "C" void cpu_ht_build_linearprobing_int64(
                              payload_attr_data, data_length);
 }
 
-template <typename T>
+// Builds the hash table like `cpu_ht_build_linearprobing`, but counts the
+// keys that don't fit into the hash table instead of dropping them silently
+template <typename T, ProbeStep S = ProbeStep::Linear>
+void cpu_ht_build_linearprobing_report(
+    HtEntry<T, T> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const T *const __restrict__ join_attr_data,
+    const T *const __restrict__ payload_attr_data,
+    uint64_t const data_length, BuildReport *const __restrict__ report) {
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  BuildReport r = {0, 0, 0};
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    uint64_t probe_len = cpu_ht_insert_linearprobing<T, S>(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        payload_or(payload_attr_data, tuple_id, T(0)));
+
+    if (probe_len == 0) {
+      ++r.failed;
+    } else {
+      ++r.inserted;
+      r.max_probe_len = probe_len > r.max_probe_len ? probe_len
+                                                    : r.max_probe_len;
+    }
+  }
+
+  *report = r;
+}
+
+extern "C" void cpu_ht_build_linearprobing_report_int32(
+    HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length, BuildReport *const __restrict__ report) {
+  cpu_ht_build_linearprobing_report(hash_table, hash_table_entries,
+                                    join_attr_data, payload_attr_data,
+                                    data_length, report);
+}
+
+extern "C" void cpu_ht_build_linearprobing_report_int64(
+    HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length, BuildReport *const __restrict__ report) {
+  cpu_ht_build_linearprobing_report(hash_table, hash_table_entries,
+                                    join_attr_data, payload_attr_data,
+                                    data_length, report);
+}
+
+extern "C" void cpu_ht_build_quadraticprobing_report_int32(
+    HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length, BuildReport *const __restrict__ report) {
+  cpu_ht_build_linearprobing_report<int, ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, report);
+}
+
+extern "C" void cpu_ht_build_quadraticprobing_report_int64(
+    HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length, BuildReport *const __restrict__ report) {
+  cpu_ht_build_linearprobing_report<long long, ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, report);
+}
+
+// Finds the next slot that contains `key`
+//
+// A quadratic probe resumes at the step after `last_index`, and thus requires
+// `last_step` to store the step. A linear probe ignores `last_step`.
+template <typename T, ProbeStep S = ProbeStep::Linear>
 bool cpu_ht_findkey_linearprobing(
     HtEntry<T, T> const *const __restrict__ hash_table,
     unsigned int log2_hash_table_entries, T key, T const **found_payload,
-    uint64_t *__restrict__ last_index, bool use_last_index) {
+    uint64_t *__restrict__ last_index, bool use_last_index,
+    uint64_t *__restrict__ last_step = nullptr) {
   uint64_t hash_table_entries = 1ULL << log2_hash_table_entries;
   uint64_t hash_table_mask = hash_table_entries - 1ULL;
 
   uint64_t index = 0;
+  uint64_t step = 0;
   if (use_last_index) {
-    index = *last_index;
-    index = (index + 1ULL) & hash_table_mask;
+    step = S == ProbeStep::Quadratic ? *last_step : 0ULL;
+    index = probe_next<S>(*last_index, step, hash_table_mask);
+    ++step;
   } else {
     index = hash<T>(key, log2_hash_table_entries);
   }
 
   for (uint64_t i = 0; i < hash_table_mask + 1ULL;
-       ++i, index = (index + 1ULL) & hash_table_mask) {
+       ++i, index = probe_next<S>(index, step, hash_table_mask), ++step) {
     if (hash_table[index].key == key) {
       *found_payload = &hash_table[index].value;
       *last_index = index;
+      if (S == ProbeStep::Quadratic) {
+        *last_step = step;
+      }
       return true;
     } else if (hash_table[index].key == null_key<T>()) {
       return false;
@@ -115,7 +220,7 @@ bool cpu_ht_findkey_linearprobing(
   return false;
 }
 
-template <typename T>
+template <typename T, ProbeStep S = ProbeStep::Linear>
 void cpu_ht_probe_aggregate_linearprobing(
     HtEntry<T, T> const *const __restrict__ hash_table,
     uint64_t const hash_table_entries,
@@ -128,13 +233,14 @@ void cpu_ht_probe_aggregate_linearprobing(
   for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
     T const *hash_table_payload = nullptr;
     uint64_t hash_table_last_index = 0;
+    uint64_t hash_table_last_step = 0;
     bool hash_table_use_last_index = false;
-    while (cpu_ht_findkey_linearprobing(
+    while (cpu_ht_findkey_linearprobing<T, S>(
         hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
         &hash_table_payload, &hash_table_last_index,
-        hash_table_use_last_index)) {
+        hash_table_use_last_index, &hash_table_last_step)) {
       hash_table_use_last_index = true;
-      *aggregation_result += payload_attr_data[tuple_id];
+      *aggregation_result += payload_or(payload_attr_data, tuple_id, T(1));
     }
   }
 }
@@ -162,6 +268,572 @@ extern "C" void cpu_ht_probe_aggregate_linearprobing_int64(
                                        data_length, aggregation_result);
 }
 
+// Linear probing with quadratic steps
+//
+// The hash table layout is unchanged, only the probe sequence differs. A key
+// inserted with quadratic steps is not found by a probe with linear steps.
+extern "C" void cpu_ht_build_quadraticprobing_int32(
+    HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  cpu_ht_build_linearprobing<int, ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length);
+}
+
+extern "C" void cpu_ht_build_quadraticprobing_int64(
+    HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  cpu_ht_build_linearprobing<long long, ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length);
+}
+
+extern "C" void cpu_ht_probe_aggregate_quadraticprobing_int32(
+    HtEntry<int, int> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ aggregation_result) {
+  cpu_ht_probe_aggregate_linearprobing<int, ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, aggregation_result);
+}
+
+extern "C" void cpu_ht_probe_aggregate_quadraticprobing_int64(
+    HtEntry<long long, long long> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length,
+    uint64_t *const __restrict__ aggregation_result) {
+  cpu_ht_probe_aggregate_linearprobing<long long, ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, aggregation_result);
+}
+
+// Linear probing with a structure-of-arrays (SoA) hash table layout
+//
+// The hash table memory holds an array of `hash_table_entries` keys, followed
+// by an array of `hash_table_entries` values. See the CUDA implementation for
+// details.
+template <typename T>
+void cpu_ht_insert_linearprobing_soa(T *const __restrict__ keys,
+                                     T *const __restrict__ values,
+                                     unsigned int log2_hash_table_entries,
+                                     T key, T payload) {
+  uint64_t index = hash<T>(key, log2_hash_table_entries);
+
+  uint64_t hash_table_entries = 1ULL << log2_hash_table_entries;
+  uint64_t hash_table_mask = hash_table_entries - 1ULL;
+
+  for (uint64_t i = 0; i < hash_table_entries;
+       ++i, index = (index + 1ULL) & hash_table_mask) {
+    if (keys[index] == null_key<T>()) {
+      T expected = null_key<T>();
+      bool is_inserted = std::atomic_compare_exchange_strong(
+          (std::atomic<T> *)&keys[index], &expected, key);
+      if (is_inserted) {
+        values[index] = payload;
+        return;
+      }
+    }
+  }
+}
+
+template <typename T>
+void cpu_ht_build_linearprobing_soa(
+    T *const __restrict__ hash_table, uint64_t const hash_table_entries,
+    const T *const __restrict__ join_attr_data,
+    const T *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+  T *const values = hash_table + hash_table_entries;
+
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    cpu_ht_insert_linearprobing_soa(
+        hash_table, values, log2_hash_table_entries, join_attr_data[tuple_id],
+        payload_or(payload_attr_data, tuple_id, T(0)));
+  }
+}
+
+template <typename T>
+bool cpu_ht_findkey_linearprobing_soa(T const *const __restrict__ keys,
+                                      unsigned int log2_hash_table_entries,
+                                      T key, uint64_t *__restrict__ last_index,
+                                      bool use_last_index) {
+  uint64_t hash_table_entries = 1ULL << log2_hash_table_entries;
+  uint64_t hash_table_mask = hash_table_entries - 1ULL;
+
+  uint64_t index = 0;
+  if (use_last_index) {
+    index = *last_index;
+    index = (index + 1ULL) & hash_table_mask;
+  } else {
+    index = hash<T>(key, log2_hash_table_entries);
+  }
+
+  for (uint64_t i = 0; i < hash_table_entries;
+       ++i, index = (index + 1ULL) & hash_table_mask) {
+    if (keys[index] == key) {
+      *last_index = index;
+      return true;
+    } else if (keys[index] == null_key<T>()) {
+      return false;
+    }
+  }
+
+  return false;
+}
+
+template <typename T>
+void cpu_ht_probe_aggregate_linearprobing_soa(
+    T const *const __restrict__ hash_table, uint64_t const hash_table_entries,
+    const T *const __restrict__ join_attr_data,
+    const T *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ aggregation_result) {
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    uint64_t hash_table_last_index = 0;
+    bool hash_table_use_last_index = false;
+    while (cpu_ht_findkey_linearprobing_soa(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_last_index, hash_table_use_last_index)) {
+      hash_table_use_last_index = true;
+      *aggregation_result += payload_or(payload_attr_data, tuple_id, T(1));
+    }
+  }
+}
+
+extern "C" void cpu_ht_build_linearprobing_soa_int32(
+    int *const __restrict__ hash_table, uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  cpu_ht_build_linearprobing_soa(hash_table, hash_table_entries,
+                                 join_attr_data, payload_attr_data,
+                                 data_length);
+}
+
+extern "C" void cpu_ht_build_linearprobing_soa_int64(
+    long long *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  cpu_ht_build_linearprobing_soa(hash_table, hash_table_entries,
+                                 join_attr_data, payload_attr_data,
+                                 data_length);
+}
+
+extern "C" void cpu_ht_probe_aggregate_linearprobing_soa_int32(
+    int const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ aggregation_result) {
+  cpu_ht_probe_aggregate_linearprobing_soa(hash_table, hash_table_entries,
+                                           join_attr_data, payload_attr_data,
+                                           data_length, aggregation_result);
+}
+
+extern "C" void cpu_ht_probe_aggregate_linearprobing_soa_int64(
+    long long const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length,
+    uint64_t *const __restrict__ aggregation_result) {
+  cpu_ht_probe_aggregate_linearprobing_soa(hash_table, hash_table_entries,
+                                           join_attr_data, payload_attr_data,
+                                           data_length, aggregation_result);
+}
+
+template <typename T>
+void cpu_ht_probe_bitmap_linearprobing(
+    HtEntry<T, T> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const T *const __restrict__ join_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ bitmap) {
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    T const *hash_table_payload = nullptr;
+    uint64_t hash_table_last_index = 0;
+    if (cpu_ht_findkey_linearprobing(hash_table, log2_hash_table_entries,
+                                     join_attr_data[tuple_id],
+                                     &hash_table_payload,
+                                     &hash_table_last_index, false)) {
+      cpu_set_bitmap_bit(bitmap, tuple_id);
+    }
+  }
+}
+
+extern "C" void cpu_ht_probe_bitmap_linearprobing_int32(
+    HtEntry<int, int> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ bitmap) {
+  cpu_ht_probe_bitmap_linearprobing(hash_table, hash_table_entries,
+                                    join_attr_data, data_length, bitmap);
+}
+
+extern "C" void cpu_ht_probe_bitmap_linearprobing_int64(
+    HtEntry<long long, long long> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    uint64_t const data_length, uint64_t *const __restrict__ bitmap) {
+  cpu_ht_probe_bitmap_linearprobing(hash_table, hash_table_entries,
+                                    join_attr_data, data_length, bitmap);
+}
+
+// Writes the matches in probe relation order.
+//
+// Matches beyond the result capacity are counted, but not written. Returns
+// the total number of matches.
+//
+// With `stop_at_capacity` set, the probe stops at the first surplus match and
+// returns one more than the capacity.
+template <typename T>
+uint64_t cpu_ht_probe_materialize_linearprobing(
+    HtEntry<T, T> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const T *const __restrict__ join_attr_data,
+    const T *const __restrict__ payload_attr_data, uint64_t const data_length,
+    JoinMatch<T> *const __restrict__ join_result,
+    uint64_t const join_result_capacity, bool const stop_at_capacity) {
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  uint64_t matches = 0;
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    T const *hash_table_payload = nullptr;
+    uint64_t hash_table_last_index = 0;
+    bool hash_table_use_last_index = false;
+    while (cpu_ht_findkey_linearprobing(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_payload, &hash_table_last_index,
+        hash_table_use_last_index)) {
+      hash_table_use_last_index = true;
+      if (matches < join_result_capacity) {
+        join_result[matches].build_payload = *hash_table_payload;
+        join_result[matches].probe_payload = payload_attr_data[tuple_id];
+      } else if (stop_at_capacity) {
+        return join_result_capacity + 1;
+      }
+      ++matches;
+    }
+  }
+
+  return matches;
+}
+
+extern "C" uint64_t cpu_ht_probe_materialize_linearprobing_int32(
+    HtEntry<int, int> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data, uint64_t const data_length,
+    JoinMatch<int> *const __restrict__ join_result,
+    uint64_t const join_result_capacity, uint32_t const stop_at_capacity) {
+  return cpu_ht_probe_materialize_linearprobing(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, join_result, join_result_capacity, stop_at_capacity != 0);
+}
+
+extern "C" uint64_t cpu_ht_probe_materialize_linearprobing_int64(
+    HtEntry<long long, long long> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length,
+    JoinMatch<long long> *const __restrict__ join_result,
+    uint64_t const join_result_capacity, uint32_t const stop_at_capacity) {
+  return cpu_ht_probe_materialize_linearprobing(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, join_result, join_result_capacity, stop_at_capacity != 0);
+}
+
+// Cuckoo hashing scheme
+//
+// The hash table is split into two equally-sized sub-tables. Each key has
+// exactly one candidate slot in each sub-table, determined by two independent
+// hash functions. Thus, a lookup reads at most two slots.
+//
+// On insert, a key that finds both of its slots occupied evicts the key in its
+// first slot. The evicted key is then re-inserted into its alternative slot,
+// possibly evicting yet another key. If the chain of evictions becomes longer
+// than CUCKOO_MAX_EVICTIONS, the insert fails. In this case, the build clears
+// the hash table and retries with a different hash seed.
+//
+// Cuckoo hashing with two sub-tables supports a load factor of up to 50%.
+// Beyond that, inserts fail with high probability regardless of the seed.
+//
+// The build is sequential and must be called once with the entire build-side
+// relation, as rebuilding requires all keys to be re-inserted.
+
+constexpr uint64_t CUCKOO_MAX_EVICTIONS = 512;
+constexpr uint32_t CUCKOO_MAX_REBUILDS = 16;
+
+template <typename T>
+T cuckoo_seed_key(T key, uint32_t seed) {
+  // Golden ratio constant to spread consecutive seeds over the key bits
+  return key ^
+         static_cast<T>(static_cast<uint64_t>(seed) * 0x9E3779B97F4A7C15ULL);
+}
+
+template <typename T>
+uint64_t cuckoo_hash_first(T key, unsigned int log2_sub_table_entries,
+                           uint32_t seed) {
+  return static_cast<uint64_t>(mult_shift_hash<T>(
+      cuckoo_seed_key(key, seed), log2_sub_table_entries));
+}
+
+template <typename T>
+uint64_t cuckoo_hash_second(T key, unsigned int log2_sub_table_entries,
+                            uint32_t seed) {
+  uint64_t sub_table_entries = 1ULL << log2_sub_table_entries;
+  return sub_table_entries +
+         static_cast<uint64_t>(murmur3_hash<T>(cuckoo_seed_key(key, seed),
+                                               log2_sub_table_entries));
+}
+
+template <typename T>
+bool cpu_ht_insert_cuckoo(HtEntry<T, T> *const __restrict__ hash_table,
+                          unsigned int log2_sub_table_entries, uint32_t seed,
+                          T key, T payload) {
+  uint64_t index = cuckoo_hash_first(key, log2_sub_table_entries, seed);
+
+  for (uint64_t i = 0; i < CUCKOO_MAX_EVICTIONS; ++i) {
+    if (hash_table[index].key == null_key<T>()) {
+      hash_table[index].key = key;
+      hash_table[index].value = payload;
+      return true;
+    }
+
+    // Try the alternative slot before evicting
+    uint64_t first = cuckoo_hash_first(key, log2_sub_table_entries, seed);
+    uint64_t second = cuckoo_hash_second(key, log2_sub_table_entries, seed);
+    uint64_t alternative = (index == first) ? second : first;
+    if (hash_table[alternative].key == null_key<T>()) {
+      hash_table[alternative].key = key;
+      hash_table[alternative].value = payload;
+      return true;
+    }
+
+    // Evict the current occupant and continue with its alternative slot
+    T evicted_key = hash_table[index].key;
+    T evicted_payload = hash_table[index].value;
+    hash_table[index].key = key;
+    hash_table[index].value = payload;
+
+    key = evicted_key;
+    payload = evicted_payload;
+
+    first = cuckoo_hash_first(key, log2_sub_table_entries, seed);
+    second = cuckoo_hash_second(key, log2_sub_table_entries, seed);
+    index = (index == first) ? second : first;
+  }
+
+  return false;
+}
+
+// extern "C"
+template <typename T>
+bool cpu_ht_build_cuckoo(HtEntry<T, T> *const __restrict__ hash_table,
+                         uint64_t const hash_table_entries,
+                         const T *const __restrict__ join_attr_data,
+                         const T *const __restrict__ payload_attr_data,
+                         uint64_t const data_length, uint32_t *const seed) {
+  const unsigned int log2_sub_table_entries =
+      log2_floor_power_of_two(hash_table_entries) - 1U;
+  const uint64_t used_entries = 2ULL << log2_sub_table_entries;
+
+  for (uint32_t attempt = 0; attempt < CUCKOO_MAX_REBUILDS; ++attempt) {
+    bool is_success = true;
+
+    for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+      T key = join_attr_data[tuple_id];
+      if (key == null_key<T>()) {
+        continue;
+      }
+
+      if (!cpu_ht_insert_cuckoo(
+              hash_table, log2_sub_table_entries, attempt, key,
+              payload_or(payload_attr_data, tuple_id, T(0)))) {
+        is_success = false;
+        break;
+      }
+    }
+
+    if (is_success) {
+      *seed = attempt;
+      return true;
+    }
+
+    // Clear the hash table before rebuilding with the next seed
+    for (uint64_t i = 0; i < used_entries; ++i) {
+      hash_table[i].key = null_key<T>();
+    }
+  }
+
+  return false;
+}
+
+extern "C" bool cpu_ht_build_cuckoo_int32(
+    HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length, uint32_t *const seed) {
+  return cpu_ht_build_cuckoo(hash_table, hash_table_entries, join_attr_data,
+                             payload_attr_data, data_length, seed);
+}
+
+extern "C" bool cpu_ht_build_cuckoo_int64(
+    HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length, uint32_t *const seed) {
+  return cpu_ht_build_cuckoo(hash_table, hash_table_entries, join_attr_data,
+                             payload_attr_data, data_length, seed);
+}
+
+template <typename T>
+bool cpu_ht_findkey_cuckoo(HtEntry<T, T> const *const __restrict__ hash_table,
+                           unsigned int log2_sub_table_entries, uint32_t seed,
+                           T key, T const **found_payload,
+                           uint32_t *__restrict__ slot_reads) {
+  uint64_t index = cuckoo_hash_first(key, log2_sub_table_entries, seed);
+  *slot_reads = 1;
+  if (hash_table[index].key == key) {
+    *found_payload = &hash_table[index].value;
+    return true;
+  }
+
+  index = cuckoo_hash_second(key, log2_sub_table_entries, seed);
+  *slot_reads = 2;
+  if (hash_table[index].key == key) {
+    *found_payload = &hash_table[index].value;
+    return true;
+  }
+
+  return false;
+}
+
+extern "C" bool cpu_ht_findkey_cuckoo_int32(
+    HtEntry<int, int> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries, uint32_t seed, int key,
+    uint32_t *__restrict__ slot_reads) {
+  const unsigned int log2_sub_table_entries =
+      log2_floor_power_of_two(hash_table_entries) - 1U;
+  int const *payload = nullptr;
+  return cpu_ht_findkey_cuckoo(hash_table, log2_sub_table_entries, seed, key,
+                               &payload, slot_reads);
+}
+
+extern "C" bool cpu_ht_findkey_cuckoo_int64(
+    HtEntry<long long, long long> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries, uint32_t seed, long long key,
+    uint32_t *__restrict__ slot_reads) {
+  const unsigned int log2_sub_table_entries =
+      log2_floor_power_of_two(hash_table_entries) - 1U;
+  long long const *payload = nullptr;
+  return cpu_ht_findkey_cuckoo(hash_table, log2_sub_table_entries, seed, key,
+                               &payload, slot_reads);
+}
+
+template <typename T>
+void cpu_ht_probe_aggregate_cuckoo(
+    HtEntry<T, T> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries, uint32_t seed,
+    const T *const __restrict__ join_attr_data,
+    const T *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ aggregation_result) {
+  const unsigned int log2_sub_table_entries =
+      log2_floor_power_of_two(hash_table_entries) - 1U;
+
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    T const *hash_table_payload = nullptr;
+    uint32_t slot_reads = 0;
+    if (cpu_ht_findkey_cuckoo(hash_table, log2_sub_table_entries, seed,
+                              join_attr_data[tuple_id], &hash_table_payload,
+                              &slot_reads)) {
+      *aggregation_result += payload_or(payload_attr_data, tuple_id, T(1));
+    }
+  }
+}
+
+extern "C" void cpu_ht_probe_aggregate_cuckoo_int32(
+    HtEntry<int, int> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries, uint32_t seed,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ aggregation_result) {
+  cpu_ht_probe_aggregate_cuckoo(hash_table, hash_table_entries, seed,
+                                join_attr_data, payload_attr_data, data_length,
+                                aggregation_result);
+}
+
+extern "C" void cpu_ht_probe_aggregate_cuckoo_int64(
+    HtEntry<long long, long long> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries, uint32_t seed,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length,
+    uint64_t *const __restrict__ aggregation_result) {
+  cpu_ht_probe_aggregate_cuckoo(hash_table, hash_table_entries, seed,
+                                join_attr_data, payload_attr_data, data_length,
+                                aggregation_result);
+}
+
+template <typename T>
+void cpu_ht_probe_bitmap_cuckoo(
+    HtEntry<T, T> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries, uint32_t seed,
+    const T *const __restrict__ join_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ bitmap) {
+  const unsigned int log2_sub_table_entries =
+      log2_floor_power_of_two(hash_table_entries) - 1U;
+
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    T const *hash_table_payload = nullptr;
+    uint32_t slot_reads = 0;
+    if (cpu_ht_findkey_cuckoo(hash_table, log2_sub_table_entries, seed,
+                              join_attr_data[tuple_id], &hash_table_payload,
+                              &slot_reads)) {
+      cpu_set_bitmap_bit(bitmap, tuple_id);
+    }
+  }
+}
+
+extern "C" void cpu_ht_probe_bitmap_cuckoo_int32(
+    HtEntry<int, int> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries, uint32_t seed,
+    const int *const __restrict__ join_attr_data, uint64_t const data_length,
+    uint64_t *const __restrict__ bitmap) {
+  cpu_ht_probe_bitmap_cuckoo(hash_table, hash_table_entries, seed,
+                             join_attr_data, data_length, bitmap);
+}
+
+extern "C" void cpu_ht_probe_bitmap_cuckoo_int64(
+    HtEntry<long long, long long> const *const __restrict__ hash_table,
+    uint64_t const hash_table_entries, uint32_t seed,
+    const long long *const __restrict__ join_attr_data,
+    uint64_t const data_length, uint64_t *const __restrict__ bitmap) {
+  cpu_ht_probe_bitmap_cuckoo(hash_table, hash_table_entries, seed,
+                             join_attr_data, data_length, bitmap);
+}
+
 template <typename T>
 void cpu_ht_build_perfect(HtEntry<T, T> *const __restrict__ hash_table,
                           uint64_t const /* hash_table_entries */,
@@ -170,7 +842,7 @@ void cpu_ht_build_perfect(HtEntry<T, T> *const __restrict__ hash_table,
                           uint64_t const data_length) {
   for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
     T key = join_attribute_data[tuple_id];
-    T val = payload_attributed_data[tuple_id];
+    T val = payload_or(payload_attributed_data, tuple_id, T(0));
     hash_table[key].key = key;
     hash_table[key].value = val;
   }
@@ -186,13 +858,72 @@ void cpu_ht_build_selective_perfect(
   for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
     T key = join_attribute_data[tuple_id];
     if (key != null_key<T>()) {
-      T val = payload_attributed_data[tuple_id];
+      T val = payload_or(payload_attributed_data, tuple_id, T(0));
       hash_table[key].key = key;
       hash_table[key].value = val;
     }
   }
 }
 
+// Builds a perfect hash table, but reports the keys that it cannot insert
+//
+// In contrast to `cpu_ht_build_perfect`, a key outside of the hash table
+// counts as a failure instead of writing out of bounds. A duplicate key also
+// counts as a failure, and the first occurrence of the key keeps its payload.
+// A selective build skips null keys without counting them.
+template <typename T>
+void cpu_ht_build_perfect_report(HtEntry<T, T> *const __restrict__ hash_table,
+                                 uint64_t const hash_table_entries,
+                                 const T *const __restrict__ join_attr_data,
+                                 const T *const __restrict__ payload_attr_data,
+                                 uint64_t const data_length,
+                                 bool const is_selective,
+                                 BuildReport *const __restrict__ report) {
+  BuildReport r = {0, 0, 0};
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    T key = join_attr_data[tuple_id];
+    if (is_selective && key == null_key<T>()) {
+      continue;
+    }
+
+    if (key < T(0) || static_cast<uint64_t>(key) >= hash_table_entries ||
+        hash_table[key].key == key) {
+      ++r.failed;
+    } else {
+      hash_table[key].key = key;
+      hash_table[key].value = payload_or(payload_attr_data, tuple_id, T(0));
+      ++r.inserted;
+      r.max_probe_len = 1;
+    }
+  }
+
+  *report = r;
+}
+
+extern "C" void cpu_ht_build_perfect_report_int32(
+    HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length, bool const is_selective,
+    BuildReport *const __restrict__ report) {
+  cpu_ht_build_perfect_report(hash_table, hash_table_entries, join_attr_data,
+                              payload_attr_data, data_length, is_selective,
+                              report);
+}
+
+extern "C" void cpu_ht_build_perfect_report_int64(
+    HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length, bool const is_selective,
+    BuildReport *const __restrict__ report) {
+  cpu_ht_build_perfect_report(hash_table, hash_table_entries, join_attr_data,
+                              payload_attr_data, data_length, is_selective,
+                              report);
+}
+
 extern "C" void cpu_ht_build_perfect_int32(
     HtEntry<int, int> *const __restrict__ hash_table,
     uint64_t const hash_table_entries,
@@ -245,7 +976,8 @@ void cpu_ht_probe_aggregate_perfect(
   for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
     T key = join_attribute_data[tuple_id];
     if (hash_table[key].key == key) {
-      *aggregation_result += payload_attribute_data[tuple_id];
+      *aggregation_result +=
+          payload_or(payload_attribute_data, tuple_id, T(1));
     }
   }
 }
@@ -271,3 +1003,120 @@ extern "C" void cpu_ht_probe_aggregate_perfect_int64(
                                  join_attribute_data, payload_attribute_data,
                                  data_length, aggregation_result);
 }
+
+template <typename T>
+void cpu_ht_probe_bitmap_perfect(
+    const HtEntry<T, T> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const T *const __restrict__ join_attribute_data, uint64_t const data_length,
+    uint64_t *const __restrict__ bitmap) {
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    T key = join_attribute_data[tuple_id];
+    if (hash_table[key].key == key) {
+      cpu_set_bitmap_bit(bitmap, tuple_id);
+    }
+  }
+}
+
+extern "C" void cpu_ht_probe_bitmap_perfect_int32(
+    const HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attribute_data,
+    uint64_t const data_length, uint64_t *const __restrict__ bitmap) {
+  cpu_ht_probe_bitmap_perfect(hash_table, hash_table_entries,
+                              join_attribute_data, data_length, bitmap);
+}
+
+extern "C" void cpu_ht_probe_bitmap_perfect_int64(
+    const HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attribute_data,
+    uint64_t const data_length, uint64_t *const __restrict__ bitmap) {
+  cpu_ht_probe_bitmap_perfect(hash_table, hash_table_entries,
+                              join_attribute_data, data_length, bitmap);
+}
+
+/*
+ * Perfect hashing for narrow keys, e.g., dictionary-encoded columns.
+ *
+ * The hash table must have an entry for each value in the key domain. As the
+ * NULL key is part of the domain, the probe explicitly skips NULL keys.
+ */
+template <typename T>
+void cpu_ht_probe_aggregate_perfect_narrow(
+    const HtEntry<T, T> *const __restrict__ hash_table,
+    const T *const __restrict__ join_attribute_data,
+    const T *const __restrict__ payload_attribute_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result) {
+  for (uint64_t tuple_id = 0; tuple_id < data_length; ++tuple_id) {
+    T key = join_attribute_data[tuple_id];
+    if (key != null_key<T>() && hash_table[key].key == key) {
+      *aggregation_result +=
+          payload_or(payload_attribute_data, tuple_id, T(1));
+    }
+  }
+}
+
+extern "C" void cpu_ht_build_perfect_uint8(
+    HtEntry<unsigned char, unsigned char> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const unsigned char *const __restrict__ join_attribute_data,
+    const unsigned char *const __restrict__ payload_attributed_data,
+    uint64_t const data_length) {
+  cpu_ht_build_perfect(hash_table, hash_table_entries, join_attribute_data,
+                       payload_attributed_data, data_length);
+}
+
+extern "C" void cpu_ht_build_selective_perfect_uint8(
+    HtEntry<unsigned char, unsigned char> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const unsigned char *const __restrict__ join_attribute_data,
+    const unsigned char *const __restrict__ payload_attributed_data,
+    uint64_t const data_length) {
+  cpu_ht_build_selective_perfect(hash_table, hash_table_entries,
+                                 join_attribute_data, payload_attributed_data,
+                                 data_length);
+}
+
+extern "C" void cpu_ht_build_perfect_uint16(
+    HtEntry<unsigned short, unsigned short> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const unsigned short *const __restrict__ join_attribute_data,
+    const unsigned short *const __restrict__ payload_attributed_data,
+    uint64_t const data_length) {
+  cpu_ht_build_perfect(hash_table, hash_table_entries, join_attribute_data,
+                       payload_attributed_data, data_length);
+}
+
+extern "C" void cpu_ht_build_selective_perfect_uint16(
+    HtEntry<unsigned short, unsigned short> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const unsigned short *const __restrict__ join_attribute_data,
+    const unsigned short *const __restrict__ payload_attributed_data,
+    uint64_t const data_length) {
+  cpu_ht_build_selective_perfect(hash_table, hash_table_entries,
+                                 join_attribute_data, payload_attributed_data,
+                                 data_length);
+}
+
+extern "C" void cpu_ht_probe_aggregate_perfect_uint8(
+    const HtEntry<unsigned char, unsigned char> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const unsigned char *const __restrict__ join_attribute_data,
+    const unsigned char *const __restrict__ payload_attribute_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result) {
+  cpu_ht_probe_aggregate_perfect_narrow(hash_table, join_attribute_data,
+                                        payload_attribute_data, data_length,
+                                        aggregation_result);
+}
+
+extern "C" void cpu_ht_probe_aggregate_perfect_uint16(
+    const HtEntry<unsigned short, unsigned short> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const unsigned short *const __restrict__ join_attribute_data,
+    const unsigned short *const __restrict__ payload_attribute_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result) {
+  cpu_ht_probe_aggregate_perfect_narrow(hash_table, join_attribute_data,
+                                        payload_attribute_data, data_length,
+                                        aggregation_result);
+}
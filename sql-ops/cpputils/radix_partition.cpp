@@ -445,6 +445,117 @@ void cpu_chunked_radix_partition(RadixPartitionArgs &args) {
   }
 }
 
+// Chunked radix partitioning that also records the inverse permutation.
+//
+// The partitioning is identical to `cpu_chunked_radix_partition`. In addition,
+// the tuple ID of each tuple is scattered into `inverse`, such that
+// `inverse[partitioned_pos] = first_tuple_id + input_pos`. `inverse` has the
+// same layout as the partitioned chunk.
+template <typename K, typename V, typename M>
+void cpu_chunked_radix_partition_inverse(
+    RadixPartitionArgs &args, unsigned long long *const __restrict__ inverse,
+    unsigned long long const first_tuple_id) {
+#ifdef __powerpc64__
+  __mtspr(PPC_DSCR, PPC_TUNE_DSCR);
+#endif
+
+  auto join_attr_data =
+      static_cast<const K *const __restrict__>(args.join_attr_data);
+  auto payload_attr_data =
+      static_cast<const V *const __restrict__>(args.payload_attr_data);
+  auto partitioned_relation =
+      static_cast<Tuple<K, V> *const __restrict__>(args.partitioned_relation);
+  auto tmp_partition_offsets = args.tmp_partition_offsets;
+
+  const size_t fanout = 1UL << args.radix_bits;
+  const M mask = static_cast<M>(fanout - 1UL);
+  const size_t partitioned_data_offset =
+      args.partition_offsets[0] - args.padding_length;
+
+  // Load partition offsets.
+  for (size_t i = 0; i < fanout; ++i) {
+    tmp_partition_offsets[i] =
+        args.partition_offsets[i] - partitioned_data_offset;
+  }
+
+  // Partition.
+  for (size_t i = 0; i < args.data_length; ++i) {
+    Tuple<K, V> tuple;
+    tuple.key = join_attr_data[i];
+    tuple.value = payload_attr_data[i];
+
+    M p_index = key_to_partition(tuple.key, mask, 0);
+    auto &offset = tmp_partition_offsets[p_index];
+    partitioned_relation[offset] = tuple;
+    inverse[offset] = first_tuple_id + i;
+    offset += 1;
+  }
+}
+
+#if defined(__x86_64__)
+// Writes a tuple to memory with non-temporal stores.
+//
+// The stores bypass the cache, and thus don't evict the input and the
+// partition offsets from the cache. The tuple is written as a sequence of
+// 8-byte stores, which don't require vector-length alignment.
+template <typename K, typename V>
+void stream_tuple(Tuple<K, V> *const __restrict__ dst,
+                  Tuple<K, V> const &tuple) {
+  static_assert(sizeof(Tuple<K, V>) % sizeof(long long) == 0,
+                "Tuple size must be a multiple of 8 bytes");
+
+  auto word_dst = reinterpret_cast<long long *>(dst);
+  for (size_t i = 0; i < sizeof(Tuple<K, V>) / sizeof(long long); ++i) {
+    long long word;
+    memcpy(&word, reinterpret_cast<const char *>(&tuple) + i * sizeof(word),
+           sizeof(word));
+    _mm_stream_si64(word_dst + i, word);
+  }
+}
+
+// Chunked radix partitioning with non-temporal stores.
+//
+// See the Rust module for details.
+template <typename K, typename V, typename M>
+void cpu_chunked_radix_partition_streaming(RadixPartitionArgs &args) {
+  auto join_attr_data =
+      static_cast<const K *const __restrict__>(args.join_attr_data);
+  auto payload_attr_data =
+      static_cast<const V *const __restrict__>(args.payload_attr_data);
+  auto partitioned_relation =
+      static_cast<Tuple<K, V> *const __restrict__>(args.partitioned_relation);
+  auto tmp_partition_offsets = args.tmp_partition_offsets;
+
+  const size_t fanout = 1UL << args.radix_bits;
+  const M mask = static_cast<M>(fanout - 1UL);
+  const size_t partitioned_data_offset =
+      args.partition_offsets[0] - args.padding_length;
+
+  // Load partition offsets.
+  for (size_t i = 0; i < fanout; ++i) {
+    tmp_partition_offsets[i] =
+        args.partition_offsets[i] - partitioned_data_offset;
+  }
+
+  // Partition.
+#pragma GCC unroll 16
+  for (size_t i = 0; i < args.data_length; ++i) {
+    Tuple<K, V> tuple;
+    tuple.key = join_attr_data[i];
+    tuple.value = payload_attr_data[i];
+
+    M p_index = key_to_partition(tuple.key, mask, 0);
+    auto &offset = tmp_partition_offsets[p_index];
+    stream_tuple(&partitioned_relation[offset], tuple);
+    offset += 1;
+  }
+
+  // Non-temporal stores are weakly ordered. The fence makes them visible to
+  // other threads before the function returns.
+  _mm_sfence();
+}
+#endif /* defined(__x86_64__) */
+
 template <typename K, typename V, typename M>
 void buffer_tuple(Tuple<K, V> *const __restrict__ partitioned_relation,
                   WriteCombineBuffer<Tuple<K, V>, SWWC_BUFFER_SIZE>
@@ -734,6 +845,45 @@ extern "C" void cpu_chunked_radix_partition_int64_int64(
   cpu_chunked_radix_partition<long long, long long, unsigned long long>(*args);
 }
 
+// Exports the inverse permutation partitioning function for 8-byte key/value
+// tuples.
+extern "C" void cpu_chunked_radix_partition_inverse_int32_int32(
+    RadixPartitionArgs *args, unsigned long long *inverse,
+    unsigned long long first_tuple_id) {
+  cpu_chunked_radix_partition_inverse<int, int, unsigned>(*args, inverse,
+                                                          first_tuple_id);
+}
+
+// Exports the inverse permutation partitioning function for 16-byte key/value
+// tuples.
+extern "C" void cpu_chunked_radix_partition_inverse_int64_int64(
+    RadixPartitionArgs *args, unsigned long long *inverse,
+    unsigned long long first_tuple_id) {
+  cpu_chunked_radix_partition_inverse<long long, long long,
+                                      unsigned long long>(
+      *args, inverse, first_tuple_id);
+}
+
+#if defined(__x86_64__)
+// Exports the partitioning function for 8-byte key/value tuples.
+extern "C" void cpu_chunked_radix_partition_streaming_int32_int32(
+    RadixPartitionArgs *args) {
+  cpu_chunked_radix_partition_streaming<int, int, unsigned>(*args);
+}
+
+// Exports the partitioning function for 16-byte key/value tuples.
+extern "C" void cpu_chunked_radix_partition_streaming_int64_int64(
+    RadixPartitionArgs *args) {
+  cpu_chunked_radix_partition_streaming<long long, long long,
+                                        unsigned long long>(*args);
+}
+#else  // define dummy function symbols
+extern "C" void cpu_chunked_radix_partition_streaming_int32_int32(
+    RadixPartitionArgs * /* args */) {}
+extern "C" void cpu_chunked_radix_partition_streaming_int64_int64(
+    RadixPartitionArgs * /* args */) {}
+#endif /* defined(__x86_64__) */
+
 // Exports the partitioning function for 8-byte key/value tuples.
 extern "C" void cpu_chunked_radix_partition_swwc_int32_int32(
     RadixPartitionArgs *args) {
@@ -0,0 +1,98 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*
+ * HyperLogLog registers of a key column.
+ *
+ * Each thread block updates its own copy of the registers in shared memory.
+ * At the end, the block merges its registers into the global registers by
+ * taking the maximum of each register. The maximum is independent of the
+ * merge order, thus the registers equal those of the CPU implementation.
+ *
+ * The hash function and the register update must be kept in sync with
+ * `sql_ops::cardinality`.
+ *
+ * Assumptions:
+ *
+ * 4 <= precision <= 12
+ * Shared memory holds 2^precision registers
+ * The global registers are zero-initialized
+ */
+
+#include <gpu_common.h>
+
+typedef unsigned long long int uint64_t;
+
+// Finalizer of MurmurHash3, which mixes all input bits into all output bits
+__device__ uint64_t hyperloglog_mix64(uint64_t x) {
+  x ^= x >> 33;
+  x *= 0xff51afd7ed558ccdULL;
+  x ^= x >> 33;
+  x *= 0xc4ceb9fe1a85ec53ULL;
+  x ^= x >> 33;
+  return x;
+}
+
+template <typename T>
+__device__ void gpu_hyperloglog(const T *__restrict__ data, uint64_t len,
+                                unsigned int precision,
+                                unsigned int *__restrict__ registers) {
+  extern __shared__ char hyperloglog_shared[];
+  unsigned int *block_registers =
+      reinterpret_cast<unsigned int *>(hyperloglog_shared);
+  unsigned int const num_registers = 1U << precision;
+
+  for (unsigned int i = threadIdx.x; i < num_registers; i += blockDim.x) {
+    block_registers[i] = 0U;
+  }
+  __syncthreads();
+
+  uint64_t const stride = static_cast<uint64_t>(blockDim.x) * gridDim.x;
+  for (uint64_t i = static_cast<uint64_t>(blockIdx.x) * blockDim.x +
+                    threadIdx.x;
+       i < len; i += stride) {
+    // Sign-extend the key, as the CPU converts the key into an i64
+    long long key = static_cast<long long>(data[i]);
+    uint64_t hash = hyperloglog_mix64(static_cast<uint64_t>(key));
+
+    unsigned int index = static_cast<unsigned int>(hash >> (64U - precision));
+    uint64_t rest = hash << precision;
+    unsigned int rank =
+        rest == 0ULL ? 65U - precision
+                     : static_cast<unsigned int>(
+                           __clzll(static_cast<long long>(rest))) + 1U;
+
+    atomicMax(&block_registers[index], rank);
+  }
+  __syncthreads();
+
+  for (unsigned int i = threadIdx.x; i < num_registers; i += blockDim.x) {
+    unsigned int rank = block_registers[i];
+    if (rank != 0U) {
+      atomicMax(&registers[i], rank);
+    }
+  }
+}
+
+extern "C" __global__ void gpu_hyperloglog_int32(
+    const int *__restrict__ data, uint64_t len, unsigned int precision,
+    unsigned int *__restrict__ registers) {
+  gpu_hyperloglog(data, len, precision, registers);
+}
+
+extern "C" __global__ void gpu_hyperloglog_int64(
+    const long long *__restrict__ data, uint64_t len, unsigned int precision,
+    unsigned int *__restrict__ registers) {
+  gpu_hyperloglog(data, len, precision, registers);
+}
@@ -36,6 +36,51 @@
 typedef unsigned int uint32_t;
 typedef unsigned long long int uint64_t;
 
+/*
+ * Granularity of the probe's aggregation result buffer.
+ *
+ * Note: must match the discriminants of `CountGranularity` in Rust.
+ */
+enum CountGranularity : uint32_t { PerThread = 0, PerBlock = 1, Global = 2 };
+
+/*
+ * Adds a thread's partial aggregate to the aggregation result.
+ *
+ * Per-thread entries are private to each thread and need no atomics. Per-block
+ * and global entries are shared, thus threads without matches skip the atomic
+ * to reduce contention.
+ */
+__device__ __forceinline__ void gpu_aggregate_result(
+    uint64_t *__restrict__ aggregation_result, uint64_t partial,
+    uint32_t count_granularity) {
+  switch (count_granularity) {
+    case PerBlock:
+      if (partial != 0) {
+        atomicAdd(&aggregation_result[blockIdx.x], partial);
+      }
+      break;
+    case Global:
+      if (partial != 0) {
+        atomicAdd(aggregation_result, partial);
+      }
+      break;
+    default:
+      aggregation_result[blockIdx.x * blockDim.x + threadIdx.x] += partial;
+  }
+}
+
+/*
+ * Marks the probe tuple as matched in the semi-join bitmap.
+ *
+ * Neighboring tuples are probed by different threads, but share a bitmap word.
+ * Thus, the update must be atomic.
+ */
+__device__ __forceinline__ void gpu_set_bitmap_bit(
+    uint64_t *__restrict__ bitmap, uint64_t tuple_id) {
+  atomicOr(&bitmap[tuple_id / 64ULL], 1ULL << (tuple_id % 64ULL));
+}
+
+template <ProbeStep S = ProbeStep::Linear>
 __device__ void gpu_ht_insert_linearprobing_int32(
     HtEntry<int, int> *const __restrict__ hash_table,
     unsigned int log2_hash_table_entries, int key, int payload) {
@@ -45,7 +90,7 @@ __device__ void gpu_ht_insert_linearprobing_int32(
   uint64_t hash_table_mask = hash_table_entries - 1ULL;
 
   for (uint64_t i = 0; i < hash_table_entries;
-       ++i, index = (index + 1ULL) & hash_table_mask) {
+       index = probe_next<S>(index, i, hash_table_mask), ++i) {
 #ifdef OPTIMIZE_INT32
     uint64_t null_key_64 = static_cast<uint64_t>(null_key<long long>());
     // Negative int32_t is promoted when cast to uint64_t,
@@ -70,6 +115,7 @@ __device__ void gpu_ht_insert_linearprobing_int32(
   }
 }
 
+template <ProbeStep S = ProbeStep::Linear>
 __device__ void gpu_ht_insert_linearprobing_int64(
     HtEntry<long long, long long> *const __restrict__ hash_table,
     unsigned int log2_hash_table_entries, long long key, long long payload) {
@@ -80,7 +126,7 @@ __device__ void gpu_ht_insert_linearprobing_int64(
   uint64_t hash_table_mask = hash_table_entries - 1ULL;
 
   for (uint64_t i = 0; i < hash_table_entries;
-       ++i, index = (index + 1ULL) & hash_table_mask) {
+       index = probe_next<S>(index, i, hash_table_mask), ++i) {
     unsigned long long int null_key_u =
         static_cast<unsigned long long>(null_key<long long>());
     long long old = static_cast<long long>(atomicCAS(
@@ -93,7 +139,15 @@ __device__ void gpu_ht_insert_linearprobing_int64(
   }
 }
 
-extern "C" __global__ void gpu_ht_build_linearprobing_int32(
+/*
+ * Linear probing with a configurable probe step
+ *
+ * The `quadraticprobing` kernels use the same hash table as linear probing,
+ * but advance with quadratic steps, see `probe_next`. Thus, a table must be
+ * built and probed with the same step.
+ */
+template <ProbeStep S>
+__device__ void gpu_ht_build_linearprobing_int32_step(
     HtEntry<int, int> *const __restrict__ hash_table,
     uint64_t const hash_table_entries,
     const int *const __restrict__ join_attr_data,
@@ -106,13 +160,36 @@ extern "C" __global__ void gpu_ht_build_linearprobing_int32(
 
   for (uint64_t tuple_id = global_idx; tuple_id < data_length;
        tuple_id += global_threads) {
-    gpu_ht_insert_linearprobing_int32(hash_table, log2_hash_table_entries,
-                                      join_attr_data[tuple_id],
-                                      payload_attr_data[tuple_id]);
+    gpu_ht_insert_linearprobing_int32<S>(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        payload_or(payload_attr_data, tuple_id, 0));
   }
 }
 
-extern "C" __global__ void gpu_ht_build_linearprobing_int64(
+extern "C" __global__ void gpu_ht_build_linearprobing_int32(
+    HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  gpu_ht_build_linearprobing_int32_step<ProbeStep::Linear>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length);
+}
+
+extern "C" __global__ void gpu_ht_build_quadraticprobing_int32(
+    HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  gpu_ht_build_linearprobing_int32_step<ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length);
+}
+
+template <ProbeStep S>
+__device__ void gpu_ht_build_linearprobing_int64_step(
     HtEntry<long long, long long> *const __restrict__ hash_table,
     uint64_t const hash_table_entries,
     const long long *const __restrict__ join_attr_data,
@@ -125,35 +202,68 @@ extern "C" __global__ void gpu_ht_build_linearprobing_int64(
 
   for (uint64_t tuple_id = global_idx; tuple_id < data_length;
        tuple_id += global_threads) {
-    gpu_ht_insert_linearprobing_int64(hash_table, log2_hash_table_entries,
-                                      join_attr_data[tuple_id],
-                                      payload_attr_data[tuple_id]);
+    gpu_ht_insert_linearprobing_int64<S>(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        payload_or(payload_attr_data, tuple_id, 0LL));
   }
 }
 
+extern "C" __global__ void gpu_ht_build_linearprobing_int64(
+    HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  gpu_ht_build_linearprobing_int64_step<ProbeStep::Linear>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length);
+}
+
+extern "C" __global__ void gpu_ht_build_quadraticprobing_int64(
+    HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  gpu_ht_build_linearprobing_int64_step<ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length);
+}
+
+template <ProbeStep S = ProbeStep::Linear>
 __device__ bool gpu_ht_findkey_linearprobing_int32(
     const HtEntry<int, int> *const __restrict__ hash_table,
     unsigned int log2_hash_table_entries, int key, int *found_payload,
-    uint64_t *__restrict__ last_index, bool use_last_index) {
+    uint64_t *__restrict__ last_index, bool use_last_index,
+    bool read_only_cache, uint64_t *__restrict__ last_step = nullptr) {
   uint64_t hash_table_entries = 1ULL << log2_hash_table_entries;
   uint64_t hash_table_mask = hash_table_entries - 1ULL;
 
   uint64_t index = 0;
+  uint64_t step = 0;
   if (use_last_index) {
-    index = *last_index;
-    index = (index + 1ULL) & hash_table_mask;
+    step = S == ProbeStep::Quadratic ? *last_step : 0ULL;
+    index = probe_next<S>(*last_index, step, hash_table_mask);
+    ++step;
   } else {
     index = hash<int>(key, log2_hash_table_entries);
   }
 
   for (uint64_t i = 0; i < hash_table_entries;
-       ++i, index = (index + 1ULL) & hash_table_mask) {
+       ++i, index = probe_next<S>(index, step, hash_table_mask), ++step) {
     HtEntry<int, int> entry;
-    entry.load(hash_table[index]);
+    if (read_only_cache) {
+      entry.load_readonly(hash_table[index]);
+    } else {
+      entry.load(hash_table[index]);
+    }
 
     if (entry.key == key) {
       *found_payload = entry.value;
       *last_index = index;
+      if (S == ProbeStep::Quadratic) {
+        *last_step = step;
+      }
       return true;
     } else if (entry.key == null_key<int>()) {
       return false;
@@ -163,30 +273,41 @@ __device__ bool gpu_ht_findkey_linearprobing_int32(
   return false;
 }
 
+template <ProbeStep S = ProbeStep::Linear>
 __device__ bool gpu_ht_findkey_linearprobing_int64(
     const HtEntry<long long, long long> *const __restrict__ hash_table,
     unsigned int log2_hash_table_entries, long long key,
     long long *found_payload, uint64_t *__restrict__ last_index,
-    bool use_last_index) {
+    bool use_last_index, bool read_only_cache,
+    uint64_t *__restrict__ last_step = nullptr) {
   uint64_t hash_table_entries = 1ULL << log2_hash_table_entries;
   uint64_t hash_table_mask = hash_table_entries - 1ULL;
 
   uint64_t index = 0;
+  uint64_t step = 0;
   if (use_last_index) {
-    index = *last_index;
-    index = (index + 1ULL) & hash_table_mask;
+    step = S == ProbeStep::Quadratic ? *last_step : 0ULL;
+    index = probe_next<S>(*last_index, step, hash_table_mask);
+    ++step;
   } else {
     index = hash<long long>(key, log2_hash_table_entries);
   }
 
   for (uint64_t i = 0; i < hash_table_entries;
-       ++i, index = (index + 1ULL) & hash_table_mask) {
+       ++i, index = probe_next<S>(index, step, hash_table_mask), ++step) {
     HtEntry<long long, long long> entry;
-    entry.load(hash_table[index]);
+    if (read_only_cache) {
+      entry.load_readonly(hash_table[index]);
+    } else {
+      entry.load(hash_table[index]);
+    }
 
     if (entry.key == key) {
       *found_payload = entry.value;
       *last_index = index;
+      if (S == ProbeStep::Quadratic) {
+        *last_step = step;
+      }
       return true;
     } else if (entry.key == null_key<long long>()) {
       return false;
@@ -196,30 +317,92 @@ __device__ bool gpu_ht_findkey_linearprobing_int64(
   return false;
 }
 
-extern "C" __global__ void gpu_ht_probe_aggregate_linearprobing_int32(
+template <ProbeStep S>
+__device__ void gpu_ht_probe_aggregate_linearprobing_int32_step(
     const HtEntry<int, int> *const __restrict__ hash_table,
     uint64_t const hash_table_entries,
     const int *const __restrict__ join_attr_data,
     const int *const __restrict__ payload_attr_data, uint64_t const data_length,
-    uint64_t *__restrict__ aggregation_result) {
+    uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
   const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
   const uint32_t global_threads = blockDim.x * gridDim.x;
   const unsigned int log2_hash_table_entries =
       log2_floor_power_of_two(hash_table_entries);
 
+  uint64_t partial = 0;
   for (uint64_t tuple_id = global_idx; tuple_id < data_length;
        tuple_id += global_threads) {
     int hash_table_payload = 0;
     uint64_t hash_table_last_index = 0;
+    uint64_t hash_table_last_step = 0;
     bool hash_table_use_last_index = false;
-    while (gpu_ht_findkey_linearprobing_int32(
+    while (gpu_ht_findkey_linearprobing_int32<S>(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_payload, &hash_table_last_index,
+        hash_table_use_last_index, read_only_cache, &hash_table_last_step)) {
+      hash_table_use_last_index = true;
+      partial += payload_or(payload_attr_data, tuple_id, 1);
+    }
+  }
+
+  gpu_aggregate_result(aggregation_result, partial, count_granularity);
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_linearprobing_int32(
+    const HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
+  gpu_ht_probe_aggregate_linearprobing_int32_step<ProbeStep::Linear>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, aggregation_result, count_granularity, read_only_cache);
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_quadraticprobing_int32(
+    const HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
+  gpu_ht_probe_aggregate_linearprobing_int32_step<ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, aggregation_result, count_granularity, read_only_cache);
+}
+
+template <ProbeStep S>
+__device__ void gpu_ht_probe_aggregate_linearprobing_int64_step(
+    const HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  uint64_t partial = 0;
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length;
+       tuple_id += global_threads) {
+    long long hash_table_payload = 0;
+    uint64_t hash_table_last_index = 0;
+    uint64_t hash_table_last_step = 0;
+    bool hash_table_use_last_index = false;
+    while (gpu_ht_findkey_linearprobing_int64<S>(
         hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
         &hash_table_payload, &hash_table_last_index,
-        hash_table_use_last_index)) {
+        hash_table_use_last_index, read_only_cache, &hash_table_last_step)) {
       hash_table_use_last_index = true;
-      aggregation_result[global_idx] += payload_attr_data[tuple_id];
+      partial += payload_or(payload_attr_data, tuple_id, 1LL);
     }
   }
+
+  gpu_aggregate_result(aggregation_result, partial, count_granularity);
 }
 
 extern "C" __global__ void gpu_ht_probe_aggregate_linearprobing_int64(
@@ -227,23 +410,508 @@ extern "C" __global__ void gpu_ht_probe_aggregate_linearprobing_int64(
     uint64_t const hash_table_entries,
     const long long *const __restrict__ join_attr_data,
     const long long *const __restrict__ payload_attr_data,
-    uint64_t const data_length, uint64_t *__restrict__ aggregation_result) {
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
+  gpu_ht_probe_aggregate_linearprobing_int64_step<ProbeStep::Linear>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, aggregation_result, count_granularity, read_only_cache);
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_quadraticprobing_int64(
+    const HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
+  gpu_ht_probe_aggregate_linearprobing_int64_step<ProbeStep::Quadratic>(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, aggregation_result, count_granularity, read_only_cache);
+}
+
+/*
+ * Linear probing with the hash table in shared memory
+ *
+ * A tiny hash table fits into the shared memory of a thread block. Each block
+ * first copies the hash table from global memory into its shared memory, and
+ * then probes only its private copy. Thus, the probes don't access the hash
+ * table in global memory. The launch must allocate the hash table's size as
+ * dynamic shared memory.
+ */
+template <typename K, typename V>
+__device__ void gpu_ht_copy_to_shared(
+    HtEntry<K, V> *const __restrict__ shared_table,
+    const HtEntry<K, V> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries) {
+  for (uint64_t i = threadIdx.x; i < hash_table_entries; i += blockDim.x) {
+    shared_table[i] = hash_table[i];
+  }
+
+  __syncthreads();
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_linearprobing_shared_int32(
+    const HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity) {
+  extern __shared__ uint32_t shared_mem[];
+
+  HtEntry<int, int> *const __restrict__ shared_table =
+      reinterpret_cast<HtEntry<int, int> *>(shared_mem);
+  gpu_ht_copy_to_shared(shared_table, hash_table, hash_table_entries);
+
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  uint64_t partial = 0;
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length;
+       tuple_id += global_threads) {
+    int hash_table_payload = 0;
+    uint64_t hash_table_last_index = 0;
+    bool hash_table_use_last_index = false;
+    while (gpu_ht_findkey_linearprobing_int32(
+        shared_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_payload, &hash_table_last_index,
+        hash_table_use_last_index, false)) {
+      hash_table_use_last_index = true;
+      partial += payload_or(payload_attr_data, tuple_id, 1);
+    }
+  }
+
+  gpu_aggregate_result(aggregation_result, partial, count_granularity);
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_linearprobing_shared_int64(
+    const HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity) {
+  extern __shared__ uint32_t shared_mem[];
+
+  HtEntry<long long, long long> *const __restrict__ shared_table =
+      reinterpret_cast<HtEntry<long long, long long> *>(shared_mem);
+  gpu_ht_copy_to_shared(shared_table, hash_table, hash_table_entries);
+
   const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
   const uint32_t global_threads = blockDim.x * gridDim.x;
   const unsigned int log2_hash_table_entries =
       log2_floor_power_of_two(hash_table_entries);
 
+  uint64_t partial = 0;
   for (uint64_t tuple_id = global_idx; tuple_id < data_length;
        tuple_id += global_threads) {
     long long hash_table_payload = 0;
     uint64_t hash_table_last_index = 0;
     bool hash_table_use_last_index = false;
     while (gpu_ht_findkey_linearprobing_int64(
+        shared_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_payload, &hash_table_last_index,
+        hash_table_use_last_index, false)) {
+      hash_table_use_last_index = true;
+      partial += payload_or(payload_attr_data, tuple_id, 1LL);
+    }
+  }
+
+  gpu_aggregate_result(aggregation_result, partial, count_granularity);
+}
+
+/*
+ * Linear probing with a structure-of-arrays (SoA) hash table layout
+ *
+ * The hash table memory holds an array of `hash_table_entries` keys, followed
+ * by an array of `hash_table_entries` values. As the keys are contiguous, a
+ * probe that only compares keys doesn't load the values, and neighboring
+ * slots share a cache line.
+ */
+
+__device__ __forceinline__ int gpu_atomic_cas_key(int *address, int compare,
+                                                  int val) {
+  return static_cast<int>(atomicCAS(reinterpret_cast<unsigned int *>(address),
+                                    static_cast<unsigned int>(compare),
+                                    static_cast<unsigned int>(val)));
+}
+
+__device__ __forceinline__ long long gpu_atomic_cas_key(long long *address,
+                                                        long long compare,
+                                                        long long val) {
+  return static_cast<long long>(
+      atomicCAS(reinterpret_cast<unsigned long long *>(address),
+                static_cast<unsigned long long>(compare),
+                static_cast<unsigned long long>(val)));
+}
+
+template <typename T>
+__device__ void gpu_ht_insert_linearprobing_soa(
+    T *const __restrict__ keys, T *const __restrict__ values,
+    unsigned int log2_hash_table_entries, T key, T payload) {
+  uint64_t index =
+      static_cast<uint64_t>(hash<T>(key, log2_hash_table_entries));
+
+  uint64_t hash_table_entries = 1ULL << log2_hash_table_entries;
+  uint64_t hash_table_mask = hash_table_entries - 1ULL;
+
+  for (uint64_t i = 0; i < hash_table_entries;
+       ++i, index = (index + 1ULL) & hash_table_mask) {
+    T old = gpu_atomic_cas_key(&keys[index], null_key<T>(), key);
+    if (old == null_key<T>()) {
+      values[index] = payload;
+      return;
+    }
+  }
+}
+
+template <typename T>
+__device__ void gpu_ht_build_linearprobing_soa(
+    T *const __restrict__ hash_table, uint64_t const hash_table_entries,
+    const T *const __restrict__ join_attr_data,
+    const T *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+  T *const values = hash_table + hash_table_entries;
+
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length;
+       tuple_id += global_threads) {
+    T payload = payload_or(payload_attr_data, tuple_id, T(0));
+    gpu_ht_insert_linearprobing_soa(hash_table, values,
+                                    log2_hash_table_entries,
+                                    join_attr_data[tuple_id], payload);
+  }
+}
+
+template <typename T>
+__device__ bool gpu_ht_findkey_linearprobing_soa(
+    const T *const __restrict__ keys, unsigned int log2_hash_table_entries,
+    T key, uint64_t *__restrict__ last_index, bool use_last_index,
+    bool read_only_cache) {
+  uint64_t hash_table_entries = 1ULL << log2_hash_table_entries;
+  uint64_t hash_table_mask = hash_table_entries - 1ULL;
+
+  uint64_t index = 0;
+  if (use_last_index) {
+    index = *last_index;
+    index = (index + 1ULL) & hash_table_mask;
+  } else {
+    index = static_cast<uint64_t>(hash<T>(key, log2_hash_table_entries));
+  }
+
+  for (uint64_t i = 0; i < hash_table_entries;
+       ++i, index = (index + 1ULL) & hash_table_mask) {
+    T entry_key = read_only_cache ? __ldg(&keys[index]) : keys[index];
+
+    if (entry_key == key) {
+      *last_index = index;
+      return true;
+    } else if (entry_key == null_key<T>()) {
+      return false;
+    }
+  }
+
+  return false;
+}
+
+template <typename T>
+__device__ void gpu_ht_probe_aggregate_linearprobing_soa(
+    const T *const __restrict__ hash_table, uint64_t const hash_table_entries,
+    const T *const __restrict__ join_attr_data,
+    const T *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  uint64_t partial = 0;
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length;
+       tuple_id += global_threads) {
+    uint64_t hash_table_last_index = 0;
+    bool hash_table_use_last_index = false;
+    while (gpu_ht_findkey_linearprobing_soa(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_last_index, hash_table_use_last_index, read_only_cache)) {
+      hash_table_use_last_index = true;
+      partial += payload_or(payload_attr_data, tuple_id, T(1));
+    }
+  }
+
+  gpu_aggregate_result(aggregation_result, partial, count_granularity);
+}
+
+extern "C" __global__ void gpu_ht_build_linearprobing_soa_int32(
+    int *const __restrict__ hash_table, uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  gpu_ht_build_linearprobing_soa(hash_table, hash_table_entries,
+                                 join_attr_data, payload_attr_data,
+                                 data_length);
+}
+
+extern "C" __global__ void gpu_ht_build_linearprobing_soa_int64(
+    long long *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length) {
+  gpu_ht_build_linearprobing_soa(hash_table, hash_table_entries,
+                                 join_attr_data, payload_attr_data,
+                                 data_length);
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_linearprobing_soa_int32(
+    const int *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data, uint64_t const data_length,
+    uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
+  gpu_ht_probe_aggregate_linearprobing_soa(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, aggregation_result, count_granularity, read_only_cache);
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_linearprobing_soa_int64(
+    const long long *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
+  gpu_ht_probe_aggregate_linearprobing_soa(
+      hash_table, hash_table_entries, join_attr_data, payload_attr_data,
+      data_length, aggregation_result, count_granularity, read_only_cache);
+}
+
+extern "C" __global__ void gpu_ht_probe_bitmap_linearprobing_int32(
+    const HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data, uint64_t const data_length,
+    uint64_t *__restrict__ bitmap) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length;
+       tuple_id += global_threads) {
+    int hash_table_payload = 0;
+    uint64_t hash_table_last_index = 0;
+    if (gpu_ht_findkey_linearprobing_int32(
+            hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+            &hash_table_payload, &hash_table_last_index, false, false)) {
+      gpu_set_bitmap_bit(bitmap, tuple_id);
+    }
+  }
+}
+
+extern "C" __global__ void gpu_ht_probe_bitmap_linearprobing_int64(
+    const HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    uint64_t const data_length, uint64_t *__restrict__ bitmap) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length;
+       tuple_id += global_threads) {
+    long long hash_table_payload = 0;
+    uint64_t hash_table_last_index = 0;
+    if (gpu_ht_findkey_linearprobing_int64(
+            hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+            &hash_table_payload, &hash_table_last_index, false, false)) {
+      gpu_set_bitmap_bit(bitmap, tuple_id);
+    }
+  }
+}
+
+/*
+ * Materializing probe
+ *
+ * Without write offsets, matches are appended to the join result with an
+ * atomic counter. The output order thus depends on the thread schedule.
+ *
+ * With write offsets, each probe tuple writes its matches starting at its own
+ * offset. The offsets are the exclusive prefix sum of the match counts from
+ * the count kernel. The join result is then in probe relation order, and is
+ * identical to the result of the sequential CPU probe.
+ *
+ * Matches beyond the result capacity are not written. With `saturate` set,
+ * the output cursor stops at one past the capacity, and each thread stops
+ * probing its remaining tuples as soon as it finds the join result full. The
+ * cursor then signals an overflow without counting all surplus matches.
+ */
+
+// Increments the output cursor, but not beyond `capacity + 1`.
+//
+// Returns the cursor's value before the increment.
+__device__ __forceinline__ uint64_t
+gpu_saturating_increment(uint64_t *cursor, uint64_t const capacity) {
+  uint64_t old = *cursor;
+  while (old <= capacity) {
+    uint64_t assumed = old;
+    old = atomicCAS(reinterpret_cast<unsigned long long *>(cursor),
+                    assumed, assumed + 1ULL);
+    if (old == assumed) {
+      return assumed;
+    }
+  }
+  return old;
+}
+
+extern "C" __global__ void gpu_ht_probe_count_linearprobing_int32(
+    const HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data, uint64_t const data_length,
+    uint64_t *__restrict__ match_counts) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length;
+       tuple_id += global_threads) {
+    int hash_table_payload = 0;
+    uint64_t hash_table_last_index = 0;
+    bool hash_table_use_last_index = false;
+    uint64_t count = 0;
+    while (gpu_ht_findkey_linearprobing_int32(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_payload, &hash_table_last_index,
+        hash_table_use_last_index, false)) {
+      hash_table_use_last_index = true;
+      ++count;
+    }
+    match_counts[tuple_id] = count;
+  }
+}
+
+extern "C" __global__ void gpu_ht_probe_materialize_linearprobing_int32(
+    const HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const int *const __restrict__ join_attr_data,
+    const int *const __restrict__ payload_attr_data,
+    uint64_t const data_length,
+    const uint64_t *const __restrict__ write_offsets,
+    JoinMatch<int> *__restrict__ join_result,
+    uint64_t const join_result_capacity,
+    uint64_t *__restrict__ join_result_len, uint32_t const saturate) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  bool full = false;
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length && !full;
+       tuple_id += global_threads) {
+    int hash_table_payload = 0;
+    uint64_t hash_table_last_index = 0;
+    bool hash_table_use_last_index = false;
+    uint64_t offset = write_offsets ? write_offsets[tuple_id] : 0;
+    while (gpu_ht_findkey_linearprobing_int32(
         hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
         &hash_table_payload, &hash_table_last_index,
-        hash_table_use_last_index)) {
+        hash_table_use_last_index, false)) {
       hash_table_use_last_index = true;
-      aggregation_result[global_idx] += payload_attr_data[tuple_id];
+      uint64_t pos;
+      if (write_offsets) {
+        pos = offset++;
+      } else if (saturate) {
+        pos = gpu_saturating_increment(join_result_len, join_result_capacity);
+      } else {
+        pos = atomicAdd(join_result_len, 1ULL);
+      }
+      if (pos < join_result_capacity) {
+        join_result[pos].build_payload = hash_table_payload;
+        join_result[pos].probe_payload = payload_attr_data[tuple_id];
+      } else if (saturate) {
+        full = true;
+        break;
+      }
+    }
+  }
+}
+
+extern "C" __global__ void gpu_ht_probe_count_linearprobing_int64(
+    const HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    uint64_t const data_length, uint64_t *__restrict__ match_counts) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length;
+       tuple_id += global_threads) {
+    long long hash_table_payload = 0;
+    uint64_t hash_table_last_index = 0;
+    bool hash_table_use_last_index = false;
+    uint64_t count = 0;
+    while (gpu_ht_findkey_linearprobing_int64(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_payload, &hash_table_last_index,
+        hash_table_use_last_index, false)) {
+      hash_table_use_last_index = true;
+      ++count;
+    }
+    match_counts[tuple_id] = count;
+  }
+}
+
+extern "C" __global__ void gpu_ht_probe_materialize_linearprobing_int64(
+    const HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const hash_table_entries,
+    const long long *const __restrict__ join_attr_data,
+    const long long *const __restrict__ payload_attr_data,
+    uint64_t const data_length,
+    const uint64_t *const __restrict__ write_offsets,
+    JoinMatch<long long> *__restrict__ join_result,
+    uint64_t const join_result_capacity,
+    uint64_t *__restrict__ join_result_len, uint32_t const saturate) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+  const unsigned int log2_hash_table_entries =
+      log2_floor_power_of_two(hash_table_entries);
+
+  bool full = false;
+  for (uint64_t tuple_id = global_idx; tuple_id < data_length && !full;
+       tuple_id += global_threads) {
+    long long hash_table_payload = 0;
+    uint64_t hash_table_last_index = 0;
+    bool hash_table_use_last_index = false;
+    uint64_t offset = write_offsets ? write_offsets[tuple_id] : 0;
+    while (gpu_ht_findkey_linearprobing_int64(
+        hash_table, log2_hash_table_entries, join_attr_data[tuple_id],
+        &hash_table_payload, &hash_table_last_index,
+        hash_table_use_last_index, false)) {
+      hash_table_use_last_index = true;
+      uint64_t pos;
+      if (write_offsets) {
+        pos = offset++;
+      } else if (saturate) {
+        pos = gpu_saturating_increment(join_result_len, join_result_capacity);
+      } else {
+        pos = atomicAdd(join_result_len, 1ULL);
+      }
+      if (pos < join_result_capacity) {
+        join_result[pos].build_payload = hash_table_payload;
+        join_result[pos].probe_payload = payload_attr_data[tuple_id];
+      } else if (saturate) {
+        full = true;
+        break;
+      }
     }
   }
 }
@@ -260,7 +928,7 @@ extern "C" __global__ void gpu_ht_build_perfect_int32(
   for (uint64_t i = global_idx; i < data_length; i += global_threads) {
     HtEntry<int, int> tuple;
     tuple.key = join_attribute_data[i];
-    tuple.value = payload_attributed_data[i];
+    tuple.value = payload_or(payload_attributed_data, i, 0);
 
     tuple.store(hash_table[tuple.key]);
   }
@@ -280,7 +948,7 @@ extern "C" __global__ void gpu_ht_build_selective_perfect_int32(
     if (key != null_key<int>()) {
       HtEntry<int, int> tuple;
       tuple.key = key;
-      tuple.value = payload_attributed_data[i];
+      tuple.value = payload_or(payload_attributed_data, i, 0);
 
       tuple.store(hash_table[key]);
     }
@@ -299,7 +967,7 @@ extern "C" __global__ void gpu_ht_build_perfect_int64(
   for (uint64_t i = global_idx; i < data_length; i += global_threads) {
     HtEntry<long long, long long> tuple;
     tuple.key = join_attribute_data[i];
-    tuple.value = payload_attributed_data[i];
+    tuple.value = payload_or(payload_attributed_data, i, 0LL);
 
     tuple.store(hash_table[tuple.key]);
   }
@@ -319,7 +987,7 @@ extern "C" __global__ void gpu_ht_build_selective_perfect_int64(
     if (key != null_key<long long>()) {
       HtEntry<long long, long long> tuple;
       tuple.key = key;
-      tuple.value = payload_attributed_data[i];
+      tuple.value = payload_or(payload_attributed_data, i, 0LL);
 
       tuple.store(hash_table[key]);
     }
@@ -331,25 +999,31 @@ extern "C" __global__ void gpu_ht_probe_aggregate_perfect_int32(
     uint64_t const /* hash_table_entries */,
     const int *const __restrict__ join_attribute_data,
     const int *const __restrict__ payload_attribute_data,
-    uint64_t const data_length, uint64_t *__restrict__ aggregation_result) {
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
   const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
   const uint32_t global_threads = blockDim.x * gridDim.x;
 
+  uint64_t partial = 0;
   for (uint64_t i = global_idx; i < data_length; i += global_threads) {
     int key = join_attribute_data[i];
+    int hash_table_key =
+        read_only_cache ? __ldg(&hash_table[key].key) : hash_table[key].key;
 
 #ifdef PREDICATED_AGGREGATION
-    int condition = hash_table[key].key == key;
+    int condition = hash_table_key == key;
     condition = (condition << 31) >> 31;
 
-    int payload = condition & payload_attribute_data[i];
-    aggregation_result[global_idx] += static_cast<uint64_t>(payload);
+    int payload = condition & payload_or(payload_attribute_data, i, 1);
+    partial += static_cast<uint64_t>(payload);
 #else
-    if (hash_table[key].key == key) {
-      aggregation_result[global_idx] += payload_attribute_data[i];
+    if (hash_table_key == key) {
+      partial += payload_or(payload_attribute_data, i, 1);
     }
 #endif /* PREDICATED_AGGREGATION */
   }
+
+  gpu_aggregate_result(aggregation_result, partial, count_granularity);
 }
 
 extern "C" __global__ void gpu_ht_probe_aggregate_perfect_int64(
@@ -357,23 +1031,176 @@ extern "C" __global__ void gpu_ht_probe_aggregate_perfect_int64(
     uint64_t const /* hash_table_entries */,
     const long long *const __restrict__ join_attribute_data,
     const long long *const __restrict__ payload_attribute_data,
-    uint64_t const data_length, uint64_t *__restrict__ aggregation_result) {
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity, uint32_t const read_only_cache) {
   const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
   const uint32_t global_threads = blockDim.x * gridDim.x;
 
+  uint64_t partial = 0;
   for (uint64_t i = global_idx; i < data_length; i += global_threads) {
     long long key = join_attribute_data[i];
+    long long hash_table_key =
+        read_only_cache ? __ldg(&hash_table[key].key) : hash_table[key].key;
 
 #ifdef PREDICATED_AGGREGATION
-    long long condition = hash_table[key].key == key;
+    long long condition = hash_table_key == key;
     condition = (condition << 63) >> 63;
 
-    long long payload = condition & payload_attribute_data[i];
-    aggregation_result[global_idx] += static_cast<uint64_t>(payload);
+    long long payload =
+        condition & payload_or(payload_attribute_data, i, 1LL);
+    partial += static_cast<uint64_t>(payload);
 #else
-    if (hash_table[key].key == key) {
-      aggregation_result[global_idx] += payload_attribute_data[i];
+    if (hash_table_key == key) {
+      partial += payload_or(payload_attribute_data, i, 1LL);
     }
 #endif /* PREDICATED_AGGREGATION */
   }
+
+  gpu_aggregate_result(aggregation_result, partial, count_granularity);
+}
+
+extern "C" __global__ void gpu_ht_probe_bitmap_perfect_int32(
+    const HtEntry<int, int> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const int *const __restrict__ join_attribute_data,
+    uint64_t const data_length, uint64_t *__restrict__ bitmap) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+
+  for (uint64_t i = global_idx; i < data_length; i += global_threads) {
+    int key = join_attribute_data[i];
+    if (hash_table[key].key == key) {
+      gpu_set_bitmap_bit(bitmap, i);
+    }
+  }
+}
+
+extern "C" __global__ void gpu_ht_probe_bitmap_perfect_int64(
+    const HtEntry<long long, long long> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const long long *const __restrict__ join_attribute_data,
+    uint64_t const data_length, uint64_t *__restrict__ bitmap) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+
+  for (uint64_t i = global_idx; i < data_length; i += global_threads) {
+    long long key = join_attribute_data[i];
+    if (hash_table[key].key == key) {
+      gpu_set_bitmap_bit(bitmap, i);
+    }
+  }
+}
+
+/*
+ * Perfect hashing for narrow keys, e.g., dictionary-encoded columns.
+ *
+ * The key domain is small enough to directly address the hash table. Thus, the
+ * hash table must have an entry for each value in the key domain. As the NULL
+ * key is part of the domain, the probe explicitly skips NULL keys.
+ */
+template <typename T>
+__device__ void gpu_ht_build_perfect_narrow(
+    HtEntry<T, T> *const __restrict__ hash_table,
+    const T *const __restrict__ join_attribute_data,
+    const T *const __restrict__ payload_attributed_data,
+    uint64_t const data_length, bool is_selective) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+
+  for (uint64_t i = global_idx; i < data_length; i += global_threads) {
+    T key = join_attribute_data[i];
+    if (!is_selective || key != null_key<T>()) {
+      HtEntry<T, T> tuple;
+      tuple.key = key;
+      tuple.value = payload_or(payload_attributed_data, i, T(0));
+
+      hash_table[key] = tuple;
+    }
+  }
+}
+
+template <typename T>
+__device__ void gpu_ht_probe_aggregate_perfect_narrow(
+    const HtEntry<T, T> *const __restrict__ hash_table,
+    const T *const __restrict__ join_attribute_data,
+    const T *const __restrict__ payload_attribute_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity) {
+  const uint32_t global_idx = blockIdx.x * blockDim.x + threadIdx.x;
+  const uint32_t global_threads = blockDim.x * gridDim.x;
+
+  uint64_t partial = 0;
+  for (uint64_t i = global_idx; i < data_length; i += global_threads) {
+    T key = join_attribute_data[i];
+
+    if (key != null_key<T>() && hash_table[key].key == key) {
+      partial += payload_or(payload_attribute_data, i, T(1));
+    }
+  }
+
+  gpu_aggregate_result(aggregation_result, partial, count_granularity);
+}
+
+extern "C" __global__ void gpu_ht_build_perfect_uint8(
+    HtEntry<unsigned char, unsigned char> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const unsigned char *const __restrict__ join_attribute_data,
+    const unsigned char *const __restrict__ payload_attributed_data,
+    uint64_t const data_length) {
+  gpu_ht_build_perfect_narrow(hash_table, join_attribute_data,
+                              payload_attributed_data, data_length, false);
+}
+
+extern "C" __global__ void gpu_ht_build_selective_perfect_uint8(
+    HtEntry<unsigned char, unsigned char> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const unsigned char *const __restrict__ join_attribute_data,
+    const unsigned char *const __restrict__ payload_attributed_data,
+    uint64_t const data_length) {
+  gpu_ht_build_perfect_narrow(hash_table, join_attribute_data,
+                              payload_attributed_data, data_length, true);
+}
+
+extern "C" __global__ void gpu_ht_build_perfect_uint16(
+    HtEntry<unsigned short, unsigned short> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const unsigned short *const __restrict__ join_attribute_data,
+    const unsigned short *const __restrict__ payload_attributed_data,
+    uint64_t const data_length) {
+  gpu_ht_build_perfect_narrow(hash_table, join_attribute_data,
+                              payload_attributed_data, data_length, false);
+}
+
+extern "C" __global__ void gpu_ht_build_selective_perfect_uint16(
+    HtEntry<unsigned short, unsigned short> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const unsigned short *const __restrict__ join_attribute_data,
+    const unsigned short *const __restrict__ payload_attributed_data,
+    uint64_t const data_length) {
+  gpu_ht_build_perfect_narrow(hash_table, join_attribute_data,
+                              payload_attributed_data, data_length, true);
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_perfect_uint8(
+    const HtEntry<unsigned char, unsigned char> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const unsigned char *const __restrict__ join_attribute_data,
+    const unsigned char *const __restrict__ payload_attribute_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity) {
+  gpu_ht_probe_aggregate_perfect_narrow(hash_table, join_attribute_data,
+                                        payload_attribute_data, data_length,
+                                        aggregation_result, count_granularity);
+}
+
+extern "C" __global__ void gpu_ht_probe_aggregate_perfect_uint16(
+    const HtEntry<unsigned short, unsigned short> *const __restrict__ hash_table,
+    uint64_t const /* hash_table_entries */,
+    const unsigned short *const __restrict__ join_attribute_data,
+    const unsigned short *const __restrict__ payload_attribute_data,
+    uint64_t const data_length, uint64_t *__restrict__ aggregation_result,
+    uint32_t const count_granularity) {
+  gpu_ht_probe_aggregate_perfect_narrow(hash_table, join_attribute_data,
+                                        payload_attribute_data, data_length,
+                                        aggregation_result, count_granularity);
 }
@@ -0,0 +1,140 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*
+ * Multi-level exclusive prefix scan.
+ *
+ * In contrast to `device_exclusive_prefix_sum`, the scan doesn't require all
+ * thread blocks to be resident at the same time, and indexes the data with
+ * 64-bit integers. Thus, the scan supports arrays that are larger than a
+ * single grid can process at once.
+ *
+ * The scan consists of three steps:
+ *
+ * 1. `gpu_scan_tiles` scans each tile of 2 * blockDim.x values in place, and
+ *    writes the tile's total to `tile_sums`.
+ * 2. The host scans `tile_sums` recursively with the same kernels, until a
+ *    single tile remains.
+ * 3. `gpu_scan_add_tile_prefixes` adds the scanned tile sums to the tiles.
+ *
+ * Assumptions:
+ *
+ * blockDim.x is a power of two
+ * gridDim.x * 2 * blockDim.x >= len
+ * Shared memory holds 2 * blockDim.x values
+ */
+
+#include <gpu_common.h>
+
+typedef unsigned long long int uint64_t;
+
+// Work-efficient (Blelloch) exclusive scan of a tile in shared memory
+template <typename T>
+__device__ void gpu_scan_tiles(T *__restrict__ data, uint64_t len,
+                               T *__restrict__ tile_sums) {
+  extern __shared__ char scan_tiles_shared[];
+  T *tile = reinterpret_cast<T *>(scan_tiles_shared);
+
+  unsigned int const tile_size = 2U * blockDim.x;
+  uint64_t const base = static_cast<uint64_t>(tile_size) * blockIdx.x;
+  unsigned int const left = threadIdx.x;
+  unsigned int const right = threadIdx.x + blockDim.x;
+
+  // Padding with zero doesn't change the prefixes of the valid values
+  tile[left] = base + left < len ? data[base + left] : T(0);
+  tile[right] = base + right < len ? data[base + right] : T(0);
+
+  // Up sweep
+  unsigned int offset = 1U;
+  for (unsigned int d = blockDim.x; d > 0U; d >>= 1) {
+    __syncthreads();
+
+    if (threadIdx.x < d) {
+      unsigned int ai = offset * (2U * threadIdx.x + 1U) - 1U;
+      unsigned int bi = offset * (2U * threadIdx.x + 2U) - 1U;
+      tile[bi] += tile[ai];
+    }
+
+    offset *= 2U;
+  }
+
+  if (threadIdx.x == 0) {
+    tile_sums[blockIdx.x] = tile[tile_size - 1U];
+    tile[tile_size - 1U] = T(0);
+  }
+
+  // Down sweep
+  for (unsigned int d = 1U; d < tile_size; d *= 2U) {
+    offset >>= 1;
+
+    __syncthreads();
+
+    if (threadIdx.x < d) {
+      unsigned int ai = offset * (2U * threadIdx.x + 1U) - 1U;
+      unsigned int bi = offset * (2U * threadIdx.x + 2U) - 1U;
+
+      T t = tile[ai];
+      tile[ai] = tile[bi];
+      tile[bi] += t;
+    }
+  }
+
+  __syncthreads();
+
+  if (base + left < len) {
+    data[base + left] = tile[left];
+  }
+  if (base + right < len) {
+    data[base + right] = tile[right];
+  }
+}
+
+// Adds the exclusive prefix of each tile to the tile's values
+template <typename T>
+__device__ void gpu_scan_add_tile_prefixes(
+    T *__restrict__ data, uint64_t len,
+    const T *__restrict__ tile_prefixes) {
+  uint64_t const base = 2ULL * blockDim.x * blockIdx.x;
+  T const prefix = tile_prefixes[blockIdx.x];
+
+  for (unsigned int i = threadIdx.x; i < 2U * blockDim.x; i += blockDim.x) {
+    if (base + i < len) {
+      data[base + i] += prefix;
+    }
+  }
+}
+
+extern "C" __global__ void gpu_scan_tiles_uint32(
+    unsigned int *__restrict__ data, uint64_t len,
+    unsigned int *__restrict__ tile_sums) {
+  gpu_scan_tiles(data, len, tile_sums);
+}
+
+extern "C" __global__ void gpu_scan_tiles_uint64(
+    uint64_t *__restrict__ data, uint64_t len,
+    uint64_t *__restrict__ tile_sums) {
+  gpu_scan_tiles(data, len, tile_sums);
+}
+
+extern "C" __global__ void gpu_scan_add_tile_prefixes_uint32(
+    unsigned int *__restrict__ data, uint64_t len,
+    const unsigned int *__restrict__ tile_prefixes) {
+  gpu_scan_add_tile_prefixes(data, len, tile_prefixes);
+}
+
+extern "C" __global__ void gpu_scan_add_tile_prefixes_uint64(
+    uint64_t *__restrict__ data, uint64_t len,
+    const uint64_t *__restrict__ tile_prefixes) {
+  gpu_scan_add_tile_prefixes(data, len, tile_prefixes);
+}
@@ -39,6 +39,7 @@ struct JoinAggregateArgs {
   uint64_t const *const probe_rel_partition_offsets;
   int64_t *const aggregation_result;
   uint32_t *const task_assignments;
+  uint32_t *const overflow;
   uint32_t const build_rel_length;
   uint32_t const probe_rel_length;
   uint32_t const build_rel_padding_length;
@@ -128,8 +129,14 @@ __device__ void gpu_radix_join_aggregate_smem_perfect(JoinAggregateArgs &args) {
     uint32_t build_size = static_cast<uint32_t>(
         build_upper - args.build_rel_partition_offsets[p]);
 
-    assert(build_size <= args.ht_entries &&
-           "Build-side relation is larger than hash table");
+    // Skip a partition that doesn't fit, and flag the overflow for the host.
+    // All threads of the block skip the same partition.
+    if (build_size > args.ht_entries) {
+      if (threadIdx.x == 0) {
+        *args.overflow = 1U;
+      }
+      continue;
+    }
 
     uint32_t probe_upper = (p + 1U < fanout)
                                ? args.probe_rel_partition_offsets[p + 1U] -
@@ -193,8 +200,24 @@ __device__ void gpu_radix_join_aggregate_smem_perfect(JoinAggregateArgs &args) {
   args.aggregation_result[blockDim.x * blockIdx.x + threadIdx.x] += sum;
 }
 
+// Returns the number of bucket chaining hash table entries that fit into
+// shared memory next to the buckets.
+//
+// The entries are linked by 16-bit indices, and USHRT_MAX marks the end of a
+// chain. Thus, there are at most USHRT_MAX entries.
+template <typename K, typename PI>
+__device__ uint32_t gpu_chaining_ht_entries(uint32_t const shared_mem_bytes,
+                                            uint32_t const buckets) {
+  size_t ht_bytes = shared_mem_bytes - buckets * sizeof(unsigned int);
+  size_t entries = ht_bytes / (sizeof(unsigned short) + sizeof(K) + sizeof(PI));
+  return static_cast<uint32_t>(entries < USHRT_MAX ? entries : USHRT_MAX);
+}
+
 // Bucket chaining hash join in shared memory.
 //
+// The hash table is sized per partition. The number of buckets is halved
+// until the build partition fits into the remaining shared memory.
+//
 // See the Rust module for details.
 template <typename K, typename PI, typename PO>
 __device__ void gpu_radix_join_aggregate_smem_chaining(
@@ -205,30 +228,10 @@ __device__ void gpu_radix_join_aggregate_smem_chaining(
   const uint64_t mask = ~static_cast<uint64_t>((1U << args.ignore_bits) - 1U);
   constexpr unsigned short tail = USHRT_MAX;
 
-  const uint32_t buckets = args.ht_entries;
-  assert(buckets * sizeof(unsigned int) < shared_mem_bytes &&
+  const uint32_t max_buckets = args.ht_entries;
+  assert(max_buckets * sizeof(unsigned int) < shared_mem_bytes &&
          "The hash table buckets are larger than shared memory, reduce the "
          "ht_entries tuning parameter.");
-  size_t ht_bytes = shared_mem_bytes - buckets * sizeof(unsigned int);
-  const uint32_t ht_entries =
-      ht_bytes / (sizeof(unsigned short) + sizeof(K) + sizeof(PI));
-  assert(ht_entries >= 1 &&
-         "Number of hash table entries is too small; try reducing the number "
-         "of hash table buckets");
-  const unsigned int log2_buckets = log2_floor_power_of_two(buckets);
-#ifdef DEBUG
-  if (blockIdx.x == 0 && threadIdx.x == 0) {
-    printf("Number of HT buckets: %u, number of entries: %u\n", buckets,
-           ht_entries);
-  }
-#endif
-
-  K *const __restrict__ keys = reinterpret_cast<K *>(shared_mem);
-  PI *const __restrict__ values = reinterpret_cast<PI *>(&keys[ht_entries]);
-  unsigned int *const __restrict__ heads =
-      reinterpret_cast<unsigned int *>(&values[ht_entries]);
-  unsigned short *const __restrict__ links =
-      reinterpret_cast<unsigned short *>(&heads[buckets]);
 
   int64_t sum = 0;
 
@@ -248,8 +251,32 @@ __device__ void gpu_radix_join_aggregate_smem_chaining(
     uint32_t build_size = static_cast<uint32_t>(
         build_upper - args.build_rel_partition_offsets[p]);
 
-    assert(build_size <= ht_entries &&
-           "Build-side relation is larger than hash table");
+    // Size the hash table for the partition
+    uint32_t buckets = max_buckets;
+    uint32_t ht_entries =
+        gpu_chaining_ht_entries<K, PI>(shared_mem_bytes, buckets);
+    while (build_size > ht_entries && buckets > 1U) {
+      buckets /= 2U;
+      ht_entries = gpu_chaining_ht_entries<K, PI>(shared_mem_bytes, buckets);
+    }
+    const unsigned int log2_buckets = log2_floor_power_of_two(buckets);
+
+    // Skip a partition that doesn't fit even with a single bucket, and flag
+    // the overflow for the host. All threads of the block skip the same
+    // partition.
+    if (build_size > ht_entries) {
+      if (threadIdx.x == 0) {
+        *args.overflow = 1U;
+      }
+      continue;
+    }
+
+    K *const __restrict__ keys = reinterpret_cast<K *>(shared_mem);
+    PI *const __restrict__ values = reinterpret_cast<PI *>(&keys[ht_entries]);
+    unsigned int *const __restrict__ heads =
+        reinterpret_cast<unsigned int *>(&values[ht_entries]);
+    unsigned short *const __restrict__ links =
+        reinterpret_cast<unsigned short *>(&heads[buckets]);
 
     uint32_t probe_upper = (p + 1U < fanout)
                                ? args.probe_rel_partition_offsets[p + 1U] -
@@ -260,8 +287,9 @@ __device__ void gpu_radix_join_aggregate_smem_chaining(
 
 #ifdef DEBUG
     if (threadIdx.x == 0) {
-      printf("part: %d, fanout: %d, build_size: %d, probe_size: %d\n", p,
-             fanout, build_size, probe_size);
+      printf("part: %d, fanout: %d, build_size: %d, probe_size: %d, "
+             "buckets: %u, entries: %u\n",
+             p, fanout, build_size, probe_size, buckets, ht_entries);
     }
 #endif
 
@@ -524,6 +524,96 @@ __device__ void gpu_chunked_radix_partition(RadixPartitionArgs &args) {
   }
 }
 
+// Fused chunked prefix sum and non-cached radix partitioning.
+//
+// Computes the histogram and exclusive prefix sum of the thread block's chunk
+// in shared memory, and then scatters the chunk using the shared memory
+// offsets. The partition offsets are written out to device memory only as a
+// result, and are not read back before the scatter.
+//
+// See the Rust module for details.
+template <typename K, typename V>
+__device__ void gpu_chunked_prefix_sum_and_radix_partition(
+    PrefixSumAndPartitionArgs &args) {
+  extern __shared__ uint32_t shared_mem[];
+
+  const uint32_t fanout = 1U << args.radix_bits;
+  const uint64_t mask = static_cast<uint64_t>(fanout - 1U) << args.ignore_bits;
+  constexpr size_t input_align_mask =
+      ~(static_cast<size_t>(ALIGN_BYTES / sizeof(K)) - 1ULL);
+
+  // Calculate the data_length per block
+  size_t data_length =
+      ((args.data_length + gridDim.x - 1U) / gridDim.x) & input_align_mask;
+  size_t data_offset = data_length * blockIdx.x;
+  size_t partitioned_data_offset =
+      (data_length + args.padding_length * fanout) * blockIdx.x;
+  if (blockIdx.x + 1U == gridDim.x) {
+    data_length = args.data_length - data_offset;
+  }
+
+  auto join_attr_data =
+      reinterpret_cast<const K *>(args.join_attr_data) + data_offset;
+  auto payload_attr_data =
+      reinterpret_cast<const V *>(args.payload_attr_data) + data_offset;
+
+  // Offsets in shared memory are relative to the first partition of the
+  // chunk, which starts after one padding length. This keeps the offsets
+  // within the range of unsigned int for relations larger than 32 GiB.
+  auto partitioned_relation =
+      reinterpret_cast<Tuple<K, V> *>(args.partitioned_relation) +
+      partitioned_data_offset + args.padding_length;
+
+  unsigned int *const tmp_partition_offsets =
+      reinterpret_cast<unsigned int *>(shared_mem);
+  unsigned int *const prefix_tmp = &tmp_partition_offsets[fanout];
+
+  // Ensure counters are all zeroed.
+  for (uint32_t i = threadIdx.x; i < fanout; i += blockDim.x) {
+    tmp_partition_offsets[i] = 0;
+  }
+
+  __syncthreads();
+
+  // 1. Compute local histograms per partition for thread block.
+  for (size_t i = threadIdx.x; i < data_length; i += blockDim.x) {
+    auto key = join_attr_data[i];
+    auto p_index = key_to_partition(key, mask, args.ignore_bits);
+    atomicAdd(&tmp_partition_offsets[p_index], 1U);
+  }
+
+  __syncthreads();
+
+  // 2. Compute offsets with exclusive prefix sum for thread block.
+  block_exclusive_prefix_sum(tmp_partition_offsets, fanout, 0, prefix_tmp);
+
+  __syncthreads();
+
+  // 3. Write out the final offsets to device memory, and add the padding
+  // between partitions to the shared memory offsets.
+  for (uint32_t i = threadIdx.x; i < fanout; i += blockDim.x) {
+    uint32_t padded_offset = tmp_partition_offsets[i] + i * args.padding_length;
+    tmp_partition_offsets[i] = padded_offset;
+
+    args.partition_offsets[blockIdx.x * fanout + i] =
+        static_cast<uint64_t>(padded_offset) + partitioned_data_offset +
+        args.padding_length;
+  }
+
+  __syncthreads();
+
+  // 4. Partition data
+  for (size_t i = threadIdx.x; i < data_length; i += blockDim.x) {
+    Tuple<K, V> tuple;
+    tuple.key = join_attr_data[i];
+    tuple.value = payload_attr_data[i];
+
+    auto p_index = key_to_partition(tuple.key, mask, args.ignore_bits);
+    auto offset = atomicAdd(&tmp_partition_offsets[p_index], 1U);
+    tuple.store(partitioned_relation[offset]);
+  }
+}
+
 template <typename K, typename V>
 __device__ void gpu_chunked_laswwc_radix_partition(RadixPartitionArgs &args,
                                                    uint32_t shared_mem_bytes) {
@@ -1540,6 +1630,20 @@ extern "C" __launch_bounds__(1024, 2) __global__
   gpu_chunked_radix_partition<long long, long long>(args);
 }
 
+// Exports the fused partitioning function for 8-byte key/value tuples.
+extern "C" __launch_bounds__(1024, 2) __global__
+    void gpu_chunked_prefix_sum_and_radix_partition_int32_int32(
+        PrefixSumAndPartitionArgs args) {
+  gpu_chunked_prefix_sum_and_radix_partition<int, int>(args);
+}
+
+// Exports the fused partitioning function for 16-byte key/value tuples.
+extern "C" __launch_bounds__(1024, 2) __global__
+    void gpu_chunked_prefix_sum_and_radix_partition_int64_int64(
+        PrefixSumAndPartitionArgs args) {
+  gpu_chunked_prefix_sum_and_radix_partition<long long, long long>(args);
+}
+
 // Exports the partitioning function for 8-byte key/value tuples.
 extern "C" __launch_bounds__(1024, 1) __global__
     void gpu_chunked_laswwc_radix_partition_int32_int32(
@@ -0,0 +1,82 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*
+ * Deterministic tree reduction.
+ *
+ * Each thread block sums a segment of 2 * blockDim.x values in a fixed
+ * pairwise tree, and writes the segment's sum to `block_sums`. The host
+ * repeats the pass on the block sums until a single value remains.
+ *
+ * The order of the additions depends only on the input length and the block
+ * size, but not on the thread schedule. Thus, floating-point sums are
+ * bitwise reproducible for a fixed block size.
+ *
+ * Assumptions:
+ *
+ * blockDim.x is a power of two
+ * gridDim.x * 2 * blockDim.x >= len
+ * Shared memory holds blockDim.x values
+ */
+
+#include <gpu_common.h>
+
+typedef unsigned long long int uint64_t;
+
+template <typename T>
+__device__ void gpu_tree_sum(const T *__restrict__ data, uint64_t len,
+                             T *__restrict__ block_sums) {
+  extern __shared__ char tree_sum_shared[];
+  T *tree = reinterpret_cast<T *>(tree_sum_shared);
+
+  uint64_t const left = 2ULL * blockDim.x * blockIdx.x + threadIdx.x;
+  uint64_t const right = left + blockDim.x;
+
+  // Padding with zero doesn't change the sum
+  T sum = left < len ? data[left] : T(0);
+  if (right < len) {
+    sum += data[right];
+  }
+  tree[threadIdx.x] = sum;
+  __syncthreads();
+
+  for (unsigned int stride = blockDim.x / 2; stride > 0; stride /= 2) {
+    if (threadIdx.x < stride) {
+      tree[threadIdx.x] += tree[threadIdx.x + stride];
+    }
+    __syncthreads();
+  }
+
+  if (threadIdx.x == 0) {
+    block_sums[blockIdx.x] = tree[0];
+  }
+}
+
+extern "C" __global__ void gpu_tree_sum_float(const float *__restrict__ data,
+                                              uint64_t len,
+                                              float *__restrict__ block_sums) {
+  gpu_tree_sum(data, len, block_sums);
+}
+
+extern "C" __global__ void gpu_tree_sum_double(
+    const double *__restrict__ data, uint64_t len,
+    double *__restrict__ block_sums) {
+  gpu_tree_sum(data, len, block_sums);
+}
+
+extern "C" __global__ void gpu_tree_sum_uint64(
+    const uint64_t *__restrict__ data, uint64_t len,
+    uint64_t *__restrict__ block_sums) {
+  gpu_tree_sum(data, len, block_sums);
+}
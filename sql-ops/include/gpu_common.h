@@ -65,6 +65,22 @@ struct Tuple {
     this->value = tmp.y;
   }
 
+  // Loads through the read-only data cache.
+  //
+  // The memory must not be written while the kernel is running.
+  __device__ __forceinline__ void load_readonly(Tuple<int, int> const &src) {
+    int2 tmp = __ldg(reinterpret_cast<int2 const *>(&src));
+    this->key = tmp.x;
+    this->value = tmp.y;
+  }
+
+  __device__ __forceinline__ void load_readonly(
+      Tuple<long long, long long> const &src) {
+    longlong2 tmp = __ldg(reinterpret_cast<longlong2 const *>(&src));
+    this->key = tmp.x;
+    this->value = tmp.y;
+  }
+
   __device__ __forceinline__ void load_streaming(Tuple<int, int> const &src) {
     int2 tmp = ptx_load_cache_streaming(reinterpret_cast<int2 const *>(&src));
     this->key = tmp.x;
@@ -102,6 +118,18 @@ struct Tuple {
 #endif /* __CUDACC__ */
 };
 
+// Tuple layouts that must match `Tuple` in Rust (see partition.rs).
+//
+// Mixed-width tuples are padded to the alignment of the wider type. Rust's
+// `repr(C)` inserts the same padding.
+static_assert(sizeof(Tuple<int, int>) == 8, "Tuple<int, int> layout");
+static_assert(sizeof(Tuple<long long, long long>) == 16,
+              "Tuple<long long, long long> layout");
+static_assert(sizeof(Tuple<int, long long>) == 16,
+              "Tuple<int, long long> layout");
+static_assert(sizeof(Tuple<long long, int>) == 16,
+              "Tuple<long long, int> layout");
+
 // A hash table entry.
 //
 // Note that the struct's layout must be kept in sync with its counterpart in
@@ -109,6 +137,29 @@ struct Tuple {
 template <typename K, typename V>
 using HtEntry = Tuple<K, V>;
 
+// A materialized join result, i.e., the build and probe payloads of a match.
+//
+// Note that the struct's layout must be kept in sync with its counterpart in
+// Rust.
+template <typename T>
+struct JoinMatch {
+  T build_payload;
+  T probe_payload;
+};
+
+// Returns the payload of a tuple, or `key_only_value` in a key-only join.
+//
+// Key-only joins don't pass a payload attribute, i.e., its pointer is NULL.
+// Then, the build stores a dummy payload, and the probe counts the matches
+// instead of summing up the payloads.
+template <typename T>
+CUDA_MODIFIER inline T payload_or(const T *const __restrict__ payload_attr_data,
+                                  unsigned long long tuple_id,
+                                  T key_only_value) {
+  return payload_attr_data != nullptr ? payload_attr_data[tuple_id]
+                                      : key_only_value;
+}
+
 template <typename K>
 CUDA_MODIFIER constexpr K null_key();
 
@@ -122,6 +173,16 @@ CUDA_MODIFIER constexpr long long null_key<long long>() {
   return 0xFFFFFFFFFFFFFFFFll;
 }
 
+template <>
+CUDA_MODIFIER constexpr unsigned char null_key<unsigned char>() {
+  return 0xFF;
+}
+
+template <>
+CUDA_MODIFIER constexpr unsigned short null_key<unsigned short>() {
+  return 0xFFFF;
+}
+
 // Multiply-shift hash function
 //
 // Takes a value and returns the hash of the value, modulo the number of
@@ -216,4 +277,22 @@ template <typename T>
 constexpr auto hash = &mult_shift_hash<T>;
 // constexpr auto hash = &murmur3_hash<T>;
 
+// Slot advancement of linear probing hash tables
+enum class ProbeStep { Linear, Quadratic };
+
+// Returns the slot that a linear probing hash table probes next
+//
+// `step` is the number of slots probed before `index`. A linear step probes
+// the adjacent slot. A quadratic step probes the slots at the triangular
+// offsets i * (i + 1) / 2 from the hashed slot. In a power-of-two table, the
+// triangular offsets visit every slot exactly once. Takes the table size as
+// a mask, i.e., the number of slots minus one.
+template <ProbeStep S>
+CUDA_MODIFIER __forceinline__ unsigned long long probe_next(
+    unsigned long long index, unsigned long long step,
+    unsigned long long hash_table_mask) {
+  unsigned long long offset = S == ProbeStep::Quadratic ? step + 1ULL : 1ULL;
+  return (index + offset) & hash_table_mask;
+}
+
 #endif /* GPU_COMMON_H */
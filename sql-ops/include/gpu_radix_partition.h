@@ -94,6 +94,20 @@ struct PrefixSumAndTransformArgs {
   unsigned long long *const __restrict__ partition_offsets;
 };
 
+struct PrefixSumAndPartitionArgs {
+  // Inputs
+  const void *const __restrict__ join_attr_data;
+  const void *const __restrict__ payload_attr_data;
+  std::size_t const data_length;
+  uint32_t const padding_length;
+  uint32_t const radix_bits;
+  uint32_t const ignore_bits;
+
+  // Outputs
+  unsigned long long *const __restrict__ partition_offsets;
+  void *const __restrict__ partitioned_relation;
+};
+
 // Arguments to the partitioning function.
 //
 // Note that the struct's layout must be kept in sync with its counterpart in
@@ -229,6 +229,10 @@ __device__ void device_exclusive_prefix_sum_initialize(
 // GPU-wide exclusive prefix sum using the Merrill-Garland decoupled lookback
 // algorithm
 //
+// The size is limited to 2^32 - 1 values by SIZE_T, and all thread blocks of
+// the grid must be resident at the same time. For larger arrays, use the
+// multi-level scan in `prefix_scan.cu` instead.
+//
 // FIXME: Replace "multiple items per thread" strategy with iteration strategy.
 //        In each iteration, process one item per thread. Then reduce
 //        state_index by one, because last position must retain
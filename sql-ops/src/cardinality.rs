@@ -0,0 +1,260 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Approximate distinct counts of a key column with HyperLogLog.
+//!
+//! The number of distinct keys determines, e.g., the size of the partitions
+//! after radix partitioning. Counting the keys exactly requires memory that
+//! grows with the number of distinct keys. HyperLogLog instead estimates the
+//! count from `m = 2^precision` small registers.
+//!
+//! Each key is hashed. The first `precision` bits of the hash select a
+//! register, and the register keeps the maximum position of the first one bit
+//! in the remaining bits. The estimate is the normalized harmonic mean of the
+//! registers. Its relative standard error is about:
+//!
+//! ```text
+//! 1.04 / sqrt(m)
+//! ```
+//!
+//! Small counts are estimated from the number of empty registers instead
+//! (i.e., linear counting), which is more accurate for a mostly empty sketch.
+//!
+//! The CPU and the GPU implementations hash the keys with the same function.
+//! Thus, both compute the same registers and return the same estimate.
+
+use crate::error::{ErrorKind, Result};
+use crate::join::bloom_filter::mix64;
+use numa_gpu::runtime::memory::{LaunchableMem, Mem};
+use rustacuda::launch;
+use rustacuda::memory::{CopyDestination, DeviceBuffer, DeviceCopy};
+use rustacuda::stream::Stream;
+use std::convert::TryInto;
+use std::ffi::CString;
+use std::mem;
+
+/// An estimated number of distinct keys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DistinctEstimate {
+    /// The estimated number of distinct keys.
+    pub distinct: u64,
+
+    /// The relative standard error of the estimate.
+    pub standard_error: f64,
+}
+
+/// Estimates the number of distinct keys on the CPU or the GPU.
+#[derive(Clone, Copy, Debug)]
+pub struct HyperLogLog {
+    precision: u32,
+}
+
+impl HyperLogLog {
+    /// The minimum number of hash bits that select a register.
+    pub const MIN_PRECISION: u32 = 4;
+
+    /// The maximum number of hash bits that select a register.
+    ///
+    /// The GPU keeps a copy of the registers in the shared memory of each
+    /// thread block, which limits the number of registers.
+    pub const MAX_PRECISION: u32 = 12;
+
+    /// The number of threads per block of the GPU implementation.
+    const BLOCK_SIZE: u32 = 256;
+
+    /// The maximum number of thread blocks of the GPU implementation.
+    ///
+    /// Each block merges all registers into the global registers. Thus, more
+    /// blocks cost more merges, but are not necessary to saturate the memory
+    /// bandwidth.
+    const MAX_GRID_SIZE: u32 = 1024;
+
+    /// Creates an estimator with `2^precision` registers.
+    ///
+    /// Returns an `InvalidArgument` error if the precision is outside of
+    /// `MIN_PRECISION` and `MAX_PRECISION`.
+    pub fn new(precision: u32) -> Result<Self> {
+        if precision < Self::MIN_PRECISION || precision > Self::MAX_PRECISION {
+            Err(ErrorKind::InvalidArgument(format!(
+                "HyperLogLog precision must be between {} and {}, got {}",
+                Self::MIN_PRECISION,
+                Self::MAX_PRECISION,
+                precision
+            )))?;
+        }
+
+        Ok(Self { precision })
+    }
+
+    /// Returns the number of registers.
+    pub fn num_registers(&self) -> usize {
+        1 << self.precision
+    }
+
+    /// Returns the relative standard error of the estimates.
+    pub fn standard_error(&self) -> f64 {
+        1.04 / (self.num_registers() as f64).sqrt()
+    }
+
+    /// Estimates the number of distinct keys in `column` on the CPU.
+    ///
+    /// The column must be accessible by the CPU.
+    pub fn estimate_cpu<T>(&self, column: &Mem<T>) -> Result<DistinctEstimate>
+    where
+        T: Copy + DeviceCopy + Into<i64>,
+    {
+        let keys: &[T] = column.try_into().map_err(|_| {
+            ErrorKind::RuntimeError(
+                "The CPU can't estimate the distinct keys of device memory".to_string(),
+            )
+        })?;
+
+        let mut registers = vec![0_u32; self.num_registers()];
+        for &key in keys {
+            let (index, rank) = self.index_and_rank(key.into());
+            let register = &mut registers[index];
+            *register = (*register).max(rank);
+        }
+
+        Ok(self.estimate(&registers))
+    }
+
+    /// Estimates the number of distinct keys in `column` on the GPU.
+    ///
+    /// The estimate is launched on `stream`, and the function blocks until
+    /// the estimate is available. The column must be accessible by the GPU.
+    pub fn estimate_gpu<T>(&self, column: &Mem<T>, stream: &Stream) -> Result<DistinctEstimate>
+    where
+        T: GpuHyperLogLogKey,
+    {
+        let mut registers = vec![0_u32; self.num_registers()];
+        let mut dev_registers = DeviceBuffer::from_slice(&registers)?;
+
+        let len = column.len();
+        let grid_size = ((len + Self::BLOCK_SIZE as usize - 1) / Self::BLOCK_SIZE as usize)
+            .max(1)
+            .min(Self::MAX_GRID_SIZE as usize) as u32;
+
+        T::update_registers(
+            column,
+            self.precision,
+            &mut dev_registers,
+            grid_size,
+            Self::BLOCK_SIZE,
+            stream,
+        )?;
+        stream.synchronize()?;
+
+        dev_registers.copy_to(&mut registers[..])?;
+
+        Ok(self.estimate(&registers))
+    }
+
+    /// Returns the register index and the rank of a key.
+    ///
+    /// Must be kept in sync with the CUDA implementation.
+    fn index_and_rank(&self, key: i64) -> (usize, u32) {
+        let hash = mix64(key as u64);
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rest = hash << self.precision;
+        let rank = if rest == 0 {
+            65 - self.precision
+        } else {
+            rest.leading_zeros() + 1
+        };
+
+        (index, rank)
+    }
+
+    /// Computes the estimate from the registers.
+    fn estimate(&self, registers: &[u32]) -> DistinctEstimate {
+        let m = registers.len() as f64;
+        let alpha = match registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+
+        let harmonic_sum: f64 = registers.iter().map(|&r| 2_f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / harmonic_sum;
+
+        // The 64-bit hash doesn't need a correction for large counts
+        let empty = registers.iter().filter(|&&r| r == 0).count();
+        let distinct = if raw <= 2.5 * m && empty != 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            raw
+        };
+
+        DistinctEstimate {
+            distinct: distinct.round() as u64,
+            standard_error: self.standard_error(),
+        }
+    }
+}
+
+/// A key type of which the GPU can estimate the distinct count.
+pub trait GpuHyperLogLogKey: Copy + DeviceCopy + Into<i64> + Sized {
+    /// Launches the kernel that updates the registers with the keys of
+    /// `column`.
+    fn update_registers(
+        column: &Mem<Self>,
+        precision: u32,
+        registers: &mut DeviceBuffer<u32>,
+        grid_size: u32,
+        block_size: u32,
+        stream: &Stream,
+    ) -> Result<()>;
+}
+
+macro_rules! impl_gpu_hyperloglog_key {
+    ($Type:ty, $Suffix:expr) => {
+        paste::item! {
+            impl GpuHyperLogLogKey for $Type {
+                fn update_registers(
+                    column: &Mem<Self>,
+                    precision: u32,
+                    registers: &mut DeviceBuffer<u32>,
+                    grid_size: u32,
+                    block_size: u32,
+                    stream: &Stream,
+                ) -> Result<()> {
+                    let module = crate::module()?;
+                    let name = CString::new(stringify!([<gpu_hyperloglog_ $Suffix>])).unwrap();
+                    let function = crate::module_function(module, &name)?;
+
+                    let data = column.as_launchable_slice();
+                    let shared_mem_bytes = (registers.len() * mem::size_of::<u32>()) as u32;
+
+                    unsafe {
+                        launch!(
+                            function<<<grid_size, block_size, shared_mem_bytes, stream>>>(
+                                data.as_launchable_ptr(),
+                                data.len() as u64,
+                                precision,
+                                registers.as_device_ptr()
+                            )
+                        )?;
+                    }
+
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+impl_gpu_hyperloglog_key!(i32, int32);
+impl_gpu_hyperloglog_key!(i64, int64);
@@ -19,8 +19,10 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug)]
 pub enum ErrorKind {
     CudaError(rustacuda::error::CudaError),
+    CudaUnavailable(String),
     IntegerOverflow(String),
     InvalidArgument(String),
+    IoError(std::io::Error),
     LikwidError(likwid::error::LikwidError),
     Msg(String),
     NulCharError(String),
@@ -55,6 +57,14 @@ impl From<rustacuda::error::CudaError> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self {
+            kind: ErrorKind::IoError(error),
+        }
+    }
+}
+
 impl From<likwid::error::LikwidError> for Error {
     fn from(error: likwid::error::LikwidError) -> Self {
         Self {
@@ -81,8 +91,10 @@ impl std::fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ErrorKind::CudaError(ref e) => e.fmt(f),
+            ErrorKind::CudaUnavailable(ref s) => write!(f, "CudaUnavailable: {}", s),
             ErrorKind::IntegerOverflow(ref s) => write!(f, "IntegerOverflow: {}", s),
             ErrorKind::InvalidArgument(ref s) => write!(f, "InvalidArgument: {}", s),
+            ErrorKind::IoError(ref e) => e.fmt(f),
             ErrorKind::LikwidError(ref e) => e.fmt(f),
             ErrorKind::NulCharError(ref s) => write!(f, "NulCharError: {}", s),
             ErrorKind::NumaGpuError(ref e) => e.fmt(f),
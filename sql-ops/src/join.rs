@@ -14,11 +14,15 @@
 
 //! A collection of relational join operators.
 
+pub mod bloom_filter;
 pub mod cuda_radix_join;
+mod custom_hash;
 mod hashing_scheme;
 pub mod no_partitioning_join;
+pub mod validity;
+pub mod wide_payload_join;
 
-pub use hashing_scheme::HashingScheme;
+pub use hashing_scheme::{HashTableLayout, HashingScheme, ProbeStep};
 
 /// A hash table entry in the C/C++ implementation.
 ///
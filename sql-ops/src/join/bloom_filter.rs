@@ -0,0 +1,253 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A Bloom filter to pre-filter probe tuples before the hash table probe.
+//!
+//! A selective join probes many keys that have no match. The Bloom filter is
+//! much smaller than the hash table, and thus filters out most of these keys
+//! with fewer cache misses. Keys that pass the filter are probed in the hash
+//! table as usual. The filter has no false negatives, but a configurable rate
+//! of false positives.
+//!
+//! ## Sizing
+//!
+//! The false-positive rate (FPR) depends on the bits per key `b = m / n` and
+//! the number of hash functions `k`, and is approximately:
+//!
+//! ```text
+//! FPR = (1 - e^(-k / b))^k
+//! ```
+//!
+//! For a given `b`, the FPR is minimal at `k = b * ln(2)`, where it is about
+//! `0.6185^b`. Fewer hash functions cost fewer memory accesses per probe at
+//! the expense of a higher FPR. `BloomFilterConfig::with_optimal_hashes`
+//! chooses the optimal `k`.
+//!
+//! The estimate assumes independent hash functions. The filter derives its
+//! `k` hash values from two hashes by double hashing, which behaves about the
+//! same in practice. `false_positive_rate` measures the actual FPR from a
+//! probe's bitmaps.
+
+use super::no_partitioning_join::probe_bitmap_len;
+use crate::error::{ErrorKind, Result};
+
+const WORD_BITS: u64 = u64::BITS as u64;
+
+/// Configures the size of a Bloom filter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BloomFilterConfig {
+    /// The number of filter bits per build key. Must be at least 1.
+    pub bits_per_key: u32,
+
+    /// The number of hash functions, i.e., the number of bits set per key.
+    /// Must be at least 1.
+    pub num_hashes: u32,
+}
+
+impl Default for BloomFilterConfig {
+    /// 10 bits per key with the optimal 7 hash functions, i.e., an FPR of
+    /// about 1%.
+    fn default() -> Self {
+        Self::with_optimal_hashes(10)
+    }
+}
+
+impl BloomFilterConfig {
+    /// Returns a configuration with the number of hash functions that
+    /// minimizes the FPR, i.e., `round(bits_per_key * ln(2))`.
+    pub fn with_optimal_hashes(bits_per_key: u32) -> Self {
+        let num_hashes = (bits_per_key as f64 * std::f64::consts::LN_2).round() as u32;
+
+        Self {
+            bits_per_key,
+            num_hashes: num_hashes.max(1),
+        }
+    }
+
+    /// Returns the estimated FPR of a filter that is filled to its
+    /// configured number of keys.
+    pub fn estimated_fpr(&self) -> f64 {
+        estimate_fpr(self.num_hashes, 1.0 / self.bits_per_key as f64)
+    }
+}
+
+/// A Bloom filter over join keys.
+#[derive(Clone, Debug)]
+pub struct BloomFilter {
+    words: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    num_keys: usize,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter that is sized for `expected_keys` build keys.
+    ///
+    /// The filter has at least one word of 64 bits.
+    pub fn new(config: BloomFilterConfig, expected_keys: usize) -> Result<Self> {
+        if config.bits_per_key == 0 || config.num_hashes == 0 {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Bloom filter requires at least one bit per key and one hash function, got {:?}",
+                config
+            )))?;
+        }
+
+        let bits = (expected_keys as u64)
+            .checked_mul(config.bits_per_key as u64)
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to compute the Bloom filter size".to_string())
+            })?;
+        let num_words = ((bits + WORD_BITS - 1) / WORD_BITS).max(1);
+
+        Ok(Self {
+            words: vec![0; num_words as usize],
+            num_bits: num_words * WORD_BITS,
+            num_hashes: config.num_hashes,
+            num_keys: 0,
+        })
+    }
+
+    /// Returns the size of the filter in bits.
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// Inserts the build keys into the filter.
+    pub fn insert<T: Copy + Into<i64>>(&mut self, keys: &[T]) {
+        for &key in keys {
+            let (h1, h2) = hash_pair(key.into());
+            for i in 0..self.num_hashes as u64 {
+                let bit = h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits;
+                self.words[(bit / WORD_BITS) as usize] |= 1 << (bit % WORD_BITS);
+            }
+        }
+
+        self.num_keys += keys.len();
+    }
+
+    /// Returns `true` if the key may have been inserted, and `false` if the
+    /// key has definitely not been inserted.
+    pub fn contains<T: Into<i64>>(&self, key: T) -> bool {
+        let (h1, h2) = hash_pair(key.into());
+
+        (0..self.num_hashes as u64).all(|i| {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits;
+            self.words[(bit / WORD_BITS) as usize] & (1 << (bit % WORD_BITS)) != 0
+        })
+    }
+
+    /// Probes the keys and sets the bits of the keys that pass the filter.
+    ///
+    /// The bitmap has the same format as the bitmap of
+    /// `CpuHashJoin::probe_bitmap`. Returns the number of keys that passed.
+    pub fn probe_bitmap<T: Copy + Into<i64>>(
+        &self,
+        keys: &[T],
+        bitmap: &mut [u64],
+    ) -> Result<usize> {
+        if bitmap.len() < probe_bitmap_len(keys.len()) {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Bitmap of {} words is too small for {} keys",
+                bitmap.len(),
+                keys.len()
+            )))?;
+        }
+
+        bitmap.iter_mut().for_each(|word| *word = 0);
+
+        let mut passed = 0;
+        for (i, &key) in keys.iter().enumerate() {
+            if self.contains(key) {
+                bitmap[i / WORD_BITS as usize] |= 1 << (i % WORD_BITS as usize);
+                passed += 1;
+            }
+        }
+
+        Ok(passed)
+    }
+
+    /// Returns the estimated FPR for the number of keys inserted so far.
+    pub fn estimated_fpr(&self) -> f64 {
+        estimate_fpr(self.num_hashes, self.num_keys as f64 / self.num_bits as f64)
+    }
+}
+
+/// Measures the FPR of a filtered probe of `tuples` keys.
+///
+/// `filter_bitmap` marks the keys that passed the filter, and `match_bitmap`
+/// marks the keys that have a match in the hash table, e.g., as returned by
+/// `CpuHashJoin::probe_bitmap`. The FPR is the share of keys without a match
+/// that passed the filter. Returns 0 if all keys have a match.
+pub fn false_positive_rate(
+    filter_bitmap: &[u64],
+    match_bitmap: &[u64],
+    tuples: usize,
+) -> Result<f64> {
+    let len = probe_bitmap_len(tuples);
+    if filter_bitmap.len() < len || match_bitmap.len() < len {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Bitmaps of {} and {} words are too small for {} keys",
+            filter_bitmap.len(),
+            match_bitmap.len(),
+            tuples
+        )))?;
+    }
+
+    let (false_positives, negatives) = filter_bitmap[..len]
+        .iter()
+        .zip(&match_bitmap[..len])
+        .enumerate()
+        .fold((0_u64, 0_u64), |(fp, neg), (word, (&passed, &matched))| {
+            // Ignore the unused bits of the last word
+            let valid = match tuples - word * WORD_BITS as usize {
+                n if n >= WORD_BITS as usize => u64::MAX,
+                n => (1 << n) - 1,
+            };
+            let unmatched = !matched & valid;
+
+            (
+                fp + (passed & unmatched).count_ones() as u64,
+                neg + unmatched.count_ones() as u64,
+            )
+        });
+
+    if negatives == 0 {
+        Ok(0.0)
+    } else {
+        Ok(false_positives as f64 / negatives as f64)
+    }
+}
+
+fn estimate_fpr(num_hashes: u32, keys_per_bit: f64) -> f64 {
+    let k = num_hashes as f64;
+    (1.0 - (-k * keys_per_bit).exp()).powf(k)
+}
+
+/// Returns two independent hashes of the key for double hashing.
+fn hash_pair(key: i64) -> (u64, u64) {
+    let h1 = mix64(key as u64);
+    let h2 = mix64(h1) | 1;
+
+    (h1, h2)
+}
+
+/// Finalizer of MurmurHash3, which mixes all input bits into all output bits.
+pub(crate) fn mix64(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^= x >> 33;
+    x
+}
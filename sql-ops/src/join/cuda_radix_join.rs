@@ -49,6 +49,13 @@
 //! This assignment method thus handles skew, as long as none of the hash tables exceeds the shared
 //! memory capacity.
 //!
+//! The bucket chaining hash table is sized per partition. A partition that doesn't fit with the
+//! default number of buckets gets fewer buckets, which leaves more shared memory for its tuples.
+//! If a build partition exceeds `CudaRadixJoin::max_build_partition_len` even with a single
+//! bucket, the GPU skips the partition and flags the overflow. The flag is checked with
+//! `CudaRadixJoin::check_overflow` after the join completes. Checking on the GPU avoids copying
+//! the partition offsets to the CPU, which would synchronize the stream before each join.
+//!
 //! Handling a high degree of skew would require dynamic recursive partitioning. I.e., if a
 //! partition exceeds the shared memory capacity, it should be recursively partitioned until all
 //! subpartitions fit into shared memory. Alternatively, spilling (parts of) the hash table to GPU
@@ -65,7 +72,7 @@ use rustacuda::context::CurrentContext;
 use rustacuda::device::DeviceAttribute;
 use rustacuda::function::{BlockSize, GridSize};
 use rustacuda::launch;
-use rustacuda::memory::DeviceCopy;
+use rustacuda::memory::{CopyDestination, DeviceBuffer, DeviceCopy, DevicePointer};
 use rustacuda::stream::Stream;
 use std::ffi;
 use std::mem;
@@ -83,6 +90,7 @@ struct JoinAggregateArgs {
     probe_rel_partition_offsets: LaunchablePtr<u64>,
     aggregation_result: LaunchableMutPtr<i64>,
     task_assignments: LaunchableMutPtr<u32>,
+    overflow: LaunchableMutPtr<u32>,
     build_rel_len: u32,
     probe_rel_len: u32,
     build_rel_padding_len: u32,
@@ -120,6 +128,7 @@ pub struct CudaRadixJoin {
     hashing_scheme: HashingScheme,
     grid_size: GridSize,
     block_size: BlockSize,
+    overflow: DeviceBuffer<u32>,
 }
 
 impl CudaRadixJoin {
@@ -137,9 +146,56 @@ impl CudaRadixJoin {
             hashing_scheme,
             grid_size: grid_size.clone(),
             block_size: block_size.clone(),
+            overflow: DeviceBuffer::from_slice(&[0])?,
         })
     }
 
+    /// Returns the maximum number of tuples in a build partition.
+    ///
+    /// Larger partitions don't fit into the shared memory hash table. For
+    /// bucket chaining, the limit applies to a hash table with a single bucket.
+    pub fn max_build_partition_len<T: CudaRadixJoinable>(&self) -> Result<usize> {
+        let device = CurrentContext::get_device()?;
+        let max_shared_mem_bytes =
+            device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)? as u32;
+
+        match self.hashing_scheme {
+            HashingScheme::Perfect => Ok(perfect_capacity::<T>(max_shared_mem_bytes)),
+            HashingScheme::BucketChaining => Ok(chaining_capacity::<T>(max_shared_mem_bytes)),
+            HashingScheme::LinearProbing | HashingScheme::Cuckoo => {
+                Err(ErrorKind::InvalidArgument(format!(
+                    "{:?} hashing is not supported by the radix join",
+                    self.hashing_scheme
+                )))?
+            }
+        }
+    }
+
+    /// Checks that no join skipped a build partition.
+    ///
+    /// A build partition that exceeds `max_build_partition_len` is skipped,
+    /// and the join result is incomplete. The check reads a flag from GPU
+    /// memory, thus all joins must be completed, e.g., by synchronizing their
+    /// streams.
+    pub fn check_overflow(&self) -> Result<()> {
+        let mut overflow = [0_u32];
+        self.overflow.copy_to(&mut overflow[..])?;
+
+        if overflow[0] != 0 {
+            Err(ErrorKind::InvalidArgument(
+                "A build partition exceeds the shared memory hash table capacity, use more radix bits"
+                    .to_string(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a pointer to the overflow flag, which the GPU sets.
+    fn overflow_ptr(&self) -> LaunchableMutPtr<u32> {
+        unsafe { DevicePointer::wrap(self.overflow.as_ptr() as *mut u32) }.into()
+    }
+
     /// Join two relations and output a set of aggregate values.
     pub fn join<T>(
         &self,
@@ -163,6 +219,25 @@ impl CudaRadixJoin {
     }
 }
 
+/// Returns the maximum number of build tuples that fit into a perfect hash table in shared
+/// memory.
+fn perfect_capacity<T>(shared_mem_bytes: u32) -> usize {
+    shared_mem_bytes as usize / mem::size_of::<HtEntry<T, T>>() - 1
+}
+
+/// Returns the maximum number of build tuples that fit into a bucket chaining hash table in
+/// shared memory.
+///
+/// The capacity is reached with a single bucket. Tuples are linked by 16-bit indices, and the
+/// largest index is reserved as the end of the chain.
+fn chaining_capacity<T>(shared_mem_bytes: u32) -> usize {
+    let bucket_bytes = mem::size_of::<u32>();
+    let entry_bytes = mem::size_of::<u16>() + 2 * mem::size_of::<T>();
+    let entries = (shared_mem_bytes as usize).saturating_sub(bucket_bytes) / entry_bytes;
+
+    entries.min(u16::MAX as usize)
+}
+
 // FIXME: build_rel and probe_rel should be of type PartitionedRelationSlice, i.e., immutable
 // FIXME: add i64 implementation
 macro_rules! impl_cuda_radix_join_for_type {
@@ -200,11 +275,12 @@ macro_rules! impl_cuda_radix_join_for_type {
 
                     let build_rel_len = build_rel.relation.len() as u32;
                     let probe_rel_len = probe_rel.relation.len() as u32;
-                    let module = *crate::MODULE;
+                    let module = crate::module()?;
                     let device = CurrentContext::get_device()?;
                     let max_shared_mem_bytes =
                         device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)? as u32;
                     let radix_bits = rj.radix_bits.pass_radix_bits(rj.radix_pass).unwrap();
+
                     let ignore_bits = rj.radix_bits.pass_ignore_bits(rj.radix_pass) + radix_bits;
 
                     let mut args = JoinAggregateArgs {
@@ -214,6 +290,7 @@ macro_rules! impl_cuda_radix_join_for_type {
                         probe_rel_partition_offsets: probe_rel.offsets.as_launchable_ptr(),
                         aggregation_result: result_set.as_launchable_mut_ptr(),
                         task_assignments: task_assignments.as_launchable_mut_ptr(),
+                        overflow: rj.overflow_ptr(),
                         build_rel_len,
                         probe_rel_len,
                         build_rel_padding_len: build_rel.padding_len(),
@@ -232,14 +309,12 @@ macro_rules! impl_cuda_radix_join_for_type {
 
                     match &rj.hashing_scheme {
                         HashingScheme::Perfect => {
-                            let ht_entries =
-                                max_shared_mem_bytes as usize / mem::size_of::<HtEntry<Self, Self>>() - 1;
-                            args.ht_entries = ht_entries as u32;
+                            args.ht_entries = perfect_capacity::<Self>(max_shared_mem_bytes) as u32;
 
                             let name =
                                 std::ffi::CString::new(stringify!([<gpu_join_aggregate_smem_perfect_ $Suffix _ $Suffix _ $Suffix>]))
                                 .unwrap();
-                            let mut function = module.get_function(&name)?;
+                            let mut function = crate::module_function(module, &name)?;
                             function.set_max_dynamic_shared_size_bytes(max_shared_mem_bytes)?;
 
                             unsafe {
@@ -251,6 +326,9 @@ macro_rules! impl_cuda_radix_join_for_type {
                             }
                         }
                         HashingScheme::LinearProbing => unimplemented!(),
+                        HashingScheme::Cuckoo => Err(ErrorKind::InvalidArgument(
+                                "Cuckoo hashing is not supported on the GPU".to_string()
+                                ))?,
                         HashingScheme::BucketChaining => {
                             args.ht_entries = crate::constants::RADIX_JOIN_BUCKET_CHAINING_ENTRIES;
 
@@ -258,7 +336,7 @@ macro_rules! impl_cuda_radix_join_for_type {
                                      gpu_join_aggregate_smem_chaining_ $Suffix _ $Suffix _ $Suffix
                                  >]))
                                 .unwrap();
-                            let mut function = module.get_function(&name)?;
+                            let mut function = crate::module_function(module, &name)?;
                             function.set_max_dynamic_shared_size_bytes(max_shared_mem_bytes)?;
 
                             unsafe {
@@ -0,0 +1,269 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Linear probing with a user-defined hash function on the CPU.
+//!
+//! The C++ hash join compiles its hash function into the build and probe
+//! loops. Calling a Rust function pointer from C++ would require an
+//! `extern "C"` function for each experiment. Instead, this module implements
+//! linear probing in Rust on the same hash table layout as the C++ code.
+//! Thus, `CpuHashJoin` can use a custom hash function without touching the
+//! C++ code.
+//!
+//! The GPU cannot call a CPU function pointer, and is therefore limited to the
+//! built-in hash functions.
+
+use super::no_partitioning_join::JoinMatch;
+use super::HtEntry;
+use crate::error::{ErrorKind, Result};
+use datagen::relation::KeyAttribute;
+use std::ptr;
+use std::sync::atomic::{AtomicI32, AtomicI64, Ordering};
+
+/// A join key that can be inserted concurrently into the hash table.
+pub(crate) trait AtomicKey: Copy + PartialEq + KeyAttribute {
+    /// Atomically loads the key at `key`.
+    ///
+    /// # Safety
+    ///
+    /// `key` must be valid and aligned for an atomic access.
+    unsafe fn load(key: *const Self) -> Self;
+
+    /// Atomically replaces the `NULL` key at `key` with `new`.
+    ///
+    /// Returns `true` if the key was replaced.
+    ///
+    /// # Safety
+    ///
+    /// `key` must be valid and aligned for an atomic access.
+    unsafe fn insert_null(key: *mut Self, new: Self) -> bool;
+}
+
+impl AtomicKey for i32 {
+    unsafe fn load(key: *const Self) -> Self {
+        (*(key as *const AtomicI32)).load(Ordering::SeqCst)
+    }
+
+    unsafe fn insert_null(key: *mut Self, new: Self) -> bool {
+        (*(key as *const AtomicI32))
+            .compare_exchange(Self::null_key(), new, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+impl AtomicKey for i64 {
+    unsafe fn load(key: *const Self) -> Self {
+        (*(key as *const AtomicI64)).load(Ordering::SeqCst)
+    }
+
+    unsafe fn insert_null(key: *mut Self, new: Self) -> bool {
+        (*(key as *const AtomicI64))
+            .compare_exchange(Self::null_key(), new, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+/// Returns the number of usable entries, i.e., the largest power of two that
+/// fits into the hash table.
+///
+/// The C++ hash tables make the same assumption.
+fn usable_entries(hash_table_size: usize) -> Result<usize> {
+    if hash_table_size == 0 {
+        Err(ErrorKind::InvalidArgument(
+            "Hash table must have at least one entry".to_string(),
+        ))?;
+    }
+
+    Ok(1 << (usize::BITS - 1 - hash_table_size.leading_zeros()))
+}
+
+/// Inserts the tuples into a linear probing hash table.
+///
+/// Without a payload attribute, i.e., in a key-only join, the entries store
+/// the default value as payload. Returns `false` if the hash table is full,
+/// and an error if the hash table has no entries.
+///
+/// # Safety
+///
+/// `hash_table` must point to `hash_table_size` initialized entries. Other
+/// threads may insert concurrently, but must not probe.
+pub(crate) unsafe fn build<T: AtomicKey + Default>(
+    hash_table: *mut HtEntry<T, T>,
+    hash_table_size: usize,
+    join_attr: &[T],
+    payload_attr: Option<&[T]>,
+    hash_fn: fn(T) -> u64,
+) -> Result<bool> {
+    let entries = usable_entries(hash_table_size)?;
+    let mask = entries - 1;
+
+    for (tuple_id, &key) in join_attr.iter().enumerate() {
+        let payload = payload_attr.map_or_else(T::default, |payload_attr| payload_attr[tuple_id]);
+        let mut index = hash_fn(key) as usize & mask;
+        let mut is_inserted = false;
+
+        for _ in 0..entries {
+            let entry = hash_table.add(index);
+            let entry_key = ptr::addr_of_mut!((*entry).key);
+            if T::load(entry_key) == T::null_key() && T::insert_null(entry_key, key) {
+                (*entry).value = payload;
+                is_inserted = true;
+                break;
+            }
+            index = (index + 1) & mask;
+        }
+
+        if !is_inserted {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Calls `on_match` with the probe tuple ID and the build payload of each
+/// match.
+///
+/// # Safety
+///
+/// `hash_table` must point to `hash_table_size` initialized entries, and the
+/// build must be completed.
+unsafe fn probe<T, F>(
+    hash_table: *const HtEntry<T, T>,
+    hash_table_size: usize,
+    join_attr: &[T],
+    hash_fn: fn(T) -> u64,
+    mut on_match: F,
+) -> Result<()>
+where
+    T: AtomicKey,
+    F: FnMut(usize, T),
+{
+    let entries = usable_entries(hash_table_size)?;
+    let mask = entries - 1;
+    let hash_table = std::slice::from_raw_parts(hash_table, entries);
+
+    for (tuple_id, &key) in join_attr.iter().enumerate() {
+        let mut index = hash_fn(key) as usize & mask;
+
+        for _ in 0..entries {
+            let entry_key = hash_table[index].key;
+            if entry_key == key {
+                on_match(tuple_id, hash_table[index].value);
+            } else if entry_key == T::null_key() {
+                break;
+            }
+            index = (index + 1) & mask;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sums the probe payloads of all matches into `join_result`.
+///
+/// Without a payload attribute, i.e., in a key-only join, counts the matches
+/// instead.
+///
+/// # Safety
+///
+/// See `probe`.
+pub(crate) unsafe fn probe_sum<T>(
+    hash_table: *const HtEntry<T, T>,
+    hash_table_size: usize,
+    join_attr: &[T],
+    payload_attr: Option<&[T]>,
+    hash_fn: fn(T) -> u64,
+    join_result: &mut u64,
+) -> Result<()>
+where
+    T: AtomicKey + Into<i64>,
+{
+    probe(
+        hash_table,
+        hash_table_size,
+        join_attr,
+        hash_fn,
+        |tuple_id, _| {
+            let payload =
+                payload_attr.map_or(1, |payload_attr| payload_attr[tuple_id].into() as u64);
+            *join_result = join_result.wrapping_add(payload);
+        },
+    )
+}
+
+/// Sets the bit of each probe tuple that has a match.
+///
+/// # Safety
+///
+/// See `probe`.
+pub(crate) unsafe fn probe_bitmap<T: AtomicKey>(
+    hash_table: *const HtEntry<T, T>,
+    hash_table_size: usize,
+    join_attr: &[T],
+    hash_fn: fn(T) -> u64,
+    bitmap: &mut [u64],
+) -> Result<()> {
+    probe(
+        hash_table,
+        hash_table_size,
+        join_attr,
+        hash_fn,
+        |tuple_id, _| {
+            bitmap[tuple_id / 64] |= 1 << (tuple_id % 64);
+        },
+    )
+}
+
+/// Writes the build and probe payloads of all matches in probe relation order.
+///
+/// Returns the number of matches. Matches beyond the capacity of `join_result`
+/// are counted, but not written. With a `limit`, at most `limit` matches are
+/// written, and surplus matches are counted only up to `limit + 1`.
+///
+/// # Safety
+///
+/// See `probe`.
+pub(crate) unsafe fn probe_materialize<T: AtomicKey>(
+    hash_table: *const HtEntry<T, T>,
+    hash_table_size: usize,
+    join_attr: &[T],
+    payload_attr: &[T],
+    hash_fn: fn(T) -> u64,
+    join_result: &mut [JoinMatch<T>],
+    limit: Option<usize>,
+) -> Result<usize> {
+    let capacity = limit.unwrap_or(join_result.len());
+    let mut matches = 0;
+
+    probe(
+        hash_table,
+        hash_table_size,
+        join_attr,
+        hash_fn,
+        |tuple_id, build_payload| {
+            if matches < capacity {
+                join_result[matches] = JoinMatch {
+                    build_payload,
+                    probe_payload: payload_attr[tuple_id],
+                };
+            } else if limit.is_some() && matches > capacity {
+                return;
+            }
+            matches += 1;
+        },
+    )?;
+
+    Ok(matches)
+}
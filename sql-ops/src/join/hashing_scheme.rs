@@ -22,6 +22,15 @@ pub enum HashingScheme {
     /// Perfect hashing assumes that build-side join keys are unique and in a
     /// contiguous range, i.e., k \in [0,N-1]. Probe-side keys are allowed to be
     /// non-unique and outside of the range.
+    ///
+    /// The key is used as the slot index, i.e., the hash table is a
+    /// direct-address array. `detect_direct_address` checks if a sorted build
+    /// relation qualifies.
+    ///
+    /// For narrow join keys, i.e., `u8` and `u16`, the hash table covers the
+    /// whole key domain and is directly addressed by the key. This is the
+    /// recommended scheme for dictionary-encoded columns, and the only scheme
+    /// supported for narrow keys.
     Perfect,
 
     /// Linear probing scheme.
@@ -40,4 +49,129 @@ pub enum HashingScheme {
     /// - key compression (not implemented)
     /// - materialization using coalesced writes (not implemented)
     BucketChaining,
+
+    /// Cuckoo hashing scheme.
+    ///
+    /// Cuckoo hashing splits the hash table into two sub-tables, and assigns
+    /// each key one slot per sub-table using two different hash functions. A
+    /// probe thus reads at most two slots, which bounds the worst-case probe
+    /// length independent of the load factor.
+    ///
+    /// On a collision, the build evicts the existing key and re-inserts it
+    /// into its alternative slot. If the chain of evictions grows too long,
+    /// the build clears the hash table and rebuilds it with a new hash seed.
+    ///
+    /// ## Limitations
+    ///
+    /// - the maximum supported load factor is 50%, i.e., the hash table must
+    ///   have at least twice as many entries as the build-side relation
+    /// - build-side join keys must be unique
+    /// - the build must be called once with the entire build-side relation,
+    ///   and thus runs single-threaded
+    /// - only implemented for CPUs
+    Cuckoo,
+}
+
+impl HashingScheme {
+    /// Returns `Perfect` if the build-side keys can directly address the hash
+    /// table, and `fallback` otherwise.
+    ///
+    /// Dense keys, e.g., surrogate keys `0..n`, turn the hash table into a
+    /// direct-address array. Perfect hashing then uses the key as the slot
+    /// index, and skips hashing and probing for collisions.
+    ///
+    /// The check accepts keys that are strictly increasing and lie within
+    /// `[0, hash_table_len)`. Sorted keys are thus unique, which is required by
+    /// perfect hashing. The check takes one pass over the keys and doesn't
+    /// allocate memory. Unsorted keys are not detected, even if they are dense.
+    pub fn detect_direct_address<T>(
+        build_keys: &[T],
+        hash_table_len: usize,
+        fallback: HashingScheme,
+    ) -> HashingScheme
+    where
+        T: Copy + Into<i64>,
+    {
+        let in_range = |key: T| {
+            let key: i64 = key.into();
+            key >= 0 && (key as u64) < hash_table_len as u64
+        };
+        let is_sorted_unique = build_keys
+            .windows(2)
+            .all(|pair| pair[0].into() < pair[1].into());
+        let is_dense = match (build_keys.first(), build_keys.last()) {
+            (Some(&first), Some(&last)) => in_range(first) && in_range(last) && is_sorted_unique,
+            _ => false,
+        };
+
+        if is_dense {
+            HashingScheme::Perfect
+        } else {
+            fallback
+        }
+    }
+}
+
+/// Specifies how the `LinearProbing` scheme advances to the next slot on a
+/// collision.
+///
+/// Linear steps probe adjacent slots. Colliding keys thus form contiguous
+/// runs, which grow into each other at high load factors (i.e., primary
+/// clustering). Quadratic steps probe the slots at the triangular offsets
+/// `i * (i + 1) / 2` from the hashed slot, and thus spread the collisions
+/// over the table.
+///
+/// ## Limitations
+///
+/// - quadratic steps visit every slot only if the hash table's size is a
+///   power of two, thus the size must be a power of two
+/// - only supported by the `LinearProbing` hashing scheme with the `AoS`
+///   layout and the built-in hash function
+/// - only supported by `build` and `probe_sum`, including their key-only
+///   variants
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProbeStep {
+    /// Probe the adjacent slot.
+    Linear,
+
+    /// Probe the slot at the next triangular offset.
+    Quadratic,
+}
+
+impl Default for ProbeStep {
+    fn default() -> Self {
+        ProbeStep::Linear
+    }
+}
+
+/// Specifies how the hash table stores its keys and values in memory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HashTableLayout {
+    /// Array of structures.
+    ///
+    /// Each hash table entry stores a key and its value next to each other.
+    /// Thus, a probe that reads the value of a match loads the key and the
+    /// value together.
+    AoS,
+
+    /// Structure of arrays.
+    ///
+    /// The hash table stores an array of keys, followed by an array of values.
+    /// A probe that only compares keys thus loads only keys, and the keys of
+    /// neighboring slots are contiguous in memory. On GPUs, this improves the
+    /// coalescing of the probe's key loads.
+    ///
+    /// ## Limitations
+    ///
+    /// - only supported by the `LinearProbing` hashing scheme
+    /// - only supported by `build` and `probe_sum`, including their key-only
+    ///   variants
+    /// - not supported for narrow join keys and custom hash functions
+    SoA,
+}
+
+impl Default for HashTableLayout {
+    fn default() -> Self {
+        HashTableLayout::AoS
+    }
 }
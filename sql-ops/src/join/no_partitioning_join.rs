@@ -29,25 +29,80 @@
 //! that specify the parallelism with which to execute on the GPU. The join
 //! can also be parallelized over multiple GPUs by calling the methods multiple
 //! times using different CUDA devices.
+//!
+//! ## Narrow keys
+//!
+//! Dictionary-encoded columns often have small key domains that fit into `u8`
+//! or `u16` join keys. Narrow keys save bandwidth compared to 32-bit keys. They
+//! are supported only with the `Perfect` hashing scheme, for which the hash
+//! table covers the whole key domain and is directly addressed by the key.
+//! Radix partitioning does not support narrow keys, as a directly-addressed
+//! hash table with at most 2^16 entries already fits into the GPU's L2 cache.
+//!
+//! ## Key-only joins
+//!
+//! Semi-joins and `COUNT(*)` queries don't read the payloads. For these
+//! queries, `build_keys` and `probe_count` skip the payload attributes
+//! entirely. The hash table then stores a dummy payload, and the probe counts
+//! the matches instead of summing up the payloads.
+//!
+//! ## Hash table layouts
+//!
+//! By default, the hash table is an array of key-value entries (`AoS`).
+//! Alternatively, the `SoA` layout stores all keys in one array and all values
+//! in another, see `HashTableLayout` for details. The layout is a property of
+//! the `HashTable`, and thus the same for all operators that share the table.
+//!
+//! ## Probe steps
+//!
+//! On a collision, the `LinearProbing` scheme advances by linear or by
+//! quadratic steps, see `ProbeStep`. The step decides where the build inserts
+//! a key. Thus, the build and the probe must use the same step.
+//!
+//! ## Empty relations
+//!
+//! All operators accept empty build and probe relations. On the GPU, the
+//! operators return without launching a kernel. Thus, an empty build leaves
+//! the hash table empty, and an empty probe leaves the result set unchanged.
 
-use super::{HashingScheme, HtEntry};
+use super::custom_hash::{self, AtomicKey};
+use super::validity::ValidityBitmap;
+use super::{HashTableLayout, HashingScheme, HtEntry, ProbeStep};
 use crate::error::{ErrorKind, Result};
+use crate::prefix_scan::exclusive_prefix_sum;
+use crate::FileTypeTag;
 use cstr::cstr;
-use cuda_driver_sys::cuMemsetD32_v2;
+use cuda_driver_sys::{
+    cuMemsetD16_v2, cuMemsetD32_v2, cuOccupancyMaxPotentialBlockSize, CUfunction,
+};
+use data_store::relation::Relation;
 use datagen::relation::KeyAttribute;
 use likwid;
 use num_traits::cast::AsPrimitive;
 use numa_gpu::error::Result as NumaGpuResult;
 use numa_gpu::error::ToResult;
 use numa_gpu::runtime::allocator;
+use numa_gpu::runtime::cpu_affinity::CpuAffinity;
+use numa_gpu::runtime::cuda_wrapper;
+use numa_gpu::runtime::l2_persistence;
 use numa_gpu::runtime::memory::*;
+use numa_gpu::runtime::numa::{self, PageType};
 use rustacuda::context::CurrentContext;
-use rustacuda::function::{BlockSize, GridSize};
+use rustacuda::device::DeviceAttribute;
+use rustacuda::event::{Event, EventFlags, EventStatus};
+use rustacuda::function::{BlockSize, Function, GridSize};
 use rustacuda::launch;
-use rustacuda::memory::DeviceCopy;
+use rustacuda::memory::{AsyncCopyDestination, DeviceCopy, LockedBuffer};
 use rustacuda::prelude::*;
-use std::mem::size_of;
-use std::os::raw::{c_uint, c_void};
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::mem::{size_of, size_of_val, transmute_copy};
+use std::os::raw::{c_int, c_uint, c_void};
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 
 extern "C" {
@@ -85,6 +140,130 @@ extern "C" {
         aggregation_result: *mut u64,
     );
 
+    fn cpu_ht_build_quadraticprobing_int32(
+        hash_table: *mut HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+    );
+
+    fn cpu_ht_build_quadraticprobing_int64(
+        hash_table: *mut HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+    );
+
+    fn cpu_ht_probe_aggregate_quadraticprobing_int32(
+        hash_table: *const HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+        aggregation_result: *mut u64,
+    );
+
+    fn cpu_ht_probe_aggregate_quadraticprobing_int64(
+        hash_table: *const HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+        aggregation_result: *mut u64,
+    );
+
+    fn cpu_ht_build_linearprobing_soa_int32(
+        hash_table: *mut i32,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+    );
+
+    fn cpu_ht_build_linearprobing_soa_int64(
+        hash_table: *mut i64,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+    );
+
+    fn cpu_ht_probe_aggregate_linearprobing_soa_int32(
+        hash_table: *const i32,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+        aggregation_result: *mut u64,
+    );
+
+    fn cpu_ht_probe_aggregate_linearprobing_soa_int64(
+        hash_table: *const i64,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+        aggregation_result: *mut u64,
+    );
+
+    fn cpu_ht_build_cuckoo_int32(
+        hash_table: *mut HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+        seed: *mut u32,
+    ) -> bool;
+
+    fn cpu_ht_build_cuckoo_int64(
+        hash_table: *mut HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+        seed: *mut u32,
+    ) -> bool;
+
+    fn cpu_ht_probe_aggregate_cuckoo_int32(
+        hash_table: *const HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        seed: u32,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+        aggregation_result: *mut u64,
+    );
+
+    fn cpu_ht_probe_aggregate_cuckoo_int64(
+        hash_table: *const HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        seed: u32,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+        aggregation_result: *mut u64,
+    );
+
+    #[cfg(test)]
+    fn cpu_ht_findkey_cuckoo_int32(
+        hash_table: *const HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        seed: u32,
+        key: i32,
+        slot_reads: *mut u32,
+    ) -> bool;
+
+    #[cfg(test)]
+    fn cpu_ht_findkey_cuckoo_int64(
+        hash_table: *const HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        seed: u32,
+        key: i64,
+        slot_reads: *mut u32,
+    ) -> bool;
+
     fn cpu_ht_build_perfect_int32(
         hash_table: *mut HtEntry<i32, i32>,
         hash_table_entries: u64,
@@ -117,6 +296,62 @@ extern "C" {
         data_length: u64,
     );
 
+    fn cpu_ht_build_linearprobing_report_int32(
+        hash_table: *mut HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+        report: *mut BuildReport,
+    );
+
+    fn cpu_ht_build_quadraticprobing_report_int32(
+        hash_table: *mut HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+        report: *mut BuildReport,
+    );
+
+    fn cpu_ht_build_linearprobing_report_int64(
+        hash_table: *mut HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+        report: *mut BuildReport,
+    );
+
+    fn cpu_ht_build_quadraticprobing_report_int64(
+        hash_table: *mut HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+        report: *mut BuildReport,
+    );
+
+    fn cpu_ht_build_perfect_report_int32(
+        hash_table: *mut HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+        is_selective: bool,
+        report: *mut BuildReport,
+    );
+
+    fn cpu_ht_build_perfect_report_int64(
+        hash_table: *mut HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+        is_selective: bool,
+        report: *mut BuildReport,
+    );
+
     fn cpu_ht_probe_aggregate_perfect_int32(
         hash_table: *const HtEntry<i32, i32>,
         hash_table_entries: u64,
@@ -134,6 +369,199 @@ extern "C" {
         data_length: u64,
         aggregation_result: *mut u64,
     );
+
+    fn cpu_ht_build_perfect_uint8(
+        hash_table: *mut HtEntry<u8, u8>,
+        hash_table_entries: u64,
+        join_attr_data: *const u8,
+        payload_attr_data: *const u8,
+        data_length: u64,
+    );
+
+    fn cpu_ht_build_selective_perfect_uint8(
+        hash_table: *mut HtEntry<u8, u8>,
+        hash_table_entries: u64,
+        join_attr_data: *const u8,
+        payload_attr_data: *const u8,
+        data_length: u64,
+    );
+
+    fn cpu_ht_probe_aggregate_perfect_uint8(
+        hash_table: *const HtEntry<u8, u8>,
+        hash_table_entries: u64,
+        join_attr_data: *const u8,
+        payload_attr_data: *const u8,
+        data_length: u64,
+        aggregation_result: *mut u64,
+    );
+
+    fn cpu_ht_build_perfect_uint16(
+        hash_table: *mut HtEntry<u16, u16>,
+        hash_table_entries: u64,
+        join_attr_data: *const u16,
+        payload_attr_data: *const u16,
+        data_length: u64,
+    );
+
+    fn cpu_ht_build_selective_perfect_uint16(
+        hash_table: *mut HtEntry<u16, u16>,
+        hash_table_entries: u64,
+        join_attr_data: *const u16,
+        payload_attr_data: *const u16,
+        data_length: u64,
+    );
+
+    fn cpu_ht_probe_aggregate_perfect_uint16(
+        hash_table: *const HtEntry<u16, u16>,
+        hash_table_entries: u64,
+        join_attr_data: *const u16,
+        payload_attr_data: *const u16,
+        data_length: u64,
+        aggregation_result: *mut u64,
+    );
+
+    fn cpu_ht_probe_bitmap_perfect_int32(
+        hash_table: *const HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        data_length: u64,
+        bitmap: *mut u64,
+    );
+
+    fn cpu_ht_probe_bitmap_perfect_int64(
+        hash_table: *const HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        data_length: u64,
+        bitmap: *mut u64,
+    );
+
+    fn cpu_ht_probe_bitmap_linearprobing_int32(
+        hash_table: *const HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        data_length: u64,
+        bitmap: *mut u64,
+    );
+
+    fn cpu_ht_probe_bitmap_linearprobing_int64(
+        hash_table: *const HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        data_length: u64,
+        bitmap: *mut u64,
+    );
+
+    fn cpu_ht_probe_bitmap_cuckoo_int32(
+        hash_table: *const HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        seed: u32,
+        join_attr_data: *const i32,
+        data_length: u64,
+        bitmap: *mut u64,
+    );
+
+    fn cpu_ht_probe_bitmap_cuckoo_int64(
+        hash_table: *const HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        seed: u32,
+        join_attr_data: *const i64,
+        data_length: u64,
+        bitmap: *mut u64,
+    );
+
+    fn cpu_ht_probe_materialize_linearprobing_int32(
+        hash_table: *const HtEntry<i32, i32>,
+        hash_table_entries: u64,
+        join_attr_data: *const i32,
+        payload_attr_data: *const i32,
+        data_length: u64,
+        join_result: *mut JoinMatch<i32>,
+        join_result_capacity: u64,
+        stop_at_capacity: u32,
+    ) -> u64;
+
+    fn cpu_ht_probe_materialize_linearprobing_int64(
+        hash_table: *const HtEntry<i64, i64>,
+        hash_table_entries: u64,
+        join_attr_data: *const i64,
+        payload_attr_data: *const i64,
+        data_length: u64,
+        join_result: *mut JoinMatch<i64>,
+        join_result_capacity: u64,
+        stop_at_capacity: u32,
+    ) -> u64;
+}
+
+/// Returns the number of `u64` words in a semi-join bitmap of `tuples` probe
+/// tuples.
+///
+/// The bitmap is written by `probe_bitmap`, which sets bit `i % 64` of word
+/// `i / 64` iff probe tuple `i` has a match. The anti-join bitmap written by
+/// `CpuHashJoin::probe_anti_bitmap` has the same length.
+pub fn probe_bitmap_len(tuples: usize) -> usize {
+    (tuples + u64::BITS as usize - 1) / u64::BITS as usize
+}
+
+/// A materialized join result, i.e., the build and probe payloads of a match.
+///
+/// Written by `probe_materialize`. Note that the struct's layout must be kept
+/// in sync with its counterpart in C++.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct JoinMatch<T> {
+    pub build_payload: T,
+    pub probe_payload: T,
+}
+
+unsafe impl<T: DeviceCopy> DeviceCopy for JoinMatch<T> {}
+
+/// Diagnostics of a hash table build, written by
+/// `CpuHashJoin::build_with_report`.
+///
+/// Note that the struct's layout must be kept in sync with its counterpart in
+/// C++.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct BuildReport {
+    /// The number of inserted tuples.
+    pub inserted: u64,
+
+    /// The number of tuples that couldn't be inserted.
+    ///
+    /// A linear probing insert fails if the hash table is full. A perfect
+    /// hashing insert fails if the key is a duplicate, or if the key lies
+    /// outside of the hash table.
+    pub failed: u64,
+
+    /// The maximum number of slots that an insert probed.
+    ///
+    /// Perfect hashing always probes a single slot.
+    pub max_probe_len: u64,
+}
+
+/// The outcome of a materializing probe with a result cap.
+///
+/// Returned by `probe_materialize_limit`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MaterializeResult {
+    /// The number of matches written into the join result.
+    pub written: usize,
+
+    /// Whether the probe found more matches than the cap allowed.
+    pub overflowed: bool,
+}
+
+impl MaterializeResult {
+    /// Converts the match count of a saturating probe into a result.
+    ///
+    /// A saturating probe counts at most one match beyond the cap.
+    fn from_saturated_matches(matches: usize, cap: usize) -> Self {
+        Self {
+            written: matches.min(cap),
+            overflowed: matches > cap,
+        }
+    }
 }
 
 /// Specifies that the implementing type can be used as a join key in
@@ -152,42 +580,105 @@ extern "C" {
 /// the RFC.
 pub trait CudaHashJoinable: DeviceCopy + KeyAttribute {
     /// Implements `CudaHashJoin::build` for the implementing type.
+    ///
+    /// Without a payload attribute, the hash table stores a dummy payload.
     fn build_impl(
         hj: &CudaHashJoin<Self>,
         join_attr: LaunchableSlice<'_, Self>,
-        payload_attr: LaunchableSlice<'_, Self>,
+        payload_attr: Option<LaunchableSlice<'_, Self>>,
         stream: &Stream,
     ) -> Result<()>;
 
     /// Implements `CudaHashJoin::probe_sum` for the implementing type.
+    ///
+    /// Without a payload attribute, the probe counts the matches.
     fn probe_sum_impl(
         hj: &CudaHashJoin<Self>,
         join_attr: LaunchableSlice<'_, Self>,
-        payload_attr: LaunchableSlice<'_, Self>,
+        payload_attr: Option<LaunchableSlice<'_, Self>>,
         result_set: &Mem<u64>,
         stream: &Stream,
     ) -> Result<()>;
-}
 
-/// Specifies that the implementing type can be used as a join key in
-/// `CpuHashJoin`.
-///
-/// See `CudaHashJoinable` for more details on the design decision.
-pub trait CpuHashJoinable: DeviceCopy + KeyAttribute {
-    /// Implements `CpuHashJoin::build` for the implementing type.
-    fn build_impl(
-        hj: &mut CpuHashJoin<Self>,
-        join_attr: &[Self],
-        payload_attr: &[Self],
-    ) -> Result<()>;
+    /// Implements `CudaHashJoin::probe_bitmap` for the implementing type.
+    fn probe_bitmap_impl(
+        hj: &CudaHashJoin<Self>,
+        join_attr: LaunchableSlice<'_, Self>,
+        bitmap: &Mem<u64>,
+        stream: &Stream,
+    ) -> Result<()>;
+
+    /// Implements `CudaHashJoin::probe_materialize` for the implementing type.
+    ///
+    /// With a `limit`, at most `limit` matches are written, and the returned
+    /// match count saturates at `limit + 1`. The `limit` must not exceed
+    /// `join_result.len()`. With `stable_output`, the matches are written in
+    /// probe relation order, which requires a counting pass.
+    fn probe_materialize_impl(
+        hj: &CudaHashJoin<Self>,
+        join_attr: LaunchableSlice<'_, Self>,
+        payload_attr: LaunchableSlice<'_, Self>,
+        join_result: &Mem<JoinMatch<Self>>,
+        limit: Option<usize>,
+        stable_output: bool,
+        stream: &Stream,
+    ) -> Result<usize>;
+
+    /// Returns the name of the build kernel for the hashing scheme.
+    fn build_kernel_name(hashing_scheme: HashingScheme) -> Result<&'static str>;
+
+    /// Returns the name of the probe kernel for the hashing scheme.
+    fn probe_kernel_name(hashing_scheme: HashingScheme) -> Result<&'static str>;
+}
+
+/// Specifies that the implementing type can be used as a join key in
+/// `CpuHashJoin`.
+///
+/// See `CudaHashJoinable` for more details on the design decision.
+pub trait CpuHashJoinable: DeviceCopy + KeyAttribute {
+    /// Implements `CpuHashJoin::build` for the implementing type.
+    ///
+    /// Without a payload attribute, the hash table stores a dummy payload.
+    fn build_impl(
+        hj: &mut CpuHashJoin<Self>,
+        join_attr: &[Self],
+        payload_attr: Option<&[Self]>,
+    ) -> Result<()>;
+
+    /// Implements `CpuHashJoin::build_with_report` for the implementing type.
+    fn build_report_impl(
+        hj: &mut CpuHashJoin<Self>,
+        join_attr: &[Self],
+        payload_attr: Option<&[Self]>,
+    ) -> Result<BuildReport>;
 
     /// Implements `CpuHashJoin::probe_sum` for the implementing type.
+    ///
+    /// Without a payload attribute, the probe counts the matches.
     fn probe_sum_impl(
         hj: &mut CpuHashJoin<Self>,
         join_attr: &[Self],
-        payload_attr: &[Self],
+        payload_attr: Option<&[Self]>,
         join_result: &mut u64,
     ) -> Result<()>;
+
+    /// Implements `CpuHashJoin::probe_bitmap` for the implementing type.
+    fn probe_bitmap_impl(
+        hj: &mut CpuHashJoin<Self>,
+        join_attr: &[Self],
+        bitmap: &mut [u64],
+    ) -> Result<()>;
+
+    /// Implements `CpuHashJoin::probe_materialize` for the implementing type.
+    ///
+    /// See `CudaHashJoinable::probe_materialize_impl` for the `limit`.
+    fn probe_materialize_impl(
+        hj: &mut CpuHashJoin<Self>,
+        join_attr: &[Self],
+        payload_attr: &[Self],
+        join_result: &mut [JoinMatch<Self>],
+        limit: Option<usize>,
+    ) -> Result<usize>;
 }
 
 /// GPU hash join implemented in CUDA.
@@ -201,10 +692,16 @@ pub trait CpuHashJoinable: DeviceCopy + KeyAttribute {
 #[derive(Debug)]
 pub struct CudaHashJoin<T: DeviceCopy + KeyAttribute> {
     hashing_scheme: HashingScheme,
+    probe_step: ProbeStep,
     is_selective: bool,
     hash_table: Arc<HashTable<T>>,
     build_dim: (GridSize, BlockSize),
     probe_dim: (GridSize, BlockSize),
+    count_granularity: CountGranularity,
+    stable_output: bool,
+    read_only_cache: bool,
+    l2_persisting: bool,
+    placement: HashTablePlacement,
 }
 
 /// CPU hash join implemented in C++.
@@ -218,39 +715,174 @@ pub struct CudaHashJoin<T: DeviceCopy + KeyAttribute> {
 #[derive(Debug)]
 pub struct CpuHashJoin<T: DeviceCopy + KeyAttribute> {
     hashing_scheme: HashingScheme,
+    probe_step: ProbeStep,
     is_selective: bool,
     hash_table: Arc<HashTable<T>>,
+    hash_fn: Option<CpuHashFn<T>>,
 }
 
+/// A user-defined hash function for `CpuHashJoin`.
+///
+/// The hash table index is computed from the lower bits of the hash value.
+/// Thus, the function should mix the key's bits into the lower bits.
+pub type CpuHashFn<T> = fn(T) -> u64;
+
+/// Identifies a file that contains a saved `HashTable`.
+const HT_FILE_MAGIC: [u8; 8] = *b"NGPUHTBL";
+
+/// Version of the hash table file layout. Must be incremented when the layout
+/// changes.
+const HT_FILE_VERSION: u32 = 2;
+
+/// Byte offset of the hash table entries in the file.
+///
+/// The entries are mapped directly into memory, thus the offset must be a
+/// multiple of the page size. 64 KiB covers both 4 KiB pages (x86_64) and
+/// 64 KiB pages (POWER9).
+const HT_FILE_ENTRIES_OFFSET: u64 = 1 << 16;
+
 /// Hash table for `CpuHashJoin` and `CudaHashJoin`.
 #[derive(Debug)]
 pub struct HashTable<T: DeviceCopy + KeyAttribute> {
     mem: Mem<HtEntry<T, T>>,
     size: usize,
+    cuckoo_seed: AtomicU32,
+    cuckoo_is_built: AtomicBool,
+    layout: HashTableLayout,
+}
+
+/// Copies of a CPU hash table, one per NUMA node.
+///
+/// The hash table is read-only after the build. Thus, the probe threads on
+/// each NUMA node can read a local copy instead of sending remote accesses to
+/// a single shared hash table. Created by `HashTable::replicate_across_nodes`.
+#[derive(Debug)]
+pub struct ReplicatedHashTable<T: DeviceCopy + KeyAttribute> {
+    replicas: Vec<(u16, Arc<HashTable<T>>)>,
+}
+
+/// Granularity of the GPU probe's result set.
+///
+/// Per-thread results avoid contention, but require a buffer with an entry
+/// for each thread, and a reduction afterwards. Coarser granularities shrink
+/// the buffer at the cost of atomic updates.
+///
+/// Atomic updates add the partial results in a nondeterministic order. For a
+/// total that is reproducible also for floating-point aggregates, use
+/// `PerThread` and sum the results with `reduction::GpuTreeSum`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum CountGranularity {
+    /// One result per thread, i.e., `grid size * block size` results.
+    PerThread = 0,
+
+    /// One result per thread block, i.e., `grid size` results.
+    PerBlock = 1,
+
+    /// A single result that all threads update atomically.
+    Global = 2,
+}
+
+impl CountGranularity {
+    /// Returns the number of results for the given probe dimensions.
+    pub fn result_len(self, grid: &GridSize, block: &BlockSize) -> usize {
+        match self {
+            CountGranularity::PerThread => (grid.x * block.x) as usize,
+            CountGranularity::PerBlock => grid.x as usize,
+            CountGranularity::Global => 1,
+        }
+    }
+}
+
+impl Default for CountGranularity {
+    fn default() -> Self {
+        CountGranularity::PerThread
+    }
+}
+
+/// Placement of the hash table during the GPU probe.
+///
+/// `CudaHashJoinBuilder` chooses the placement when it builds the join.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HashTablePlacement {
+    /// All threads probe the shared hash table in global memory.
+    Global,
+
+    /// Each thread block copies the hash table into its shared memory before
+    /// probing its private copy.
+    ///
+    /// Chosen only if enabled with `CudaHashJoinBuilder::shared_memory_placement`,
+    /// and if the hash table fits into the shared memory budget of a thread
+    /// block. Requires linear probing with the `AoS` layout. The probe reads
+    /// shared memory, thus the read-only cache setting doesn't apply.
+    SharedMemory,
 }
 
 /// Build a `CudaHashJoin`.
 #[derive(Clone, Debug)]
 pub struct CudaHashJoinBuilder<T: DeviceCopy + KeyAttribute> {
     hashing_scheme: HashingScheme,
+    probe_step: ProbeStep,
     is_selective: bool,
     hash_table_i: Option<Arc<HashTable<T>>>,
     build_dim_i: (GridSize, BlockSize),
     probe_dim_i: (GridSize, BlockSize),
+    count_granularity_i: CountGranularity,
+    stable_output_i: bool,
+    read_only_cache_i: bool,
+    l2_persisting_i: bool,
+    shared_memory_placement_i: bool,
+    shared_memory_budget_i: Option<usize>,
 }
 
 /// Build a `CpuHashJoin`.
 #[derive(Clone, Debug)]
 pub struct CpuHashJoinBuilder<T: DeviceCopy + KeyAttribute> {
     hashing_scheme: HashingScheme,
+    probe_step: ProbeStep,
     is_selective: bool,
     hash_table_i: Option<Arc<HashTable<T>>>,
+    hash_fn_i: Option<CpuHashFn<T>>,
+}
+
+/// Completion handle of an asynchronous probe.
+///
+/// Returned by `CudaHashJoin::probe_sum_async`. The handle owns the result
+/// buffers, and borrows the probed relation until the probe completes.
+///
+/// Dropping the handle blocks until the probe completes, because the GPU
+/// still writes into the result buffers.
+#[derive(Debug)]
+pub struct ProbeSumHandle<'a> {
+    // Keeps the device buffer alive until the copy to the CPU completes
+    _result_set: Mem<u64>,
+    result_set_host: LockedBuffer<u64>,
+    event: Event,
+    phantom: PhantomData<&'a ()>,
 }
 
 impl<T> CudaHashJoin<T>
 where
     T: DeviceCopy + KeyAttribute + CudaHashJoinable,
 {
+    /// Computes the launch dimensions that maximize the occupancy of the
+    /// build kernel on the device.
+    pub fn max_occupancy_build_dim(
+        hashing_scheme: HashingScheme,
+        device: &Device,
+    ) -> Result<(GridSize, BlockSize)> {
+        max_occupancy_launch_dim(T::build_kernel_name(hashing_scheme)?, device)
+    }
+
+    /// Computes the launch dimensions that maximize the occupancy of the
+    /// probe kernel on the device.
+    pub fn max_occupancy_probe_dim(
+        hashing_scheme: HashingScheme,
+        device: &Device,
+    ) -> Result<(GridSize, BlockSize)> {
+        max_occupancy_launch_dim(T::probe_kernel_name(hashing_scheme)?, device)
+    }
+
     /// Build a hash table on the GPU.
     pub fn build(
         &self,
@@ -258,7 +890,16 @@ where
         payload_attr: LaunchableSlice<'_, T>,
         stream: &Stream,
     ) -> Result<()> {
-        T::build_impl(self, join_attr, payload_attr, stream)
+        T::build_impl(self, join_attr, Some(payload_attr), stream)
+    }
+
+    /// Build a hash table on the GPU from only the join attribute.
+    ///
+    /// The hash table doesn't store payloads. Thus, it only supports probes
+    /// that don't read the build payload, i.e., `probe_count` and
+    /// `probe_bitmap`.
+    pub fn build_keys(&self, join_attr: LaunchableSlice<'_, T>, stream: &Stream) -> Result<()> {
+        T::build_impl(self, join_attr, None, stream)
     }
 
     /// Probe the hash table on the GPU and sum the payload attribute rows.
@@ -267,6 +908,10 @@ where
     /// ```SQL
     /// SELECT SUM(s.payload_attr) FROM r JOIN s ON r.join_attr = s.join_attr
     /// ```
+    ///
+    /// The partial sums are added to `result_set`, which must hold at least
+    /// `result_set_len` zero-initialized entries. The sum is the total over
+    /// all entries.
     pub fn probe_sum(
         &self,
         join_attr: LaunchableSlice<'_, T>,
@@ -274,7 +919,289 @@ where
         result_set: &Mem<u64>,
         stream: &Stream,
     ) -> Result<()> {
-        T::probe_sum_impl(self, join_attr, payload_attr, result_set, stream)
+        self.persist_hash_table(stream)?;
+        let result = T::probe_sum_impl(self, join_attr, Some(payload_attr), result_set, stream);
+        self.reset_hash_table_persistence(stream)?;
+        result
+    }
+
+    /// Probe the hash table on the GPU and count the matches.
+    ///
+    /// This effectively implements the SQL code:
+    /// ```SQL
+    /// SELECT COUNT(*) FROM r JOIN s ON r.join_attr = s.join_attr
+    /// ```
+    ///
+    /// In contrast to `probe_sum`, the probe doesn't read a payload attribute,
+    /// which saves memory bandwidth. `result_set` has the same requirements as
+    /// for `probe_sum`.
+    pub fn probe_count(
+        &self,
+        join_attr: LaunchableSlice<'_, T>,
+        result_set: &Mem<u64>,
+        stream: &Stream,
+    ) -> Result<()> {
+        self.persist_hash_table(stream)?;
+        let result = T::probe_sum_impl(self, join_attr, None, result_set, stream);
+        self.reset_hash_table_persistence(stream)?;
+        result
+    }
+
+    /// Build a hash table on the GPU from a relation.
+    ///
+    /// Equivalent to `build` with the relation's key and value attributes.
+    pub fn build_relation(&self, relation: &Relation<T, T>, stream: &Stream) -> Result<()> {
+        let (join_attr, payload_attr) = relation.as_launchable_slices();
+        self.build(join_attr, payload_attr, stream)
+    }
+
+    /// Probe the hash table on the GPU with a relation and sum its payload
+    /// attribute rows.
+    ///
+    /// Equivalent to `probe_sum` with the relation's key and value attributes.
+    pub fn probe_sum_relation(
+        &self,
+        relation: &Relation<T, T>,
+        result_set: &Mem<u64>,
+        stream: &Stream,
+    ) -> Result<()> {
+        let (join_attr, payload_attr) = relation.as_launchable_slices();
+        self.probe_sum(join_attr, payload_attr, result_set, stream)
+    }
+
+    /// Probe the hash table on the GPU and mark the matching probe tuples in
+    /// a bitmap.
+    ///
+    /// This effectively implements a semi-join, e.g., the SQL code:
+    /// ```SQL
+    /// SELECT * FROM s WHERE EXISTS (SELECT * FROM r WHERE r.join_attr = s.join_attr)
+    /// ```
+    ///
+    /// Sets bit `i` iff `join_attr[i]` has a match. `bitmap` must hold at
+    /// least `probe_bitmap_len(join_attr.len())` zero-initialized words. The
+    /// bits are set with an atomic OR, thus bits that are already set remain
+    /// set.
+    pub fn probe_bitmap(
+        &self,
+        join_attr: LaunchableSlice<'_, T>,
+        bitmap: &Mem<u64>,
+        stream: &Stream,
+    ) -> Result<()> {
+        self.persist_hash_table(stream)?;
+        let result = T::probe_bitmap_impl(self, join_attr, bitmap, stream);
+        self.reset_hash_table_persistence(stream)?;
+        result
+    }
+
+    /// Probe the hash table on the GPU and write the payloads of all matches
+    /// into the join result.
+    ///
+    /// This effectively implements the SQL code:
+    /// ```SQL
+    /// SELECT r.payload_attr, s.payload_attr FROM r JOIN s ON r.join_attr = s.join_attr
+    /// ```
+    ///
+    /// Returns the number of matches. If there are more matches than
+    /// `join_result.len()`, the surplus matches are not written. The output
+    /// order depends on the thread schedule, unless the join is built with
+    /// `stable_output`. Only the `LinearProbing` hashing scheme is supported.
+    ///
+    /// The method blocks until the probe completes.
+    pub fn probe_materialize(
+        &self,
+        join_attr: LaunchableSlice<'_, T>,
+        payload_attr: LaunchableSlice<'_, T>,
+        join_result: &Mem<JoinMatch<T>>,
+        stream: &Stream,
+    ) -> Result<usize> {
+        self.persist_hash_table(stream)?;
+        let result = T::probe_materialize_impl(
+            self,
+            join_attr,
+            payload_attr,
+            join_result,
+            None,
+            self.stable_output,
+            stream,
+        );
+        self.reset_hash_table_persistence(stream)?;
+        result
+    }
+
+    /// Probe the hash table on the GPU and write at most `max_results` matches
+    /// into the join result.
+    ///
+    /// The cap is the smaller one of `max_results` and `join_result.len()`.
+    /// Once the cap is reached, each GPU thread stops probing its remaining
+    /// tuples, because the output cursor saturates instead of counting all
+    /// surplus matches. Which matches are written depends on the thread
+    /// schedule, unless the join is built with `stable_output`. In that case,
+    /// the written matches are a prefix of the full result in probe relation
+    /// order.
+    ///
+    /// Returns the number of written matches, and whether there were more
+    /// matches than the cap.
+    pub fn probe_materialize_limit(
+        &self,
+        join_attr: LaunchableSlice<'_, T>,
+        payload_attr: LaunchableSlice<'_, T>,
+        join_result: &Mem<JoinMatch<T>>,
+        max_results: Option<usize>,
+        stream: &Stream,
+    ) -> Result<MaterializeResult> {
+        let cap = max_results.map_or(join_result.len(), |max| max.min(join_result.len()));
+        self.persist_hash_table(stream)?;
+        let matches = T::probe_materialize_impl(
+            self,
+            join_attr,
+            payload_attr,
+            join_result,
+            Some(cap),
+            self.stable_output,
+            stream,
+        );
+        self.reset_hash_table_persistence(stream)?;
+
+        Ok(MaterializeResult::from_saturated_matches(matches?, cap))
+    }
+
+    /// Probe the hash table on the GPU and append the matches to the join
+    /// result in a single pass.
+    ///
+    /// The GPU threads append their matches at a global atomic output cursor.
+    /// This skips the counting pass and the prefix sum of `stable_output`,
+    /// even if the join is built with `stable_output`. In exchange, the output
+    /// order depends on the thread schedule. Thus, the single pass suits
+    /// small results that are bounded by `join_result.len()`.
+    ///
+    /// Once `join_result` is full, each GPU thread stops probing its remaining
+    /// tuples. Returns the number of written matches, and whether there were
+    /// more matches than `join_result.len()`.
+    pub fn probe_materialize_single_pass(
+        &self,
+        join_attr: LaunchableSlice<'_, T>,
+        payload_attr: LaunchableSlice<'_, T>,
+        join_result: &Mem<JoinMatch<T>>,
+        stream: &Stream,
+    ) -> Result<MaterializeResult> {
+        let cap = join_result.len();
+        self.persist_hash_table(stream)?;
+        let matches = T::probe_materialize_impl(
+            self,
+            join_attr,
+            payload_attr,
+            join_result,
+            Some(cap),
+            false,
+            stream,
+        );
+        self.reset_hash_table_persistence(stream)?;
+
+        Ok(MaterializeResult::from_saturated_matches(matches?, cap))
+    }
+
+    /// Returns the minimum length of the probe's result set.
+    ///
+    /// The length depends on the probe dimensions and the count granularity.
+    pub fn result_set_len(&self) -> usize {
+        let (grid, block) = &self.probe_dim;
+        self.count_granularity.result_len(grid, block)
+    }
+
+    /// Returns the placement of the hash table in `probe_sum` and
+    /// `probe_count`.
+    pub fn placement(&self) -> HashTablePlacement {
+        self.placement
+    }
+
+    /// Sets the L2 access policy window over the hash table, if the join is
+    /// built with `l2_persisting`.
+    ///
+    /// The builder already set aside the L2 cache, thus this only sets the
+    /// window on `stream`.
+    fn persist_hash_table(&self, stream: &Stream) -> Result<()> {
+        if self.l2_persisting {
+            l2_persistence::set_access_policy_window(
+                stream,
+                self.hash_table.mem.as_launchable_slice(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the L2 access policy window after the probe is launched, if
+    /// the join is built with `l2_persisting`.
+    ///
+    /// Thus, later kernels on `stream` don't evict other data in favor of the
+    /// hash table.
+    fn reset_hash_table_persistence(&self, stream: &Stream) -> Result<()> {
+        if self.l2_persisting {
+            l2_persistence::reset_access_policy_window(stream)?;
+        }
+
+        Ok(())
+    }
+
+    /// Probe the hash table on the GPU and sum the payload attribute rows,
+    /// without blocking.
+    ///
+    /// Enqueues the probe and the copy of the partial sums to the CPU on
+    /// `stream`, and returns immediately. The result is retrieved from the
+    /// returned handle by `wait`, whereas `poll` checks for completion.
+    pub fn probe_sum_async<'a>(
+        &'a self,
+        join_attr: LaunchableSlice<'a, T>,
+        payload_attr: LaunchableSlice<'a, T>,
+        stream: &Stream,
+    ) -> Result<ProbeSumHandle<'a>> {
+        let result_len = self.result_set_len();
+
+        let mut result_set = Mem::CudaDevMem(unsafe { DeviceBuffer::uninitialized(result_len)? });
+        cuda_wrapper::memset_async(result_set.as_launchable_mut_slice(), 0, stream)?;
+
+        self.persist_hash_table(stream)?;
+        let result = T::probe_sum_impl(self, join_attr, Some(payload_attr), &result_set, stream);
+        self.reset_hash_table_persistence(stream)?;
+        result?;
+
+        let mut result_set_host = LockedBuffer::new(&0, result_len)?;
+        if let Mem::CudaDevMem(ref results) = result_set {
+            unsafe { results.async_copy_to(result_set_host.as_mut_slice(), stream)? };
+        }
+
+        let event = Event::new(EventFlags::DISABLE_TIMING)?;
+        event.record(stream)?;
+
+        Ok(ProbeSumHandle {
+            _result_set: result_set,
+            result_set_host,
+            event,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<'a> ProbeSumHandle<'a> {
+    /// Returns `true` if the probe has completed. Never blocks.
+    pub fn poll(&self) -> Result<bool> {
+        match self.event.query()? {
+            EventStatus::Ready => Ok(true),
+            EventStatus::NotReady => Ok(false),
+        }
+    }
+
+    /// Blocks until the probe has completed, and returns the sum.
+    pub fn wait(self) -> Result<u64> {
+        self.event.synchronize()?;
+        Ok(self.result_set_host.iter().sum())
+    }
+}
+
+impl<'a> Drop for ProbeSumHandle<'a> {
+    fn drop(&mut self) {
+        // Don't free the result buffers while the GPU is still writing them
+        let _ = self.event.synchronize();
     }
 }
 
@@ -284,7 +1211,31 @@ where
 {
     /// Build a hash table on the CPU.
     pub fn build(&mut self, join_attr: &[T], payload_attr: &[T]) -> Result<()> {
-        T::build_impl(self, join_attr, payload_attr)
+        T::build_impl(self, join_attr, Some(payload_attr))
+    }
+
+    /// Build a hash table on the CPU from only the join attribute.
+    ///
+    /// See `CudaHashJoin::build_keys` for details.
+    pub fn build_keys(&mut self, join_attr: &[T]) -> Result<()> {
+        T::build_impl(self, join_attr, None)
+    }
+
+    /// Build a hash table on the CPU, and report the inserts that failed.
+    ///
+    /// In contrast to `build`, keys that don't fit into the hash table neither
+    /// cause an error nor are dropped silently. Instead, the build continues
+    /// and counts them in the `BuildReport`. Under perfect hashing, the first
+    /// occurrence of a duplicate key stays in the hash table.
+    ///
+    /// Only the built-in hash functions of the `Perfect` and `LinearProbing`
+    /// schemes with the AoS layout report their inserts.
+    pub fn build_with_report(
+        &mut self,
+        join_attr: &[T],
+        payload_attr: &[T],
+    ) -> Result<BuildReport> {
+        T::build_report_impl(self, join_attr, Some(payload_attr))
     }
 
     /// Probe the hash table on the CPU and sum the payload attribute rows.
@@ -299,74 +1250,296 @@ where
         payload_attr: &[T],
         join_result: &mut u64,
     ) -> Result<()> {
-        T::probe_sum_impl(self, join_attr, payload_attr, join_result)
+        T::probe_sum_impl(self, join_attr, Some(payload_attr), join_result)
     }
-}
 
-/// A Rust macro for specializing the implementation of a join key type. Each
-/// type calls a different CUDA function. The function to be called is specified
-/// by the `Suffix` parameter.
-macro_rules! impl_cuda_hash_join_for_type {
-    ($Type:ty, $Suffix:expr) => {
-        impl CudaHashJoinable for $Type {
-            paste::item!{
-                fn build_impl(
-                    hj: &CudaHashJoin<$Type>,
-                    join_attr: LaunchableSlice<'_, $Type>,
-                    payload_attr: LaunchableSlice<'_, $Type>,
-                    stream: &Stream,
-                    ) -> Result<()> {
+    /// Probe the hash table on the CPU and add the number of matches to
+    /// `join_result`.
+    ///
+    /// See `CudaHashJoin::probe_count` for details.
+    pub fn probe_count(&mut self, join_attr: &[T], join_result: &mut u64) -> Result<()> {
+        T::probe_sum_impl(self, join_attr, None, join_result)
+    }
 
-                    if join_attr.len() != payload_attr.len() {
-                        Err(ErrorKind::InvalidArgument(
-                                "Join and payload attributes have different sizes"
-                                .to_string()
-                                ))?;
-                    }
-                    if join_attr.len() > hj.hash_table.mem.len() {
-                        Err(ErrorKind::InvalidArgument(
-                                "Hash table is too small for the build data"
-                                .to_string()
-                                ))?;
-                    }
+    /// Build a hash table on the CPU from a relation.
+    ///
+    /// The relation must be accessible by the CPU.
+    pub fn build_relation(&mut self, relation: &Relation<T, T>) -> Result<()> {
+        let join_attr = relation.key().try_as_slice()?;
+        let payload_attr = relation.value().try_as_slice()?;
+        T::build_impl(self, join_attr, Some(payload_attr))
+    }
 
-                    let (grid, block) = hj.build_dim.clone();
+    /// Probe the hash table on the CPU with a relation and sum its payload
+    /// attribute rows.
+    ///
+    /// The relation must be accessible by the CPU.
+    pub fn probe_sum_relation(
+        &mut self,
+        relation: &Relation<T, T>,
+        join_result: &mut u64,
+    ) -> Result<()> {
+        let join_attr = relation.key().try_as_slice()?;
+        let payload_attr = relation.value().try_as_slice()?;
+        T::probe_sum_impl(self, join_attr, Some(payload_attr), join_result)
+    }
 
-                    let join_attr_len = join_attr.len() as u64;
-                    let hash_table_size = hj.hash_table.size as u64;
-                    let module = *crate::MODULE;
+    /// Probe the hash table on the CPU and mark the matching probe tuples in
+    /// a bitmap.
+    ///
+    /// Sets bit `i` iff `join_attr[i]` has a match, and leaves all other bits
+    /// unchanged. `bitmap` must hold at least `probe_bitmap_len(join_attr.len())`
+    /// zero-initialized words. See `CudaHashJoin::probe_bitmap` for details.
+    pub fn probe_bitmap(&mut self, join_attr: &[T], bitmap: &mut [u64]) -> Result<()> {
+        T::probe_bitmap_impl(self, join_attr, bitmap)
+    }
 
-                    match (&hj.hashing_scheme, &hj.is_selective) {
-                        (HashingScheme::Perfect, false) => unsafe{ launch!(
-                                module.[<gpu_ht_build_perfect_ $Suffix>]<<<grid, block, 0, stream>>>(
-                                    hj.hash_table.mem.as_launchable_ptr(),
-                                    hash_table_size,
-                                    join_attr.as_launchable_ptr(),
-                                    payload_attr.as_launchable_ptr(),
-                                    join_attr_len
-                                    )
-                                )? },
-                        (HashingScheme::Perfect, true) => unsafe{ launch!(
-                                module.[<gpu_ht_build_selective_perfect_ $Suffix>]<<<grid, block, 0, stream>>>(
-                                    hj.hash_table.mem.as_launchable_ptr(),
-                                    hash_table_size,
-                                    join_attr.as_launchable_ptr(),
-                                    payload_attr.as_launchable_ptr(),
-                                    join_attr_len
-                                    )
-                                )? },
-                        (HashingScheme::LinearProbing, false) => unsafe { launch!(
-                                module.[<gpu_ht_build_linearprobing_ $Suffix>]<<<grid, block, 0, stream>>>(
-                                    hj.hash_table.mem.as_launchable_ptr(),
-                                    hash_table_size,
-                                    join_attr.as_launchable_ptr(),
-                                    payload_attr.as_launchable_ptr(),
-                                    join_attr_len
-                                    )
-                                )? },
-                        (HashingScheme::LinearProbing, true) => unimplemented!(),
-			(HashingScheme::BucketChaining, false) => unimplemented!(),
-			(HashingScheme::BucketChaining, true) => unimplemented!(),
+    /// Probe the hash table on the CPU and write the payloads of all matches
+    /// into the join result.
+    ///
+    /// The matches are written in probe relation order. Returns the number of
+    /// matches, which may exceed `join_result.len()`. See
+    /// `CudaHashJoin::probe_materialize` for details.
+    pub fn probe_materialize(
+        &mut self,
+        join_attr: &[T],
+        payload_attr: &[T],
+        join_result: &mut [JoinMatch<T>],
+    ) -> Result<usize> {
+        T::probe_materialize_impl(self, join_attr, payload_attr, join_result, None)
+    }
+
+    /// Probe the hash table on the CPU and write at most `max_results` matches
+    /// into the join result.
+    ///
+    /// The probe stops at the first match beyond the cap. The written matches
+    /// are thus a prefix of the full result in probe relation order. See
+    /// `CudaHashJoin::probe_materialize_limit` for details.
+    pub fn probe_materialize_limit(
+        &mut self,
+        join_attr: &[T],
+        payload_attr: &[T],
+        join_result: &mut [JoinMatch<T>],
+        max_results: Option<usize>,
+    ) -> Result<MaterializeResult> {
+        let cap = max_results.map_or(join_result.len(), |max| max.min(join_result.len()));
+        let matches =
+            T::probe_materialize_impl(self, join_attr, payload_attr, join_result, Some(cap))?;
+
+        Ok(MaterializeResult::from_saturated_matches(matches, cap))
+    }
+
+    /// Build a hash table on the CPU from a relation with SQL `NULL` keys.
+    ///
+    /// Tuples marked as `NULL` in `validity` are not inserted into the hash
+    /// table, and thus are never matched by a probe.
+    ///
+    /// The hash table marks empty slots with `KeyAttribute::null_key()`, and
+    /// thus cannot store a valid key equal to it. Returns an
+    /// `InvalidArgument` error if a valid key equals `null_key()`.
+    pub fn build_nullable(
+        &mut self,
+        join_attr: &[T],
+        payload_attr: &[T],
+        validity: &ValidityBitmap,
+    ) -> Result<()>
+    where
+        T: PartialEq,
+    {
+        let (join_attr, payload_attr) = validity.filter_valid(join_attr, payload_attr)?;
+
+        if join_attr.iter().any(|key| *key == T::null_key()) {
+            Err(ErrorKind::InvalidArgument(
+                "Valid join key collides with the hash table's empty slot marker".to_string(),
+            ))?;
+        }
+
+        T::build_impl(self, &join_attr, Some(&payload_attr))
+    }
+
+    /// Probe the hash table on the CPU with a relation with SQL `NULL` keys.
+    ///
+    /// Following SQL's three-valued logic, `NULL = NULL` is unknown. Thus,
+    /// tuples marked as `NULL` in `validity` never match, even if their key
+    /// value is contained in the hash table.
+    ///
+    /// Valid keys equal to `KeyAttribute::null_key()` never match either,
+    /// because `build_nullable` rejects them.
+    pub fn probe_sum_nullable(
+        &mut self,
+        join_attr: &[T],
+        payload_attr: &[T],
+        validity: &ValidityBitmap,
+        join_result: &mut u64,
+    ) -> Result<()>
+    where
+        T: PartialEq,
+    {
+        let (join_attr, payload_attr) = validity.filter_valid(join_attr, payload_attr)?;
+        let (join_attr, payload_attr): (Vec<T>, Vec<T>) = join_attr
+            .into_iter()
+            .zip(payload_attr)
+            .filter(|(key, _)| *key != T::null_key())
+            .unzip();
+
+        T::probe_sum_impl(self, &join_attr, Some(&payload_attr), join_result)
+    }
+
+    /// Probe the hash table on the CPU and mark the probe tuples without a
+    /// match in a bitmap.
+    ///
+    /// This effectively implements an anti-join, e.g., the SQL code:
+    /// ```SQL
+    /// SELECT s.* FROM s WHERE s.join_attr NOT IN (SELECT r.join_attr FROM r)
+    /// ```
+    ///
+    /// Sets bit `i` iff `join_attr[i]` has no match, and is thus the
+    /// complement of `probe_bitmap`. In contrast to `probe_bitmap`, the first
+    /// `probe_bitmap_len(join_attr.len())` words are overwritten, and the bits
+    /// beyond the last probe tuple are cleared. Thus, the bitmap need not be
+    /// initialized.
+    ///
+    /// ## `NULL` handling
+    ///
+    /// `build_validity` is the validity bitmap with which the hash table was
+    /// built by `build_nullable`, or `None` if the build relation has no
+    /// `NULL` keys. In SQL, `x NOT IN (a, b, NULL)` evaluates to
+    /// `x <> a AND x <> b AND x <> NULL`. The last comparison is unknown, and
+    /// thus the predicate is false if `x` matches, and unknown otherwise. A
+    /// `WHERE` clause keeps neither. Therefore, if the build relation contains
+    /// at least one `NULL` key, no bit is set and the hash table isn't probed.
+    ///
+    /// The probe keys must not be SQL `NULL`. Their bits are set as for any
+    /// other key.
+    pub fn probe_anti_bitmap(
+        &mut self,
+        join_attr: &[T],
+        build_validity: Option<&ValidityBitmap>,
+        bitmap: &mut [u64],
+    ) -> Result<()> {
+        let words = probe_bitmap_len(join_attr.len());
+        if bitmap.len() < words {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Anti-join bitmap requires {} words, but has only {}",
+                words,
+                bitmap.len()
+            )))?;
+        }
+
+        let bitmap = &mut bitmap[..words];
+        bitmap.iter_mut().for_each(|word| *word = 0);
+
+        if build_validity.map_or(false, |validity| validity.null_count() > 0) {
+            return Ok(());
+        }
+
+        T::probe_bitmap_impl(self, join_attr, bitmap)?;
+
+        bitmap.iter_mut().for_each(|word| *word = !*word);
+        let tail_bits = join_attr.len() % u64::BITS as usize;
+        if tail_bits != 0 {
+            if let Some(last) = bitmap.last_mut() {
+                *last &= (1 << tail_bits) - 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A Rust macro for specializing the implementation of a join key type. Each
+/// type calls a different CUDA function. The function to be called is specified
+/// by the `Suffix` parameter.
+macro_rules! impl_cuda_hash_join_for_type {
+    ($Type:ty, $Suffix:expr) => {
+        impl CudaHashJoinable for $Type {
+            paste::item!{
+                fn build_impl(
+                    hj: &CudaHashJoin<$Type>,
+                    join_attr: LaunchableSlice<'_, $Type>,
+                    payload_attr: Option<LaunchableSlice<'_, $Type>>,
+                    stream: &Stream,
+                    ) -> Result<()> {
+
+                    check_payload_len(join_attr.len(), payload_attr.as_ref().map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr
+                        .as_ref()
+                        .map_or_else(LaunchablePtr::null, |p| p.as_launchable_ptr());
+                    check_layout_scheme(hj.hash_table.layout, hj.hashing_scheme, hj.is_selective)?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+
+                    if join_attr.len() > hj.hash_table.mem.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Hash table is too small for the build data"
+                                .to_string()
+                                ))?;
+                    }
+
+                    // An empty relation doesn't require a kernel launch
+                    if join_attr.len() == 0 {
+                        return Ok(());
+                    }
+
+                    let (grid, block) = hj.build_dim.clone();
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+                    let module = crate::module()?;
+
+                    match (&hj.hashing_scheme, &hj.is_selective) {
+                        (HashingScheme::Perfect, false) => unsafe{ launch!(
+                                module.[<gpu_ht_build_perfect_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                    hj.hash_table.mem.as_launchable_ptr(),
+                                    hash_table_size,
+                                    join_attr.as_launchable_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len
+                                    )
+                                )? },
+                        (HashingScheme::Perfect, true) => unsafe{ launch!(
+                                module.[<gpu_ht_build_selective_perfect_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                    hj.hash_table.mem.as_launchable_ptr(),
+                                    hash_table_size,
+                                    join_attr.as_launchable_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len
+                                    )
+                                )? },
+                        (HashingScheme::LinearProbing, false) => match hj.hash_table.layout {
+                            HashTableLayout::AoS if hj.probe_step == ProbeStep::Quadratic => unsafe { launch!(
+                                    module.[<gpu_ht_build_quadraticprobing_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                        hj.hash_table.mem.as_launchable_ptr(),
+                                        hash_table_size,
+                                        join_attr.as_launchable_ptr(),
+                                        payload_attr_ptr,
+                                        join_attr_len
+                                        )
+                                    )? },
+                            HashTableLayout::AoS => unsafe { launch!(
+                                    module.[<gpu_ht_build_linearprobing_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                        hj.hash_table.mem.as_launchable_ptr(),
+                                        hash_table_size,
+                                        join_attr.as_launchable_ptr(),
+                                        payload_attr_ptr,
+                                        join_attr_len
+                                        )
+                                    )? },
+                            HashTableLayout::SoA => unsafe { launch!(
+                                    module.[<gpu_ht_build_linearprobing_soa_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                        hj.hash_table.mem.as_launchable_ptr(),
+                                        hash_table_size,
+                                        join_attr.as_launchable_ptr(),
+                                        payload_attr_ptr,
+                                        join_attr_len
+                                        )
+                                    )? },
+                        },
+                        (HashingScheme::LinearProbing, true) => unimplemented!(),
+			(HashingScheme::BucketChaining, false) => unimplemented!(),
+			(HashingScheme::BucketChaining, true) => unimplemented!(),
+                        (HashingScheme::Cuckoo, _) => Err(ErrorKind::InvalidArgument(
+                                "Cuckoo hashing is not supported on the GPU".to_string()
+                                ))?,
                     };
 
                     Ok(())
@@ -377,30 +1550,38 @@ macro_rules! impl_cuda_hash_join_for_type {
                 fn probe_sum_impl(
                     hj: &CudaHashJoin<$Type>,
                     join_attr: LaunchableSlice<'_, $Type>,
-                    payload_attr: LaunchableSlice<'_, $Type>,
+                    payload_attr: Option<LaunchableSlice<'_, $Type>>,
                     result_set: &Mem<u64>,
                     stream: &Stream,
                     ) -> Result<()> {
 
                     let (grid, block) = hj.probe_dim.clone();
 
-                    if result_set.len() < (grid.x * block.x) as usize {
+                    if result_set.len() < hj.result_set_len() {
                        Err(ErrorKind::InvalidArgument(
-                               "Result set size is too small, must be at least grid * block size"
-                               .to_string()
+                               format!(
+                                   "Result set size is too small, must be at least {} for {:?} granularity",
+                                   hj.result_set_len(),
+                                   hj.count_granularity
+                                   )
                                ))?;
                     }
 
-                    if join_attr.len() != payload_attr.len() {
-                        Err(ErrorKind::InvalidArgument(
-                                "Join and payload attributes have different sizes"
-                                .to_string()
-                                ))?;
+                    check_payload_len(join_attr.len(), payload_attr.as_ref().map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr
+                        .as_ref()
+                        .map_or_else(LaunchablePtr::null, |p| p.as_launchable_ptr());
+                    check_layout_scheme(hj.hash_table.layout, hj.hashing_scheme, hj.is_selective)?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+
+                    // An empty relation doesn't require a kernel launch
+                    if join_attr.len() == 0 {
+                        return Ok(());
                     }
 
                     let join_attr_len = join_attr.len() as u64;
                     let hash_table_size = hj.hash_table.size as u64;
-                    let module = *crate::MODULE;
+                    let module = crate::module()?;
 
                     match &hj.hashing_scheme {
                         HashingScheme::Perfect => unsafe { launch!(
@@ -408,616 +1589,3431 @@ macro_rules! impl_cuda_hash_join_for_type {
                                     hj.hash_table.mem.as_launchable_ptr(),
                                     hash_table_size,
                                     join_attr.as_launchable_ptr(),
-                                    payload_attr.as_launchable_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len,
+                                    result_set.as_launchable_ptr(),
+                                    hj.count_granularity as u32,
+                                    hj.read_only_cache as u32
+                                    )
+                                )? },
+                        HashingScheme::LinearProbing => match (hj.hash_table.layout, hj.placement) {
+                            (HashTableLayout::AoS, HashTablePlacement::Global) if hj.probe_step == ProbeStep::Quadratic => unsafe { launch!(
+                                    module.[<gpu_ht_probe_aggregate_quadraticprobing_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                        hj.hash_table.mem.as_launchable_ptr(),
+                                        hash_table_size,
+                                        join_attr.as_launchable_ptr(),
+                                        payload_attr_ptr,
+                                        join_attr_len,
+                                        result_set.as_launchable_ptr(),
+                                        hj.count_granularity as u32,
+                                        hj.read_only_cache as u32
+                                        )
+                                    )? },
+                            (HashTableLayout::AoS, HashTablePlacement::Global) => unsafe { launch!(
+                                    module.[<gpu_ht_probe_aggregate_linearprobing_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                        hj.hash_table.mem.as_launchable_ptr(),
+                                        hash_table_size,
+                                        join_attr.as_launchable_ptr(),
+                                        payload_attr_ptr,
+                                        join_attr_len,
+                                        result_set.as_launchable_ptr(),
+                                        hj.count_granularity as u32,
+                                        hj.read_only_cache as u32
+                                        )
+                                    )? },
+                            (HashTableLayout::AoS, HashTablePlacement::SharedMemory) => {
+                                let shared_mem_bytes =
+                                    (hj.hash_table.size * size_of::<HtEntry<$Type, $Type>>()) as u32;
+                                let name = CString::new(stringify!(
+                                        [<gpu_ht_probe_aggregate_linearprobing_shared_ $Suffix>]
+                                        ))
+                                    .unwrap();
+                                let mut function = crate::module_function(module, &name)?;
+                                function.set_max_dynamic_shared_size_bytes(shared_mem_bytes)?;
+
+                                unsafe { launch!(
+                                        function<<<grid, block, shared_mem_bytes, stream>>>(
+                                            hj.hash_table.mem.as_launchable_ptr(),
+                                            hash_table_size,
+                                            join_attr.as_launchable_ptr(),
+                                            payload_attr_ptr,
+                                            join_attr_len,
+                                            result_set.as_launchable_ptr(),
+                                            hj.count_granularity as u32
+                                            )
+                                        )? }
+                            }
+                            (HashTableLayout::SoA, _) => unsafe { launch!(
+                                    module.[<gpu_ht_probe_aggregate_linearprobing_soa_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                        hj.hash_table.mem.as_launchable_ptr(),
+                                        hash_table_size,
+                                        join_attr.as_launchable_ptr(),
+                                        payload_attr_ptr,
+                                        join_attr_len,
+                                        result_set.as_launchable_ptr(),
+                                        hj.count_granularity as u32,
+                                        hj.read_only_cache as u32
+                                        )
+                                    )? },
+                        },
+                        HashingScheme::BucketChaining => unimplemented!(),
+                        HashingScheme::Cuckoo => Err(ErrorKind::InvalidArgument(
+                                "Cuckoo hashing is not supported on the GPU".to_string()
+                                ))?,
+                    };
+
+                    Ok(())
+                }
+            }
+
+            paste::item!{
+                fn probe_bitmap_impl(
+                    hj: &CudaHashJoin<$Type>,
+                    join_attr: LaunchableSlice<'_, $Type>,
+                    bitmap: &Mem<u64>,
+                    stream: &Stream,
+                    ) -> Result<()> {
+
+                    let (grid, block) = hj.probe_dim.clone();
+
+                    check_aos_layout(hj.hash_table.layout, "Bitmap probes")?;
+                    check_linear_step(hj.probe_step, "Bitmap probes")?;
+
+                    if bitmap.len() < probe_bitmap_len(join_attr.len()) {
+                        Err(ErrorKind::InvalidArgument(format!(
+                                    "Bitmap is too small, must be at least {} words",
+                                    probe_bitmap_len(join_attr.len())
+                                    )))?;
+                    }
+
+                    // An empty relation doesn't require a kernel launch
+                    if join_attr.len() == 0 {
+                        return Ok(());
+                    }
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+                    let module = crate::module()?;
+
+                    match &hj.hashing_scheme {
+                        HashingScheme::Perfect => unsafe { launch!(
+                                module.[<gpu_ht_probe_bitmap_perfect_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                    hj.hash_table.mem.as_launchable_ptr(),
+                                    hash_table_size,
+                                    join_attr.as_launchable_ptr(),
                                     join_attr_len,
-                                    result_set.as_launchable_ptr()
+                                    bitmap.as_launchable_ptr()
                                     )
                                 )? },
                         HashingScheme::LinearProbing => unsafe { launch!(
-                                module.[<gpu_ht_probe_aggregate_linearprobing_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                module.[<gpu_ht_probe_bitmap_linearprobing_ $Suffix>]<<<grid, block, 0, stream>>>(
                                     hj.hash_table.mem.as_launchable_ptr(),
                                     hash_table_size,
                                     join_attr.as_launchable_ptr(),
-                                    payload_attr.as_launchable_ptr(),
                                     join_attr_len,
-                                    result_set.as_launchable_ptr()
+                                    bitmap.as_launchable_ptr()
                                     )
                                 )? },
-                        HashingScheme::BucketChaining => unimplemented!(),
+                        HashingScheme::BucketChaining => Err(ErrorKind::InvalidArgument(
+                                "Bucket chaining is not supported by bitmap probes".to_string()
+                                ))?,
+                        HashingScheme::Cuckoo => Err(ErrorKind::InvalidArgument(
+                                "Cuckoo hashing is not supported on the GPU".to_string()
+                                ))?,
                     };
 
                     Ok(())
                 }
             }
-        }
-    };
-}
-
-impl_cuda_hash_join_for_type!(i32, int32);
-impl_cuda_hash_join_for_type!(i64, int64);
 
-/// A Rust macro for specializing the implementation of a join key type. Each
-/// type calls a different C++ function. The function to be called is specified
-/// by the `Suffix` parameter.
-macro_rules! impl_cpu_hash_join_for_type {
-    ($Type:ty, $Suffix:expr) => {
-        impl CpuHashJoinable for $Type {
             paste::item!{
-                fn build_impl(hj: &mut CpuHashJoin<$Type>, join_attr: &[$Type], payload_attr: &[$Type]) -> Result<()> {
+                fn probe_materialize_impl(
+                    hj: &CudaHashJoin<$Type>,
+                    join_attr: LaunchableSlice<'_, $Type>,
+                    payload_attr: LaunchableSlice<'_, $Type>,
+                    join_result: &Mem<JoinMatch<$Type>>,
+                    limit: Option<usize>,
+                    stable_output: bool,
+                    stream: &Stream,
+                    ) -> Result<usize> {
+
                     if join_attr.len() != payload_attr.len() {
                         Err(ErrorKind::InvalidArgument(
                                 "Join and payload attributes have different sizes"
                                 .to_string()
                                 ))?;
                     }
+                    check_aos_layout(hj.hash_table.layout, "Materializing probes")?;
+                    check_linear_step(hj.probe_step, "Materializing probes")?;
 
-                    if join_attr.len() > hj.hash_table.mem.len() {
-                        Err(ErrorKind::InvalidArgument(
-                                "Hash table is too small for the build data"
-                                .to_string()
-                                ))?;
+                    match hj.hashing_scheme {
+                        HashingScheme::LinearProbing => {}
+                        _ => Err(ErrorKind::InvalidArgument(format!(
+                                    "Materializing probes support only LinearProbing, got {:?}",
+                                    hj.hashing_scheme
+                                    )))?,
+                    }
+
+                    // An empty probe relation has no matches
+                    if join_attr.len() == 0 {
+                        return Ok(0);
                     }
 
+                    let (grid, block) = hj.probe_dim.clone();
                     let join_attr_len = join_attr.len() as u64;
                     let hash_table_size = hj.hash_table.size as u64;
+                    let join_result_capacity = limit.unwrap_or(join_result.len()) as u64;
+                    let saturate = limit.is_some() as u32;
+                    let module = crate::module()?;
 
-                    let region_name = cstr!("cpu_hash_join_build");
-                    likwid::marker_start_region(region_name)?;
+                    // In the stable mode, the probe tuples write at their
+                    // offsets and the CPU sums up the total
+                    let mut join_result_len = allocator::Allocator::alloc_mem::<u64>(
+                        allocator::MemType::CudaUniMem,
+                        1,
+                        );
+                    join_result_len.try_as_mut_slice()?[0] = 0;
 
-                    match (&hj.hashing_scheme, &hj.is_selective) {
-                        (HashingScheme::Perfect, false) => unsafe {
-                            [<cpu_ht_build_perfect_ $Suffix>](
-                                hj.hash_table.mem.as_ptr() as *mut _,
-                                hash_table_size,
-                                join_attr.as_ptr(),
-                                payload_attr.as_ptr(),
-                                join_attr_len,
-                                )
-                        },
-                        (HashingScheme::Perfect, true) => unsafe {
-                            [<cpu_ht_build_selective_perfect_ $Suffix>](
-                                hj.hash_table.mem.as_ptr() as *mut _,
-                                hash_table_size,
-                                join_attr.as_ptr(),
-                                payload_attr.as_ptr(),
-                                join_attr_len,
-                                )
-                        },
-                        (HashingScheme::LinearProbing, false) => unsafe {
-                            [<cpu_ht_build_linearprobing_ $Suffix>](
-                                hj.hash_table.mem.as_ptr() as *mut _,
+                    let write_offsets = if stable_output {
+                        let mut match_counts = allocator::Allocator::alloc_mem::<u64>(
+                            allocator::MemType::CudaUniMem,
+                            join_attr.len().max(1),
+                            );
+
+                        unsafe { launch!(
+                                module.[<gpu_ht_probe_count_linearprobing_ $Suffix>]<<<grid.clone(), block.clone(), 0, stream>>>(
+                                    hj.hash_table.mem.as_launchable_ptr(),
+                                    hash_table_size,
+                                    join_attr.as_launchable_ptr(),
+                                    join_attr_len,
+                                    match_counts.as_launchable_ptr()
+                                    )
+                                )? };
+                        stream.synchronize()?;
+
+                        // Exclusive prefix sum of the match counts
+                        let mut total = exclusive_prefix_sum(
+                            &mut match_counts.try_as_mut_slice()?[..join_attr.len()]
+                            );
+                        // A saturating probe reports at most one surplus match
+                        if limit.is_some() {
+                            total = total.min(join_result_capacity + 1);
+                        }
+                        join_result_len.try_as_mut_slice()?[0] = total;
+
+                        Some(match_counts)
+                    } else {
+                        None
+                    };
+
+                    let write_offsets_ptr = match write_offsets {
+                        Some(ref offsets) => offsets.as_launchable_ptr(),
+                        None => LaunchablePtr::null(),
+                    };
+
+                    unsafe { launch!(
+                            module.[<gpu_ht_probe_materialize_linearprobing_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                hj.hash_table.mem.as_launchable_ptr(),
                                 hash_table_size,
-                                join_attr.as_ptr(),
-                                payload_attr.as_ptr(),
+                                join_attr.as_launchable_ptr(),
+                                payload_attr.as_launchable_ptr(),
                                 join_attr_len,
+                                write_offsets_ptr,
+                                join_result.as_launchable_ptr(),
+                                join_result_capacity,
+                                join_result_len.as_launchable_ptr(),
+                                saturate
                                 )
-                        },
-                        (HashingScheme::LinearProbing, true) => unimplemented!(),
-			(HashingScheme::BucketChaining, false) => unimplemented!(),
-			(HashingScheme::BucketChaining, true) => unimplemented!(),
-                    };
+                            )? };
+                    stream.synchronize()?;
 
-                    likwid::marker_stop_region(region_name)?;
+                    let matches = join_result_len.try_as_slice()?[0];
+                    Ok(matches as usize)
+                }
+            }
 
-                    Ok(())
+            fn build_kernel_name(hashing_scheme: HashingScheme) -> Result<&'static str> {
+                match hashing_scheme {
+                    HashingScheme::Perfect => {
+                        Ok(concat!("gpu_ht_build_perfect_", stringify!($Suffix)))
+                    }
+                    HashingScheme::LinearProbing => {
+                        Ok(concat!("gpu_ht_build_linearprobing_", stringify!($Suffix)))
+                    }
+                    _ => Err(ErrorKind::InvalidArgument(format!(
+                        "{:?} hashing is not supported on the GPU",
+                        hashing_scheme
+                    ))
+                    .into()),
+                }
+            }
+
+            fn probe_kernel_name(hashing_scheme: HashingScheme) -> Result<&'static str> {
+                match hashing_scheme {
+                    HashingScheme::Perfect => {
+                        Ok(concat!("gpu_ht_probe_aggregate_perfect_", stringify!($Suffix)))
+                    }
+                    HashingScheme::LinearProbing => {
+                        Ok(concat!("gpu_ht_probe_aggregate_linearprobing_", stringify!($Suffix)))
+                    }
+                    _ => Err(ErrorKind::InvalidArgument(format!(
+                        "{:?} hashing is not supported on the GPU",
+                        hashing_scheme
+                    ))
+                    .into()),
                 }
             }
+        }
+    };
+}
+
+impl_cuda_hash_join_for_type!(i32, int32);
+impl_cuda_hash_join_for_type!(i64, int64);
 
+/// A Rust macro for specializing the implementation of a narrow join key type,
+/// e.g., of a dictionary-encoded column.
+///
+/// Narrow keys support only the `Perfect` hashing scheme. See
+/// `check_narrow_hash_table` for details.
+macro_rules! impl_cuda_hash_join_for_narrow_type {
+    ($Type:ty, $Suffix:expr) => {
+        impl CudaHashJoinable for $Type {
             paste::item!{
-                fn probe_sum_impl(
-                    hj: &mut CpuHashJoin<$Type>,
-                    join_attr: &[$Type],
-                    payload_attr: &[$Type],
-                    join_result: &mut u64,
+                fn build_impl(
+                    hj: &CudaHashJoin<$Type>,
+                    join_attr: LaunchableSlice<'_, $Type>,
+                    payload_attr: Option<LaunchableSlice<'_, $Type>>,
+                    stream: &Stream,
                     ) -> Result<()> {
 
-                    if join_attr.len() != payload_attr.len() {
-                        Err(ErrorKind::InvalidArgument(
-                                "Join and payload attributes have different sizes"
-                                .to_string()
-                                ))?;
+                    check_payload_len(join_attr.len(), payload_attr.as_ref().map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr
+                        .as_ref()
+                        .map_or_else(LaunchablePtr::null, |p| p.as_launchable_ptr());
+                    check_layout_scheme(hj.hash_table.layout, hj.hashing_scheme, hj.is_selective)?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+
+                    check_narrow_hash_table::<$Type>(hj.hashing_scheme, hj.hash_table.size)?;
+
+                    // An empty relation doesn't require a kernel launch
+                    if join_attr.len() == 0 {
+                        return Ok(());
                     }
 
+                    let (grid, block) = hj.build_dim.clone();
+
                     let join_attr_len = join_attr.len() as u64;
                     let hash_table_size = hj.hash_table.size as u64;
+                    let module = crate::module()?;
+
+                    if hj.is_selective {
+                        unsafe { launch!(
+                                module.[<gpu_ht_build_selective_perfect_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                    hj.hash_table.mem.as_launchable_ptr(),
+                                    hash_table_size,
+                                    join_attr.as_launchable_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len
+                                    )
+                                )? };
+                    } else {
+                        unsafe { launch!(
+                                module.[<gpu_ht_build_perfect_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                    hj.hash_table.mem.as_launchable_ptr(),
+                                    hash_table_size,
+                                    join_attr.as_launchable_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len
+                                    )
+                                )? };
+                    }
+
+                    Ok(())
+                }
+            }
+
+            paste::item!{
+                fn probe_sum_impl(
+                    hj: &CudaHashJoin<$Type>,
+                    join_attr: LaunchableSlice<'_, $Type>,
+                    payload_attr: Option<LaunchableSlice<'_, $Type>>,
+                    result_set: &Mem<u64>,
+                    stream: &Stream,
+                    ) -> Result<()> {
+
+                    let (grid, block) = hj.probe_dim.clone();
+
+                    if result_set.len() < hj.result_set_len() {
+                       Err(ErrorKind::InvalidArgument(
+                               format!(
+                                   "Result set size is too small, must be at least {} for {:?} granularity",
+                                   hj.result_set_len(),
+                                   hj.count_granularity
+                                   )
+                               ))?;
+                    }
+
+                    check_payload_len(join_attr.len(), payload_attr.as_ref().map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr
+                        .as_ref()
+                        .map_or_else(LaunchablePtr::null, |p| p.as_launchable_ptr());
+                    check_layout_scheme(hj.hash_table.layout, hj.hashing_scheme, hj.is_selective)?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+
+                    check_narrow_hash_table::<$Type>(hj.hashing_scheme, hj.hash_table.size)?;
+
+                    if hj.read_only_cache {
+                        Err(ErrorKind::InvalidArgument(
+                                "Narrow join keys don't support the read-only cache".to_string()
+                                ))?;
+                    }
+
+                    // An empty relation doesn't require a kernel launch
+                    if join_attr.len() == 0 {
+                        return Ok(());
+                    }
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+                    let module = crate::module()?;
+
+                    unsafe { launch!(
+                            module.[<gpu_ht_probe_aggregate_perfect_ $Suffix>]<<<grid, block, 0, stream>>>(
+                                hj.hash_table.mem.as_launchable_ptr(),
+                                hash_table_size,
+                                join_attr.as_launchable_ptr(),
+                                payload_attr_ptr,
+                                join_attr_len,
+                                result_set.as_launchable_ptr(),
+                                hj.count_granularity as u32
+                                )
+                            )? };
+
+                    Ok(())
+                }
+            }
+
+            fn probe_bitmap_impl(
+                _hj: &CudaHashJoin<$Type>,
+                _join_attr: LaunchableSlice<'_, $Type>,
+                _bitmap: &Mem<u64>,
+                _stream: &Stream,
+                ) -> Result<()> {
+                Err(ErrorKind::InvalidArgument(
+                        "Narrow join keys don't support bitmap probes".to_string()
+                        ).into())
+            }
+
+            fn probe_materialize_impl(
+                _hj: &CudaHashJoin<$Type>,
+                _join_attr: LaunchableSlice<'_, $Type>,
+                _payload_attr: LaunchableSlice<'_, $Type>,
+                _join_result: &Mem<JoinMatch<$Type>>,
+                _limit: Option<usize>,
+                _stable_output: bool,
+                _stream: &Stream,
+                ) -> Result<usize> {
+                Err(ErrorKind::InvalidArgument(
+                        "Narrow join keys don't support materializing probes".to_string()
+                        ).into())
+            }
+
+            fn build_kernel_name(hashing_scheme: HashingScheme) -> Result<&'static str> {
+                match hashing_scheme {
+                    HashingScheme::Perfect => {
+                        Ok(concat!("gpu_ht_build_perfect_", stringify!($Suffix)))
+                    }
+                    _ => Err(ErrorKind::InvalidArgument(format!(
+                        "Narrow join keys support only the Perfect hashing scheme, got {:?}",
+                        hashing_scheme
+                    ))
+                    .into()),
+                }
+            }
+
+            fn probe_kernel_name(hashing_scheme: HashingScheme) -> Result<&'static str> {
+                match hashing_scheme {
+                    HashingScheme::Perfect => {
+                        Ok(concat!("gpu_ht_probe_aggregate_perfect_", stringify!($Suffix)))
+                    }
+                    _ => Err(ErrorKind::InvalidArgument(format!(
+                        "Narrow join keys support only the Perfect hashing scheme, got {:?}",
+                        hashing_scheme
+                    ))
+                    .into()),
+                }
+            }
+        }
+    };
+}
+
+impl_cuda_hash_join_for_narrow_type!(u8, uint8);
+impl_cuda_hash_join_for_narrow_type!(u16, uint16);
+
+/// A Rust macro for specializing the implementation of a join key type. Each
+/// type calls a different C++ function. The function to be called is specified
+/// by the `Suffix` parameter.
+macro_rules! impl_cpu_hash_join_for_type {
+    ($Type:ty, $Suffix:expr) => {
+        impl CpuHashJoinable for $Type {
+            paste::item!{
+                fn build_impl(hj: &mut CpuHashJoin<$Type>, join_attr: &[$Type], payload_attr: Option<&[$Type]>) -> Result<()> {
+                    check_payload_len(join_attr.len(), payload_attr.map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr.map_or(ptr::null(), |p| p.as_ptr());
+                    check_layout_scheme(hj.hash_table.layout, hj.hashing_scheme, hj.is_selective)?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+
+                    if join_attr.len() > hj.hash_table.mem.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Hash table is too small for the build data"
+                                .to_string()
+                                ))?;
+                    }
+
+                    if let Some(hash_fn) = hj.hash_fn {
+                        check_aos_layout(hj.hash_table.layout, "Custom hash functions")?;
+                        check_linear_step(hj.probe_step, "Custom hash functions")?;
+                        return build_custom_hash(hj, join_attr, payload_attr, hash_fn);
+                    }
+
+                    if let HashingScheme::Cuckoo = hj.hashing_scheme {
+                        if hj.hash_table.size < 4 {
+                            Err(ErrorKind::InvalidArgument(
+                                    "Cuckoo hash table must have at least 4 entries"
+                                    .to_string()
+                                    ))?;
+                        }
+
+                        // Evictions and rebuilds aren't thread-safe, and a
+                        // rebuild must re-insert all keys. Thus, only a single
+                        // build call may insert into the hash table.
+                        if hj.hash_table.cuckoo_is_built.compare_exchange(
+                                false,
+                                true,
+                                Ordering::SeqCst,
+                                Ordering::SeqCst,
+                                ).is_err() {
+                            Err(ErrorKind::InvalidArgument(
+                                    "Cuckoo hash table must be built in a single call \
+                                    with the entire build relation"
+                                    .to_string()
+                                    ))?;
+                        }
+                    }
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+                    let mut is_built = true;
+
+                    let region_name = cstr!("cpu_hash_join_build");
+                    likwid::marker_start_region(region_name)?;
+
+                    match (&hj.hashing_scheme, &hj.is_selective) {
+                        (HashingScheme::Perfect, false) => unsafe {
+                            [<cpu_ht_build_perfect_ $Suffix>](
+                                hj.hash_table.mem.as_ptr() as *mut _,
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                payload_attr_ptr,
+                                join_attr_len,
+                                )
+                        },
+                        (HashingScheme::Perfect, true) => unsafe {
+                            [<cpu_ht_build_selective_perfect_ $Suffix>](
+                                hj.hash_table.mem.as_ptr() as *mut _,
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                payload_attr_ptr,
+                                join_attr_len,
+                                )
+                        },
+                        (HashingScheme::LinearProbing, false) => match hj.hash_table.layout {
+                            HashTableLayout::AoS if hj.probe_step == ProbeStep::Quadratic => unsafe {
+                                [<cpu_ht_build_quadraticprobing_ $Suffix>](
+                                    hj.hash_table.mem.as_ptr() as *mut _,
+                                    hash_table_size,
+                                    join_attr.as_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len,
+                                    )
+                            },
+                            HashTableLayout::AoS => unsafe {
+                                [<cpu_ht_build_linearprobing_ $Suffix>](
+                                    hj.hash_table.mem.as_ptr() as *mut _,
+                                    hash_table_size,
+                                    join_attr.as_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len,
+                                    )
+                            },
+                            HashTableLayout::SoA => unsafe {
+                                [<cpu_ht_build_linearprobing_soa_ $Suffix>](
+                                    hj.hash_table.mem.as_ptr() as *mut _,
+                                    hash_table_size,
+                                    join_attr.as_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len,
+                                    )
+                            },
+                        },
+                        (HashingScheme::LinearProbing, true) => unimplemented!(),
+			(HashingScheme::BucketChaining, false) => unimplemented!(),
+			(HashingScheme::BucketChaining, true) => unimplemented!(),
+                        (HashingScheme::Cuckoo, _) => {
+                            let mut seed: u32 = 0;
+                            is_built = unsafe {
+                                [<cpu_ht_build_cuckoo_ $Suffix>](
+                                    hj.hash_table.mem.as_ptr() as *mut _,
+                                    hash_table_size,
+                                    join_attr.as_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len,
+                                    &mut seed,
+                                    )
+                            };
+                            hj.hash_table.cuckoo_seed.store(seed, Ordering::SeqCst);
+                        }
+                    };
+
+                    likwid::marker_stop_region(region_name)?;
+
+                    if !is_built {
+                        Err(ErrorKind::RuntimeError(
+                                "Failed to build the cuckoo hash table after rebuilding \
+                                with all seeds. Is the load factor above 50%, or are the \
+                                build keys not unique?"
+                                .to_string()
+                                ))?;
+                    }
+
+                    Ok(())
+                }
+            }
+
+            paste::item!{
+                fn build_report_impl(
+                    hj: &mut CpuHashJoin<$Type>,
+                    join_attr: &[$Type],
+                    payload_attr: Option<&[$Type]>,
+                    ) -> Result<BuildReport> {
+                    check_payload_len(join_attr.len(), payload_attr.map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr.map_or(ptr::null(), |p| p.as_ptr());
+                    check_aos_layout(hj.hash_table.layout, "Build reports")?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+                    if hj.hash_fn.is_some() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Build reports don't support custom hash functions"
+                                .to_string()
+                                ))?;
+                    }
+
+                    match (hj.hashing_scheme, hj.is_selective) {
+                        (HashingScheme::Perfect, _) | (HashingScheme::LinearProbing, false) => {}
+                        (hashing_scheme, _) => Err(ErrorKind::InvalidArgument(format!(
+                                    "Build reports support only Perfect and non-selective LinearProbing, got {:?}",
+                                    hashing_scheme
+                                    )))?,
+                    };
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+                    let mut report = BuildReport::default();
+
+                    let region_name = cstr!("cpu_hash_join_build");
+                    likwid::marker_start_region(region_name)?;
+
+                    match (&hj.hashing_scheme, &hj.is_selective) {
+                        (HashingScheme::Perfect, _) => unsafe {
+                            [<cpu_ht_build_perfect_report_ $Suffix>](
+                                hj.hash_table.mem.as_ptr() as *mut _,
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                payload_attr_ptr,
+                                join_attr_len,
+                                hj.is_selective,
+                                &mut report,
+                                )
+                        },
+                        (HashingScheme::LinearProbing, false) if hj.probe_step == ProbeStep::Quadratic => unsafe {
+                            [<cpu_ht_build_quadraticprobing_report_ $Suffix>](
+                                hj.hash_table.mem.as_ptr() as *mut _,
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                payload_attr_ptr,
+                                join_attr_len,
+                                &mut report,
+                                )
+                        },
+                        (HashingScheme::LinearProbing, false) => unsafe {
+                            [<cpu_ht_build_linearprobing_report_ $Suffix>](
+                                hj.hash_table.mem.as_ptr() as *mut _,
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                payload_attr_ptr,
+                                join_attr_len,
+                                &mut report,
+                                )
+                        },
+                        _ => unreachable!(),
+                    };
+
+                    likwid::marker_stop_region(region_name)?;
+
+                    Ok(report)
+                }
+            }
+
+            paste::item!{
+                fn probe_sum_impl(
+                    hj: &mut CpuHashJoin<$Type>,
+                    join_attr: &[$Type],
+                    payload_attr: Option<&[$Type]>,
+                    join_result: &mut u64,
+                    ) -> Result<()> {
+
+                    check_payload_len(join_attr.len(), payload_attr.map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr.map_or(ptr::null(), |p| p.as_ptr());
+                    check_layout_scheme(hj.hash_table.layout, hj.hashing_scheme, hj.is_selective)?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+
+                    if let Some(hash_fn) = hj.hash_fn {
+                        check_aos_layout(hj.hash_table.layout, "Custom hash functions")?;
+                        check_linear_step(hj.probe_step, "Custom hash functions")?;
+                        return probe_sum_custom_hash(hj, join_attr, payload_attr, hash_fn, join_result);
+                    }
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+
+                    let region_name = cstr!("cpu_hash_join_probe");
+                    likwid::marker_start_region(region_name)?;
 
-                    let region_name = cstr!("cpu_hash_join_probe");
-                    likwid::marker_start_region(region_name)?;
-
                     match &hj.hashing_scheme {
                         HashingScheme::Perfect => unsafe {
                             [<cpu_ht_probe_aggregate_perfect_ $Suffix>](
                                 hj.hash_table.mem.as_ptr(),
                                 hash_table_size,
                                 join_attr.as_ptr(),
-                                payload_attr.as_ptr(),
+                                payload_attr_ptr,
                                 join_attr_len,
                                 join_result,
                                 )
                         },
-                        HashingScheme::LinearProbing => unsafe {
-                            [<cpu_ht_probe_aggregate_linearprobing_ $Suffix>](
+                        HashingScheme::LinearProbing => match hj.hash_table.layout {
+                            HashTableLayout::AoS if hj.probe_step == ProbeStep::Quadratic => unsafe {
+                                [<cpu_ht_probe_aggregate_quadraticprobing_ $Suffix>](
+                                    hj.hash_table.mem.as_ptr(),
+                                    hash_table_size,
+                                    join_attr.as_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len,
+                                    join_result,
+                                    )
+                            },
+                            HashTableLayout::AoS => unsafe {
+                                [<cpu_ht_probe_aggregate_linearprobing_ $Suffix>](
+                                    hj.hash_table.mem.as_ptr(),
+                                    hash_table_size,
+                                    join_attr.as_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len,
+                                    join_result,
+                                    )
+                            },
+                            HashTableLayout::SoA => unsafe {
+                                [<cpu_ht_probe_aggregate_linearprobing_soa_ $Suffix>](
+                                    hj.hash_table.mem.as_ptr() as *const _,
+                                    hash_table_size,
+                                    join_attr.as_ptr(),
+                                    payload_attr_ptr,
+                                    join_attr_len,
+                                    join_result,
+                                    )
+                            },
+                        },
+                        HashingScheme::BucketChaining => unimplemented!(),
+                        HashingScheme::Cuckoo => unsafe {
+                            [<cpu_ht_probe_aggregate_cuckoo_ $Suffix>](
                                 hj.hash_table.mem.as_ptr(),
                                 hash_table_size,
+                                hj.hash_table.cuckoo_seed.load(Ordering::SeqCst),
                                 join_attr.as_ptr(),
-                                payload_attr.as_ptr(),
+                                payload_attr_ptr,
                                 join_attr_len,
                                 join_result,
                                 )
                         },
-                        HashingScheme::BucketChaining => unimplemented!(),
                     };
 
-                    likwid::marker_stop_region(region_name)?;
+                    likwid::marker_stop_region(region_name)?;
+
+                    Ok(())
+                }
+            }
+
+            paste::item!{
+                fn probe_bitmap_impl(
+                    hj: &mut CpuHashJoin<$Type>,
+                    join_attr: &[$Type],
+                    bitmap: &mut [u64],
+                    ) -> Result<()> {
+
+                    check_aos_layout(hj.hash_table.layout, "Bitmap probes")?;
+                    check_linear_step(hj.probe_step, "Bitmap probes")?;
+
+                    if bitmap.len() < probe_bitmap_len(join_attr.len()) {
+                        Err(ErrorKind::InvalidArgument(format!(
+                                    "Bitmap is too small, must be at least {} words",
+                                    probe_bitmap_len(join_attr.len())
+                                    )))?;
+                    }
+
+                    if let Some(hash_fn) = hj.hash_fn {
+                        return probe_bitmap_custom_hash(hj, join_attr, hash_fn, bitmap);
+                    }
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+
+                    match &hj.hashing_scheme {
+                        HashingScheme::Perfect => unsafe {
+                            [<cpu_ht_probe_bitmap_perfect_ $Suffix>](
+                                hj.hash_table.mem.as_ptr(),
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                join_attr_len,
+                                bitmap.as_mut_ptr(),
+                                )
+                        },
+                        HashingScheme::LinearProbing => unsafe {
+                            [<cpu_ht_probe_bitmap_linearprobing_ $Suffix>](
+                                hj.hash_table.mem.as_ptr(),
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                join_attr_len,
+                                bitmap.as_mut_ptr(),
+                                )
+                        },
+                        HashingScheme::BucketChaining => Err(ErrorKind::InvalidArgument(
+                                "Bucket chaining is not supported by bitmap probes".to_string()
+                                ))?,
+                        HashingScheme::Cuckoo => unsafe {
+                            [<cpu_ht_probe_bitmap_cuckoo_ $Suffix>](
+                                hj.hash_table.mem.as_ptr(),
+                                hash_table_size,
+                                hj.hash_table.cuckoo_seed.load(Ordering::SeqCst),
+                                join_attr.as_ptr(),
+                                join_attr_len,
+                                bitmap.as_mut_ptr(),
+                                )
+                        },
+                    };
+
+                    Ok(())
+                }
+            }
+
+            paste::item!{
+                fn probe_materialize_impl(
+                    hj: &mut CpuHashJoin<$Type>,
+                    join_attr: &[$Type],
+                    payload_attr: &[$Type],
+                    join_result: &mut [JoinMatch<$Type>],
+                    limit: Option<usize>,
+                    ) -> Result<usize> {
+
+                    if join_attr.len() != payload_attr.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Join and payload attributes have different sizes"
+                                .to_string()
+                                ))?;
+                    }
+                    check_aos_layout(hj.hash_table.layout, "Materializing probes")?;
+                    check_linear_step(hj.probe_step, "Materializing probes")?;
+
+                    if let Some(hash_fn) = hj.hash_fn {
+                        return probe_materialize_custom_hash(hj, join_attr, payload_attr, hash_fn, join_result, limit);
+                    }
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+                    let join_result_capacity = limit.unwrap_or(join_result.len()) as u64;
+
+                    let matches = match &hj.hashing_scheme {
+                        HashingScheme::LinearProbing => unsafe {
+                            [<cpu_ht_probe_materialize_linearprobing_ $Suffix>](
+                                hj.hash_table.mem.as_ptr(),
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                payload_attr.as_ptr(),
+                                join_attr_len,
+                                join_result.as_mut_ptr(),
+                                join_result_capacity,
+                                limit.is_some() as u32,
+                                )
+                        },
+                        _ => Err(ErrorKind::InvalidArgument(format!(
+                                    "Materializing probes support only LinearProbing, got {:?}",
+                                    hj.hashing_scheme
+                                    )))?,
+                    };
+
+                    Ok(matches as usize)
+                }
+            }
+        }
+    };
+}
+
+impl_cpu_hash_join_for_type!(i32, int32);
+impl_cpu_hash_join_for_type!(i64, int64);
+
+/// A Rust macro for specializing the implementation of a narrow join key type,
+/// e.g., of a dictionary-encoded column.
+///
+/// Narrow keys support only the `Perfect` hashing scheme. See
+/// `check_narrow_hash_table` for details.
+macro_rules! impl_cpu_hash_join_for_narrow_type {
+    ($Type:ty, $Suffix:expr) => {
+        impl CpuHashJoinable for $Type {
+            paste::item!{
+                fn build_impl(hj: &mut CpuHashJoin<$Type>, join_attr: &[$Type], payload_attr: Option<&[$Type]>) -> Result<()> {
+                    check_payload_len(join_attr.len(), payload_attr.map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr.map_or(ptr::null(), |p| p.as_ptr());
+                    check_layout_scheme(hj.hash_table.layout, hj.hashing_scheme, hj.is_selective)?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+
+                    check_narrow_hash_table::<$Type>(hj.hashing_scheme, hj.hash_table.size)?;
+                    if hj.hash_fn.is_some() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Narrow join keys don't support custom hash functions"
+                                .to_string()
+                                ))?;
+                    }
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+
+                    let region_name = cstr!("cpu_hash_join_build");
+                    likwid::marker_start_region(region_name)?;
+
+                    if hj.is_selective {
+                        unsafe {
+                            [<cpu_ht_build_selective_perfect_ $Suffix>](
+                                hj.hash_table.mem.as_ptr() as *mut _,
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                payload_attr_ptr,
+                                join_attr_len,
+                                )
+                        };
+                    } else {
+                        unsafe {
+                            [<cpu_ht_build_perfect_ $Suffix>](
+                                hj.hash_table.mem.as_ptr() as *mut _,
+                                hash_table_size,
+                                join_attr.as_ptr(),
+                                payload_attr_ptr,
+                                join_attr_len,
+                                )
+                        };
+                    }
+
+                    likwid::marker_stop_region(region_name)?;
+
+                    Ok(())
+                }
+            }
+
+            fn build_report_impl(
+                _hj: &mut CpuHashJoin<$Type>,
+                _join_attr: &[$Type],
+                _payload_attr: Option<&[$Type]>,
+                ) -> Result<BuildReport> {
+                Err(ErrorKind::InvalidArgument(
+                        "Narrow join keys don't support build reports".to_string()
+                        ).into())
+            }
+
+            paste::item!{
+                fn probe_sum_impl(
+                    hj: &mut CpuHashJoin<$Type>,
+                    join_attr: &[$Type],
+                    payload_attr: Option<&[$Type]>,
+                    join_result: &mut u64,
+                    ) -> Result<()> {
+
+                    check_payload_len(join_attr.len(), payload_attr.map(|p| p.len()))?;
+                    let payload_attr_ptr = payload_attr.map_or(ptr::null(), |p| p.as_ptr());
+                    check_layout_scheme(hj.hash_table.layout, hj.hashing_scheme, hj.is_selective)?;
+                    check_probe_step(hj.probe_step, hj.hashing_scheme, hj.hash_table.layout, hj.hash_table.size)?;
+
+                    check_narrow_hash_table::<$Type>(hj.hashing_scheme, hj.hash_table.size)?;
+                    if hj.hash_fn.is_some() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Narrow join keys don't support custom hash functions"
+                                .to_string()
+                                ))?;
+                    }
+
+                    let join_attr_len = join_attr.len() as u64;
+                    let hash_table_size = hj.hash_table.size as u64;
+
+                    let region_name = cstr!("cpu_hash_join_probe");
+                    likwid::marker_start_region(region_name)?;
+
+                    unsafe {
+                        [<cpu_ht_probe_aggregate_perfect_ $Suffix>](
+                            hj.hash_table.mem.as_ptr(),
+                            hash_table_size,
+                            join_attr.as_ptr(),
+                            payload_attr_ptr,
+                            join_attr_len,
+                            join_result,
+                            )
+                    };
+
+                    likwid::marker_stop_region(region_name)?;
+
+                    Ok(())
+                }
+            }
+
+            fn probe_bitmap_impl(
+                _hj: &mut CpuHashJoin<$Type>,
+                _join_attr: &[$Type],
+                _bitmap: &mut [u64],
+                ) -> Result<()> {
+                Err(ErrorKind::InvalidArgument(
+                        "Narrow join keys don't support bitmap probes".to_string()
+                        ).into())
+            }
+
+            fn probe_materialize_impl(
+                _hj: &mut CpuHashJoin<$Type>,
+                _join_attr: &[$Type],
+                _payload_attr: &[$Type],
+                _join_result: &mut [JoinMatch<$Type>],
+                _limit: Option<usize>,
+                ) -> Result<usize> {
+                Err(ErrorKind::InvalidArgument(
+                        "Narrow join keys don't support materializing probes".to_string()
+                        ).into())
+            }
+        }
+    };
+}
+
+impl_cpu_hash_join_for_narrow_type!(u8, uint8);
+impl_cpu_hash_join_for_narrow_type!(u16, uint16);
+
+/// Computes the launch dimensions that maximize the occupancy of a join kernel.
+///
+/// The block size is chosen by the CUDA occupancy calculator, and rounded down
+/// to a multiple of the warp size. The grid size is the number of blocks that
+/// can be resident per streaming multiprocessor, scaled to all
+/// multiprocessors of the device. The join kernels don't use dynamic shared
+/// memory.
+fn max_occupancy_launch_dim(kernel_name: &str, device: &Device) -> Result<(GridSize, BlockSize)> {
+    let kernel_name = CString::new(kernel_name)
+        .map_err(|_| ErrorKind::NulCharError("Kernel name contains a nul character".to_string()))?;
+    let function = crate::module_function(crate::module()?, &kernel_name)?;
+
+    let mut min_grid_size: c_int = 0;
+    let mut block_size: c_int = 0;
+
+    unsafe {
+        // FIXME: Find a safer solution to replace transmute_copy!!!
+        let cu_function = transmute_copy::<Function<'_>, CUfunction>(&function);
+        cuOccupancyMaxPotentialBlockSize(
+            &mut min_grid_size,
+            &mut block_size,
+            cu_function,
+            None,
+            0,
+            0,
+        )
+    }
+    .to_result()?;
+
+    let warp_size = device.get_attribute(DeviceAttribute::WarpSize)? as u32;
+    let multiprocessors = device.get_attribute(DeviceAttribute::MultiprocessorCount)? as u32;
+
+    let block_size = block_size as u32 / warp_size * warp_size;
+    if block_size == 0 {
+        Err(ErrorKind::RuntimeError(
+            "The kernel cannot be launched with a full warp".to_string(),
+        ))?;
+    }
+
+    // The minimum grid size for maximum occupancy fills all multiprocessors.
+    // Round it up to a multiple of the multiprocessors to balance the load.
+    let blocks_per_sm = (min_grid_size as u32 + multiprocessors - 1) / multiprocessors;
+    let grid_size = std::cmp::max(blocks_per_sm, 1) * multiprocessors;
+
+    Ok((GridSize::x(grid_size), BlockSize::x(block_size)))
+}
+
+/// Checks that the payload attribute, if there is one, has the same length as
+/// the join attribute.
+fn check_payload_len(join_attr_len: usize, payload_attr_len: Option<usize>) -> Result<()> {
+    match payload_attr_len {
+        Some(len) if len != join_attr_len => Err(ErrorKind::InvalidArgument(
+            "Join and payload attributes have different sizes".to_string(),
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the hashing scheme supports the hash table layout.
+fn check_layout_scheme(
+    layout: HashTableLayout,
+    hashing_scheme: HashingScheme,
+    is_selective: bool,
+) -> Result<()> {
+    match (layout, hashing_scheme, is_selective) {
+        (HashTableLayout::AoS, _, _)
+        | (HashTableLayout::SoA, HashingScheme::LinearProbing, false) => Ok(()),
+        _ => Err(ErrorKind::InvalidArgument(format!(
+            "The SoA hash table layout supports only non-selective LinearProbing, got {:?}",
+            hashing_scheme
+        ))
+        .into()),
+    }
+}
+
+/// Checks that the hash table has the `AoS` layout, which `operation`
+/// requires.
+fn check_aos_layout(layout: HashTableLayout, operation: &str) -> Result<()> {
+    match layout {
+        HashTableLayout::AoS => Ok(()),
+        HashTableLayout::SoA => Err(ErrorKind::InvalidArgument(format!(
+            "{} don't support the SoA hash table layout",
+            operation
+        ))
+        .into()),
+    }
+}
+
+/// Checks that the hash table supports the probe step.
+///
+/// Quadratic steps cover the whole table only if its size is a power of two.
+fn check_probe_step(
+    probe_step: ProbeStep,
+    hashing_scheme: HashingScheme,
+    hash_table_layout: HashTableLayout,
+    hash_table_size: usize,
+) -> Result<()> {
+    match (probe_step, hashing_scheme, hash_table_layout) {
+        (ProbeStep::Linear, _, _) => Ok(()),
+        (ProbeStep::Quadratic, HashingScheme::LinearProbing, HashTableLayout::AoS) => {
+            if hash_table_size.is_power_of_two() {
+                Ok(())
+            } else {
+                Err(ErrorKind::InvalidArgument(format!(
+                    "Quadratic probing requires a power-of-two hash table size, got {}",
+                    hash_table_size
+                ))
+                .into())
+            }
+        }
+        _ => Err(ErrorKind::InvalidArgument(format!(
+            "Quadratic probing supports only LinearProbing with the AoS layout, got {:?} with {:?}",
+            hashing_scheme, hash_table_layout
+        ))
+        .into()),
+    }
+}
+
+/// Checks that `operation`, which probes only with linear steps, isn't used
+/// with quadratic steps.
+fn check_linear_step(probe_step: ProbeStep, operation: &str) -> Result<()> {
+    match probe_step {
+        ProbeStep::Linear => Ok(()),
+        ProbeStep::Quadratic => Err(ErrorKind::InvalidArgument(format!(
+            "{} don't support quadratic probing",
+            operation
+        ))
+        .into()),
+    }
+}
+
+/// Checks that a custom hash function is used with a supported hashing scheme.
+fn check_custom_hash_scheme(hashing_scheme: HashingScheme, is_selective: bool) -> Result<()> {
+    match (hashing_scheme, is_selective) {
+        (HashingScheme::LinearProbing, false) => Ok(()),
+        _ => Err(ErrorKind::InvalidArgument(format!(
+            "Custom hash functions support only non-selective LinearProbing, got {:?}",
+            hashing_scheme
+        ))
+        .into()),
+    }
+}
+
+/// Implements `CpuHashJoin::build` with a custom hash function.
+fn build_custom_hash<T>(
+    hj: &CpuHashJoin<T>,
+    join_attr: &[T],
+    payload_attr: Option<&[T]>,
+    hash_fn: CpuHashFn<T>,
+) -> Result<()>
+where
+    T: AtomicKey + Default + DeviceCopy,
+{
+    check_custom_hash_scheme(hj.hashing_scheme, hj.is_selective)?;
+
+    let region_name = cstr!("cpu_hash_join_build");
+    likwid::marker_start_region(region_name)?;
+
+    let is_built = unsafe {
+        custom_hash::build(
+            hj.hash_table.mem.as_ptr() as *mut _,
+            hj.hash_table.size,
+            join_attr,
+            payload_attr,
+            hash_fn,
+        )
+    };
+
+    likwid::marker_stop_region(region_name)?;
+
+    if !is_built? {
+        Err(ErrorKind::RuntimeError(
+            "Failed to build the hash table with the custom hash function, because \
+            the hash table is full"
+                .to_string(),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Implements `CpuHashJoin::probe_sum` with a custom hash function.
+fn probe_sum_custom_hash<T>(
+    hj: &CpuHashJoin<T>,
+    join_attr: &[T],
+    payload_attr: Option<&[T]>,
+    hash_fn: CpuHashFn<T>,
+    join_result: &mut u64,
+) -> Result<()>
+where
+    T: AtomicKey + DeviceCopy + Into<i64>,
+{
+    check_custom_hash_scheme(hj.hashing_scheme, hj.is_selective)?;
+
+    let region_name = cstr!("cpu_hash_join_probe");
+    likwid::marker_start_region(region_name)?;
+
+    let probe_result = unsafe {
+        custom_hash::probe_sum(
+            hj.hash_table.mem.as_ptr(),
+            hj.hash_table.size,
+            join_attr,
+            payload_attr,
+            hash_fn,
+            join_result,
+        )
+    };
+
+    likwid::marker_stop_region(region_name)?;
+
+    probe_result
+}
+
+/// Implements `CpuHashJoin::probe_bitmap` with a custom hash function.
+fn probe_bitmap_custom_hash<T>(
+    hj: &CpuHashJoin<T>,
+    join_attr: &[T],
+    hash_fn: CpuHashFn<T>,
+    bitmap: &mut [u64],
+) -> Result<()>
+where
+    T: AtomicKey + DeviceCopy,
+{
+    check_custom_hash_scheme(hj.hashing_scheme, hj.is_selective)?;
+
+    unsafe {
+        custom_hash::probe_bitmap(
+            hj.hash_table.mem.as_ptr(),
+            hj.hash_table.size,
+            join_attr,
+            hash_fn,
+            bitmap,
+        )
+    }?;
+
+    Ok(())
+}
+
+/// Implements `CpuHashJoin::probe_materialize` with a custom hash function.
+fn probe_materialize_custom_hash<T>(
+    hj: &CpuHashJoin<T>,
+    join_attr: &[T],
+    payload_attr: &[T],
+    hash_fn: CpuHashFn<T>,
+    join_result: &mut [JoinMatch<T>],
+    limit: Option<usize>,
+) -> Result<usize>
+where
+    T: AtomicKey + DeviceCopy,
+{
+    check_custom_hash_scheme(hj.hashing_scheme, hj.is_selective)?;
+
+    let matches = unsafe {
+        custom_hash::probe_materialize(
+            hj.hash_table.mem.as_ptr(),
+            hj.hash_table.size,
+            join_attr,
+            payload_attr,
+            hash_fn,
+            join_result,
+            limit,
+        )
+    }?;
+
+    Ok(matches)
+}
+
+/// Checks that a narrow key type uses a directly-addressed hash table.
+///
+/// Narrow keys have a small domain, e.g., 2^8 values for `u8`. Thus, the
+/// `Perfect` hashing scheme can use the key as the index into a hash table that
+/// covers the whole domain, which requires neither hashing nor collision
+/// handling. This is the only hashing scheme supported for narrow keys,
+/// because the other schemes require atomic compare-and-swap instructions on
+/// the key, which the GPU does not support for 8-bit integers.
+///
+/// As the hash table covers the whole domain including the `NULL` key, the
+/// probe skips `NULL` keys.
+fn check_narrow_hash_table<T: AsPrimitive<usize> + KeyAttribute>(
+    hashing_scheme: HashingScheme,
+    hash_table_size: usize,
+) -> Result<()> {
+    // The NULL key is the largest value in the domain
+    let domain_len = T::null_key().as_() + 1;
+
+    match hashing_scheme {
+        HashingScheme::Perfect => {}
+        _ => Err(ErrorKind::InvalidArgument(format!(
+            "Narrow join keys support only the Perfect hashing scheme, got {:?}",
+            hashing_scheme
+        )))?,
+    }
+
+    if hash_table_size < domain_len {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Perfect hash table for narrow join keys must cover the key domain of {} values",
+            domain_len
+        )))?;
+    }
+
+    Ok(())
+}
+
+impl<T: AsPrimitive<c_uint> + DeviceCopy + KeyAttribute> HashTable<T> {
+    /// Returns an empty hash table entry.
+    ///
+    /// The value is also set to the NULL key, because the SoA layout stores
+    /// keys in the memory of the AoS values.
+    fn null_entry() -> HtEntry<T, T> {
+        HtEntry {
+            key: T::null_key(),
+            value: T::null_key(),
+        }
+    }
+
+    /// Checks that a hash table of `size` entries fits into the maximum
+    /// allocation size.
+    ///
+    /// The constructors take memory that the caller allocated. Thus, call this
+    /// function before allocating the memory, so that an overflowing size
+    /// returns an `IntegerOverflow` error instead of a failed allocation.
+    pub fn check_len(size: usize) -> Result<()> {
+        crate::checked_alloc_bytes::<HtEntry<T, T>>(size, "Hash table")?;
+        Ok(())
+    }
+
+    /// Create a new CPU hash table.
+    ///
+    /// The hash table can be used on CPUs. In the case of NVLink 2.0 on POWER9,
+    /// it can also be used on GPUs.
+    ///
+    /// `mem` must be allocated after checking `size` with `check_len`.
+    pub fn new_on_cpu(mut mem: DerefMem<HtEntry<T, T>>, size: usize) -> Result<Self> {
+        Self::check_len(size)?;
+
+        if mem.len() < size {
+            Err(ErrorKind::InvalidArgument(
+                "Provided memory must be larger than hash table size".to_string(),
+            ))?;
+        }
+
+        mem.iter_mut()
+            .by_ref()
+            .for_each(|x| *x = Self::null_entry());
+
+        Ok(Self {
+            mem: mem.into(),
+            size,
+            cuckoo_seed: AtomicU32::new(0),
+            cuckoo_is_built: AtomicBool::new(false),
+            layout: HashTableLayout::default(),
+        })
+    }
+
+    /// Create a new GPU hash table.
+    ///
+    /// The hash table can be used on GPUs. It cannot always be used on CPUs,
+    /// due to the possibility of using GPU device memory. This also holds true
+    /// for NVLink 2.0 on POWER9.
+    ///
+    /// The hash table takes ownership of `mem` without copying it. CPU memory
+    /// is initialized by the CPU, whereas GPU device memory is initialized
+    /// on the GPU with a memset. In both cases, the function blocks until the
+    /// initialization is complete.
+    ///
+    /// `mem` must be allocated after checking `size` with `check_len`.
+    pub fn new_on_gpu(mut mem: Mem<HtEntry<T, T>>, size: usize) -> Result<Self> {
+        Self::check_len(size)?;
+
+        if mem.len() < size {
+            Err(ErrorKind::InvalidArgument(
+                "Provided memory must be larger than hash table size".to_string(),
+            ))?;
+        }
+
+        let mem_ptr = mem.as_ptr();
+        let mem_len = mem.len();
+
+        // Initialize hash table
+        match mem {
+            Mem::SysMem(ref mut mem) => mem
+                .iter_mut()
+                .by_ref()
+                .for_each(|x| *x = Self::null_entry()),
+            Mem::BoxedSysMem(ref mut mem) => mem
+                .iter_mut()
+                .by_ref()
+                .for_each(|x| *x = Self::null_entry()),
+            Mem::NumaMem(ref mut mem) => mem
+                .iter_mut()
+                .by_ref()
+                .for_each(|x| *x = Self::null_entry()),
+            Mem::CudaPinnedMem(ref mut mem) => mem
+                .iter_mut()
+                .by_ref()
+                .for_each(|x| *x = Self::null_entry()),
+            Mem::DistributedNumaMem(ref mut mem) => mem
+                .iter_mut()
+                .by_ref()
+                .for_each(|x| *x = Self::null_entry()),
+            _ => {
+                let entry_bytes = size_of::<HtEntry<T, T>>();
+
+                // Entries of narrow keys (e.g., `u8`) can be smaller than 32 bits
+                if entry_bytes % size_of::<c_uint>() == 0 {
+                    unsafe {
+                        cuMemsetD32_v2(
+                            mem_ptr as *mut c_void as u64,
+                            T::null_key().as_(),
+                            mem_len
+                                .checked_mul(entry_bytes / size_of::<c_uint>())
+                                .ok_or_else(|| {
+                                    ErrorKind::IntegerOverflow(
+                                        "Failed to compute hash table bytes".to_string(),
+                                    )
+                                })?,
+                        )
+                    }
+                    .to_result()?;
+                } else {
+                    unsafe {
+                        cuMemsetD16_v2(
+                            mem_ptr as *mut c_void as u64,
+                            T::null_key().as_() as u16,
+                            mem_len
+                                .checked_mul(entry_bytes / size_of::<u16>())
+                                .ok_or_else(|| {
+                                    ErrorKind::IntegerOverflow(
+                                        "Failed to compute hash table bytes".to_string(),
+                                    )
+                                })?,
+                        )
+                    }
+                    .to_result()?;
+                }
+
+                // FIXME: use cuMemsetD32Async on a user-given stream, and
+                // remove the context synchronization. Sync is required because
+                // cuMemsetD32_v2 is async if using device memory (see CUDA docs).
+                CurrentContext::synchronize()?;
+            }
+        }
+
+        Ok(Self {
+            mem,
+            size,
+            cuckoo_seed: AtomicU32::new(0),
+            cuckoo_is_built: AtomicBool::new(false),
+            layout: HashTableLayout::default(),
+        })
+    }
+
+    /// Create a new GPU hash table in an existing device buffer.
+    ///
+    /// In contrast to `new_on_gpu`, the buffer is initialized asynchronously
+    /// on `stream`. Thus, the hash table is ready after the stream has
+    /// completed the initialization. Kernels launched afterwards on the same
+    /// stream, e.g., the build kernel, observe the initialized hash table.
+    ///
+    /// `mem` must be allocated after checking `size` with `check_len`.
+    pub fn from_device_buffer(
+        mut mem: DeviceBuffer<HtEntry<T, T>>,
+        size: usize,
+        stream: &Stream,
+    ) -> Result<Self> {
+        Self::check_len(size)?;
+
+        if mem.len() < size {
+            Err(ErrorKind::InvalidArgument(
+                "Provided memory must be larger than hash table size".to_string(),
+            ))?;
+        }
+
+        let null_key: c_uint = T::null_key().as_();
+
+        // Entries of narrow keys (e.g., `u8`) can be smaller than 32 bits
+        if size_of::<HtEntry<T, T>>() % size_of::<c_uint>() == 0 {
+            cuda_wrapper::memset_async(mem.as_launchable_mut_slice(), null_key as i32, stream)?;
+        } else {
+            cuda_wrapper::memset_d16_async(mem.as_launchable_mut_slice(), null_key as u16, stream)?;
+        }
+
+        Ok(Self {
+            mem: Mem::CudaDevMem(mem),
+            size,
+            cuckoo_seed: AtomicU32::new(0),
+            cuckoo_is_built: AtomicBool::new(false),
+            layout: HashTableLayout::default(),
+        })
+    }
+
+    /// Create a new hash table from another hash table.
+    ///
+    /// Copies the contents of the source hash table into the new hash table.
+    pub fn new_from_hash_table(mut mem: Mem<HtEntry<T, T>>, src: &Self) -> Result<Self> {
+        mem.copy_from_mem(&src.mem)?;
+
+        Ok(Self {
+            mem,
+            size: src.size,
+            cuckoo_seed: AtomicU32::new(src.cuckoo_seed.load(Ordering::SeqCst)),
+            cuckoo_is_built: AtomicBool::new(src.cuckoo_is_built.load(Ordering::SeqCst)),
+            layout: src.layout,
+        })
+    }
+
+    /// Copies the built hash table to each of the NUMA `nodes`.
+    ///
+    /// The replicas are snapshots, thus the hash table must be completely
+    /// built before replicating it. The hash table must be accessible by the
+    /// CPU.
+    pub fn replicate_across_nodes(&self, nodes: &[u16]) -> Result<ReplicatedHashTable<T>> {
+        if nodes.is_empty() {
+            Err(ErrorKind::InvalidArgument(
+                "Replication requires at least one NUMA node".to_string(),
+            ))?;
+        }
+
+        let replicas = nodes
+            .iter()
+            .map(|&node| {
+                let mem = allocator::Allocator::alloc_mem(
+                    allocator::MemType::NumaMem {
+                        node,
+                        page_type: PageType::Default,
+                    },
+                    self.mem.len(),
+                );
+                let replica = Self::new_from_hash_table(mem, self)?;
+                Ok((node, Arc::new(replica)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ReplicatedHashTable { replicas })
+    }
+
+    /// Sets the memory layout of the hash table.
+    ///
+    /// The layout must be set before the build, as the existing entries are
+    /// not converted. The default layout is `AoS`.
+    pub fn with_layout(mut self, layout: HashTableLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Returns the memory layout of the hash table.
+    pub fn layout(&self) -> HashTableLayout {
+        self.layout
+    }
+
+    /// Returns the hash table entries, if the CPU can access the memory.
+    pub fn try_as_slice(&self) -> Result<&[HtEntry<T, T>]> {
+        Ok(&self.mem.try_as_slice()?[..self.size])
+    }
+}
+
+impl<T: AsPrimitive<c_uint> + DeviceCopy + FileTypeTag + KeyAttribute> HashTable<T> {
+    /// Saves the built hash table to a file.
+    ///
+    /// A hash table doesn't know the hashing scheme with which it was built.
+    /// Thus, the scheme is passed in and recorded in the file header, together
+    /// with the key type, the layout, and the size. `load_mmap` checks the
+    /// header before probing the table in another process.
+    ///
+    /// The entries are written in native byte order. The hash table must be
+    /// accessible by the CPU.
+    pub fn save<P: AsRef<Path>>(&self, path: P, hashing_scheme: HashingScheme) -> Result<()> {
+        let entries = self.mem.try_as_slice().map_err(|_| {
+            ErrorKind::InvalidArgument("Cannot save a hash table from device memory".to_string())
+        })?;
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&HT_FILE_MAGIC);
+        header.extend_from_slice(&HT_FILE_VERSION.to_le_bytes());
+        header.extend_from_slice(&T::FILE_TYPE_TAG.to_le_bytes());
+        header.extend_from_slice(&(size_of::<HtEntry<T, T>>() as u32).to_le_bytes());
+        header.extend_from_slice(&hashing_scheme_tag(hashing_scheme).to_le_bytes());
+        header.extend_from_slice(&layout_tag(self.layout).to_le_bytes());
+        header.extend_from_slice(&self.cuckoo_seed.load(Ordering::SeqCst).to_le_bytes());
+        header.extend_from_slice(&(self.size as u64).to_le_bytes());
+        header.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+        header.resize(HT_FILE_ENTRIES_OFFSET as usize, 0);
+
+        let entry_bytes = unsafe {
+            std::slice::from_raw_parts(entries.as_ptr() as *const u8, size_of_val(entries))
+        };
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&header)?;
+        writer.write_all(entry_bytes)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Maps a hash table file created by `save` into memory.
+    ///
+    /// Returns an error if the file was saved with another hashing scheme,
+    /// another key type, or another file version. The table is ready to be
+    /// probed, e.g., by a `CpuHashJoin` with the same hashing scheme.
+    ///
+    /// The file is mapped privately and read-only. Entries are read lazily on
+    /// first access. The loaded table can only be probed; it must not be built
+    /// again.
+    pub fn load_mmap<P: AsRef<Path>>(
+        path: P,
+        hashing_scheme: HashingScheme,
+        node: u16,
+    ) -> Result<Self> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(&file);
+
+        let mut magic = [0_u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != HT_FILE_MAGIC {
+            Err(ErrorKind::InvalidArgument(
+                "File does not contain a hash table".to_string(),
+            ))?;
+        }
+
+        let version = read_u32(&mut reader)?;
+        if version != HT_FILE_VERSION {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Unsupported hash table file version {}, expected version {}",
+                version, HT_FILE_VERSION
+            )))?;
+        }
+
+        let type_tag = read_u32(&mut reader)?;
+        let entry_bytes = read_u32(&mut reader)?;
+        if type_tag != T::FILE_TYPE_TAG || entry_bytes as usize != size_of::<HtEntry<T, T>>() {
+            Err(ErrorKind::InvalidArgument(format!(
+                "File contains a hash table with key type tag {} ({} bytes per entry), but expected {} ({} bytes per entry)",
+                type_tag,
+                entry_bytes,
+                T::FILE_TYPE_TAG,
+                size_of::<HtEntry<T, T>>()
+            )))?;
+        }
+
+        let scheme_tag = read_u32(&mut reader)?;
+        if scheme_tag != hashing_scheme_tag(hashing_scheme) {
+            Err(ErrorKind::InvalidArgument(format!(
+                "File contains a hash table with hashing scheme tag {}, but expected {:?}",
+                scheme_tag, hashing_scheme
+            )))?;
+        }
+
+        let layout = match read_u32(&mut reader)? {
+            0 => HashTableLayout::AoS,
+            1 => HashTableLayout::SoA,
+            tag => Err(ErrorKind::InvalidArgument(format!(
+                "File contains an invalid hash table layout tag {}",
+                tag
+            )))?,
+        };
+        let cuckoo_seed = read_u32(&mut reader)?;
+        let size = read_u64(&mut reader)? as usize;
+        let len = read_u64(&mut reader)? as usize;
+        if size > len {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Hash table size {} exceeds the number of entries {}",
+                size, len
+            )))?;
+        }
+        Self::check_len(len)?;
+
+        let mem = numa::NumaMemory::map_file(&file, HT_FILE_ENTRIES_OFFSET, len, node)?;
+
+        Ok(Self {
+            mem: Mem::NumaMem(mem),
+            size,
+            cuckoo_seed: AtomicU32::new(cuckoo_seed),
+            cuckoo_is_built: AtomicBool::new(true),
+            layout,
+        })
+    }
+}
+
+/// Returns the tag of a hashing scheme in the hash table file header.
+fn hashing_scheme_tag(hashing_scheme: HashingScheme) -> u32 {
+    match hashing_scheme {
+        HashingScheme::Perfect => 0,
+        HashingScheme::LinearProbing => 1,
+        HashingScheme::BucketChaining => 2,
+        HashingScheme::Cuckoo => 3,
+    }
+}
+
+/// Returns the tag of a hash table layout in the hash table file header.
+fn layout_tag(layout: HashTableLayout) -> u32 {
+    match layout {
+        HashTableLayout::AoS => 0,
+        HashTableLayout::SoA => 1,
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut bytes = [0_u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0_u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+impl<T: DeviceCopy + KeyAttribute> ReplicatedHashTable<T> {
+    /// Returns the NUMA nodes that hold a replica.
+    pub fn nodes(&self) -> Vec<u16> {
+        self.replicas.iter().map(|&(node, _)| node).collect()
+    }
+
+    /// Returns the replica on `node`, if one exists.
+    pub fn replica(&self, node: u16) -> Option<Arc<HashTable<T>>> {
+        self.replicas
+            .iter()
+            .find(|&&(replica_node, _)| replica_node == node)
+            .map(|(_, replica)| replica.clone())
+    }
+
+    /// Returns the replica on the NUMA node of the calling thread.
+    ///
+    /// Threads on a node without a replica fall back to the first replica.
+    /// The thread should be pinned to a core, as otherwise the OS might later
+    /// migrate the thread to a different node.
+    pub fn local_replica(&self) -> Result<Arc<HashTable<T>>> {
+        let cpu = CpuAffinity::get_cpu()?;
+        let node = numa::node_of_cpu(cpu)?;
+
+        Ok(self
+            .replica(node)
+            .unwrap_or_else(|| self.replicas[0].1.clone()))
+    }
+}
+
+impl<T: DeviceCopy + KeyAttribute> MemLock for HashTable<T> {
+    fn mlock(&mut self) -> NumaGpuResult<()> {
+        self.mem.mlock()?;
+
+        Ok(())
+    }
+
+    fn munlock(&mut self) -> NumaGpuResult<()> {
+        self.mem.munlock()?;
+
+        Ok(())
+    }
+}
+
+impl<T: DeviceCopy + KeyAttribute> ::std::default::Default for CudaHashJoinBuilder<T> {
+    fn default() -> Self {
+        // Pre-load the CUDA module to enable callers to compute the amount of
+        // free GPU memory after instatiating `CudaHashJoinBuilder`. Errors are
+        // deferred to `build`, which tries to load the module again.
+        let _ = crate::module();
+
+        Self {
+            hashing_scheme: HashingScheme::default(),
+            probe_step: ProbeStep::default(),
+            is_selective: false,
+            hash_table_i: None,
+            build_dim_i: (1.into(), 1.into()),
+            probe_dim_i: (1.into(), 1.into()),
+            count_granularity_i: CountGranularity::default(),
+            stable_output_i: false,
+            read_only_cache_i: false,
+            l2_persisting_i: false,
+            shared_memory_placement_i: false,
+            shared_memory_budget_i: None,
+        }
+    }
+}
+
+impl<T> CudaHashJoinBuilder<T>
+where
+    T: Clone + Default + DeviceCopy + KeyAttribute,
+{
+    const DEFAULT_HT_SIZE: usize = 1024;
+
+    pub fn hashing_scheme(mut self, hashing_scheme: HashingScheme) -> Self {
+        self.hashing_scheme = hashing_scheme;
+        self
+    }
+
+    /// Sets the slot advancement of the `LinearProbing` scheme.
+    ///
+    /// The build and the probe must use the same step. The shared memory
+    /// placement supports only linear steps, thus quadratic steps always
+    /// probe the hash table in global memory. See `ProbeStep` for details.
+    pub fn probe_step(mut self, probe_step: ProbeStep) -> Self {
+        self.probe_step = probe_step;
+        self
+    }
+
+    pub fn is_selective(mut self, is_selective: bool) -> Self {
+        self.is_selective = is_selective;
+        self
+    }
+
+    pub fn hash_table(mut self, ht: Arc<HashTable<T>>) -> Self {
+        self.hash_table_i = Some(ht);
+        self
+    }
+
+    pub fn build_dim(mut self, grid: GridSize, block: BlockSize) -> Self {
+        self.build_dim_i = (grid, block);
+        self
+    }
+
+    pub fn probe_dim(mut self, grid: GridSize, block: BlockSize) -> Self {
+        self.probe_dim_i = (grid, block);
+        self
+    }
+
+    pub fn count_granularity(mut self, count_granularity: CountGranularity) -> Self {
+        self.count_granularity_i = count_granularity;
+        self
+    }
+
+    /// Writes the results of `probe_materialize` in probe relation order.
+    ///
+    /// By default, the GPU appends matches in the order that threads find them,
+    /// which differs between runs. A stable output is byte-identical to the
+    /// output of `CpuHashJoin::probe_materialize`, given unique build keys. The
+    /// probe then runs twice: first to count the matches of each probe tuple,
+    /// and then to write the matches at the prefix sum of the counts.
+    pub fn stable_output(mut self, stable_output: bool) -> Self {
+        self.stable_output_i = stable_output;
+        self
+    }
+
+    /// Loads the hash table through the read-only data cache in `probe_sum`.
+    ///
+    /// The hash table is immutable after the build, thus the probe can use the
+    /// non-coherent read-only path (i.e., `__ldg`) for its random accesses.
+    /// The hash table must not be modified while a probe is running, e.g., by
+    /// a concurrent build on another stream that shares the hash table.
+    pub fn read_only_cache(mut self, read_only_cache: bool) -> Self {
+        self.read_only_cache_i = read_only_cache;
+        self
+    }
+
+    /// Persists the hash table in the L2 cache during the probes.
+    ///
+    /// `build` sets aside the L2 cache for the hash table once. Each probe
+    /// then sets an access policy window over the hash table on its stream, so
+    /// that the streaming accesses to the probe relation don't evict the hash
+    /// table from the L2 cache. After launching the probe, the window is
+    /// removed and the persisting cache lines are reset. On GPUs without a
+    /// set-aside L2 cache (i.e., before Ampere), the option has no effect.
+    pub fn l2_persisting(mut self, l2_persisting: bool) -> Self {
+        self.l2_persisting_i = l2_persisting;
+        self
+    }
+
+    /// Probe a tiny hash table from shared memory.
+    ///
+    /// If enabled and the hash table fits into the shared memory budget, each
+    /// thread block of the probe copies the hash table into its shared memory
+    /// (see `HashTablePlacement::SharedMemory`). `CudaHashJoin::placement`
+    /// returns the chosen placement. By default, the option is disabled, and
+    /// the probe always reads the hash table from global memory.
+    pub fn shared_memory_placement(mut self, shared_memory_placement: bool) -> Self {
+        self.shared_memory_placement_i = shared_memory_placement;
+        self
+    }
+
+    /// Limits the shared memory per thread block that may hold the hash table.
+    ///
+    /// Applies only if `shared_memory_placement` is enabled. By default, the
+    /// budget is the device's maximum shared memory per thread block.
+    pub fn shared_memory_budget(mut self, bytes: usize) -> Self {
+        self.shared_memory_budget_i = Some(bytes);
+        self
+    }
+
+    /// Chooses the shared memory placement if it's enabled, and the hash table
+    /// supports it and fits into the budget.
+    fn placement(&self, hash_table: &HashTable<T>) -> Result<HashTablePlacement> {
+        let is_supported = self.shared_memory_placement_i
+            && matches!(self.hashing_scheme, HashingScheme::LinearProbing)
+            && self.probe_step == ProbeStep::Linear
+            && !self.is_selective
+            && hash_table.layout == HashTableLayout::AoS
+            && hash_table.size > 0;
+        if !is_supported {
+            return Ok(HashTablePlacement::Global);
+        }
+
+        let budget = match self.shared_memory_budget_i {
+            Some(bytes) => bytes,
+            None => CurrentContext::get_device()?
+                .get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)?
+                as usize,
+        };
+        let hash_table_bytes = hash_table.size.checked_mul(size_of::<HtEntry<T, T>>());
+
+        match hash_table_bytes {
+            Some(bytes) if bytes <= budget => Ok(HashTablePlacement::SharedMemory),
+            _ => Ok(HashTablePlacement::Global),
+        }
+    }
+
+    pub fn build(&self) -> Result<CudaHashJoin<T>> {
+        if self.hash_table_i.is_none() {
+            Err(ErrorKind::InvalidArgument("Hash table not set".to_string()))?;
+        }
+
+        crate::module()?;
+
+        let hash_table = if let Some(ht) = self.hash_table_i.clone() {
+            ht
+        } else {
+            Arc::new(HashTable {
+                mem: allocator::Allocator::alloc_mem::<HtEntry<T, T>>(
+                    allocator::MemType::CudaUniMem,
+                    Self::DEFAULT_HT_SIZE,
+                ),
+                size: Self::DEFAULT_HT_SIZE,
+                cuckoo_seed: AtomicU32::new(0),
+                cuckoo_is_built: AtomicBool::new(false),
+                layout: HashTableLayout::default(),
+            })
+        };
+
+        let placement = self.placement(&hash_table)?;
+
+        if self.l2_persisting_i {
+            let hash_table_bytes = hash_table
+                .mem
+                .len()
+                .checked_mul(size_of::<HtEntry<T, T>>())
+                .ok_or_else(|| {
+                    ErrorKind::IntegerOverflow("Failed to compute hash table bytes".to_string())
+                })?;
+            l2_persistence::set_aside_l2_cache(hash_table_bytes)?;
+        }
+
+        Ok(CudaHashJoin {
+            hashing_scheme: self.hashing_scheme,
+            probe_step: self.probe_step,
+            is_selective: self.is_selective,
+            hash_table,
+            build_dim: self.build_dim_i.clone(),
+            probe_dim: self.probe_dim_i.clone(),
+            count_granularity: self.count_granularity_i,
+            stable_output: self.stable_output_i,
+            read_only_cache: self.read_only_cache_i,
+            l2_persisting: self.l2_persisting_i,
+            placement,
+        })
+    }
+}
+
+impl<T: DeviceCopy + KeyAttribute> ::std::default::Default for CpuHashJoinBuilder<T> {
+    fn default() -> Self {
+        Self {
+            hashing_scheme: HashingScheme::default(),
+            probe_step: ProbeStep::default(),
+            is_selective: false,
+            hash_table_i: None,
+            hash_fn_i: None,
+        }
+    }
+}
+
+impl<T: Clone + Default + DeviceCopy + KeyAttribute> CpuHashJoinBuilder<T> {
+    const DEFAULT_HT_SIZE: usize = 1024;
+
+    pub fn hashing_scheme(mut self, hashing_scheme: HashingScheme) -> Self {
+        self.hashing_scheme = hashing_scheme;
+        self
+    }
+
+    /// Sets the slot advancement of the `LinearProbing` scheme.
+    ///
+    /// A hash table built with one step cannot be probed with the other. See
+    /// `ProbeStep` for the supported operations.
+    pub fn probe_step(mut self, probe_step: ProbeStep) -> Self {
+        self.probe_step = probe_step;
+        self
+    }
+
+    pub fn is_selective(mut self, is_selective: bool) -> Self {
+        self.is_selective = is_selective;
+        self
+    }
+
+    pub fn hash_table(mut self, hash_table: Arc<HashTable<T>>) -> Self {
+        self.hash_table_i = Some(hash_table);
+        self
+    }
+
+    /// Replaces the built-in hash function with `hash_fn`.
+    ///
+    /// A custom hash function requires the `LinearProbing` hashing scheme, and
+    /// is supported only for 32-bit and 64-bit keys. The build and probe run in
+    /// Rust instead of C++, which might affect the performance. Note that this
+    /// option is CPU-only, because the GPU cannot call CPU functions.
+    pub fn hash_fn(mut self, hash_fn: CpuHashFn<T>) -> Self {
+        self.hash_fn_i = Some(hash_fn);
+        self
+    }
+
+    /// Build a `CpuHashJoin` that probes the replica local to the calling
+    /// thread.
+    ///
+    /// Call this method on the probe thread, because the replica is selected
+    /// by the thread's current NUMA node. Ignores the hash table set with
+    /// `hash_table`.
+    pub fn build_with_local_replica(
+        &self,
+        replicas: &ReplicatedHashTable<T>,
+    ) -> Result<CpuHashJoin<T>> {
+        Ok(CpuHashJoin {
+            hashing_scheme: self.hashing_scheme,
+            probe_step: self.probe_step,
+            is_selective: self.is_selective,
+            hash_table: replicas.local_replica()?,
+            hash_fn: self.hash_fn_i,
+        })
+    }
+
+    pub fn build(&self) -> CpuHashJoin<T> {
+        let hash_table = match &self.hash_table_i {
+            Some(ht) => ht.clone(),
+            None => Arc::new(HashTable {
+                mem: allocator::Allocator::alloc_mem::<HtEntry<T, T>>(
+                    allocator::MemType::SysMem,
+                    Self::DEFAULT_HT_SIZE,
+                ),
+                size: Self::DEFAULT_HT_SIZE,
+                cuckoo_seed: AtomicU32::new(0),
+                cuckoo_is_built: AtomicBool::new(false),
+                layout: HashTableLayout::default(),
+            }),
+        };
+
+        CpuHashJoin {
+            hashing_scheme: self.hashing_scheme,
+            probe_step: self.probe_step,
+            is_selective: self.is_selective,
+            hash_table,
+            hash_fn: self.hash_fn_i,
+        }
+    }
+}
+
+impl<T> ::std::fmt::Display for HashTable<T>
+where
+    T: DeviceCopy + ::std::fmt::Display + KeyAttribute,
+{
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        if let CudaDevMem(_) = self.mem {
+            write!(f, "[Cannot print device memory]")?;
+        } else {
+            write!(f, "[")?;
+            match self.mem {
+                SysMem(ref m) => m.as_slice(),
+                CudaUniMem(ref m) => m.as_slice(),
+                _ => &[],
+            }
+            .iter()
+            .take(self.size)
+            .map(|entry| write!(f, "{}:{},", entry.key, entry.value))
+            .collect::<::std::fmt::Result>()?;
+            write!(f, "]")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ::std::default::Default for HashingScheme {
+    fn default() -> Self {
+        HashingScheme::LinearProbing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        probe_bitmap_len, CountGranularity, CpuHashJoinBuilder, CudaHashJoin, CudaHashJoinBuilder,
+        HashTable, HashingScheme, HtEntry, JoinMatch,
+    };
+    use crate::error::ErrorKind;
+    use datagen::relation::{KeyAttribute, UniformRelation};
+    use num_traits::cast::{AsPrimitive, FromPrimitive};
+    use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+    use numa_gpu::runtime::cuda_wrapper;
+    use numa_gpu::runtime::l2_persistence;
+    use numa_gpu::runtime::memory::{LaunchableMem, Mem};
+    use numa_gpu::runtime::numa::PageType;
+    use once_cell::sync::Lazy;
+    use rustacuda::context::{Context, CurrentContext, UnownedContext};
+    use rustacuda::device::DeviceAttribute;
+    use rustacuda::memory::DeviceBuffer;
+    use rustacuda::stream::{Stream, StreamFlags};
+    use std::convert::TryInto;
+    use std::error::Error;
+    use std::result::Result;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    static mut CUDA_CONTEXT_OWNER: Option<Context> = None;
+    static CUDA_CONTEXT: Lazy<UnownedContext> = Lazy::new(|| {
+        let context = rustacuda::quick_init().expect("Failed to initialize CUDA context");
+        let unowned = context.get_unowned();
+
+        unsafe {
+            CUDA_CONTEXT_OWNER = Some(context);
+        }
+
+        unowned
+    });
+
+    macro_rules! test_cpu_seq {
+        ($name:ident, $mem_type:expr, $scheme:expr, $is_selective:expr, $type:ty) => {
+            #[test]
+            fn $name() -> Result<(), Box<dyn Error>> {
+                const ROWS: usize = (32 << 20) / std::mem::size_of::<$type>();
+                const HT_LEN: usize = 2 * ROWS;
+                const EXCLUDE_KEY: $type = 1;
+
+                let alloc_fn = Allocator::deref_mem_alloc_fn::<$type>($mem_type);
+
+                let mut inner_rel_key = alloc_fn(ROWS);
+                let mut inner_rel_pay = alloc_fn(ROWS);
+                let mut outer_rel_key = alloc_fn(ROWS);
+                let mut outer_rel_pay = alloc_fn(ROWS);
+
+                UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+                UniformRelation::gen_foreign_key_from_primary_key(
+                    &mut outer_rel_key,
+                    &inner_rel_key,
+                );
+
+                inner_rel_pay
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, x)| *x = (i + 1) as $type);
+                outer_rel_pay
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, x)| *x = (i + 1) as $type);
+
+                let exclude_sum = if $is_selective {
+                    let pos = inner_rel_key
+                        .iter_mut()
+                        .find(|&&mut key| key == EXCLUDE_KEY);
+
+                    if let Some(key) = pos {
+                        *key = <$type>::null_key();
+                    } else {
+                        panic!("Failed to find the excluded key! The test is buggy.");
+                    }
+
+                    outer_rel_key
+                        .iter()
+                        .zip(outer_rel_pay.iter())
+                        .filter(|(&key, _)| key == EXCLUDE_KEY)
+                        .map(|(_, &pay)| pay)
+                        .sum()
+                } else {
+                    0
+                };
+
+                let ht_mem = Allocator::alloc_deref_mem($mem_type, HT_LEN);
+
+                let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+                let mut hj_op = CpuHashJoinBuilder::default()
+                    .hashing_scheme($scheme)
+                    .hash_table(Arc::new(hash_table))
+                    .is_selective($is_selective)
+                    .build();
+
+                hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+                let mut result_sum: u64 = 0;
+                hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+
+                let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2 - exclude_sum as u64;
+
+                assert_eq!(expected_sum, result_sum);
+
+                Ok(())
+            }
+        };
+    }
+
+    test_cpu_seq!(
+        cpu_seq_sysmem_perfect_i32,
+        DerefMemType::SysMem,
+        HashingScheme::Perfect,
+        false,
+        i32
+    );
+    test_cpu_seq!(
+        cpu_seq_sysmem_perfect_selective_i32,
+        DerefMemType::SysMem,
+        HashingScheme::Perfect,
+        true,
+        i32
+    );
+    test_cpu_seq!(
+        cpu_seq_sysmem_perfect_i64,
+        DerefMemType::SysMem,
+        HashingScheme::Perfect,
+        false,
+        i64
+    );
+    test_cpu_seq!(
+        cpu_seq_sysmem_perfect_selective_i64,
+        DerefMemType::SysMem,
+        HashingScheme::Perfect,
+        true,
+        i64
+    );
+    test_cpu_seq!(
+        cpu_seq_sysmem_linearprobing_i32,
+        DerefMemType::SysMem,
+        HashingScheme::LinearProbing,
+        false,
+        i32
+    );
+    test_cpu_seq!(
+        cpu_seq_sysmem_linearprobing_i64,
+        DerefMemType::SysMem,
+        HashingScheme::LinearProbing,
+        false,
+        i64
+    );
+
+    macro_rules! test_cpu_cuckoo_eviction_limit {
+        ($fallback_name:ident, $error_name:ident, $type:ty, $findkey_fn:ident) => {
+            #[test]
+            fn $fallback_name() -> Result<(), Box<dyn Error>> {
+                // Tiny hash tables at the 50% maximum load factor exceed the
+                // eviction limit with the first seed for some of the key sets
+                const HT_LEN: usize = 16;
+                const ROWS: usize = HT_LEN / 2;
+                const MAX_KEY_SETS: usize = 1024;
+
+                // Scrambles the keys with SplitMix64, because consecutive keys
+                // spread evenly over the sub-tables and rarely collide
+                let scramble = |x: u64| {
+                    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    z ^ (z >> 31)
+                };
+
+                for key_set in 0..MAX_KEY_SETS {
+                    // Keep 31 bits, so that the keys are positive for all types
+                    let inner_rel_key: Vec<$type> = (0..ROWS)
+                        .map(|i| (scramble((key_set * ROWS + i) as u64) >> 33) as $type)
+                        .collect();
+                    let inner_rel_pay: Vec<$type> = (1..=ROWS).map(|i| i as $type).collect();
+
+                    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+                    let hash_table = Arc::new(HashTable::new_on_cpu(ht_mem, HT_LEN)?);
+
+                    let mut hj_op = CpuHashJoinBuilder::default()
+                        .hashing_scheme(HashingScheme::Cuckoo)
+                        .hash_table(hash_table.clone())
+                        .build();
+
+                    // Skip key sets that fail with all seeds, e.g., due to a
+                    // duplicate key, or that succeed with the first seed, as
+                    // neither falls back to a rebuild
+                    if hj_op.build(&inner_rel_key, &inner_rel_pay).is_err() {
+                        continue;
+                    }
+                    let seed = hash_table.cuckoo_seed.load(Ordering::SeqCst);
+                    if seed == 0 {
+                        continue;
+                    }
+
+                    // The rebuild with the new seed must contain all keys
+                    for &key in inner_rel_key.iter() {
+                        let mut slot_reads: u32 = 0;
+                        let is_found = unsafe {
+                            super::$findkey_fn(
+                                hash_table.mem.as_ptr(),
+                                HT_LEN as u64,
+                                seed,
+                                key,
+                                &mut slot_reads,
+                            )
+                        };
+
+                        assert!(
+                            is_found,
+                            "Key {} is missing after the rebuild with seed {}",
+                            key, seed
+                        );
+                    }
+
+                    let mut result_sum: u64 = 0;
+                    hj_op.probe_sum(&inner_rel_key, &inner_rel_pay, &mut result_sum)?;
+
+                    let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2;
+                    assert_eq!(expected_sum, result_sum);
+
+                    return Ok(());
+                }
+
+                panic!(
+                    "None of the {} key sets exceeded the eviction limit with the first seed",
+                    MAX_KEY_SETS
+                );
+            }
+
+            #[test]
+            fn $error_name() -> Result<(), Box<dyn Error>> {
+                // A key has only two candidate slots. Thus, the third copy of
+                // a key exceeds the eviction limit with every seed.
+                const HT_LEN: usize = 1 << 10;
+
+                let inner_rel_key = vec![<$type>::from(1_u8); 3];
+                let inner_rel_pay = vec![<$type>::from(1_u8); 3];
+
+                let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+                let hash_table = Arc::new(HashTable::new_on_cpu(ht_mem, HT_LEN)?);
+
+                let mut hj_op = CpuHashJoinBuilder::default()
+                    .hashing_scheme(HashingScheme::Cuckoo)
+                    .hash_table(hash_table)
+                    .build();
+
+                match hj_op.build(&inner_rel_key, &inner_rel_pay) {
+                    Err(ref e) => match e.kind() {
+                        ErrorKind::RuntimeError(_) => {}
+                        _ => panic!("Expected a RuntimeError, but got: {}", e),
+                    },
+                    Ok(_) => panic!("Expected an error for exceeding the eviction limit"),
+                }
+
+                Ok(())
+            }
+        };
+    }
+
+    test_cpu_cuckoo_eviction_limit!(
+        cpu_seq_sysmem_cuckoo_rebuild_fallback_i32,
+        cpu_seq_sysmem_cuckoo_eviction_limit_error_i32,
+        i32,
+        cpu_ht_findkey_cuckoo_int32
+    );
+    test_cpu_cuckoo_eviction_limit!(
+        cpu_seq_sysmem_cuckoo_rebuild_fallback_i64,
+        cpu_seq_sysmem_cuckoo_eviction_limit_error_i64,
+        i64,
+        cpu_ht_findkey_cuckoo_int64
+    );
+
+    #[test]
+    fn cpu_cuckoo_rejects_second_build() -> Result<(), Box<dyn Error>> {
+        const HT_LEN: usize = 1 << 10;
+        const ROWS: usize = HT_LEN / 4;
+
+        let mut inner_rel_key = Allocator::alloc_deref_mem::<i64>(DerefMemType::SysMem, ROWS);
+        let inner_rel_pay = Allocator::alloc_deref_mem::<i64>(DerefMemType::SysMem, ROWS);
+        UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+
+        let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+        let hash_table = Arc::new(HashTable::new_on_cpu(ht_mem, HT_LEN)?);
+
+        let hj_builder = CpuHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::Cuckoo)
+            .hash_table(hash_table);
+
+        let (first_key, second_key) = inner_rel_key.split_at(ROWS / 2);
+        let (first_pay, second_pay) = inner_rel_pay.split_at(ROWS / 2);
+
+        hj_builder.build().build(first_key, first_pay)?;
+        assert!(hj_builder.build().build(second_key, second_pay).is_err());
+
+        Ok(())
+    }
+
+    /// Generates relations with narrow join keys, and returns the expected
+    /// join result.
+    ///
+    /// The inner relation contains each non-`NULL` key of the domain once. If
+    /// `exclude_key` is set, that key is replaced by `NULL`. The outer relation
+    /// cycles through the whole domain, including the `NULL` key.
+    fn gen_narrow_relations<T>(
+        outer_len: usize,
+        exclude_key: Option<T>,
+    ) -> Result<(Vec<T>, Vec<T>, Vec<T>, Vec<T>, u64), Box<dyn Error>>
+    where
+        T: Copy + PartialEq + AsPrimitive<usize> + AsPrimitive<u64> + FromPrimitive + KeyAttribute,
+    {
+        let null_key = T::null_key();
+        let domain_len = AsPrimitive::<usize>::as_(null_key) + 1;
+
+        let inner_rel_key = (0..domain_len - 1)
+            .map(|k| {
+                let key = T::try_from_usize(k)?;
+                Ok(if Some(key) == exclude_key {
+                    null_key
+                } else {
+                    key
+                })
+            })
+            .collect::<Result<Vec<T>, datagen::error::Error>>()?;
+        let inner_rel_pay = inner_rel_key
+            .iter()
+            .map(|_| T::from_u8(1).unwrap())
+            .collect();
+
+        let outer_rel_key: Vec<T> = (0..outer_len)
+            .map(|i| T::from_usize(i % domain_len).unwrap())
+            .collect();
+        let outer_rel_pay: Vec<T> = (0..outer_len)
+            .map(|i| T::from_usize(i % 5 + 1).unwrap())
+            .collect();
+
+        let expected_sum = outer_rel_key
+            .iter()
+            .zip(outer_rel_pay.iter())
+            .filter(|&(&key, _)| key != null_key && Some(key) != exclude_key)
+            .map(|(_, &pay)| AsPrimitive::<u64>::as_(pay))
+            .sum();
+
+        Ok((
+            inner_rel_key,
+            inner_rel_pay,
+            outer_rel_key,
+            outer_rel_pay,
+            expected_sum,
+        ))
+    }
+
+    macro_rules! test_cpu_narrow {
+        ($name:ident, $is_selective:expr, $type:ty) => {
+            #[test]
+            fn $name() -> Result<(), Box<dyn Error>> {
+                const OUTER_ROWS: usize = 1 << 20;
+                const EXCLUDE_KEY: $type = 1;
+
+                let exclude_key = if $is_selective {
+                    Some(EXCLUDE_KEY)
+                } else {
+                    None
+                };
+                let (inner_rel_key, inner_rel_pay, outer_rel_key, outer_rel_pay, expected_sum) =
+                    gen_narrow_relations::<$type>(OUTER_ROWS, exclude_key)?;
+
+                let ht_len = <$type>::null_key() as usize + 1;
+                let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, ht_len);
+                let hash_table = HashTable::new_on_cpu(ht_mem, ht_len)?;
+
+                let mut hj_op = CpuHashJoinBuilder::default()
+                    .hashing_scheme(HashingScheme::Perfect)
+                    .hash_table(Arc::new(hash_table))
+                    .is_selective($is_selective)
+                    .build();
+
+                hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+                let mut result_sum: u64 = 0;
+                hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+
+                assert_eq!(expected_sum, result_sum);
+
+                Ok(())
+            }
+        };
+    }
+
+    test_cpu_narrow!(cpu_seq_sysmem_perfect_u8, false, u8);
+    test_cpu_narrow!(cpu_seq_sysmem_perfect_selective_u8, true, u8);
+    test_cpu_narrow!(cpu_seq_sysmem_perfect_u16, false, u16);
+
+    #[test]
+    fn cpu_narrow_key_linearprobing_fails() -> Result<(), Box<dyn Error>> {
+        let (inner_rel_key, inner_rel_pay, _, _, _) = gen_narrow_relations::<u8>(0, None)?;
+
+        let ht_len = 2 * (u8::null_key() as usize + 1);
+        let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, ht_len);
+        let hash_table = HashTable::new_on_cpu(ht_mem, ht_len)?;
+
+        let mut hj_op = CpuHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .hash_table(Arc::new(hash_table))
+            .build();
+
+        assert!(hj_op.build(&inner_rel_key, &inner_rel_pay).is_err());
+
+        Ok(())
+    }
+
+    macro_rules! test_cuda {
+        ($name:ident, $mem_type:expr, $scheme:expr, $is_selective:expr, $type:ty) => {
+            #[test]
+            fn $name() -> Result<(), Box<dyn Error>> {
+                const GRID_SIZE: u32 = 16;
+                const BLOCK_SIZE: u32 = 1024;
+                const ROWS: usize = (32 << 20) / std::mem::size_of::<$type>();
+                const HT_LEN: usize = 2 * ROWS;
+                const EXCLUDE_KEY: $type = 1;
+
+                CurrentContext::set_current(&*CUDA_CONTEXT)?;
+                let alloc_fn = Allocator::deref_mem_alloc_fn::<$type>($mem_type);
+
+                let mut inner_rel_key = alloc_fn(ROWS);
+                let mut inner_rel_pay = alloc_fn(ROWS);
+                let mut outer_rel_key = alloc_fn(ROWS);
+                let mut outer_rel_pay = alloc_fn(ROWS);
+
+                UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+                UniformRelation::gen_foreign_key_from_primary_key(
+                    &mut outer_rel_key,
+                    &inner_rel_key,
+                );
+
+                inner_rel_pay
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, x)| *x = (i + 1) as $type);
+                outer_rel_pay
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, x)| *x = (i + 1) as $type);
+
+                let exclude_sum = if $is_selective {
+                    let pos = inner_rel_key
+                        .iter_mut()
+                        .find(|&&mut key| key == EXCLUDE_KEY);
+
+                    if let Some(key) = pos {
+                        *key = <$type>::null_key();
+                    } else {
+                        panic!("Failed to find the excluded key! The test is buggy.");
+                    }
+
+                    outer_rel_key
+                        .iter()
+                        .zip(outer_rel_pay.iter())
+                        .filter(|(&key, _)| key == EXCLUDE_KEY)
+                        .map(|(_, &pay)| pay)
+                        .sum()
+                } else {
+                    0
+                };
+
+                let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+                let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
+
+                let mut result_sum_per_thread =
+                    Allocator::alloc_mem(MemType::CudaUniMem, (GRID_SIZE * BLOCK_SIZE) as usize);
+
+                let hj_op = CudaHashJoinBuilder::default()
+                    .hashing_scheme($scheme)
+                    .hash_table(Arc::new(hash_table))
+                    .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+                    .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+                    .is_selective($is_selective)
+                    .build()?;
+
+                let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+                hj_op.build(
+                    Mem::from(inner_rel_key).as_launchable_slice(),
+                    Mem::from(inner_rel_pay).as_launchable_slice(),
+                    &stream,
+                )?;
+                hj_op.probe_sum(
+                    Mem::from(outer_rel_key).as_launchable_slice(),
+                    Mem::from(outer_rel_pay).as_launchable_slice(),
+                    &mut result_sum_per_thread,
+                    &stream,
+                )?;
+                stream.synchronize()?;
+
+                let result_sum_slice: &[u64] = (&result_sum_per_thread)
+                    .try_into()
+                    .map_err(|(err, _)| err)?;
+                let result_sum = result_sum_slice.iter().sum();
+
+                let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2 - exclude_sum as u64;
+
+                assert_eq!(expected_sum, result_sum);
+
+                Ok(())
+            }
+        };
+    }
+
+    test_cuda!(
+        cuda_pinnedmem_perfect_i32,
+        DerefMemType::CudaPinnedMem,
+        HashingScheme::Perfect,
+        false,
+        i32
+    );
+    test_cuda!(
+        cuda_pinnedmem_perfect_selective_i32,
+        DerefMemType::CudaPinnedMem,
+        HashingScheme::Perfect,
+        true,
+        i32
+    );
+    test_cuda!(
+        cuda_pinnedmem_perfect_i64,
+        DerefMemType::CudaPinnedMem,
+        HashingScheme::Perfect,
+        false,
+        i64
+    );
+    test_cuda!(
+        cuda_pinnedmem_perfect_selective_i64,
+        DerefMemType::CudaPinnedMem,
+        HashingScheme::Perfect,
+        true,
+        i64
+    );
+    test_cuda!(
+        cuda_pinnedmem_linearprobing_i32,
+        DerefMemType::CudaPinnedMem,
+        HashingScheme::LinearProbing,
+        false,
+        i32
+    );
+    test_cuda!(
+        cuda_pinnedmem_linearprobing_i64,
+        DerefMemType::CudaPinnedMem,
+        HashingScheme::LinearProbing,
+        false,
+        i64
+    );
+    test_cuda!(
+        cuda_unimem,
+        DerefMemType::CudaUniMem,
+        HashingScheme::Perfect,
+        false,
+        i32
+    );
+
+    macro_rules! test_cuda_mem_types {
+        ($name:ident, $hash_table_mem_type:expr, $relation_mem_type:expr) => {
+            #[test]
+            fn $name() -> Result<(), Box<dyn Error>> {
+                const GRID_SIZE: u32 = 16;
+                const BLOCK_SIZE: u32 = 1024;
+                const ROWS: usize = 1 << 20;
+                const HT_LEN: usize = 2 * ROWS;
+
+                CurrentContext::set_current(&*CUDA_CONTEXT)?;
+                let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>($relation_mem_type);
+
+                let mut inner_rel_key = alloc_fn(ROWS);
+                let mut inner_rel_pay = alloc_fn(ROWS);
+                let mut outer_rel_key = alloc_fn(ROWS);
+                let mut outer_rel_pay = alloc_fn(ROWS);
+
+                UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+                UniformRelation::gen_foreign_key_from_primary_key(
+                    &mut outer_rel_key,
+                    &inner_rel_key,
+                );
+
+                inner_rel_pay
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, x)| *x = (i + 1) as i32);
+                outer_rel_pay
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, x)| *x = (i + 1) as i32);
+
+                let ht_mem = Allocator::alloc_mem($hash_table_mem_type, HT_LEN);
+                let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
+
+                let mut result_sum_per_thread =
+                    Allocator::alloc_mem(MemType::CudaUniMem, (GRID_SIZE * BLOCK_SIZE) as usize);
 
-                    Ok(())
-                }
-            }
-        }
-    };
-}
+                let hj_op = CudaHashJoinBuilder::default()
+                    .hashing_scheme(HashingScheme::LinearProbing)
+                    .hash_table(Arc::new(hash_table))
+                    .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+                    .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+                    .build()?;
 
-impl_cpu_hash_join_for_type!(i32, int32);
-impl_cpu_hash_join_for_type!(i64, int64);
+                let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+                hj_op.build(
+                    Mem::from(inner_rel_key).as_launchable_slice(),
+                    Mem::from(inner_rel_pay).as_launchable_slice(),
+                    &stream,
+                )?;
+                hj_op.probe_sum(
+                    Mem::from(outer_rel_key).as_launchable_slice(),
+                    Mem::from(outer_rel_pay).as_launchable_slice(),
+                    &mut result_sum_per_thread,
+                    &stream,
+                )?;
+                stream.synchronize()?;
 
-impl<T: AsPrimitive<c_uint> + DeviceCopy + KeyAttribute> HashTable<T> {
-    /// Create a new CPU hash table.
-    ///
-    /// The hash table can be used on CPUs. In the case of NVLink 2.0 on POWER9,
-    /// it can also be used on GPUs.
-    pub fn new_on_cpu(mut mem: DerefMem<HtEntry<T, T>>, size: usize) -> Result<Self> {
-        if mem.len() < size {
-            Err(ErrorKind::InvalidArgument(
-                "Provided memory must be larger than hash table size".to_string(),
-            ))?;
-        }
+                let result_sum_slice: &[u64] = (&result_sum_per_thread)
+                    .try_into()
+                    .map_err(|(err, _)| err)?;
+                let result_sum: u64 = result_sum_slice.iter().sum();
 
-        mem.iter_mut().by_ref().for_each(|x| x.key = T::null_key());
+                let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2;
+                assert_eq!(expected_sum, result_sum);
 
-        Ok(Self {
-            mem: mem.into(),
-            size,
-        })
+                Ok(())
+            }
+        };
     }
 
-    /// Create a new GPU hash table.
-    ///
-    /// The hash table can be used on GPUs. It cannot always be used on CPUs,
-    /// due to the possibility of using GPU device memory. This also holds true
-    /// for NVLink 2.0 on POWER9.
-    pub fn new_on_gpu(mut mem: Mem<HtEntry<T, T>>, size: usize) -> Result<Self> {
-        if mem.len() < size {
-            Err(ErrorKind::InvalidArgument(
-                "Provided memory must be larger than hash table size".to_string(),
-            ))?;
+    // Hash table and relation memory types that every GPU can access
+    test_cuda_mem_types!(
+        cuda_mem_types_devmem_pinned,
+        MemType::CudaDevMem,
+        DerefMemType::CudaPinnedMem
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_devmem_unimem,
+        MemType::CudaDevMem,
+        DerefMemType::CudaUniMem
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_devmem_numapinned,
+        MemType::CudaDevMem,
+        DerefMemType::NumaPinnedMem {
+            node: 0,
+            page_type: PageType::Default
+        }
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_unimem_pinned,
+        MemType::CudaUniMem,
+        DerefMemType::CudaPinnedMem
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_unimem_unimem,
+        MemType::CudaUniMem,
+        DerefMemType::CudaUniMem
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_unimem_numapinned,
+        MemType::CudaUniMem,
+        DerefMemType::NumaPinnedMem {
+            node: 0,
+            page_type: PageType::Default
         }
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_pinned_pinned,
+        MemType::CudaPinnedMem,
+        DerefMemType::CudaPinnedMem
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_pinned_unimem,
+        MemType::CudaPinnedMem,
+        DerefMemType::CudaUniMem
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_numapinned_pinned,
+        MemType::NumaPinnedMem {
+            node: 0,
+            page_type: PageType::Default
+        },
+        DerefMemType::CudaPinnedMem
+    );
+    test_cuda_mem_types!(
+        cuda_mem_types_numapinned_unimem,
+        MemType::NumaPinnedMem {
+            node: 0,
+            page_type: PageType::Default
+        },
+        DerefMemType::CudaUniMem
+    );
 
-        let mem_ptr = mem.as_ptr();
-        let mem_len = mem.len();
+    // Pageable memory requires a cache-coherent interconnect
+    #[cfg(target_arch = "powerpc64")]
+    test_cuda_mem_types!(
+        cuda_mem_types_sysmem_sysmem,
+        MemType::SysMem,
+        DerefMemType::SysMem
+    );
+    #[cfg(target_arch = "powerpc64")]
+    test_cuda_mem_types!(
+        cuda_mem_types_devmem_numamem,
+        MemType::CudaDevMem,
+        DerefMemType::NumaMem {
+            node: 0,
+            page_type: PageType::Default
+        }
+    );
+    #[cfg(target_arch = "powerpc64")]
+    test_cuda_mem_types!(
+        cuda_mem_types_numamem_unimem,
+        MemType::NumaMem {
+            node: 0,
+            page_type: PageType::Default
+        },
+        DerefMemType::CudaUniMem
+    );
 
-        // Initialize hash table
-        match mem {
-            Mem::SysMem(ref mut mem) => mem.iter_mut().by_ref().for_each(|x| x.key = T::null_key()),
-            Mem::NumaMem(ref mut mem) => {
-                mem.iter_mut().by_ref().for_each(|x| x.key = T::null_key())
-            }
-            Mem::CudaPinnedMem(ref mut mem) => {
-                mem.iter_mut().by_ref().for_each(|x| x.key = T::null_key())
-            }
-            Mem::DistributedNumaMem(ref mut mem) => {
-                mem.iter_mut().by_ref().for_each(|x| x.key = T::null_key())
-            }
-            _ => {
-                unsafe {
-                    cuMemsetD32_v2(
-                        mem_ptr as *mut c_void as u64,
-                        T::null_key().as_(),
-                        mem_len
-                            .checked_mul(size_of::<HtEntry<T, T>>() / size_of::<c_uint>())
-                            .ok_or_else(|| {
-                                ErrorKind::IntegerOverflow(
-                                    "Failed to compute hash table bytes".to_string(),
-                                )
-                            })?,
-                    )
-                }
-                .to_result()?;
+    #[test]
+    fn cuda_probe_sum_async() -> Result<(), Box<dyn Error>> {
+        const GRID_SIZE: u32 = 16;
+        const BLOCK_SIZE: u32 = 1024;
+        const ROWS: usize = (32 << 20) / std::mem::size_of::<i32>();
+        const HT_LEN: usize = 2 * ROWS;
 
-                // FIXME: use cuMemsetD32Async on a user-given stream, and
-                // remove the context synchronization. Sync is required because
-                // cuMemsetD32_v2 is async if using device memory (see CUDA docs).
-                CurrentContext::synchronize()?;
-            }
-        }
+        CurrentContext::set_current(&*CUDA_CONTEXT)?;
+        let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaPinnedMem);
 
-        Ok(Self { mem, size })
-    }
+        let mut inner_rel_key = alloc_fn(ROWS);
+        let mut inner_rel_pay = alloc_fn(ROWS);
+        let mut outer_rel_key = alloc_fn(ROWS);
+        let mut outer_rel_pay = alloc_fn(ROWS);
 
-    /// Create a new hash table from another hash table.
-    ///
-    /// Copies the contents of the source hash table into the new hash table.
-    pub fn new_from_hash_table(mut mem: Mem<HtEntry<T, T>>, src: &Self) -> Result<Self> {
-        mem.copy_from_mem(&src.mem)?;
+        UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+        UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
 
-        Ok(Self {
-            mem,
-            size: src.size,
-        })
-    }
-}
+        inner_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i + 1) as i32);
+        outer_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i + 1) as i32);
 
-impl<T: DeviceCopy + KeyAttribute> MemLock for HashTable<T> {
-    fn mlock(&mut self) -> NumaGpuResult<()> {
-        self.mem.mlock()?;
+        let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+        let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
 
-        Ok(())
-    }
+        let hj_op = CudaHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .hash_table(Arc::new(hash_table))
+            .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+            .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+            .build()?;
 
-    fn munlock(&mut self) -> NumaGpuResult<()> {
-        self.mem.munlock()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let inner_rel_key = Mem::from(inner_rel_key);
+        let inner_rel_pay = Mem::from(inner_rel_pay);
+        let outer_rel_key = Mem::from(outer_rel_key);
+        let outer_rel_pay = Mem::from(outer_rel_pay);
+
+        hj_op.build(
+            inner_rel_key.as_launchable_slice(),
+            inner_rel_pay.as_launchable_slice(),
+            &stream,
+        )?;
+        let handle = hj_op.probe_sum_async(
+            outer_rel_key.as_launchable_slice(),
+            outer_rel_pay.as_launchable_slice(),
+            &stream,
+        )?;
+
+        // Compute the expected sum on the CPU while the GPU probes
+        let outer_rel_pay_slice: &[i32] = (&outer_rel_pay).try_into().map_err(|(err, _)| err)?;
+        let expected_sum: u64 = outer_rel_pay_slice.iter().map(|&pay| pay as u64).sum();
+        let _ = handle.poll()?;
+
+        let result_sum = handle.wait()?;
+        assert_eq!(expected_sum, result_sum);
 
         Ok(())
     }
-}
 
-impl<T: DeviceCopy + KeyAttribute> ::std::default::Default for CudaHashJoinBuilder<T> {
-    fn default() -> Self {
-        // Pre-load the CUDA module to enable callers to compute the amount of
-        // free GPU memory after instatiating `CudaHashJoinBuilder`.
-        let _ = *crate::MODULE;
+    #[test]
+    fn cuda_device_buffer_hash_table() -> Result<(), Box<dyn Error>> {
+        const GRID_SIZE: u32 = 16;
+        const BLOCK_SIZE: u32 = 1024;
+        const ROWS: usize = (32 << 20) / std::mem::size_of::<i32>();
+        const HT_LEN: usize = 2 * ROWS;
 
-        Self {
-            hashing_scheme: HashingScheme::default(),
-            is_selective: false,
-            hash_table_i: None,
-            build_dim_i: (1.into(), 1.into()),
-            probe_dim_i: (1.into(), 1.into()),
-        }
-    }
-}
+        CurrentContext::set_current(&*CUDA_CONTEXT)?;
+        let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaPinnedMem);
 
-impl<T> CudaHashJoinBuilder<T>
-where
-    T: Clone + Default + DeviceCopy + KeyAttribute,
-{
-    const DEFAULT_HT_SIZE: usize = 1024;
+        let mut inner_rel_key = alloc_fn(ROWS);
+        let mut inner_rel_pay = alloc_fn(ROWS);
+        let mut outer_rel_key = alloc_fn(ROWS);
+        let mut outer_rel_pay = alloc_fn(ROWS);
 
-    pub fn hashing_scheme(mut self, hashing_scheme: HashingScheme) -> Self {
-        self.hashing_scheme = hashing_scheme;
-        self
-    }
+        UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+        UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
 
-    pub fn is_selective(mut self, is_selective: bool) -> Self {
-        self.is_selective = is_selective;
-        self
-    }
+        inner_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i + 1) as i32);
+        outer_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i + 1) as i32);
 
-    pub fn hash_table(mut self, ht: Arc<HashTable<T>>) -> Self {
-        self.hash_table_i = Some(ht);
-        self
-    }
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
 
-    pub fn build_dim(mut self, grid: GridSize, block: BlockSize) -> Self {
-        self.build_dim_i = (grid, block);
-        self
-    }
+        // Fill the buffer with garbage to check that the hash table initializes it
+        let mut ht_mem = unsafe { DeviceBuffer::uninitialized(HT_LEN)? };
+        cuda_wrapper::memset_async(ht_mem.as_launchable_mut_slice(), 0x5a5a_5a5a, &stream)?;
+        let hash_table = HashTable::<i32>::from_device_buffer(ht_mem, HT_LEN, &stream)?;
 
-    pub fn probe_dim(mut self, grid: GridSize, block: BlockSize) -> Self {
-        self.probe_dim_i = (grid, block);
-        self
-    }
+        let mut result_sum_per_thread =
+            Allocator::alloc_mem(MemType::CudaUniMem, (GRID_SIZE * BLOCK_SIZE) as usize);
 
-    pub fn build(&self) -> Result<CudaHashJoin<T>> {
-        if self.hash_table_i.is_none() {
-            Err(ErrorKind::InvalidArgument("Hash table not set".to_string()))?;
-        }
+        let hj_op = CudaHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .hash_table(Arc::new(hash_table))
+            .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+            .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+            .build()?;
 
-        let hash_table = if let Some(ht) = self.hash_table_i.clone() {
-            ht
-        } else {
-            Arc::new(HashTable {
-                mem: allocator::Allocator::alloc_mem::<HtEntry<T, T>>(
-                    allocator::MemType::CudaUniMem,
-                    Self::DEFAULT_HT_SIZE,
-                ),
-                size: Self::DEFAULT_HT_SIZE,
-            })
-        };
+        hj_op.build(
+            Mem::from(inner_rel_key).as_launchable_slice(),
+            Mem::from(inner_rel_pay).as_launchable_slice(),
+            &stream,
+        )?;
+        hj_op.probe_sum(
+            Mem::from(outer_rel_key).as_launchable_slice(),
+            Mem::from(outer_rel_pay).as_launchable_slice(),
+            &mut result_sum_per_thread,
+            &stream,
+        )?;
+        stream.synchronize()?;
 
-        Ok(CudaHashJoin {
-            hashing_scheme: self.hashing_scheme,
-            is_selective: self.is_selective,
-            hash_table,
-            build_dim: self.build_dim_i.clone(),
-            probe_dim: self.probe_dim_i.clone(),
-        })
+        let result_sum_slice: &[u64] = (&result_sum_per_thread)
+            .try_into()
+            .map_err(|(err, _)| err)?;
+        let result_sum: u64 = result_sum_slice.iter().sum();
+
+        let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2;
+        assert_eq!(expected_sum, result_sum);
+
+        Ok(())
     }
-}
 
-impl<T: DeviceCopy + KeyAttribute> ::std::default::Default for CpuHashJoinBuilder<T> {
-    fn default() -> Self {
-        Self {
-            hashing_scheme: HashingScheme::default(),
-            is_selective: false,
-            hash_table_i: None,
+    #[test]
+    fn cuda_probe_bitmap_marks_matches() -> Result<(), Box<dyn Error>> {
+        const GRID_SIZE: u32 = 16;
+        const BLOCK_SIZE: u32 = 256;
+        const INNER_ROWS: usize = 1 << 16;
+        const OUTER_ROWS: usize = 3 * INNER_ROWS + 7;
+        const HT_LEN: usize = 2 * INNER_ROWS;
+
+        CurrentContext::set_current(&*CUDA_CONTEXT)?;
+        let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaPinnedMem);
+
+        let mut inner_rel_key = alloc_fn(INNER_ROWS);
+        let mut inner_rel_pay = alloc_fn(INNER_ROWS);
+        let mut outer_rel_key = alloc_fn(OUTER_ROWS);
+
+        UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+        inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+
+        // Every third probe key is outside of the inner relation's key domain
+        outer_rel_key.iter_mut().enumerate().for_each(|(i, x)| {
+            *x = if i % 3 == 0 {
+                (INNER_ROWS + i % INNER_ROWS) as i32
+            } else {
+                (i % INNER_ROWS) as i32
+            }
+        });
+        let expected: Vec<bool> = outer_rel_key
+            .iter()
+            .map(|&key| (key as usize) < INNER_ROWS)
+            .collect();
+        let inner_rel_key = Mem::from(inner_rel_key);
+        let inner_rel_pay = Mem::from(inner_rel_pay);
+        let outer_rel_key = Mem::from(outer_rel_key);
+
+        for &hashing_scheme in &[HashingScheme::Perfect, HashingScheme::LinearProbing] {
+            let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+            let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
+
+            let hj_op = CudaHashJoinBuilder::default()
+                .hashing_scheme(hashing_scheme)
+                .hash_table(Arc::new(hash_table))
+                .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+                .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+                .build()?;
+
+            let mut bitmap =
+                Allocator::alloc_mem(MemType::CudaUniMem, probe_bitmap_len(OUTER_ROWS));
+            if let Mem::CudaUniMem(ref mut bitmap) = bitmap {
+                bitmap.iter_mut().for_each(|word| *word = 0);
+            }
+
+            let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+            hj_op.build(
+                inner_rel_key.as_launchable_slice(),
+                inner_rel_pay.as_launchable_slice(),
+                &stream,
+            )?;
+            hj_op.probe_bitmap(outer_rel_key.as_launchable_slice(), &bitmap, &stream)?;
+            stream.synchronize()?;
+
+            let bitmap: &[u64] = (&bitmap).try_into().map_err(|(err, _)| err)?;
+            for (i, &is_match) in expected.iter().enumerate() {
+                let is_set = bitmap[i / 64] & (1 << (i % 64)) != 0;
+                assert_eq!(
+                    is_match, is_set,
+                    "Wrong bit for probe tuple {} with {:?} hashing",
+                    i, hashing_scheme
+                );
+            }
+            assert_eq!(0, bitmap.last().unwrap() >> (OUTER_ROWS % 64));
         }
+
+        Ok(())
     }
-}
 
-impl<T: Clone + Default + DeviceCopy + KeyAttribute> CpuHashJoinBuilder<T> {
-    const DEFAULT_HT_SIZE: usize = 1024;
+    #[test]
+    fn cuda_stable_materialize_matches_cpu() -> Result<(), Box<dyn Error>> {
+        const GRID_SIZE: u32 = 16;
+        const BLOCK_SIZE: u32 = 256;
+        const INNER_ROWS: usize = 1 << 16;
+        const OUTER_ROWS: usize = 4 * INNER_ROWS + 3;
+        const HT_LEN: usize = 2 * INNER_ROWS;
 
-    pub fn hashing_scheme(mut self, hashing_scheme: HashingScheme) -> Self {
-        self.hashing_scheme = hashing_scheme;
-        self
-    }
+        CurrentContext::set_current(&*CUDA_CONTEXT)?;
+        let alloc_fn = Allocator::deref_mem_alloc_fn::<i64>(DerefMemType::CudaPinnedMem);
 
-    pub fn is_selective(mut self, is_selective: bool) -> Self {
-        self.is_selective = is_selective;
-        self
-    }
+        let mut inner_rel_key = alloc_fn(INNER_ROWS);
+        let mut inner_rel_pay = alloc_fn(INNER_ROWS);
+        let mut outer_rel_key = alloc_fn(OUTER_ROWS);
+        let mut outer_rel_pay = alloc_fn(OUTER_ROWS);
 
-    pub fn hash_table(mut self, hash_table: Arc<HashTable<T>>) -> Self {
-        self.hash_table_i = Some(hash_table);
-        self
-    }
+        UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+        inner_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i + 1) as i64);
 
-    pub fn build(&self) -> CpuHashJoin<T> {
-        let hash_table = match &self.hash_table_i {
-            Some(ht) => ht.clone(),
-            None => Arc::new(HashTable {
-                mem: allocator::Allocator::alloc_mem::<HtEntry<T, T>>(
-                    allocator::MemType::SysMem,
-                    Self::DEFAULT_HT_SIZE,
-                ),
-                size: Self::DEFAULT_HT_SIZE,
-            }),
-        };
+        // Every fourth probe key has no match
+        outer_rel_key.iter_mut().enumerate().for_each(|(i, x)| {
+            *x = if i % 4 == 0 {
+                (INNER_ROWS + i) as i64
+            } else {
+                ((i * 7) % INNER_ROWS) as i64
+            }
+        });
+        outer_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = i as i64);
 
-        CpuHashJoin {
-            hashing_scheme: self.hashing_scheme,
-            is_selective: self.is_selective,
-            hash_table,
-        }
+        // CPU reference
+        let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+        let mut cpu_hj_op = CpuHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .hash_table(Arc::new(HashTable::new_on_cpu(ht_mem, HT_LEN)?))
+            .build();
+        cpu_hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+
+        let mut cpu_result = vec![JoinMatch::default(); OUTER_ROWS];
+        let cpu_matches =
+            cpu_hj_op.probe_materialize(&outer_rel_key, &outer_rel_pay, &mut cpu_result)?;
+        assert_eq!(OUTER_ROWS - (OUTER_ROWS + 3) / 4, cpu_matches);
+
+        // GPU
+        let inner_rel_key = Mem::from(inner_rel_key);
+        let inner_rel_pay = Mem::from(inner_rel_pay);
+        let outer_rel_key = Mem::from(outer_rel_key);
+        let outer_rel_pay = Mem::from(outer_rel_pay);
+
+        let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+        let gpu_hj_op = CudaHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .hash_table(Arc::new(HashTable::new_on_gpu(ht_mem, HT_LEN)?))
+            .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+            .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+            .stable_output(true)
+            .build()?;
+        let gpu_result = Allocator::alloc_mem(MemType::CudaUniMem, OUTER_ROWS);
+
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        gpu_hj_op.build(
+            inner_rel_key.as_launchable_slice(),
+            inner_rel_pay.as_launchable_slice(),
+            &stream,
+        )?;
+        let gpu_matches = gpu_hj_op.probe_materialize(
+            outer_rel_key.as_launchable_slice(),
+            outer_rel_pay.as_launchable_slice(),
+            &gpu_result,
+            &stream,
+        )?;
+
+        assert_eq!(cpu_matches, gpu_matches);
+
+        let gpu_result: &[JoinMatch<i64>] = (&gpu_result).try_into().map_err(|(err, _)| err)?;
+        assert_eq!(cpu_result[..cpu_matches], gpu_result[..gpu_matches]);
+
+        Ok(())
     }
-}
 
-impl<T> ::std::fmt::Display for HashTable<T>
-where
-    T: DeviceCopy + ::std::fmt::Display + KeyAttribute,
-{
-    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-        if let CudaDevMem(_) = self.mem {
-            write!(f, "[Cannot print device memory]")?;
-        } else {
-            write!(f, "[")?;
-            match self.mem {
-                SysMem(ref m) => m.as_slice(),
-                CudaUniMem(ref m) => m.as_slice(),
-                _ => &[],
+    #[test]
+    fn cuda_max_occupancy_dim() -> Result<(), Box<dyn Error>> {
+        CurrentContext::set_current(&*CUDA_CONTEXT)?;
+        let device = CurrentContext::get_device()?;
+        let warp_size = device.get_attribute(DeviceAttribute::WarpSize)? as u32;
+        let max_block_size = device.get_attribute(DeviceAttribute::MaxThreadsPerBlock)? as u32;
+        let multiprocessors = device.get_attribute(DeviceAttribute::MultiprocessorCount)? as u32;
+
+        for &hashing_scheme in &[HashingScheme::Perfect, HashingScheme::LinearProbing] {
+            let dims = [
+                CudaHashJoin::<i64>::max_occupancy_build_dim(hashing_scheme, &device)?,
+                CudaHashJoin::<i64>::max_occupancy_probe_dim(hashing_scheme, &device)?,
+            ];
+
+            for (grid_size, block_size) in dims.iter() {
+                assert_eq!(block_size.x % warp_size, 0);
+                assert!(0 < block_size.x && block_size.x <= max_block_size);
+                assert_eq!(grid_size.x % multiprocessors, 0);
+                assert!(grid_size.x > 0);
             }
-            .iter()
-            .take(self.size)
-            .map(|entry| write!(f, "{}:{},", entry.key, entry.value))
-            .collect::<::std::fmt::Result>()?;
-            write!(f, "]")?;
         }
 
+        assert!(
+            CudaHashJoin::<u8>::max_occupancy_build_dim(HashingScheme::LinearProbing, &device)
+                .is_err()
+        );
+        assert!(
+            CudaHashJoin::<u8>::max_occupancy_probe_dim(HashingScheme::LinearProbing, &device)
+                .is_err()
+        );
+
         Ok(())
     }
-}
 
-impl ::std::default::Default for HashingScheme {
-    fn default() -> Self {
-        HashingScheme::LinearProbing
-    }
-}
+    #[test]
+    fn cuda_probe_sum_count_granularities() -> Result<(), Box<dyn Error>> {
+        const GRID_SIZE: u32 = 16;
+        const BLOCK_SIZE: u32 = 1024;
+        const ROWS: usize = (32 << 20) / std::mem::size_of::<i32>();
+        const HT_LEN: usize = 2 * ROWS;
 
-#[cfg(test)]
-mod tests {
-    use super::{CpuHashJoinBuilder, CudaHashJoinBuilder, HashTable, HashingScheme};
-    use datagen::relation::{KeyAttribute, UniformRelation};
-    use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
-    use numa_gpu::runtime::memory::Mem;
-    use once_cell::sync::Lazy;
-    use rustacuda::context::{Context, CurrentContext, UnownedContext};
-    use rustacuda::stream::{Stream, StreamFlags};
-    use std::convert::TryInto;
-    use std::error::Error;
-    use std::result::Result;
-    use std::sync::Arc;
+        CurrentContext::set_current(&*CUDA_CONTEXT)?;
+        let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaPinnedMem);
 
-    static mut CUDA_CONTEXT_OWNER: Option<Context> = None;
-    static CUDA_CONTEXT: Lazy<UnownedContext> = Lazy::new(|| {
-        let context = rustacuda::quick_init().expect("Failed to initialize CUDA context");
-        let unowned = context.get_unowned();
+        let mut inner_rel_key = alloc_fn(ROWS);
+        let mut inner_rel_pay = alloc_fn(ROWS);
+        let mut outer_rel_key = alloc_fn(ROWS);
+        let mut outer_rel_pay = alloc_fn(ROWS);
 
-        unsafe {
-            CUDA_CONTEXT_OWNER = Some(context);
-        }
+        UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+        UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
 
-        unowned
-    });
+        inner_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i + 1) as i32);
+        outer_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i + 1) as i32);
 
-    macro_rules! test_cpu_seq {
-        ($name:ident, $mem_type:expr, $scheme:expr, $is_selective:expr, $type:ty) => {
-            #[test]
-            fn $name() -> Result<(), Box<dyn Error>> {
-                const ROWS: usize = (32 << 20) / std::mem::size_of::<$type>();
-                const HT_LEN: usize = 2 * ROWS;
-                const EXCLUDE_KEY: $type = 1;
+        let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+        let hash_table = Arc::new(HashTable::new_on_gpu(ht_mem, HT_LEN)?);
 
-                let alloc_fn = Allocator::deref_mem_alloc_fn::<$type>($mem_type);
+        let hj_builder = CudaHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .hash_table(hash_table)
+            .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+            .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into());
 
-                let mut inner_rel_key = alloc_fn(ROWS);
-                let mut inner_rel_pay = alloc_fn(ROWS);
-                let mut outer_rel_key = alloc_fn(ROWS);
-                let mut outer_rel_pay = alloc_fn(ROWS);
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let inner_rel_key = Mem::from(inner_rel_key);
+        let inner_rel_pay = Mem::from(inner_rel_pay);
+        let outer_rel_key = Mem::from(outer_rel_key);
+        let outer_rel_pay = Mem::from(outer_rel_pay);
 
-                UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
-                UniformRelation::gen_foreign_key_from_primary_key(
-                    &mut outer_rel_key,
-                    &inner_rel_key,
-                );
+        hj_builder.build()?.build(
+            inner_rel_key.as_launchable_slice(),
+            inner_rel_pay.as_launchable_slice(),
+            &stream,
+        )?;
 
-                inner_rel_pay
-                    .iter_mut()
-                    .enumerate()
-                    .for_each(|(i, x)| *x = (i + 1) as $type);
-                outer_rel_pay
-                    .iter_mut()
-                    .enumerate()
-                    .for_each(|(i, x)| *x = (i + 1) as $type);
+        let sums = [
+            CountGranularity::PerThread,
+            CountGranularity::PerBlock,
+            CountGranularity::Global,
+        ]
+        .iter()
+        .map(|&count_granularity| -> Result<u64, Box<dyn Error>> {
+            let hj_op = hj_builder
+                .clone()
+                .count_granularity(count_granularity)
+                .build()?;
 
-                let exclude_sum = if $is_selective {
-                    let pos = inner_rel_key
-                        .iter_mut()
-                        .find(|&&mut key| key == EXCLUDE_KEY);
+            let result_len = hj_op.result_set_len();
+            assert_eq!(
+                count_granularity.result_len(&GRID_SIZE.into(), &BLOCK_SIZE.into()),
+                result_len
+            );
 
-                    if let Some(key) = pos {
-                        *key = <$type>::null_key();
-                    } else {
-                        panic!("Failed to find the excluded key! The test is buggy.");
-                    }
+            let mut result_set =
+                Allocator::alloc_deref_mem::<u64>(DerefMemType::CudaPinnedMem, result_len);
+            result_set.iter_mut().for_each(|x| *x = 0);
+            let result_set = Mem::from(result_set);
 
-                    outer_rel_key
-                        .iter()
-                        .zip(outer_rel_pay.iter())
-                        .filter(|(&key, _)| key == EXCLUDE_KEY)
-                        .map(|(_, &pay)| pay)
-                        .sum()
-                } else {
-                    0
-                };
+            hj_op.probe_sum(
+                outer_rel_key.as_launchable_slice(),
+                outer_rel_pay.as_launchable_slice(),
+                &result_set,
+                &stream,
+            )?;
+            stream.synchronize()?;
 
-                let ht_mem = Allocator::alloc_deref_mem($mem_type, HT_LEN);
+            let result_slice: &[u64] = (&result_set).try_into().map_err(|(err, _)| err)?;
+            Ok(result_slice.iter().sum())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-                let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+        let outer_rel_pay_slice: &[i32] = (&outer_rel_pay).try_into().map_err(|(err, _)| err)?;
+        let expected_sum: u64 = outer_rel_pay_slice.iter().map(|&pay| pay as u64).sum();
 
-                let mut hj_op = CpuHashJoinBuilder::default()
-                    .hashing_scheme($scheme)
-                    .hash_table(Arc::new(hash_table))
-                    .is_selective($is_selective)
-                    .build();
+        assert!(sums.iter().all(|&sum| sum == expected_sum));
 
-                hj_op.build(&inner_rel_key, &inner_rel_pay)?;
-                let mut result_sum: u64 = 0;
-                hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+        Ok(())
+    }
 
-                let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2 - exclude_sum as u64;
+    #[test]
+    fn cuda_read_only_cache_probe_matches_default() -> Result<(), Box<dyn Error>> {
+        const GRID_SIZE: u32 = 16;
+        const BLOCK_SIZE: u32 = 1024;
+        const ROWS: usize = 1 << 20;
+        const HT_LEN: usize = 2 * ROWS;
 
-                assert_eq!(expected_sum, result_sum);
+        CurrentContext::set_current(&*CUDA_CONTEXT)?;
+        let alloc_fn = Allocator::deref_mem_alloc_fn::<i64>(DerefMemType::CudaPinnedMem);
 
-                Ok(())
-            }
-        };
+        let mut inner_rel_key = alloc_fn(ROWS);
+        let mut inner_rel_pay = alloc_fn(ROWS);
+        let mut outer_rel_key = alloc_fn(ROWS);
+        let mut outer_rel_pay = alloc_fn(ROWS);
+
+        UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+        UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+        inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+        outer_rel_pay
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i + 1) as i64);
+
+        let inner_rel_key = Mem::from(inner_rel_key);
+        let inner_rel_pay = Mem::from(inner_rel_pay);
+        let outer_rel_key = Mem::from(outer_rel_key);
+        let outer_rel_pay = Mem::from(outer_rel_pay);
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        for &hashing_scheme in &[HashingScheme::Perfect, HashingScheme::LinearProbing] {
+            let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+            let hj_builder = CudaHashJoinBuilder::default()
+                .hashing_scheme(hashing_scheme)
+                .hash_table(Arc::new(HashTable::new_on_gpu(ht_mem, HT_LEN)?))
+                .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+                .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into());
+
+            hj_builder.build()?.build(
+                inner_rel_key.as_launchable_slice(),
+                inner_rel_pay.as_launchable_slice(),
+                &stream,
+            )?;
+
+            let sums = [false, true]
+                .iter()
+                .map(|&read_only_cache| -> Result<u64, Box<dyn Error>> {
+                    let hj_op = hj_builder
+                        .clone()
+                        .read_only_cache(read_only_cache)
+                        .build()?;
+                    let sum = hj_op
+                        .probe_sum_async(
+                            outer_rel_key.as_launchable_slice(),
+                            outer_rel_pay.as_launchable_slice(),
+                            &stream,
+                        )?
+                        .wait()?;
+                    Ok(sum)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            assert_eq!(
+                sums[0], sums[1],
+                "Read-only probe differs with {:?} hashing",
+                hashing_scheme
+            );
+            assert_eq!((ROWS as u64 * (ROWS as u64 + 1)) / 2, sums[0]);
+        }
+
+        Ok(())
     }
 
-    test_cpu_seq!(
-        cpu_seq_sysmem_perfect_i32,
-        DerefMemType::SysMem,
-        HashingScheme::Perfect,
-        false,
-        i32
-    );
-    test_cpu_seq!(
-        cpu_seq_sysmem_perfect_selective_i32,
-        DerefMemType::SysMem,
-        HashingScheme::Perfect,
-        true,
-        i32
-    );
-    test_cpu_seq!(
-        cpu_seq_sysmem_perfect_i64,
-        DerefMemType::SysMem,
-        HashingScheme::Perfect,
-        false,
-        i64
-    );
-    test_cpu_seq!(
-        cpu_seq_sysmem_perfect_selective_i64,
-        DerefMemType::SysMem,
-        HashingScheme::Perfect,
-        true,
-        i64
-    );
-    test_cpu_seq!(
-        cpu_seq_sysmem_linearprobing_i32,
-        DerefMemType::SysMem,
-        HashingScheme::LinearProbing,
-        false,
-        i32
-    );
-    test_cpu_seq!(
-        cpu_seq_sysmem_linearprobing_i64,
-        DerefMemType::SysMem,
-        HashingScheme::LinearProbing,
-        false,
-        i64
-    );
+    #[test]
+    fn cuda_l2_persisting_probe_matches_default() -> Result<(), Box<dyn Error>> {
+        const GRID_SIZE: u32 = 16;
+        const BLOCK_SIZE: u32 = 1024;
+        const ROWS: usize = 1 << 16;
+        const HT_LEN: usize = 2 * ROWS;
 
-    macro_rules! test_cuda {
-        ($name:ident, $mem_type:expr, $scheme:expr, $is_selective:expr, $type:ty) => {
+        CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+        // Skip the test on GPUs without a set-aside L2 cache
+        if l2_persistence::max_persisting_l2_bytes()? == 0 {
+            return Ok(());
+        }
+
+        let alloc_fn = Allocator::deref_mem_alloc_fn::<i64>(DerefMemType::CudaPinnedMem);
+
+        let mut inner_rel_key = alloc_fn(ROWS);
+        let mut inner_rel_pay = alloc_fn(ROWS);
+        let mut outer_rel_key = alloc_fn(4 * ROWS);
+        let mut outer_rel_pay = alloc_fn(4 * ROWS);
+
+        UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+        UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+        inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+        outer_rel_pay.iter_mut().for_each(|x| *x = 1);
+
+        let inner_rel_key = Mem::from(inner_rel_key);
+        let inner_rel_pay = Mem::from(inner_rel_pay);
+        let outer_rel_key = Mem::from(outer_rel_key);
+        let outer_rel_pay = Mem::from(outer_rel_pay);
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+        let hash_table = Arc::new(HashTable::new_on_gpu(ht_mem, HT_LEN)?);
+        let hj_builder = CudaHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .hash_table(hash_table.clone())
+            .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
+            .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into());
+
+        hj_builder.build()?.build(
+            inner_rel_key.as_launchable_slice(),
+            inner_rel_pay.as_launchable_slice(),
+            &stream,
+        )?;
+
+        let sums = [false, true]
+            .iter()
+            .map(|&l2_persisting| -> Result<u64, Box<dyn Error>> {
+                let hj_op = hj_builder.clone().l2_persisting(l2_persisting).build()?;
+                let sum = hj_op
+                    .probe_sum_async(
+                        outer_rel_key.as_launchable_slice(),
+                        outer_rel_pay.as_launchable_slice(),
+                        &stream,
+                    )?
+                    .wait()?;
+                Ok(sum)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert!(l2_persistence::set_aside_l2_cache(
+            hash_table.mem.len() * std::mem::size_of::<HtEntry<i64, i64>>()
+        )?);
+        assert!(l2_persistence::set_access_policy_window(
+            &stream,
+            hash_table.mem.as_launchable_slice()
+        )?);
+        assert!(l2_persistence::reset_access_policy_window(&stream)?);
+        stream.synchronize()?;
+
+        assert_eq!(sums[0], sums[1]);
+        assert_eq!(4 * ROWS as u64, sums[0]);
+
+        Ok(())
+    }
+
+    macro_rules! test_cuda_narrow {
+        ($name:ident, $mem_type:expr, $is_selective:expr, $type:ty) => {
             #[test]
             fn $name() -> Result<(), Box<dyn Error>> {
                 const GRID_SIZE: u32 = 16;
                 const BLOCK_SIZE: u32 = 1024;
-                const ROWS: usize = (32 << 20) / std::mem::size_of::<$type>();
-                const HT_LEN: usize = 2 * ROWS;
+                const OUTER_ROWS: usize = 1 << 20;
                 const EXCLUDE_KEY: $type = 1;
 
                 CurrentContext::set_current(&*CUDA_CONTEXT)?;
-                let alloc_fn = Allocator::deref_mem_alloc_fn::<$type>($mem_type);
-
-                let mut inner_rel_key = alloc_fn(ROWS);
-                let mut inner_rel_pay = alloc_fn(ROWS);
-                let mut outer_rel_key = alloc_fn(ROWS);
-                let mut outer_rel_pay = alloc_fn(ROWS);
-
-                UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
-                UniformRelation::gen_foreign_key_from_primary_key(
-                    &mut outer_rel_key,
-                    &inner_rel_key,
-                );
-
-                inner_rel_pay
-                    .iter_mut()
-                    .enumerate()
-                    .for_each(|(i, x)| *x = (i + 1) as $type);
-                outer_rel_pay
-                    .iter_mut()
-                    .enumerate()
-                    .for_each(|(i, x)| *x = (i + 1) as $type);
-
-                let exclude_sum = if $is_selective {
-                    let pos = inner_rel_key
-                        .iter_mut()
-                        .find(|&&mut key| key == EXCLUDE_KEY);
-
-                    if let Some(key) = pos {
-                        *key = <$type>::null_key();
-                    } else {
-                        panic!("Failed to find the excluded key! The test is buggy.");
-                    }
 
-                    outer_rel_key
-                        .iter()
-                        .zip(outer_rel_pay.iter())
-                        .filter(|(&key, _)| key == EXCLUDE_KEY)
-                        .map(|(_, &pay)| pay)
-                        .sum()
+                let exclude_key = if $is_selective {
+                    Some(EXCLUDE_KEY)
                 } else {
-                    0
+                    None
                 };
+                let (inner_rel_key, inner_rel_pay, outer_rel_key, outer_rel_pay, expected_sum) =
+                    gen_narrow_relations::<$type>(OUTER_ROWS, exclude_key)?;
 
-                let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
-                let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
+                let ht_len = <$type>::null_key() as usize + 1;
+                let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, ht_len);
+                let hash_table = HashTable::new_on_gpu(ht_mem, ht_len)?;
+
+                let copy_to_mem = |data: &[$type]| {
+                    let mut mem = Allocator::alloc_deref_mem($mem_type, data.len());
+                    mem.copy_from_slice(data);
+                    Mem::from(mem)
+                };
 
                 let mut result_sum_per_thread =
                     Allocator::alloc_mem(MemType::CudaUniMem, (GRID_SIZE * BLOCK_SIZE) as usize);
 
                 let hj_op = CudaHashJoinBuilder::default()
-                    .hashing_scheme($scheme)
+                    .hashing_scheme(HashingScheme::Perfect)
                     .hash_table(Arc::new(hash_table))
                     .build_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
                     .probe_dim(GRID_SIZE.into(), BLOCK_SIZE.into())
@@ -1026,13 +5022,13 @@ mod tests {
 
                 let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
                 hj_op.build(
-                    Mem::from(inner_rel_key).as_launchable_slice(),
-                    Mem::from(inner_rel_pay).as_launchable_slice(),
+                    copy_to_mem(&inner_rel_key).as_launchable_slice(),
+                    copy_to_mem(&inner_rel_pay).as_launchable_slice(),
                     &stream,
                 )?;
                 hj_op.probe_sum(
-                    Mem::from(outer_rel_key).as_launchable_slice(),
-                    Mem::from(outer_rel_pay).as_launchable_slice(),
+                    copy_to_mem(&outer_rel_key).as_launchable_slice(),
+                    copy_to_mem(&outer_rel_pay).as_launchable_slice(),
                     &mut result_sum_per_thread,
                     &stream,
                 )?;
@@ -1041,9 +5037,7 @@ mod tests {
                 let result_sum_slice: &[u64] = (&result_sum_per_thread)
                     .try_into()
                     .map_err(|(err, _)| err)?;
-                let result_sum = result_sum_slice.iter().sum();
-
-                let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2 - exclude_sum as u64;
+                let result_sum: u64 = result_sum_slice.iter().sum();
 
                 assert_eq!(expected_sum, result_sum);
 
@@ -1052,53 +5046,22 @@ mod tests {
         };
     }
 
-    test_cuda!(
-        cuda_pinnedmem_perfect_i32,
-        DerefMemType::CudaPinnedMem,
-        HashingScheme::Perfect,
-        false,
-        i32
-    );
-    test_cuda!(
-        cuda_pinnedmem_perfect_selective_i32,
-        DerefMemType::CudaPinnedMem,
-        HashingScheme::Perfect,
-        true,
-        i32
-    );
-    test_cuda!(
-        cuda_pinnedmem_perfect_i64,
+    test_cuda_narrow!(
+        cuda_pinnedmem_perfect_u8,
         DerefMemType::CudaPinnedMem,
-        HashingScheme::Perfect,
         false,
-        i64
+        u8
     );
-    test_cuda!(
-        cuda_pinnedmem_perfect_selective_i64,
+    test_cuda_narrow!(
+        cuda_pinnedmem_perfect_selective_u8,
         DerefMemType::CudaPinnedMem,
-        HashingScheme::Perfect,
         true,
-        i64
-    );
-    test_cuda!(
-        cuda_pinnedmem_linearprobing_i32,
-        DerefMemType::CudaPinnedMem,
-        HashingScheme::LinearProbing,
-        false,
-        i32
+        u8
     );
-    test_cuda!(
-        cuda_pinnedmem_linearprobing_i64,
+    test_cuda_narrow!(
+        cuda_pinnedmem_perfect_u16,
         DerefMemType::CudaPinnedMem,
-        HashingScheme::LinearProbing,
         false,
-        i64
-    );
-    test_cuda!(
-        cuda_unimem,
-        DerefMemType::CudaUniMem,
-        HashingScheme::Perfect,
-        false,
-        i32
+        u16
     );
 }
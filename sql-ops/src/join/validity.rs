@@ -0,0 +1,158 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validity bitmaps for SQL `NULL` values.
+//!
+//! The hash tables mark empty slots with `KeyAttribute::null_key()`. This
+//! sentinel is an implementation detail of the hash table, and is distinct
+//! from a SQL `NULL`. In SQL, `NULL = NULL` evaluates to unknown, and thus a
+//! tuple with a `NULL` join key never matches any other tuple.
+//!
+//! However, a valid key equal to the sentinel cannot be stored in the hash
+//! table. Thus, `CpuHashJoin::build_nullable` rejects such a key with an
+//! `InvalidArgument` error.
+//!
+//! A `ValidityBitmap` stores one bit per tuple. A set bit means that the
+//! tuple's key is valid, and a cleared bit means that the key is SQL `NULL`.
+//! The value stored in the key attribute of a `NULL` tuple is ignored.
+//!
+//! ## Limitations
+//!
+//! Currently, only `CpuHashJoin` accepts validity bitmaps. It removes the
+//! `NULL` tuples before the build and probe, which costs an additional copy of
+//! the valid tuples.
+
+use crate::error::{ErrorKind, Result};
+use std::iter::FromIterator;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A bitmap that marks the valid, i.e., non-`NULL`, tuples of an attribute.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidityBitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl ValidityBitmap {
+    /// Creates a bitmap of `len` tuples that are all valid.
+    pub fn new_all_valid(len: usize) -> Self {
+        let mut words = vec![u64::MAX; (len + WORD_BITS - 1) / WORD_BITS];
+
+        // Keep the unused bits of the last word cleared
+        if len % WORD_BITS != 0 {
+            if let Some(last) = words.last_mut() {
+                *last = (1 << (len % WORD_BITS)) - 1;
+            }
+        }
+
+        Self { words, len }
+    }
+
+    /// Returns the number of tuples in the bitmap.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the bitmap contains no tuples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the tuple at `index` is valid.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn is_valid(&self, index: usize) -> bool {
+        assert!(index < self.len, "Index out of bounds");
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    /// Marks the tuple at `index` as valid or as `NULL`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_valid(&mut self, index: usize, is_valid: bool) {
+        assert!(index < self.len, "Index out of bounds");
+        let mask = 1 << (index % WORD_BITS);
+        if is_valid {
+            self.words[index / WORD_BITS] |= mask;
+        } else {
+            self.words[index / WORD_BITS] &= !mask;
+        }
+    }
+
+    /// Returns the number of `NULL` tuples.
+    pub fn null_count(&self) -> usize {
+        self.len
+            - self
+                .words
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum::<usize>()
+    }
+
+    /// Gathers the valid tuples of a key and a payload attribute.
+    ///
+    /// The order of the tuples is preserved.
+    pub fn filter_valid<K: Copy, V: Copy>(
+        &self,
+        join_attr: &[K],
+        payload_attr: &[V],
+    ) -> Result<(Vec<K>, Vec<V>)> {
+        if join_attr.len() != payload_attr.len() {
+            Err(ErrorKind::InvalidArgument(
+                "Join and payload attributes have different sizes".to_string(),
+            ))?;
+        }
+        if join_attr.len() != self.len {
+            Err(ErrorKind::InvalidArgument(
+                "Validity bitmap and join attribute have different sizes".to_string(),
+            ))?;
+        }
+
+        let valid_len = self.len - self.null_count();
+        let mut keys = Vec::with_capacity(valid_len);
+        let mut payloads = Vec::with_capacity(valid_len);
+
+        join_attr
+            .iter()
+            .zip(payload_attr.iter())
+            .enumerate()
+            .filter(|&(i, _)| self.is_valid(i))
+            .for_each(|(_, (&key, &payload))| {
+                keys.push(key);
+                payloads.push(payload);
+            });
+
+        Ok((keys, payloads))
+    }
+}
+
+impl FromIterator<bool> for ValidityBitmap {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut words = Vec::new();
+        let mut len = 0;
+
+        for is_valid in iter {
+            if len % WORD_BITS == 0 {
+                words.push(0);
+            }
+            if is_valid {
+                *words.last_mut().unwrap() |= 1 << (len % WORD_BITS);
+            }
+            len += 1;
+        }
+
+        Self { words, len }
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A CPU hash join with payloads that are wider than the join key.
+//!
+//! The hash tables in `no_partitioning_join` store the key and the payload
+//! with the same type. Real tables, however, often have narrow keys and wide
+//! payloads, e.g., a 4-byte key and a 60-byte payload. This join stores a
+//! payload of `N` bytes next to each key. Thus, the payload width can be
+//! varied independently of the key width to measure its effect on the build
+//! and probe bandwidth.
+//!
+//! The probe materializes the build-side payload of each match, in the same
+//! way as a primary key-foreign key join fetches the attributes of a dimension
+//! table.
+//!
+//! ## Limitations
+//!
+//! The hash table uses linear probing and requires unique build keys. The
+//! build is single-threaded, whereas the probe can run on multiple threads,
+//! because it only reads the hash table.
+
+use crate::error::{ErrorKind, Result};
+use datagen::relation::KeyAttribute;
+use num_traits::cast::AsPrimitive;
+
+/// Multiplier of the Fibonacci hash function.
+const HASH_FACTOR: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// A hash table entry with an `N`-byte payload.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct WideHtEntry<K, const N: usize> {
+    pub key: K,
+    pub payload: [u8; N],
+}
+
+/// A linear probing hash table with `N`-byte payloads.
+#[derive(Debug)]
+pub struct WidePayloadHashTable<K, const N: usize> {
+    entries: Vec<WideHtEntry<K, N>>,
+    log2_len: u32,
+}
+
+impl<K, const N: usize> WidePayloadHashTable<K, N>
+where
+    K: Copy + PartialEq + AsPrimitive<u64> + KeyAttribute,
+{
+    /// Creates an empty hash table with `len` entries.
+    ///
+    /// `len` must be a power of two.
+    pub fn new(len: usize) -> Result<Self> {
+        if !len.is_power_of_two() {
+            Err(ErrorKind::InvalidArgument(
+                "Hash table length must be a power of two".to_string(),
+            ))?;
+        }
+
+        let entries = vec![
+            WideHtEntry {
+                key: K::null_key(),
+                payload: [0; N],
+            };
+            len
+        ];
+
+        Ok(Self {
+            entries,
+            log2_len: len.trailing_zeros(),
+        })
+    }
+
+    /// Returns the number of entries in the hash table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the hash table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Inserts the keys and their payloads into the hash table.
+    pub fn build(&mut self, join_attr: &[K], payload_attr: &[[u8; N]]) -> Result<()> {
+        if join_attr.len() != payload_attr.len() {
+            Err(ErrorKind::InvalidArgument(
+                "Join and payload attributes have different sizes".to_string(),
+            ))?;
+        }
+
+        let mask = self.entries.len() - 1;
+        for (&key, payload) in join_attr.iter().zip(payload_attr.iter()) {
+            let mut index = self.hash(key);
+            let mut probes = 0;
+
+            while self.entries[index].key != K::null_key() {
+                probes += 1;
+                if probes == self.entries.len() {
+                    Err(ErrorKind::RuntimeError(
+                        "Hash table is too small for the build data".to_string(),
+                    ))?;
+                }
+                index = (index + 1) & mask;
+            }
+
+            self.entries[index] = WideHtEntry {
+                key,
+                payload: *payload,
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Probes the hash table and copies the payload of each match into the
+    /// join result.
+    ///
+    /// `join_result[i]` is overwritten with the build payload that matches
+    /// `join_attr[i]`. The entries of tuples without a match are left
+    /// unchanged.
+    ///
+    /// Returns the number of matches.
+    pub fn probe(&self, join_attr: &[K], join_result: &mut [[u8; N]]) -> Result<u64> {
+        if join_attr.len() != join_result.len() {
+            Err(ErrorKind::InvalidArgument(
+                "Join attribute and join result have different sizes".to_string(),
+            ))?;
+        }
+
+        let mask = self.entries.len() - 1;
+        let mut matches = 0;
+
+        for (&key, result) in join_attr.iter().zip(join_result.iter_mut()) {
+            let mut index = self.hash(key);
+
+            for _ in 0..self.entries.len() {
+                let entry = &self.entries[index];
+                if entry.key == key {
+                    *result = entry.payload;
+                    matches += 1;
+                    break;
+                } else if entry.key == K::null_key() {
+                    break;
+                }
+                index = (index + 1) & mask;
+            }
+        }
+
+        Ok(matches)
+    }
+
+    fn hash(&self, key: K) -> usize {
+        if self.log2_len == 0 {
+            0
+        } else {
+            (key.as_().wrapping_mul(HASH_FACTOR) >> (u64::BITS - self.log2_len)) as usize
+        }
+    }
+}
@@ -20,6 +20,8 @@
 //! - Hash join (no-partitioning and radix-partitioned)
 //! - Radix partition
 //! - Prefix scan (exclusive)
+//! - Sum reduction (deterministic)
+//! - Distinct count (approximate, HyperLogLog)
 //!
 //! # Tuning parameters
 //!
@@ -102,6 +104,10 @@
 //! performed when a GPU operator is executed for the first time. Thus, later
 //! executions of any GPU operator use the already-loaded module.
 //!
+//! CPU operators never load the module. Thus, CPU operators also run on
+//! machines without a GPU or without the CUDA driver. On such machines, GPU
+//! operators return a `CudaUnavailable` error instead of panicking.
+//!
 //! **Important:** The CUDA context must be initialized before calling the
 //! a GPU operator. *Destroying this context will also destroy the module!*
 //!
@@ -114,14 +120,19 @@
 //! [fatbin]: https://docs.nvidia.com/cuda/cuda-compiler-driver-nvcc/index.html#fatbinaries
 //! [cuModuleLoad]: https://docs.nvidia.com/cuda/archive/10.2/cuda-driver-api/group__CUDA__MODULE.html#group__CUDA__MODULE_1g366093bd269dafd0af21f1c7d18115d3
 
+pub mod cardinality;
 pub mod error;
 pub mod join;
 pub mod partition;
 pub mod prefix_scan;
+pub mod reduction;
 
-use once_cell::sync::Lazy;
+use crate::error::{ErrorKind, Result};
+use once_cell::sync::OnceCell;
+use rustacuda::error::CudaError;
+use rustacuda::function::Function;
 use rustacuda::module::Module;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 
 #[allow(dead_code)]
 pub(crate) mod constants {
@@ -133,10 +144,107 @@ pub use constants::CACHE_LINE_SIZE as CPU_CACHE_LINE_SIZE;
 pub use constants::GPU_CACHE_LINE_SIZE;
 
 static mut MODULE_OWNER: Option<Module> = None;
-static MODULE: Lazy<&'static Module> = Lazy::new(|| {
-    let module_path = CString::new(env!("CUDAUTILS_PATH"))
-        .expect("Failed to load CUDA module, check your CUDAUTILS_PATH");
-    let module = Module::load_from_file(&module_path).expect("Failed to load CUDA module");
+static MODULE: OnceCell<&'static Module> = OnceCell::new();
+
+/// Returns the CUDA module, and loads it on first use.
+///
+/// Returns a `CudaUnavailable` error if there is no CUDA device, or if no CUDA
+/// context is current. In this case, the load is retried on the next call.
+pub(crate) fn module() -> Result<&'static Module> {
+    MODULE
+        .get_or_try_init(|| {
+            let module_path = CString::new(env!("CUDAUTILS_PATH")).map_err(|_| {
+                ErrorKind::NulCharError(
+                    "Failed to load CUDA module, check your CUDAUTILS_PATH".to_string(),
+                )
+            })?;
+            let module = Module::load_from_file(&module_path).map_err(|error| match error {
+                CudaError::NotInitialized
+                | CudaError::Deinitialized
+                | CudaError::NoDevice
+                | CudaError::InvalidDevice
+                | CudaError::InvalidContext => {
+                    ErrorKind::CudaUnavailable(format!("Failed to load CUDA module: {}", error))
+                }
+                _ => ErrorKind::CudaError(error),
+            })?;
+
+            Ok(unsafe { &*MODULE_OWNER.get_or_insert(module) })
+        })
+        .map(|module| *module)
+}
+
+/// Returns `true` if the CUDA module contains a kernel named `name`.
+///
+/// Loads the module on first use. Returns `false` if the module cannot be
+/// loaded, e.g., because no CUDA context is current.
+pub fn module_has_function(name: &str) -> bool {
+    let name = match CString::new(name) {
+        Ok(name) => name,
+        Err(_) => return false,
+    };
+
+    module()
+        .map(|module| module.get_function(&name).is_ok())
+        .unwrap_or(false)
+}
+
+/// Returns the kernel `name` from the CUDA module.
+///
+/// In contrast to `Module::get_function`, a missing kernel results in an
+/// error that names the kernel. A kernel is missing if the module was built
+/// from a mismatched fatbinary.
+pub(crate) fn module_function(module: &'static Module, name: &CStr) -> Result<Function<'static>> {
+    module.get_function(name).map_err(|error| match error {
+        CudaError::NotFound => ErrorKind::RuntimeError(format!(
+            "Kernel {} missing from CUDA module",
+            name.to_string_lossy()
+        ))
+        .into(),
+        _ => error.into(),
+    })
+}
+
+/// Identifies a plain data type in the header of a saved file.
+///
+/// The name returned by `std::any::type_name` isn't stable across compiler
+/// versions. Thus, file headers record an explicit tag instead. The tags are
+/// part of the file formats, and must never change.
+pub trait FileTypeTag {
+    const FILE_TYPE_TAG: u32;
+}
+
+impl FileTypeTag for i32 {
+    const FILE_TYPE_TAG: u32 = 1;
+}
+
+impl FileTypeTag for i64 {
+    const FILE_TYPE_TAG: u32 = 2;
+}
 
-    unsafe { MODULE_OWNER.get_or_insert(module) }
-});
+impl FileTypeTag for u8 {
+    const FILE_TYPE_TAG: u32 = 3;
+}
+
+impl FileTypeTag for u16 {
+    const FILE_TYPE_TAG: u32 = 4;
+}
+
+/// Returns the size in bytes of an allocation of `len` elements of type `T`.
+///
+/// Allocations are limited to `isize::MAX` bytes, because pointer offsets are
+/// signed. Returns an `IntegerOverflow` error if the allocation exceeds this
+/// limit. `what` describes the allocation in the error message.
+pub(crate) fn checked_alloc_bytes<T>(len: usize, what: &str) -> Result<usize> {
+    len.checked_mul(std::mem::size_of::<T>())
+        .filter(|&bytes| bytes <= isize::MAX as usize)
+        .ok_or_else(|| {
+            ErrorKind::IntegerOverflow(format!(
+                "{} of {} elements with {} bytes each exceeds the maximum allocation size",
+                what,
+                len,
+                std::mem::size_of::<T>()
+            ))
+            .into()
+        })
+}
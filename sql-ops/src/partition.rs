@@ -15,19 +15,24 @@
 //! A collection of partitioning operators.
 
 use crate::error::{Error, ErrorKind};
+use crate::FileTypeTag;
+use num_traits::cast::AsPrimitive;
 use rustacuda::memory::DeviceCopy;
 use std::convert::TryFrom;
+use std::mem;
 
 pub mod cpu_radix_partition;
 pub mod gpu_radix_partition;
 mod partition_input_chunk;
 mod partitioned_relation;
+pub mod range_index;
+pub mod skew;
 
 // Export structs
 pub use partition_input_chunk::{RadixPartitionInputChunk, RadixPartitionInputChunkable};
 pub use partitioned_relation::{
     PartitionOffsets, PartitionOffsetsChunksMut, PartitionOffsetsMutSlice, PartitionedRelation,
-    PartitionedRelationChunksMut, PartitionedRelationMutSlice,
+    PartitionedRelationChunksMut, PartitionedRelationMutSlice, PartitionedRelationPartitions,
 };
 
 /// Histogram algorithm type
@@ -47,10 +52,26 @@ pub enum HistogramAlgorithmType {
 
 /// Compute the fanout (i.e., the number of partitions) from the number of radix
 /// bits.
-fn fanout(radix_bits: u32) -> u32 {
+pub fn fanout(radix_bits: u32) -> u32 {
     1 << radix_bits
 }
 
+/// Compute the mask that selects the radix bits of a key.
+///
+/// The mask covers the lowest `radix_bits` bits. Passes after the first pass
+/// shift the mask left by `RadixBits::pass_ignore_bits`.
+pub fn radix_mask(radix_bits: u32) -> usize {
+    fanout(radix_bits) as usize - 1
+}
+
+/// Compute the partition to which the partitioner assigns a key.
+///
+/// Matches the partitioning kernels, which cast the key to a 64-bit unsigned
+/// integer before masking it. Thus, negative keys are sign-extended.
+pub fn partition_of<T: AsPrimitive<u64>>(key: T, radix_bits: u32) -> usize {
+    (key.as_() & radix_mask(radix_bits) as u64) as usize
+}
+
 /// A radix pass
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum RadixPass {
@@ -139,6 +160,13 @@ impl TryFrom<&[u32]> for RadixBits {
 ///
 /// Note that the struct's layout must be kept in sync with its counterpart in
 /// C/C++.
+///
+/// `repr(C)` lays out the key and the value in order and pads them to their
+/// natural alignment, exactly like the C++ compiler does for `Tuple<K, V>`.
+/// Thus, a mixed-width tuple such as `Tuple<i32, i64>` has four bytes of
+/// padding after the key on both sides, and no `packed` representation is
+/// necessary. The assertions below check the sizes at compile time. The C++
+/// header `gpu_common.h` checks the same sizes.
 #[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub struct Tuple<Key: Sized, Value: Sized> {
@@ -146,9 +174,64 @@ pub struct Tuple<Key: Sized, Value: Sized> {
     pub value: Value,
 }
 
+const _: () = assert!(mem::size_of::<Tuple<i32, i32>>() == 8);
+const _: () = assert!(mem::size_of::<Tuple<i64, i64>>() == 16);
+const _: () = assert!(mem::size_of::<Tuple<u8, u8>>() == 2);
+const _: () = assert!(mem::size_of::<Tuple<u16, u16>>() == 4);
+const _: () = assert!(mem::size_of::<Tuple<i32, i64>>() == 16);
+const _: () = assert!(mem::size_of::<Tuple<i64, i32>>() == 16);
+
 unsafe impl<K, V> DeviceCopy for Tuple<K, V>
 where
     K: DeviceCopy,
     V: DeviceCopy,
 {
 }
+
+/// The tag of a tuple combines the tags of the key and the value type.
+impl<K, V> FileTypeTag for Tuple<K, V>
+where
+    K: FileTypeTag,
+    V: FileTypeTag,
+{
+    const FILE_TYPE_TAG: u32 = (K::FILE_TYPE_TAG << 16) | V::FILE_TYPE_TAG;
+}
+
+/// A fixed-size payload of `N` opaque bytes.
+///
+/// Wide payloads can't be expressed as a primitive type. The standard library
+/// implements `Default` only for arrays of up to 32 bytes, and rustacuda
+/// doesn't implement `DeviceCopy` for arrays. `Payload` implements both, and
+/// can thus be used as the value type of a `Tuple`, e.g., `Tuple<i64,
+/// Payload<64>>`.
+///
+/// The payload is an array of bytes, and thus has an alignment of one byte.
+/// In a `Tuple`, the payload follows the key without padding, and the tuple is
+/// padded to the key's alignment.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[repr(transparent)]
+pub struct Payload<const N: usize>(pub [u8; N]);
+
+const _: () = assert!(mem::size_of::<Tuple<i64, Payload<64>>>() == 72);
+const _: () = assert!(mem::size_of::<Tuple<i32, Payload<6>>>() == 12);
+
+impl<const N: usize> Payload<N> {
+    /// Returns the bytes of the payload.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> Default for Payload<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Payload<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+unsafe impl<const N: usize> DeviceCopy for Payload<N> {}
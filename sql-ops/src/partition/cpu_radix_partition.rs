@@ -126,15 +126,21 @@
 //!
 //!  - Add SWWC flush variants for POWERPC64 VSX and x86_64 AVX-512.
 
+use super::skew::{SaltedPartitions, SkewPolicy};
 use super::{
-    fanout, HistogramAlgorithmType, PartitionOffsetsMutSlice, PartitionedRelationMutSlice,
-    RadixPartitionInputChunk, Tuple,
+    fanout, partition_of, HistogramAlgorithmType, PartitionOffsets, PartitionOffsetsMutSlice,
+    PartitionedRelation, PartitionedRelationMutSlice, RadixPartitionInputChunk,
+    RadixPartitionInputChunkable, Tuple,
 };
 use crate::constants;
 use crate::error::{ErrorKind, Result};
-use numa_gpu::runtime::allocator::{Allocator, DerefMemAllocFn, DerefMemType};
-use numa_gpu::runtime::memory::DerefMem;
+use crate::prefix_scan::exclusive_prefix_sum;
+use num_traits::cast::AsPrimitive;
+use numa_gpu::runtime::allocator::{Allocator, DerefMemAllocFn, DerefMemType, MemAllocFn, MemType};
+use numa_gpu::runtime::memory::{DerefMem, LaunchableMem};
 use numa_gpu::utils::CachePadded;
+use rayon::prelude::*;
+use rayon::ThreadPool;
 use rustacuda::memory::DeviceCopy;
 use std::ffi::c_void;
 use std::{mem, ptr};
@@ -149,6 +155,20 @@ extern "C" {
     fn cpu_chunked_prefix_sum_simd_int64(args: *mut PrefixSumArgs, chunk_id: u32, num_chunks: u32);
     fn cpu_chunked_radix_partition_int32_int32(args: *mut RadixPartitionArgs);
     fn cpu_chunked_radix_partition_int64_int64(args: *mut RadixPartitionArgs);
+    fn cpu_chunked_radix_partition_inverse_int32_int32(
+        args: *mut RadixPartitionArgs,
+        inverse: *mut u64,
+        first_tuple_id: u64,
+    );
+    fn cpu_chunked_radix_partition_inverse_int64_int64(
+        args: *mut RadixPartitionArgs,
+        inverse: *mut u64,
+        first_tuple_id: u64,
+    );
+    #[cfg(target_arch = "x86_64")]
+    fn cpu_chunked_radix_partition_streaming_int32_int32(args: *mut RadixPartitionArgs);
+    #[cfg(target_arch = "x86_64")]
+    fn cpu_chunked_radix_partition_streaming_int64_int64(args: *mut RadixPartitionArgs);
     fn cpu_chunked_radix_partition_swwc_int32_int32(args: *mut RadixPartitionArgs);
     fn cpu_chunked_radix_partition_swwc_int64_int64(args: *mut RadixPartitionArgs);
     #[cfg(target_arch = "powerpc64")]
@@ -268,6 +288,10 @@ impl WriteCombineBuffer {
 /// exist for each implementing type (currently i32 and i64). Specialization is
 /// necessary because each type requires a different C++ function to be called.
 ///
+/// Narrow keys, i.e., `u8` and `u16`, are not partitionable. Their hash join
+/// uses a directly-addressed hash table that fits into the cache without
+/// partitioning.
+///
 /// See `CudaHashJoinable` for more details on the design decision.
 pub trait CpuRadixPartitionable: Sized + DeviceCopy {
     fn prefix_sum_impl(
@@ -283,6 +307,15 @@ pub trait CpuRadixPartitionable: Sized + DeviceCopy {
         partition_offsets: PartitionOffsetsMutSlice<Tuple<Self, Self>>,
         partitioned_relation: PartitionedRelationMutSlice<Tuple<Self, Self>>,
     ) -> Result<()>;
+
+    fn partition_with_inverse_impl(
+        rp: &mut CpuRadixPartitioner,
+        partition_attr: RadixPartitionInputChunk<'_, Self>,
+        payload_attr: RadixPartitionInputChunk<'_, Self>,
+        partition_offsets: PartitionOffsetsMutSlice<Tuple<Self, Self>>,
+        partitioned_relation: PartitionedRelationMutSlice<Tuple<Self, Self>>,
+        inverse: &mut [u64],
+    ) -> Result<()>;
 }
 
 /// Specifies the histogram algorithm that computes the partition offsets.
@@ -323,6 +356,19 @@ pub enum CpuRadixPartitionAlgorithm {
     /// This is a standard, parallel radix partition algorithm.
     NC,
 
+    /// Non-caching radix partition with non-temporal stores.
+    ///
+    /// This is the same algorithm as `NC`, but scatters the tuples with
+    /// non-temporal stores. The stores bypass the cache, and thus don't evict
+    /// the input and the partition offsets. This can increase the throughput
+    /// for large fanouts.
+    ///
+    /// # Limitations
+    ///
+    /// Non-temporal stores are used only on x86-64 CPUs with SSE2, which is
+    /// detected at runtime. Other CPUs fall back to `NC`.
+    NCStreaming,
+
     /// Radix partition with software write-combining.
     ///
     /// This algorithm uses software-write combine buffers to avoid TLB misses.
@@ -355,6 +401,7 @@ enum PrefixSumState {
 #[derive(Debug)]
 enum RadixPartitionState {
     NC(DerefMem<u64>),
+    NCStreaming(DerefMem<u64>),
     Swwc(WriteCombineBuffer),
     SwwcSimd(WriteCombineBuffer),
 }
@@ -365,16 +412,35 @@ pub struct CpuRadixPartitioner {
     radix_bits: u32,
     prefix_sum_state: PrefixSumState,
     radix_partition_state: RadixPartitionState,
+    skew_policy: SkewPolicy,
 }
 
 impl CpuRadixPartitioner {
+    /// The maximum number of radix bits per pass.
+    ///
+    /// The partitioner and each chunk of the partition offsets allocate state
+    /// proportional to the fanout. Beyond 2^20 partitions, this state exceeds
+    /// the CPU caches by far, and partitioning is no longer efficient.
+    pub const MAX_RADIX_BITS: u32 = 20;
+
     /// Creates a new CPU radix partitioner.
+    ///
+    /// Returns an `InvalidArgument` error if `radix_bits` exceeds
+    /// `MAX_RADIX_BITS`.
     pub fn new(
         prefix_sum_algorithm: CpuHistogramAlgorithm,
         partition_algorithm: CpuRadixPartitionAlgorithm,
         radix_bits: u32,
         state_mem_type: DerefMemType,
-    ) -> Self {
+    ) -> Result<Self> {
+        if radix_bits > Self::MAX_RADIX_BITS {
+            Err(ErrorKind::InvalidArgument(format!(
+                "CPU radix partitioning supports at most {} radix bits, but {} were requested",
+                Self::MAX_RADIX_BITS,
+                radix_bits
+            )))?;
+        }
+
         let num_partitions = fanout(radix_bits) as usize;
         let vec_len = 4;
         let unroll_len = 4;
@@ -397,6 +463,14 @@ impl CpuRadixPartitioner {
                 state_mem_type.clone(),
                 num_partitions,
             )),
+            CpuRadixPartitionAlgorithm::NCStreaming => {
+                let offsets = Allocator::alloc_deref_mem(state_mem_type.clone(), num_partitions);
+                if Self::has_streaming_stores() {
+                    RadixPartitionState::NCStreaming(offsets)
+                } else {
+                    RadixPartitionState::NC(offsets)
+                }
+            }
             CpuRadixPartitionAlgorithm::Swwc => RadixPartitionState::Swwc(WriteCombineBuffer::new(
                 radix_bits,
                 Allocator::deref_mem_alloc_fn(state_mem_type.clone()),
@@ -409,11 +483,31 @@ impl CpuRadixPartitioner {
             }
         };
 
-        Self {
+        Ok(Self {
             radix_bits,
             prefix_sum_state,
             radix_partition_state,
-        }
+            skew_policy: SkewPolicy::default(),
+        })
+    }
+
+    /// Sets the policy with which `salt_heavy_partitions` handles skewed
+    /// partitions.
+    ///
+    /// By default, partitions are not salted.
+    pub fn with_skew_policy(mut self, skew_policy: SkewPolicy) -> Self {
+        self.skew_policy = skew_policy;
+        self
+    }
+
+    /// Returns `true` if `CpuRadixPartitionAlgorithm::NCStreaming` uses
+    /// non-temporal stores on the target architecture.
+    ///
+    /// The non-temporal stores require SSE2, which every x86_64 CPU supports.
+    /// Thus, the streaming variant is compiled for all x86_64 targets, and
+    /// falls back to `NC` on other architectures.
+    pub fn has_streaming_stores() -> bool {
+        cfg!(target_arch = "x86_64")
     }
 
     /// Computes the prefix sum.
@@ -439,6 +533,104 @@ impl CpuRadixPartitioner {
         T::prefix_sum_impl(self, partition_attr, partition_offsets)
     }
 
+    /// Computes the histogram and the partition offsets of a relation.
+    ///
+    /// Runs the prefix sum in parallel on the current rayon thread pool, with
+    /// one chunk of `partition_attr` per thread. The prefix sum computes the
+    /// histogram and its prefix sum in one pass. Returns the per-partition
+    /// counts together with their exclusive prefix sum. See
+    /// `PartitionOffsets::histogram_and_offsets` for details.
+    ///
+    /// Each thread uses its own temporary state, thus the partitioner's state
+    /// remains untouched.
+    pub fn histogram_and_offsets<T>(&self, partition_attr: &[T]) -> Result<(Vec<u64>, Vec<u64>)>
+    where
+        T: DeviceCopy + Sync + CpuRadixPartitionable,
+    {
+        let radix_bits = self.radix_bits;
+        let threads = rayon::current_num_threads() as u32;
+        let mut partition_offsets = PartitionOffsets::new(
+            HistogramAlgorithmType::Chunked,
+            threads,
+            radix_bits,
+            Allocator::mem_alloc_fn(MemType::SysMem),
+        );
+
+        let input_chunks = partition_attr.input_chunks::<T>(threads)?;
+        let offsets_chunks: Vec<_> = partition_offsets.chunks_mut().collect();
+
+        input_chunks
+            .into_par_iter()
+            .zip(offsets_chunks.into_par_iter())
+            .try_for_each(|(input_chunk, offsets_chunk)| {
+                let mut partitioner = Self::new(
+                    CpuHistogramAlgorithm::Chunked,
+                    CpuRadixPartitionAlgorithm::NC,
+                    radix_bits,
+                    DerefMemType::SysMem,
+                )?;
+                partitioner.prefix_sum(input_chunk, offsets_chunk)
+            })?;
+
+        partition_offsets.histogram_and_offsets()
+    }
+
+    /// Computes the histogram and the partition offsets of a relation in
+    /// parallel.
+    ///
+    /// The relation is split into one chunk per thread of `thread_pool`, and
+    /// each thread computes a local histogram of its chunk. The local
+    /// histograms are then merged in parallel, with each thread summing up a
+    /// range of partitions over all local histograms. Thus, the same thread
+    /// pool as for the scatter should be used.
+    ///
+    /// The result is identical to `histogram_and_offsets`.
+    pub fn histogram_and_offsets_parallel<T>(
+        &self,
+        partition_attr: &[T],
+        thread_pool: &ThreadPool,
+    ) -> Result<(Vec<u64>, Vec<u64>)>
+    where
+        T: DeviceCopy + AsPrimitive<u64> + Sync,
+    {
+        let fanout = fanout(self.radix_bits) as usize;
+        let radix_bits = self.radix_bits;
+        let threads = thread_pool.current_num_threads().max(1);
+        let chunk_len = ((partition_attr.len() + threads - 1) / threads).max(1);
+
+        let counts = thread_pool.install(|| {
+            let local_histograms: Vec<Vec<u64>> = partition_attr
+                .par_chunks(chunk_len)
+                .map(|chunk| {
+                    let mut histogram = vec![0; fanout];
+                    for key in chunk {
+                        histogram[partition_of(*key, radix_bits)] += 1;
+                    }
+                    histogram
+                })
+                .collect();
+
+            let mut counts = vec![0; fanout];
+            counts
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(partition_id, count)| {
+                    *count = local_histograms
+                        .iter()
+                        .map(|histogram| histogram[partition_id])
+                        .sum();
+                });
+            counts
+        });
+
+        // The prefix sum only scans the fanout, and is cheap compared to the
+        // histogram
+        let mut offsets = counts.clone();
+        exclusive_prefix_sum(&mut offsets);
+
+        Ok((counts, offsets))
+    }
+
     /// Radix-partitions a relation by its key attribute.
     ///
     /// See the module-level documentation for details on the algorithm.
@@ -457,6 +649,240 @@ impl CpuRadixPartitioner {
             partitioned_relation,
         )
     }
+
+    /// Radix-partitions a relation by its key attribute, and records the
+    /// inverse permutation.
+    ///
+    /// The partitioned relation is the same as returned by `partition`. In
+    /// addition, the original position of each tuple is written to `inverse`,
+    /// such that `inverse[partitioned_pos] = original_pos`. `original_pos` is
+    /// the tuple's position in the whole input relation, not only in the input
+    /// chunk. Thus, the inverse permutation maps a partitioned tuple back to
+    /// its original row.
+    ///
+    /// `inverse` has the same length and layout as the chunk of the
+    /// partitioned relation. Use `PartitionedRelation::inverse_chunks_mut` to
+    /// split an inverse permutation for the whole relation into chunks.
+    /// Padding entries are left unchanged.
+    ///
+    /// ## Limitations
+    ///
+    /// The inverse permutation is only supported by the `NC` and `NCStreaming`
+    /// algorithms. Both scatter the tuples with regular stores.
+    pub fn partition_with_inverse<T: DeviceCopy + CpuRadixPartitionable>(
+        &mut self,
+        partition_attr: RadixPartitionInputChunk<'_, T>,
+        payload_attr: RadixPartitionInputChunk<'_, T>,
+        partition_offsets: PartitionOffsetsMutSlice<Tuple<T, T>>,
+        partitioned_relation: PartitionedRelationMutSlice<Tuple<T, T>>,
+        inverse: &mut [u64],
+    ) -> Result<()> {
+        T::partition_with_inverse_impl(
+            self,
+            partition_attr,
+            payload_attr,
+            partition_offsets,
+            partitioned_relation,
+            inverse,
+        )
+    }
+
+    /// Splits the heavy partitions of a partitioned relation into
+    /// sub-partitions.
+    ///
+    /// The heavy partitions are detected from the histogram of the
+    /// partitioned relation, as specified by the partitioner's skew policy.
+    /// The tuples of each heavy partition are assigned round-robin to its
+    /// sub-partitions, which are generated on demand without copying the
+    /// tuples. The returned `SaltMap` tracks which virtual partition belongs to
+    /// which radix partition. See the `skew` module for details.
+    ///
+    /// The partitioned relation must be accessible by the CPU.
+    pub fn salt_heavy_partitions<'a, K: DeviceCopy, V: DeviceCopy>(
+        &self,
+        partitioned_relation: &'a PartitionedRelation<Tuple<K, V>>,
+    ) -> Result<SaltedPartitions<'a, Tuple<K, V>>> {
+        if partitioned_relation.radix_bits() != self.radix_bits {
+            Err(ErrorKind::InvalidArgument(
+                "PartitionedRelation has mismatching radix bits".to_string(),
+            ))?;
+        }
+
+        SaltedPartitions::new(self.skew_policy, partitioned_relation)
+    }
+
+    /// Radix-partitions a relation that is produced as a sequence of chunks.
+    ///
+    /// The chunks are consumed one at a time, e.g., while they are streamed
+    /// from disk. Thus, the caller doesn't have to materialize the whole input
+    /// relation. The sequence is traversed twice. The first pass computes the
+    /// histogram of the whole relation. The second pass partitions each chunk
+    /// with the partitioner's algorithm directly into the `PartitionedRelation`.
+    /// Each chunk continues at the running partition offsets of the previous
+    /// chunks.
+    ///
+    /// The result is identical to partitioning the concatenation of all chunks
+    /// at once with a single thread.
+    ///
+    /// ## Limitations
+    ///
+    /// Only the `NC` and `NCStreaming` algorithms are supported. The SWWC
+    /// algorithms flush whole cache lines, and thus would overwrite the tuples
+    /// that the previous chunks wrote in front of the running offsets.
+    pub fn partition_chunks<'c, T, I>(
+        &mut self,
+        chunks: I,
+        partition_alloc_fn: MemAllocFn<Tuple<T, T>>,
+        offsets_alloc_fn: MemAllocFn<u64>,
+    ) -> Result<PartitionedRelation<Tuple<T, T>>>
+    where
+        T: CpuRadixPartitionable + AsPrimitive<u64>,
+        I: IntoIterator<Item = &'c [Tuple<T, T>]> + Clone,
+    {
+        match self.radix_partition_state {
+            RadixPartitionState::NC(_) | RadixPartitionState::NCStreaming(_) => {}
+            RadixPartitionState::Swwc(_) | RadixPartitionState::SwwcSimd(_) => {
+                Err(ErrorKind::InvalidArgument(
+                    "Partitioning chunks requires the NC partitioning algorithm".to_string(),
+                ))?
+            }
+        }
+
+        let radix_bits = self.radix_bits;
+        let fanout = fanout(radix_bits) as usize;
+        let mut histogram = vec![0_usize; fanout];
+        for chunk in chunks.clone() {
+            for tuple in chunk {
+                histogram[partition_of(tuple.key, radix_bits)] += 1;
+            }
+        }
+
+        let len = histogram.iter().sum();
+        let mut partitioned_relation = PartitionedRelation::try_new(
+            len,
+            HistogramAlgorithmType::Chunked,
+            radix_bits,
+            1,
+            partition_alloc_fn,
+            offsets_alloc_fn,
+        )?;
+        let padding_len = partitioned_relation.padding_len() as usize;
+
+        // Lay out the partitions in the same way as the prefix sum
+        let mut running_offsets = PartitionOffsets::new(
+            HistogramAlgorithmType::Chunked,
+            1,
+            radix_bits,
+            Allocator::mem_alloc_fn(MemType::SysMem),
+        );
+        let mut offset = padding_len;
+        for (count, partition_offset) in histogram
+            .iter()
+            .zip(running_offsets.offsets.try_as_mut_slice()?.iter_mut())
+        {
+            *partition_offset = offset as u64;
+            offset += count + padding_len;
+        }
+        partitioned_relation
+            .offsets
+            .try_as_mut_slice()?
+            .copy_from_slice(running_offsets.offsets.try_as_slice()?);
+
+        let relation = partitioned_relation.relation.try_as_mut_slice()?;
+        let mut chunk_offsets = vec![0_u64; fanout];
+        let mut keys = Vec::new();
+        let mut payloads = Vec::new();
+
+        for chunk in chunks {
+            keys.clear();
+            payloads.clear();
+            keys.extend(chunk.iter().map(|tuple| tuple.key));
+            payloads.extend(chunk.iter().map(|tuple| tuple.value));
+
+            let key_chunk = RadixPartitionInputChunk {
+                data: keys.as_slice(),
+                canonical_chunk_len: keys.len(),
+                chunk_id: 0,
+                num_chunks: 1,
+                total_data_len: len,
+            };
+            let payload_chunk = RadixPartitionInputChunk {
+                data: payloads.as_slice(),
+                canonical_chunk_len: payloads.len(),
+                chunk_id: 0,
+                num_chunks: 1,
+                total_data_len: len,
+            };
+
+            // The partitioning function writes relative to the first
+            // partition, which begins after its padding
+            let first_offset = running_offsets.offsets.try_as_slice()?[0] as usize;
+            let partitioned_chunk = PartitionedRelationMutSlice {
+                relation: relation[(first_offset - padding_len)..].as_launchable_mut_slice(),
+                offsets: chunk_offsets.as_mut_slice().as_launchable_mut_slice(),
+                chunks: 1,
+                radix_bits,
+            };
+            let offsets_chunk = running_offsets
+                .chunks_mut()
+                .next()
+                .ok_or_else(|| ErrorKind::RuntimeError("Missing partition offsets".to_string()))?;
+
+            T::partition_impl(
+                self,
+                key_chunk,
+                payload_chunk,
+                offsets_chunk,
+                partitioned_chunk,
+            )?;
+
+            // Advance the running offsets past the chunk's tuples
+            let offsets = running_offsets.offsets.try_as_mut_slice()?;
+            for &key in keys.iter() {
+                offsets[partition_of(key, radix_bits)] += 1;
+            }
+        }
+
+        Ok(partitioned_relation)
+    }
+}
+
+/// Checks that the arguments of a partitioning function are consistent with
+/// each other and with the partitioner.
+fn check_partition_args<T: DeviceCopy>(
+    rp: &CpuRadixPartitioner,
+    partition_attr: &RadixPartitionInputChunk<'_, T>,
+    payload_attr: &RadixPartitionInputChunk<'_, T>,
+    partition_offsets: &PartitionOffsetsMutSlice<Tuple<T, T>>,
+    partitioned_relation: &PartitionedRelationMutSlice<Tuple<T, T>>,
+) -> Result<()> {
+    if partition_attr.data.len() != payload_attr.data.len() {
+        Err(ErrorKind::InvalidArgument(
+            "Partition and payload attributes have different sizes".to_string(),
+        ))?;
+    }
+    if partitioned_relation.radix_bits != rp.radix_bits {
+        Err(ErrorKind::InvalidArgument(
+            "PartitionedRelation has mismatching radix bits".to_string(),
+        ))?;
+    }
+    if partition_offsets.radix_bits != rp.radix_bits {
+        Err(ErrorKind::InvalidArgument(
+            "PartitionOffsets has mismatching radix bits".to_string(),
+        ))?;
+    }
+    if partition_offsets.chunks != partitioned_relation.chunks {
+        Err(ErrorKind::InvalidArgument(
+            "PartitionOffsets and PartitionedRelation have mismatching chunks".to_string(),
+        ))?;
+    }
+    if partition_offsets.padding_len() != partitioned_relation.padding_len() {
+        Err(ErrorKind::InvalidArgument(
+            "PartitionOffsets and PartitionedRelation have mismatching padding".to_string(),
+        ))?;
+    }
+
+    Ok(())
 }
 
 macro_rules! impl_cpu_radix_partition_for_type {
@@ -542,33 +968,13 @@ macro_rules! impl_cpu_radix_partition_for_type {
                     mut partitioned_relation: PartitionedRelationMutSlice<Tuple<Self, Self>>,
                     ) -> Result<()>
                 {
-                    if partition_attr.data.len() != payload_attr.data.len() {
-                        Err(ErrorKind::InvalidArgument(
-                                "Partition and payload attributes have different sizes"
-                                .to_string()
-                            ))?;
-                    }
-                    if partitioned_relation.radix_bits != rp.radix_bits {
-                        Err(ErrorKind::InvalidArgument(
-                                "PartitionedRelation has mismatching radix bits"
-                                .to_string()
-                            ))?;
-                    }
-                    if (partition_offsets.radix_bits != rp.radix_bits) {
-                        Err(ErrorKind::InvalidArgument(
-                                "PartitionOffsets has mismatching radix bits".to_string(),
-                                ))?;
-                    }
-                    if (partition_offsets.chunks != partitioned_relation.chunks) {
-                        Err(ErrorKind::InvalidArgument(
-                                "PartitionOffsets and PartitionedRelation have mismatching chunks".to_string(),
-                                ))?;
-                    }
-                    if (partition_offsets.padding_len() != partitioned_relation.padding_len()) {
-                        Err(ErrorKind::InvalidArgument(
-                                "PartitionOffsets and PartitionedRelation have mismatching padding".to_string(),
-                                ))?;
-                    }
+                    check_partition_args(
+                        rp,
+                        &partition_attr,
+                        &payload_attr,
+                        &partition_offsets,
+                        &partitioned_relation,
+                    )?;
 
                     let data_len = partition_attr.data.len();
                     let (partition_fn, tmp_partition_offsets, write_combine_buffer):
@@ -584,6 +990,16 @@ macro_rules! impl_cpu_radix_partition_for_type {
                                 offsets.as_mut_ptr(),
                                 ptr::null_mut(),
                             ),
+                        #[cfg(target_arch = "x86_64")]
+                        RadixPartitionState::NCStreaming(ref mut offsets) =>
+                            (
+                                [<cpu_chunked_radix_partition_streaming_ $Suffix _ $Suffix>],
+                                offsets.as_mut_ptr(),
+                                ptr::null_mut(),
+                            ),
+                        #[cfg(not(target_arch = "x86_64"))]
+                        RadixPartitionState::NCStreaming(_) =>
+                            unreachable!("Streaming stores are only detected on x86-64"),
                         RadixPartitionState::Swwc(ref mut swwc) =>
                             (
                                 [<cpu_chunked_radix_partition_swwc_ $Suffix _ $Suffix>],
@@ -631,6 +1047,72 @@ macro_rules! impl_cpu_radix_partition_for_type {
 
                     Ok(())
                 }
+
+                fn partition_with_inverse_impl(
+                    rp: &mut CpuRadixPartitioner,
+                    partition_attr: RadixPartitionInputChunk<'_, Self>,
+                    payload_attr: RadixPartitionInputChunk<'_, Self>,
+                    partition_offsets: PartitionOffsetsMutSlice<Tuple<Self, Self>>,
+                    mut partitioned_relation: PartitionedRelationMutSlice<Tuple<Self, Self>>,
+                    inverse: &mut [u64],
+                    ) -> Result<()>
+                {
+                    check_partition_args(
+                        rp,
+                        &partition_attr,
+                        &payload_attr,
+                        &partition_offsets,
+                        &partitioned_relation,
+                    )?;
+                    if inverse.len() != partitioned_relation.relation.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Inverse permutation and PartitionedRelation chunk have different sizes".to_string(),
+                                ))?;
+                    }
+
+                    let tmp_partition_offsets = match rp.radix_partition_state {
+                        RadixPartitionState::NC(ref mut offsets)
+                            | RadixPartitionState::NCStreaming(ref mut offsets) =>
+                            offsets.as_mut_ptr(),
+                        RadixPartitionState::Swwc(_) | RadixPartitionState::SwwcSimd(_) =>
+                            Err(ErrorKind::InvalidArgument(
+                                    "Inverse permutation requires the NC partitioning algorithm".to_string(),
+                                    ))?,
+                    };
+
+                    let mut args = RadixPartitionArgs {
+                        partition_attr_data: partition_attr.data.as_ptr() as *const c_void,
+                        payload_attr_data: payload_attr.data.as_ptr() as *const c_void,
+                        data_len: partition_attr.data.len(),
+                        padding_len: partitioned_relation.padding_len() as usize,
+                        radix_bits: rp.radix_bits,
+                        ignore_bits: 0,
+                        partition_offsets: partition_offsets.offsets.as_ptr(),
+                        tmp_partition_offsets,
+                        write_combine_buffer: ptr::null_mut(),
+                        partitioned_relation: partitioned_relation.relation
+                            .as_mut_ptr() as *mut c_void,
+                    };
+                    let first_tuple_id =
+                        partition_attr.chunk_id as u64 * partition_attr.canonical_chunk_len as u64;
+
+                    unsafe {
+                        [<cpu_chunked_radix_partition_inverse_ $Suffix _ $Suffix>](
+                            &mut args as *mut RadixPartitionArgs,
+                            inverse.as_mut_ptr(),
+                            first_tuple_id,
+                        );
+                    }
+
+                    // Copy offsets to PartitionedRelation.
+                    unsafe {
+                        partitioned_relation.offsets
+                            .as_mut_slice()
+                            .copy_from_slice(partition_offsets.offsets.as_slice());
+                    }
+
+                    Ok(())
+                }
             }
         }
     };
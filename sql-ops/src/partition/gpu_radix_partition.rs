@@ -14,12 +14,13 @@
 
 use super::cpu_radix_partition::CpuHistogramAlgorithm;
 use super::{
-    partition_input_chunk, HistogramAlgorithmType, PartitionOffsets, PartitionedRelation,
+    fanout, partition_input_chunk, HistogramAlgorithmType, PartitionOffsets, PartitionedRelation,
     RadixBits, RadixPass, Tuple,
 };
 use crate::constants;
 use crate::error::{ErrorKind, Result};
 use crate::prefix_scan::{GpuPrefixScanState, GpuPrefixSum};
+use data_store::relation::Relation;
 use numa_gpu::runtime::allocator::{Allocator, MemType};
 use numa_gpu::runtime::memory::{
     LaunchableMem, LaunchableMutPtr, LaunchableMutSlice, LaunchablePtr, LaunchableSlice, Mem,
@@ -117,6 +118,28 @@ struct PrefixSumAndTransformArgs {
 
 unsafe impl DeviceCopy for PrefixSumAndTransformArgs {}
 
+/// Arguments to the C/C++ fused prefix sum and partitioning function.
+///
+/// Note that the struct's layout must be kept in sync with its counterpart in
+/// C/C++.
+#[repr(C)]
+#[derive(Clone, Debug)]
+struct PrefixSumAndPartitionArgs {
+    // Inputs
+    partition_attr_data: LaunchablePtr<ffi::c_void>,
+    payload_attr_data: LaunchablePtr<ffi::c_void>,
+    data_len: usize,
+    padding_len: u32,
+    radix_bits: u32,
+    ignore_bits: u32,
+
+    // Outputs
+    partition_offsets: LaunchableMutPtr<u64>,
+    partitioned_relation: LaunchableMutPtr<ffi::c_void>,
+}
+
+unsafe impl DeviceCopy for PrefixSumAndPartitionArgs {}
+
 /// Arguments to the C/C++ partitioning function.
 ///
 /// Note that the struct's layout must be kept in sync with its counterpart in
@@ -145,6 +168,12 @@ struct RadixPartitionArgs {
 
 unsafe impl DeviceCopy for RadixPartitionArgs {}
 
+/// Specifies that the implementing type can be used as partitioning key in
+/// `GpuRadixPartitioner`.
+///
+/// Specialized implementations exist for i32 and i64. Narrow keys, i.e., `u8`
+/// and `u16`, are not partitionable. Their hash join uses a directly-addressed
+/// hash table that fits into the GPU's L2 cache without partitioning.
 pub trait GpuRadixPartitionable: Sized + DeviceCopy {
     fn prefix_sum_impl(
         rp: &mut GpuRadixPartitioner,
@@ -176,6 +205,15 @@ pub trait GpuRadixPartitionable: Sized + DeviceCopy {
         stream: &Stream,
     ) -> Result<()>;
 
+    fn prefix_sum_and_partition_impl(
+        rp: &mut GpuRadixPartitioner,
+        pass: RadixPass,
+        partition_attr: LaunchableSlice<'_, Self>,
+        payload_attr: LaunchableSlice<'_, Self>,
+        partitioned_relation: &mut PartitionedRelation<Tuple<Self, Self>>,
+        stream: &Stream,
+    ) -> Result<()>;
+
     fn allocate_partition_state_impl(rp: &mut GpuRadixPartitioner, pass: RadixPass) -> Result<()>;
 
     fn partition_impl(
@@ -325,7 +363,13 @@ pub struct GpuRadixPartitioner {
 }
 
 impl GpuRadixPartitioner {
-    /// Creates a new CPU radix partitioner.
+    /// Creates a new GPU radix partitioner.
+    ///
+    /// The radix bits of each pass are validated against `max_radix_bits` of
+    /// the smallest supported tuple, i.e., `Tuple<i32, i32>`. Thus, a
+    /// configuration that doesn't fit into shared memory for any tuple type
+    /// returns an `InvalidArgument` error. As the limit depends on the tuple
+    /// type, the radix bits are validated again when the pass is executed.
     pub fn new(
         prefix_sum_algorithm: GpuHistogramAlgorithm,
         partition_algorithm: GpuRadixPartitionAlgorithm,
@@ -334,6 +378,16 @@ impl GpuRadixPartitioner {
         block_size: &BlockSize,
         dmem_buffer_bytes: usize,
     ) -> Result<Self> {
+        for &pass in &[RadixPass::First, RadixPass::Second, RadixPass::Third] {
+            Self::validate_radix_bits::<i32>(
+                prefix_sum_algorithm,
+                partition_algorithm,
+                block_size,
+                &radix_bits,
+                pass,
+            )?;
+        }
+
         let prefix_scan_state_len = GpuPrefixSum::state_len(grid_size.clone(), block_size.clone())?;
 
         let prefix_sum_state = match prefix_sum_algorithm {
@@ -358,16 +412,7 @@ impl GpuRadixPartitioner {
             _ => RadixPartitionState::None,
         };
 
-        let rp_block_size = BlockSize::from(cmp::min(
-            block_size.x,
-            match partition_algorithm {
-                GpuRadixPartitionAlgorithm::NC => 1024,
-                GpuRadixPartitionAlgorithm::LASWWC => 1024,
-                GpuRadixPartitionAlgorithm::SSWWCv2 => 1024,
-                GpuRadixPartitionAlgorithm::SSWWCv2G => 1024,
-                GpuRadixPartitionAlgorithm::HSSWWCv4 => 512,
-            },
-        ));
+        let rp_block_size = Self::rp_block_size(partition_algorithm, block_size);
 
         Ok(Self {
             radix_bits,
@@ -382,6 +427,57 @@ impl GpuRadixPartitioner {
         })
     }
 
+    /// Returns the maximum number of radix bits per pass.
+    ///
+    /// The prefix sum and partitioning kernels keep per-partition state in
+    /// shared memory, e.g., the histogram, offsets, and write-combine buffers.
+    /// Thus, the shared memory size of the current device limits the fanout.
+    /// The limit depends on the algorithms, the thread block size, and the
+    /// tuple size of `Tuple<T, T>`.
+    ///
+    /// `SSWWCv2G` stores its buffers in device memory, and is thus limited only
+    /// by the prefix sum. `HSSWWCv4` is additionally limited to 2^16 buffers
+    /// per thread block, including one spare buffer per warp.
+    ///
+    /// Returns an `InvalidArgument` error if not even a single partition fits
+    /// into shared memory, i.e., if the thread block is too large.
+    pub fn max_radix_bits<T: DeviceCopy>(
+        prefix_sum_algorithm: GpuHistogramAlgorithm,
+        partition_algorithm: GpuRadixPartitionAlgorithm,
+        block_size: &BlockSize,
+    ) -> Result<u32> {
+        let device = CurrentContext::get_device()?;
+        let max_shared_mem_bytes =
+            device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)? as u64;
+        let warp_size = device.get_attribute(DeviceAttribute::WarpSize)? as u32;
+
+        let rp_block_size = Self::rp_block_size(partition_algorithm, block_size);
+        let tuple_bytes = mem::size_of::<Tuple<T, T>>() as u64;
+
+        let max_radix_bits = (0..u32::BITS)
+            .take_while(|&radix_bits| {
+                Self::shared_mem_bytes(
+                    prefix_sum_algorithm,
+                    partition_algorithm,
+                    block_size.x,
+                    rp_block_size.x / warp_size,
+                    rp_block_size.x,
+                    tuple_bytes,
+                    fanout(radix_bits),
+                )
+                .map_or(false, |bytes| bytes <= max_shared_mem_bytes)
+            })
+            .last()
+            .ok_or_else(|| {
+                ErrorKind::InvalidArgument(format!(
+                    "Thread block size {} is too large to fit a single partition into {} bytes of shared memory",
+                    block_size.x, max_shared_mem_bytes
+                ))
+            })?;
+
+        Ok(max_radix_bits)
+    }
+
     /// Computes the prefix sum.
     ///
     /// The prefix sum performs a scan over all partitioning keys. It first
@@ -404,6 +500,7 @@ impl GpuRadixPartitioner {
         partition_offsets: &mut PartitionOffsets<Tuple<T, T>>,
         stream: &Stream,
     ) -> Result<()> {
+        self.check_radix_bits::<T>(pass)?;
         T::prefix_sum_impl(self, pass, partition_attr, partition_offsets, stream)
     }
 
@@ -428,6 +525,9 @@ impl GpuRadixPartitioner {
     /// for small relations that fit into GPU memory. Thus the next step in the
     /// workflow is a SQL operator (e.g., join), which only takes a contiguous
     /// relation as input.
+    ///
+    /// The source and destination attributes must all have the same length.
+    /// Otherwise, an `InvalidArgument` error is returned.
     pub fn prefix_sum_and_copy_with_payload<T: DeviceCopy + GpuRadixPartitionable>(
         &mut self,
         pass: RadixPass,
@@ -438,6 +538,7 @@ impl GpuRadixPartitioner {
         partition_offsets: &mut PartitionOffsets<Tuple<T, T>>,
         stream: &Stream,
     ) -> Result<()> {
+        self.check_radix_bits::<T>(pass)?;
         T::prefix_sum_and_copy_with_payload_impl(
             self,
             pass,
@@ -477,6 +578,9 @@ impl GpuRadixPartitioner {
     ///
     /// Currently only the `Contiguous` histogram algorithm is supported. See
     /// `prefix_sum_and_copy_with_payload` for details.
+    ///
+    /// The destination attributes must have the same length as the source
+    /// partition. Otherwise, an `InvalidArgument` error is returned.
     pub fn prefix_sum_and_transform<T: DeviceCopy + GpuRadixPartitionable>(
         &mut self,
         pass: RadixPass,
@@ -487,6 +591,7 @@ impl GpuRadixPartitioner {
         partition_offsets: &mut PartitionOffsets<Tuple<T, T>>,
         stream: &Stream,
     ) -> Result<()> {
+        self.check_radix_bits::<T>(pass)?;
         T::prefix_sum_and_transform_impl(
             self,
             pass,
@@ -499,6 +604,60 @@ impl GpuRadixPartitioner {
         )
     }
 
+    /// Computes the prefix sum and radix-partitions a relation in a single
+    /// kernel.
+    ///
+    /// The typical partitioning workflow first calls `prefix_sum`, and then
+    /// calls `partition`. Between the two kernels, the partition offsets make a
+    /// round trip through global memory: `prefix_sum` writes them out, and
+    /// `partition` reads them back in. `prefix_sum_and_partition` instead keeps
+    /// each thread block's histogram and exclusive prefix sum in shared memory,
+    /// and scatters the tuples directly afterwards. The offsets are written to
+    /// `partitioned_relation` only as a result.
+    ///
+    /// ## Bandwidth savings
+    ///
+    /// The fused kernel saves one kernel launch, and reading the offsets back
+    /// from memory, i.e., `grid_size * fanout * 8` bytes. In addition, the
+    /// offsets do not need to be swapped or copied from a separate
+    /// `PartitionOffsets` into the `PartitionedRelation`. The savings are
+    /// largest for high fanouts and large grids, and if the offsets reside in
+    /// CPU memory that is accessed over the interconnect.
+    ///
+    /// Note that the partition key attribute is still read twice, once for the
+    /// histogram and once for the scatter. Thus, the fused kernel does not
+    /// reduce the traffic caused by the relation itself.
+    ///
+    /// ## Parallelism
+    ///
+    /// The function is internally parallelized by the GPU. The function is
+    /// *not* thread-safe for multiple callers.
+    ///
+    /// ## Limitations
+    ///
+    /// Currently only the `Chunked` histogram algorithm together with the `NC`
+    /// partitioning algorithm is supported. The other partitioning algorithms
+    /// require all of the shared memory for their buffers, and the
+    /// `Contiguous` histogram algorithm requires a grid synchronization.
+    pub fn prefix_sum_and_partition<T: DeviceCopy + GpuRadixPartitionable>(
+        &mut self,
+        pass: RadixPass,
+        partition_attr: LaunchableSlice<'_, T>,
+        payload_attr: LaunchableSlice<'_, T>,
+        partitioned_relation: &mut PartitionedRelation<Tuple<T, T>>,
+        stream: &Stream,
+    ) -> Result<()> {
+        self.check_radix_bits::<T>(pass)?;
+        T::prefix_sum_and_partition_impl(
+            self,
+            pass,
+            partition_attr,
+            payload_attr,
+            partitioned_relation,
+            stream,
+        )
+    }
+
     /// Preallocates the internal state of `partition`
     ///
     /// Some partitioning variants use GPU memory buffers to hold internal state
@@ -512,6 +671,8 @@ impl GpuRadixPartitioner {
         &mut self,
         pass: RadixPass,
     ) -> Result<()> {
+        self.check_radix_bits::<T>(pass)?;
+
         // Pre-load the CUDA module. The module consumes several hundred MB of
         // GPU memory, but is loaded lazily on first use. Thus, the preallocation
         // must also load the module to complete all state.
@@ -520,7 +681,7 @@ impl GpuRadixPartitioner {
         // out-of-memory error if the module is not pre-loaded. The reason is
         // that the amount of free memory isn't correct without accounting for
         // the module.
-        let _ = *crate::MODULE;
+        crate::module()?;
 
         T::allocate_partition_state_impl(self, pass)
     }
@@ -542,6 +703,7 @@ impl GpuRadixPartitioner {
         partitioned_relation: &mut PartitionedRelation<Tuple<T, T>>,
         stream: &Stream,
     ) -> Result<()> {
+        self.check_radix_bits::<T>(pass)?;
         T::partition_impl(
             self,
             pass,
@@ -552,6 +714,148 @@ impl GpuRadixPartitioner {
             stream,
         )
     }
+
+    /// Computes the prefix sum of a relation by its key attribute.
+    ///
+    /// Equivalent to `prefix_sum` with the relation's key attribute.
+    pub fn prefix_sum_relation<T: DeviceCopy + GpuRadixPartitionable>(
+        &mut self,
+        pass: RadixPass,
+        relation: &Relation<T, T>,
+        partition_offsets: &mut PartitionOffsets<Tuple<T, T>>,
+        stream: &Stream,
+    ) -> Result<()> {
+        let (partition_attr, _) = relation.as_launchable_slices();
+        self.prefix_sum(pass, partition_attr, partition_offsets, stream)
+    }
+
+    /// Radix-partitions a relation by its key attribute.
+    ///
+    /// Equivalent to `partition` with the relation's key and value attributes.
+    pub fn partition_relation<T: DeviceCopy + GpuRadixPartitionable>(
+        &mut self,
+        pass: RadixPass,
+        relation: &Relation<T, T>,
+        partition_offsets: &mut PartitionOffsets<Tuple<T, T>>,
+        partitioned_relation: &mut PartitionedRelation<Tuple<T, T>>,
+        stream: &Stream,
+    ) -> Result<()> {
+        let (partition_attr, payload_attr) = relation.as_launchable_slices();
+        self.partition(
+            pass,
+            partition_attr,
+            payload_attr,
+            partition_offsets,
+            partitioned_relation,
+            stream,
+        )
+    }
+
+    /// Checks that the radix bits of a pass don't exceed `max_radix_bits`.
+    ///
+    /// The check runs before any state is allocated or kernel is launched.
+    fn check_radix_bits<T: DeviceCopy>(&self, pass: RadixPass) -> Result<()> {
+        Self::validate_radix_bits::<T>(
+            self.prefix_sum_algorithm,
+            self.partition_algorithm,
+            &self.block_size,
+            &self.radix_bits,
+            pass,
+        )
+    }
+
+    /// Checks that the radix bits of a pass don't exceed `max_radix_bits` for
+    /// tuples of type `Tuple<T, T>`.
+    fn validate_radix_bits<T: DeviceCopy>(
+        prefix_sum_algorithm: GpuHistogramAlgorithm,
+        partition_algorithm: GpuRadixPartitionAlgorithm,
+        block_size: &BlockSize,
+        radix_bits: &RadixBits,
+        pass: RadixPass,
+    ) -> Result<()> {
+        let radix_bits = match radix_bits.pass_radix_bits(pass) {
+            Some(radix_bits) => radix_bits,
+            None => return Ok(()),
+        };
+
+        let max_radix_bits =
+            Self::max_radix_bits::<T>(prefix_sum_algorithm, partition_algorithm, block_size)?;
+
+        if radix_bits > max_radix_bits {
+            Err(ErrorKind::InvalidArgument(format!(
+                "{:?} pass requests {} radix bits, but the {:?} histogram and {:?} partitioning algorithms support at most {} radix bits on this device",
+                pass, radix_bits, prefix_sum_algorithm, partition_algorithm, max_radix_bits
+            )))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the thread block size of the partitioning kernel.
+    fn rp_block_size(
+        partition_algorithm: GpuRadixPartitionAlgorithm,
+        block_size: &BlockSize,
+    ) -> BlockSize {
+        BlockSize::from(cmp::min(
+            block_size.x,
+            match partition_algorithm {
+                GpuRadixPartitionAlgorithm::NC => 1024,
+                GpuRadixPartitionAlgorithm::LASWWC => 1024,
+                GpuRadixPartitionAlgorithm::SSWWCv2 => 1024,
+                GpuRadixPartitionAlgorithm::SSWWCv2G => 1024,
+                GpuRadixPartitionAlgorithm::HSSWWCv4 => 512,
+            },
+        ))
+    }
+
+    /// Computes the shared memory bytes required by the prefix sum and the
+    /// partitioning kernels of a pass.
+    ///
+    /// The sizes must be kept in sync with the shared memory layouts of the
+    /// CUDA kernels. Returns `None` if the algorithm doesn't support the
+    /// fanout, regardless of the shared memory size.
+    fn shared_mem_bytes(
+        prefix_sum_algorithm: GpuHistogramAlgorithm,
+        partition_algorithm: GpuRadixPartitionAlgorithm,
+        block_size: u32,
+        warps_per_rp_block: u32,
+        rp_block_size: u32,
+        tuple_bytes: u64,
+        fanout: u32,
+    ) -> Option<u64> {
+        let fanout = fanout as u64;
+        let scan_len = (block_size + (block_size >> constants::LOG2_NUM_BANKS)) as u64 + fanout;
+
+        let prefix_sum_bytes = match prefix_sum_algorithm {
+            GpuHistogramAlgorithm::Chunked => scan_len * mem::size_of::<u32>() as u64,
+            GpuHistogramAlgorithm::Contiguous => scan_len * mem::size_of::<u64>() as u64,
+        };
+
+        let partition_bytes = match partition_algorithm {
+            GpuRadixPartitionAlgorithm::NC => fanout * mem::size_of::<u32>() as u64,
+            GpuRadixPartitionAlgorithm::LASWWC => {
+                rp_block_size as u64 * constants::LASWWC_TUPLES_PER_THREAD as u64 * tuple_bytes
+                    + 2 * fanout * mem::size_of::<u32>() as u64
+            }
+            GpuRadixPartitionAlgorithm::SSWWCv2 => {
+                fanout * (tuple_bytes + 4 * mem::size_of::<u32>() as u64)
+            }
+            GpuRadixPartitionAlgorithm::SSWWCv2G => 0,
+            GpuRadixPartitionAlgorithm::HSSWWCv4 => {
+                // The dmem buffer map uses 16-bit buffer IDs
+                if fanout + warps_per_rp_block as u64 > u16::MAX as u64 + 1 {
+                    return None;
+                }
+
+                fanout
+                    * (tuple_bytes
+                        + mem::size_of::<u16>() as u64
+                        + 3 * mem::size_of::<u32>() as u64)
+            }
+        };
+
+        Some(cmp::max(prefix_sum_bytes, partition_bytes))
+    }
 }
 
 macro_rules! impl_gpu_radix_partition_for_type {
@@ -612,7 +916,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
 
                     partition_offsets.set_data_len(partition_attr.len());
 
-                    let module = *crate::MODULE;
+                    let module = crate::module()?;
                     let max_shared_mem_bytes =
                         device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)? as u32;
                     let fanout_u32 = rp.radix_bits.pass_fanout(pass).unwrap();
@@ -652,7 +956,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
                             let name = std::ffi::CString::new(
                                 stringify!([<gpu_chunked_prefix_sum_ $Suffix>])
                                 ).unwrap();
-                            let mut function = module.get_function(&name)?;
+                            let mut function = crate::module_function(module, &name)?;
                             function.set_max_dynamic_shared_size_bytes(shared_mem_bytes)?;
 
                             unsafe {
@@ -716,6 +1020,17 @@ macro_rules! impl_gpu_radix_partition_for_type {
                                     "The requested partitioning pass is not specified".to_string()
                                     ))?;
 
+                    if src_partition_attr.len() != src_payload_attr.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Partition and payload attributes have different sizes".to_string(),
+                                ))?;
+                    }
+                    if dst_partition_attr.len() != src_partition_attr.len()
+                        || dst_payload_attr.len() != src_payload_attr.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Source and destination attributes have different sizes".to_string(),
+                                ))?;
+                    }
                     if partition_offsets.radix_bits() != radix_bits {
                         Err(ErrorKind::InvalidArgument(
                                 "PartitionOffsets has mismatching radix bits".to_string(),
@@ -754,7 +1069,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
 
                     partition_offsets.set_data_len(src_partition_attr.len());
 
-                    let module = *crate::MODULE;
+                    let module = crate::module()?;
                     let max_shared_mem_bytes =
                         device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)? as u32;
                     let fanout_u32 = rp.radix_bits.pass_fanout(pass).unwrap();
@@ -801,7 +1116,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
                             let name = std::ffi::CString::new(
                                 stringify!([<gpu_contiguous_prefix_sum_and_copy_with_payload_ $Suffix _ $Suffix>])
                                 ).unwrap();
-                            let mut function = module.get_function(&name)?;
+                            let mut function = crate::module_function(module, &name)?;
                             function.set_max_dynamic_shared_size_bytes(shared_mem_bytes)?;
 
                             args.prefix_scan_state = prefix_scan_state.as_launchable_mut_ptr();
@@ -849,6 +1164,20 @@ macro_rules! impl_gpu_radix_partition_for_type {
                                 "Invalid partition ID".to_string(),
                                 ))?;
                     }
+                    if dst_partition_attr.len() != dst_payload_attr.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Partition and payload attributes have different sizes".to_string(),
+                                ))?;
+                    }
+                    // The partition length is known only if the offsets are
+                    // accessible by the CPU
+                    if let Ok(partition_len) = src_relation.partition_len(partition_id) {
+                        if dst_partition_attr.len() != partition_len {
+                            Err(ErrorKind::InvalidArgument(
+                                    "Destination attributes and source partition have mismatching lengths".to_string(),
+                                    ))?;
+                        }
+                    }
                     if partition_offsets.radix_bits() != radix_bits {
                         Err(ErrorKind::InvalidArgument(
                                 "PartitionedRelation has mismatching radix bits".to_string(),
@@ -887,7 +1216,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
 
                     partition_offsets.set_data_len(dst_partition_attr.len());
 
-                    let module = *crate::MODULE;
+                    let module = crate::module()?;
                     let max_shared_mem_bytes =
                         device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)? as u32;
                     let fanout_u32 = rp.radix_bits.pass_fanout(pass).unwrap();
@@ -934,7 +1263,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
                             let name = std::ffi::CString::new(
                                 stringify!([<gpu_contiguous_prefix_sum_and_transform_ $Suffix _ $Suffix>])
                                 ).unwrap();
-                            let mut function = module.get_function(&name)?;
+                            let mut function = crate::module_function(module, &name)?;
                             function.set_max_dynamic_shared_size_bytes(shared_mem_bytes)?;
 
                             args.prefix_scan_state = prefix_scan_state.as_launchable_mut_ptr();
@@ -957,6 +1286,109 @@ macro_rules! impl_gpu_radix_partition_for_type {
                 }
             }
 
+            paste::item! {
+                fn prefix_sum_and_partition_impl(
+                    rp: &mut GpuRadixPartitioner,
+                    pass: RadixPass,
+                    partition_attr: LaunchableSlice<'_, $Type>,
+                    payload_attr: LaunchableSlice<'_, $Type>,
+                    partitioned_relation: &mut PartitionedRelation<Tuple<$Type, $Type>>,
+                    stream: &Stream,
+                    ) -> Result<()> {
+
+                    let radix_bits = rp
+                        .radix_bits
+                        .pass_radix_bits(pass)
+                        .ok_or_else(||
+                                ErrorKind::InvalidArgument(
+                                    "The requested partitioning pass is not specified".to_string()
+                                    ))?;
+
+                    match (rp.prefix_sum_algorithm, rp.partition_algorithm) {
+                        (GpuHistogramAlgorithm::Chunked, GpuRadixPartitionAlgorithm::NC) => {},
+                        _ => Err(ErrorKind::InvalidArgument(
+                                "The fused prefix sum and partition only supports the Chunked \
+                                histogram and NC partition algorithms".to_string(),
+                                ))?,
+                    }
+                    if partition_attr.len() != payload_attr.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Partition and payload attributes have different sizes".to_string(),
+                                ))?;
+                    }
+                    if partitioned_relation.len() != partition_attr.len() {
+                        Err(ErrorKind::InvalidArgument(
+                                "Partition attribute and PartitionedRelation have mismatching lengths".to_string(),
+                                ))?;
+                    }
+                    if partitioned_relation.radix_bits() != radix_bits {
+                        Err(ErrorKind::InvalidArgument(
+                                "PartitionedRelation has mismatching radix bits".to_string(),
+                                ))?;
+                    }
+                    if partitioned_relation.num_chunks() != rp.grid_size.x {
+                        Err(ErrorKind::InvalidArgument(
+                                "PartitionedRelation has mismatching number of chunks".to_string(),
+                                ))?;
+                    }
+                    if (partition_attr.len() + (rp.grid_size.x as usize) - 1)
+                        / (rp.grid_size.x as usize) >= std::u32::MAX as usize {
+                            let msg = "Relation is too large and causes an integer overflow. Try using more chunks by setting a higher CUDA grid size";
+                            Err(ErrorKind::IntegerOverflow(msg.to_string(),))?
+                    }
+
+                    let module = crate::module()?;
+                    let device = CurrentContext::get_device()?;
+                    let max_shared_mem_bytes =
+                        device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)? as u32;
+                    let fanout_u32 = rp.radix_bits.pass_fanout(pass).unwrap();
+                    let ignore_bits = rp.radix_bits.pass_ignore_bits(pass);
+                    let grid_size = rp.grid_size.clone();
+                    let rp_block_size = rp.rp_block_size.clone();
+
+                    let args = PrefixSumAndPartitionArgs {
+                        partition_attr_data: partition_attr.as_launchable_ptr().as_void(),
+                        payload_attr_data: payload_attr.as_launchable_ptr().as_void(),
+                        data_len: partition_attr.len(),
+                        padding_len: partitioned_relation.padding_len(),
+                        radix_bits,
+                        ignore_bits,
+                        partition_offsets: partitioned_relation.offsets.as_launchable_mut_ptr(),
+                        partitioned_relation: partitioned_relation.relation.as_launchable_mut_ptr().as_void(),
+                    };
+
+                    // The shared memory holds the offsets and the temporary
+                    // space required by the block-wide prefix sum.
+                    let shared_mem_bytes = (
+                        (rp_block_size.x + (rp_block_size.x >> constants::LOG2_NUM_BANKS)) + fanout_u32
+                        ) * mem::size_of::<u32>() as u32;
+                    assert!(
+                        shared_mem_bytes <= max_shared_mem_bytes,
+                        "Failed to allocate enough shared memory"
+                        );
+
+                    let name = std::ffi::CString::new(
+                        stringify!([<gpu_chunked_prefix_sum_and_radix_partition_ $Suffix _ $Suffix>])
+                        ).unwrap();
+                    let mut function = crate::module_function(module, &name)?;
+                    function.set_max_dynamic_shared_size_bytes(shared_mem_bytes)?;
+
+                    unsafe {
+                        launch!(
+                            function<<<
+                            grid_size,
+                            rp_block_size,
+                            shared_mem_bytes,
+                            stream
+                            >>>(
+                                args.clone()
+                               ))?;
+                    }
+
+                    Ok(())
+                }
+            }
+
             fn allocate_partition_state_impl(
                     rp: &mut GpuRadixPartitioner,
                     pass: RadixPass,
@@ -1079,7 +1511,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
                                 ))?;
                     }
 
-                    let module = *crate::MODULE;
+                    let module = crate::module()?;
                     let grid_size = rp.grid_size.clone();
                     let rp_block_size = rp.rp_block_size.clone();
                     let device = CurrentContext::get_device()?;
@@ -1159,7 +1591,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
                             let name = std::ffi::CString::new(
                                 stringify!([<gpu_chunked_laswwc_radix_partition_ $Suffix _ $Suffix>])
                                 ).unwrap();
-                            let mut function = module.get_function(&name)?;
+                            let mut function = crate::module_function(module, &name)?;
                             function.set_max_dynamic_shared_size_bytes(max_shared_mem_bytes)?;
 
                             unsafe {
@@ -1179,7 +1611,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
                             let name = std::ffi::CString::new(
                                 stringify!([<gpu_chunked_sswwc_radix_partition_v2_ $Suffix _ $Suffix>])
                                 ).unwrap();
-                            let mut function = module.get_function(&name)?;
+                            let mut function = crate::module_function(module, &name)?;
                             function.set_max_dynamic_shared_size_bytes(max_shared_mem_bytes)?;
 
                             unsafe {
@@ -1199,7 +1631,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
                             let name = std::ffi::CString::new(
                                 stringify!([<gpu_chunked_sswwc_radix_partition_v2g_ $Suffix _ $Suffix>])
                                 ).unwrap();
-                            let function = module.get_function(&name)?;
+                            let function = crate::module_function(module, &name)?;
 
                             unsafe {
                                 launch!(
@@ -1217,7 +1649,7 @@ macro_rules! impl_gpu_radix_partition_for_type {
                             let name = std::ffi::CString::new(
                                 stringify!([<gpu_chunked_hsswwc_radix_partition_v4_ $Suffix _ $Suffix>])
                                 ).unwrap();
-                            let mut function = module.get_function(&name)?;
+                            let mut function = crate::module_function(module, &name)?;
                             function.set_max_dynamic_shared_size_bytes(max_shared_mem_bytes)?;
 
                             unsafe {
@@ -20,8 +20,16 @@ use std::mem;
 
 /// Returns the reference chunk size with which input should be partitioned.
 ///
+/// An empty input results in empty chunks.
+///
 /// Note that this is an internal method.
 pub(super) fn input_chunk_size<Key>(data_len: usize, num_chunks: u32) -> Result<usize> {
+    if num_chunks == 0 {
+        Err(ErrorKind::InvalidArgument(
+            "Input must be split into at least one chunk".to_string(),
+        ))?;
+    }
+
     let num_chunks_usize = num_chunks as usize;
     let input_align_mask = !(constants::ALIGN_BYTES as usize / mem::size_of::<Key>() - 1);
     let chunk_len = ((data_len + num_chunks_usize - 1) / num_chunks_usize) & input_align_mask;
@@ -14,14 +14,25 @@
 
 use super::{fanout, HistogramAlgorithmType, Tuple};
 use crate::error::{ErrorKind, Result};
+use crate::prefix_scan::exclusive_prefix_sum;
+use crate::FileTypeTag;
 use numa_gpu::error::Result as NumaGpuResult;
 use numa_gpu::runtime::allocator::MemAllocFn;
 use numa_gpu::runtime::memory::{LaunchableMem, LaunchableMutSlice, Mem, MemLock};
 use rustacuda::memory::DeviceCopy;
 use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::mem;
 use std::ops::{Index, IndexMut};
-use std::slice::ChunksMut;
+use std::path::Path;
+use std::slice::{self, ChunksMut};
+
+/// Identifies a file that contains a saved `PartitionedRelation`.
+const FILE_MAGIC: [u8; 8] = *b"NGPUPREL";
+
+/// Version of the file layout. Must be incremented when the layout changes.
+const FILE_VERSION: u32 = 2;
 
 /// Convert padding bytes into padding length for the type `T`
 fn padding_len<T: Sized>() -> u32 {
@@ -194,6 +205,26 @@ impl<T: DeviceCopy> PartitionOffsets<T> {
         Ok(len)
     }
 
+    /// Returns the histogram and the offsets of all partitions.
+    ///
+    /// The histogram contains the length of each partition, summed up over all
+    /// chunks. The offsets are the exclusive prefix sum of the histogram, and
+    /// exclude padding. Thus, `offsets[0]` is zero and
+    /// `offsets[i + 1] - offsets[i]` equals `counts[i]`.
+    ///
+    /// The offsets must be accessible by the CPU, and the prefix sum must be
+    /// computed.
+    pub fn histogram_and_offsets(&self) -> Result<(Vec<u64>, Vec<u64>)> {
+        let counts = (0..self.fanout())
+            .map(|partition_id| self.partition_len(partition_id).map(|len| len as u64))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut offsets = counts.clone();
+        exclusive_prefix_sum(&mut offsets);
+
+        Ok((counts, offsets))
+    }
+
     /// Returns the number of padding elements per partition.
     pub(super) fn padding_len(&self) -> u32 {
         padding_len::<T>()
@@ -332,6 +363,9 @@ pub struct PartitionedRelation<T: DeviceCopy> {
 impl<T: DeviceCopy> PartitionedRelation<T> {
     /// Creates a new partitioned relation, and automatically includes the
     /// necessary padding and metadata.
+    ///
+    /// Panics if the relation is too large to be allocated. Use `try_new` to
+    /// handle this case as an error.
     pub fn new(
         len: usize,
         histogram_algorithm_type: HistogramAlgorithmType,
@@ -340,6 +374,30 @@ impl<T: DeviceCopy> PartitionedRelation<T> {
         partition_alloc_fn: MemAllocFn<T>,
         offsets_alloc_fn: MemAllocFn<u64>,
     ) -> Self {
+        Self::try_new(
+            len,
+            histogram_algorithm_type,
+            radix_bits,
+            max_chunks,
+            partition_alloc_fn,
+            offsets_alloc_fn,
+        )
+        .expect("Failed to create the partitioned relation")
+    }
+
+    /// Creates a new partitioned relation, and automatically includes the
+    /// necessary padding and metadata.
+    ///
+    /// Returns an `IntegerOverflow` error without allocating memory if the
+    /// padded relation or the offsets exceed the maximum allocation size.
+    pub fn try_new(
+        len: usize,
+        histogram_algorithm_type: HistogramAlgorithmType,
+        radix_bits: u32,
+        max_chunks: u32,
+        partition_alloc_fn: MemAllocFn<T>,
+        offsets_alloc_fn: MemAllocFn<u64>,
+    ) -> Result<Self> {
         let chunks: u32 = match histogram_algorithm_type {
             HistogramAlgorithmType::Chunked => max_chunks,
             HistogramAlgorithmType::Contiguous => 1,
@@ -347,18 +405,29 @@ impl<T: DeviceCopy> PartitionedRelation<T> {
 
         let padding_len = padding_len::<T>();
         let num_partitions = fanout(radix_bits) as usize;
-        let relation_len = len + (num_partitions * chunks as usize) * padding_len as usize;
+        let offsets_len = num_partitions.checked_mul(chunks as usize).ok_or_else(|| {
+            ErrorKind::IntegerOverflow("Failed to compute the offsets length".to_string())
+        })?;
+        let relation_len = offsets_len
+            .checked_mul(padding_len as usize)
+            .and_then(|padding| padding.checked_add(len))
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to compute the relation length".to_string())
+            })?;
+
+        crate::checked_alloc_bytes::<T>(relation_len, "Partitioned relation")?;
+        crate::checked_alloc_bytes::<u64>(offsets_len, "Partition offsets")?;
 
         let relation = partition_alloc_fn(relation_len);
-        let offsets = offsets_alloc_fn(num_partitions * chunks as usize);
+        let offsets = offsets_alloc_fn(offsets_len);
 
-        Self {
+        Ok(Self {
             relation,
             offsets,
             chunks,
             radix_bits,
             len,
-        }
+        })
     }
 
     /// Returns the total number of elements in the relation (excluding padding).
@@ -458,11 +527,50 @@ impl<T: DeviceCopy> PartitionedRelation<T> {
         Ok(len)
     }
 
+    /// Returns the length of each partition, summed up over all chunks.
+    ///
+    /// The offsets must be accessible by the CPU.
+    pub fn histogram(&self) -> Result<Vec<u64>> {
+        (0..self.fanout())
+            .map(|partition_id| self.partition_len(partition_id).map(|len| len as u64))
+            .collect()
+    }
+
     /// Returns the number of padding elements per partition.
     pub fn padding_len(&self) -> u32 {
         padding_len::<T>()
     }
 
+    /// Returns an iterator over the partitions as `(partition_id, tuples)`
+    /// pairs.
+    ///
+    /// The padding is skipped. Partitions are yielded in ascending order. If
+    /// the relation has multiple chunks, then a partition is split into one
+    /// slice per chunk. These slices are yielded consecutively, in chunk
+    /// order. Empty slices are yielded as well.
+    ///
+    /// Returns an error if the relation or the offsets are in device memory.
+    pub fn iter_partitions(&self) -> Result<PartitionedRelationPartitions<'_, T>> {
+        let (offsets, relation): (&[u64], &[T]) =
+            match ((&self.offsets).try_into(), (&self.relation).try_into()) {
+                (Ok(offsets), Ok(relation)) => (offsets, relation),
+                _ => Err(ErrorKind::RuntimeError(
+                    "Trying to dereference device memory!".to_string(),
+                ))?,
+            };
+
+        Ok(PartitionedRelationPartitions {
+            relation,
+            offsets,
+            padded_len: self.padded_len(),
+            padding_len: self.padding_len() as usize,
+            fanout: self.fanout(),
+            chunks: self.chunks,
+            partition_id: 0,
+            chunk_id: 0,
+        })
+    }
+
     /// Returns the internal representation of the relation data as a slice.
     ///
     /// This function is intended for unit testing. Use the methods provided by
@@ -496,6 +604,121 @@ impl<T: DeviceCopy> PartitionedRelation<T> {
     }
 }
 
+impl<T: DeviceCopy + FileTypeTag> PartitionedRelation<T> {
+    /// Saves the partitioned relation to a file.
+    ///
+    /// The file begins with a self-describing header, which records a tag of
+    /// the tuple type and the partitioning layout. The header is followed by
+    /// the partition offsets and the partitioned tuples, including padding.
+    ///
+    /// The tuples are written in native byte order. Thus, the file can only be
+    /// loaded on a machine with the same endianness.
+    ///
+    /// The relation and offsets must be accessible by the CPU.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let padded_len = self.padded_len();
+        let (offsets, relation): (&[u64], &[T]) =
+            match ((&self.offsets).try_into(), (&self.relation).try_into()) {
+                (Ok(offsets), Ok(relation)) => (offsets, relation),
+                _ => Err(ErrorKind::InvalidArgument(
+                    "Cannot save a partitioned relation from device memory".to_string(),
+                ))?,
+            };
+
+        let header = FileHeader::new::<T>(self.len, self.chunks, self.radix_bits);
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        header.write(&mut writer)?;
+        writer.write_all(as_bytes(offsets))?;
+        writer.write_all(as_bytes(&relation[0..padded_len]))?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Loads a partitioned relation from a file created by `save`.
+    ///
+    /// Returns an error if the file contains tuples of another type than `T`,
+    /// or if its layout differs from the layout of this build, e.g., due to
+    /// different padding. The header and the partition offsets are checked
+    /// against the file length before any memory is allocated.
+    ///
+    /// The allocation functions must return memory that is accessible by the
+    /// CPU.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        partition_alloc_fn: MemAllocFn<T>,
+        offsets_alloc_fn: MemAllocFn<u64>,
+    ) -> Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let header = FileHeader::read(&mut reader)?;
+        header.check_layout(&FileHeader::new::<T>(0, 0, 0))?;
+
+        if header.radix_bits >= u32::BITS {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Invalid number of radix bits: {}",
+                header.radix_bits
+            )))?;
+        }
+
+        let len: usize = header.len.try_into().map_err(|_| {
+            ErrorKind::IntegerOverflow("Relation length doesn't fit into usize".to_string())
+        })?;
+        let offsets_len = (fanout(header.radix_bits) as usize)
+            .checked_mul(header.chunks as usize)
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to compute the offsets length".to_string())
+            })?;
+        let padded_len = offsets_len
+            .checked_mul(header.padding_len as usize)
+            .and_then(|padding| padding.checked_add(len))
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to compute the relation length".to_string())
+            })?;
+
+        let offsets_bytes = crate::checked_alloc_bytes::<u64>(offsets_len, "Partition offsets")?;
+        let relation_bytes = crate::checked_alloc_bytes::<T>(padded_len, "Partitioned relation")?;
+        let expected_file_len = FileHeader::BYTES
+            .checked_add(offsets_bytes as u64)
+            .and_then(|bytes| bytes.checked_add(relation_bytes as u64))
+            .ok_or_else(|| {
+                ErrorKind::IntegerOverflow("Failed to compute the file length".to_string())
+            })?;
+        if file_len != expected_file_len {
+            Err(ErrorKind::InvalidArgument(format!(
+                "File has {} bytes, but the header describes {} bytes",
+                file_len, expected_file_len
+            )))?;
+        }
+
+        let mut partitioned_relation = Self {
+            relation: partition_alloc_fn(padded_len),
+            offsets: offsets_alloc_fn(offsets_len),
+            len,
+            chunks: header.chunks,
+            radix_bits: header.radix_bits,
+        };
+
+        let (offsets, relation): (&mut [u64], &mut [T]) = match (
+            (&mut partitioned_relation.offsets).try_into(),
+            (&mut partitioned_relation.relation).try_into(),
+        ) {
+            (Ok(offsets), Ok(relation)) => (offsets, relation),
+            _ => Err(ErrorKind::InvalidArgument(
+                "Cannot load a partitioned relation into device memory".to_string(),
+            ))?,
+        };
+
+        reader.read_exact(as_bytes_mut(offsets))?;
+        check_offsets(offsets, header.padding_len, padded_len)?;
+        reader.read_exact(as_bytes_mut(relation))?;
+
+        Ok(partitioned_relation)
+    }
+}
+
 impl<K: DeviceCopy, V: DeviceCopy> PartitionedRelation<Tuple<K, V>> {
     /// Returns an iterator over the chunks contained inside the relation.
     ///
@@ -504,6 +727,26 @@ impl<K: DeviceCopy, V: DeviceCopy> PartitionedRelation<Tuple<K, V>> {
     pub fn chunks_mut(&mut self) -> PartitionedRelationChunksMut<'_, Tuple<K, V>> {
         PartitionedRelationChunksMut::new(self)
     }
+
+    /// Splits an inverse permutation into the same chunks as the relation.
+    ///
+    /// `inverse` must have the same length as the relation's memory, i.e.,
+    /// including padding. Each chunk can be passed together with the
+    /// corresponding chunk of `chunks_mut` to
+    /// `CpuRadixPartitioner::partition_with_inverse`.
+    pub fn inverse_chunks_mut<'a>(&self, inverse: &'a mut [u64]) -> Result<ChunksMut<'a, u64>> {
+        if inverse.len() != self.relation.len() {
+            Err(ErrorKind::InvalidArgument(
+                "Inverse permutation and PartitionedRelation have different sizes".to_string(),
+            ))?;
+        }
+
+        let canonical_chunk_len =
+            super::partition_input_chunk::input_chunk_size::<K>(self.len(), self.num_chunks())?
+                + self.fanout() as usize * self.padding_len() as usize;
+
+        Ok(inverse.chunks_mut(canonical_chunk_len))
+    }
 }
 
 /// Returns the specified chunk and partition as a subslice of the relation.
@@ -647,6 +890,48 @@ impl<'a, T: DeviceCopy> Iterator for PartitionedRelationChunksMut<'a, T> {
     }
 }
 
+/// An iterator over the partitions of a `PartitionedRelation`.
+///
+/// Created by `PartitionedRelation::iter_partitions`.
+#[derive(Debug)]
+pub struct PartitionedRelationPartitions<'a, T> {
+    relation: &'a [T],
+    offsets: &'a [u64],
+    padded_len: usize,
+    padding_len: usize,
+    fanout: u32,
+    chunks: u32,
+    partition_id: u32,
+    chunk_id: u32,
+}
+
+impl<'a, T> Iterator for PartitionedRelationPartitions<'a, T> {
+    type Item = (usize, &'a [T]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.partition_id >= self.fanout || self.chunks == 0 {
+            return None;
+        }
+
+        let partition_id = self.partition_id;
+        let ofi = (self.chunk_id * self.fanout + partition_id) as usize;
+        let begin = self.offsets[ofi] as usize;
+        let end = if ofi + 1 < self.offsets.len() {
+            self.offsets[ofi + 1] as usize - self.padding_len
+        } else {
+            self.padded_len
+        };
+
+        self.chunk_id += 1;
+        if self.chunk_id == self.chunks {
+            self.chunk_id = 0;
+            self.partition_id += 1;
+        }
+
+        Some((partition_id as usize, &self.relation[begin..end]))
+    }
+}
+
 /// A mutable slice that references part of a `PartitionedRelation`.
 ///
 /// Effectively a mutable slice containing additional metadata about the chunk.
@@ -666,3 +951,140 @@ impl<'a, T: DeviceCopy> PartitionedRelationMutSlice<'a, T> {
         padding_len::<T>()
     }
 }
+
+/// The self-describing header of a saved `PartitionedRelation`.
+///
+/// All fields are stored in little-endian byte order.
+#[derive(Debug)]
+struct FileHeader {
+    type_tag: u32,
+    tuple_bytes: u32,
+    padding_len: u32,
+    radix_bits: u32,
+    chunks: u32,
+    len: u64,
+}
+
+impl FileHeader {
+    /// The size of the header in bytes, including the magic and the version.
+    const BYTES: u64 = 40;
+
+    fn new<T: DeviceCopy + FileTypeTag>(len: usize, chunks: u32, radix_bits: u32) -> Self {
+        Self {
+            type_tag: T::FILE_TYPE_TAG,
+            tuple_bytes: mem::size_of::<T>() as u32,
+            padding_len: padding_len::<T>(),
+            radix_bits,
+            chunks,
+            len: len as u64,
+        }
+    }
+
+    /// Checks that the header describes the same tuple type and layout as `expected`.
+    fn check_layout(&self, expected: &Self) -> Result<()> {
+        if self.type_tag != expected.type_tag || self.tuple_bytes != expected.tuple_bytes {
+            Err(ErrorKind::InvalidArgument(format!(
+                "File contains tuples with type tag {:#x} ({} bytes), but expected {:#x} ({} bytes)",
+                self.type_tag, self.tuple_bytes, expected.type_tag, expected.tuple_bytes
+            )))?;
+        }
+
+        if self.padding_len != expected.padding_len {
+            Err(ErrorKind::InvalidArgument(format!(
+                "File has a padding length of {}, but expected {}",
+                self.padding_len, expected.padding_len
+            )))?;
+        }
+
+        Ok(())
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&FILE_MAGIC)?;
+        writer.write_all(&FILE_VERSION.to_le_bytes())?;
+        writer.write_all(&self.type_tag.to_le_bytes())?;
+        writer.write_all(&self.tuple_bytes.to_le_bytes())?;
+        writer.write_all(&self.padding_len.to_le_bytes())?;
+        writer.write_all(&self.radix_bits.to_le_bytes())?;
+        writer.write_all(&self.chunks.to_le_bytes())?;
+        writer.write_all(&self.len.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut magic = [0_u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != FILE_MAGIC {
+            Err(ErrorKind::InvalidArgument(
+                "File does not contain a partitioned relation".to_string(),
+            ))?;
+        }
+
+        let version = read_u32(reader)?;
+        if version != FILE_VERSION {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Unsupported file version {}, expected version {}",
+                version, FILE_VERSION
+            )))?;
+        }
+
+        Ok(Self {
+            type_tag: read_u32(reader)?,
+            tuple_bytes: read_u32(reader)?,
+            padding_len: read_u32(reader)?,
+            radix_bits: read_u32(reader)?,
+            chunks: read_u32(reader)?,
+            len: read_u64(reader)?,
+        })
+    }
+}
+
+/// Checks that the partition offsets of a loaded file lie within the relation.
+///
+/// Each partition is preceded by its padding. Thus, consecutive offsets must be
+/// at least the padding length apart.
+fn check_offsets(offsets: &[u64], padding_len: u32, padded_len: usize) -> Result<()> {
+    let mut partition_begin = padding_len as u64;
+    for &offset in offsets {
+        if offset < partition_begin {
+            Err(ErrorKind::InvalidArgument(
+                "File contains overlapping partition offsets".to_string(),
+            ))?;
+        }
+        partition_begin = offset.saturating_add(padding_len as u64);
+    }
+
+    if offsets
+        .last()
+        .map_or(false, |&last| last > padded_len as u64)
+    {
+        Err(ErrorKind::InvalidArgument(
+            "File contains partition offsets outside of the relation".to_string(),
+        ))?;
+    }
+
+    Ok(())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut bytes = [0_u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0_u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Reinterprets a slice of plain data as bytes.
+fn as_bytes<T: DeviceCopy>(data: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, mem::size_of_val(data)) }
+}
+
+/// Reinterprets a mutable slice of plain data as bytes.
+fn as_bytes_mut<T: DeviceCopy>(data: &mut [T]) -> &mut [u8] {
+    unsafe { slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, mem::size_of_val(data)) }
+}
@@ -0,0 +1,209 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A range index for non-equi predicates.
+//!
+//! Hash tables only answer equality predicates. Range predicates, e.g.,
+//! `key BETWEEN lo AND hi`, instead require an ordered structure.
+//!
+//! `RangeIndex` radix-partitions a relation by the *high* bits of its keys,
+//! and then sorts each partition by key. In contrast to the hash join's radix
+//! partitioning on the low bits, partitioning on the high bits preserves the
+//! key order between partitions. Thus, the index is sorted as a whole.
+//!
+//! A range probe computes the partitions of the lower and upper bounds, and
+//! binary searches only within these two partitions. All partitions in between
+//! match completely.
+//!
+//! The key bits are computed relative to the smallest key in the relation.
+//! Thus, the partitions spread evenly over the key domain of the relation
+//! instead of the key domain of the type.
+//!
+//! ## Limitations
+//!
+//! `NULL` keys never satisfy a range predicate, and are therefore not inserted
+//! into the index. The index is built once and is immutable afterwards.
+
+use super::{fanout, Tuple};
+use crate::error::{ErrorKind, Result};
+use datagen::relation::KeyAttribute;
+use std::cmp::{self, Ordering};
+
+/// A sorted, radix-partitioned relation that supports range probes.
+#[derive(Debug)]
+pub struct RangeIndex<K, V> {
+    tuples: Vec<Tuple<K, V>>,
+    offsets: Vec<usize>,
+    min_key: i64,
+    max_key: i64,
+    radix_bits: u32,
+    ignore_bits: u32,
+}
+
+impl<K, V> RangeIndex<K, V>
+where
+    K: Copy + Default + Ord + Into<i64> + KeyAttribute,
+    V: Copy + Default,
+{
+    /// Builds a new range index from a key and a payload attribute.
+    ///
+    /// `radix_bits` specifies the number of high key bits with which the
+    /// relation is partitioned.
+    pub fn new(keys: &[K], payloads: &[V], radix_bits: u32) -> Result<Self> {
+        if keys.len() != payloads.len() {
+            Err(ErrorKind::InvalidArgument(
+                "Key and payload attributes have different sizes".to_string(),
+            ))?;
+        }
+        if radix_bits >= u32::BITS {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Radix bits must be less than {}",
+                u32::BITS
+            )))?;
+        }
+
+        let null_key = K::null_key();
+        let (min_key, max_key) = keys.iter().filter(|&&key| key != null_key).fold(
+            (i64::MAX, i64::MIN),
+            |(min, max), &key| {
+                let key: i64 = key.into();
+                (cmp::min(min, key), cmp::max(max, key))
+            },
+        );
+
+        // The difference always fits into an u64, even if it overflows an i64.
+        let key_range = if min_key <= max_key {
+            max_key.wrapping_sub(min_key) as u64
+        } else {
+            0
+        };
+        let key_bits = u64::BITS - key_range.leading_zeros();
+        let ignore_bits = key_bits.saturating_sub(radix_bits);
+
+        let mut index = Self {
+            tuples: Vec::new(),
+            offsets: vec![0; fanout(radix_bits) as usize + 1],
+            min_key,
+            max_key,
+            radix_bits,
+            ignore_bits,
+        };
+
+        // 1. Compute the histogram
+        let mut histogram = vec![0_usize; fanout(radix_bits) as usize];
+        keys.iter()
+            .filter(|&&key| key != null_key)
+            .for_each(|&key| histogram[index.partition_id(key.into())] += 1);
+
+        // 2. Compute the partition offsets with an exclusive prefix sum
+        histogram.iter().enumerate().for_each(|(i, &count)| {
+            index.offsets[i + 1] = index.offsets[i] + count;
+        });
+
+        // 3. Partition the tuples
+        let len = index.offsets[histogram.len()];
+        let mut write_offsets = index.offsets.clone();
+        index.tuples = vec![Tuple::default(); len];
+        keys.iter()
+            .zip(payloads.iter())
+            .filter(|&(&key, _)| key != null_key)
+            .for_each(|(&key, &value)| {
+                let offset = &mut write_offsets[index.partition_id(key.into())];
+                index.tuples[*offset] = Tuple { key, value };
+                *offset += 1;
+            });
+
+        // 4. Sort each partition
+        for window in index.offsets.windows(2) {
+            index.tuples[window[0]..window[1]].sort_unstable_by_key(|tuple| tuple.key);
+        }
+
+        Ok(index)
+    }
+
+    /// Returns all tuples with keys in the range `[lo, hi]` (inclusive).
+    ///
+    /// The tuples are sorted by key.
+    pub fn range_probe(&self, lo: K, hi: K) -> &[Tuple<K, V>] {
+        let (lo_i64, hi_i64): (i64, i64) = (lo.into(), hi.into());
+        if self.tuples.is_empty() || lo > hi || hi_i64 < self.min_key || lo_i64 > self.max_key {
+            return &[];
+        }
+
+        let lo_pid = self.partition_id(cmp::max(lo_i64, self.min_key));
+        let hi_pid = self.partition_id(cmp::min(hi_i64, self.max_key));
+
+        // Find the first tuple with `key >= lo`, and the first tuple with `key > hi`
+        let begin =
+            self.offsets[lo_pid] + Self::lower_bound(self.partition(lo_pid), |key| key < lo);
+        let end = self.offsets[hi_pid] + Self::lower_bound(self.partition(hi_pid), |key| key <= hi);
+
+        &self.tuples[begin..end]
+    }
+
+    /// Returns the number of tuples in the index.
+    pub fn len(&self) -> usize {
+        self.tuples.len()
+    }
+
+    /// Returns `true` if the index contains no tuples.
+    pub fn is_empty(&self) -> bool {
+        self.tuples.is_empty()
+    }
+
+    /// Returns the number of radix bits.
+    pub fn radix_bits(&self) -> u32 {
+        self.radix_bits
+    }
+
+    /// Returns the number of partitions.
+    pub fn fanout(&self) -> u32 {
+        fanout(self.radix_bits)
+    }
+
+    /// Returns the sorted tuples of a partition.
+    pub fn partition(&self, partition_id: usize) -> &[Tuple<K, V>] {
+        &self.tuples[self.offsets[partition_id]..self.offsets[partition_id + 1]]
+    }
+
+    /// Computes the partition ID from the high bits of the key.
+    ///
+    /// The key must be within the range `[min_key, max_key]`.
+    fn partition_id(&self, key: i64) -> usize {
+        let relative_key = key.wrapping_sub(self.min_key) as u64;
+
+        // Shifting an u64 by 64 bits overflows, thus handle the case separately
+        if self.ignore_bits >= u64::BITS {
+            0
+        } else {
+            (relative_key >> self.ignore_bits) as usize
+        }
+    }
+
+    /// Returns the index of the first tuple for which `is_before` is `false`.
+    fn lower_bound<F>(tuples: &[Tuple<K, V>], is_before: F) -> usize
+    where
+        F: Fn(K) -> bool,
+    {
+        tuples
+            .binary_search_by(|tuple| {
+                if is_before(tuple.key) {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            })
+            .unwrap_or_else(|index| index)
+    }
+}
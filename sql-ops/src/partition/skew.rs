@@ -0,0 +1,270 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Skew mitigation for radix-partitioned relations.
+//!
+//! Radix partitioning assigns all tuples with the same key to the same
+//! partition. Thus, a heavy-hitter key results in one partition that is much
+//! larger than the others, and the per-partition processing that follows
+//! becomes unbalanced.
+//!
+//! Salting splits a heavy partition into several sub-partitions. The tuples of
+//! the heavy partition are distributed round-robin over its sub-partitions,
+//! which makes the sub-partitions about equally large. Each sub-partition is
+//! then processed as a virtual partition on its own. The sub-partitions are
+//! generated while iterating over the partition, thus salting doesn't copy
+//! the relation.
+//!
+//! In a join, only the probe side is salted. Each probe sub-partition is
+//! joined with the whole build partition. Thus, the result of a heavy
+//! partition is the sum over the results of its sub-partitions, which
+//! `SaltMap::merge` computes.
+
+use super::{PartitionedRelation, Tuple};
+use crate::error::{ErrorKind, Result};
+use crate::prefix_scan::exclusive_prefix_sum;
+use rustacuda::memory::DeviceCopy;
+use std::ops::Range;
+
+/// Specifies how the partitioner handles skewed partitions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SkewPolicy {
+    /// Keep all partitions as they are.
+    None,
+
+    /// Split heavy partitions into sub-partitions.
+    ///
+    /// A partition is heavy if it has more than `threshold` times the number of
+    /// tuples of an average partition. A heavy partition is split into as many
+    /// sub-partitions as required for each sub-partition to hold about the
+    /// average number of tuples. The `threshold` must be at least 1.
+    SaltHeavy { threshold: f64 },
+}
+
+impl Default for SkewPolicy {
+    fn default() -> Self {
+        SkewPolicy::None
+    }
+}
+
+impl SkewPolicy {
+    /// Computes the salt map from the histogram of a partitioned relation.
+    ///
+    /// The histogram contains the number of tuples in each partition, e.g., as
+    /// returned by `PartitionOffsets::histogram_and_offsets`.
+    pub fn salt_map(&self, histogram: &[u64]) -> Result<SaltMap> {
+        let sub_partitions = match *self {
+            SkewPolicy::None => vec![1; histogram.len()],
+            SkewPolicy::SaltHeavy { threshold } => {
+                if !(threshold >= 1.0) {
+                    Err(ErrorKind::InvalidArgument(format!(
+                        "Skew threshold must be at least 1, got {}",
+                        threshold
+                    )))?;
+                }
+
+                let total: u64 = histogram.iter().sum();
+                let average = (total as f64 / histogram.len().max(1) as f64).max(1.0);
+
+                histogram
+                    .iter()
+                    .map(|&len| {
+                        if len as f64 > threshold * average {
+                            (len as f64 / average).ceil() as u32
+                        } else {
+                            1
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(SaltMap::new(sub_partitions))
+    }
+}
+
+/// Maps the virtual partitions produced by salting to the radix partitions.
+///
+/// Virtual partitions are numbered consecutively. A partition that is not
+/// salted has exactly one virtual partition, a salted partition has one
+/// virtual partition per sub-partition.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SaltMap {
+    sub_partitions: Vec<u32>,
+    first_virtual_partition: Vec<u32>,
+}
+
+impl SaltMap {
+    fn new(sub_partitions: Vec<u32>) -> Self {
+        let mut first_virtual_partition = sub_partitions.clone();
+        exclusive_prefix_sum(&mut first_virtual_partition);
+
+        Self {
+            sub_partitions,
+            first_virtual_partition,
+        }
+    }
+
+    /// Returns the number of radix partitions.
+    pub fn fanout(&self) -> u32 {
+        self.sub_partitions.len() as u32
+    }
+
+    /// Returns the total number of virtual partitions.
+    pub fn num_virtual_partitions(&self) -> u32 {
+        self.sub_partitions.iter().sum()
+    }
+
+    /// Returns the number of sub-partitions of a radix partition.
+    pub fn sub_partitions(&self, partition_id: u32) -> u32 {
+        self.sub_partitions[partition_id as usize]
+    }
+
+    /// Returns `true` if the radix partition is split into sub-partitions.
+    pub fn is_salted(&self, partition_id: u32) -> bool {
+        self.sub_partitions(partition_id) > 1
+    }
+
+    /// Returns the IDs of the salted radix partitions.
+    pub fn salted_partitions(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.fanout()).filter(move |&partition_id| self.is_salted(partition_id))
+    }
+
+    /// Returns the virtual partition IDs of a radix partition.
+    pub fn virtual_partitions(&self, partition_id: u32) -> Range<u32> {
+        let first = self.first_virtual_partition[partition_id as usize];
+        first..(first + self.sub_partitions(partition_id))
+    }
+
+    /// Returns the radix partition ID and the salt of a virtual partition.
+    pub fn partition_of(&self, virtual_partition_id: u32) -> Result<(u32, u32)> {
+        if virtual_partition_id >= self.num_virtual_partitions() {
+            Err(ErrorKind::InvalidArgument(
+                "Invalid virtual partition ID".to_string(),
+            ))?;
+        }
+
+        // Each partition has at least one virtual partition, thus the first
+        // virtual partition IDs are strictly increasing
+        let partition_id = match self
+            .first_virtual_partition
+            .binary_search(&virtual_partition_id)
+        {
+            Ok(id) => id,
+            Err(id) => id - 1,
+        };
+        let salt = virtual_partition_id - self.first_virtual_partition[partition_id];
+
+        Ok((partition_id as u32, salt))
+    }
+
+    /// Merges the per-virtual-partition results into per-partition results.
+    ///
+    /// The results of the sub-partitions of a salted partition are summed up.
+    pub fn merge(&self, virtual_results: &[u64]) -> Result<Vec<u64>> {
+        if virtual_results.len() != self.num_virtual_partitions() as usize {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Expected {} virtual partition results, got {}",
+                self.num_virtual_partitions(),
+                virtual_results.len()
+            )))?;
+        }
+
+        let results = (0..self.fanout())
+            .map(|partition_id| {
+                let range = self.virtual_partitions(partition_id);
+                virtual_results[range.start as usize..range.end as usize]
+                    .iter()
+                    .sum()
+            })
+            .collect();
+
+        Ok(results)
+    }
+}
+
+/// The virtual partitions of a salted partitioned relation.
+///
+/// Salting doesn't copy any tuples. Instead, each sub-partition is generated
+/// on demand by iterating over the tuples of its radix partition, and taking
+/// every n-th tuple. Thus, a partition that is not salted is its own and only
+/// sub-partition.
+#[derive(Debug)]
+pub struct SaltedPartitions<'a, T: DeviceCopy> {
+    salt_map: SaltMap,
+    partitioned_relation: &'a PartitionedRelation<T>,
+}
+
+impl<'a, K, V> SaltedPartitions<'a, Tuple<K, V>>
+where
+    K: DeviceCopy,
+    V: DeviceCopy,
+{
+    /// Computes the virtual partitions of a partitioned relation, as specified
+    /// by `policy`.
+    ///
+    /// The relation must be accessible by the CPU.
+    pub fn new(
+        policy: SkewPolicy,
+        partitioned_relation: &'a PartitionedRelation<Tuple<K, V>>,
+    ) -> Result<Self> {
+        let histogram = (0..partitioned_relation.fanout())
+            .map(|partition_id| {
+                partitioned_relation
+                    .partition_len(partition_id)
+                    .map(|len| len as u64)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let salt_map = policy.salt_map(&histogram)?;
+
+        Ok(Self {
+            salt_map,
+            partitioned_relation,
+        })
+    }
+}
+
+impl<'a, T: DeviceCopy> SaltedPartitions<'a, T> {
+    /// Returns the salt map that describes the virtual partitions.
+    pub fn salt_map(&self) -> &SaltMap {
+        &self.salt_map
+    }
+
+    /// Returns the tuples of a sub-partition.
+    ///
+    /// The tuples of a radix partition are assigned round-robin to its
+    /// sub-partitions. Returns `None` if the partition ID or the salt is out of
+    /// range.
+    pub fn sub_partition(
+        &self,
+        partition_id: u32,
+        salt: u32,
+    ) -> Option<impl Iterator<Item = &'a T> + 'a> {
+        if partition_id >= self.salt_map.fanout() {
+            return None;
+        }
+        let sub_partitions = self.salt_map.sub_partitions(partition_id);
+        if salt >= sub_partitions {
+            return None;
+        }
+
+        let partitioned_relation = self.partitioned_relation;
+        let tuples = (0..partitioned_relation.num_chunks())
+            .flat_map(move |chunk_id| partitioned_relation[(chunk_id, partition_id)].iter())
+            .skip(salt as usize)
+            .step_by(sub_partitions as usize);
+
+        Some(tuples)
+    }
+}
@@ -14,6 +14,8 @@
 
 //! A collection of prefix scan operators.
 
+mod cpu_prefix_scan;
 mod gpu_prefix_scan;
 
-pub use gpu_prefix_scan::{GpuPrefixScanState, GpuPrefixSum};
+pub use cpu_prefix_scan::exclusive_prefix_sum;
+pub use gpu_prefix_scan::{GpuPrefixScanState, GpuPrefixScannable, GpuPrefixSum};
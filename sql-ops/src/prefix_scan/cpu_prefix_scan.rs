@@ -0,0 +1,32 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::AddAssign;
+
+/// Computes the exclusive prefix sum of `data` in place on the CPU.
+///
+/// Each value is replaced by the sum of all values before it. Thus, the first
+/// value becomes zero.
+///
+/// Returns the sum of all values.
+pub fn exclusive_prefix_sum<T: Copy + Default + AddAssign>(data: &mut [T]) -> T {
+    let mut sum = T::default();
+    for value in data.iter_mut() {
+        let item = *value;
+        *value = sum;
+        sum += item;
+    }
+
+    sum
+}
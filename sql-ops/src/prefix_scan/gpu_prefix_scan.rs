@@ -12,11 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::error::Result;
+use crate::error::{ErrorKind, Result};
+use numa_gpu::runtime::memory::{LaunchableMem, LaunchableMutSlice};
 use rustacuda::context::CurrentContext;
 use rustacuda::device::DeviceAttribute;
 use rustacuda::function::{BlockSize, GridSize};
-use rustacuda::memory::DeviceCopy;
+use rustacuda::launch;
+use rustacuda::memory::{DeviceBuffer, DeviceCopy};
+use rustacuda::stream::Stream;
+use std::ffi::CString;
+use std::mem;
 
 #[repr(C)]
 #[allow(unused)]
@@ -50,3 +55,172 @@ impl GpuPrefixSum {
         Ok((gs.x as usize * bs.x as usize) / warp_size + warp_size)
     }
 }
+
+impl GpuPrefixSum {
+    /// The number of threads per block of `exclusive_scan`.
+    ///
+    /// Each block scans a tile of `2 * BLOCK_SIZE` values.
+    pub const BLOCK_SIZE: u32 = 512;
+
+    /// The maximum number of values that `exclusive_scan` supports.
+    ///
+    /// The first level launches one block per tile, and thus the length is
+    /// limited by the maximum grid size of `2^31 - 1` blocks. With the default
+    /// block size, that's about 2.2 trillion values, which exceeds the memory
+    /// of current GPUs.
+    pub const MAX_SCAN_LEN: u64 = ((1_u64 << 31) - 1) * 2 * Self::BLOCK_SIZE as u64;
+
+    /// Computes the exclusive prefix sum of `data` in place.
+    ///
+    /// In contrast to the decoupled look-back prefix sum, the scan processes
+    /// arrays of any length up to `MAX_SCAN_LEN` with multiple levels. Each
+    /// block scans its tile locally, then the tile sums are scanned
+    /// recursively, and finally each tile adds its prefix. Each level shrinks
+    /// the input by a factor of `2 * BLOCK_SIZE`, thus a billion values take
+    /// three levels.
+    ///
+    /// The scan is launched on `stream`, and the function blocks until the
+    /// scan has completed.
+    pub fn exclusive_scan<T: GpuPrefixScannable>(
+        mut data: LaunchableMutSlice<'_, T>,
+        stream: &Stream,
+    ) -> Result<()> {
+        if data.len() as u64 > Self::MAX_SCAN_LEN {
+            Err(ErrorKind::InvalidArgument(format!(
+                "The prefix scan supports at most {} values",
+                Self::MAX_SCAN_LEN
+            )))?;
+        }
+
+        if data.len() == 0 {
+            return Ok(());
+        }
+
+        // Allocate the tile sums of all levels before the first launch, so
+        // that the levels run back-to-back on the stream. The last level
+        // holds the total sum.
+        let mut levels: Vec<DeviceBuffer<T>> = Vec::new();
+        let mut len = data.len();
+        while len > 1 || levels.is_empty() {
+            len = Self::tiles(len);
+            levels.push(unsafe { DeviceBuffer::uninitialized(len)? });
+        }
+
+        // Scan the tiles bottom-up. The tile sums of each level are the input
+        // of the next level
+        T::scan_tiles(&mut data, &mut levels[0], stream)?;
+        for i in 1..levels.len() {
+            let (inputs, outputs) = levels.split_at_mut(i);
+            T::scan_tiles(
+                &mut inputs[i - 1].as_launchable_mut_slice(),
+                &mut outputs[0],
+                stream,
+            )?;
+        }
+
+        // Add the prefixes top-down. A level's input is complete once the
+        // level above has added its prefixes. The top level is a single tile,
+        // and thus needs no prefix
+        for i in (1..levels.len().saturating_sub(1)).rev() {
+            let (inputs, prefixes) = levels.split_at_mut(i);
+            T::add_tile_prefixes(
+                &mut inputs[i - 1].as_launchable_mut_slice(),
+                &prefixes[0],
+                stream,
+            )?;
+        }
+        if levels.len() > 1 {
+            T::add_tile_prefixes(&mut data, &levels[0], stream)?;
+        }
+
+        stream.synchronize()?;
+
+        Ok(())
+    }
+
+    /// Returns the number of tiles for an input of `len` values.
+    fn tiles(len: usize) -> usize {
+        let values_per_tile = 2 * Self::BLOCK_SIZE as usize;
+        (len + values_per_tile - 1) / values_per_tile
+    }
+}
+
+/// A value type that the GPU can scan with `GpuPrefixSum::exclusive_scan`.
+pub trait GpuPrefixScannable: Copy + Default + DeviceCopy + Sized {
+    /// Scans each tile of `data` in place, and writes the tile sums.
+    fn scan_tiles(
+        data: &mut LaunchableMutSlice<'_, Self>,
+        tile_sums: &mut DeviceBuffer<Self>,
+        stream: &Stream,
+    ) -> Result<()>;
+
+    /// Adds the scanned tile sums to the values of each tile.
+    fn add_tile_prefixes(
+        data: &mut LaunchableMutSlice<'_, Self>,
+        tile_prefixes: &DeviceBuffer<Self>,
+        stream: &Stream,
+    ) -> Result<()>;
+}
+
+macro_rules! impl_gpu_prefix_scannable {
+    ($Type:ty, $Suffix:expr) => {
+        paste::item! {
+            impl GpuPrefixScannable for $Type {
+                fn scan_tiles(
+                    data: &mut LaunchableMutSlice<'_, Self>,
+                    tile_sums: &mut DeviceBuffer<Self>,
+                    stream: &Stream,
+                ) -> Result<()> {
+                    let module = crate::module()?;
+                    let name = CString::new(stringify!([<gpu_scan_tiles_ $Suffix>])).unwrap();
+                    let function = crate::module_function(module, &name)?;
+
+                    let grid_size = tile_sums.len() as u32;
+                    let block_size = GpuPrefixSum::BLOCK_SIZE;
+                    let shared_mem_bytes = 2 * block_size * mem::size_of::<Self>() as u32;
+
+                    unsafe {
+                        launch!(
+                            function<<<grid_size, block_size, shared_mem_bytes, stream>>>(
+                                data.as_launchable_mut_ptr(),
+                                data.len() as u64,
+                                tile_sums.as_device_ptr()
+                            )
+                        )?;
+                    }
+
+                    Ok(())
+                }
+
+                fn add_tile_prefixes(
+                    data: &mut LaunchableMutSlice<'_, Self>,
+                    tile_prefixes: &DeviceBuffer<Self>,
+                    stream: &Stream,
+                ) -> Result<()> {
+                    let module = crate::module()?;
+                    let name =
+                        CString::new(stringify!([<gpu_scan_add_tile_prefixes_ $Suffix>])).unwrap();
+                    let function = crate::module_function(module, &name)?;
+
+                    let grid_size = tile_prefixes.len() as u32;
+                    let block_size = GpuPrefixSum::BLOCK_SIZE;
+
+                    unsafe {
+                        launch!(
+                            function<<<grid_size, block_size, 0, stream>>>(
+                                data.as_launchable_mut_ptr(),
+                                data.len() as u64,
+                                tile_prefixes.as_launchable_ptr()
+                            )
+                        )?;
+                    }
+
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+impl_gpu_prefix_scannable!(u32, uint32);
+impl_gpu_prefix_scannable!(u64, uint64);
@@ -0,0 +1,154 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic sum reduction on the GPU.
+//!
+//! The GPU probe adds its partial aggregates to the result set either with
+//! atomics or per thread (see `CountGranularity`). Atomics add the partials
+//! in the order in which the threads happen to be scheduled. Integer addition
+//! is associative, thus the order doesn't matter for counts. In contrast,
+//! floating-point addition rounds after each step, and thus a different order
+//! can yield a slightly different sum in each run.
+//!
+//! `GpuTreeSum` instead sums the per-thread partials in a fixed pairwise tree.
+//! The shape of the tree depends only on the number of partials, and thus
+//! the sum is bitwise reproducible from run to run.
+//!
+//! ## Performance
+//!
+//! The tree sum costs more than a global atomic:
+//!
+//! - The probe must write one partial per thread, i.e., `grid size * block
+//!   size` values, instead of updating a single value.
+//! - The reduction reads all partials again, and launches one kernel per tree
+//!   level. Each level shrinks the input by a factor of `2 * BLOCK_SIZE`, thus
+//!   a million partials take three launches.
+//!
+//! For a typical probe, the partials are a few megabytes, and the reduction
+//! is negligible compared to the probe. But for small inputs, the launch
+//! latency of the additional kernels dominates. Thus, use the tree sum only
+//! if reproducible sums are required.
+
+use crate::error::{ErrorKind, Result};
+use numa_gpu::runtime::memory::{LaunchableMem, LaunchableSlice};
+use rustacuda::launch;
+use rustacuda::memory::{CopyDestination, DeviceBuffer, DeviceCopy};
+use rustacuda::stream::Stream;
+use std::convert::TryInto;
+use std::ffi::CString;
+use std::mem;
+
+/// Sums values on the GPU in a deterministic order.
+pub struct GpuTreeSum;
+
+impl GpuTreeSum {
+    /// The number of threads per block.
+    ///
+    /// The block size determines the shape of the tree, and is therefore
+    /// fixed. Each block sums `2 * BLOCK_SIZE` values.
+    pub const BLOCK_SIZE: u32 = 256;
+
+    /// Returns the sum of `data`.
+    ///
+    /// The reduction is launched on `stream`, and the function blocks until
+    /// the sum is available. An empty input sums to zero.
+    pub fn sum<T: GpuTreeSummable>(data: LaunchableSlice<'_, T>, stream: &Stream) -> Result<T> {
+        if data.len() == 0 {
+            return Ok(T::default());
+        }
+
+        // Allocate all tree levels before the first launch, so that the
+        // levels run back-to-back on the stream
+        let mut levels: Vec<DeviceBuffer<T>> = Vec::new();
+        let mut len = data.len();
+        while len > 1 || levels.is_empty() {
+            len = Self::level_len(len);
+            levels.push(unsafe { DeviceBuffer::uninitialized(len)? });
+        }
+
+        T::tree_sum_level(data, &mut levels[0], stream)?;
+        for i in 1..levels.len() {
+            let (inputs, outputs) = levels.split_at_mut(i);
+            T::tree_sum_level(inputs[i - 1].as_launchable_slice(), &mut outputs[0], stream)?;
+        }
+
+        stream.synchronize()?;
+
+        let mut sum = [T::default()];
+        levels
+            .last()
+            .expect("The tree has at least one level")
+            .copy_to(&mut sum[..])?;
+
+        Ok(sum[0])
+    }
+
+    /// Returns the number of block sums for an input of `len` values.
+    fn level_len(len: usize) -> usize {
+        let values_per_block = 2 * Self::BLOCK_SIZE as usize;
+        (len + values_per_block - 1) / values_per_block
+    }
+}
+
+/// A value type that the GPU can sum with `GpuTreeSum`.
+pub trait GpuTreeSummable: Copy + Default + DeviceCopy + Sized {
+    /// Launches one level of the tree, and writes one sum per block.
+    fn tree_sum_level(
+        data: LaunchableSlice<'_, Self>,
+        block_sums: &mut DeviceBuffer<Self>,
+        stream: &Stream,
+    ) -> Result<()>;
+}
+
+macro_rules! impl_gpu_tree_summable {
+    ($Type:ty, $Suffix:expr) => {
+        paste::item! {
+            impl GpuTreeSummable for $Type {
+                fn tree_sum_level(
+                    data: LaunchableSlice<'_, Self>,
+                    block_sums: &mut DeviceBuffer<Self>,
+                    stream: &Stream,
+                ) -> Result<()> {
+                    let module = crate::module()?;
+                    let name = CString::new(stringify!([<gpu_tree_sum_ $Suffix>])).unwrap();
+                    let function = crate::module_function(module, &name)?;
+
+                    let grid_size: u32 = block_sums.len().try_into().map_err(|_| {
+                        ErrorKind::IntegerOverflow(
+                            "Tree sum input exceeds the maximum grid size".to_string(),
+                        )
+                    })?;
+                    let block_size = GpuTreeSum::BLOCK_SIZE;
+                    let shared_mem_bytes = block_size * mem::size_of::<Self>() as u32;
+
+                    unsafe {
+                        launch!(
+                            function<<<grid_size, block_size, shared_mem_bytes, stream>>>(
+                                data.as_launchable_ptr(),
+                                data.len() as u64,
+                                block_sums.as_device_ptr()
+                            )
+                        )?;
+                    }
+
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+impl_gpu_tree_summable!(f32, float);
+impl_gpu_tree_summable!(f64, double);
+impl_gpu_tree_summable!(u64, uint64);
@@ -0,0 +1,220 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that the CPU and GPU hash joins produce the same join result.
+//!
+//! Both joins materialize their matches, which are compared as multisets.
+//! The GPU writes its matches in the order of the thread schedule, thus both
+//! results are sorted before they are compared. Materializing probes support
+//! only `LinearProbing`; other hashing schemes return the join's error.
+
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::memory::LaunchableMem;
+use rustacuda::function::{BlockSize, GridSize};
+use rustacuda::memory::DeviceCopy;
+use rustacuda::stream::{Stream, StreamFlags};
+use sql_ops::join::no_partitioning_join::{
+    CpuHashJoinBuilder, CpuHashJoinable, CudaHashJoinBuilder, CudaHashJoinable, HashTable,
+    JoinMatch,
+};
+use sql_ops::join::HashingScheme;
+use std::error::Error;
+use std::fmt::Debug;
+use std::result::Result;
+use std::sync::Arc;
+
+/// The build and probe relations of a join.
+#[derive(Clone, Debug, Default)]
+pub struct JoinRelations<T> {
+    pub build_key: Vec<T>,
+    pub build_pay: Vec<T>,
+    pub probe_key: Vec<T>,
+    pub probe_pay: Vec<T>,
+}
+
+impl<T> JoinRelations<T> {
+    /// Returns a hash table length with room for all build tuples.
+    fn hash_table_len(&self) -> usize {
+        (self.build_key.len() * 2).next_power_of_two().max(1024)
+    }
+}
+
+/// Joins the relations on the CPU and returns the materialized matches.
+pub fn cpu_join_matches<T>(
+    relations: &JoinRelations<T>,
+    hashing_scheme: HashingScheme,
+) -> Result<Vec<JoinMatch<T>>, Box<dyn Error>>
+where
+    T: Copy + Default + DeviceCopy + CpuHashJoinable,
+{
+    let ht_len = relations.hash_table_len();
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, ht_len);
+    let hash_table = HashTable::new_on_cpu(ht_mem, ht_len)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(hashing_scheme)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build(&relations.build_key, &relations.build_pay)?;
+
+    let mut join_result = vec![JoinMatch::default(); relations.probe_key.len()];
+    let matches =
+        hj_op.probe_materialize(&relations.probe_key, &relations.probe_pay, &mut join_result)?;
+    assert!(
+        matches <= join_result.len(),
+        "Join result has more matches than probe tuples"
+    );
+    join_result.truncate(matches);
+
+    Ok(join_result)
+}
+
+/// Joins the relations on the GPU and returns the materialized matches.
+///
+/// Requires a current CUDA context.
+pub fn gpu_join_matches<T>(
+    relations: &JoinRelations<T>,
+    hashing_scheme: HashingScheme,
+) -> Result<Vec<JoinMatch<T>>, Box<dyn Error>>
+where
+    T: Copy + Default + DeviceCopy + CudaHashJoinable,
+{
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<T>(DerefMemType::CudaUniMem);
+    let copy_to_gpu = |attr: &[T]| {
+        let mut mem = alloc_fn(attr.len());
+        mem.copy_from_slice(attr);
+        mem
+    };
+
+    let build_key = copy_to_gpu(&relations.build_key);
+    let build_pay = copy_to_gpu(&relations.build_pay);
+    let probe_key = copy_to_gpu(&relations.probe_key);
+    let probe_pay = copy_to_gpu(&relations.probe_pay);
+
+    let ht_len = relations.hash_table_len();
+    let ht_mem = Allocator::alloc_mem(MemType::CudaUniMem, ht_len);
+    let hash_table = HashTable::new_on_gpu(ht_mem, ht_len)?;
+
+    let hj_op = CudaHashJoinBuilder::<T>::default()
+        .hashing_scheme(hashing_scheme)
+        .build_dim(GridSize::from(4), BlockSize::from(128))
+        .probe_dim(GridSize::from(4), BlockSize::from(128))
+        .hash_table(Arc::new(hash_table))
+        .build()?;
+
+    hj_op.build(
+        build_key.as_launchable_slice(),
+        build_pay.as_launchable_slice(),
+        &stream,
+    )?;
+
+    let join_result =
+        Allocator::alloc_mem::<JoinMatch<T>>(MemType::CudaUniMem, relations.probe_key.len());
+    let matches = hj_op.probe_materialize(
+        probe_key.as_launchable_slice(),
+        probe_pay.as_launchable_slice(),
+        &join_result,
+        &stream,
+    )?;
+    assert!(
+        matches <= join_result.len(),
+        "Join result has more matches than probe tuples"
+    );
+
+    Ok(join_result.try_as_slice()?[..matches].to_vec())
+}
+
+/// Returns a description of the first difference between two join results,
+/// or `None` if the results are equal multisets.
+///
+/// The results are sorted by the build payload, and then by the probe
+/// payload. The first difference is reported in this order, with matches
+/// formatted as `(build_payload, probe_payload)`.
+pub fn first_difference<T>(cpu: &[JoinMatch<T>], gpu: &[JoinMatch<T>]) -> Option<String>
+where
+    T: Copy + Debug + Ord,
+{
+    let sorted = |matches: &[JoinMatch<T>]| {
+        let mut pairs: Vec<_> = matches
+            .iter()
+            .map(|m| (m.build_payload, m.probe_payload))
+            .collect();
+        pairs.sort_unstable();
+        pairs
+    };
+
+    let cpu = sorted(cpu);
+    let gpu = sorted(gpu);
+
+    if let Some((i, (c, g))) = cpu
+        .iter()
+        .zip(gpu.iter())
+        .enumerate()
+        .find(|(_, (c, g))| c != g)
+    {
+        return Some(format!(
+            "Sorted join results differ at match {}: CPU has {:?}, GPU has {:?}",
+            i, c, g
+        ));
+    }
+
+    if cpu.len() != gpu.len() {
+        let (longer, extra) = if cpu.len() > gpu.len() {
+            ("CPU", cpu[gpu.len()])
+        } else {
+            ("GPU", gpu[cpu.len()])
+        };
+        return Some(format!(
+            "CPU has {} and GPU has {} matches, first surplus {} match is {:?}",
+            cpu.len(),
+            gpu.len(),
+            longer,
+            extra
+        ));
+    }
+
+    None
+}
+
+/// Asserts that two join results are equal multisets.
+///
+/// Panics with the first difference.
+pub fn assert_matches_equivalent<T>(cpu: &[JoinMatch<T>], gpu: &[JoinMatch<T>])
+where
+    T: Copy + Debug + Ord,
+{
+    if let Some(difference) = first_difference(cpu, gpu) {
+        panic!("{}", difference);
+    }
+}
+
+/// Asserts that the CPU and GPU joins produce the same join result.
+///
+/// Requires a current CUDA context.
+pub fn assert_join_equivalent<T>(
+    relations: &JoinRelations<T>,
+    hashing_scheme: HashingScheme,
+) -> Result<(), Box<dyn Error>>
+where
+    T: Copy + Debug + Default + DeviceCopy + Ord + CpuHashJoinable + CudaHashJoinable,
+{
+    let cpu = cpu_join_matches(relations, hashing_scheme)?;
+    let gpu = gpu_join_matches(relations, hashing_scheme)?;
+
+    assert_matches_equivalent(&cpu, &gpu);
+
+    Ok(())
+}
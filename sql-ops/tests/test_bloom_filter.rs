@@ -0,0 +1,114 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use sql_ops::join::bloom_filter::{self, BloomFilter, BloomFilterConfig};
+use sql_ops::join::no_partitioning_join::probe_bitmap_len;
+use std::error::Error;
+use std::result::Result;
+
+const BUILD_KEYS: usize = 1 << 14;
+const PROBE_KEYS: usize = 4 * BUILD_KEYS;
+
+/// Builds a filter over `[0, BUILD_KEYS)`, probes `[0, PROBE_KEYS)`, and
+/// returns the measured FPR.
+fn measure_fpr(config: BloomFilterConfig) -> Result<f64, Box<dyn Error>> {
+    let build_keys: Vec<i32> = (0..BUILD_KEYS as i32).collect();
+    let probe_keys: Vec<i32> = (0..PROBE_KEYS as i32).collect();
+
+    let mut filter = BloomFilter::new(config, BUILD_KEYS)?;
+    filter.insert(&build_keys);
+
+    let mut filter_bitmap = vec![0; probe_bitmap_len(PROBE_KEYS)];
+    let passed = filter.probe_bitmap(&probe_keys, &mut filter_bitmap)?;
+    assert!(passed >= BUILD_KEYS);
+
+    // The first BUILD_KEYS probe keys have a match
+    let mut match_bitmap = vec![0; probe_bitmap_len(PROBE_KEYS)];
+    match_bitmap[..BUILD_KEYS / 64]
+        .iter_mut()
+        .for_each(|word| *word = u64::MAX);
+
+    // A Bloom filter has no false negatives
+    assert!(filter_bitmap
+        .iter()
+        .zip(match_bitmap.iter())
+        .all(|(&passed, &matched)| passed & matched == matched));
+
+    let fpr = bloom_filter::false_positive_rate(&filter_bitmap, &match_bitmap, PROBE_KEYS)?;
+    assert_eq!(
+        fpr,
+        (passed - BUILD_KEYS) as f64 / (PROBE_KEYS - BUILD_KEYS) as f64
+    );
+
+    Ok(fpr)
+}
+
+#[test]
+fn bloom_filter_fpr_decreases_with_bits_per_key() -> Result<(), Box<dyn Error>> {
+    let fprs = [2, 4, 8, 16]
+        .iter()
+        .map(|&bits_per_key| {
+            measure_fpr(BloomFilterConfig {
+                bits_per_key,
+                num_hashes: 3,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    assert!(
+        fprs.windows(2).all(|w| w[0] > w[1]),
+        "FPRs not decreasing: {:?}",
+        fprs
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bloom_filter_fpr_is_close_to_estimate() -> Result<(), Box<dyn Error>> {
+    let config = BloomFilterConfig::with_optimal_hashes(10);
+    assert_eq!(config.num_hashes, 7);
+
+    let measured = measure_fpr(config)?;
+    let estimated = config.estimated_fpr();
+
+    assert!(
+        (measured - estimated).abs() < 0.01,
+        "Measured FPR {} differs from estimated FPR {}",
+        measured,
+        estimated
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bloom_filter_rejects_empty_config() {
+    assert!(BloomFilter::new(
+        BloomFilterConfig {
+            bits_per_key: 0,
+            num_hashes: 1
+        },
+        BUILD_KEYS
+    )
+    .is_err());
+    assert!(BloomFilter::new(
+        BloomFilterConfig {
+            bits_per_key: 8,
+            num_hashes: 0
+        },
+        BUILD_KEYS
+    )
+    .is_err());
+}
@@ -0,0 +1,1377 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests that CPU operators run without CUDA.
+//!
+//! None of the tests in this file initialize CUDA or create a CUDA context.
+//! Thus, the tests behave the same on machines with and without a GPU.
+
+use data_store::relation::Relation;
+use datagen::relation::{KeyAttribute, UniformRelation};
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType};
+use numa_gpu::runtime::linux_wrapper;
+use numa_gpu::runtime::memory::Mem;
+use numa_gpu::runtime::numa;
+use sql_ops::error::ErrorKind;
+use sql_ops::join::no_partitioning_join::{
+    probe_bitmap_len, BuildReport, CpuHashFn, CpuHashJoinBuilder, CudaHashJoinBuilder, HashTable,
+    JoinMatch, MaterializeResult,
+};
+use sql_ops::join::validity::ValidityBitmap;
+use sql_ops::join::{HashTableLayout, HashingScheme, ProbeStep};
+use sql_ops::FileTypeTag;
+use std::error::Error;
+use std::result::Result;
+use std::sync::Arc;
+
+#[test]
+fn cpu_hash_join_without_cuda() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 16;
+    const HT_LEN: usize = 2 * ROWS;
+
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::SysMem);
+
+    let mut inner_rel_key = alloc_fn(ROWS);
+    let mut inner_rel_pay = alloc_fn(ROWS);
+    let mut outer_rel_key = alloc_fn(ROWS);
+    let mut outer_rel_pay = alloc_fn(ROWS);
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    inner_rel_pay
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i + 1) as i32);
+    outer_rel_pay
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i + 1) as i32);
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+
+    let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2;
+    assert_eq!(expected_sum, result_sum);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_with_relation() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 12;
+    const HT_LEN: usize = 2 * ROWS;
+
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i64>(DerefMemType::SysMem);
+
+    let mut inner_rel_key = alloc_fn(ROWS);
+    let mut inner_rel_pay = alloc_fn(ROWS);
+    let mut outer_rel_key = alloc_fn(ROWS);
+    let mut outer_rel_pay = alloc_fn(ROWS);
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+    outer_rel_pay
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i + 1) as i64);
+
+    let inner_rel = Relation::new(Mem::from(inner_rel_key), Mem::from(inner_rel_pay))?;
+    let outer_rel = Relation::new(Mem::from(outer_rel_key), Mem::from(outer_rel_pay))?;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build_relation(&inner_rel)?;
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum_relation(&outer_rel, &mut result_sum)?;
+
+    let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2;
+    assert_eq!(expected_sum, result_sum);
+
+    Ok(())
+}
+
+/// Joins a primary key and a foreign key relation with a payload of one, and
+/// returns the result sum of each probe thread.
+///
+/// With a payload of one, the sum equals the number of matches.
+fn multi_threaded_join(
+    rows: usize,
+    build_threads: usize,
+    probe_threads: usize,
+) -> Result<Vec<u64>, Box<dyn Error>> {
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::SysMem);
+
+    let mut inner_rel_key = alloc_fn(rows);
+    let mut inner_rel_pay = alloc_fn(rows);
+    let mut outer_rel_key = alloc_fn(rows);
+    let mut outer_rel_pay = alloc_fn(rows);
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+    outer_rel_pay.iter_mut().for_each(|x| *x = 1);
+
+    let ht_len = 2 * rows;
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, ht_len);
+    let hash_table = HashTable::new_on_cpu(ht_mem, ht_len)?;
+
+    let hj_builder = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table));
+
+    let build_chunk_size = (rows + build_threads - 1) / build_threads;
+    rayon::scope(|s| {
+        for (key, pay) in inner_rel_key
+            .chunks(build_chunk_size)
+            .zip(inner_rel_pay.chunks(build_chunk_size))
+        {
+            let mut hj_op = hj_builder.build();
+            s.spawn(move |_| {
+                hj_op.build(key, pay).expect("Couldn't build hash table");
+            });
+        }
+    });
+
+    let probe_chunk_size = (rows + probe_threads - 1) / probe_threads;
+    let mut result_sums = vec![0_u64; probe_threads];
+    rayon::scope(|s| {
+        for ((key, pay), sum) in outer_rel_key
+            .chunks(probe_chunk_size)
+            .zip(outer_rel_pay.chunks(probe_chunk_size))
+            .zip(result_sums.iter_mut())
+        {
+            let mut hj_op = hj_builder.build();
+            s.spawn(move |_| {
+                hj_op
+                    .probe_sum(key, pay, sum)
+                    .expect("Couldn't execute hash table probe");
+            });
+        }
+    });
+
+    Ok(result_sums)
+}
+
+#[test]
+fn cpu_hash_join_multi_threaded_sums_all_threads() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 16;
+    const THREADS: usize = 4;
+
+    let result_sums = multi_threaded_join(ROWS, 1, THREADS)?;
+
+    assert!(result_sums
+        .iter()
+        .all(|&sum| sum == (ROWS / THREADS) as u64));
+    assert_eq!(ROWS as u64, result_sums.iter().sum::<u64>());
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_different_build_and_probe_threads() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 16;
+
+    for &(build_threads, probe_threads) in &[(1, 4), (4, 1), (3, 5), (8, 2)] {
+        let result_sums = multi_threaded_join(ROWS, build_threads, probe_threads)?;
+
+        assert_eq!(
+            ROWS as u64,
+            result_sums.iter().sum::<u64>(),
+            "Wrong result with {} build and {} probe threads",
+            build_threads,
+            probe_threads
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_null_keys_never_match() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 16;
+    const HT_LEN: usize = 2 * ROWS;
+
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::SysMem);
+
+    let mut inner_rel_key = alloc_fn(ROWS);
+    let mut inner_rel_pay = alloc_fn(ROWS);
+    let mut outer_rel_key = alloc_fn(ROWS);
+    let mut outer_rel_pay = alloc_fn(ROWS);
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+    outer_rel_pay.iter_mut().for_each(|x| *x = 1);
+
+    // Store the same key value in all NULL tuples on both sides. Without the
+    // validity bitmaps, the NULL tuples would match each other.
+    const NULL_VALUE: i32 = 0;
+    let inner_validity: ValidityBitmap = (0..ROWS).map(|i| i % 4 != 0).collect();
+    let outer_validity: ValidityBitmap = (0..ROWS).map(|i| i % 3 != 0).collect();
+    inner_rel_key
+        .iter_mut()
+        .enumerate()
+        .filter(|&(i, _)| !inner_validity.is_valid(i))
+        .for_each(|(_, key)| *key = NULL_VALUE);
+    outer_rel_key
+        .iter_mut()
+        .enumerate()
+        .filter(|&(i, _)| !outer_validity.is_valid(i))
+        .for_each(|(_, key)| *key = NULL_VALUE);
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build_nullable(&inner_rel_key, &inner_rel_pay, &inner_validity)?;
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum_nullable(
+        &outer_rel_key,
+        &outer_rel_pay,
+        &outer_validity,
+        &mut result_sum,
+    )?;
+
+    // A valid outer tuple matches iff its key is a valid inner key
+    let valid_inner_keys: std::collections::HashSet<i32> = inner_rel_key
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| inner_validity.is_valid(i))
+        .map(|(_, &key)| key)
+        .collect();
+    let expected_sum = outer_rel_key
+        .iter()
+        .enumerate()
+        .filter(|&(i, key)| outer_validity.is_valid(i) && valid_inner_keys.contains(key))
+        .count() as u64;
+
+    assert_eq!(ROWS / 4, inner_validity.null_count());
+    assert_eq!(expected_sum, result_sum);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_all_null_keys_produce_no_matches() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1024;
+    const HT_LEN: usize = 2 * ROWS;
+
+    // All tuples have the same key value, but are SQL NULL
+    let keys = vec![7_i32; ROWS];
+    let payloads = vec![1_i32; ROWS];
+    let validity: ValidityBitmap = std::iter::repeat(false).take(ROWS).collect();
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build_nullable(&keys, &payloads, &validity)?;
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum_nullable(&keys, &payloads, &validity, &mut result_sum)?;
+
+    assert_eq!(0, result_sum);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_rejects_valid_key_equal_to_null_key() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 64;
+
+    // The NULL tuple may store the sentinel, but the valid tuple must not
+    let keys = vec![1_i32, i32::null_key(), i32::null_key()];
+    let payloads = vec![1_i32; keys.len()];
+    let validity: ValidityBitmap = vec![true, false, true].into_iter().collect();
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    match hj_op.build_nullable(&keys, &payloads, &validity) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for a valid key equal to the NULL key"),
+    }
+
+    // Without the colliding tuple, the build succeeds and a valid probe key
+    // equal to the sentinel never matches
+    let build_validity: ValidityBitmap = vec![true, false, false].into_iter().collect();
+    hj_op.build_nullable(&keys, &payloads, &build_validity)?;
+
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum_nullable(&keys, &payloads, &validity, &mut result_sum)?;
+    assert_eq!(1, result_sum);
+
+    Ok(())
+}
+
+#[test]
+fn validity_bitmap_rejects_mismatching_length() -> Result<(), Box<dyn Error>> {
+    let validity = ValidityBitmap::new_all_valid(10);
+    let keys = vec![0_i32; 11];
+
+    match validity.filter_valid(&keys, &keys) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for a mismatching bitmap length"),
+    }
+
+    assert_eq!(0, validity.null_count());
+    assert!((0..10).all(|i| validity.is_valid(i)));
+
+    Ok(())
+}
+
+/// Joins a primary key and a foreign key relation with a custom hash function,
+/// and returns the result sum.
+fn custom_hash_join(hash_fn: CpuHashFn<i64>) -> Result<u64, Box<dyn Error>> {
+    const ROWS: usize = 1 << 12;
+    const HT_LEN: usize = 2 * ROWS;
+
+    let mut inner_rel_key = vec![0_i64; ROWS];
+    let mut outer_rel_key = vec![0_i64; ROWS];
+    let inner_rel_pay: Vec<i64> = (1..=ROWS as i64).collect();
+    let outer_rel_pay: Vec<i64> = (1..=ROWS as i64).collect();
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_fn(hash_fn)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+
+    Ok(result_sum)
+}
+
+#[test]
+fn cpu_hash_join_custom_identity_hash() -> Result<(), Box<dyn Error>> {
+    const ROWS: u64 = 1 << 12;
+
+    let result_sum = custom_hash_join(|key| key as u64)?;
+    assert_eq!((ROWS * (ROWS + 1)) / 2, result_sum);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_custom_multiplicative_hash() -> Result<(), Box<dyn Error>> {
+    const ROWS: u64 = 1 << 12;
+
+    // The upper bits are the best-mixed bits of a multiplicative hash
+    let result_sum = custom_hash_join(|key| {
+        (key as u64)
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            .rotate_left(32)
+    })?;
+    assert_eq!((ROWS * (ROWS + 1)) / 2, result_sum);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_custom_hash_requires_linear_probing() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 1024;
+
+    let keys: Vec<i32> = (0..16).collect();
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::Perfect)
+        .hash_fn(|key: i32| key as u64)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    match hj_op.build(&keys, &keys) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for a custom hash with perfect hashing"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_custom_hash_rejects_empty_hash_table() -> Result<(), Box<dyn Error>> {
+    let keys: Vec<i64> = Vec::new();
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, 1);
+    let hash_table = HashTable::new_on_cpu(ht_mem, 0)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_fn(|key: i64| key as u64)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    match hj_op.build(&keys, &keys) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for an empty hash table"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn hash_table_rejects_oversized_length() -> Result<(), Box<dyn Error>> {
+    // The length is only checked, the hash table isn't allocated with it
+    const HUGE_LEN: usize = usize::MAX / 4;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, 1024);
+
+    match HashTable::<i32>::new_on_cpu(ht_mem, HUGE_LEN) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::IntegerOverflow(_) => {}
+            _ => panic!("Expected an IntegerOverflow error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for an oversized hash table"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn hash_table_check_len_rejects_oversized_length() -> Result<(), Box<dyn Error>> {
+    const HUGE_LEN: usize = usize::MAX / 4;
+
+    HashTable::<i32>::check_len(1024)?;
+
+    match HashTable::<i32>::check_len(HUGE_LEN) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::IntegerOverflow(_) => {}
+            _ => panic!("Expected an IntegerOverflow error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for an oversized hash table"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_probe_bitmap_marks_matches() -> Result<(), Box<dyn Error>> {
+    // Not a multiple of 64 to cover the partially filled last word
+    const INNER_ROWS: usize = 1000;
+    const OUTER_ROWS: usize = 3001;
+    const HT_LEN: usize = 2048;
+
+    let mut inner_rel_key = vec![0_i32; INNER_ROWS];
+    let inner_rel_pay = vec![1_i32; INNER_ROWS];
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+
+    // Every third probe key is outside of the inner relation's key domain, but
+    // within the bounds of the perfect hash table
+    let outer_rel_key: Vec<i32> = (0..OUTER_ROWS)
+        .map(|i| {
+            if i % 3 == 0 {
+                (INNER_ROWS + i % (HT_LEN - INNER_ROWS)) as i32
+            } else {
+                inner_rel_key[i % INNER_ROWS]
+            }
+        })
+        .collect();
+
+    for &hashing_scheme in &[
+        HashingScheme::Perfect,
+        HashingScheme::LinearProbing,
+        HashingScheme::Cuckoo,
+    ] {
+        let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+        let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+        let mut hj_op = CpuHashJoinBuilder::default()
+            .hashing_scheme(hashing_scheme)
+            .hash_table(Arc::new(hash_table))
+            .build();
+
+        hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+
+        let mut bitmap = vec![0_u64; probe_bitmap_len(OUTER_ROWS)];
+        hj_op.probe_bitmap(&outer_rel_key, &mut bitmap)?;
+
+        for (i, &key) in outer_rel_key.iter().enumerate() {
+            let is_set = bitmap[i / 64] & (1 << (i % 64)) != 0;
+            let is_match = inner_rel_key.contains(&key);
+            assert_eq!(
+                is_match, is_set,
+                "Wrong bit for probe tuple {} with {:?} hashing",
+                i, hashing_scheme
+            );
+        }
+
+        // Bits beyond the last probe tuple remain cleared
+        let tail_bits = bitmap.last().unwrap() >> (OUTER_ROWS % 64);
+        assert_eq!(0, tail_bits);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_probe_bitmap_rejects_small_bitmap() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 1024;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::<i32>::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    let keys = vec![1_i32; 65];
+    let mut bitmap = vec![0_u64; 1];
+
+    match hj_op.probe_bitmap(&keys, &mut bitmap) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for a too small bitmap"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_probe_bitmap_rejects_bucket_chaining() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 1024;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::<i32>::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::BucketChaining)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    let keys = vec![1_i32; 64];
+    let mut bitmap = vec![0_u64; probe_bitmap_len(keys.len())];
+
+    match hj_op.probe_bitmap(&keys, &mut bitmap) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for bucket chaining"),
+    }
+
+    Ok(())
+}
+
+/// Builds a hash table from `keys` with `validity`, and returns the
+/// anti-join bitmap of `probe_keys`.
+fn anti_join_bitmap(
+    keys: &[i32],
+    validity: &ValidityBitmap,
+    probe_keys: &[i32],
+) -> Result<Vec<u64>, Box<dyn Error>> {
+    const HT_LEN: usize = 4096;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    let payloads = vec![1_i32; keys.len()];
+    hj_op.build_nullable(keys, &payloads, validity)?;
+
+    // Non-zero initial words must be overwritten
+    let mut bitmap = vec![u64::MAX; probe_bitmap_len(probe_keys.len())];
+    hj_op.probe_anti_bitmap(probe_keys, Some(validity), &mut bitmap)?;
+
+    Ok(bitmap)
+}
+
+#[test]
+fn cpu_hash_join_probe_anti_bitmap_without_nulls() -> Result<(), Box<dyn Error>> {
+    // Not a multiple of 64 to cover the partially filled last word
+    const INNER_ROWS: usize = 1000;
+    const OUTER_ROWS: usize = 3001;
+
+    let mut inner_rel_key = vec![0_i32; INNER_ROWS];
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    let validity = ValidityBitmap::new_all_valid(INNER_ROWS);
+
+    // Every third probe key is outside of the inner relation's key domain
+    let outer_rel_key: Vec<i32> = (0..OUTER_ROWS)
+        .map(|i| {
+            if i % 3 == 0 {
+                (INNER_ROWS + i) as i32
+            } else {
+                inner_rel_key[i % INNER_ROWS]
+            }
+        })
+        .collect();
+
+    let bitmap = anti_join_bitmap(&inner_rel_key, &validity, &outer_rel_key)?;
+
+    for (i, &key) in outer_rel_key.iter().enumerate() {
+        let is_set = bitmap[i / 64] & (1 << (i % 64)) != 0;
+        let is_match = inner_rel_key.contains(&key);
+        assert_eq!(!is_match, is_set, "Wrong bit for probe tuple {}", i);
+    }
+
+    // Bits beyond the last probe tuple are cleared
+    let tail_bits = bitmap.last().unwrap() >> (OUTER_ROWS % 64);
+    assert_eq!(0, tail_bits);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_probe_anti_bitmap_with_build_null_is_empty() -> Result<(), Box<dyn Error>> {
+    const INNER_ROWS: usize = 100;
+    const OUTER_ROWS: usize = 200;
+
+    let inner_rel_key: Vec<i32> = (0..INNER_ROWS as i32).collect();
+    let outer_rel_key: Vec<i32> = (0..OUTER_ROWS as i32).collect();
+
+    // A single NULL in the subquery makes NOT IN unknown for all probe tuples
+    let mut validity = ValidityBitmap::new_all_valid(INNER_ROWS);
+    validity.set_valid(INNER_ROWS / 2, false);
+
+    let bitmap = anti_join_bitmap(&inner_rel_key, &validity, &outer_rel_key)?;
+    assert!(bitmap.iter().all(|&word| word == 0));
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_probe_anti_bitmap_without_build_validity() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 1024;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::<i32>::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    // An empty subquery, thus NOT IN is true for all probe tuples
+    hj_op.build(&[], &[])?;
+
+    let probe_keys: Vec<i32> = (0..70).collect();
+    let mut bitmap = vec![0_u64; probe_bitmap_len(probe_keys.len())];
+    hj_op.probe_anti_bitmap(&probe_keys, None, &mut bitmap)?;
+
+    assert_eq!(vec![u64::MAX, (1 << 6) - 1], bitmap);
+
+    let mut small_bitmap = vec![0_u64; 1];
+    assert!(hj_op
+        .probe_anti_bitmap(&probe_keys, None, &mut small_bitmap)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn cuda_hash_join_without_cuda_returns_error() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 1024;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::<i32>::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let result = CudaHashJoinBuilder::default()
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    match result {
+        Err(ref e) => match e.kind() {
+            ErrorKind::CudaUnavailable(_) => {}
+            _ => panic!("Expected a CudaUnavailable error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected a CudaUnavailable error, but the build succeeded"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_table_replicas_probe_locally() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 16;
+    const HT_LEN: usize = 2 * ROWS;
+    let nodes = [0_u16, 1];
+
+    // Replication is pointless on single-node machines
+    if linux_wrapper::numa_mem_info(nodes[1]).is_err() {
+        eprintln!("Skipping test, because the machine has only one NUMA node");
+        return Ok(());
+    }
+
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::SysMem);
+
+    let mut inner_rel_key = alloc_fn(ROWS);
+    let mut inner_rel_pay = alloc_fn(ROWS);
+    let mut outer_rel_key = alloc_fn(ROWS);
+    let mut outer_rel_pay = alloc_fn(ROWS);
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+    outer_rel_pay
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i + 1) as i32);
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = Arc::new(HashTable::new_on_cpu(ht_mem, HT_LEN)?);
+
+    let hj_builder = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(hash_table.clone());
+    hj_builder.build().build(&inner_rel_key, &inner_rel_pay)?;
+
+    let replicas = hash_table.replicate_across_nodes(&nodes)?;
+    assert_eq!(nodes.to_vec(), replicas.nodes());
+
+    let expected_sum = (ROWS as u64 * (ROWS as u64 + 1)) / 2;
+    for &node in &nodes {
+        let replica = replicas.replica(node).expect("Replica is missing");
+        assert!(hash_table.try_as_slice()? == replica.try_as_slice()?);
+
+        numa::run_on_node(node)?;
+        assert!(Arc::ptr_eq(&replica, &replicas.local_replica()?));
+
+        let mut hj_op = hj_builder.build_with_local_replica(&replicas)?;
+        let mut result_sum: u64 = 0;
+        hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+
+        assert_eq!(expected_sum, result_sum, "Wrong sum on node {}", node);
+    }
+
+    Ok(())
+}
+
+/// Joins a primary key relation with a foreign key relation, of which every
+/// third tuple has no match, and returns the number of matches.
+///
+/// The full join sums up a probe payload of one, which equals the number of
+/// matches. The key-only join counts the matches without any payloads.
+fn count_matches(hashing_scheme: HashingScheme, key_only: bool) -> Result<u64, Box<dyn Error>> {
+    const ROWS: usize = 1 << 12;
+    const HT_LEN: usize = 2 * ROWS;
+
+    let mut inner_rel_key = vec![0_i64; ROWS];
+    let mut outer_rel_key = vec![0_i64; ROWS];
+    let inner_rel_pay: Vec<i64> = (1..=ROWS as i64).collect();
+    let outer_rel_pay = vec![1_i64; ROWS];
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    // The primary keys are in [0, ROWS), thus these keys have no match
+    outer_rel_key
+        .iter_mut()
+        .enumerate()
+        .filter(|(i, _)| i % 3 == 0)
+        .for_each(|(i, key)| *key = (ROWS + i) as i64);
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(hashing_scheme)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    let mut result: u64 = 0;
+    if key_only {
+        hj_op.build_keys(&inner_rel_key)?;
+        hj_op.probe_count(&outer_rel_key, &mut result)?;
+    } else {
+        hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+        hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result)?;
+    }
+
+    Ok(result)
+}
+
+#[test]
+fn cpu_hash_join_key_only_counts_same_matches() -> Result<(), Box<dyn Error>> {
+    const EXPECTED_MATCHES: u64 = (1 << 12) - ((1 << 12) + 2) / 3;
+
+    for &hashing_scheme in &[
+        HashingScheme::Perfect,
+        HashingScheme::LinearProbing,
+        HashingScheme::Cuckoo,
+    ] {
+        let full_count = count_matches(hashing_scheme, false)?;
+        let key_only_count = count_matches(hashing_scheme, true)?;
+
+        assert_eq!(EXPECTED_MATCHES, full_count, "{:?}", hashing_scheme);
+        assert_eq!(full_count, key_only_count, "{:?}", hashing_scheme);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_key_only_with_custom_hash() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 10;
+    const HT_LEN: usize = 2 * ROWS;
+
+    let mut inner_rel_key = vec![0_i64; ROWS];
+    let mut outer_rel_key = vec![0_i64; ROWS];
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_fn(|key: i64| key as u64)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build_keys(&inner_rel_key)?;
+    let mut result_count: u64 = 0;
+    hj_op.probe_count(&outer_rel_key, &mut result_count)?;
+
+    assert_eq!(ROWS as u64, result_count);
+
+    Ok(())
+}
+
+/// Joins a build relation with keys `[0, build_rows)` and a probe relation
+/// with keys `[0, probe_rows)`, and returns the payload sum and the number of
+/// materialized matches.
+fn join_with_lengths(
+    build_rows: usize,
+    probe_rows: usize,
+    hashing_scheme: HashingScheme,
+) -> Result<(u64, usize), Box<dyn Error>> {
+    const HT_LEN: usize = 1 << 12;
+
+    let inner_rel_key: Vec<i64> = (0..build_rows as i64).collect();
+    let inner_rel_pay = vec![1_i64; build_rows];
+    let outer_rel_key: Vec<i64> = (0..probe_rows as i64).collect();
+    let outer_rel_pay = vec![1_i64; probe_rows];
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(hashing_scheme)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+
+    let matches = if let HashingScheme::LinearProbing = hashing_scheme {
+        let mut join_result = vec![JoinMatch::default(); probe_rows];
+        hj_op.probe_materialize(&outer_rel_key, &outer_rel_pay, &mut join_result)?
+    } else {
+        result_sum as usize
+    };
+
+    Ok((result_sum, matches))
+}
+
+#[test]
+fn cpu_hash_join_empty_build_relation() -> Result<(), Box<dyn Error>> {
+    for &hashing_scheme in &[HashingScheme::Perfect, HashingScheme::LinearProbing] {
+        assert_eq!((0, 0), join_with_lengths(0, 1024, hashing_scheme)?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_empty_probe_relation() -> Result<(), Box<dyn Error>> {
+    for &hashing_scheme in &[HashingScheme::Perfect, HashingScheme::LinearProbing] {
+        assert_eq!((0, 0), join_with_lengths(1024, 0, hashing_scheme)?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_empty_build_and_probe_relations() -> Result<(), Box<dyn Error>> {
+    for &hashing_scheme in &[HashingScheme::Perfect, HashingScheme::LinearProbing] {
+        assert_eq!((0, 0), join_with_lengths(0, 0, hashing_scheme)?);
+    }
+
+    Ok(())
+}
+
+/// Joins two relations with a hash table of the given layout, and returns the
+/// payload sum and the number of matches.
+fn join_with_layout(layout: HashTableLayout) -> Result<(u64, u64), Box<dyn Error>> {
+    const ROWS: usize = 1 << 12;
+    const HT_LEN: usize = 2 * ROWS;
+
+    let mut inner_rel_key = vec![0_i64; ROWS];
+    let mut outer_rel_key = vec![0_i64; ROWS];
+    let inner_rel_pay: Vec<i64> = (1..=ROWS as i64).collect();
+    let outer_rel_pay: Vec<i64> = (1..=ROWS as i64).collect();
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_attr(&mut outer_rel_key, 0..(2 * ROWS))?;
+
+    let new_join = || -> Result<_, Box<dyn Error>> {
+        let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+        let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?.with_layout(layout);
+
+        Ok(CpuHashJoinBuilder::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .hash_table(Arc::new(hash_table))
+            .build())
+    };
+
+    let mut hj_op = new_join()?;
+    hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+
+    let mut hj_op = new_join()?;
+    hj_op.build_keys(&inner_rel_key)?;
+    let mut result_count: u64 = 0;
+    hj_op.probe_count(&outer_rel_key, &mut result_count)?;
+
+    Ok((result_sum, result_count))
+}
+
+#[test]
+fn cpu_hash_join_soa_layout_matches_aos_layout() -> Result<(), Box<dyn Error>> {
+    let aos = join_with_layout(HashTableLayout::AoS)?;
+    let soa = join_with_layout(HashTableLayout::SoA)?;
+
+    assert!(aos.1 > 0);
+    assert_eq!(aos, soa);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_soa_layout_requires_linear_probing() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 1024;
+
+    let keys: Vec<i64> = (0..HT_LEN as i64 / 2).collect();
+    let mut bitmap = vec![0_u64; probe_bitmap_len(keys.len())];
+
+    for &hashing_scheme in &[HashingScheme::Perfect, HashingScheme::Cuckoo] {
+        let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+        let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?.with_layout(HashTableLayout::SoA);
+        let mut hj_op = CpuHashJoinBuilder::default()
+            .hashing_scheme(hashing_scheme)
+            .hash_table(Arc::new(hash_table))
+            .build();
+
+        assert!(hj_op.build(&keys, &keys).is_err());
+    }
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?.with_layout(HashTableLayout::SoA);
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    hj_op.build(&keys, &keys)?;
+    assert!(hj_op.probe_bitmap(&keys, &mut bitmap).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn cpu_probe_materialize_limit_truncates_and_reports_overflow() -> Result<(), Box<dyn Error>> {
+    const BUILD_ROWS: usize = 100;
+    const PROBE_ROWS: usize = 1000;
+    const HT_LEN: usize = 1024;
+
+    let inner_rel_key: Vec<i64> = (0..BUILD_ROWS as i64).collect();
+    let outer_rel_key: Vec<i64> = (0..PROBE_ROWS as i64)
+        .map(|i| i % BUILD_ROWS as i64)
+        .collect();
+    let outer_rel_pay: Vec<i64> = (0..PROBE_ROWS as i64).collect();
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+    hj_op.build(&inner_rel_key, &inner_rel_key)?;
+
+    let mut full_result = vec![JoinMatch::default(); PROBE_ROWS];
+    let full =
+        hj_op.probe_materialize_limit(&outer_rel_key, &outer_rel_pay, &mut full_result, None)?;
+    assert_eq!(
+        MaterializeResult {
+            written: PROBE_ROWS,
+            overflowed: false
+        },
+        full
+    );
+
+    let mut capped_result = vec![JoinMatch::default(); PROBE_ROWS];
+    let capped = hj_op.probe_materialize_limit(
+        &outer_rel_key,
+        &outer_rel_pay,
+        &mut capped_result,
+        Some(10),
+    )?;
+    assert_eq!(
+        MaterializeResult {
+            written: 10,
+            overflowed: true
+        },
+        capped
+    );
+    assert_eq!(&full_result[..10], &capped_result[..10]);
+    assert!(capped_result[10..]
+        .iter()
+        .all(|&m| m == JoinMatch::default()));
+
+    // A cap larger than the join result is bounded by the join result
+    let mut small_result = vec![JoinMatch::default(); 10];
+    let bounded = hj_op.probe_materialize_limit(
+        &outer_rel_key,
+        &outer_rel_pay,
+        &mut small_result,
+        Some(2 * PROBE_ROWS),
+    )?;
+    assert_eq!(capped, bounded);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_detects_direct_address_table() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 14;
+    const HT_LEN: usize = 2 * ROWS;
+
+    // Surrogate keys are dense and sorted
+    let inner_rel_key: Vec<i64> = (0..ROWS as i64).collect();
+    let inner_rel_pay = vec![1_i64; ROWS];
+    let mut outer_rel_key = vec![0_i64; ROWS];
+    UniformRelation::gen_attr(&mut outer_rel_key, 0..(2 * ROWS))?;
+    let outer_rel_pay: Vec<i64> = (1..=ROWS as i64).collect();
+
+    let detected =
+        HashingScheme::detect_direct_address(&inner_rel_key, HT_LEN, HashingScheme::LinearProbing);
+    assert!(matches!(detected, HashingScheme::Perfect));
+
+    let mut sums = Vec::new();
+    for &hashing_scheme in &[detected, HashingScheme::LinearProbing] {
+        let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+        let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+        let mut hj_op = CpuHashJoinBuilder::default()
+            .hashing_scheme(hashing_scheme)
+            .hash_table(Arc::new(hash_table))
+            .build();
+
+        hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+        let mut result_sum = 0;
+        hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+        sums.push(result_sum);
+    }
+
+    let expected: u64 = outer_rel_key
+        .iter()
+        .zip(outer_rel_pay.iter())
+        .filter(|(&key, _)| key < ROWS as i64)
+        .map(|(_, &pay)| pay as u64)
+        .sum();
+    assert_eq!(vec![expected, expected], sums);
+
+    Ok(())
+}
+
+#[test]
+fn direct_address_detection_rejects_sparse_or_unsorted_keys() {
+    const HT_LEN: usize = 1024;
+    let fallback = HashingScheme::LinearProbing;
+
+    let unsorted: Vec<i32> = (0..512).rev().collect();
+    let out_of_range: Vec<i32> = (0..512).map(|k| k * 4).collect();
+    let negative: Vec<i32> = (-1..511).collect();
+    let duplicates = vec![1_i32, 1, 2];
+
+    for keys in &[unsorted, out_of_range, negative, duplicates, Vec::new()] {
+        assert!(matches!(
+            HashingScheme::detect_direct_address(keys, HT_LEN, fallback),
+            HashingScheme::LinearProbing
+        ));
+    }
+}
+
+#[test]
+fn cpu_hash_table_save_and_load_mmap_round_trip() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 12;
+    const HT_LEN: usize = 2 * ROWS;
+
+    let path = std::env::temp_dir().join(format!("sql_ops_hash_table_{}.bin", std::process::id()));
+
+    let mut inner_rel_key = vec![0_i32; ROWS];
+    let inner_rel_pay: Vec<i32> = (1..=ROWS as i32).collect();
+    let mut outer_rel_key = vec![0_i32; 4 * ROWS];
+    let outer_rel_pay: Vec<i32> = (1..=4 * ROWS as i32).collect();
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_attr(&mut outer_rel_key, 0..(2 * ROWS))?;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = Arc::new(HashTable::new_on_cpu(ht_mem, HT_LEN)?);
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(hash_table.clone())
+        .build();
+    hj_op.build(&inner_rel_key, &inner_rel_pay)?;
+    let mut expected_sum: u64 = 0;
+    hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut expected_sum)?;
+
+    hash_table.save(&path, HashingScheme::LinearProbing)?;
+    let bytes = std::fs::read(&path)?;
+    assert_eq!(bytes[12..16], i32::FILE_TYPE_TAG.to_le_bytes());
+
+    let loaded = HashTable::<i32>::load_mmap(&path, HashingScheme::LinearProbing, 0)?;
+    assert_eq!(hash_table.try_as_slice()?, loaded.try_as_slice()?);
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(loaded))
+        .build();
+    let mut result_sum: u64 = 0;
+    hj_op.probe_sum(&outer_rel_key, &outer_rel_pay, &mut result_sum)?;
+    assert!(expected_sum > 0);
+    assert_eq!(expected_sum, result_sum);
+
+    assert!(HashTable::<i32>::load_mmap(&path, HashingScheme::Perfect, 0).is_err());
+    assert!(HashTable::<i64>::load_mmap(&path, HashingScheme::LinearProbing, 0).is_err());
+
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+/// Returns the slot of `key` that the built-in multiply-shift hash function
+/// computes for 64-bit keys.
+fn mult_shift_hash_i64(key: i64, log2_slots: u32) -> u64 {
+    const HASH_FACTOR: u64 = 11400714819323198485;
+    (key as u64).wrapping_mul(HASH_FACTOR) >> (64 - log2_slots)
+}
+
+/// Builds a hash table with `probe_step`, and returns the average number of
+/// slots probed to find each build key, together with the probe's result sum.
+fn average_probe_len(
+    keys: &[i64],
+    log2_slots: u32,
+    probe_step: ProbeStep,
+) -> Result<(f64, u64), Box<dyn Error>> {
+    let slots = 1_usize << log2_slots;
+    let mask = slots as u64 - 1;
+    let payloads = vec![1_i64; keys.len()];
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, slots);
+    let hash_table = Arc::new(HashTable::new_on_cpu(ht_mem, slots)?);
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .probe_step(probe_step)
+        .hash_table(hash_table.clone())
+        .build();
+
+    hj_op.build(keys, &payloads)?;
+    let mut result_sum = 0;
+    hj_op.probe_sum(keys, &payloads, &mut result_sum)?;
+
+    // Replays the probe sequence from the hashed slot to the key's slot
+    let probe_len = |key: i64, slot: u64| -> u64 {
+        let mut index = mult_shift_hash_i64(key, log2_slots);
+        let mut step = 0;
+        while index != slot {
+            index = match probe_step {
+                ProbeStep::Linear => (index + 1) & mask,
+                ProbeStep::Quadratic => (index + step + 1) & mask,
+            };
+            step += 1;
+        }
+        step + 1
+    };
+
+    let total: u64 = hash_table
+        .try_as_slice()?
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.key != -1)
+        .map(|(slot, entry)| probe_len(entry.key, slot as u64))
+        .sum();
+
+    Ok((total as f64 / keys.len() as f64, result_sum))
+}
+
+#[test]
+fn cpu_hash_join_quadratic_step_shortens_clustered_probes() -> Result<(), Box<dyn Error>> {
+    const LOG2_SLOTS: u32 = 12;
+    const SLOTS: usize = 1 << LOG2_SLOTS;
+    const KEYS: usize = SLOTS * 3 / 4;
+
+    // Adversarial keys that all hash into the first quarter of the table. At
+    // a load factor of 75%, linear steps merge them into one long run.
+    let keys: Vec<i64> = (1..)
+        .filter(|&key| mult_shift_hash_i64(key, LOG2_SLOTS) < SLOTS as u64 / 4)
+        .take(KEYS)
+        .collect();
+
+    let (linear_len, linear_sum) = average_probe_len(&keys, LOG2_SLOTS, ProbeStep::Linear)?;
+    let (quadratic_len, quadratic_sum) =
+        average_probe_len(&keys, LOG2_SLOTS, ProbeStep::Quadratic)?;
+
+    assert_eq!(linear_sum, KEYS as u64);
+    assert_eq!(quadratic_sum, KEYS as u64);
+    assert!(
+        quadratic_len * 4.0 < linear_len,
+        "Expected quadratic probes ({}) to be much shorter than linear probes ({})",
+        quadratic_len,
+        linear_len
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_quadratic_step_requires_power_of_two_table() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 1000;
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .probe_step(ProbeStep::Quadratic)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    match hj_op.build(&[1_i64, 2, 3], &[1, 1, 1]) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for a table size that isn't a power of two"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_build_report_perfect_duplicate_key_fails() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 16;
+
+    let keys = [1_i64, 2, 3, 2];
+    let payloads = [10_i64, 20, 30, 40];
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::Perfect)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    let report = hj_op.build_with_report(&keys, &payloads)?;
+    assert_eq!(
+        report,
+        BuildReport {
+            inserted: 3,
+            failed: 1,
+            max_probe_len: 1,
+        }
+    );
+
+    let mut count = 0;
+    hj_op.probe_count(&keys, &mut count)?;
+    assert_eq!(count, keys.len() as u64);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_hash_join_build_report_full_linear_probing_table() -> Result<(), Box<dyn Error>> {
+    const HT_LEN: usize = 8;
+
+    let keys: Vec<i32> = (0..12).collect();
+
+    let ht_mem = Allocator::alloc_deref_mem(DerefMemType::SysMem, HT_LEN);
+    let hash_table = HashTable::new_on_cpu(ht_mem, HT_LEN)?;
+
+    let mut hj_op = CpuHashJoinBuilder::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .hash_table(Arc::new(hash_table))
+        .build();
+
+    let report = hj_op.build_with_report(&keys, &keys)?;
+    assert_eq!(report.inserted, HT_LEN as u64);
+    assert_eq!(report.failed, (keys.len() - HT_LEN) as u64);
+    assert!(report.max_probe_len >= 1 && report.max_probe_len <= HT_LEN as u64);
+
+    Ok(())
+}
@@ -16,15 +16,20 @@ pub mod radix_partition;
 
 use datagen::relation::{KeyAttribute, UniformRelation};
 use itertools::izip;
-use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemAllocFn, MemType};
+use numa_gpu::runtime::hw_info::ProcessorCache;
+use numa_gpu::runtime::linux_wrapper;
+use numa_gpu::runtime::numa::{self, PageType};
 use radix_partition::{tuple_loss_or_duplicates, verify_partitions};
 use rustacuda::memory::DeviceCopy;
+use sql_ops::error::ErrorKind;
 use sql_ops::partition::cpu_radix_partition::{
     CpuHistogramAlgorithm, CpuRadixPartitionAlgorithm, CpuRadixPartitionable, CpuRadixPartitioner,
 };
+use sql_ops::partition::skew::SkewPolicy;
 use sql_ops::partition::{
-    PartitionOffsets, PartitionedRelation, RadixBits, RadixPartitionInputChunkable, RadixPass,
-    Tuple,
+    self, PartitionOffsets, PartitionedRelation, RadixBits, RadixPartitionInputChunkable,
+    RadixPass, Tuple,
 };
 use std::error::Error;
 use std::mem::size_of;
@@ -92,7 +97,7 @@ where
         partition_algorithm,
         radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
         DerefMemType::SysMem,
-    );
+    )?;
 
     let data_key_chunks = data_key.as_slice().input_chunks::<T>(threads)?;
 
@@ -523,6 +528,457 @@ fn cpu_verify_partitions_chunked_simd_i32_12_bits() -> Result<(), Box<dyn Error>
     )
 }
 
+// ======================== Chunked NC streaming ========================
+
+/// Partitions the relation with the given algorithm in a single pass.
+fn partition_with_algorithm<T>(
+    data_key: &[T],
+    data_pay: &[T],
+    partition_algorithm: CpuRadixPartitionAlgorithm,
+    radix_bits: u32,
+    threads: u32,
+) -> Result<PartitionedRelation<Tuple<T, T>>, Box<dyn Error>>
+where
+    T: Clone + Default + KeyAttribute + DeviceCopy + CpuRadixPartitionable,
+{
+    let mut partition_offsets = PartitionOffsets::new(
+        CpuHistogramAlgorithm::Chunked.into(),
+        threads,
+        radix_bits,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+
+    let mut partitioned_relation = PartitionedRelation::new(
+        data_key.len(),
+        CpuHistogramAlgorithm::Chunked.into(),
+        radix_bits,
+        threads,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+
+    unsafe {
+        partitioned_relation
+            .as_raw_relation_mut_slice()?
+            .iter_mut()
+            .for_each(|x| {
+                *x = Tuple {
+                    key: T::null_key(),
+                    value: T::default(),
+                }
+            });
+    }
+
+    let mut partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        partition_algorithm,
+        radix_bits,
+        DerefMemType::SysMem,
+    )?;
+
+    for (key_chunk, offsets_chunk) in izip!(
+        data_key.input_chunks::<T>(threads)?.into_iter(),
+        partition_offsets.chunks_mut()
+    ) {
+        partitioner.prefix_sum(key_chunk, offsets_chunk)?;
+    }
+
+    for (key_chunk, pay_chunk, offsets_chunk, partitioned_chunk) in izip!(
+        data_key.input_chunks::<T>(threads)?.into_iter(),
+        data_pay.input_chunks::<T>(threads)?.into_iter(),
+        partition_offsets.chunks_mut(),
+        partitioned_relation.chunks_mut()
+    ) {
+        partitioner.partition(key_chunk, pay_chunk, offsets_chunk, partitioned_chunk)?;
+    }
+
+    Ok(partitioned_relation)
+}
+
+/// Checks that non-temporal stores produce the same partitions as regular
+/// stores.
+fn streaming_matches_regular_stores<T>(
+    tuples: usize,
+    radix_bits: u32,
+    threads: u32,
+) -> Result<(), Box<dyn Error>>
+where
+    T: Clone + Default + KeyAttribute + DeviceCopy + CpuRadixPartitionable + std::fmt::Debug,
+{
+    let mut data_key: Vec<T> = vec![T::default(); tuples];
+    let mut data_pay: Vec<T> = vec![T::default(); tuples];
+    UniformRelation::gen_primary_key(&mut data_key, None)?;
+    UniformRelation::gen_primary_key(&mut data_pay, None)?;
+
+    let regular = partition_with_algorithm(
+        &data_key,
+        &data_pay,
+        CpuRadixPartitionAlgorithm::NC,
+        radix_bits,
+        threads,
+    )?;
+    let streaming = partition_with_algorithm(
+        &data_key,
+        &data_pay,
+        CpuRadixPartitionAlgorithm::NCStreaming,
+        radix_bits,
+        threads,
+    )?;
+
+    for chunk_id in 0..regular.num_chunks() {
+        for partition_id in 0..regular.fanout() {
+            assert_eq!(
+                regular[(chunk_id, partition_id)],
+                streaming[(chunk_id, partition_id)],
+                "Chunk {} partition {} differs",
+                chunk_id,
+                partition_id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_streaming_matches_regular_stores_i32() -> Result<(), Box<dyn Error>> {
+    streaming_matches_regular_stores::<i32>((32 << 20) / size_of::<i32>(), 12, 4)
+}
+
+#[test]
+fn cpu_streaming_matches_regular_stores_i64() -> Result<(), Box<dyn Error>> {
+    streaming_matches_regular_stores::<i64>((32 << 20) / size_of::<i64>(), 12, 4)
+}
+
+#[test]
+fn cpu_tuple_loss_or_duplicates_chunked_streaming_i64_small_data() -> Result<(), Box<dyn Error>> {
+    run_cpu_partitioning(
+        15,
+        Box::new(|keys: &mut _| Ok(UniformRelation::gen_primary_key::<i64>(keys, None)?)),
+        Box::new(|pays: &mut _| Ok(UniformRelation::gen_attr::<i64>(pays, 0..10000)?)),
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::NCStreaming,
+        RadixBits::from(4),
+        4,
+        Box::new(&tuple_loss_or_duplicates),
+    )
+}
+
+/// Checks that applying the inverse permutation to the partitioned relation
+/// reconstructs the original order of the input relation.
+fn inverse_reconstructs_original_order<T>(
+    tuples: usize,
+    radix_bits: u32,
+    threads: u32,
+) -> Result<(), Box<dyn Error>>
+where
+    T: Clone
+        + Default
+        + PartialEq
+        + KeyAttribute
+        + DeviceCopy
+        + CpuRadixPartitionable
+        + std::fmt::Debug,
+{
+    let mut data_key: Vec<T> = vec![T::default(); tuples];
+    let mut data_pay: Vec<T> = vec![T::default(); tuples];
+    UniformRelation::gen_primary_key(&mut data_key, None)?;
+    UniformRelation::gen_primary_key(&mut data_pay, None)?;
+
+    let mut partition_offsets = PartitionOffsets::new(
+        CpuHistogramAlgorithm::Chunked.into(),
+        threads,
+        radix_bits,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+
+    let mut partitioned_relation = PartitionedRelation::new(
+        data_key.len(),
+        CpuHistogramAlgorithm::Chunked.into(),
+        radix_bits,
+        threads,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let raw_len = unsafe { partitioned_relation.as_raw_relation_mut_slice()?.len() };
+    let mut inverse = vec![u64::MAX; raw_len];
+
+    let mut partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::NC,
+        radix_bits,
+        DerefMemType::SysMem,
+    )?;
+
+    for (key_chunk, offsets_chunk) in izip!(
+        data_key.input_chunks::<T>(threads)?.into_iter(),
+        partition_offsets.chunks_mut()
+    ) {
+        partitioner.prefix_sum(key_chunk, offsets_chunk)?;
+    }
+
+    let inverse_chunks: Vec<&mut [u64]> = partitioned_relation
+        .inverse_chunks_mut(&mut inverse)?
+        .collect();
+    for (key_chunk, pay_chunk, offsets_chunk, partitioned_chunk, inverse_chunk) in izip!(
+        data_key.input_chunks::<T>(threads)?.into_iter(),
+        data_pay.input_chunks::<T>(threads)?.into_iter(),
+        partition_offsets.chunks_mut(),
+        partitioned_relation.chunks_mut(),
+        inverse_chunks.into_iter()
+    ) {
+        partitioner.partition_with_inverse(
+            key_chunk,
+            pay_chunk,
+            offsets_chunk,
+            partitioned_chunk,
+            inverse_chunk,
+        )?;
+    }
+
+    let partitioned_tuples = unsafe { partitioned_relation.as_raw_relation_mut_slice()? };
+    let mut reconstructed: Vec<Option<Tuple<T, T>>> = vec![None; tuples];
+    for (tuple, &original_pos) in partitioned_tuples.iter().zip(inverse.iter()) {
+        if original_pos == u64::MAX {
+            continue;
+        }
+
+        let slot = &mut reconstructed[original_pos as usize];
+        assert!(slot.is_none(), "Tuple {} is duplicated", original_pos);
+        *slot = Some(tuple.clone());
+    }
+
+    for (i, (tuple, key, pay)) in izip!(reconstructed, data_key, data_pay).enumerate() {
+        let tuple = tuple.unwrap_or_else(|| panic!("Tuple {} is lost", i));
+        assert_eq!(tuple.key, key);
+        assert_eq!(tuple.value, pay);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_inverse_reconstructs_original_order_i32() -> Result<(), Box<dyn Error>> {
+    inverse_reconstructs_original_order::<i32>((1 << 20) + 15, 8, 4)
+}
+
+#[test]
+fn cpu_inverse_reconstructs_original_order_i64() -> Result<(), Box<dyn Error>> {
+    inverse_reconstructs_original_order::<i64>((1 << 20) + 15, 8, 4)
+}
+
+#[test]
+fn cpu_inverse_requires_nc_algorithm() -> Result<(), Box<dyn Error>> {
+    const TUPLES: usize = 1 << 10;
+    const RADIX_BITS: u32 = 4;
+
+    let mut data_key: Vec<i32> = vec![0; TUPLES];
+    let data_pay: Vec<i32> = vec![0; TUPLES];
+    UniformRelation::gen_primary_key(&mut data_key, None)?;
+
+    let mut partition_offsets = PartitionOffsets::new(
+        CpuHistogramAlgorithm::Chunked.into(),
+        1,
+        RADIX_BITS,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let mut partitioned_relation = PartitionedRelation::new(
+        TUPLES,
+        CpuHistogramAlgorithm::Chunked.into(),
+        RADIX_BITS,
+        1,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let raw_len = unsafe { partitioned_relation.as_raw_relation_mut_slice()?.len() };
+    let mut inverse = vec![u64::MAX; raw_len];
+
+    let mut partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::Swwc,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?;
+
+    let key_chunk = data_key.input_chunks::<i32>(1)?.remove(0);
+    let pay_chunk = data_pay.input_chunks::<i32>(1)?.remove(0);
+    let offsets_chunk = partition_offsets.chunks_mut().next().unwrap();
+    let partitioned_chunk = partitioned_relation.chunks_mut().next().unwrap();
+
+    match partitioner.partition_with_inverse(
+        key_chunk,
+        pay_chunk,
+        offsets_chunk,
+        partitioned_chunk,
+        &mut inverse,
+    ) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for the SWWC algorithm"),
+    }
+
+    Ok(())
+}
+
+/// Checks that partitioning a sequence of chunks yields the same partitioned
+/// relation as partitioning the whole relation at once.
+fn chunk_iterator_matches_whole_relation(
+    partition_algorithm: CpuRadixPartitionAlgorithm,
+    tuples: usize,
+    radix_bits: u32,
+    chunk_lens: &[usize],
+) -> Result<(), Box<dyn Error>> {
+    let mut data_key: Vec<i64> = vec![0; tuples];
+    let mut data_pay: Vec<i64> = vec![0; tuples];
+    UniformRelation::gen_attr(&mut data_key, 0..(32 << 20))?;
+    UniformRelation::gen_attr(&mut data_pay, 0..10000)?;
+
+    let data: Vec<Tuple<i64, i64>> = data_key
+        .iter()
+        .zip(data_pay.iter())
+        .map(|(&key, &value)| Tuple { key, value })
+        .collect();
+
+    // Split the data into chunks of the given lengths, and the remainder
+    let mut chunks: Vec<&[Tuple<i64, i64>]> = Vec::new();
+    let mut remainder = data.as_slice();
+    for &len in chunk_lens {
+        let (chunk, rest) = remainder.split_at(len.min(remainder.len()));
+        chunks.push(chunk);
+        remainder = rest;
+    }
+    chunks.push(remainder);
+
+    let mut partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        partition_algorithm,
+        radix_bits,
+        DerefMemType::SysMem,
+    )?;
+    let whole = partitioner.partition_chunks(
+        std::iter::once(data.as_slice()),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    )?;
+    let chunked = partitioner.partition_chunks(
+        chunks,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    )?;
+    let reference = partition_with_algorithm(
+        &data_key,
+        &data_pay,
+        CpuRadixPartitionAlgorithm::NC,
+        radix_bits,
+        1,
+    )?;
+
+    assert_eq!(chunked.len(), tuples);
+    assert_eq!(
+        chunked.offsets.try_as_slice()?,
+        whole.offsets.try_as_slice()?
+    );
+    assert_eq!(
+        chunked.offsets.try_as_slice()?,
+        reference.offsets.try_as_slice()?
+    );
+
+    for partition_id in 0..chunked.fanout() {
+        assert_eq!(chunked[(0, partition_id)], whole[(0, partition_id)]);
+        assert_eq!(chunked[(0, partition_id)], reference[(0, partition_id)]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_chunk_iterator_matches_whole_relation_even_chunks() -> Result<(), Box<dyn Error>> {
+    chunk_iterator_matches_whole_relation(
+        CpuRadixPartitionAlgorithm::NC,
+        1 << 16,
+        6,
+        &[1 << 12; 15],
+    )
+}
+
+#[test]
+fn cpu_chunk_iterator_matches_whole_relation_uneven_chunks() -> Result<(), Box<dyn Error>> {
+    chunk_iterator_matches_whole_relation(
+        CpuRadixPartitionAlgorithm::NC,
+        10_007,
+        4,
+        &[0, 1, 999, 3, 4096, 0, 17],
+    )
+}
+
+#[test]
+fn cpu_chunk_iterator_streaming_matches_whole_relation() -> Result<(), Box<dyn Error>> {
+    chunk_iterator_matches_whole_relation(
+        CpuRadixPartitionAlgorithm::NCStreaming,
+        10_007,
+        4,
+        &[0, 1, 999, 3, 4096, 0, 17],
+    )
+}
+
+#[test]
+fn cpu_chunk_iterator_requires_nc_algorithm() -> Result<(), Box<dyn Error>> {
+    let data = vec![
+        Tuple {
+            key: 1_i64,
+            value: 1_i64
+        };
+        100
+    ];
+    let mut partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::Swwc,
+        4,
+        DerefMemType::SysMem,
+    )?;
+
+    match partitioner.partition_chunks(
+        std::iter::once(data.as_slice()),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    ) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for the SWWC algorithm"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_radix_bits_validated_at_construction() -> Result<(), Box<dyn Error>> {
+    let new_partitioner = |radix_bits| {
+        CpuRadixPartitioner::new(
+            CpuHistogramAlgorithm::Chunked,
+            CpuRadixPartitionAlgorithm::NC,
+            radix_bits,
+            DerefMemType::SysMem,
+        )
+    };
+
+    new_partitioner(6)?;
+    new_partitioner(CpuRadixPartitioner::MAX_RADIX_BITS)?;
+
+    match new_partitioner(CpuRadixPartitioner::MAX_RADIX_BITS + 1) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for too many radix bits"),
+    }
+
+    Ok(())
+}
+
 // ======================== Chunked SWWC ========================
 
 #[test]
@@ -1640,3 +2096,509 @@ fn cpu_tuple_loss_or_duplicates_chunked_simd_swwc_simd_i64_17_bits() -> Result<(
         Box::new(&tuple_loss_or_duplicates),
     )
 }
+
+#[test]
+fn cpu_partitioned_relation_rejects_oversized_length() -> Result<(), Box<dyn Error>> {
+    const HUGE_LEN: usize = usize::MAX / 4;
+
+    // The allocation must be rejected before any memory is allocated
+    let partition_alloc_fn: MemAllocFn<Tuple<i64, i64>> =
+        Box::new(|_| panic!("Tried to allocate an oversized relation"));
+    let offsets_alloc_fn: MemAllocFn<u64> =
+        Box::new(|_| panic!("Tried to allocate oversized offsets"));
+
+    let result = PartitionedRelation::try_new(
+        HUGE_LEN,
+        CpuHistogramAlgorithm::Chunked.into(),
+        10,
+        4,
+        partition_alloc_fn,
+        offsets_alloc_fn,
+    );
+
+    match result {
+        Err(ref e) => match e.kind() {
+            ErrorKind::IntegerOverflow(_) => {}
+            _ => panic!("Expected an IntegerOverflow error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for an oversized relation"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_histogram_and_offsets_are_consistent() -> Result<(), Box<dyn Error>> {
+    const RADIX_BITS: u32 = 6;
+
+    let mut data_key = vec![0_i64; 10_000];
+    UniformRelation::gen_attr::<i64>(&mut data_key, 0..1_000_000)?;
+
+    let partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?;
+
+    // The histogram is computed over the low radix bits of the key
+    let mask = (1 << RADIX_BITS) - 1;
+    let mut expected = vec![0_u64; 1 << RADIX_BITS];
+    data_key
+        .iter()
+        .for_each(|&key| expected[(key & mask) as usize] += 1);
+
+    for &threads in &[1, 4] {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        let (counts, offsets) =
+            thread_pool.install(|| partitioner.histogram_and_offsets(&data_key))?;
+
+        assert_eq!(counts.len(), 1 << RADIX_BITS);
+        assert_eq!(offsets.len(), counts.len());
+        assert_eq!(offsets[0], 0);
+        for i in 0..counts.len() - 1 {
+            assert_eq!(offsets[i + 1] - offsets[i], counts[i]);
+        }
+        assert_eq!(counts.iter().sum::<u64>(), data_key.len() as u64);
+        assert_eq!(counts, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_parallel_histogram_matches_single_threaded() -> Result<(), Box<dyn Error>> {
+    const RADIX_BITS: u32 = 10;
+
+    let mut data_key = vec![0_i64; 100_000];
+    UniformRelation::gen_attr::<i64>(&mut data_key, 0..1_000_000)?;
+
+    let partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?;
+    let expected = partitioner.histogram_and_offsets(&data_key)?;
+
+    for &threads in &[1, 2, 7, 16] {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        let parallel = partitioner.histogram_and_offsets_parallel(&data_key, &thread_pool)?;
+        assert_eq!(expected, parallel);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_salt_heavy_partitions_splits_heavy_hitter() -> Result<(), Box<dyn Error>> {
+    const RADIX_BITS: u32 = 4;
+    const TUPLES: usize = 4096;
+    const HEAVY_HITTER: i64 = 5;
+
+    // Every second tuple has the heavy-hitter key
+    let data_key: Vec<i64> = (0..TUPLES as i64)
+        .map(|i| if i % 2 == 0 { HEAVY_HITTER } else { i })
+        .collect();
+    let data_pay: Vec<i64> = (0..TUPLES as i64).collect();
+
+    let partitioned_relation = partition_with_algorithm(
+        &data_key,
+        &data_pay,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        2,
+    )?;
+
+    let partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?
+    .with_skew_policy(SkewPolicy::SaltHeavy { threshold: 2.0 });
+    let salted = partitioner.salt_heavy_partitions(&partitioned_relation)?;
+    let salt_map = salted.salt_map();
+
+    let heavy_partition = HEAVY_HITTER as u32;
+    assert_eq!(
+        vec![heavy_partition],
+        salt_map.salted_partitions().collect::<Vec<_>>()
+    );
+
+    let heavy_len = partitioned_relation.partition_len(heavy_partition)?;
+    let sub_partitions = salt_map.sub_partitions(heavy_partition);
+    assert!(sub_partitions > 1);
+    assert_eq!(
+        salt_map.num_virtual_partitions(),
+        partitioned_relation.fanout() + sub_partitions - 1
+    );
+
+    // Round-robin assignment balances the sub-partitions
+    let sub_lens: Vec<usize> = (0..sub_partitions)
+        .map(|salt| {
+            salted
+                .sub_partition(heavy_partition, salt)
+                .expect("Sub-partition is missing")
+                .count()
+        })
+        .collect();
+    assert_eq!(heavy_len, sub_lens.iter().sum::<usize>());
+    assert!(sub_lens.iter().max().unwrap() - sub_lens.iter().min().unwrap() <= 1);
+
+    let mut salted_payloads: Vec<i64> = (0..sub_partitions)
+        .flat_map(|salt| salted.sub_partition(heavy_partition, salt).unwrap())
+        .map(|tuple| {
+            assert_eq!(heavy_partition as i64, tuple.key & ((1 << RADIX_BITS) - 1));
+            tuple.value
+        })
+        .collect();
+    let mut expected_payloads: Vec<i64> = (0..partitioned_relation.num_chunks())
+        .flat_map(|chunk_id| partitioned_relation[(chunk_id, heavy_partition)].iter())
+        .map(|tuple| tuple.value)
+        .collect();
+    salted_payloads.sort_unstable();
+    expected_payloads.sort_unstable();
+    assert_eq!(expected_payloads, salted_payloads);
+
+    // Each virtual partition maps back to its radix partition
+    for virtual_id in salt_map.virtual_partitions(heavy_partition) {
+        let (partition_id, salt) = salt_map.partition_of(virtual_id)?;
+        assert_eq!(heavy_partition, partition_id);
+        assert!(salt < sub_partitions);
+    }
+
+    // Merging the per-virtual-partition tuple counts restores the histogram
+    let virtual_lens: Vec<u64> = (0..salt_map.num_virtual_partitions())
+        .map(|virtual_id| {
+            let (partition_id, salt) = salt_map.partition_of(virtual_id)?;
+            let len = salted
+                .sub_partition(partition_id, salt)
+                .expect("Sub-partition is missing")
+                .count();
+            Ok(len as u64)
+        })
+        .collect::<Result<_, Box<dyn Error>>>()?;
+    let (histogram, _) = partitioner.histogram_and_offsets(&data_key)?;
+    assert_eq!(histogram, salt_map.merge(&virtual_lens)?);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_salt_heavy_partitions_keeps_uniform_partitions() -> Result<(), Box<dyn Error>> {
+    const RADIX_BITS: u32 = 4;
+
+    let data_key: Vec<i64> = (0..4096).collect();
+    let data_pay = data_key.clone();
+
+    let partitioned_relation = partition_with_algorithm(
+        &data_key,
+        &data_pay,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        1,
+    )?;
+
+    let partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?
+    .with_skew_policy(SkewPolicy::SaltHeavy { threshold: 2.0 });
+    let salted = partitioner.salt_heavy_partitions(&partitioned_relation)?;
+
+    assert_eq!(0, salted.salt_map().salted_partitions().count());
+
+    // A partition that is not salted is its own and only sub-partition
+    assert_eq!(
+        partitioned_relation.partition_len(0)?,
+        salted
+            .sub_partition(0, 0)
+            .expect("Partition is missing")
+            .count()
+    );
+    assert!(salted.sub_partition(0, 1).is_none());
+    assert_eq!(
+        partitioned_relation.fanout(),
+        salted.salt_map().num_virtual_partitions()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cpu_partition_empty_relation() -> Result<(), Box<dyn Error>> {
+    const RADIX_BITS: u32 = 6;
+
+    let data_key: Vec<i32> = Vec::new();
+    let data_pay: Vec<i32> = Vec::new();
+
+    for &algorithm in &[
+        CpuRadixPartitionAlgorithm::NC,
+        CpuRadixPartitionAlgorithm::Swwc,
+    ] {
+        for &threads in &[1, 4] {
+            let partitioned_relation =
+                partition_with_algorithm(&data_key, &data_pay, algorithm, RADIX_BITS, threads)?;
+
+            assert_eq!(0, partitioned_relation.len());
+            for partition_id in 0..partitioned_relation.fanout() {
+                assert_eq!(0, partitioned_relation.partition_len(partition_id)?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cpu_input_chunks_rejects_zero_chunks() {
+    let data_key = vec![0_i32; 1024];
+
+    assert!(data_key.as_slice().input_chunks::<i32>(0).is_err());
+}
+
+#[test]
+fn cpu_partition_into_remote_numa_memory() -> Result<(), Box<dyn Error>> {
+    const TUPLES: usize = 1 << 20;
+    const RADIX_BITS: u32 = 6;
+    const THREADS: u32 = 1;
+    const LOCAL_NODE: u16 = 0;
+
+    let remote_node = match (1..=255)
+        .find(|&node| linux_wrapper::numa_mem_info(node).map_or(false, |info| info.total > 0))
+    {
+        Some(node) => node,
+        None => {
+            eprintln!("Skipping test, requires at least two NUMA nodes with memory");
+            return Ok(());
+        }
+    };
+
+    let mut data_key = vec![0_i32; TUPLES];
+    let mut data_pay = vec![0_i32; TUPLES];
+    UniformRelation::gen_primary_key(&mut data_key, None)?;
+    UniformRelation::gen_attr(&mut data_pay, 0..10000)?;
+    let (data_key, data_pay) = (data_key.as_slice(), data_pay.as_slice());
+
+    // Touch the output pages first from the local node
+    numa::run_on_node(LOCAL_NODE)?;
+
+    let mut partition_offsets = PartitionOffsets::new(
+        CpuHistogramAlgorithm::Chunked.into(),
+        THREADS,
+        RADIX_BITS,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let mut partitioned_relation = PartitionedRelation::new(
+        TUPLES,
+        CpuHistogramAlgorithm::Chunked.into(),
+        RADIX_BITS,
+        THREADS,
+        Allocator::mem_alloc_fn(MemType::NumaMem {
+            node: remote_node,
+            page_type: PageType::Default,
+        }),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let mut partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?;
+
+    for (key_chunk, offsets_chunk) in izip!(
+        data_key.input_chunks::<i32>(THREADS)?.into_iter(),
+        partition_offsets.chunks_mut()
+    ) {
+        partitioner.prefix_sum(key_chunk, offsets_chunk)?;
+    }
+
+    for (key_chunk, pay_chunk, offsets_chunk, partitioned_chunk) in izip!(
+        data_key.input_chunks::<i32>(THREADS)?.into_iter(),
+        data_pay.input_chunks::<i32>(THREADS)?.into_iter(),
+        partition_offsets.chunks_mut(),
+        partitioned_relation.chunks_mut()
+    ) {
+        partitioner.partition(key_chunk, pay_chunk, offsets_chunk, partitioned_chunk)?;
+    }
+
+    // Padding pages might be untouched, and are thus not allocated
+    let relation = unsafe { partitioned_relation.as_raw_relation_slice()? };
+    let nodes = numa::page_nodes(relation, ProcessorCache::page_size())?;
+    assert!(nodes.iter().any(Option::is_some));
+    assert!(nodes.iter().flatten().all(|&node| node == remote_node));
+
+    Ok(())
+}
+
+#[test]
+fn cpu_iter_partitions_yields_all_tuples() -> Result<(), Box<dyn Error>> {
+    const TUPLES: usize = 100_000;
+    const THREADS: u32 = 4;
+    const RADIX_BITS: u32 = 6;
+
+    let mut data_key = vec![0_i32; TUPLES];
+    let mut data_pay = vec![0_i32; TUPLES];
+    UniformRelation::gen_primary_key(&mut data_key, None)?;
+    UniformRelation::gen_attr(&mut data_pay, 0..10000)?;
+    let (data_key, data_pay) = (data_key.as_slice(), data_pay.as_slice());
+
+    let mut partition_offsets = PartitionOffsets::new(
+        CpuHistogramAlgorithm::Chunked.into(),
+        THREADS,
+        RADIX_BITS,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let mut partitioned_relation = PartitionedRelation::new(
+        TUPLES,
+        CpuHistogramAlgorithm::Chunked.into(),
+        RADIX_BITS,
+        THREADS,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let mut partitioner = CpuRadixPartitioner::new(
+        CpuHistogramAlgorithm::Chunked,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?;
+
+    for (key_chunk, offsets_chunk) in izip!(
+        data_key.input_chunks::<i32>(THREADS)?.into_iter(),
+        partition_offsets.chunks_mut()
+    ) {
+        partitioner.prefix_sum(key_chunk, offsets_chunk)?;
+    }
+
+    for (key_chunk, pay_chunk, offsets_chunk, partitioned_chunk) in izip!(
+        data_key.input_chunks::<i32>(THREADS)?.into_iter(),
+        data_pay.input_chunks::<i32>(THREADS)?.into_iter(),
+        partition_offsets.chunks_mut(),
+        partitioned_relation.chunks_mut()
+    ) {
+        partitioner.partition(key_chunk, pay_chunk, offsets_chunk, partitioned_chunk)?;
+    }
+
+    let radix_bits = RadixBits::from(RADIX_BITS);
+    let partition_of =
+        |key| radix_partition::key_to_partition(key, &radix_bits, RadixPass::First) as usize;
+    let mut slices = 0;
+    let mut previous_id = 0;
+    let mut partitioned_tuples = Vec::with_capacity(TUPLES);
+
+    for (partition_id, tuples) in partitioned_relation.iter_partitions()? {
+        assert!(partition_id >= previous_id);
+        assert!(partition_id < partitioned_relation.fanout() as usize);
+        assert!(tuples.iter().all(|t| partition_of(t.key) == partition_id));
+
+        previous_id = partition_id;
+        slices += 1;
+        partitioned_tuples.extend(tuples.iter().map(|t| (t.key, t.value)));
+    }
+
+    assert_eq!(
+        partitioned_relation.fanout() as usize * THREADS as usize,
+        slices
+    );
+
+    let mut original_tuples: Vec<_> = data_key
+        .iter()
+        .copied()
+        .zip(data_pay.iter().copied())
+        .collect();
+    original_tuples.sort_unstable();
+    partitioned_tuples.sort_unstable();
+    assert_eq!(original_tuples, partitioned_tuples);
+
+    Ok(())
+}
+
+#[test]
+fn cpu_partition_of_matches_partitioner() -> Result<(), Box<dyn Error>> {
+    const TUPLES: usize = 100_000;
+    const THREADS: u32 = 4;
+    const RADIX_BITS: u32 = 7;
+
+    assert_eq!(partition::fanout(RADIX_BITS), 128);
+    assert_eq!(partition::radix_mask(RADIX_BITS), 127);
+    assert_eq!(partition::partition_of(-1_i64, RADIX_BITS), 127);
+
+    // Negative keys check that `partition_of` sign-extends like the kernels
+    let mut data_key = vec![0_i64; TUPLES];
+    let mut data_pay = vec![0_i64; TUPLES];
+    UniformRelation::gen_attr(&mut data_key, 0..(1 << 20))?;
+    UniformRelation::gen_attr(&mut data_pay, 0..10000)?;
+    data_key.iter_mut().step_by(2).for_each(|key| *key = -*key);
+    let (data_key, data_pay) = (data_key.as_slice(), data_pay.as_slice());
+
+    for &algorithm in [
+        CpuRadixPartitionAlgorithm::NC,
+        CpuRadixPartitionAlgorithm::Swwc,
+    ]
+    .iter()
+    {
+        let mut partition_offsets = PartitionOffsets::new(
+            CpuHistogramAlgorithm::Chunked.into(),
+            THREADS,
+            RADIX_BITS,
+            Allocator::mem_alloc_fn(MemType::SysMem),
+        );
+        let mut partitioned_relation = PartitionedRelation::new(
+            TUPLES,
+            CpuHistogramAlgorithm::Chunked.into(),
+            RADIX_BITS,
+            THREADS,
+            Allocator::mem_alloc_fn(MemType::SysMem),
+            Allocator::mem_alloc_fn(MemType::SysMem),
+        );
+        let mut partitioner = CpuRadixPartitioner::new(
+            CpuHistogramAlgorithm::Chunked,
+            algorithm,
+            RADIX_BITS,
+            DerefMemType::SysMem,
+        )?;
+
+        for (key_chunk, offsets_chunk) in izip!(
+            data_key.input_chunks::<i64>(THREADS)?.into_iter(),
+            partition_offsets.chunks_mut()
+        ) {
+            partitioner.prefix_sum(key_chunk, offsets_chunk)?;
+        }
+
+        for (key_chunk, pay_chunk, offsets_chunk, partitioned_chunk) in izip!(
+            data_key.input_chunks::<i64>(THREADS)?.into_iter(),
+            data_pay.input_chunks::<i64>(THREADS)?.into_iter(),
+            partition_offsets.chunks_mut(),
+            partitioned_relation.chunks_mut()
+        ) {
+            partitioner.partition(key_chunk, pay_chunk, offsets_chunk, partitioned_chunk)?;
+        }
+
+        let mut partitioned_len = 0;
+        for (partition_id, tuples) in partitioned_relation.iter_partitions()? {
+            for tuple in tuples {
+                assert_eq!(
+                    partition::partition_of(tuple.key, RADIX_BITS),
+                    partition_id,
+                    "Key {} placed in partition {} by {:?}",
+                    tuple.key,
+                    partition_id,
+                    algorithm
+                );
+            }
+            partitioned_len += tuples.len();
+        }
+        assert_eq!(TUPLES, partitioned_len);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,501 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datagen::relation::UniformRelation;
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::cuda_wrapper;
+use numa_gpu::runtime::memory::{LaunchableMem, Mem};
+use once_cell::sync::Lazy;
+use rustacuda::context::{Context, CurrentContext, UnownedContext};
+use rustacuda::function::{BlockSize, GridSize};
+use rustacuda::stream::{Stream, StreamFlags};
+use sql_ops::join::no_partitioning_join::{
+    CudaHashJoinBuilder, HashTable, HashTablePlacement, JoinMatch, MaterializeResult,
+};
+use sql_ops::join::{HashTableLayout, HashingScheme};
+use std::collections::HashSet;
+use std::error::Error;
+use std::result::Result;
+use std::sync::Arc;
+
+static mut CUDA_CONTEXT_OWNER: Option<Context> = None;
+static CUDA_CONTEXT: Lazy<UnownedContext> = Lazy::new(|| {
+    let context = rustacuda::quick_init().expect("Failed to initialize CUDA context");
+    let unowned = context.get_unowned();
+
+    unsafe {
+        CUDA_CONTEXT_OWNER = Some(context);
+    }
+
+    unowned
+});
+
+/// Joins a build relation with keys `[0, build_rows)` and a probe relation
+/// with keys `[0, probe_rows)` on the GPU, and returns the payload sum and the
+/// number of materialized matches.
+fn gpu_join_with_lengths(
+    build_rows: usize,
+    probe_rows: usize,
+) -> Result<(u64, usize), Box<dyn Error>> {
+    const HT_LEN: usize = 1 << 12;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaUniMem);
+
+    let mut inner_rel_key = alloc_fn(build_rows);
+    let mut inner_rel_pay = alloc_fn(build_rows);
+    let mut outer_rel_key = alloc_fn(probe_rows);
+    let mut outer_rel_pay = alloc_fn(probe_rows);
+
+    inner_rel_key
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as i32);
+    outer_rel_key
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as i32);
+    inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+    outer_rel_pay.iter_mut().for_each(|x| *x = 1);
+
+    let ht_mem = Allocator::alloc_mem(MemType::CudaUniMem, HT_LEN);
+    let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
+
+    let hj_op = CudaHashJoinBuilder::<i32>::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .build_dim(GridSize::from(4), BlockSize::from(128))
+        .probe_dim(GridSize::from(4), BlockSize::from(128))
+        .hash_table(Arc::new(hash_table))
+        .build()?;
+
+    let mut result_set = Allocator::alloc_mem::<u64>(MemType::CudaUniMem, hj_op.result_set_len());
+    result_set.zero_async(&stream)?;
+
+    hj_op.build(
+        inner_rel_key.as_launchable_slice(),
+        inner_rel_pay.as_launchable_slice(),
+        &stream,
+    )?;
+    hj_op.probe_sum(
+        outer_rel_key.as_launchable_slice(),
+        outer_rel_pay.as_launchable_slice(),
+        &result_set,
+        &stream,
+    )?;
+    stream.synchronize()?;
+
+    let result_sum = result_set.try_as_slice()?.iter().sum();
+
+    let join_result = Allocator::alloc_mem::<JoinMatch<i32>>(MemType::CudaUniMem, probe_rows);
+    let matches = hj_op.probe_materialize(
+        outer_rel_key.as_launchable_slice(),
+        outer_rel_pay.as_launchable_slice(),
+        &join_result,
+        &stream,
+    )?;
+
+    Ok((result_sum, matches))
+}
+
+#[test]
+fn gpu_hash_join_empty_build_relation() -> Result<(), Box<dyn Error>> {
+    assert_eq!((0, 0), gpu_join_with_lengths(0, 1024)?);
+
+    Ok(())
+}
+
+#[test]
+fn gpu_hash_join_empty_probe_relation() -> Result<(), Box<dyn Error>> {
+    assert_eq!((0, 0), gpu_join_with_lengths(1024, 0)?);
+
+    Ok(())
+}
+
+#[test]
+fn gpu_hash_join_empty_build_and_probe_relations() -> Result<(), Box<dyn Error>> {
+    assert_eq!((0, 0), gpu_join_with_lengths(0, 0)?);
+
+    Ok(())
+}
+
+#[test]
+fn gpu_hash_join_non_empty_relations() -> Result<(), Box<dyn Error>> {
+    assert_eq!((512, 512), gpu_join_with_lengths(512, 1024)?);
+
+    Ok(())
+}
+
+/// Joins two relations on the GPU with a hash table of the given layout, and
+/// returns the payload sum.
+fn gpu_join_with_layout(layout: HashTableLayout) -> Result<u64, Box<dyn Error>> {
+    const ROWS: usize = 1 << 16;
+    const HT_LEN: usize = 2 * ROWS;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaUniMem);
+
+    let mut inner_rel_key = alloc_fn(ROWS);
+    let mut inner_rel_pay = alloc_fn(ROWS);
+    let mut outer_rel_key = alloc_fn(ROWS);
+    let mut outer_rel_pay = alloc_fn(ROWS);
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_attr(&mut outer_rel_key, 0..(2 * ROWS))?;
+    inner_rel_pay.iter_mut().for_each(|x| *x = 1);
+    outer_rel_pay
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i + 1) as i32);
+
+    let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+    let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?.with_layout(layout);
+
+    let hj_op = CudaHashJoinBuilder::<i32>::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .build_dim(GridSize::from(16), BlockSize::from(256))
+        .probe_dim(GridSize::from(16), BlockSize::from(256))
+        .hash_table(Arc::new(hash_table))
+        .build()?;
+
+    let mut result_set = Allocator::alloc_mem::<u64>(MemType::CudaUniMem, hj_op.result_set_len());
+    result_set.zero_async(&stream)?;
+
+    hj_op.build(
+        inner_rel_key.as_launchable_slice(),
+        inner_rel_pay.as_launchable_slice(),
+        &stream,
+    )?;
+    hj_op.probe_sum(
+        outer_rel_key.as_launchable_slice(),
+        outer_rel_pay.as_launchable_slice(),
+        &result_set,
+        &stream,
+    )?;
+    stream.synchronize()?;
+
+    Ok(result_set.try_as_slice()?.iter().sum())
+}
+
+#[test]
+fn gpu_zeroed_result_sets_read_back_zeros() -> Result<(), Box<dyn Error>> {
+    const LEN: usize = 1 << 16;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    for mem_type in [MemType::CudaDevMem, MemType::CudaUniMem].iter() {
+        let mut result_set = Allocator::alloc_mem::<u64>(mem_type.clone(), LEN);
+        cuda_wrapper::memset_async(result_set.as_launchable_mut_slice(), -1, &stream)?;
+        result_set.zero_async(&stream)?;
+        stream.synchronize()?;
+
+        let mut host = Mem::SysMem(vec![u64::MAX; LEN]);
+        host.copy_from_mem(&result_set)?;
+        assert!(
+            host.try_as_slice()?.iter().all(|&x| x == 0),
+            "{:?} isn't zeroed",
+            mem_type
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpu_hash_join_soa_layout_matches_aos_layout() -> Result<(), Box<dyn Error>> {
+    let aos = gpu_join_with_layout(HashTableLayout::AoS)?;
+    let soa = gpu_join_with_layout(HashTableLayout::SoA)?;
+
+    assert!(aos > 0);
+    assert_eq!(aos, soa);
+
+    Ok(())
+}
+
+/// Counts the matches of a tiny build relation on the GPU, and returns the
+/// count and the hash table placement of the probe.
+///
+/// Without a shared memory budget, the builder uses the device's limit.
+fn gpu_count_tiny_build(
+    shared_memory_placement: bool,
+    shared_memory_budget: Option<usize>,
+) -> Result<(u64, u64, HashTablePlacement), Box<dyn Error>> {
+    const BUILD_ROWS: usize = 256;
+    const PROBE_ROWS: usize = 1 << 16;
+    const HT_LEN: usize = 2 * BUILD_ROWS;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaUniMem);
+
+    let mut inner_rel_key = alloc_fn(BUILD_ROWS);
+    let mut outer_rel_key = alloc_fn(PROBE_ROWS);
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_attr(&mut outer_rel_key, 0..(4 * BUILD_ROWS))?;
+
+    let build_keys: HashSet<i32> = inner_rel_key.iter().copied().collect();
+    let expected = outer_rel_key
+        .iter()
+        .filter(|key| build_keys.contains(key))
+        .count() as u64;
+
+    let ht_mem = Allocator::alloc_mem(MemType::CudaDevMem, HT_LEN);
+    let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
+
+    let mut builder = CudaHashJoinBuilder::<i32>::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .build_dim(GridSize::from(16), BlockSize::from(256))
+        .probe_dim(GridSize::from(16), BlockSize::from(256))
+        .hash_table(Arc::new(hash_table));
+    if shared_memory_placement {
+        builder = builder.shared_memory_placement(true);
+    }
+    if let Some(bytes) = shared_memory_budget {
+        builder = builder.shared_memory_budget(bytes);
+    }
+    let hj_op = builder.build()?;
+
+    let mut result_set = Allocator::alloc_mem::<u64>(MemType::CudaUniMem, hj_op.result_set_len());
+    result_set.zero_async(&stream)?;
+
+    hj_op.build_keys(inner_rel_key.as_launchable_slice(), &stream)?;
+    hj_op.probe_count(outer_rel_key.as_launchable_slice(), &result_set, &stream)?;
+    stream.synchronize()?;
+
+    let count = result_set.try_as_slice()?.iter().sum();
+
+    Ok((count, expected, hj_op.placement()))
+}
+
+#[test]
+fn gpu_tiny_build_is_probed_in_shared_memory() -> Result<(), Box<dyn Error>> {
+    let (count, expected, placement) = gpu_count_tiny_build(true, None)?;
+
+    assert_eq!(placement, HashTablePlacement::SharedMemory);
+    assert!(expected > 0);
+    assert_eq!(count, expected);
+
+    Ok(())
+}
+
+#[test]
+fn gpu_shared_memory_and_global_probes_count_all_matches() -> Result<(), Box<dyn Error>> {
+    // The relations are random, thus each count is checked against its own
+    // relations
+    let (shared_count, shared_expected, shared_placement) = gpu_count_tiny_build(true, None)?;
+    let (global_count, global_expected, global_placement) = gpu_count_tiny_build(true, Some(0))?;
+    let (default_count, default_expected, default_placement) = gpu_count_tiny_build(false, None)?;
+
+    assert_eq!(shared_placement, HashTablePlacement::SharedMemory);
+    assert_eq!(global_placement, HashTablePlacement::Global);
+    assert_eq!(default_placement, HashTablePlacement::Global);
+    assert_eq!(shared_count, shared_expected);
+    assert_eq!(global_count, global_expected);
+    assert_eq!(default_count, default_expected);
+
+    Ok(())
+}
+
+#[test]
+fn gpu_probe_materialize_limit_truncates_and_reports_overflow() -> Result<(), Box<dyn Error>> {
+    const BUILD_ROWS: usize = 100;
+    const PROBE_ROWS: usize = 1 << 14;
+    const HT_LEN: usize = 1 << 10;
+    const CAP: usize = 10;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaUniMem);
+
+    let mut inner_rel_key = alloc_fn(BUILD_ROWS);
+    let mut outer_rel_key = alloc_fn(PROBE_ROWS);
+    let mut outer_rel_pay = alloc_fn(PROBE_ROWS);
+
+    inner_rel_key
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as i32);
+    outer_rel_key
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i % BUILD_ROWS) as i32);
+    outer_rel_pay
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as i32);
+
+    for &stable_output in &[false, true] {
+        let ht_mem = Allocator::alloc_mem(MemType::CudaUniMem, HT_LEN);
+        let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
+
+        let hj_op = CudaHashJoinBuilder::<i32>::default()
+            .hashing_scheme(HashingScheme::LinearProbing)
+            .build_dim(GridSize::from(4), BlockSize::from(128))
+            .probe_dim(GridSize::from(4), BlockSize::from(128))
+            .stable_output(stable_output)
+            .hash_table(Arc::new(hash_table))
+            .build()?;
+
+        hj_op.build(
+            inner_rel_key.as_launchable_slice(),
+            inner_rel_key.as_launchable_slice(),
+            &stream,
+        )?;
+
+        let mut join_result =
+            Allocator::alloc_mem::<JoinMatch<i32>>(MemType::CudaUniMem, PROBE_ROWS);
+        join_result
+            .try_as_mut_slice()?
+            .iter_mut()
+            .for_each(|m| *m = JoinMatch::default());
+
+        let capped = hj_op.probe_materialize_limit(
+            outer_rel_key.as_launchable_slice(),
+            outer_rel_pay.as_launchable_slice(),
+            &join_result,
+            Some(CAP),
+            &stream,
+        )?;
+        assert_eq!(
+            MaterializeResult {
+                written: CAP,
+                overflowed: true
+            },
+            capped
+        );
+
+        let join_result = join_result.try_as_slice()?;
+        assert!(join_result[..CAP]
+            .iter()
+            .all(|m| m.build_payload == m.probe_payload % BUILD_ROWS as i32));
+        assert!(join_result[CAP..]
+            .iter()
+            .all(|&m| m == JoinMatch::default()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpu_probe_materialize_single_pass_equals_two_pass_when_sorted() -> Result<(), Box<dyn Error>> {
+    const BUILD_ROWS: usize = 1000;
+    const PROBE_ROWS: usize = 1 << 14;
+    const HT_LEN: usize = 1 << 12;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaUniMem);
+
+    let mut inner_rel_key = alloc_fn(BUILD_ROWS);
+    let mut outer_rel_key = alloc_fn(PROBE_ROWS);
+    let mut outer_rel_pay = alloc_fn(PROBE_ROWS);
+
+    inner_rel_key
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as i32);
+
+    // Only every eighth probe tuple has a match, i.e., the result is small
+    outer_rel_key
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = (i % (8 * BUILD_ROWS)) as i32);
+    outer_rel_pay
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as i32);
+
+    let ht_mem = Allocator::alloc_mem(MemType::CudaUniMem, HT_LEN);
+    let hash_table = HashTable::new_on_gpu(ht_mem, HT_LEN)?;
+
+    let hj_op = CudaHashJoinBuilder::<i32>::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .build_dim(GridSize::from(4), BlockSize::from(128))
+        .probe_dim(GridSize::from(4), BlockSize::from(128))
+        .stable_output(true)
+        .hash_table(Arc::new(hash_table))
+        .build()?;
+
+    hj_op.build(
+        inner_rel_key.as_launchable_slice(),
+        inner_rel_key.as_launchable_slice(),
+        &stream,
+    )?;
+
+    let two_pass_result = Allocator::alloc_mem::<JoinMatch<i32>>(MemType::CudaUniMem, PROBE_ROWS);
+    let two_pass_matches = hj_op.probe_materialize(
+        outer_rel_key.as_launchable_slice(),
+        outer_rel_pay.as_launchable_slice(),
+        &two_pass_result,
+        &stream,
+    )?;
+
+    let single_pass_result =
+        Allocator::alloc_mem::<JoinMatch<i32>>(MemType::CudaUniMem, two_pass_matches);
+    let single_pass = hj_op.probe_materialize_single_pass(
+        outer_rel_key.as_launchable_slice(),
+        outer_rel_pay.as_launchable_slice(),
+        &single_pass_result,
+        &stream,
+    )?;
+    assert_eq!(
+        MaterializeResult {
+            written: two_pass_matches,
+            overflowed: false
+        },
+        single_pass
+    );
+
+    let mut sorted = single_pass_result.try_as_slice()?.to_vec();
+    sorted.sort_by_key(|m| (m.probe_payload, m.build_payload));
+    assert_eq!(
+        &two_pass_result.try_as_slice()?[..two_pass_matches],
+        &sorted[..]
+    );
+
+    // A result buffer that is too small overflows
+    let small_result =
+        Allocator::alloc_mem::<JoinMatch<i32>>(MemType::CudaUniMem, two_pass_matches / 2);
+    let overflowed = hj_op.probe_materialize_single_pass(
+        outer_rel_key.as_launchable_slice(),
+        outer_rel_pay.as_launchable_slice(),
+        &small_result,
+        &stream,
+    )?;
+    assert_eq!(
+        MaterializeResult {
+            written: two_pass_matches / 2,
+            overflowed: true
+        },
+        overflowed
+    );
+
+    Ok(())
+}
+
+#[test]
+fn module_reports_whether_it_has_a_kernel() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    assert!(sql_ops::module_has_function(
+        "gpu_ht_probe_aggregate_linearprobing_int32"
+    ));
+    assert!(!sql_ops::module_has_function("gpu_no_such_kernel"));
+    assert!(!sql_ops::module_has_function("gpu_nul\0kernel"));
+
+    Ok(())
+}
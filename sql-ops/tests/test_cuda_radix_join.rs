@@ -49,6 +49,41 @@ fn gpu_verify_join_aggregate(
     grid_size: GridSize,
     block_size: BlockSize,
 ) -> Result<(), Box<dyn Error>> {
+    let mut inner_rel_key = vec![0; build_tuples];
+    let mut outer_rel_key = vec![0; probe_tuples];
+
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    let result_sum = gpu_join_aggregate(
+        &inner_rel_key,
+        &outer_rel_key,
+        hashing_scheme,
+        radix_bits,
+        grid_size,
+        block_size,
+    )?;
+
+    assert_eq!(
+        (probe_tuples as i64 * (probe_tuples as i64 + 1)) / 2,
+        result_sum
+    );
+
+    Ok(())
+}
+
+/// Partitions and joins the relations on the GPU, and returns the sum of the
+/// matching probe payloads.
+///
+/// The payload of the i-th tuple is `i + 1`.
+fn gpu_join_aggregate(
+    inner_keys: &[i32],
+    outer_keys: &[i32],
+    hashing_scheme: HashingScheme,
+    radix_bits: u32,
+    grid_size: GridSize,
+    block_size: BlockSize,
+) -> Result<i64, Box<dyn Error>> {
     let histogram_algorithm = GpuHistogramAlgorithm::Chunked;
     let partition_algorithm = GpuRadixPartitionAlgorithm::NC;
     let num_chunks = GridSize::from(1); // one contiguous chunk
@@ -57,13 +92,13 @@ fn gpu_verify_join_aggregate(
     let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
     let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaUniMem);
 
-    let mut inner_rel_key = alloc_fn(build_tuples);
-    let mut inner_rel_pay = alloc_fn(build_tuples);
-    let mut outer_rel_key = alloc_fn(probe_tuples);
-    let mut outer_rel_pay = alloc_fn(probe_tuples);
+    let mut inner_rel_key = alloc_fn(inner_keys.len());
+    let mut inner_rel_pay = alloc_fn(inner_keys.len());
+    let mut outer_rel_key = alloc_fn(outer_keys.len());
+    let mut outer_rel_pay = alloc_fn(outer_keys.len());
 
-    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
-    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+    inner_rel_key.copy_from_slice(inner_keys);
+    outer_rel_key.copy_from_slice(outer_keys);
 
     inner_rel_pay
         .iter_mut()
@@ -170,16 +205,74 @@ fn gpu_verify_join_aggregate(
     )?;
 
     stream.synchronize()?;
+    radix_join.check_overflow()?;
+
     let result_sum = if let Mem::CudaUniMem(ref r) = result_sums {
         r.iter().sum()
     } else {
         0
     };
 
-    assert_eq!(
-        (probe_tuples as i64 * (probe_tuples as i64 + 1)) / 2,
-        result_sum
+    Ok(result_sum)
+}
+
+/// Returns the maximum bucket chaining build partition length for `i32` tuples.
+fn bucket_chaining_max_partition_len() -> Result<usize, Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let radix_join = CudaRadixJoin::new(
+        RadixPass::First,
+        2_u32.into(),
+        HashingScheme::BucketChaining,
+        &GridSize::from(4),
+        &BlockSize::from(128),
+    )?;
+
+    Ok(radix_join.max_build_partition_len::<i32>()?)
+}
+
+/// Generates keys for 4 partitions, of which partition 0 is heavy.
+fn skewed_partition_keys(heavy_tuples: usize, light_tuples: usize) -> Vec<i32> {
+    let heavy = (0..heavy_tuples as i32).map(|i| 4 * i);
+    let light = (0..light_tuples as i32).flat_map(|i| (1..4).map(move |p| 4 * i + p));
+
+    heavy.chain(light).collect()
+}
+
+#[test]
+fn gpu_join_aggregate_smem_bucketchaining_skewed_partition() -> Result<(), Box<dyn Error>> {
+    // The heavy partition fits only with fewer than the default number of
+    // buckets
+    let keys = skewed_partition_keys(bucket_chaining_max_partition_len()?, 100);
+
+    let result_sum = gpu_join_aggregate(
+        &keys,
+        &keys,
+        HashingScheme::BucketChaining,
+        2,
+        GridSize::from(4),
+        BlockSize::from(128),
+    )?;
+
+    let tuples = keys.len() as i64;
+    assert_eq!((tuples * (tuples + 1)) / 2, result_sum);
+
+    Ok(())
+}
+
+#[test]
+fn gpu_join_aggregate_smem_bucketchaining_oversized_partition_fails() -> Result<(), Box<dyn Error>>
+{
+    let keys = skewed_partition_keys(bucket_chaining_max_partition_len()? + 1, 100);
+
+    let result = gpu_join_aggregate(
+        &keys,
+        &keys,
+        HashingScheme::BucketChaining,
+        2,
+        GridSize::from(4),
+        BlockSize::from(128),
     );
+    assert!(result.is_err());
 
     Ok(())
 }
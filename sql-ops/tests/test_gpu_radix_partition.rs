@@ -14,6 +14,7 @@
 
 mod radix_partition;
 
+use data_store::relation::Relation;
 use datagen::relation::{KeyAttribute, UniformRelation};
 use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
 use numa_gpu::runtime::memory::{LaunchableMem, Mem};
@@ -24,6 +25,7 @@ use rustacuda::context::{Context, CurrentContext, UnownedContext};
 use rustacuda::function::{BlockSize, GridSize};
 use rustacuda::memory::LockedBuffer;
 use rustacuda::stream::{Stream, StreamFlags};
+use sql_ops::error::ErrorKind;
 use sql_ops::partition::cpu_radix_partition::{
     CpuHistogramAlgorithm, CpuRadixPartitionAlgorithm, CpuRadixPartitioner,
 };
@@ -35,6 +37,7 @@ use sql_ops::partition::{
     Tuple,
 };
 use std::cmp;
+use std::convert::TryInto;
 use std::error::Error;
 use std::mem;
 use std::result::Result;
@@ -134,7 +137,7 @@ where
                 CpuRadixPartitionAlgorithm::NC,
                 radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
                 DerefMemType::SysMem,
-            );
+            )?;
 
             for (key_chunk, offset_chunk) in key_chunks.into_iter().zip(offset_chunks) {
                 radix_prnr.prefix_sum(key_chunk, offset_chunk)?;
@@ -173,6 +176,75 @@ where
     Ok(())
 }
 
+fn run_gpu_partitioning_i64(
+    partition_algorithm: GpuRadixPartitionAlgorithm,
+    radix_bits: u32,
+) -> sql_ops::error::Result<()> {
+    const TUPLES: usize = 1 << 16;
+    const DMEM_BUFFER_BYTES: usize = 8 * 1024;
+
+    let grid_size = GridSize::from(8);
+    let block_size = BlockSize::from(128);
+    let histogram_algorithm = GpuHistogramAlgorithm::Chunked;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let mut data_key = Allocator::alloc_deref_mem::<i64>(DerefMemType::CudaPinnedMem, TUPLES);
+    let mut data_pay = Allocator::alloc_deref_mem::<i64>(DerefMemType::CudaPinnedMem, TUPLES);
+    data_key
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as i64);
+    data_pay.iter_mut().for_each(|x| *x = 0);
+
+    let mut partition_offsets = PartitionOffsets::new(
+        histogram_algorithm.into(),
+        grid_size.x,
+        radix_bits,
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+    );
+
+    let mut partitioned_relation = PartitionedRelation::new(
+        TUPLES,
+        histogram_algorithm.into(),
+        radix_bits,
+        grid_size.x,
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+    );
+
+    let mut partitioner = GpuRadixPartitioner::new(
+        histogram_algorithm,
+        partition_algorithm,
+        RadixBits::from(radix_bits),
+        &grid_size,
+        &block_size,
+        DMEM_BUFFER_BYTES,
+    )?;
+
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    partitioner.prefix_sum(
+        RadixPass::First,
+        data_key.as_launchable_slice(),
+        &mut partition_offsets,
+        &stream,
+    )?;
+
+    partitioner.partition(
+        RadixPass::First,
+        data_key.as_launchable_slice(),
+        data_pay.as_launchable_slice(),
+        &mut partition_offsets,
+        &mut partitioned_relation,
+        &stream,
+    )?;
+
+    stream.synchronize()?;
+
+    Ok(())
+}
+
 fn run_gpu_partitioning_and_copy_with_payload<KeyGenFn, PayGenFn, ValidatorFn>(
     tuples: usize,
     key_gen: Box<KeyGenFn>,
@@ -282,6 +354,131 @@ where
     Ok(())
 }
 
+fn run_gpu_fused_partitioning<KeyGenFn, PayGenFn, ValidatorFn>(
+    tuples: usize,
+    key_gen: Box<KeyGenFn>,
+    pay_gen: Box<PayGenFn>,
+    radix_bits: RadixBits,
+    grid_size: GridSize,
+    block_size: BlockSize,
+    mut validator: Box<ValidatorFn>,
+) -> Result<(), Box<dyn Error>>
+where
+    KeyGenFn: FnOnce(&mut [i32]) -> Result<(), Box<dyn Error>>,
+    PayGenFn: FnOnce(&mut [i32]) -> Result<(), Box<dyn Error>>,
+    ValidatorFn: FnMut(
+        RadixPass,
+        &RadixBits,
+        &[i32],
+        &[i32],
+        &PartitionedRelation<Tuple<i32, i32>>,
+        Option<u32>,
+    ) -> Result<(), Box<dyn Error>>,
+{
+    const DMEM_BUFFER_BYTES: usize = 8 * 1024;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let mut data_key = Allocator::alloc_deref_mem::<i32>(DerefMemType::CudaPinnedMem, tuples);
+    let mut data_pay = Allocator::alloc_deref_mem::<i32>(DerefMemType::CudaPinnedMem, tuples);
+
+    key_gen(data_key.as_mut_slice())?;
+    pay_gen(data_pay.as_mut_slice())?;
+
+    // Ensure that the allocated memory is zeroed
+    let alloc_fn = || {
+        Box::new(|len: usize| -> Mem<Tuple<i32, i32>> {
+            let mut mem = Allocator::alloc_deref_mem(DerefMemType::CudaUniMem, len);
+            mem.iter_mut().for_each(|x| {
+                *x = Tuple {
+                    key: i32::null_key(),
+                    value: i32::default(),
+                }
+            });
+            mem.into()
+        })
+    };
+
+    let mut partition_offsets = PartitionOffsets::new(
+        GpuHistogramAlgorithm::Chunked.into(),
+        grid_size.x,
+        radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+    );
+
+    let mut separate_relation = PartitionedRelation::new(
+        tuples,
+        GpuHistogramAlgorithm::Chunked.into(),
+        radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
+        grid_size.x,
+        alloc_fn(),
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+    );
+
+    let mut fused_relation = PartitionedRelation::new(
+        tuples,
+        GpuHistogramAlgorithm::Chunked.into(),
+        radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
+        grid_size.x,
+        alloc_fn(),
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+    );
+
+    let mut partitioner = GpuRadixPartitioner::new(
+        GpuHistogramAlgorithm::Chunked,
+        GpuRadixPartitionAlgorithm::NC,
+        radix_bits.into(),
+        &grid_size,
+        &block_size,
+        DMEM_BUFFER_BYTES,
+    )?;
+
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    partitioner.prefix_sum(
+        RadixPass::First,
+        data_key.as_launchable_slice(),
+        &mut partition_offsets,
+        &stream,
+    )?;
+
+    partitioner.partition(
+        RadixPass::First,
+        data_key.as_launchable_slice(),
+        data_pay.as_launchable_slice(),
+        &mut partition_offsets,
+        &mut separate_relation,
+        &stream,
+    )?;
+
+    partitioner.prefix_sum_and_partition(
+        RadixPass::First,
+        data_key.as_launchable_slice(),
+        data_pay.as_launchable_slice(),
+        &mut fused_relation,
+        &stream,
+    )?;
+
+    stream.synchronize()?;
+
+    let separate_offsets: &[u64] = (&separate_relation.offsets)
+        .try_into()
+        .map_err(|(e, _)| e)?;
+    let fused_offsets: &[u64] = (&fused_relation.offsets).try_into().map_err(|(e, _)| e)?;
+    assert_eq!(separate_offsets, fused_offsets);
+
+    validator(
+        RadixPass::First,
+        &radix_bits,
+        data_key.as_slice(),
+        data_pay.as_slice(),
+        &fused_relation,
+        None,
+    )?;
+
+    Ok(())
+}
+
 fn run_gpu_two_pass_partitioning<KeyGenFn, PayGenFn, ValidatorFn>(
     tuples: usize,
     key_gen: Box<KeyGenFn>,
@@ -575,6 +772,45 @@ fn gpu_verify_partitions_chunked_i32_10_bits() -> Result<(), Box<dyn Error>> {
     )
 }
 
+#[test]
+fn gpu_fused_tuple_loss_or_duplicates_chunked_i32_2_bits() -> Result<(), Box<dyn Error>> {
+    run_gpu_fused_partitioning(
+        (32 << 20) / mem::size_of::<i32>(),
+        Box::new(|keys: &mut _| Ok(UniformRelation::gen_primary_key(keys, None)?)),
+        Box::new(|pays: &mut _| Ok(UniformRelation::gen_attr(pays, 0..10000)?)),
+        RadixBits::from(2),
+        GridSize::from(10),
+        BlockSize::from(128),
+        Box::new(&tuple_loss_or_duplicates),
+    )
+}
+
+#[test]
+fn gpu_fused_verify_partitions_chunked_i32_10_bits() -> Result<(), Box<dyn Error>> {
+    run_gpu_fused_partitioning(
+        (32 << 20) / mem::size_of::<i32>(),
+        Box::new(|keys: &mut _| Ok(UniformRelation::gen_attr(keys, 0..(32 << 20))?)),
+        Box::new(|pays: &mut _| Ok(UniformRelation::gen_attr(pays, 0..10000)?)),
+        RadixBits::from(10),
+        GridSize::from(10),
+        BlockSize::from(128),
+        Box::new(&verify_partitions),
+    )
+}
+
+#[test]
+fn gpu_fused_verify_partitions_chunked_i32_unaligned_len() -> Result<(), Box<dyn Error>> {
+    run_gpu_fused_partitioning(
+        (32 << 20) / mem::size_of::<i32>() + 67,
+        Box::new(|keys: &mut _| Ok(UniformRelation::gen_attr(keys, 0..(32 << 20))?)),
+        Box::new(|pays: &mut _| Ok(UniformRelation::gen_attr(pays, 0..10000)?)),
+        RadixBits::from(8),
+        GridSize::from(7),
+        BlockSize::from(256),
+        Box::new(&verify_partitions),
+    )
+}
+
 #[test]
 fn gpu_tuple_loss_or_duplicates_chunked_i32_12_bits() -> Result<(), Box<dyn Error>> {
     run_gpu_partitioning(
@@ -1548,3 +1784,311 @@ fn gpu_verify_partitions_two_pass_sswwc_v2_i32_6_6_bits() -> Result<(), Box<dyn
         Box::new(&two_pass_verify_partitions),
     )
 }
+
+#[test]
+fn gpu_radix_bits_valid_sswwc_v2_i64() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let max_radix_bits = GpuRadixPartitioner::max_radix_bits::<i64>(
+        GpuHistogramAlgorithm::Chunked,
+        GpuRadixPartitionAlgorithm::SSWWCv2,
+        &BlockSize::from(128),
+    )?;
+    assert!(max_radix_bits > 8);
+
+    run_gpu_partitioning_i64(GpuRadixPartitionAlgorithm::SSWWCv2, 8)?;
+
+    Ok(())
+}
+
+#[test]
+fn gpu_radix_bits_boundary_sswwc_v2_i64() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let max_radix_bits = GpuRadixPartitioner::max_radix_bits::<i64>(
+        GpuHistogramAlgorithm::Chunked,
+        GpuRadixPartitionAlgorithm::SSWWCv2,
+        &BlockSize::from(128),
+    )?;
+
+    run_gpu_partitioning_i64(GpuRadixPartitionAlgorithm::SSWWCv2, max_radix_bits)?;
+
+    Ok(())
+}
+
+#[test]
+fn gpu_radix_bits_over_limit_sswwc_v2_i64() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let max_radix_bits = GpuRadixPartitioner::max_radix_bits::<i64>(
+        GpuHistogramAlgorithm::Chunked,
+        GpuRadixPartitionAlgorithm::SSWWCv2,
+        &BlockSize::from(128),
+    )?;
+
+    match run_gpu_partitioning_i64(GpuRadixPartitionAlgorithm::SSWWCv2, max_radix_bits + 1) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for too many radix bits"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpu_radix_bits_over_limit_fails_at_construction() -> Result<(), Box<dyn Error>> {
+    const DMEM_BUFFER_BYTES: usize = 8 * 1024;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let block_size = BlockSize::from(128);
+    let max_radix_bits = GpuRadixPartitioner::max_radix_bits::<i32>(
+        GpuHistogramAlgorithm::Chunked,
+        GpuRadixPartitionAlgorithm::SSWWCv2,
+        &block_size,
+    )?;
+
+    GpuRadixPartitioner::new(
+        GpuHistogramAlgorithm::Chunked,
+        GpuRadixPartitionAlgorithm::SSWWCv2,
+        RadixBits::from(max_radix_bits),
+        &GridSize::from(4),
+        &block_size,
+        DMEM_BUFFER_BYTES,
+    )?;
+
+    match GpuRadixPartitioner::new(
+        GpuHistogramAlgorithm::Chunked,
+        GpuRadixPartitionAlgorithm::SSWWCv2,
+        RadixBits::from(max_radix_bits + 1),
+        &GridSize::from(4),
+        &block_size,
+        DMEM_BUFFER_BYTES,
+    ) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error for too many radix bits"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpu_max_radix_bits_decreases_with_tuple_size() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let max_radix_bits = |partition_algorithm| -> Result<(u32, u32), Box<dyn Error>> {
+        let block_size = BlockSize::from(1024);
+        let narrow = GpuRadixPartitioner::max_radix_bits::<i32>(
+            GpuHistogramAlgorithm::Chunked,
+            partition_algorithm,
+            &block_size,
+        )?;
+        let wide = GpuRadixPartitioner::max_radix_bits::<i64>(
+            GpuHistogramAlgorithm::Chunked,
+            partition_algorithm,
+            &block_size,
+        )?;
+        Ok((narrow, wide))
+    };
+
+    let (narrow, wide) = max_radix_bits(GpuRadixPartitionAlgorithm::NC)?;
+    assert_eq!(narrow, wide);
+
+    let (narrow, wide) = max_radix_bits(GpuRadixPartitionAlgorithm::LASWWC)?;
+    assert!(narrow >= wide);
+
+    Ok(())
+}
+
+#[test]
+fn gpu_partition_empty_relation() -> Result<(), Box<dyn Error>> {
+    for &partition_algorithm in &[
+        GpuRadixPartitionAlgorithm::NC,
+        GpuRadixPartitionAlgorithm::LASWWC,
+        GpuRadixPartitionAlgorithm::SSWWCv2,
+    ] {
+        run_gpu_partitioning(
+            0,
+            Box::new(|_: &mut _| Ok(())),
+            Box::new(|_: &mut _| Ok(())),
+            DeviceType::Gpu(GpuHistogramAlgorithm::Chunked),
+            partition_algorithm,
+            RadixBits::from(6),
+            GridSize::from(4),
+            BlockSize::from(128),
+            Box::new(
+                |_: RadixPass,
+                 _: &RadixBits,
+                 _: &[i32],
+                 _: &[i32],
+                 partitioned_relation: &PartitionedRelation<Tuple<i32, i32>>,
+                 _: Option<u32>|
+                 -> Result<(), Box<dyn Error>> {
+                    assert_eq!(0, partitioned_relation.len());
+                    for partition_id in 0..partitioned_relation.fanout() {
+                        assert_eq!(0, partitioned_relation.partition_len(partition_id)?);
+                    }
+                    Ok(())
+                },
+            ),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Copies the source attributes into destination attributes of `dst_len`
+/// tuples.
+fn copy_with_payload_lens(
+    src_key_len: usize,
+    src_pay_len: usize,
+    dst_len: usize,
+) -> Result<(), sql_ops::error::Error> {
+    const DMEM_BUFFER_BYTES: usize = 8 * 1024;
+
+    let histogram_algorithm = GpuHistogramAlgorithm::Contiguous;
+    let radix_bits = RadixBits::from(6);
+    let grid_size = GridSize::from(4);
+    let block_size = BlockSize::from(128);
+
+    let data_key = Allocator::alloc_deref_mem::<i32>(DerefMemType::CudaPinnedMem, src_key_len);
+    let data_pay = Allocator::alloc_deref_mem::<i32>(DerefMemType::CudaPinnedMem, src_pay_len);
+    let mut cached_key: LockedBuffer<i32> = LockedBuffer::new(&0, dst_len)?;
+    let mut cached_pay: LockedBuffer<i32> = LockedBuffer::new(&0, dst_len)?;
+
+    let mut partition_offsets = PartitionOffsets::new(
+        histogram_algorithm.into(),
+        grid_size.x,
+        radix_bits.pass_radix_bits(RadixPass::First).unwrap(),
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+    );
+
+    let mut partitioner = GpuRadixPartitioner::new(
+        histogram_algorithm,
+        GpuRadixPartitionAlgorithm::NC,
+        radix_bits,
+        &grid_size,
+        &block_size,
+        DMEM_BUFFER_BYTES,
+    )?;
+
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    partitioner.prefix_sum_and_copy_with_payload(
+        RadixPass::First,
+        data_key.as_launchable_slice(),
+        data_pay.as_launchable_slice(),
+        cached_key.as_launchable_mut_slice(),
+        cached_pay.as_launchable_mut_slice(),
+        &mut partition_offsets,
+        &stream,
+    )?;
+    stream.synchronize()?;
+
+    Ok(())
+}
+
+#[test]
+fn gpu_copy_with_payload_accepts_equal_lengths() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    copy_with_payload_lens(1000, 1000, 1000)?;
+
+    Ok(())
+}
+
+#[test]
+fn gpu_copy_with_payload_rejects_mismatched_lengths() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    for (src_key_len, src_pay_len, dst_len) in [(1000, 999, 1000), (1000, 1000, 999)] {
+        match copy_with_payload_lens(src_key_len, src_pay_len, dst_len) {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidArgument(_) => {}
+                _ => panic!("Expected an invalid argument error, got: {}", e),
+            },
+            Ok(_) => panic!(
+                "Accepted {} keys and {} payloads with {} destination tuples",
+                src_key_len, src_pay_len, dst_len
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpu_partition_relation_tuple_loss_or_duplicates() -> Result<(), Box<dyn Error>> {
+    const TUPLES: usize = 1 << 16;
+    const DMEM_BUFFER_BYTES: usize = 8 * 1024;
+    const RADIX_BITS: u32 = 6;
+
+    let grid_size = GridSize::from(8);
+    let block_size = BlockSize::from(128);
+    let histogram_algorithm = GpuHistogramAlgorithm::Chunked;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let mut data_key = Allocator::alloc_deref_mem::<i32>(DerefMemType::CudaPinnedMem, TUPLES);
+    let mut data_pay = Allocator::alloc_deref_mem::<i32>(DerefMemType::CudaPinnedMem, TUPLES);
+    UniformRelation::gen_primary_key(data_key.as_mut_slice(), None)?;
+    UniformRelation::gen_attr(data_pay.as_mut_slice(), 0..10000)?;
+    let relation = Relation::new(data_key.into(), data_pay.into())?;
+
+    let mut partition_offsets = PartitionOffsets::new(
+        histogram_algorithm.into(),
+        grid_size.x,
+        RADIX_BITS,
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+    );
+
+    let mut partitioned_relation = PartitionedRelation::new(
+        TUPLES,
+        histogram_algorithm.into(),
+        RADIX_BITS,
+        grid_size.x,
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+        Allocator::mem_alloc_fn(MemType::CudaUniMem),
+    );
+
+    let mut partitioner = GpuRadixPartitioner::new(
+        histogram_algorithm,
+        GpuRadixPartitionAlgorithm::NC,
+        RadixBits::from(RADIX_BITS),
+        &grid_size,
+        &block_size,
+        DMEM_BUFFER_BYTES,
+    )?;
+
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    partitioner.prefix_sum_relation(
+        RadixPass::First,
+        &relation,
+        &mut partition_offsets,
+        &stream,
+    )?;
+    partitioner.partition_relation(
+        RadixPass::First,
+        &relation,
+        &mut partition_offsets,
+        &mut partitioned_relation,
+        &stream,
+    )?;
+
+    stream.synchronize()?;
+
+    let (data_key, data_pay) = relation.try_as_slices()?;
+    tuple_loss_or_duplicates(
+        RadixPass::First,
+        &RadixBits::from(RADIX_BITS),
+        data_key,
+        data_pay,
+        &partitioned_relation,
+        None,
+    )
+}
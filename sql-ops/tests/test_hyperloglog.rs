@@ -0,0 +1,118 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType};
+use numa_gpu::runtime::memory::Mem;
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rustacuda::context::{Context, CurrentContext, UnownedContext};
+use rustacuda::stream::{Stream, StreamFlags};
+use sql_ops::cardinality::{DistinctEstimate, HyperLogLog};
+use std::error::Error;
+use std::result::Result;
+
+static mut CUDA_CONTEXT_OWNER: Option<Context> = None;
+static CUDA_CONTEXT: Lazy<UnownedContext> = Lazy::new(|| {
+    let context = rustacuda::quick_init().expect("Failed to initialize CUDA context");
+    let unowned = context.get_unowned();
+
+    unsafe {
+        CUDA_CONTEXT_OWNER = Some(context);
+    }
+
+    unowned
+});
+
+const DISTINCT_KEYS: usize = 100_000;
+const REPEATS: usize = 8;
+const PRECISION: u32 = 12;
+
+/// Generates a shuffled column in which each of the distinct keys occurs
+/// `REPEATS` times.
+fn gen_column(mem_type: DerefMemType) -> Mem<i64> {
+    let mut keys: Vec<i64> = (0..DISTINCT_KEYS as i64)
+        .flat_map(|key| std::iter::repeat(key * 7 + 3).take(REPEATS))
+        .collect();
+    keys.shuffle(&mut StdRng::seed_from_u64(42));
+
+    let mut column = Allocator::alloc_deref_mem(mem_type, keys.len());
+    column.copy_from_slice(&keys);
+
+    Mem::from(column)
+}
+
+/// Asserts that the estimate is within three standard errors of the distinct
+/// count, i.e., a bound that holds with a probability of about 99.7%.
+fn assert_within_error_bound(estimate: DistinctEstimate) {
+    let error = (estimate.distinct as f64 - DISTINCT_KEYS as f64).abs() / DISTINCT_KEYS as f64;
+
+    assert!(
+        error <= 3.0 * estimate.standard_error,
+        "Estimated {} distinct keys instead of {} (relative error {})",
+        estimate.distinct,
+        DISTINCT_KEYS,
+        error
+    );
+}
+
+#[test]
+fn hyperloglog_rejects_invalid_precision() {
+    assert!(HyperLogLog::new(HyperLogLog::MIN_PRECISION - 1).is_err());
+    assert!(HyperLogLog::new(HyperLogLog::MAX_PRECISION + 1).is_err());
+}
+
+#[test]
+fn hyperloglog_cpu_estimate_within_error_bound() -> Result<(), Box<dyn Error>> {
+    let column = gen_column(DerefMemType::SysMem);
+    let hll = HyperLogLog::new(PRECISION)?;
+
+    let estimate = hll.estimate_cpu(&column)?;
+    assert_eq!(hll.standard_error(), estimate.standard_error);
+    assert_within_error_bound(estimate);
+
+    Ok(())
+}
+
+#[test]
+fn hyperloglog_cpu_estimates_small_count_exactly() -> Result<(), Box<dyn Error>> {
+    let column: Mem<i32> = Mem::from(Allocator::alloc_deref_mem(DerefMemType::SysMem, 0));
+    let hll = HyperLogLog::new(PRECISION)?;
+    assert_eq!(0, hll.estimate_cpu(&column)?.distinct);
+
+    let mut column = Allocator::alloc_deref_mem(DerefMemType::SysMem, 1000);
+    column.iter_mut().for_each(|key| *key = 5_i32);
+    assert_eq!(1, hll.estimate_cpu(&Mem::from(column))?.distinct);
+
+    Ok(())
+}
+
+#[test]
+fn hyperloglog_gpu_estimate_equals_cpu_estimate() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    let column = gen_column(DerefMemType::CudaPinnedMem);
+    let hll = HyperLogLog::new(PRECISION)?;
+
+    let gpu_estimate = hll.estimate_gpu(&column, &stream)?;
+    assert_within_error_bound(gpu_estimate);
+
+    // The merged block registers equal the CPU registers
+    let cpu_estimate = hll.estimate_cpu(&column)?;
+    assert_eq!(cpu_estimate, gpu_estimate);
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod join_equivalence;
+
+use datagen::relation::{KeyAttribute, UniformRelation};
+use join_equivalence::{
+    assert_join_equivalent, assert_matches_equivalent, cpu_join_matches, first_difference,
+    gpu_join_matches, JoinRelations,
+};
+use num_traits::FromPrimitive;
+use once_cell::sync::Lazy;
+use rustacuda::context::{Context, CurrentContext, UnownedContext};
+use sql_ops::join::no_partitioning_join::JoinMatch;
+use sql_ops::join::HashingScheme;
+use std::error::Error;
+use std::result::Result;
+
+static mut CUDA_CONTEXT_OWNER: Option<Context> = None;
+static CUDA_CONTEXT: Lazy<UnownedContext> = Lazy::new(|| {
+    let context = rustacuda::quick_init().expect("Failed to initialize CUDA context");
+    let unowned = context.get_unowned();
+
+    unsafe {
+        CUDA_CONTEXT_OWNER = Some(context);
+    }
+
+    unowned
+});
+
+/// Generates a primary key build relation and a foreign key probe relation.
+///
+/// The payloads are the row IDs, thus each match identifies its build and
+/// probe tuples.
+fn gen_relations<T>(
+    build_rows: usize,
+    probe_rows: usize,
+) -> Result<JoinRelations<T>, Box<dyn Error>>
+where
+    T: Copy + Default + FromPrimitive + KeyAttribute,
+{
+    let mut relations = JoinRelations {
+        build_key: vec![T::default(); build_rows],
+        build_pay: (0..build_rows).map(|i| T::from_usize(i).unwrap()).collect(),
+        probe_key: vec![T::default(); probe_rows],
+        probe_pay: (0..probe_rows).map(|i| T::from_usize(i).unwrap()).collect(),
+    };
+
+    UniformRelation::gen_primary_key(&mut relations.build_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(
+        &mut relations.probe_key,
+        &relations.build_key,
+    );
+
+    Ok(relations)
+}
+
+#[test]
+fn cpu_and_gpu_joins_are_equivalent_i32() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let relations = gen_relations::<i32>(1000, 10_000)?;
+    assert_join_equivalent(&relations, HashingScheme::LinearProbing)
+}
+
+#[test]
+fn cpu_and_gpu_joins_are_equivalent_i64() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let relations = gen_relations::<i64>(1000, 10_000)?;
+    assert_join_equivalent(&relations, HashingScheme::LinearProbing)
+}
+
+#[test]
+fn perturbed_gpu_join_result_is_detected() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+
+    let relations = gen_relations::<i32>(1000, 10_000)?;
+    let cpu = cpu_join_matches(&relations, HashingScheme::LinearProbing)?;
+    let mut gpu = gpu_join_matches(&relations, HashingScheme::LinearProbing)?;
+    assert_eq!(None, first_difference(&cpu, &gpu));
+
+    // Redirect one match to a build tuple that doesn't exist
+    gpu[0].build_payload = -1;
+    let difference = first_difference(&cpu, &gpu).expect("Perturbed result wasn't detected");
+    assert!(difference.contains("(-1,"), "{}", difference);
+
+    // Lose one match
+    let mut truncated = cpu.clone();
+    truncated.pop();
+    let difference = first_difference(&cpu, &truncated).expect("Truncated result wasn't detected");
+    assert!(difference.contains("surplus CPU match"), "{}", difference);
+
+    Ok(())
+}
+
+#[test]
+fn join_results_are_compared_independent_of_order() {
+    let matches: Vec<_> = (0..100)
+        .map(|i| JoinMatch {
+            build_payload: i % 10,
+            probe_payload: i,
+        })
+        .collect();
+    let reversed: Vec<_> = matches.iter().rev().copied().collect();
+
+    assert_matches_equivalent(&matches, &reversed);
+}
+
+#[test]
+#[should_panic(expected = "differ at match 1")]
+fn duplicated_join_match_fails_assertion() {
+    let cpu = [
+        JoinMatch {
+            build_payload: 1,
+            probe_payload: 1,
+        },
+        JoinMatch {
+            build_payload: 2,
+            probe_payload: 2,
+        },
+    ];
+    let gpu = [cpu[0], cpu[0]];
+
+    assert_matches_equivalent(&cpu, &gpu);
+}
@@ -0,0 +1,234 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datagen::relation::UniformRelation;
+use itertools::izip;
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use sql_ops::error::ErrorKind;
+use sql_ops::partition::cpu_radix_partition::{
+    CpuHistogramAlgorithm, CpuRadixPartitionAlgorithm, CpuRadixPartitioner,
+};
+use sql_ops::partition::{
+    PartitionOffsets, PartitionedRelation, RadixPartitionInputChunkable, Tuple,
+};
+use sql_ops::FileTypeTag;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::result::Result;
+
+const TUPLES: usize = 1 << 16;
+const RADIX_BITS: u32 = 6;
+const THREADS: u32 = 4;
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sql_ops_{}_{}.bin", name, std::process::id()))
+}
+
+fn partition_relation() -> Result<PartitionedRelation<Tuple<i32, i32>>, Box<dyn Error>> {
+    let histogram_algorithm = CpuHistogramAlgorithm::Chunked;
+
+    let mut data_key = vec![0_i32; TUPLES];
+    let data_pay: Vec<i32> = (0..TUPLES as i32).collect();
+    UniformRelation::gen_primary_key(&mut data_key, None)?;
+
+    let mut partition_offsets = PartitionOffsets::new(
+        histogram_algorithm.into(),
+        THREADS,
+        RADIX_BITS,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let mut partitioned_relation = PartitionedRelation::new(
+        TUPLES,
+        histogram_algorithm.into(),
+        RADIX_BITS,
+        THREADS,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    unsafe {
+        partitioned_relation
+            .as_raw_relation_mut_slice()?
+            .iter_mut()
+            .for_each(|x| *x = Tuple::default());
+    }
+
+    let mut partitioner = CpuRadixPartitioner::new(
+        histogram_algorithm,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?;
+
+    let key_chunks = data_key.as_slice().input_chunks::<i32>(THREADS)?;
+    for (key_chunk, offsets_chunk) in izip!(key_chunks.into_iter(), partition_offsets.chunks_mut())
+    {
+        partitioner.prefix_sum(key_chunk, offsets_chunk)?;
+    }
+
+    let key_chunks = data_key.as_slice().input_chunks::<i32>(THREADS)?;
+    let pay_chunks = data_pay.as_slice().input_chunks::<i32>(THREADS)?;
+    for (key_chunk, pay_chunk, offsets_chunk, partitioned_chunk) in izip!(
+        key_chunks.into_iter(),
+        pay_chunks.into_iter(),
+        partition_offsets.chunks_mut(),
+        partitioned_relation.chunks_mut()
+    ) {
+        partitioner.partition(key_chunk, pay_chunk, offsets_chunk, partitioned_chunk)?;
+    }
+
+    Ok(partitioned_relation)
+}
+
+#[test]
+fn save_and_load_round_trip() -> Result<(), Box<dyn Error>> {
+    let path = temp_file("round_trip");
+    let original = partition_relation()?;
+
+    original.save(&path)?;
+    let loaded = PartitionedRelation::<Tuple<i32, i32>>::load(
+        &path,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    fs::remove_file(&path)?;
+    let loaded = loaded?;
+
+    assert_eq!(original.len(), loaded.len());
+    assert_eq!(original.radix_bits(), loaded.radix_bits());
+    assert_eq!(original.num_chunks(), loaded.num_chunks());
+
+    for chunk_id in 0..original.num_chunks() {
+        for partition_id in 0..original.fanout() {
+            assert_eq!(
+                &original[(chunk_id, partition_id)],
+                &loaded[(chunk_id, partition_id)]
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn load_rejects_mismatching_tuple_type() -> Result<(), Box<dyn Error>> {
+    let path = temp_file("type_mismatch");
+    partition_relation()?.save(&path)?;
+
+    let loaded = PartitionedRelation::<Tuple<i64, i64>>::load(
+        &path,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    fs::remove_file(&path)?;
+
+    match loaded {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error when loading a mismatching tuple type"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn load_rejects_foreign_file() -> Result<(), Box<dyn Error>> {
+    let path = temp_file("foreign_file");
+    fs::write(&path, b"this is not a partitioned relation")?;
+
+    let loaded = PartitionedRelation::<Tuple<i32, i32>>::load(
+        &path,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    fs::remove_file(&path)?;
+
+    assert!(loaded.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn save_writes_explicit_type_tag() -> Result<(), Box<dyn Error>> {
+    let path = temp_file("type_tag");
+    partition_relation()?.save(&path)?;
+    let bytes = fs::read(&path)?;
+    fs::remove_file(&path)?;
+
+    // The tag follows the magic and the version
+    let mut tag = [0_u8; 4];
+    tag.copy_from_slice(&bytes[12..16]);
+    assert_eq!(u32::from_le_bytes(tag), Tuple::<i32, i32>::FILE_TYPE_TAG);
+    assert_eq!(Tuple::<i32, i32>::FILE_TYPE_TAG, 0x0001_0001);
+
+    Ok(())
+}
+
+#[test]
+fn load_rejects_truncated_file() -> Result<(), Box<dyn Error>> {
+    let path = temp_file("truncated");
+    partition_relation()?.save(&path)?;
+    let len = fs::metadata(&path)?.len();
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&path)?
+        .set_len(len - 1)?;
+
+    let loaded = PartitionedRelation::<Tuple<i32, i32>>::load(
+        &path,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    fs::remove_file(&path)?;
+
+    match loaded {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error when loading a truncated file"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn load_rejects_out_of_bounds_offsets() -> Result<(), Box<dyn Error>> {
+    const HEADER_BYTES: usize = 40;
+
+    let path = temp_file("bad_offsets");
+    partition_relation()?.save(&path)?;
+    let mut bytes = fs::read(&path)?;
+    bytes[HEADER_BYTES..HEADER_BYTES + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    fs::write(&path, &bytes)?;
+
+    let loaded = PartitionedRelation::<Tuple<i32, i32>>::load(
+        &path,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    fs::remove_file(&path)?;
+
+    match loaded {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an InvalidArgument error, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error when loading out-of-bounds offsets"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datagen::relation::UniformRelation;
+use sql_ops::join::wide_payload_join::WidePayloadHashTable;
+use sql_ops::partition::{Payload, Tuple};
+use std::error::Error;
+use std::result::Result;
+
+const PAYLOAD_BYTES: usize = 64;
+
+/// Fills a payload with a byte pattern that is unique for each key.
+fn payload_of(key: i64) -> Payload<PAYLOAD_BYTES> {
+    let mut payload = Payload::<PAYLOAD_BYTES>::default();
+    let key_bytes = key.to_le_bytes();
+    payload
+        .0
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, byte)| *byte = key_bytes[i % key_bytes.len()] ^ (i as u8));
+    payload
+}
+
+#[test]
+fn payload_default_is_zeroed() {
+    let payload = Payload::<PAYLOAD_BYTES>::default();
+
+    assert!(payload.as_bytes().iter().all(|&byte| byte == 0));
+}
+
+/// Joins build tuples with `Payload<64>` values, and checks that each match
+/// carries the bytes of its build tuple.
+///
+/// The hash tables in `no_partitioning_join` and the radix partitioners store
+/// keys and values of the same type. Thus, `Payload` is joined with the wide
+/// payload hash table.
+#[test]
+fn payload_join_preserves_bytes() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 12;
+
+    let mut inner_rel_key = vec![0_i64; ROWS];
+    let mut outer_rel_key = vec![0_i64; 2 * ROWS];
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    let inner_rel: Vec<Tuple<i64, Payload<PAYLOAD_BYTES>>> = inner_rel_key
+        .iter()
+        .map(|&key| Tuple {
+            key,
+            value: payload_of(key),
+        })
+        .collect();
+
+    let (build_keys, build_payloads): (Vec<_>, Vec<_>) = inner_rel
+        .iter()
+        .map(|tuple| (tuple.key, tuple.value.0))
+        .unzip();
+
+    let mut hash_table = WidePayloadHashTable::<i64, PAYLOAD_BYTES>::new(2 * ROWS)?;
+    hash_table.build(&build_keys, &build_payloads)?;
+
+    let mut join_result = vec![[0_u8; PAYLOAD_BYTES]; outer_rel_key.len()];
+    let matches = hash_table.probe(&outer_rel_key, &mut join_result)?;
+
+    assert_eq!(outer_rel_key.len() as u64, matches);
+    for (&key, bytes) in outer_rel_key.iter().zip(join_result.into_iter()) {
+        assert_eq!(payload_of(key), Payload::from(bytes));
+    }
+
+    Ok(())
+}
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use numa_gpu::runtime::memory::LaunchableMem;
 use rand::{thread_rng, Rng};
 use rustacuda::context::CurrentContext;
 use rustacuda::device::DeviceAttribute;
@@ -19,7 +20,9 @@ use rustacuda::function::{BlockSize, GridSize};
 use rustacuda::memory::CopyDestination;
 use rustacuda::prelude::*;
 use rustacuda::{launch, launch_cooperative};
-use sql_ops::prefix_scan::{GpuPrefixScanState, GpuPrefixSum};
+use sql_ops::prefix_scan::{
+    exclusive_prefix_sum, GpuPrefixScanState, GpuPrefixScannable, GpuPrefixSum,
+};
 use std::error::Error;
 use std::ffi::CString;
 use std::mem::size_of;
@@ -154,6 +157,36 @@ where
     Ok(())
 }
 
+fn multi_level_exclusive_scan<T>(data: Vec<T>) -> Result<(), Box<dyn Error>>
+where
+    T: GpuPrefixScannable + std::fmt::Debug + PartialEq + std::ops::Add<Output = T>,
+{
+    let _context = rustacuda::quick_init()?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    let mut dev_data = DeviceBuffer::from_slice(&data)?;
+    GpuPrefixSum::exclusive_scan(dev_data.as_launchable_mut_slice(), &stream)?;
+
+    let mut result = vec![T::default(); data.len()];
+    dev_data.copy_to(&mut result)?;
+
+    let prefix_sum: Vec<_> = data
+        .iter()
+        .scan(T::default(), |sum, &item| {
+            let old_sum = *sum;
+            *sum = *sum + item;
+            Some(old_sum)
+        })
+        .collect();
+
+    assert!(
+        prefix_sum == result,
+        "GPU prefix scan differs from CPU prefix scan"
+    );
+
+    Ok(())
+}
+
 #[test]
 fn block_prefix_sum_block_size() -> Result<(), Box<dyn Error>> {
     block_prefix_sum(1024_usize, 1_u32, 1024_u32)
@@ -198,3 +231,38 @@ fn device_prefix_sum_hundred_blocks() -> Result<(), Box<dyn Error>> {
 fn device_prefix_sum_multiple_items_per_thread() -> Result<(), Box<dyn Error>> {
     device_prefix_sum(100_usize * 1024, 2_u32, 1024_u32)
 }
+
+#[test]
+fn exclusive_scan_single_tile() -> Result<(), Box<dyn Error>> {
+    let data: Vec<u64> = (0..1000).collect();
+    multi_level_exclusive_scan(data)
+}
+
+#[test]
+fn exclusive_scan_two_levels() -> Result<(), Box<dyn Error>> {
+    let data: Vec<u64> = (0..1_000_003)
+        .scan(thread_rng(), |rng, _| Some(rng.gen_range(0, 1 << 32)))
+        .collect();
+    multi_level_exclusive_scan(data)
+}
+
+#[test]
+fn exclusive_scan_far_larger_than_a_block() -> Result<(), Box<dyn Error>> {
+    // Spans three levels, and isn't a multiple of the tile size. The values
+    // are small, so that the u32 sums don't overflow.
+    const DATA_LEN: usize = 200_000_001;
+
+    let data: Vec<u32> = (0..DATA_LEN)
+        .scan(thread_rng(), |rng, _| Some(rng.gen_range(0, 16)))
+        .collect();
+    multi_level_exclusive_scan(data)
+}
+
+#[test]
+fn cpu_exclusive_prefix_sum_returns_total() {
+    let mut data: Vec<u64> = vec![3, 0, 5, 1, 0, 2];
+    let total = exclusive_prefix_sum(&mut data);
+
+    assert_eq!(data, vec![0, 3, 3, 8, 9, 9]);
+    assert_eq!(total, 11);
+}
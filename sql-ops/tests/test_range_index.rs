@@ -0,0 +1,140 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datagen::relation::{KeyAttribute, UniformRelation};
+use sql_ops::partition::range_index::RangeIndex;
+use sql_ops::partition::Tuple;
+use std::error::Error;
+use std::result::Result;
+
+fn expected_tuples<K, V>(keys: &[K], payloads: &[V], lo: K, hi: K) -> Vec<Tuple<K, V>>
+where
+    K: Copy + Ord + KeyAttribute,
+    V: Copy + Ord,
+{
+    let mut expected: Vec<_> = keys
+        .iter()
+        .zip(payloads.iter())
+        .filter(|&(&key, _)| key != K::null_key() && lo <= key && key <= hi)
+        .map(|(&key, &value)| Tuple { key, value })
+        .collect();
+    expected.sort_unstable_by_key(|tuple| (tuple.key, tuple.value));
+    expected
+}
+
+fn check_range_probe<K, V>(index: &RangeIndex<K, V>, keys: &[K], payloads: &[V], lo: K, hi: K)
+where
+    K: Copy + Default + Ord + Into<i64> + KeyAttribute + std::fmt::Debug,
+    V: Copy + Default + Ord + std::fmt::Debug,
+{
+    let result = index.range_probe(lo, hi);
+    assert!(result.windows(2).all(|w| w[0].key <= w[1].key));
+
+    let mut result = result.to_vec();
+    result.sort_unstable_by_key(|tuple| (tuple.key, tuple.value));
+
+    assert_eq!(result, expected_tuples(keys, payloads, lo, hi));
+}
+
+#[test]
+fn range_probe_unique_keys_i32() -> Result<(), Box<dyn Error>> {
+    const LEN: usize = 1 << 16;
+
+    let mut keys = vec![0_i32; LEN];
+    let payloads: Vec<i32> = (0..LEN as i32).collect();
+    UniformRelation::gen_primary_key(&mut keys, None)?;
+
+    let index = RangeIndex::new(&keys, &payloads, 6)?;
+    assert_eq!(index.len(), LEN);
+
+    let bounds = [
+        (0, 0),
+        (0, LEN as i32 - 1),
+        (100, 200),
+        (1023, 1024),
+        (5000, 40000),
+        (LEN as i32 - 10, LEN as i32 + 10),
+        (-100, 10),
+    ];
+    for &(lo, hi) in &bounds {
+        check_range_probe(&index, &keys, &payloads, lo, hi);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn range_probe_duplicate_keys_i64() -> Result<(), Box<dyn Error>> {
+    const LEN: usize = 1 << 16;
+
+    let mut keys = vec![0_i64; LEN];
+    let payloads: Vec<i64> = (0..LEN as i64).collect();
+    UniformRelation::gen_attr(&mut keys, 0..1000)?;
+
+    let index = RangeIndex::new(&keys, &payloads, 4)?;
+
+    let bounds = [(0, 0), (10, 10), (0, 999), (250, 750), (998, 2000)];
+    for &(lo, hi) in &bounds {
+        check_range_probe(&index, &keys, &payloads, lo, hi);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn range_probe_negative_keys_i64() -> Result<(), Box<dyn Error>> {
+    let keys: Vec<i64> = vec![i64::MIN, -1000, -5, 0, 3, 7, 1 << 40, i64::MAX];
+    let payloads: Vec<i64> = (0..keys.len() as i64).collect();
+
+    let index = RangeIndex::new(&keys, &payloads, 3)?;
+
+    let bounds = [
+        (i64::MIN, i64::MAX),
+        (i64::MIN, -1000),
+        (-10, 10),
+        (1, 1 << 40),
+        (i64::MAX, i64::MAX),
+    ];
+    for &(lo, hi) in &bounds {
+        check_range_probe(&index, &keys, &payloads, lo, hi);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn range_probe_skips_null_keys() -> Result<(), Box<dyn Error>> {
+    let keys: Vec<i32> = vec![-2, i32::null_key(), 0, 1, i32::null_key(), 2];
+    let payloads: Vec<i32> = (0..keys.len() as i32).collect();
+
+    let index = RangeIndex::new(&keys, &payloads, 2)?;
+    assert_eq!(index.len(), 4);
+
+    check_range_probe(&index, &keys, &payloads, -2, 2);
+
+    Ok(())
+}
+
+#[test]
+fn range_probe_empty_range() -> Result<(), Box<dyn Error>> {
+    let keys: Vec<i32> = (0..100).collect();
+    let payloads = keys.clone();
+
+    let index = RangeIndex::new(&keys, &payloads, 2)?;
+
+    assert!(index.range_probe(50, 10).is_empty());
+    assert!(index.range_probe(200, 300).is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,187 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use numa_gpu::runtime::memory::LaunchableMem;
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rustacuda::context::{Context, CurrentContext, UnownedContext};
+use rustacuda::function::{BlockSize, GridSize};
+use rustacuda::memory::DeviceBuffer;
+use rustacuda::stream::{Stream, StreamFlags};
+use sql_ops::join::no_partitioning_join::{CudaHashJoinBuilder, HashTable};
+use sql_ops::join::HashingScheme;
+use sql_ops::reduction::GpuTreeSum;
+use std::error::Error;
+use std::result::Result;
+use std::sync::Arc;
+
+static mut CUDA_CONTEXT_OWNER: Option<Context> = None;
+static CUDA_CONTEXT: Lazy<UnownedContext> = Lazy::new(|| {
+    let context = rustacuda::quick_init().expect("Failed to initialize CUDA context");
+    let unowned = context.get_unowned();
+
+    unsafe {
+        CUDA_CONTEXT_OWNER = Some(context);
+    }
+
+    unowned
+});
+
+// Not a multiple of the values per block, and spans three tree levels
+const PARTIALS_LEN: usize = 1_000_003;
+
+/// Generates partial aggregates that span many orders of magnitude, so that
+/// the rounding error depends on the order of the additions.
+fn gen_float_partials() -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(42);
+    (0..PARTIALS_LEN)
+        .map(|_| (rng.gen::<f64>() - 0.5) * 10_f64.powi(rng.gen_range(-8, 8)))
+        .collect()
+}
+
+#[test]
+fn tree_sum_of_float_aggregate_is_bitwise_reproducible() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    let partials = gen_float_partials();
+    let dev_partials = DeviceBuffer::from_slice(&partials)?;
+
+    let first = GpuTreeSum::sum(dev_partials.as_launchable_slice(), &stream)?;
+    let second = GpuTreeSum::sum(dev_partials.as_launchable_slice(), &stream)?;
+    assert_eq!(first.to_bits(), second.to_bits());
+
+    let magnitude: f64 = partials.iter().map(|x| x.abs()).sum();
+    let expected: f64 = partials.iter().sum();
+    assert!(
+        (first - expected).abs() <= magnitude * 1e-12,
+        "GPU sum {} differs from CPU sum {}",
+        first,
+        expected
+    );
+
+    let partials_f32: Vec<f32> = partials.iter().map(|&x| x as f32).collect();
+    let dev_partials_f32 = DeviceBuffer::from_slice(&partials_f32)?;
+    let first_f32 = GpuTreeSum::sum(dev_partials_f32.as_launchable_slice(), &stream)?;
+    let second_f32 = GpuTreeSum::sum(dev_partials_f32.as_launchable_slice(), &stream)?;
+    assert_eq!(first_f32.to_bits(), second_f32.to_bits());
+
+    Ok(())
+}
+
+#[test]
+fn tree_sum_of_counts_is_exact() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for &len in &[1, 511, 512, 513, PARTIALS_LEN] {
+        let counts: Vec<u64> = (0..len).map(|_| rng.gen_range(0, 1 << 20)).collect();
+        let dev_counts = DeviceBuffer::from_slice(&counts)?;
+
+        let sum = GpuTreeSum::sum(dev_counts.as_launchable_slice(), &stream)?;
+        assert_eq!(
+            sum,
+            counts.iter().sum::<u64>(),
+            "Wrong sum of {} counts",
+            len
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn tree_sum_of_empty_input_is_zero() -> Result<(), Box<dyn Error>> {
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    let empty: Vec<f64> = Vec::new();
+    assert_eq!(
+        GpuTreeSum::sum(empty.as_slice().as_launchable_slice(), &stream)?,
+        0.0
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tree_sum_of_probe_results_is_reproducible_end_to_end() -> Result<(), Box<dyn Error>> {
+    const BUILD_LEN: usize = 1 << 12;
+    const PROBE_LEN: usize = 1 << 16;
+
+    CurrentContext::set_current(&*CUDA_CONTEXT)?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    let alloc_fn = Allocator::deref_mem_alloc_fn::<i32>(DerefMemType::CudaUniMem);
+
+    let mut build_key = alloc_fn(BUILD_LEN);
+    let mut build_pay = alloc_fn(BUILD_LEN);
+    let mut probe_key = alloc_fn(PROBE_LEN);
+    let mut probe_pay = alloc_fn(PROBE_LEN);
+
+    let mut rng = StdRng::seed_from_u64(3);
+    build_key
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, x)| *x = i as i32);
+    build_pay.iter_mut().for_each(|x| *x = 1);
+    probe_key
+        .iter_mut()
+        .for_each(|x| *x = rng.gen_range(0, BUILD_LEN as i32));
+    probe_pay
+        .iter_mut()
+        .for_each(|x| *x = rng.gen_range(0, 1 << 20));
+
+    let ht_len = 2 * BUILD_LEN;
+    let hash_table =
+        HashTable::new_on_gpu(Allocator::alloc_mem(MemType::CudaUniMem, ht_len), ht_len)?;
+    let hj_op = CudaHashJoinBuilder::<i32>::default()
+        .hashing_scheme(HashingScheme::LinearProbing)
+        .build_dim(GridSize::from(4), BlockSize::from(128))
+        .probe_dim(GridSize::from(16), BlockSize::from(128))
+        .hash_table(Arc::new(hash_table))
+        .build()?;
+
+    hj_op.build(
+        build_key.as_launchable_slice(),
+        build_pay.as_launchable_slice(),
+        &stream,
+    )?;
+
+    // Runs the aggregate twice, each time from a zeroed result set
+    let run_aggregate = || -> Result<u64, Box<dyn Error>> {
+        let mut result_set =
+            Allocator::alloc_mem::<u64>(MemType::CudaDevMem, hj_op.result_set_len());
+        result_set.zero_async(&stream)?;
+        hj_op.probe_sum(
+            probe_key.as_launchable_slice(),
+            probe_pay.as_launchable_slice(),
+            &result_set,
+            &stream,
+        )?;
+        Ok(GpuTreeSum::sum(result_set.as_launchable_slice(), &stream)?)
+    };
+
+    let first = run_aggregate()?;
+    let second = run_aggregate()?;
+
+    // Each probe key matches exactly one build key
+    let expected: u64 = probe_pay.iter().map(|&x| x as u64).sum();
+    assert_eq!(first, expected);
+    assert_eq!(first, second);
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests that `Tuple` has the same byte layout as a C struct.
+//!
+//! The C++ partitioner writes same-width tuples, which are read back in Rust.
+//! The GPU kernels only instantiate tuples with keys and values of the same
+//! width. Mixed-width tuples are thus checked against the layout that a C
+//! compiler produces for `struct { K key; V value; }`.
+
+use datagen::relation::{KeyAttribute, UniformRelation};
+use itertools::izip;
+use numa_gpu::runtime::allocator::{Allocator, DerefMemType, MemType};
+use rustacuda::memory::DeviceCopy;
+use sql_ops::partition::cpu_radix_partition::{
+    CpuHistogramAlgorithm, CpuRadixPartitionAlgorithm, CpuRadixPartitionable, CpuRadixPartitioner,
+};
+use sql_ops::partition::{
+    PartitionOffsets, PartitionedRelation, RadixPartitionInputChunkable, Tuple,
+};
+use std::error::Error;
+use std::fmt::Debug;
+use std::mem;
+
+/// Returns the byte offsets of the key and the value.
+fn field_offsets<K: Default, V: Default>() -> (usize, usize) {
+    let tuple = Tuple::<K, V>::default();
+    let base = &tuple as *const _ as usize;
+
+    (
+        &tuple.key as *const _ as usize - base,
+        &tuple.value as *const _ as usize - base,
+    )
+}
+
+#[test]
+fn same_width_tuples_are_not_padded() {
+    assert_eq!(field_offsets::<i32, i32>(), (0, 4));
+    assert_eq!(field_offsets::<i64, i64>(), (0, 8));
+    assert_eq!(mem::size_of::<Tuple<i32, i32>>(), 8);
+    assert_eq!(mem::size_of::<Tuple<i64, i64>>(), 16);
+}
+
+#[test]
+fn mixed_width_tuples_match_c_layout() {
+    // The value is aligned to 8 bytes, which leaves 4 bytes of padding after
+    // the key
+    assert_eq!(field_offsets::<i32, i64>(), (0, 8));
+    assert_eq!(mem::size_of::<Tuple<i32, i64>>(), 16);
+    assert_eq!(mem::align_of::<Tuple<i32, i64>>(), 8);
+
+    // The struct is padded at the end to a multiple of its alignment
+    assert_eq!(field_offsets::<i64, i32>(), (0, 8));
+    assert_eq!(mem::size_of::<Tuple<i64, i32>>(), 16);
+}
+
+/// Partitions keys and payloads with the C++ partitioner, and checks that
+/// Rust reads back each tuple with its payload.
+///
+/// The payload of a key differs in each byte from the key, thus a tuple
+/// layout mismatch between Rust and C++ mixes up keys and payloads.
+fn partitioned_tuples_read_back<T>(payload_of: fn(T) -> T) -> Result<(), Box<dyn Error>>
+where
+    T: Copy + Debug + Default + Eq + KeyAttribute + DeviceCopy + CpuRadixPartitionable,
+{
+    const TUPLES: usize = 1000;
+    const RADIX_BITS: u32 = 2;
+    const THREADS: u32 = 1;
+
+    let histogram_algorithm = CpuHistogramAlgorithm::Chunked;
+    let mut data_key = vec![T::default(); TUPLES];
+    UniformRelation::gen_primary_key(&mut data_key, None)?;
+    let data_pay: Vec<T> = data_key.iter().map(|&key| payload_of(key)).collect();
+
+    let mut partition_offsets = PartitionOffsets::new(
+        histogram_algorithm.into(),
+        THREADS,
+        RADIX_BITS,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    let mut partitioned_relation = PartitionedRelation::new(
+        TUPLES,
+        histogram_algorithm.into(),
+        RADIX_BITS,
+        THREADS,
+        Allocator::mem_alloc_fn(MemType::SysMem),
+        Allocator::mem_alloc_fn(MemType::SysMem),
+    );
+    unsafe {
+        partitioned_relation
+            .as_raw_relation_mut_slice()?
+            .iter_mut()
+            .for_each(|tuple| {
+                *tuple = Tuple {
+                    key: T::null_key(),
+                    value: T::default(),
+                }
+            });
+    }
+
+    let mut partitioner = CpuRadixPartitioner::new(
+        histogram_algorithm,
+        CpuRadixPartitionAlgorithm::NC,
+        RADIX_BITS,
+        DerefMemType::SysMem,
+    )?;
+
+    for (key_chunk, offsets_chunk) in izip!(
+        data_key.as_slice().input_chunks::<T>(THREADS)?,
+        partition_offsets.chunks_mut()
+    ) {
+        partitioner.prefix_sum(key_chunk, offsets_chunk)?;
+    }
+    for (key_chunk, pay_chunk, offsets_chunk, partitioned_chunk) in izip!(
+        data_key.as_slice().input_chunks::<T>(THREADS)?,
+        data_pay.as_slice().input_chunks::<T>(THREADS)?,
+        partition_offsets.chunks_mut(),
+        partitioned_relation.chunks_mut()
+    ) {
+        partitioner.partition(key_chunk, pay_chunk, offsets_chunk, partitioned_chunk)?;
+    }
+
+    let tuples: Vec<Tuple<T, T>> = unsafe { partitioned_relation.as_raw_relation_slice()? }
+        .iter()
+        .filter(|tuple| tuple.key != T::null_key())
+        .cloned()
+        .collect();
+
+    assert_eq!(tuples.len(), TUPLES);
+    for tuple in tuples {
+        assert_eq!(tuple.value, payload_of(tuple.key));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn partitioned_i32_tuples_read_back() -> Result<(), Box<dyn Error>> {
+    partitioned_tuples_read_back::<i32>(|key| !key)
+}
+
+#[test]
+fn partitioned_i64_tuples_read_back() -> Result<(), Box<dyn Error>> {
+    partitioned_tuples_read_back::<i64>(|key| !key)
+}
@@ -0,0 +1,99 @@
+// Copyright 2022 Clemens Lutz
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datagen::relation::UniformRelation;
+use sql_ops::error::ErrorKind;
+use sql_ops::join::wide_payload_join::WidePayloadHashTable;
+use std::error::Error;
+use std::result::Result;
+
+/// Fills a payload with a byte pattern that is unique for each key.
+fn payload_of<const N: usize>(key: i32) -> [u8; N] {
+    let mut payload = [0_u8; N];
+    let key_bytes = key.to_le_bytes();
+    payload
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, byte)| *byte = key_bytes[i % key_bytes.len()] ^ (i as u8));
+    payload
+}
+
+#[test]
+fn wide_payload_join_carries_payload_bytes() -> Result<(), Box<dyn Error>> {
+    const ROWS: usize = 1 << 14;
+    const PAYLOAD_BYTES: usize = 60;
+
+    let mut inner_rel_key = vec![0_i32; ROWS];
+    let mut outer_rel_key = vec![0_i32; 2 * ROWS];
+    UniformRelation::gen_primary_key(&mut inner_rel_key, None)?;
+    UniformRelation::gen_foreign_key_from_primary_key(&mut outer_rel_key, &inner_rel_key);
+
+    let inner_rel_pay: Vec<[u8; PAYLOAD_BYTES]> =
+        inner_rel_key.iter().map(|&key| payload_of(key)).collect();
+
+    let mut hash_table = WidePayloadHashTable::<i32, PAYLOAD_BYTES>::new(2 * ROWS)?;
+    hash_table.build(&inner_rel_key, &inner_rel_pay)?;
+
+    let mut join_result = vec![[0_u8; PAYLOAD_BYTES]; outer_rel_key.len()];
+    let matches = hash_table.probe(&outer_rel_key, &mut join_result)?;
+
+    assert_eq!(outer_rel_key.len() as u64, matches);
+    for (&key, payload) in outer_rel_key.iter().zip(join_result.iter()) {
+        assert_eq!(&payload_of::<PAYLOAD_BYTES>(key)[..], &payload[..]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn wide_payload_join_skips_missing_keys() -> Result<(), Box<dyn Error>> {
+    const PAYLOAD_BYTES: usize = 32;
+
+    let inner_rel_key = vec![1_i32, 2, 3, 4];
+    let inner_rel_pay: Vec<[u8; PAYLOAD_BYTES]> =
+        inner_rel_key.iter().map(|&key| payload_of(key)).collect();
+    let outer_rel_key = vec![2_i32, 5, 4, 6];
+
+    let mut hash_table = WidePayloadHashTable::<i32, PAYLOAD_BYTES>::new(8)?;
+    hash_table.build(&inner_rel_key, &inner_rel_pay)?;
+
+    let mut join_result = vec![[0xff_u8; PAYLOAD_BYTES]; outer_rel_key.len()];
+    let matches = hash_table.probe(&outer_rel_key, &mut join_result)?;
+
+    assert_eq!(2, matches);
+    assert_eq!(payload_of::<PAYLOAD_BYTES>(2), join_result[0]);
+    assert_eq!([0xff_u8; PAYLOAD_BYTES], join_result[1]);
+    assert_eq!(payload_of::<PAYLOAD_BYTES>(4), join_result[2]);
+    assert_eq!([0xff_u8; PAYLOAD_BYTES], join_result[3]);
+
+    Ok(())
+}
+
+#[test]
+fn wide_payload_join_rejects_full_hash_table() -> Result<(), Box<dyn Error>> {
+    let keys = vec![1_i64, 2, 3, 4, 5];
+    let payloads = vec![[0_u8; 16]; keys.len()];
+
+    let mut hash_table = WidePayloadHashTable::<i64, 16>::new(4)?;
+
+    match hash_table.build(&keys, &payloads) {
+        Err(ref e) => match e.kind() {
+            ErrorKind::RuntimeError(_) => {}
+            _ => panic!("Expected a RuntimeError, but got: {}", e),
+        },
+        Ok(_) => panic!("Expected an error when the hash table overflows"),
+    }
+
+    Ok(())
+}